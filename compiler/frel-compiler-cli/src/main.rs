@@ -2,11 +2,45 @@
 //
 // Command-line interface for the Frel compiler.
 
+mod reporter;
+mod tokens;
+
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use frel_compiler_core::ast::{self, ChangeKind, DumpOptions};
+use frel_compiler_core::diagnostic::{ColorChoice, RenderOptions, Theme};
+use frel_compiler_core::semantic::dump::{name_annotations, node_types};
+use frel_compiler_core::semantic::eval::EVAL_PATH;
+use frel_compiler_core::semantic::{
+    evaluate_with, execute_statement, preview_scheme, rename, EvalContext, Execution, Interpreter,
+    Module, Translations,
+};
+use frel_compiler_core::session::{same_diagnostics, sort_diagnostics, Bundle, Session, Step};
+use frel_compiler_core::source_map::SourceMap;
+use frel_compiler_core::{
+    analyze_module, build_signature, profile, run_passes, ArtifactKind, CompilerOutput,
+    ConstValue, Diagnostics, DiskFs, Experimental, Features, FileDiagnostic, FileId, LineIndex,
+    LintLevels,
+    MessageCatalog, ProjectConfig, SarifBuilder, SarifLog, ScopeGraphExport, SignatureRegistry,
+    SourceProvider,
+};
+use frel_compiler_plugin_api::{
+    content_hash, fingerprinted_path, AssetManifest, CodegenPlugin, GenerateOptions, OutputFile,
+    PluginContext, PluginRegistry, MANIFEST_FILE,
+};
+use frel_compiler_plugin_javascript::JavaScriptPlugin;
+use frel_compiler_server::{compiler, record};
+use frel_compiler_server::state::ProjectState;
+use reporter::{ErrorFormat, Reporter};
+
+/// Name of the recording tool in `frelc` session bundles
+const TOOL: &str = "frelc";
 
 #[derive(Parser)]
 #[command(name = "frel")]
@@ -15,6 +49,97 @@ use clap::{Parser, Subcommand};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// When to color diagnostics: auto, always or never
+    #[arg(long, global = true, default_value = "auto")]
+    color: ColorChoice,
+
+    /// Diagnostic color theme: dark, light or mono
+    #[arg(long, global = true, default_value = "dark")]
+    theme: Theme,
+
+    /// How diagnostics are written to stderr: human, short (one line each) or
+    /// json (one object per line, written as each file is checked)
+    #[arg(long, global = true, value_enum, default_value = "human")]
+    error_format: ErrorFormat,
+
+    /// Stop reporting diagnostics after this many errors
+    #[arg(long, global = true, value_name = "N")]
+    max_errors: Option<usize>,
+
+    /// Report errors only: no warnings, summaries or success messages
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Allow a lint, given by code or name: its diagnostics are not reported
+    #[arg(short = 'A', long = "allow", global = true, value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report a lint as a warning; `-W warnings` keeps all warnings
+    #[arg(short = 'W', long = "warn", global = true, value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Report a lint as an error, failing the build; `-D warnings` denies all
+    #[arg(short = 'D', long = "deny", global = true, value_name = "LINT")]
+    deny: Vec<String>,
+
+    /// Report every warning as an error, as `-D warnings`
+    #[arg(long, global = true)]
+    deny_warnings: bool,
+
+    /// Profile the compiler and write folded stacks for flamegraph tools
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        num_args = 0..=1,
+        default_missing_value = "frelc.folded"
+    )]
+    profile_internal: Option<PathBuf>,
+}
+
+/// Output of the compile command
+#[derive(Clone, Copy, ValueEnum)]
+enum Emit {
+    /// Generated code for the target
+    Code,
+    /// Scopes, symbols and resolutions as a Graphviz DOT graph
+    Scopes,
+    /// Scopes, symbols and resolutions as JSON
+    ScopesJson,
+    /// The AST in DUMP format, shaped by the `--dump-*` options
+    AstDump,
+}
+
+/// How the compile command reports its result
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Rendered diagnostics and a summary line
+    Human,
+    /// One JSON envelope with artifacts, diagnostics and timings on stdout
+    Json,
+    /// A SARIF 2.1.0 log of the diagnostics on stdout, for code scanning
+    Sarif,
+}
+
+/// Diagram format of the graph command
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GraphFormat {
+    /// A Mermaid state diagram
+    Mermaid,
+    /// A Graphviz DOT graph
+    Dot,
+}
+
+/// How the check command reports its result
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CheckFormat {
+    /// Rendered diagnostics and a summary line
+    Human,
+    /// The JSON envelope of `compile --message-format json` on stdout
+    Json,
+    /// A SARIF 2.1.0 log on stdout, for code scanning in CI
+    Sarif,
 }
 
 #[derive(Subcommand)]
@@ -29,16 +154,262 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Target language (currently only 'javascript')
+        /// Target language; one of the registered code generators (javascript)
         #[arg(short, long, default_value = "javascript")]
         target: String,
+
+        /// Omit stable fragment IDs (used as test selectors) from the output
+        #[arg(long)]
+        no_fragment_ids: bool,
+
+        /// Minify the output: strip dead branches and whitespace and shorten
+        /// local names, writing their originals to <module>.names.json
+        #[arg(long)]
+        minify: bool,
+
+        /// Name the output files by a hash of their contents, listing them in
+        /// manifest.json next to the output
+        #[arg(long)]
+        fingerprint: bool,
+
+        /// Make the build byte-for-byte reproducible: paths relative to the
+        /// current directory, diagnostics in a stable order, no timings, and
+        /// the module compiled twice to check that its output is the same
+        #[arg(long)]
+        reproducible: bool,
+
+        /// What to write: generated code, the scope graph as DOT or JSON, or
+        /// the AST in DUMP format
+        #[arg(long, value_enum, default_value = "code")]
+        emit: Emit,
+
+        /// With `--emit ast-dump`, fold the nodes deeper than N levels, the
+        /// file being level 0
+        #[arg(long, value_name = "N")]
+        dump_depth: Option<usize>,
+
+        /// With `--emit ast-dump`, shorten long expressions
+        #[arg(long)]
+        dump_collapse: bool,
+
+        /// With `--emit ast-dump`, append the span of each node
+        #[arg(long)]
+        dump_spans: bool,
+
+        /// With `--emit ast-dump`, append the types found by the analysis and
+        /// annotate names with the symbols and types they resolve to
+        #[arg(long)]
+        dump_types: bool,
+
+        /// Report diagnostics for humans, as a JSON envelope for build tools, or
+        /// as SARIF for code scanning
+        #[arg(long, value_enum, default_value = "human")]
+        message_format: MessageFormat,
+
+        /// Accept the experimental syntax of a feature (e.g. command-results)
+        #[arg(long = "enable-feature", value_name = "NAME")]
+        enable_features: Vec<Experimental>,
+
+        /// Write a session bundle into this directory if compilation fails
+        #[arg(long, value_name = "DIR")]
+        record: Option<PathBuf>,
+
+        /// Report diagnostics at template positions, read from a source map
+        /// next to the input (input.frel.map)
+        #[arg(long)]
+        remap_diagnostics: bool,
     },
 
-    /// Check a Frel file for errors without compiling
+    /// Check a Frel file, or every Frel file in a directory, for errors
+    /// without compiling
     Check {
+        /// Input Frel file, or a directory whose modules are checked together
+        #[arg(value_name = "PATH")]
+        input: PathBuf,
+
+        /// Apply the suggested fixes to the file before checking it
+        #[arg(long)]
+        fix: bool,
+
+        /// Report diagnostics for humans, as a JSON envelope, or as SARIF
+        #[arg(long, value_enum, default_value = "human")]
+        format: CheckFormat,
+
+        /// Accept the experimental syntax of a feature (e.g. command-results)
+        #[arg(long = "enable-feature", value_name = "NAME")]
+        enable_features: Vec<Experimental>,
+
+        /// Write a session bundle into this directory if the check fails
+        #[arg(long, value_name = "DIR")]
+        record: Option<PathBuf>,
+
+        /// Report diagnostics at template positions, read from a source map
+        /// next to the input (input.frel.map)
+        #[arg(long)]
+        remap_diagnostics: bool,
+    },
+
+    /// Replay a session bundle recorded with --record and compare diagnostics
+    Replay {
+        /// Bundle directory
+        #[arg(value_name = "BUNDLE")]
+        bundle: PathBuf,
+    },
+
+    /// Generate a Frel theme module from W3C design token JSON
+    ImportTokens {
+        /// Design token file (e.g. exported from Figma)
+        #[arg(value_name = "TOKENS")]
+        input: PathBuf,
+
+        /// Output file (defaults to input with .frel extension)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Module path of the generated file
+        #[arg(short, long, default_value = "tokens")]
+        module: String,
+
+        /// Name of the generated theme
+        #[arg(long, default_value = "Tokens")]
+        theme_name: String,
+    },
+
+    /// Check translation files against the localized messages of a Frel file
+    CheckMessages {
+        /// Input Frel file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Translation files; the locale is taken from the file name (e.g. de.json)
+        #[arg(value_name = "TRANSLATIONS", required = true)]
+        translations: Vec<PathBuf>,
+
+        /// Source-locale translation file that placeholders are checked against
+        #[arg(short, long)]
+        reference: Option<PathBuf>,
+    },
+
+    /// Generate a pseudo-locale translation file for layout testing
+    PseudoLocale {
         /// Input Frel file
         #[arg(value_name = "FILE")]
         input: PathBuf,
+
+        /// Source-locale translation file to pseudo-localize
+        #[arg(short, long)]
+        reference: Option<PathBuf>,
+
+        /// Output file (defaults to input with .pseudo.json extension)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Report blueprint complexity and flag blueprints likely to be slow
+    Report {
+        /// Input Frel file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Draw the state machines of a backend: its fields of an enum type and
+    /// the handlers assigning them a variant
+    Graph {
+        /// Input Frel file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Backend whose enum fields are drawn
+        #[arg(long, value_name = "BACKEND")]
+        state: String,
+
+        /// Diagram format
+        #[arg(long, value_enum, default_value = "mermaid")]
+        format: GraphFormat,
+    },
+
+    /// Rename a declaration and all its references in a file
+    Rename {
+        /// Input Frel file, rewritten in place
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Current name, qualified by its scope if ambiguous (e.g. Counter.count)
+        #[arg(value_name = "OLD")]
+        old: String,
+
+        /// New name
+        #[arg(value_name = "NEW")]
+        new: String,
+    },
+
+    /// Show the structural differences between the ASTs of two Frel files
+    Diff {
+        /// Old version of the file
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// New version of the file
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+
+        /// Print the changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Evaluate a Frel expression with a project's declarations and print its
+    /// value and type; without an expression, read expressions and handler
+    /// statements (`count = count + 1`, `save()`) line by line
+    Eval {
+        /// Expression to evaluate, e.g. "AppTheme.padding * 2"
+        #[arg(value_name = "EXPR")]
+        expression: Option<String>,
+
+        /// Evaluate the virtual fields of this scheme for the `--data` instead
+        /// of an expression (e.g. Person or app.people.Person)
+        #[arg(
+            long,
+            value_name = "SCHEME",
+            conflicts_with = "expression",
+            requires = "data"
+        )]
+        scheme: Option<String>,
+
+        /// JSON file with an object of the `--scheme`'s fields
+        #[arg(long, value_name = "FILE", requires = "scheme")]
+        data: Option<PathBuf>,
+
+        /// Project directory (defaults to the one containing frel.toml)
+        #[arg(long, value_name = "DIR")]
+        project: Option<PathBuf>,
+
+        /// Evaluate inside this module, seeing its declarations and imports
+        #[arg(long, value_name = "MODULE")]
+        in_module: Option<String>,
+
+        /// Use this backend, making its fields available (e.g. app.store.Store)
+        #[arg(long, value_name = "BACKEND")]
+        with_backend: Option<String>,
+
+        /// Print each result as a line of JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Format Frel files in place in the canonical style
+    Fmt {
+        /// Input Frel files
+        #[arg(value_name = "FILE", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Only check formatting; fail if a file would be reformatted
+        #[arg(long)]
+        check: bool,
     },
 
     /// Show version information
@@ -47,14 +418,149 @@ enum Commands {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let lints = LintLevels::from_flags(&cli.allow, &cli.warn, &cli.deny, cli.deny_warnings)
+        .map_err(anyhow::Error::msg)?;
+    let reporter = Reporter::new(
+        render_options(cli.color, cli.theme),
+        cli.error_format,
+        cli.max_errors,
+        cli.quiet,
+        lints,
+    );
+
+    let Some(profile_path) = cli.profile_internal else {
+        let result = run(cli.command, &reporter);
+        reporter.finish();
+        return result;
+    };
+    profile::start();
+    let result = run(cli.command, &reporter);
+    reporter.finish();
+    let profile = profile::finish();
+    fs::write(&profile_path, profile.to_folded())
+        .with_context(|| format!("Failed to write profile: {}", profile_path.display()))?;
+    eprintln!(
+        "Profiled {:.2} ms of compiler work -> {}",
+        profile.total().as_secs_f64() * 1000.0,
+        profile_path.display()
+    );
+    result
+}
 
-    match cli.command {
+fn run(command: Commands, reporter: &Reporter) -> Result<()> {
+    match command {
         Commands::Compile {
             input,
             output,
             target,
-        } => compile(&input, output.as_deref(), &target),
-        Commands::Check { input } => check(&input),
+            no_fragment_ids,
+            minify,
+            fingerprint,
+            reproducible,
+            emit,
+            dump_depth,
+            dump_collapse,
+            dump_spans,
+            dump_types,
+            message_format,
+            enable_features,
+            record,
+            remap_diagnostics,
+        } => {
+            let options = GenerateOptions {
+                fragment_ids: !no_fragment_ids,
+                minify,
+                fingerprint,
+            };
+            let features = Features::from_iter(enable_features);
+            let output = CompileOutput {
+                path: output.as_deref(),
+                emit,
+                dump: DumpOptions {
+                    max_depth: dump_depth,
+                    collapse_exprs: dump_collapse,
+                    spans: dump_spans,
+                    ..DumpOptions::default()
+                },
+                dump_types,
+                format: message_format,
+                record: record.as_deref(),
+                remap: remap_diagnostics,
+                reproducible,
+            };
+            compile(&input, &output, &target, &options, &features, reporter)
+        }
+        Commands::Check {
+            input,
+            fix,
+            format,
+            enable_features,
+            record,
+            remap_diagnostics,
+        } => {
+            let features = Features::from_iter(enable_features);
+            let options = CheckOptions {
+                fix,
+                format,
+                record: record.as_deref(),
+                remap: remap_diagnostics,
+            };
+            check(&input, &options, &features, reporter)
+        }
+        Commands::Replay { bundle } => replay(&bundle),
+        Commands::ImportTokens {
+            input,
+            output,
+            module,
+            theme_name,
+        } => import_tokens(&input, output.as_deref(), &module, &theme_name),
+        Commands::CheckMessages {
+            input,
+            translations,
+            reference,
+        } => check_messages(&input, &translations, reference.as_deref(), reporter),
+        Commands::PseudoLocale {
+            input,
+            reference,
+            output,
+        } => pseudo_locale(&input, reference.as_deref(), output.as_deref(), reporter),
+        Commands::Report { input, json } => report_complexity(&input, json, reporter),
+        Commands::Graph {
+            input,
+            state,
+            format,
+        } => graph_states(&input, &state, format, reporter),
+        Commands::Rename { input, old, new } => rename(&input, &old, &new, reporter),
+        Commands::Diff { old, new, json } => diff(&old, &new, json, reporter),
+        Commands::Eval {
+            expression,
+            scheme,
+            data,
+            project,
+            in_module,
+            with_backend,
+            json,
+        } => {
+            let root = match project {
+                Some(dir) => dir,
+                None => project_root()?,
+            };
+            let (files, registry) = load_project(&root, reporter)?;
+            if let (Some(scheme), Some(data)) = (scheme, data) {
+                return preview(&files, in_module.as_deref(), &scheme, &data, json);
+            }
+            let context = EvalContext {
+                files: &files,
+                registry: &registry,
+                module: in_module.as_deref(),
+                backend: with_backend.as_deref(),
+            };
+            match expression {
+                Some(expression) => eval(&expression, &context, &Interpreter::new(), json, reporter),
+                None => eval_lines(&context, json, reporter),
+            }
+        }
+        Commands::Fmt { inputs, check } => format_files(&inputs, check, reporter),
         Commands::Version => {
             println!("frelc {}", env!("CARGO_PKG_VERSION"));
             println!("frel-compiler-core {}", frel_compiler_core::VERSION);
@@ -63,79 +569,1254 @@ fn main() -> Result<()> {
     }
 }
 
-fn compile(input: &Path, output: Option<&Path>, target: &str) -> Result<()> {
+/// What the compile command writes and how it reports the result
+struct CompileOutput<'a> {
+    /// Output file; defaults to the input with the target's extension
+    path: Option<&'a Path>,
+    emit: Emit,
+    /// Options of `--emit ast-dump`, without the types
+    dump: DumpOptions,
+    /// Whether the AST dump shows the types found by the analysis
+    dump_types: bool,
+    format: MessageFormat,
+    /// Bundle directory to record a failed compilation into
+    record: Option<&'a Path>,
+    /// Report diagnostics at template positions by the input's source map
+    remap: bool,
+    /// Leave out what differs between machines and runs, and check that
+    /// compiling twice gives the same files
+    reproducible: bool,
+}
+
+fn compile(
+    input: &Path,
+    output: &CompileOutput,
+    target: &str,
+    options: &GenerateOptions,
+    features: &Features,
+    reporter: &Reporter,
+) -> Result<()> {
+    // Paths given as absolute would differ between machines
+    let (input, output_file) = if output.reproducible {
+        (relative_path(input)?, output.path.map(relative_path).transpose()?)
+    } else {
+        (input.to_path_buf(), output.path.map(Path::to_path_buf))
+    };
+    let input = input.as_path();
+
     // Read input file
     let source = fs::read_to_string(input)
         .with_context(|| format!("Failed to read input file: {}", input.display()))?;
 
     // Parse and compile with file path for better diagnostics
-    let result = frel_compiler_core::compile_with_path(&source, &input.display().to_string());
+    let start = Instant::now();
+    let config = project_config(input, reporter)?;
+    let edition = config.edition();
+    let path = input.display().to_string();
+    let registry = plugins();
+    let build_dir = output_file.as_deref().unwrap_or(input).parent();
+    let context = plugin_context(input, build_dir);
+    let mut result = frel_compiler_core::compile_with_passes(
+        &source,
+        &path,
+        edition,
+        features.clone(),
+        registry.passes(),
+        &context,
+    );
+    result.diagnostics.apply_lints(&reporter.lints(&config));
+    let mut envelope = CompilerOutput::new();
+    envelope.timings.record("compile", start.elapsed());
+    let reported = diagnostics_by_file(input, &source, &result.diagnostics, output.remap)?;
+    for (file, text, diagnostics) in &reported {
+        envelope.add_diagnostics(&file.display().to_string(), Some(text), diagnostics);
+    }
+    let emit = output.emit;
+    let print = |envelope: &mut CompilerOutput| {
+        if output.reproducible {
+            sort_diagnostics(&mut envelope.diagnostics);
+            envelope.timings = Default::default();
+        } else {
+            envelope.finish(start.elapsed());
+        }
+        match output.format {
+            MessageFormat::Human => {}
+            MessageFormat::Json => println!("{}", envelope.to_json()),
+            MessageFormat::Sarif => println!("{}", sarif_log(&reported).to_json()),
+        }
+    };
+
+    // Warnings are shown even when compilation succeeds
+    if output.format == MessageFormat::Human {
+        report_files(&reported, reporter);
+    }
 
-    // Check for errors
+    // Check for errors; the scope graph and the AST can still be exported
+    // after resolution errors, as they are used to debug them
     if result.diagnostics.has_errors() {
-        let line_index = frel_compiler_core::LineIndex::new(&source);
-        for diag in result.diagnostics.iter() {
-            let loc = line_index.line_col(diag.span.start);
-            eprintln!(
-                "error[{}]: {} at {}:{}:{}",
-                diag.code.as_deref().unwrap_or("E????"),
-                diag.message,
-                input.display(),
-                loc.line,
-                loc.col
+        if let Some(dir) = output.record {
+            let session = Session::new(TOOL, edition, features, target);
+            record(dir, session, input, &source, &result.diagnostics)?;
+        }
+        if matches!(emit, Emit::Code) || result.semantic.is_none() {
+            print(&mut envelope);
+            anyhow::bail!("Compilation failed with {} error(s)", result.diagnostics.error_count());
+        }
+    }
+
+    // Generate code, or export the scope graph or the AST for debugging
+    let module = result.ast.as_ref().map(|file| file.module.clone());
+    let codegen_start = Instant::now();
+    let (files, extension, kind) = match emit {
+        Emit::Code => {
+            let plugin = registry.get(target).with_context(|| {
+                format!(
+                    "Unsupported target: {} (available: {})",
+                    target,
+                    registry.names().join(", ")
+                )
+            })?;
+            let ir = result.ir.context("No IR produced")?;
+            let files = plugin.generate(&ir, options, &context)?;
+            if output.reproducible {
+                let again = frel_compiler_core::compile_with_passes(
+                    &source,
+                    &path,
+                    edition,
+                    features.clone(),
+                    registry.passes(),
+                    &context,
+                );
+                let ir = again.ir.context("No IR produced")?;
+                check_reproducible(&files, &plugin.generate(&ir, options, &context)?)?;
+            }
+            let files = files.into_iter().map(|f| (f.path, f.contents)).collect();
+            (files, plugin.file_extension().to_string(), ArtifactKind::Code)
+        }
+        Emit::Scopes | Emit::ScopesJson => {
+            let analysis = result.semantic.context("No semantic analysis produced")?;
+            let export =
+                ScopeGraphExport::new(&analysis.scopes, &analysis.symbols, &analysis.resolutions);
+            let (text, extension) = match emit {
+                Emit::Scopes => (export.to_dot(), "scopes.dot"),
+                _ => (export.to_json(), "scopes.json"),
+            };
+            let files = vec![(PathBuf::new(), text)];
+            (files, extension.to_string(), ArtifactKind::ScopeGraph)
+        }
+        Emit::AstDump => {
+            let file = result.ast.as_ref().context("No AST produced")?;
+            let mut dump = output.dump.clone();
+            if output.dump_types {
+                let analysis = result
+                    .semantic
+                    .as_ref()
+                    .context("No semantic analysis produced")?;
+                dump.types = node_types(&analysis.expr_types, &analysis.symbols);
+                dump.names = name_annotations(&analysis.names, &analysis.symbols);
+            }
+            let files = vec![(PathBuf::new(), ast::DumpVisitor::dump_with(file, dump))];
+            (files, "ast.txt".to_string(), ArtifactKind::AstDump)
+        }
+    };
+    envelope.timings.record("codegen", codegen_start.elapsed());
+
+    // Determine output path; the first file is the module's main output,
+    // further ones are written next to it
+    let output_path = output_file
+        .unwrap_or_else(|| input.with_extension(&extension));
+    let fingerprint = options.fingerprint && matches!(emit, Emit::Code);
+    let mut manifest = AssetManifest::new();
+    let mut written = output_path.clone();
+    for (i, (path, contents)) in files.into_iter().enumerate() {
+        let mut file_path = match path.file_name() {
+            Some(name) if i > 0 => output_path.with_file_name(name),
+            _ => output_path.clone(),
+        };
+        if fingerprint {
+            let logical = Path::new(file_path.file_name().context("Output is not a file")?);
+            let hashed = fingerprinted_path(logical, &contents);
+            manifest.insert(logical, &hashed);
+            file_path.set_file_name(hashed);
+        }
+        fs::write(&file_path, contents)
+            .with_context(|| format!("Failed to write output file: {}", file_path.display()))?;
+        if i == 0 {
+            written = file_path.clone();
+        }
+        let artifact = file_path.display().to_string();
+        envelope.add_artifact(artifact, kind, module.as_deref());
+    }
+    if fingerprint {
+        let manifest_path = write_manifest(&output_path, &manifest)?;
+        let artifact = manifest_path.display().to_string();
+        envelope.add_artifact(artifact, ArtifactKind::Manifest, module.as_deref());
+    }
+
+    match output.format {
+        MessageFormat::Human if reporter.quiet() => {}
+        MessageFormat::Human => {
+            println!("Compiled {} -> {}", input.display(), written.display())
+        }
+        _ => print(&mut envelope),
+    }
+
+    Ok(())
+}
+
+/// Add the entries of `manifest` to the manifest next to `output`, keeping
+/// those of the other files compiled into its directory
+fn write_manifest(output: &Path, manifest: &AssetManifest) -> Result<PathBuf> {
+    let path = output.with_file_name(MANIFEST_FILE);
+    let mut merged = match fs::read_to_string(&path) {
+        Ok(json) => AssetManifest::parse(&json)
+            .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?,
+        Err(_) => AssetManifest::new(),
+    };
+    for (logical, hashed) in manifest.iter() {
+        merged.insert(Path::new(logical), Path::new(hashed));
+    }
+    fs::write(&path, merged.to_json())
+        .with_context(|| format!("Failed to write manifest: {}", path.display()))?;
+    Ok(path)
+}
+
+/// `path` relative to the current directory, if it is absolute
+fn relative_path(path: &Path) -> Result<PathBuf> {
+    if path.is_relative() {
+        return Ok(path.to_path_buf());
+    }
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+    let path = path.components().collect::<Vec<_>>();
+    let base = cwd.components().collect::<Vec<_>>();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        // On another drive; there is no relative path
+        return Ok(path.iter().collect());
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    Ok(relative)
+}
+
+/// Fail unless two generations of the same module wrote the same files
+fn check_reproducible(first: &[OutputFile], second: &[OutputFile]) -> Result<()> {
+    let hashes = |files: &[OutputFile]| {
+        files
+            .iter()
+            .map(|f| (f.path.clone(), content_hash(&f.contents)))
+            .collect::<Vec<_>>()
+    };
+    let (first, second) = (hashes(first), hashes(second));
+    if first.len() != second.len() {
+        anyhow::bail!(
+            "Build is not reproducible: {} files on the first compilation, {} on the second",
+            first.len(),
+            second.len()
+        );
+    }
+    for ((path, a), (_, b)) in first.iter().zip(&second) {
+        if a != b {
+            anyhow::bail!(
+                "Build is not reproducible: {} differs between two compilations ({} and {})",
+                path.display(),
+                a,
+                b
             );
         }
-        anyhow::bail!("Compilation failed with {} error(s)", result.diagnostics.error_count());
     }
+    Ok(())
+}
+
+/// How the check command treats its input and reports the result
+struct CheckOptions<'a> {
+    /// Apply the suggested fixes before checking; single files only
+    fix: bool,
+    format: CheckFormat,
+    /// Bundle directory to record a failed check into; single files only
+    record: Option<&'a Path>,
+    /// Report diagnostics at template positions by the inputs' source maps
+    remap: bool,
+}
+
+fn check(
+    input: &Path,
+    options: &CheckOptions,
+    features: &Features,
+    reporter: &Reporter,
+) -> Result<()> {
+    let start = Instant::now();
+    let human = options.format == CheckFormat::Human;
+    let files = if input.is_dir() {
+        if options.fix || options.record.is_some() {
+            anyhow::bail!("--fix and --record check a single file, not a directory");
+        }
+        check_dir(input, features, options.remap, human, reporter)?
+    } else {
+        let files = check_file(input, options, features, reporter)?;
+        if human {
+            report_files(&files, reporter);
+        }
+        files
+    };
+    let error_count: usize = files.iter().map(|(_, _, d)| d.error_count()).sum();
+
+    match options.format {
+        CheckFormat::Human => {}
+        CheckFormat::Json => {
+            let mut envelope = CompilerOutput::new();
+            for (file, text, diagnostics) in &files {
+                envelope.add_diagnostics(&file.display().to_string(), Some(text), diagnostics);
+            }
+            envelope.finish(start.elapsed());
+            println!("{}", envelope.to_json());
+        }
+        CheckFormat::Sarif => println!("{}", sarif_log(&files).to_json()),
+    }
+    if error_count > 0 {
+        anyhow::bail!("Check failed with {} error(s)", error_count);
+    }
+
+    if human && !reporter.quiet() {
+        match files.len() {
+            1 => println!("✓ {} OK", input.display()),
+            count => println!("✓ {} OK ({} files)", input.display(), count),
+        }
+    }
+
+    Ok(())
+}
+
+/// Check one file, returning its diagnostics by the file they are reported in
+fn check_file(
+    input: &Path,
+    options: &CheckOptions,
+    features: &Features,
+    reporter: &Reporter,
+) -> Result<Vec<(PathBuf, String, Diagnostics)>> {
+    // Read input file
+    let mut source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+    // Parse and check with file path for better diagnostics
+    let config = project_config(input, reporter)?;
+    let (edition, lints) = (config.edition(), reporter.lints(&config));
+    let path = input.display().to_string();
+    let registry = plugins();
+    let context = plugin_context(input, None);
+    let compile = |source: &str| {
+        let passes = registry.passes();
+        let mut result = frel_compiler_core::compile_with_passes(
+            source,
+            &path,
+            edition,
+            features.clone(),
+            passes,
+            &context,
+        );
+        result.diagnostics.apply_lints(&lints);
+        result
+    };
+    let mut result = compile(&source);
+
+    // Apply suggestions, then check the fixed source again
+    if options.fix {
+        let (fixed, count) = result.diagnostics.apply_fixes(&source);
+        if count > 0 {
+            fs::write(input, &fixed)
+                .with_context(|| format!("Failed to write fixed file: {}", input.display()))?;
+            eprintln!("Fixed {} issue(s) in {}", count, input.display());
+            source = fixed;
+            result = compile(&source);
+        }
+    }
+
+    if result.diagnostics.has_errors() {
+        if let Some(dir) = options.record {
+            let target = JavaScriptPlugin.name();
+            let session = Session::new(TOOL, edition, features, target);
+            record(dir, session, input, &source, &result.diagnostics)?;
+        }
+    }
+
+    diagnostics_by_file(input, &source, &result.diagnostics, options.remap)
+}
+
+/// Check every Frel file under `root`, returning the diagnostics by the file
+/// they are reported in
+///
+/// The signatures of all modules are built before any is analyzed, so imports
+/// between the modules resolve. Files with parse errors are left out of their
+/// module; module diagnostics are reported against the module's first file,
+/// as the compiler server does.
+fn check_dir(
+    root: &Path,
+    features: &Features,
+    remap: bool,
+    stream: bool,
+    reporter: &Reporter,
+) -> Result<Vec<(PathBuf, String, Diagnostics)>> {
+    let config = project_config(root, reporter)?;
+    let (edition, lints) = (config.edition(), reporter.lints(&config));
+    let mut sources = Vec::new();
+    let mut modules: BTreeMap<String, Vec<ast::File>> = BTreeMap::new();
+    let mut paths = compiler::discover_frel_files(&DiskFs, root);
+    paths.sort();
+    // The file of the n-th source is `FileId(n + 1)`, so a module's diagnostics
+    // can be sent back to the file they point into
+    for (index, path) in paths.into_iter().enumerate() {
+        let source = DiskFs
+            .read(&path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        let name = path.display().to_string();
+        let result = frel_compiler_core::parse_file_in_module(
+            &source,
+            &name,
+            edition,
+            features.clone(),
+            FileId(index as u32 + 1),
+        );
+        match result.file {
+            Some(file) if !result.diagnostics.has_errors() => {
+                modules.entry(file.module.clone()).or_default().push(file)
+            }
+            _ => {}
+        }
+        sources.push((path, source, result.diagnostics));
+    }
+    if sources.is_empty() {
+        anyhow::bail!("No Frel files found in {}", root.display());
+    }
+
+    let modules: Vec<Module> = modules
+        .into_iter()
+        .map(|(path, files)| Module::from_files(path, files))
+        .collect();
+    let signatures = SignatureRegistry::new();
+    for module in &modules {
+        signatures.register(build_signature(module).signature);
+    }
+
+    // Each module's files are done once it is analyzed; with `stream` their
+    // diagnostics are reported right away rather than at the end of the check
+    let mut sources: Vec<_> = sources.into_iter().map(Some).collect();
+    let mut files = Vec::new();
+    let mut finish = |entry: Option<(PathBuf, String, Diagnostics)>| -> Result<()> {
+        if let Some((path, source, mut diagnostics)) = entry {
+            diagnostics.apply_lints(&lints);
+            let reported = diagnostics_by_file(&path, &source, &diagnostics, remap)?;
+            if stream {
+                report_files(&reported, reporter);
+            }
+            files.extend(reported);
+        }
+        Ok(())
+    };
+
+    let plugins = plugins();
+    let context = plugin_context(root, None);
+    for module in &modules {
+        let mut result = analyze_module(module, &signatures);
+        let findings = run_passes(plugins.passes(), module, &result, &context);
+        result.diagnostics.merge(findings);
+        let mut analysis = result
+            .diagnostics
+            .split_by_file(|id| module.file(id).and_then(|file| file.source_path.clone()));
+        // Diagnostics in no file of the module are reported against its first
+        if let (Some(unplaced), Some(first)) =
+            (analysis.remove(&FileId::default()), module.files.first())
+        {
+            analysis.entry(first.file).or_default().merge(unplaced);
+        }
+        for (index, entry) in sources.iter_mut().enumerate() {
+            let id = FileId(index as u32 + 1);
+            if module.files.iter().all(|file| file.file != id) {
+                continue;
+            }
+            let mut entry = entry.take();
+            if let (Some((_, _, diagnostics)), Some(found)) = (&mut entry, analysis.remove(&id)) {
+                diagnostics.merge(found);
+            }
+            finish(entry)?;
+        }
+    }
+    // Files that did not parse belong to no module
+    for entry in sources {
+        finish(entry)?;
+    }
+    Ok(files)
+}
+
+/// The diagnostics of several files as a SARIF log
+fn sarif_log(files: &[(PathBuf, String, Diagnostics)]) -> SarifLog {
+    // Related information may point into files that weren't compiled
+    let load = |file: &str| fs::read_to_string(file).ok();
+    let mut sarif = SarifBuilder::new(TOOL, frel_compiler_core::VERSION, &load);
+    for (file, text, diagnostics) in files {
+        sarif.add_diagnostics(&file.display().to_string(), Some(text), diagnostics);
+    }
+    sarif.finish()
+}
+
+/// Write a bundle that replays the compilation of `input` into `dir`
+fn record(
+    dir: &Path,
+    mut session: Session,
+    input: &Path,
+    source: &str,
+    diagnostics: &Diagnostics,
+) -> Result<()> {
+    // The file is recorded by name only, so the bundle replays anywhere
+    let file = input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .context("Input is not a file")?;
+    let mut outcome = CompilerOutput::new();
+    outcome.add_diagnostics(&file, Some(source), diagnostics);
+    session.steps.push(Step::Compile { file: file.clone() });
+    session.set_outcome(outcome.diagnostics);
+
+    let files = BTreeMap::from([(file, source.to_string())]);
+    Bundle { session, files }
+        .save(dir)
+        .with_context(|| format!("Failed to write session bundle: {}", dir.display()))?;
+    eprintln!("Recorded session to {}", dir.display());
+    Ok(())
+}
 
-    let ast = result.file.context("No AST produced")?;
+fn replay(dir: &Path) -> Result<()> {
+    let bundle = Bundle::load(dir)
+        .with_context(|| format!("Failed to read session bundle: {}", dir.display()))?;
+    let session = &bundle.session;
+    let features = session
+        .features()
+        .map_err(|e| anyhow::anyhow!("Invalid session: {}", e))?;
+    let registry = plugins();
 
-    // Generate code
-    let code = match target {
-        "javascript" | "js" => frel_compiler_plugin_javascript::generate(&ast),
-        _ => anyhow::bail!("Unsupported target: {}", target),
+    let mut replayed = match session.tool.as_str() {
+        TOOL => {
+            let mut output = CompilerOutput::new();
+            for step in &session.steps {
+                let Step::Compile { file } = step else {
+                    anyhow::bail!("Invalid session: {} only compiles files", TOOL);
+                };
+                let source = bundle
+                    .files
+                    .get(file)
+                    .with_context(|| format!("Session bundle is missing {}", file))?;
+                let result = frel_compiler_core::compile_with_passes(
+                    source,
+                    file,
+                    session.edition,
+                    features.clone(),
+                    registry.passes(),
+                    &PluginContext::new(),
+                );
+                output.add_diagnostics(file, Some(source), &result.diagnostics);
+            }
+            output.diagnostics
+        }
+        record::TOOL => {
+            let root = std::env::temp_dir().join(format!("frelc-replay-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root)
+                .with_context(|| format!("Failed to create {}", root.display()))?;
+            let mut state = ProjectState::new(root.clone(), root.join("build"));
+            state.edition = session.edition;
+            state.features = features;
+            state.codegen = registry
+                .get(&session.target)
+                .with_context(|| format!("Unsupported target: {}", session.target))?;
+            state.passes = registry.passes().to_vec();
+            let result = record::replay(&bundle, &mut state);
+            let _ = fs::remove_dir_all(&root);
+            result.context("Failed to replay session")?
+        }
+        tool => anyhow::bail!("Invalid session: unknown tool {}", tool),
     };
+    sort_diagnostics(&mut replayed);
+
+    if session.compiler_version != frel_compiler_core::VERSION {
+        eprintln!(
+            "note: recorded with compiler {}, replaying with {}",
+            session.compiler_version,
+            frel_compiler_core::VERSION
+        );
+    }
+    if same_diagnostics(&session.outcome, &replayed) {
+        println!(
+            "✓ Reproduced {} diagnostic(s) from {}",
+            replayed.len(),
+            dir.display()
+        );
+        return Ok(());
+    }
+
+    eprintln!("Recorded diagnostics:");
+    for diagnostic in &session.outcome {
+        eprintln!("  {}", describe(diagnostic));
+    }
+    eprintln!("Replayed diagnostics:");
+    for diagnostic in &replayed {
+        eprintln!("  {}", describe(diagnostic));
+    }
+    anyhow::bail!("Replay diverged from the recorded session")
+}
+
+/// One-line summary of a diagnostic in a session
+fn describe(diagnostic: &FileDiagnostic) -> String {
+    let location = match diagnostic.start {
+        Some(start) => format!("{}:{}:{}", diagnostic.file, start.line, start.col),
+        None => diagnostic.file.clone(),
+    };
+    let d = &diagnostic.diagnostic;
+    format!("{}: {}: {}", location, d.severity.as_str(), d.message)
+}
+
+fn import_tokens(input: &Path, output: Option<&Path>, module: &str, theme: &str) -> Result<()> {
+    let json = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read token file: {}", input.display()))?;
+
+    let origin = input.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let result = tokens::import_tokens(&json, module, theme, &origin)?;
 
-    // Determine output path
     let output_path = output
         .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| input.with_extension("js"));
+        .unwrap_or_else(|| input.with_extension("frel"));
+    fs::write(&output_path, &result.source)
+        .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
+
+    for (path, reason) in &result.skipped {
+        eprintln!("warning: skipped token {}: {}", path, reason);
+    }
+    println!(
+        "Imported {} token(s) from {} -> {}",
+        result.field_count,
+        input.display(),
+        output_path.display()
+    );
+
+    Ok(())
+}
+
+fn check_messages(
+    input: &Path,
+    translations: &[PathBuf],
+    reference: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<()> {
+    let (source, catalog) = load_messages(input, reporter)?;
+    let reference = reference.map(load_translations).transpose()?;
+
+    let mut errors = 0;
+    for path in translations {
+        let locale = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let entries = load_translations(path)?;
+        let diagnostics =
+            frel_compiler_core::check_translations(&catalog, &entries, reference.as_ref(), &locale);
+
+        if diagnostics.is_empty() {
+            println!("✓ {} OK", path.display());
+        } else {
+            report(&diagnostics, &source, input, reporter);
+            errors += diagnostics.error_count();
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("Message check failed with {} error(s)", errors);
+    }
 
-    // Write output
-    fs::write(&output_path, code)
+    Ok(())
+}
+
+fn pseudo_locale(
+    input: &Path,
+    reference: Option<&Path>,
+    output: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<()> {
+    let (_, catalog) = load_messages(input, reporter)?;
+    let reference = reference.map(load_translations).transpose()?;
+
+    let pseudo = frel_compiler_core::pseudo_locale(&catalog, reference.as_ref());
+    let json = serde_json::to_string_pretty(&pseudo)?;
+
+    let output_path = output
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| input.with_extension("pseudo.json"));
+    fs::write(&output_path, json + "\n")
         .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
 
-    println!("Compiled {} -> {}", input.display(), output_path.display());
+    println!(
+        "Generated {} pseudo-localized message(s) -> {}",
+        pseudo.len(),
+        output_path.display()
+    );
 
     Ok(())
 }
 
-fn check(input: &Path) -> Result<()> {
-    // Read input file
+fn report_complexity(input: &Path, json: bool, reporter: &Reporter) -> Result<()> {
     let source = fs::read_to_string(input)
         .with_context(|| format!("Failed to read input file: {}", input.display()))?;
 
-    // Parse and check with file path for better diagnostics
-    let result = frel_compiler_core::compile_with_path(&source, &input.display().to_string());
+    let result = frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
+    if result.diagnostics.has_errors() {
+        report(&result.diagnostics, &source, input, reporter);
+        anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+    }
+
+    let file = result.file.context("No AST produced")?;
+    let complexity = frel_compiler_core::analyze_complexity(&file);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&complexity)?);
+        return Ok(());
+    }
+
+    let width = complexity
+        .blueprints
+        .iter()
+        .map(|bp| bp.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Blueprint".len());
+    println!(
+        "{:<width$}  {:>9}  {:>5}  {:>6}  {:>8}  {:>4}  {:>6}",
+        "Blueprint", "Fragments", "Total", "Repeat", "Bindings", "Deps", "Cost"
+    );
+    for bp in &complexity.blueprints {
+        println!(
+            "{:<width$}  {:>9}  {:>5}  {:>6}  {:>8}  {:>4}  {:>6}{}",
+            bp.name,
+            bp.fragments,
+            bp.total_fragments,
+            bp.repeat_depth,
+            bp.bindings,
+            bp.dependencies,
+            bp.cost,
+            if bp.is_slow() { "  !" } else { "" }
+        );
+    }
+
+    let slow: Vec<_> = complexity.slow().collect();
+    if !slow.is_empty() {
+        println!();
+        println!("{} blueprint(s) likely to be slow:", slow.len());
+        for bp in slow {
+            println!("  {}: {}", bp.name, bp.warnings.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+fn graph_states(input: &Path, backend: &str, format: GraphFormat, reporter: &Reporter) -> Result<()> {
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
 
-    // Check for errors
+    let result = frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
     if result.diagnostics.has_errors() {
-        let line_index = frel_compiler_core::LineIndex::new(&source);
-        for diag in result.diagnostics.iter() {
-            let loc = line_index.line_col(diag.span.start);
-            eprintln!(
-                "error[{}]: {} at {}:{}:{}",
-                diag.code.as_deref().unwrap_or("E????"),
-                diag.message,
-                input.display(),
-                loc.line,
-                loc.col
-            );
+        report(&result.diagnostics, &source, input, reporter);
+        anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+    }
+
+    let file = result.file.context("No AST produced")?;
+    let machines = frel_compiler_core::extract_state_machines(&file, backend)
+        .with_context(|| format!("No backend `{}` in {}", backend, input.display()))?;
+    if machines.is_empty() {
+        anyhow::bail!("Backend `{}` has no field of an enum declared in the file", backend);
+    }
+
+    let diagrams: Vec<String> = machines
+        .iter()
+        .map(|machine| match format {
+            GraphFormat::Mermaid => machine.to_mermaid(),
+            GraphFormat::Dot => machine.to_dot(),
+        })
+        .collect();
+    print!("{}", diagrams.join("\n"));
+    Ok(())
+}
+
+fn rename(input: &Path, old: &str, new: &str, reporter: &Reporter) -> Result<()> {
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+    let result = frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
+    if result.diagnostics.has_errors() {
+        report(&result.diagnostics, &source, input, reporter);
+        anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+    }
+
+    let file = result.file.context("No AST produced")?;
+    let resolved = frel_compiler_core::semantic::resolve(&file);
+    let spans = rename::find_symbol(&resolved, old)
+        .and_then(|symbol| rename::rename(&source, &resolved, symbol, new))
+        .map_err(|e| anyhow::anyhow!("Cannot rename: {}", e))?;
+
+    let renamed = rename::apply_rename(&source, &spans, new);
+    fs::write(input, renamed)
+        .with_context(|| format!("Failed to write file: {}", input.display()))?;
+
+    println!(
+        "Renamed {} occurrence(s) of `{}` to `{}` in {}",
+        spans.len(),
+        old,
+        new,
+        input.display()
+    );
+
+    Ok(())
+}
+
+fn diff(old: &Path, new: &Path, json: bool, reporter: &Reporter) -> Result<()> {
+    let (old_source, old_file) = parse_input(old, reporter)?;
+    let (new_source, new_file) = parse_input(new, reporter)?;
+    let changes = ast::diff_files(&old_file, &new_file);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+        return Ok(());
+    }
+
+    let (old_index, new_index) = (LineIndex::new(&old_source), LineIndex::new(&new_source));
+    for change in &changes {
+        let sign = match change.kind {
+            ChangeKind::Insert => '+',
+            ChangeKind::Delete => '-',
+            ChangeKind::Modify => '~',
+        };
+        println!("{} {}: {}", sign, change.path, change.detail);
+        let locations = [
+            (old, &old_index, change.old_span),
+            (new, &new_index, change.new_span),
+        ];
+        for (path, index, span) in locations {
+            if let Some(span) = span {
+                let start = index.line_col(span.start);
+                println!("    {}:{}:{}", path.display(), start.line, start.col);
+            }
         }
-        anyhow::bail!("Check failed with {} error(s)", result.diagnostics.error_count());
+    }
+    if changes.is_empty() {
+        println!("No structural changes");
+    } else {
+        println!("{} change(s)", changes.len());
+    }
+    Ok(())
+}
+
+/// Evaluate an expression and print its value and type
+fn eval(
+    expression: &str,
+    context: &EvalContext,
+    interpreter: &Interpreter,
+    json: bool,
+    reporter: &Reporter,
+) -> Result<()> {
+    let evaluation = match evaluate_with(expression, context, interpreter) {
+        Ok(evaluation) => evaluation,
+        Err(failure) => {
+            report(&failure.diagnostics, &failure.source, Path::new(EVAL_PATH), reporter);
+            let errors = failure.diagnostics.error_count();
+            anyhow::bail!("Evaluation failed with {} error(s)", errors);
+        }
+    };
+    if json {
+        let (value, error) = match &evaluation.value {
+            Ok(value) => (Some(value), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+        let result = serde_json::json!({
+            "type": evaluation.type_name,
+            "value": value,
+            "error": error,
+        });
+        println!("{}", result);
+        return Ok(());
     }
 
-    println!("✓ {} OK", input.display());
+    match &evaluation.value {
+        Ok(ConstValue::String(text)) => println!("{:?}: {}", text, evaluation.type_name),
+        Ok(value) => println!("{}: {}", value, evaluation.type_name),
+        Err(error) => anyhow::bail!(
+            "Cannot evaluate the value of type {}: {}",
+            evaluation.type_name,
+            error
+        ),
+    }
+    Ok(())
+}
 
+/// Evaluate the virtual fields of a scheme for the data of a JSON file
+fn preview(
+    files: &[ast::File],
+    module: Option<&str>,
+    scheme: &str,
+    data: &Path,
+    json: bool,
+) -> Result<()> {
+    let text = fs::read_to_string(data)
+        .with_context(|| format!("Failed to read data file: {}", data.display()))?;
+    let data: serde_json::Value = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid JSON in {}", data.display()))?;
+    let preview =
+        preview_scheme(files, module, scheme, &data).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let failed = preview
+        .virtuals
+        .values()
+        .filter(|field| field.value.is_err())
+        .count();
+    if json {
+        let virtuals: serde_json::Map<String, serde_json::Value> = preview
+            .virtuals
+            .iter()
+            .map(|(name, field)| {
+                let (value, error) = match &field.value {
+                    Ok(value) => (Some(value), None),
+                    Err(error) => (None, Some(error.to_string())),
+                };
+                let result = serde_json::json!({
+                    "type": field.type_name,
+                    "value": value,
+                    "error": error,
+                });
+                (name.clone(), result)
+            })
+            .collect();
+        let result = serde_json::json!({
+            "scheme": format!("{}.{}", preview.module, preview.scheme),
+            "virtuals": virtuals,
+            "unknown": preview.unknown,
+        });
+        println!("{}", result);
+    } else {
+        for name in &preview.unknown {
+            eprintln!("warning: `{}` is not a field of {}", name, preview.scheme);
+        }
+        for (name, field) in &preview.virtuals {
+            match &field.value {
+                Ok(ConstValue::String(text)) => {
+                    println!("{} = {:?}: {}", name, text, field.type_name)
+                }
+                Ok(value) => println!("{} = {}: {}", name, value, field.type_name),
+                Err(error) => println!(
+                    "{}: {} cannot be evaluated: {}",
+                    name, field.type_name, error
+                ),
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} virtual field(s) of {} cannot be evaluated", failed, preview.scheme);
+    }
     Ok(())
 }
+
+/// Evaluate the expressions and execute the handler statements read from
+/// stdin, one per line; expressions see the values statements assigned
+fn eval_lines(context: &EvalContext, json: bool, reporter: &Reporter) -> Result<()> {
+    let mut interpreter = Interpreter::new();
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut line = String::new();
+    loop {
+        if interactive {
+            print!("> ");
+            std::io::stdout().flush()?;
+        }
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        let result = match execute_statement(input, context, &mut interpreter) {
+            Ok(Some(execution)) => {
+                print_execution(&execution, json);
+                Ok(())
+            }
+            Ok(None) => eval(input, context, &interpreter, json, reporter),
+            Err(failure) => {
+                report(&failure.diagnostics, &failure.source, Path::new(EVAL_PATH), reporter);
+                Err(anyhow::anyhow!(
+                    "Statement failed with {} error(s)",
+                    failure.diagnostics.error_count()
+                ))
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// Print what executing a statement did: the value assigned or the command
+/// called
+fn print_execution(execution: &Execution, json: bool) {
+    match (execution, json) {
+        (Execution::Assigned { name, type_name, value }, true) => {
+            let result = serde_json::json!({
+                "assigned": name,
+                "type": type_name,
+                "value": value,
+            });
+            println!("{}", result);
+        }
+        (Execution::Assigned { name, type_name, value: ConstValue::String(text) }, false) => {
+            println!("{} = {:?}: {}", name, text, type_name)
+        }
+        (Execution::Assigned { name, type_name, value }, false) => {
+            println!("{} = {}: {}", name, value, type_name)
+        }
+        (Execution::Called(call), true) => {
+            let result = serde_json::json!({ "called": call.name, "args": call.args });
+            println!("{}", result);
+        }
+        (Execution::Called(call), false) => {
+            let args: Vec<String> = call
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    ConstValue::String(text) => format!("{:?}", text),
+                    value => value.to_string(),
+                })
+                .collect();
+            println!("called {}({})", call.name, args.join(", "));
+        }
+    }
+}
+
+fn format_files(inputs: &[PathBuf], check: bool, reporter: &Reporter) -> Result<()> {
+    let mut unformatted = 0;
+    for input in inputs {
+        let source = fs::read_to_string(input)
+            .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+        let result =
+            frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
+        if result.diagnostics.has_errors() {
+            report(&result.diagnostics, &source, input, reporter);
+            anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+        }
+
+        let file = result.file.context("No AST produced")?;
+        let formatted = frel_fmt::format_file(&source, &file);
+        if formatted == source {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            println!("Would reformat: {}", input.display());
+        } else {
+            fs::write(input, formatted)
+                .with_context(|| format!("Failed to write file: {}", input.display()))?;
+            println!("Formatted: {}", input.display());
+        }
+    }
+
+    if check && unformatted > 0 {
+        anyhow::bail!("{} file(s) would be reformatted", unformatted);
+    }
+    Ok(())
+}
+
+/// Code generators selectable with `--target`, and the semantic passes run on
+/// every module
+fn plugins() -> PluginRegistry {
+    let mut registry = PluginRegistry::new();
+    registry.register(JavaScriptPlugin);
+    registry
+}
+
+/// The `frel.toml` of the project containing `input`, or the defaults
+fn project_config(input: &Path, reporter: &Reporter) -> Result<ProjectConfig> {
+    let input = input.canonicalize().unwrap_or_else(|_| input.to_path_buf());
+    let Some(config_path) = ProjectConfig::find(&DiskFs, &input) else {
+        return Ok(ProjectConfig::default());
+    };
+    let text = DiskFs
+        .read(&config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    match ProjectConfig::parse(&text) {
+        Ok(config) => Ok(config),
+        Err(diagnostics) => {
+            report(&diagnostics, &text, &config_path, reporter);
+            anyhow::bail!("Invalid project configuration: {}", config_path.display())
+        }
+    }
+}
+
+/// What the plugins may access when compiling `input`: its project (the
+/// directory of the `frel.toml` above it, or its own directory), the build
+/// directory if any, and the `FREL_` environment variables
+fn plugin_context(input: &Path, build_dir: Option<&Path>) -> PluginContext {
+    let dir = if input.is_dir() { Some(input) } else { input.parent() };
+    let dir = dir.filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let root = ProjectConfig::find(&DiskFs, &dir)
+        .and_then(|config| config.parent().map(Path::to_path_buf))
+        .unwrap_or(dir);
+    let mut context = PluginContext::new().with_project_root(root).with_process_env();
+    if let Some(build_dir) = build_dir {
+        let build_dir = if build_dir.as_os_str().is_empty() { Path::new(".") } else { build_dir };
+        context = context.with_build_dir(build_dir);
+    }
+    context
+}
+
+/// Directory of the `frel.toml` above the working directory, or the working
+/// directory itself
+fn project_root() -> Result<PathBuf> {
+    let dir = std::env::current_dir().context("Failed to get the working directory")?;
+    let root = ProjectConfig::find(&DiskFs, &dir)
+        .and_then(|config| config.parent().map(Path::to_path_buf))
+        .unwrap_or(dir);
+    Ok(root)
+}
+
+/// Parse the Frel files of a project and build the signatures of its modules
+///
+/// Files with parse errors are skipped.
+fn load_project(
+    root: &Path,
+    reporter: &Reporter,
+) -> Result<(Vec<ast::File>, SignatureRegistry)> {
+    let edition = project_config(root, reporter)?.edition();
+    let mut modules: BTreeMap<String, Vec<ast::File>> = BTreeMap::new();
+    for path in compiler::discover_frel_files(&DiskFs, root) {
+        let source = DiskFs
+            .read(&path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        let name = path.display().to_string();
+        let result = frel_compiler_core::parse_file_with_edition(&source, &name, edition);
+        match result.file {
+            Some(file) if !result.diagnostics.has_errors() => {
+                modules.entry(file.module.clone()).or_default().push(file)
+            }
+            _ => eprintln!("Skipping {}: it has parse errors", path.display()),
+        }
+    }
+
+    let registry = SignatureRegistry::new();
+    let mut files = Vec::new();
+    for (path, module_files) in modules {
+        let module = Module::from_files(path, module_files);
+        registry.register(build_signature(&module).signature);
+        files.extend(module.files);
+    }
+    Ok((files, registry))
+}
+
+/// Read and parse a Frel file, reporting parse errors
+fn parse_input(input: &Path, reporter: &Reporter) -> Result<(String, ast::File)> {
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+    let result = frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
+    if result.diagnostics.has_errors() {
+        report(&result.diagnostics, &source, input, reporter);
+        anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+    }
+
+    let file = result.file.context("No AST produced")?;
+    Ok((source, file))
+}
+
+/// Parse a Frel file and extract its localized messages
+fn load_messages(input: &Path, reporter: &Reporter) -> Result<(String, MessageCatalog)> {
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+    let result = frel_compiler_core::parse_file_with_path(&source, &input.display().to_string());
+    if result.diagnostics.has_errors() {
+        report(&result.diagnostics, &source, input, reporter);
+        anyhow::bail!("Parsing failed with {} error(s)", result.diagnostics.error_count());
+    }
+
+    let file = result.file.context("No AST produced")?;
+    let catalog = frel_compiler_core::extract_messages(&file);
+    Ok((source, catalog))
+}
+
+fn load_translations(path: &Path) -> Result<Translations> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read translation file: {}", path.display()))?;
+    frel_compiler_core::parse_translations(&json)
+        .map_err(|e| anyhow::anyhow!("Invalid translation file {}: {}", path.display(), e))
+}
+
+/// Resolve color and width settings for diagnostics written to stderr
+fn render_options(color: ColorChoice, theme: Theme) -> RenderOptions {
+    let stderr = std::io::stderr();
+    let mut options = if color.should_color(stderr.is_terminal()) {
+        RenderOptions::colored(theme)
+    } else {
+        RenderOptions::plain()
+    };
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size_of(&stderr) {
+        options = options.with_width(width as usize);
+    }
+    options
+}
+
+/// Diagnostics of `input` as (file, source, diagnostics), by the file they
+/// are reported in
+///
+/// With `remap`, the diagnostics covered by the input's source map are moved
+/// into the templates it points to.
+fn diagnostics_by_file(
+    input: &Path,
+    source: &str,
+    diagnostics: &Diagnostics,
+    remap: bool,
+) -> Result<Vec<(PathBuf, String, Diagnostics)>> {
+    let unmapped = || vec![(input.to_path_buf(), source.to_string(), diagnostics.clone())];
+    if !remap {
+        return Ok(unmapped());
+    }
+    let Some(map) = SourceMap::load_sidecar(&DiskFs, input) else {
+        return Ok(unmapped());
+    };
+    let map = map.map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let read = |template: &str| DiskFs.read(Path::new(template)).ok();
+    let (diagnostics, remapped) = map.remap_diagnostics(diagnostics, source, read);
+    let mut files = vec![(input.to_path_buf(), source.to_string(), diagnostics)];
+    for template in remapped {
+        files.push((PathBuf::from(template.file), template.source, template.diagnostics));
+    }
+    Ok(files)
+}
+
+/// Print the diagnostics of several files to stderr
+fn report_files(files: &[(PathBuf, String, Diagnostics)], reporter: &Reporter) {
+    for (file, source, diagnostics) in files {
+        if !diagnostics.is_empty() {
+            report(diagnostics, source, file, reporter);
+        }
+    }
+}
+
+/// Print diagnostics to stderr
+///
+/// Files named by related information are read so their snippets can be shown;
+/// relative paths are tried next to the input file first.
+fn report(diagnostics: &Diagnostics, source: &str, input: &Path, reporter: &Reporter) {
+    let base = input.parent().unwrap_or(Path::new(""));
+    let load = |file: &str| {
+        fs::read_to_string(base.join(file))
+            .or_else(|_| fs::read_to_string(file))
+            .ok()
+    };
+    reporter.report(diagnostics, source, &input.display().to_string(), &load);
+}
@@ -0,0 +1,143 @@
+// Diagnostic output of frelc
+//
+// Diagnostics are written to stderr as soon as the diagnostics of a file are
+// ready, through a `StreamingSink`: rendered with source snippets (`human`),
+// one per line (`short`), or as JSON lines of `FileDiagnostic` (`json`) so
+// tools can follow a long build. `--max-errors` stops the output after that
+// many errors, across all files, and `--quiet` leaves out everything but
+// errors.
+//
+// The reporter also carries the lint levels set on the command line, which
+// win over the `[lints]` table of `frel.toml`.
+
+use std::cell::Cell;
+use std::io::Write;
+
+use clap::ValueEnum;
+use frel_compiler_core::diagnostic::{
+    format_short, format_summary, render_diagnostic_with_sources, MaxErrorsSink, RenderOptions,
+    SourceLoader, StreamingSink,
+};
+use frel_compiler_core::{
+    DiagnosticSink, Diagnostics, FileDiagnostic, LineIndex, LintLevels, ProjectConfig, Severity,
+};
+
+/// How diagnostics are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ErrorFormat {
+    /// Rendered with source snippets, and a summary line per file
+    #[default]
+    Human,
+    /// One line per diagnostic: file:line:col: severity[code]: message
+    Short,
+    /// One JSON object per line, with the file, position and diagnostic
+    Json,
+}
+
+/// Writes diagnostics to stderr in the chosen format
+pub struct Reporter {
+    render: RenderOptions,
+    format: ErrorFormat,
+    max_errors: Option<usize>,
+    quiet: bool,
+    /// Lint levels of `-A`, `-W`, `-D` and `--deny-warnings`
+    lints: LintLevels,
+    /// Errors written so far, counted against `max_errors`
+    errors: Cell<usize>,
+    /// Diagnostics dropped once `max_errors` was reached
+    suppressed: Cell<usize>,
+}
+
+impl Reporter {
+    pub fn new(
+        render: RenderOptions,
+        format: ErrorFormat,
+        max_errors: Option<usize>,
+        quiet: bool,
+        lints: LintLevels,
+    ) -> Self {
+        Self {
+            render,
+            format,
+            max_errors,
+            quiet,
+            lints,
+            errors: Cell::new(0),
+            suppressed: Cell::new(0),
+        }
+    }
+
+    /// Whether only errors are reported, without progress or success messages
+    pub fn quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Lint levels of a project: its `[lints]`, then the command line's
+    pub fn lints(&self, config: &ProjectConfig) -> LintLevels {
+        let mut lints = config.lints.clone();
+        lints.extend(&self.lints);
+        lints
+    }
+
+    /// Write the diagnostics of `file`
+    ///
+    /// `loader` reads the other files named by related information, so human
+    /// output can show their snippets.
+    pub fn report(
+        &self,
+        diagnostics: &Diagnostics,
+        source: &str,
+        file: &str,
+        loader: &dyn SourceLoader,
+    ) {
+        let index = LineIndex::new(source);
+        let mut stderr = std::io::stderr().lock();
+        let write = |diagnostic: &frel_compiler_core::Diagnostic| {
+            let text = match self.format {
+                ErrorFormat::Human => {
+                    let rendered = render_diagnostic_with_sources(
+                        diagnostic,
+                        source,
+                        file,
+                        &index,
+                        &self.render,
+                        loader,
+                    );
+                    format!("{}\n", rendered)
+                }
+                ErrorFormat::Short => format!("{}\n", format_short(diagnostic, file, &index)),
+                ErrorFormat::Json => {
+                    let line = FileDiagnostic::new(file, Some(&index), diagnostic.clone());
+                    format!("{}\n", serde_json::to_string(&line).unwrap_or_default())
+                }
+            };
+            let _ = stderr.write_all(text.as_bytes());
+        };
+
+        let remaining = self
+            .max_errors
+            .map_or(usize::MAX, |max| max.saturating_sub(self.errors.get()));
+        let mut sink = MaxErrorsSink::new(StreamingSink::new(write), remaining);
+        for diagnostic in diagnostics.iter() {
+            if !self.quiet || diagnostic.severity == Severity::Error {
+                sink.emit(diagnostic.clone());
+            }
+        }
+        self.suppressed.set(self.suppressed.get() + sink.suppressed());
+        self.errors.set(self.errors.get() + sink.into_inner().error_count());
+
+        if self.format == ErrorFormat::Human && !self.quiet {
+            eprint!("{}", format_summary(diagnostics.error_count(), diagnostics.warning_count()));
+        }
+    }
+
+    /// Say how many diagnostics `--max-errors` left out, if any
+    pub fn finish(&self) {
+        if let (Some(max), suppressed @ 1..) = (self.max_errors, self.suppressed.get()) {
+            eprintln!(
+                "note: {} more diagnostic(s) not shown after {} error(s) (--max-errors)",
+                suppressed, max
+            );
+        }
+    }
+}
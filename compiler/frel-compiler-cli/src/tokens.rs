@@ -0,0 +1,517 @@
+// Design token import
+//
+// Converts W3C design token JSON (https://tr.designtokens.org/format/) into a
+// Frel theme module, so design systems exported from Figma and similar tools
+// can be synced into Frel without hand-transcription.
+//
+// Supported token types:
+// - color -> Color
+// - dimension, number, fontWeight, duration -> u32 / i32 / f64
+// - fontFamily, string -> String
+// - typography -> one field per sub-value
+//
+// Aliases (`{group.token}`) become references to the generated field. Tokens
+// of other types, and aliases that are unknown or form a cycle, are skipped
+// and listed in the generated header.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use frel_compiler_core::TokenKind;
+use serde_json::{Map, Value};
+
+/// Root font size used to convert `rem`/`em` dimensions to pixels
+const ROOT_FONT_SIZE: f64 = 16.0;
+
+/// Result of converting a token file
+pub struct ImportResult {
+    /// Generated Frel source
+    pub source: String,
+    /// Number of theme fields generated
+    pub field_count: usize,
+    /// Token paths that were skipped, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Convert design token JSON into a Frel module declaring one theme
+pub fn import_tokens(json: &str, module: &str, theme: &str, origin: &str) -> Result<ImportResult> {
+    let root: Value = serde_json::from_str(json).context("Invalid design token JSON")?;
+    let Value::Object(root) = root else {
+        bail!("Design token file must contain a JSON object");
+    };
+
+    let mut tokens = Vec::new();
+    collect_tokens(&root, &mut Vec::new(), None, &mut tokens);
+
+    // Expand composite tokens first so aliases can point at their parts
+    let mut fields: Vec<Field> = Vec::new();
+    let mut skipped = Vec::new();
+    for token in &tokens {
+        match expand_token(token) {
+            Ok(expanded) => fields.extend(expanded),
+            Err(reason) => skipped.push((token.path.join("."), reason)),
+        }
+    }
+
+    let mut names: HashMap<String, String> = HashMap::new();
+    for field in &fields {
+        if let Some(existing) = names.insert(field.name.clone(), field.path.clone()) {
+            bail!(
+                "Tokens '{}' and '{}' both map to the field name '{}'",
+                existing,
+                field.path,
+                field.name
+            );
+        }
+    }
+
+    // Resolve aliases to the type of the field they reference
+    let by_path: HashMap<&str, &Field> = fields.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut lines = Vec::new();
+    for field in &fields {
+        let (ty, value) = match &field.value {
+            FieldValue::Literal(ty, value) => (ty.clone(), value.clone()),
+            FieldValue::Alias(target) => match alias_type(target, &by_path) {
+                Ok(ty) => (ty, by_path[target.as_str()].name.clone()),
+                Err(reason) => {
+                    skipped.push((field.path.clone(), reason));
+                    continue;
+                }
+            },
+        };
+        lines.push(format!("    {} : {} = {}", field.name, ty, value));
+    }
+
+    let mut source = String::new();
+    source.push_str(&format!(
+        "// Generated by `frelc import-tokens` from {}. Do not edit by hand.\n",
+        origin
+    ));
+    for (path, reason) in &skipped {
+        source.push_str(&format!("// Skipped {}: {}\n", path, reason));
+    }
+    source.push_str(&format!("\nmodule {}\n\ntheme {} {{\n", module, theme));
+    for line in &lines {
+        source.push_str(line);
+        source.push('\n');
+    }
+    source.push_str("}\n");
+
+    Ok(ImportResult {
+        source,
+        field_count: lines.len(),
+        skipped,
+    })
+}
+
+/// A token found in the JSON tree
+struct Token {
+    path: Vec<String>,
+    ty: Option<String>,
+    value: Value,
+}
+
+/// Walk groups collecting tokens; `$type` on a group applies to its members
+fn collect_tokens(
+    group: &Map<String, Value>,
+    path: &mut Vec<String>,
+    inherited_type: Option<&str>,
+    tokens: &mut Vec<Token>,
+) {
+    let group_type = group
+        .get("$type")
+        .and_then(Value::as_str)
+        .or(inherited_type);
+
+    for (key, value) in group {
+        if key.starts_with('$') {
+            continue;
+        }
+        let Value::Object(member) = value else {
+            continue;
+        };
+
+        path.push(key.clone());
+        if let Some(token_value) = member.get("$value") {
+            let ty = member.get("$type").and_then(Value::as_str).or(group_type);
+            tokens.push(Token {
+                path: path.clone(),
+                ty: ty.map(String::from),
+                value: token_value.clone(),
+            });
+        } else {
+            collect_tokens(member, path, group_type, tokens);
+        }
+        path.pop();
+    }
+}
+
+/// A theme field to generate
+struct Field {
+    /// Dotted token path, used to resolve aliases
+    path: String,
+    name: String,
+    value: FieldValue,
+}
+
+enum FieldValue {
+    /// Frel type and literal
+    Literal(String, String),
+    /// Dotted path of the aliased token
+    Alias(String),
+}
+
+/// Frel type of an alias, following the chain to the literal it ends at
+fn alias_type(target: &str, by_path: &HashMap<&str, &Field>) -> Result<String, String> {
+    let mut visited = HashSet::new();
+    let mut target = target;
+    loop {
+        let field = by_path
+            .get(target)
+            .ok_or_else(|| format!("unknown alias '{{{}}}'", target))?;
+        if !visited.insert(target) {
+            return Err(format!("cyclic alias '{{{}}}'", target));
+        }
+        match &field.value {
+            FieldValue::Literal(ty, _) => return Ok(ty.clone()),
+            FieldValue::Alias(next) => target = next,
+        }
+    }
+}
+
+/// Convert a token to one or more fields, or explain why it is skipped
+fn expand_token(token: &Token) -> Result<Vec<Field>, String> {
+    let path = token.path.join(".");
+
+    if let Some(target) = alias_target(&token.value) {
+        return Ok(vec![Field {
+            name: field_name(&token.path),
+            path,
+            value: FieldValue::Alias(target.to_string()),
+        }]);
+    }
+
+    let ty = token.ty.as_deref().ok_or("token has no $type")?;
+    if ty == "typography" {
+        let Value::Object(parts) = &token.value else {
+            return Err("typography value must be an object".to_string());
+        };
+        let mut fields = Vec::new();
+        for (part, value) in parts {
+            let part_type = match part.as_str() {
+                "fontFamily" => "fontFamily",
+                "fontWeight" => "fontWeight",
+                "fontSize" | "lineHeight" | "letterSpacing" => "dimension",
+                _ => continue,
+            };
+            let mut part_path = token.path.clone();
+            part_path.push(part.clone());
+            fields.extend(expand_token(&Token {
+                path: part_path,
+                ty: Some(part_type.to_string()),
+                value: value.clone(),
+            })?);
+        }
+        return Ok(fields);
+    }
+
+    let (frel_type, literal) = convert_value(ty, &token.value)?;
+    Ok(vec![Field {
+        name: field_name(&token.path),
+        path,
+        value: FieldValue::Literal(frel_type, literal),
+    }])
+}
+
+/// The referenced path if a value is an alias like `{color.primary}`
+fn alias_target(value: &Value) -> Option<&str> {
+    value.as_str()?.strip_prefix('{')?.strip_suffix('}')
+}
+
+/// Convert a simple token value to a Frel type and literal
+fn convert_value(ty: &str, value: &Value) -> Result<(String, String), String> {
+    match ty {
+        "color" => {
+            let text = value.as_str().ok_or("color must be a string")?;
+            color_literal(text).map(|c| ("Color".to_string(), c))
+        }
+        "dimension" => {
+            let px = match value {
+                Value::String(text) => parse_dimension(text)?,
+                Value::Number(n) => n.as_f64().ok_or("invalid number")?,
+                // Newer drafts use `{ "value": 16, "unit": "px" }`
+                Value::Object(parts) => {
+                    let number = parts
+                        .get("value")
+                        .and_then(Value::as_f64)
+                        .ok_or("dimension has no value")?;
+                    let unit = parts.get("unit").and_then(Value::as_str).unwrap_or("px");
+                    parse_dimension(&format!("{}{}", number, unit))?
+                }
+                _ => return Err("dimension must be a string or number".to_string()),
+            };
+            Ok(number_literal(px))
+        }
+        "number" => value
+            .as_f64()
+            .map(number_literal)
+            .ok_or_else(|| "number must be numeric".to_string()),
+        "fontWeight" => {
+            let weight = match value {
+                Value::Number(n) => n.as_f64().ok_or("invalid number")?,
+                Value::String(name) => {
+                    font_weight(name).ok_or_else(|| format!("unknown font weight '{}'", name))?
+                }
+                _ => return Err("fontWeight must be a number or name".to_string()),
+            };
+            Ok(number_literal(weight))
+        }
+        "duration" => {
+            let text = value.as_str().ok_or("duration must be a string")?;
+            let ms = if let Some(ms) = text.strip_suffix("ms") {
+                ms.trim().parse::<f64>()
+            } else if let Some(s) = text.strip_suffix('s') {
+                s.trim().parse::<f64>().map(|s| s * 1000.0)
+            } else {
+                return Err(format!("unsupported duration '{}'", text));
+            }
+            .map_err(|_| format!("invalid duration '{}'", text))?;
+            Ok(number_literal(ms))
+        }
+        "fontFamily" => {
+            let family = match value {
+                Value::String(name) => name.clone(),
+                Value::Array(names) => names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => return Err("fontFamily must be a string or list".to_string()),
+            };
+            Ok(("String".to_string(), string_literal(&family)))
+        }
+        "string" => {
+            let text = value.as_str().ok_or("string token must be a string")?;
+            Ok(("String".to_string(), string_literal(text)))
+        }
+        other => Err(format!("unsupported token type '{}'", other)),
+    }
+}
+
+/// Parse `16px`, `1.5rem` or a bare number to pixels
+fn parse_dimension(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let (number, scale) = if let Some(n) = text.strip_suffix("px") {
+        (n, 1.0)
+    } else if let Some(n) = text.strip_suffix("rem").or_else(|| text.strip_suffix("em")) {
+        (n, ROOT_FONT_SIZE)
+    } else {
+        (text, 1.0)
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|n| n * scale)
+        .map_err(|_| format!("unsupported dimension '{}'", text))
+}
+
+/// Frel type and literal for a number: `u32` or `i32` when whole, else `f64`
+fn number_literal(n: f64) -> (String, String) {
+    if n.fract() == 0.0 && n >= 0.0 && n <= u32::MAX as f64 {
+        ("u32".to_string(), format!("{}", n as u64))
+    } else if n.fract() == 0.0 && n >= i32::MIN as f64 && n < 0.0 {
+        ("i32".to_string(), format!("{}", n as i64))
+    } else {
+        ("f64".to_string(), format!("{:?}", n))
+    }
+}
+
+/// Normalize `#RGB`, `#RRGGBB` or `#RRGGBBAA` to an uppercase Frel color literal
+fn color_literal(text: &str) -> Result<String, String> {
+    let hex = text
+        .strip_prefix('#')
+        .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| format!("unsupported color '{}'", text))?;
+    let hex = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return Err(format!("unsupported color '{}'", text)),
+    };
+    Ok(format!("#{}", hex.to_ascii_uppercase()))
+}
+
+fn font_weight(name: &str) -> Option<f64> {
+    let weight = match name.to_ascii_lowercase().replace(['-', ' '], "").as_str() {
+        "thin" | "hairline" => 100,
+        "extralight" | "ultralight" => 200,
+        "light" => 300,
+        "normal" | "regular" | "book" => 400,
+        "medium" => 500,
+        "semibold" | "demibold" => 600,
+        "bold" => 700,
+        "extrabold" | "ultrabold" => 800,
+        "black" | "heavy" => 900,
+        _ => return None,
+    };
+    Some(weight as f64)
+}
+
+/// Quote a string, escaping characters the Frel lexer treats specially
+fn string_literal(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '$' => literal.push_str("\\$"),
+            '\n' => literal.push_str("\\n"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// camelCase identifier for a token path, e.g. `color.primary-500` -> `colorPrimary500`
+///
+/// Names that aren't identifiers, or are Frel keywords, get a `t` prefix.
+fn field_name(path: &[String]) -> String {
+    let mut name = String::new();
+    for word in path
+        .iter()
+        .flat_map(|segment| segment.split(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|word| !word.is_empty())
+    {
+        let mut chars = word.chars();
+        let first = chars.next().unwrap();
+        if name.is_empty() {
+            name.push(first.to_ascii_lowercase());
+        } else {
+            name.push(first.to_ascii_uppercase());
+        }
+        name.extend(chars);
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 't');
+    } else if TokenKind::keyword_from_str(&name).is_some()
+        || TokenKind::is_contextual_keyword(&name)
+    {
+        name[..1].make_ascii_uppercase();
+        name.insert(0, 't');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frel_compiler_core::{evaluate_theme, ConstValue};
+
+    const TOKENS: &str = r##"{
+        "color": {
+            "$type": "color",
+            "primary": { "$value": "#3366ff" },
+            "overlay": { "$value": "#00000080" },
+            "accent": { "$value": "{color.primary}" }
+        },
+        "spacing": {
+            "small": { "$value": "8px", "$type": "dimension" },
+            "large": { "$value": "1.5rem", "$type": "dimension" },
+            "half": { "$value": "0.5px", "$type": "dimension" }
+        },
+        "font": {
+            "body": {
+                "$type": "typography",
+                "$value": { "fontFamily": ["Inter", "sans-serif"], "fontSize": "14px", "fontWeight": "bold" }
+            }
+        },
+        "shadow": { "card": { "$type": "shadow", "$value": { "blur": "4px" } } }
+    }"##;
+
+    #[test]
+    fn test_generated_theme_compiles() {
+        let result = import_tokens(TOKENS, "design.tokens", "Tokens", "tokens.json").unwrap();
+        let parsed = frel_compiler_core::parse_file(&result.source);
+        assert!(!parsed.diagnostics.has_errors(), "{}", result.source);
+
+        let file = parsed.file.unwrap();
+        let values = evaluate_theme(&file, "Tokens", None).unwrap();
+        assert!(values.is_complete());
+        assert_eq!(
+            values.get("colorPrimary"),
+            Some(&ConstValue::Color(0x3366FFFF))
+        );
+        assert_eq!(
+            values.get("colorAccent"),
+            Some(&ConstValue::Color(0x3366FFFF))
+        );
+        assert_eq!(
+            values.get("colorOverlay"),
+            Some(&ConstValue::Color(0x00000080))
+        );
+        assert_eq!(values.get("spacingSmall"), Some(&ConstValue::Int(8)));
+        assert_eq!(values.get("spacingLarge"), Some(&ConstValue::Int(24)));
+        assert_eq!(values.get("spacingHalf"), Some(&ConstValue::Float(0.5)));
+        assert_eq!(
+            values.get("fontBodyFontFamily"),
+            Some(&ConstValue::String("Inter, sans-serif".to_string()))
+        );
+        assert_eq!(
+            values.get("fontBodyFontWeight"),
+            Some(&ConstValue::Int(700))
+        );
+        assert_eq!(result.field_count, 9);
+    }
+
+    #[test]
+    fn test_unsupported_tokens_are_skipped() {
+        let result = import_tokens(TOKENS, "design.tokens", "Tokens", "tokens.json").unwrap();
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].0, "shadow.card");
+        assert!(result
+            .source
+            .contains("// Skipped shadow.card: unsupported token type 'shadow'"));
+    }
+
+    #[test]
+    fn test_field_names() {
+        let path = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            field_name(&path(&["color", "primary-500"])),
+            "colorPrimary500"
+        );
+        assert_eq!(field_name(&path(&["Spacing", "x small"])), "spacingXSmall");
+        assert_eq!(field_name(&path(&["100"])), "t100");
+        assert_eq!(field_name(&path(&["select"])), "tSelect");
+        assert_eq!(field_name(&path(&["enum"])), "tEnum");
+        assert_eq!(field_name(&path(&["module", "gap"])), "moduleGap");
+    }
+
+    #[test]
+    fn test_cyclic_aliases_are_skipped() {
+        let json = r#"{
+            "a": { "$value": "{b}" },
+            "b": { "$value": "{a}" },
+            "c": { "$value": "{a}" },
+            "on": { "$type": "number", "$value": 1 }
+        }"#;
+        let result = import_tokens(json, "m", "T", "t.json").unwrap();
+        let skipped: Vec<&str> = result
+            .skipped
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(skipped, ["a", "b", "c"]);
+        assert_eq!(result.skipped[0].1, "cyclic alias '{b}'");
+        assert_eq!(result.field_count, 1);
+
+        let parsed = frel_compiler_core::parse_file(&result.source);
+        assert!(!parsed.diagnostics.has_errors(), "{}", result.source);
+    }
+
+    #[test]
+    fn test_name_collision_is_an_error() {
+        let json = r#"{ "a-b": { "$type": "number", "$value": 1 }, "aB": { "$type": "number", "$value": 2 } }"#;
+        assert!(import_tokens(json, "m", "T", "t.json").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+// Structural AST diff
+//
+// Compares two parsed files node by node instead of as text. Spans, comments
+// and the source path are ignored, so reformatting a file or moving code
+// within it reports no changes. Each change inserts, deletes or modifies a
+// node, located by a path such as `declarations[Main].body[count].init` and
+// by the span of the node, or of its nearest spanned ancestor, in each file.
+//
+// The diff walks the serialized form of the AST (the form of the `.ast.json`
+// baselines), so it covers every node type without a case per node.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::File;
+use crate::source::Span;
+
+/// Fields of `File` that don't affect the program
+const IGNORED_FIELDS: &[&str] = &["comments", "source_path"];
+
+/// Longest node summary in a change before it is shortened
+const MAX_SUMMARY_LEN: usize = 60;
+
+/// What happened to a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Insert,
+    Delete,
+    Modify,
+}
+
+/// A node-level difference between two files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AstChange {
+    pub kind: ChangeKind,
+    /// Location of the node, e.g. `declarations[Main].body[1]`; list elements
+    /// are labeled by name where they have one
+    pub path: String,
+    /// Span of the node, or of its nearest spanned ancestor, in the old file
+    pub old_span: Option<Span>,
+    /// Span of the node, or of its nearest spanned ancestor, in the new file
+    pub new_span: Option<Span>,
+    /// The inserted or deleted node, or the old and new value
+    pub detail: String,
+}
+
+/// Structural differences from `old` to `new`, in tree order
+pub fn diff_files(old: &File, new: &File) -> Vec<AstChange> {
+    let (old, new) = (file_value(old), file_value(new));
+    let mut changes = Vec::new();
+    diff(&old, &new, "", (None, None), &mut changes);
+    changes
+}
+
+fn file_value(file: &File) -> Value {
+    let mut value = serde_json::to_value(file).expect("AST serializes");
+    if let Value::Object(fields) = &mut value {
+        for field in IGNORED_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    value
+}
+
+type Spans = (Option<Span>, Option<Span>);
+
+fn diff(old: &Value, new: &Value, path: &str, spans: Spans, changes: &mut Vec<AstChange>) {
+    if same(old, new) {
+        return;
+    }
+    let spans = (node_span(old).or(spans.0), node_span(new).or(spans.1));
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) if variant(a) == variant(b) => {
+            // Variants are transparent in paths: `body[0].init`, not
+            // `body[0].local_decl.init`
+            let fields = a.keys().chain(b.keys().filter(|key| !a.contains_key(*key)));
+            for key in fields.filter(|key| *key != "span") {
+                let child = match variant(a) {
+                    Some(_) => path.to_string(),
+                    None => join(path, key),
+                };
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => diff(a, b, &child, spans, changes),
+                    (Some(a), None) => changes.push(change(ChangeKind::Delete, child, spans, a)),
+                    (None, Some(b)) => changes.push(change(ChangeKind::Insert, child, spans, b)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => diff_list(a, b, path, spans, changes),
+        _ => changes.push(AstChange {
+            kind: ChangeKind::Modify,
+            path: path.to_string(),
+            old_span: spans.0,
+            new_span: spans.1,
+            detail: format!("{} -> {}", summary(old), summary(new)),
+        }),
+    }
+}
+
+/// Diff two lists: unchanged elements are aligned first, then the remaining
+/// elements are paired by kind and name as modifications
+fn diff_list(old: &[Value], new: &[Value], path: &str, spans: Spans, changes: &mut Vec<AstChange>) {
+    let anchors = common_subsequence(old, new);
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in anchors.into_iter().chain([(old.len(), new.len())]) {
+        // Pair the elements between the previous anchor and this one
+        for (old_index, element) in old.iter().enumerate().take(next_i).skip(i) {
+            let paired = (j..next_j).find(|&k| identity(element) == identity(&new[k]));
+            let Some(new_index) = paired else {
+                let child = element_path(path, element, old_index);
+                changes.push(change(ChangeKind::Delete, child, spans, element));
+                continue;
+            };
+            for (k, inserted) in new.iter().enumerate().take(new_index).skip(j) {
+                let child = element_path(path, inserted, k);
+                changes.push(change(ChangeKind::Insert, child, spans, inserted));
+            }
+            let child = element_path(path, &new[new_index], new_index);
+            diff(element, &new[new_index], &child, spans, changes);
+            j = new_index + 1;
+        }
+        for (k, inserted) in new.iter().enumerate().take(next_j).skip(j) {
+            let child = element_path(path, inserted, k);
+            changes.push(change(ChangeKind::Insert, child, spans, inserted));
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+}
+
+/// Index pairs of the longest common subsequence of equal elements
+fn common_subsequence(old: &[Value], new: &[Value]) -> Vec<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if same(&old[i], &new[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(&old[i], &new[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Whether two nodes are equal, ignoring spans
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let fields = |map: &Map<String, Value>| map.keys().filter(|k| *k != "span").count();
+            fields(a) == fields(b)
+                && a.iter()
+                    .filter(|(key, _)| *key != "span")
+                    .all(|(key, value)| b.get(key).is_some_and(|other| same(value, other)))
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// The variant name of an enum node: enums serialize as `{"variant": ...}`
+fn variant(object: &Map<String, Value>) -> Option<&str> {
+    match object.len() {
+        1 => object.keys().next().map(String::as_str),
+        _ => None,
+    }
+}
+
+/// The fields of a node, looking through its variant
+fn fields(value: &Value) -> Option<&Map<String, Value>> {
+    let object = value.as_object()?;
+    match variant(object) {
+        Some(name) => object[name].as_object().or(Some(object)),
+        None => Some(object),
+    }
+}
+
+fn node_span(value: &Value) -> Option<Span> {
+    serde_json::from_value(fields(value)?.get("span")?.clone()).ok()
+}
+
+fn name(value: &Value) -> Option<&str> {
+    fields(value)?.get("name")?.as_str()
+}
+
+/// What list elements are paired by: their variant and name
+fn identity(value: &Value) -> (Option<&str>, Option<&str>) {
+    let kind = value.as_object().and_then(variant);
+    (kind, name(value))
+}
+
+fn element_path(path: &str, element: &Value, index: usize) -> String {
+    match name(element) {
+        Some(name) => format!("{}[{}]", path, name),
+        None => format!("{}[{}]", path, index),
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    match path {
+        "" => field.to_string(),
+        _ => format!("{}.{}", path, field),
+    }
+}
+
+fn change(kind: ChangeKind, path: String, spans: Spans, node: &Value) -> AstChange {
+    let (old_span, new_span) = match kind {
+        ChangeKind::Delete => (node_span(node).or(spans.0), spans.1),
+        _ => (spans.0, node_span(node).or(spans.1)),
+    };
+    AstChange {
+        kind,
+        path,
+        old_span,
+        new_span,
+        detail: summary(node),
+    }
+}
+
+/// Short description of a node: its variant and name, or its value
+fn summary(value: &Value) -> String {
+    let kind = value.as_object().and_then(variant);
+    if let (Some(kind), Some(name)) = (kind, name(value)) {
+        return format!("{} {}", kind, name);
+    }
+    let text = serde_json::to_string(&without_spans(value)).expect("JSON value serializes");
+    match text.char_indices().nth(MAX_SUMMARY_LEN) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+fn without_spans(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .filter(|(key, _)| *key != "span")
+                .map(|(key, value)| (key.clone(), without_spans(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(without_spans).collect()),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn diff_sources(old: &str, new: &str) -> Vec<AstChange> {
+        let old = parser::parse(old).file.unwrap();
+        let new = parser::parse(new).file.unwrap();
+        diff_files(&old, &new)
+    }
+
+    const COUNTER: &str =
+        "module app\n\nblueprint Counter {\n    count: i32 = 0\n    text { \"Count\" }\n}\n";
+
+    #[test]
+    fn test_formatting_is_not_a_change() {
+        let reformatted = "module app\n// A counter\nblueprint Counter {\n\n  count : i32 = 0\n  text { \"Count\" }\n}\n";
+        assert!(diff_sources(COUNTER, reformatted).is_empty());
+    }
+
+    #[test]
+    fn test_modify_reports_path_and_spans() {
+        let new = COUNTER.replace("= 0", "= 10");
+        let changes = diff_sources(COUNTER, &new);
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!(change.kind, ChangeKind::Modify);
+        assert_eq!(change.path, "declarations[Counter].body[count].init");
+        assert_eq!(change.detail, "0 -> 10");
+        assert_eq!(change.old_span, Some(Span::new(36, 51)));
+        assert_eq!(change.new_span, Some(Span::new(36, 52)));
+    }
+
+    #[test]
+    fn test_insert_and_delete_nodes() {
+        let new = COUNTER
+            .replace("    text { \"Count\" }\n", "")
+            .replace("}\n", "}\n\nblueprint Card { }\n");
+        let changes = diff_sources(COUNTER, &new);
+        let summary: Vec<(ChangeKind, &str, &str)> = changes
+            .iter()
+            .map(|c| (c.kind, c.path.as_str(), c.detail.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    ChangeKind::Delete,
+                    "declarations[Counter].body[text]",
+                    "fragment_creation text"
+                ),
+                (ChangeKind::Insert, "declarations[Card]", "blueprint Card"),
+            ]
+        );
+        assert_eq!(changes[1].old_span, None);
+        assert!(changes[1].new_span.is_some());
+    }
+}
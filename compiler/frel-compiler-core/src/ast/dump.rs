@@ -2,35 +2,104 @@
 //
 // This module implements a human-readable DUMP format for the AST.
 // The format is indentation-based and suitable for debugging and review.
+//
+// For big files, `DumpOptions` folds the nodes below a depth into one
+// `… (N nodes)` line, shortens long expressions, and appends the spans of
+// nodes and the types the semantic analysis found for them. With the names
+// the analysis resolved, identifiers read `count /* Field: i32 */`.
+
+use std::collections::HashMap;
 
 use super::visitor::Visitor;
 use super::*;
 
+/// Width above which collapsed expressions are shortened, in characters
+const COLLAPSED_WIDTH: usize = 40;
+
+/// Options of the DUMP output
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    /// Deepest level written, the file being level 0; deeper nodes are folded
+    pub max_depth: Option<usize>,
+    /// Shorten expressions longer than 40 characters with `…`
+    pub collapse_exprs: bool,
+    /// Append the span of nodes as `@start..end`
+    pub spans: bool,
+    /// Types of nodes by span, appended as `-> type`; see
+    /// `semantic::dump::node_types`
+    pub types: HashMap<Span, String>,
+    /// Annotations of identifiers by the span of their statement and name,
+    /// written after them as `/* … */`; see `semantic::dump::name_annotations`
+    pub names: HashMap<(Span, String), String>,
+}
+
 /// A visitor that produces DUMP format output
 pub struct DumpVisitor {
     output: String,
     indent: usize,
     indent_str: String,
+    options: DumpOptions,
+    /// Lines folded since the last `… (N nodes)` line
+    folded: usize,
+    /// Span of the statement written, under which its names are annotated
+    context: Span,
 }
 
 impl DumpVisitor {
     /// Create a new DumpVisitor
     pub fn new() -> Self {
+        Self::with_options(DumpOptions::default())
+    }
+
+    /// Create a DumpVisitor with options
+    pub fn with_options(options: DumpOptions) -> Self {
         Self {
             output: String::new(),
             indent: 0,
             indent_str: "    ".to_string(),
+            options,
+            folded: 0,
+            context: Span::default(),
         }
     }
 
     /// Dump a file to DUMP format
     pub fn dump(file: &File) -> String {
-        let mut visitor = Self::new();
+        Self::dump_with(file, DumpOptions::default())
+    }
+
+    /// Dump a file to DUMP format with options
+    pub fn dump_with(file: &File, options: DumpOptions) -> String {
+        let mut visitor = Self::with_options(options);
         visitor.visit_file(file);
         visitor.output
     }
 
     fn write(&mut self, text: &str) {
+        if self
+            .options
+            .max_depth
+            .is_some_and(|depth| self.indent > depth)
+        {
+            self.folded += 1;
+            return;
+        }
+        self.push_line(text);
+    }
+
+    /// Write a node with its type and span, as the options ask
+    fn write_node(&mut self, text: &str, span: Span) {
+        let mut line = text.to_string();
+        if let Some(ty) = self.options.types.get(&span) {
+            line.push_str(&format!(" -> {}", ty));
+        }
+        if self.options.spans {
+            line.push_str(&format!(" @{}..{}", span.start, span.end));
+        }
+        self.write(&line);
+    }
+
+    fn push_line(&mut self, text: &str) {
         for _ in 0..self.indent {
             self.output.push_str(&self.indent_str);
         }
@@ -46,36 +115,35 @@ impl DumpVisitor {
         if self.indent > 0 {
             self.indent -= 1;
         }
+        // Leaving the deepest level written: tell what was folded below it
+        if self.folded > 0 && self.options.max_depth == Some(self.indent) {
+            let folded = std::mem::take(&mut self.folded);
+            self.indent += 1;
+            match folded {
+                1 => self.push_line("… (1 node)"),
+                n => self.push_line(&format!("… ({} nodes)", n)),
+            }
+            self.indent -= 1;
+        }
     }
 
     // Helper to format a type expression inline
     fn type_inline(&self, type_expr: &TypeExpr) -> String {
-        match type_expr {
-            TypeExpr::Named(name) => name.clone(),
-            TypeExpr::Nullable(inner) => format!("{}?", self.type_inline(inner)),
-            TypeExpr::Ref(inner) => format!("ref {}", self.type_inline(inner)),
-            TypeExpr::Draft(inner) => format!("draft {}", self.type_inline(inner)),
-            TypeExpr::Asset(inner) => format!("asset {}", self.type_inline(inner)),
-            TypeExpr::Blueprint(params) => {
-                if params.is_empty() {
-                    "Blueprint".to_string()
-                } else {
-                    let p: Vec<_> = params.iter().map(|p| self.type_inline(p)).collect();
-                    format!("Blueprint<{}>", p.join(", "))
-                }
-            }
-            TypeExpr::Accessor(inner) => format!("Accessor<{}>", self.type_inline(inner)),
-            TypeExpr::List(elem) => format!("List<{}>", self.type_inline(elem)),
-            TypeExpr::Set(elem) => format!("Set<{}>", self.type_inline(elem)),
-            TypeExpr::Map(key, value) => {
-                format!("Map<{}, {}>", self.type_inline(key), self.type_inline(value))
-            }
-            TypeExpr::Tree(elem) => format!("Tree<{}>", self.type_inline(elem)),
-        }
+        type_expr.to_string()
     }
 
-    // Helper to format an expression inline (for simple expressions)
+    // Helper to format an expression inline, shortened when collapsing
     fn expr_inline(&self, expr: &Expr) -> String {
+        let text = self.expr_text(expr);
+        if !self.options.collapse_exprs || text.chars().count() <= COLLAPSED_WIDTH {
+            return text;
+        }
+        let mut short: String = text.chars().take(COLLAPSED_WIDTH - 1).collect();
+        short.push('…');
+        short
+    }
+
+    fn expr_text(&self, expr: &Expr) -> String {
         match expr {
             Expr::Null => "null".to_string(),
             Expr::Bool(b) => b.to_string(),
@@ -83,29 +151,34 @@ impl DumpVisitor {
             Expr::Float(f) => f.to_string(),
             Expr::Color(c) => format!("#{:08X}", c),
             Expr::String(s) => format!("{:?}", s),
-            Expr::Identifier(name) => name.clone(),
-            Expr::QualifiedName(parts) => parts.join("."),
+            Expr::Identifier(name) => self.name_text(name),
+            Expr::QualifiedName(parts) => match parts.split_first() {
+                Some((first, rest)) if !rest.is_empty() => {
+                    format!("{}.{}", self.name_text(first), rest.join("."))
+                }
+                _ => parts.join("."),
+            },
             Expr::List(items) => {
-                let items: Vec<_> = items.iter().map(|i| self.expr_inline(i)).collect();
+                let items: Vec<_> = items.iter().map(|i| self.expr_text(i)).collect();
                 format!("[{}]", items.join(", "))
             }
             Expr::Object(fields) => {
                 let fields: Vec<_> = fields
                     .iter()
-                    .map(|(k, v)| format!("{}: {}", k, self.expr_inline(v)))
+                    .map(|(k, v)| format!("{}: {}", k, self.expr_text(v)))
                     .collect();
                 format!("{{ {} }}", fields.join(", "))
             }
             Expr::Binary { op, left, right } => {
                 format!(
                     "{} {} {}",
-                    self.expr_inline(left),
+                    self.expr_text(left),
                     self.op_str(op),
-                    self.expr_inline(right)
+                    self.expr_text(right)
                 )
             }
             Expr::Unary { op, expr } => {
-                format!("{}{}", self.unary_op_str(op), self.expr_inline(expr))
+                format!("{}{}", self.unary_op_str(op), self.expr_text(expr))
             }
             Expr::Ternary {
                 condition,
@@ -114,20 +187,38 @@ impl DumpVisitor {
             } => {
                 format!(
                     "{} ? {} : {}",
-                    self.expr_inline(condition),
-                    self.expr_inline(then_expr),
-                    self.expr_inline(else_expr)
+                    self.expr_text(condition),
+                    self.expr_text(then_expr),
+                    self.expr_text(else_expr)
                 )
             }
-            Expr::FieldAccess { base, field } => {
-                format!("{}.{}", self.expr_inline(base), field)
+            Expr::FieldAccess { base, field, .. } => {
+                format!("{}.{}", self.expr_text(base), field)
             }
             Expr::OptionalChain { base, field } => {
-                format!("{}?.{}", self.expr_inline(base), field)
+                format!("{}?.{}", self.expr_text(base), field)
             }
             Expr::Call { callee, args } => {
-                let args: Vec<_> = args.iter().map(|a| self.expr_inline(a)).collect();
-                format!("{}({})", self.expr_inline(callee), args.join(", "))
+                let args: Vec<_> = args.iter().map(|a| self.expr_text(a)).collect();
+                format!("{}({})", self.expr_text(callee), args.join(", "))
+            }
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                format!("{}{}{}", self.expr_text(start), op, self.expr_text(end))
+            }
+            Expr::Lambda { params, body, .. } => {
+                let params: Vec<_> = params
+                    .iter()
+                    .map(|p| match &p.type_expr {
+                        Some(ty) => format!("{}: {}", p.name, ty),
+                        None => p.name.clone(),
+                    })
+                    .collect();
+                format!("({}) -> {}", params.join(", "), self.expr_text(body))
             }
             Expr::StringTemplate(elems) => {
                 let parts: Vec<_> = elems
@@ -135,7 +226,7 @@ impl DumpVisitor {
                     .map(|e| match e {
                         TemplateElement::Text(t) => t.clone(),
                         TemplateElement::Interpolation(expr) => {
-                            format!("${{{}}}", self.expr_inline(expr))
+                            format!("${{{}}}", self.expr_text(expr))
                         }
                     })
                     .collect();
@@ -144,6 +235,14 @@ impl DumpVisitor {
         }
     }
 
+    // Helper to format a name with its annotation in the current statement
+    fn name_text(&self, name: &str) -> String {
+        match self.options.names.get(&(self.context, name.to_string())) {
+            Some(annotation) => format!("{} /* {} */", name, annotation),
+            None => name.to_string(),
+        }
+    }
+
     fn op_str(&self, op: &BinaryOp) -> &'static str {
         match op {
             BinaryOp::Add => "+",
@@ -254,7 +353,7 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_import(&mut self, import: &Import) {
-        self.write(&format!("IMPORT {}", import.path));
+        self.write_node(&format!("IMPORT {}", import.path), import.span);
     }
 
     // =========================================================================
@@ -262,6 +361,7 @@ impl Visitor for DumpVisitor {
     // =========================================================================
 
     fn visit_top_level_decl(&mut self, decl: &TopLevelDecl) {
+        self.context = Span::default();
         match decl {
             TopLevelDecl::Blueprint(bp) => self.visit_blueprint(bp),
             TopLevelDecl::Backend(be) => self.visit_backend(be),
@@ -284,7 +384,10 @@ impl Visitor for DumpVisitor {
                 .collect();
             format!("({})", p.join(", "))
         };
-        self.write(&format!("BLUEPRINT {}{}", blueprint.name, params));
+        self.write_node(
+            &format!("BLUEPRINT {}{}", blueprint.name, params),
+            blueprint.span,
+        );
         self.indent();
 
         for stmt in &blueprint.body {
@@ -305,7 +408,17 @@ impl Visitor for DumpVisitor {
                 .collect();
             format!("({})", p.join(", "))
         };
-        self.write(&format!("BACKEND {}{}", backend.name, params));
+        let mut modifier = String::new();
+        if backend.undoable {
+            modifier.push_str("UNDOABLE ");
+        }
+        if backend.lifetime != BackendLifetime::Fragment {
+            modifier.push_str(&format!("{} ", backend.lifetime.as_str().to_uppercase()));
+        }
+        self.write_node(
+            &format!("{}BACKEND {}{}", modifier, backend.name, params),
+            backend.span,
+        );
         self.indent();
 
         for member in &backend.members {
@@ -316,7 +429,7 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_contract(&mut self, contract: &Contract) {
-        self.write(&format!("CONTRACT {}", contract.name));
+        self.write_node(&format!("CONTRACT {}", contract.name), contract.span);
         self.indent();
 
         for method in &contract.methods {
@@ -327,7 +440,7 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_scheme(&mut self, scheme: &Scheme) {
-        self.write(&format!("SCHEME {}", scheme.name));
+        self.write_node(&format!("SCHEME {}", scheme.name), scheme.span);
         self.indent();
 
         for member in &scheme.members {
@@ -338,12 +451,35 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_enum(&mut self, enum_decl: &Enum) {
-        let variants = enum_decl.variants.join(", ");
-        self.write(&format!("ENUM {} {{ {} }}", enum_decl.name, variants));
+        let variants: Vec<_> = enum_decl
+            .variants
+            .iter()
+            .map(|variant| {
+                let instructions: Vec<_> = enum_decl
+                    .instructions
+                    .iter()
+                    .filter(|(v, _)| v == variant)
+                    .map(|(_, i)| i.clone())
+                    .collect();
+                let payload = enum_decl.payload(variant);
+                let payload = if payload.is_empty() {
+                    String::new()
+                } else {
+                    let types: Vec<_> = payload.iter().map(ToString::to_string).collect();
+                    format!("({})", types.join(", "))
+                };
+                format!("{}{}{}", variant, payload, self.format_instructions(&instructions))
+            })
+            .collect();
+        let variants = variants.join(", ");
+        self.write_node(
+            &format!("ENUM {} {{ {} }}", enum_decl.name, variants),
+            enum_decl.span,
+        );
     }
 
     fn visit_theme(&mut self, theme: &Theme) {
-        self.write(&format!("THEME {}", theme.name));
+        self.write_node(&format!("THEME {}", theme.name), theme.span);
         self.indent();
 
         for member in &theme.members {
@@ -359,10 +495,10 @@ impl Visitor for DumpVisitor {
             .as_ref()
             .map(|c| format!(" WITH {}", c))
             .unwrap_or_default();
-        self.write(&format!(
-            "ARENA {} FOR {}{}",
-            arena.name, arena.scheme_name, contract
-        ));
+        self.write_node(
+            &format!("ARENA {} FOR {}{}", arena.name, arena.scheme_name, contract),
+            arena.span,
+        );
     }
 
     // =========================================================================
@@ -371,9 +507,12 @@ impl Visitor for DumpVisitor {
 
     fn visit_blueprint_stmt(&mut self, stmt: &BlueprintStmt) {
         match stmt {
-            BlueprintStmt::With(name) => {
-                self.write(&format!("WITH {}", name));
-            }
+            BlueprintStmt::With(with) => match &with.name {
+                Some(name) => {
+                    self.write_node(&format!("WITH {}: {}", name, with.backend), with.span)
+                }
+                None => self.write_node(&format!("WITH {}", with.backend), with.span),
+            },
             BlueprintStmt::LocalDecl(decl) => {
                 self.visit_local_decl(decl);
             }
@@ -389,6 +528,28 @@ impl Visitor for DumpVisitor {
             BlueprintStmt::EventHandler(handler) => {
                 self.visit_event_handler(handler);
             }
+            BlueprintStmt::Shortcut(shortcut) => {
+                self.visit_shortcut(shortcut);
+            }
+            BlueprintStmt::Provide(provide) => {
+                self.context = provide.span;
+                self.write_node(
+                    &format!(
+                        "PROVIDE {} = {}",
+                        provide.name,
+                        self.expr_inline(&provide.value)
+                    ),
+                    provide.span,
+                )
+            }
+            BlueprintStmt::Consume(consume) => self.write_node(
+                &format!(
+                    "CONSUME {} TYPE {}",
+                    consume.name,
+                    self.type_inline(&consume.type_expr)
+                ),
+                consume.span,
+            ),
             BlueprintStmt::Layout(layout) => {
                 self.visit_layout_stmt(layout);
             }
@@ -427,7 +588,11 @@ impl Visitor for DumpVisitor {
                 .as_ref()
                 .map(|s| format!("{} ", self.format_layout_size(s)))
                 .unwrap_or_default();
-            let cells: Vec<_> = row.cells.iter().map(|c| self.format_layout_cell(c)).collect();
+            let cells: Vec<_> = row
+                .cells
+                .iter()
+                .map(|c| self.format_layout_cell(c))
+                .collect();
             self.write(&format!("ROW {}| {} |", size, cells.join(" | ")));
         }
 
@@ -435,15 +600,20 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_local_decl(&mut self, decl: &LocalDecl) {
-        self.write(&format!(
-            "LOCAL {} TYPE {} INIT {}",
-            decl.name,
-            self.type_inline(&decl.type_expr),
-            self.expr_inline(&decl.init)
-        ));
+        self.context = decl.span;
+        self.write_node(
+            &format!(
+                "LOCAL {} TYPE {} INIT {}",
+                decl.name,
+                self.type_inline(&decl.type_expr),
+                self.expr_inline(&decl.init)
+            ),
+            decl.span,
+        );
     }
 
     fn visit_fragment_creation(&mut self, frag: &FragmentCreation) {
+        self.context = frag.span;
         let name = if frag.name.is_empty() {
             "BLOCK".to_string()
         } else {
@@ -457,13 +627,14 @@ impl Visitor for DumpVisitor {
             format!(" ARGS({})", a.join(", "))
         };
 
-        self.write(&format!("{}{}", name, args));
+        self.write_node(&format!("{}{}", name, args), frag.span);
         self.indent();
 
         if let Some(body) = &frag.body {
             self.visit_fragment_body(body);
         }
 
+        self.context = frag.span;
         for item in &frag.postfix {
             self.visit_postfix_item(item);
         }
@@ -544,7 +715,7 @@ impl Visitor for DumpVisitor {
             }
             ControlStmt::Repeat {
                 iterable,
-                item_name,
+                item,
                 key_expr,
                 body,
             } => {
@@ -554,7 +725,7 @@ impl Visitor for DumpVisitor {
                     .unwrap_or_default();
                 self.write(&format!(
                     "REPEAT {} ON {}{}",
-                    item_name,
+                    item,
                     self.expr_inline(iterable),
                     key
                 ));
@@ -568,6 +739,7 @@ impl Visitor for DumpVisitor {
                 discriminant,
                 branches,
                 else_branch,
+                ..
             } => {
                 let on = discriminant
                     .as_ref()
@@ -586,11 +758,52 @@ impl Visitor for DumpVisitor {
                 }
                 self.dedent();
             }
+            ControlStmt::OnError {
+                body,
+                error_name,
+                fallback,
+                ..
+            } => {
+                self.write("ON_ERROR");
+                self.indent();
+                for stmt in body {
+                    self.visit_blueprint_stmt(stmt);
+                }
+                self.dedent();
+                if let Some(fallback) = fallback {
+                    match error_name {
+                        Some(name) => self.write(&format!("FALLBACK {}", name)),
+                        None => self.write("FALLBACK"),
+                    }
+                    self.indent();
+                    for stmt in fallback {
+                        self.visit_blueprint_stmt(stmt);
+                    }
+                    self.dedent();
+                }
+            }
+            ControlStmt::Loading { body, .. } => {
+                self.write("LOADING");
+                self.indent();
+                for stmt in body {
+                    self.visit_blueprint_stmt(stmt);
+                }
+                self.dedent();
+            }
         }
     }
 
     fn visit_select_branch(&mut self, branch: &SelectBranch) {
-        self.write(&format!("CASE {}", self.expr_inline(&branch.condition)));
+        let payload = if branch.payload.is_empty() {
+            String::new()
+        } else {
+            let parts: Vec<_> = branch.payload.iter().map(ToString::to_string).collect();
+            format!("({})", parts.join(", "))
+        };
+        self.write_node(
+            &format!("CASE {}{}", self.expr_inline(&branch.condition), payload),
+            branch.span,
+        );
         self.indent();
         self.visit_blueprint_stmt(&branch.body);
         self.dedent();
@@ -639,6 +852,7 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_instruction(&mut self, instr: &Instruction) {
+        self.context = instr.span;
         if instr.params.is_empty() {
             self.write(&format!("INSTR {}", instr.name));
         } else {
@@ -664,7 +878,7 @@ impl Visitor for DumpVisitor {
                 format!("({}{})", p.name, t)
             })
             .unwrap_or_default();
-        self.write(&format!("ON {}{}", handler.event_name, param));
+        self.write_node(&format!("ON {}{}", handler.event_name, param), handler.span);
         self.indent();
 
         for stmt in &handler.body {
@@ -674,6 +888,18 @@ impl Visitor for DumpVisitor {
         self.dedent();
     }
 
+    fn visit_shortcut(&mut self, shortcut: &Shortcut) {
+        self.context = shortcut.span;
+        self.write_node(&format!("SHORTCUT \"{}\"", shortcut.chord), shortcut.span);
+        self.indent();
+
+        for stmt in &shortcut.body {
+            self.visit_handler_stmt(stmt);
+        }
+
+        self.dedent();
+    }
+
     fn visit_event_param(&mut self, _param: &EventParam) {
         // Handled inline in visit_event_handler
     }
@@ -712,27 +938,43 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_field(&mut self, field: &Field) {
+        self.context = field.span;
         let init = field
             .init
             .as_ref()
             .map(|e| format!(" INIT {}", self.expr_inline(e)))
             .unwrap_or_default();
-        self.write(&format!(
-            "FIELD {} TYPE {}{}",
-            field.name,
-            self.type_inline(&field.type_expr),
-            init
-        ));
+        self.write_node(
+            &format!(
+                "{}FIELD {} TYPE {}{}",
+                if field.undoable { "UNDOABLE " } else { "" },
+                field.name,
+                self.type_inline(&field.type_expr),
+                init
+            ),
+            field.span,
+        );
     }
 
     fn visit_method(&mut self, method: &Method) {
+        self.context = method.span;
         let params: Vec<_> = method.params.iter().map(|p| self.format_param(p)).collect();
-        self.write(&format!(
-            "METHOD {}({}) RETURN {}",
-            method.name,
-            params.join(", "),
-            self.type_inline(&method.return_type)
-        ));
+        let body = method
+            .body
+            .as_ref()
+            .map(|e| format!(" BODY {}", self.expr_inline(e)))
+            .unwrap_or_default();
+        self.write_node(
+            &format!(
+                "{}METHOD {}({}) RETURN {}{}",
+                Self::policy_prefix(method.policy),
+                method.name,
+                params.join(", "),
+                self.type_inline(&method.return_type),
+                body
+            ),
+            method.span,
+        );
     }
 
     fn visit_command(&mut self, command: &Command) {
@@ -741,7 +983,15 @@ impl Visitor for DumpVisitor {
             .iter()
             .map(|p| self.format_param(p))
             .collect();
-        self.write(&format!("COMMAND {}({})", command.name, params.join(", ")));
+        self.write_node(
+            &format!(
+                "{}COMMAND {}({})",
+                Self::policy_prefix(command.policy),
+                command.name,
+                params.join(", ")
+            ),
+            command.span,
+        );
     }
 
     // =========================================================================
@@ -755,12 +1005,16 @@ impl Visitor for DumpVisitor {
             .as_ref()
             .map(|t| format!(" RETURN {}", self.type_inline(t)))
             .unwrap_or_default();
-        self.write(&format!(
-            "METHOD {}({}){}",
-            method.name,
-            params.join(", "),
-            ret
-        ));
+        self.write_node(
+            &format!(
+                "METHOD {}({}){}{}",
+                method.name,
+                params.join(", "),
+                ret,
+                self.format_instructions(&method.instructions)
+            ),
+            method.span,
+        );
     }
 
     // =========================================================================
@@ -775,46 +1029,33 @@ impl Visitor for DumpVisitor {
     }
 
     fn visit_scheme_field(&mut self, field: &SchemeField) {
-        let instrs = if field.instructions.is_empty() {
-            String::new()
-        } else {
-            let i: Vec<_> = field
-                .instructions
-                .iter()
-                .map(|i| {
-                    if i.params.is_empty() {
-                        i.name.clone()
-                    } else {
-                        let params: Vec<_> = i
-                            .params
-                            .iter()
-                            .map(|(k, v)| format!("{}: {}", k, self.expr_inline(v)))
-                            .collect();
-                        format!("{}({})", i.name, params.join(", "))
-                    }
-                })
-                .collect();
-            format!(" [{}]", i.join(", "))
-        };
-        self.write(&format!(
-            "FIELD {} TYPE {}{}",
-            field.name,
-            self.type_inline(&field.type_expr),
-            instrs
-        ));
+        let instrs = self.format_instructions(&field.instructions);
+        self.write_node(
+            &format!(
+                "FIELD {} TYPE {}{}",
+                field.name,
+                self.type_inline(&field.type_expr),
+                instrs
+            ),
+            field.span,
+        );
     }
 
     fn visit_virtual_field(&mut self, field: &VirtualField) {
-        self.write(&format!(
-            "VIRTUAL {} TYPE {} = {}",
-            field.name,
-            self.type_inline(&field.type_expr),
-            self.expr_inline(&field.expr)
-        ));
+        self.context = field.span;
+        self.write_node(
+            &format!(
+                "VIRTUAL {} TYPE {} = {}",
+                field.name,
+                self.type_inline(&field.type_expr),
+                self.expr_inline(&field.expr)
+            ),
+            field.span,
+        );
     }
 
     fn visit_field_instruction(&mut self, _instr: &FieldInstruction) {
-        // Handled inline in visit_scheme_field
+        // Handled inline in visit_scheme_field and visit_contract_method
     }
 
     // =========================================================================
@@ -845,13 +1086,16 @@ impl Visitor for DumpVisitor {
             .as_ref()
             .map(|e| format!(" = {}", self.expr_inline(e)))
             .unwrap_or_default();
-        self.write(&format!(
-            "{}FIELD {} TYPE {}{}",
-            asset,
-            field.name,
-            self.type_inline(&field.type_expr),
-            init
-        ));
+        self.write_node(
+            &format!(
+                "{}FIELD {} TYPE {}{}",
+                asset,
+                field.name,
+                self.type_inline(&field.type_expr),
+                init
+            ),
+            field.span,
+        );
     }
 
     fn visit_instruction_set(&mut self, set: &InstructionSet) {
@@ -914,13 +1158,47 @@ impl Visitor for DumpVisitor {
 }
 
 impl DumpVisitor {
+    fn policy_prefix(policy: Option<CommandPolicy>) -> String {
+        policy
+            .map(|p| format!("{} ", p.as_str().to_uppercase()))
+            .unwrap_or_default()
+    }
+
+    /// Field instructions as ` [name(key: value), ...]`; empty if there are none
+    fn format_instructions(&self, instructions: &[FieldInstruction]) -> String {
+        if instructions.is_empty() {
+            return String::new();
+        }
+        let i: Vec<_> = instructions
+            .iter()
+            .map(|i| {
+                if i.params.is_empty() {
+                    i.name.clone()
+                } else {
+                    let params: Vec<_> = i
+                        .params
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", k, self.expr_inline(v)))
+                        .collect();
+                    format!("{}({})", i.name, params.join(", "))
+                }
+            })
+            .collect();
+        format!(" [{}]", i.join(", "))
+    }
+
     fn format_param(&self, param: &Parameter) -> String {
         let default = param
             .default
             .as_ref()
             .map(|d| format!(" = {}", self.expr_inline(d)))
             .unwrap_or_default();
-        format!("{}: {}{}", param.name, self.type_inline(&param.type_expr), default)
+        format!(
+            "{}: {}{}",
+            param.name,
+            self.type_inline(&param.type_expr),
+            default
+        )
     }
 
     fn format_arg(&self, arg: &Arg) -> String {
@@ -941,7 +1219,12 @@ mod tests {
         let file = File {
             module: "test".to_string(),
             source_path: None,
+            file: Default::default(),
+            edition: None,
+            features: Default::default(),
             imports: vec![],
+            comments: vec![],
+            outline: Default::default(),
             declarations: vec![],
         };
 
@@ -954,16 +1237,26 @@ mod tests {
         let file = File {
             module: "test".to_string(),
             source_path: None,
+            file: Default::default(),
+            edition: None,
+            features: Default::default(),
             imports: vec![],
+            comments: vec![],
+            outline: Default::default(),
             declarations: vec![TopLevelDecl::Enum(Enum {
                 name: "Status".to_string(),
                 variants: vec!["Active".to_string(), "Inactive".to_string()],
+                instructions: vec![],
+                payloads: vec![(
+                    "Inactive".to_string(),
+                    vec![TypeExpr::Named("String".to_string())],
+                )],
                 span: Default::default(),
             })],
         };
 
         let output = DumpVisitor::dump(&file);
-        assert!(output.contains("ENUM Status { Active, Inactive }"));
+        assert!(output.contains("ENUM Status { Active, Inactive(String) }"));
     }
 
     #[test]
@@ -971,18 +1264,25 @@ mod tests {
         let file = File {
             module: "test".to_string(),
             source_path: None,
+            file: Default::default(),
+            edition: None,
+            features: Default::default(),
             imports: vec![],
+            comments: vec![],
+            outline: Default::default(),
             declarations: vec![TopLevelDecl::Backend(Backend {
                 name: "Counter".to_string(),
+                lifetime: BackendLifetime::Fragment,
+                undoable: false,
                 params: vec![],
-                members: vec![
-                    BackendMember::Field(Field {
-                        name: "count".to_string(),
-                        type_expr: TypeExpr::Named("i32".to_string()),
-                        init: Some(Expr::Int(0)),
-                        span: Default::default(),
-                    }),
-                ],
+                members: vec![BackendMember::Field(Field {
+                    name: "count".to_string(),
+                    type_expr: TypeExpr::Named("i32".to_string()),
+                    init: Some(Expr::Int(0)),
+                    undoable: false,
+                    span: Default::default(),
+                })],
+                doc: None,
                 span: Default::default(),
             })],
         };
@@ -990,4 +1290,52 @@ mod tests {
         let output = DumpVisitor::dump(&file);
         assert!(output.contains("FIELD count TYPE i32 INIT 0"));
     }
+
+    #[test]
+    fn test_dump_options() {
+        let source = r#"
+module test
+
+backend Counter {
+    count : i32 = 0
+    label : String = "a rather long label for the counter shown to users"
+    command reset()
+}
+"#;
+        let file = crate::parse_file(source).file.expect("parse failed");
+
+        let folded = DumpOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            DumpVisitor::dump_with(&file, folded),
+            "FILE module=test\n    BACKEND Counter\n        … (3 nodes)\n"
+        );
+
+        let collapsed = DumpOptions {
+            collapse_exprs: true,
+            ..Default::default()
+        };
+        let output = DumpVisitor::dump_with(&file, collapsed);
+        assert!(output.contains("INIT \"a rather long label for the counter sh…\n"));
+
+        let Some(TopLevelDecl::Backend(backend)) = file.declarations.first() else {
+            panic!("expected a backend");
+        };
+        let BackendMember::Field(count) = &backend.members[0] else {
+            panic!("expected a field");
+        };
+        let annotated = DumpOptions {
+            spans: true,
+            types: HashMap::from([(count.span, "i32".to_string())]),
+            ..Default::default()
+        };
+        let output = DumpVisitor::dump_with(&file, annotated);
+        let expected = format!(
+            "FIELD count TYPE i32 INIT 0 -> i32 @{}..{}",
+            count.span.start, count.span.end
+        );
+        assert!(output.contains(&expected), "{}", output);
+    }
 }
@@ -6,13 +6,18 @@
 //
 // Use qualified imports (e.g., `ast::File`, `ast::Expr`) for clarity.
 
+pub mod diff;
 pub mod dump;
 pub mod visitor;
 
-pub use dump::DumpVisitor;
+pub use diff::{diff_files, AstChange, ChangeKind};
+pub use dump::{DumpOptions, DumpVisitor};
 pub use visitor::Visitor;
 
-use crate::source::Span;
+pub use crate::lexer::{Comment, CommentKind};
+
+use crate::edition::{Edition, Features};
+use crate::source::{FileId, Span};
 use serde::{Deserialize, Serialize};
 
 /// A Frel source file
@@ -22,8 +27,37 @@ pub struct File {
     pub module: String,
     /// The source file path (for diagnostics)
     pub source_path: Option<String>,
+    /// File of a multi-file module that the spans in this file point into
+    #[serde(skip)]
+    pub file: FileId,
+    /// Edition declared by an `edition` line before the module declaration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+    /// Experimental features enabled when the file was parsed, for later passes
+    #[serde(skip)]
+    pub features: Features,
     pub imports: Vec<Import>,
     pub declarations: Vec<TopLevelDecl>,
+    /// Comments in source order, for tools that reprint the source
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Where blocks and statements start, for tools that reprint the source
+    #[serde(skip)]
+    pub outline: Outline,
+}
+
+/// Block structure of a file as the parser found it
+///
+/// Most statements and members carry no span, so tools laying out the
+/// source by the tree's structure find their starts here.
+#[derive(Debug, Clone, Default)]
+pub struct Outline {
+    /// Spans from `{` to `}` of the bodies holding statements or members
+    pub blocks: Vec<Span>,
+    /// Start offsets of declarations, statements and members
+    pub items: Vec<u32>,
+    /// Start offsets of the `..` of fragment postfix items
+    pub postfix: Vec<u32>,
 }
 
 /// Import statement
@@ -54,12 +88,43 @@ pub enum TopLevelDecl {
     Arena(Arena),
 }
 
+impl TopLevelDecl {
+    /// Name of the declared item
+    pub fn name(&self) -> &str {
+        match self {
+            TopLevelDecl::Blueprint(bp) => &bp.name,
+            TopLevelDecl::Backend(be) => &be.name,
+            TopLevelDecl::Contract(ct) => &ct.name,
+            TopLevelDecl::Scheme(sc) => &sc.name,
+            TopLevelDecl::Enum(en) => &en.name,
+            TopLevelDecl::Theme(th) => &th.name,
+            TopLevelDecl::Arena(ar) => &ar.name,
+        }
+    }
+
+    /// Span of the declaration
+    pub fn span(&self) -> Span {
+        match self {
+            TopLevelDecl::Blueprint(bp) => bp.span,
+            TopLevelDecl::Backend(be) => be.span,
+            TopLevelDecl::Contract(ct) => ct.span,
+            TopLevelDecl::Scheme(sc) => sc.span,
+            TopLevelDecl::Enum(en) => en.span,
+            TopLevelDecl::Theme(th) => th.span,
+            TopLevelDecl::Arena(ar) => ar.span,
+        }
+    }
+}
+
 /// Blueprint declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blueprint {
     pub name: String,
     pub params: Vec<Parameter>,
     pub body: Vec<BlueprintStmt>,
+    /// Doc comment before the declaration (`///` lines or `/** */`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     pub span: Span,
 }
 
@@ -67,12 +132,18 @@ pub struct Blueprint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BlueprintStmt {
-    With(String),
+    With(WithClause),
     LocalDecl(LocalDecl),
     FragmentCreation(FragmentCreation),
     Control(ControlStmt),
     Instruction(InstructionExpr),
     EventHandler(EventHandler),
+    /// Keyboard shortcut: `shortcut "Ctrl+S" { save() }`
+    Shortcut(Shortcut),
+    /// Context value for the fragments below: `provide theme = dark`
+    Provide(Provide),
+    /// Context value of the nearest provider above: `consume theme: Theme`
+    Consume(Consume),
     /// Layout grid statement
     Layout(LayoutStmt),
     /// Slot binding (at slot: { ... }) - used with layout statements
@@ -81,6 +152,16 @@ pub enum BlueprintStmt {
     ContentExpr(Expr),
 }
 
+/// Backend used by a blueprint: `with Backend`, `with param` or `with name: Backend`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithClause {
+    /// Slot name when a parent may inject the instance (`with counter: Counter`)
+    pub name: Option<String>,
+    /// Backend name, or a backend-typed parameter when `name` is `None`
+    pub backend: String,
+    pub span: Span,
+}
+
 /// Local declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalDecl {
@@ -97,6 +178,7 @@ pub struct FragmentCreation {
     pub args: Vec<Arg>,
     pub body: Option<FragmentBody>,
     pub postfix: Vec<PostfixItem>,
+    pub span: Span,
 }
 
 /// Postfix item (instruction or event handler)
@@ -229,7 +311,8 @@ pub enum ControlStmt {
     },
     Repeat {
         iterable: Expr,
-        item_name: String,
+        /// The names each item is bound to: `item` or `(key, value)`
+        item: Pattern,
         key_expr: Option<Expr>,
         body: Vec<BlueprintStmt>,
     },
@@ -237,14 +320,68 @@ pub enum ControlStmt {
         discriminant: Option<Expr>,
         branches: Vec<SelectBranch>,
         else_branch: Option<Box<BlueprintStmt>>,
+        span: Span,
     },
+    /// Error boundary: `on_error { body } fallback { error -> body }`
+    OnError {
+        body: Vec<BlueprintStmt>,
+        /// Name the error message is bound to in the fallback
+        error_name: Option<String>,
+        /// `None` when the fallback is missing, which semantic analysis reports
+        fallback: Option<Vec<BlueprintStmt>>,
+        span: Span,
+    },
+    /// Loading placeholder: `loading { body }`, shown while a command invoked
+    /// by the statements next to it is in flight
+    Loading { body: Vec<BlueprintStmt>, span: Span },
 }
 
 /// Select branch
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SelectBranch {
     pub condition: Expr,
+    /// Patterns of the matched variant's payload: `Ok(value) => ...`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub payload: Vec<Pattern>,
     pub body: Box<BlueprintStmt>,
+    /// Span of the branch, from the condition to the end of its body
+    pub span: Span,
+}
+
+/// Pattern binding the parts of a value to names
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    /// `item`, binds the whole value
+    Binding(String),
+    /// `_`, ignores the value
+    Wildcard,
+    /// `(key, value)`, the key and value of a map entry
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Names the pattern binds, in order
+    pub fn bindings(&self) -> Vec<&str> {
+        match self {
+            Pattern::Binding(name) => vec![name.as_str()],
+            Pattern::Wildcard => Vec::new(),
+            Pattern::Tuple(parts) => parts.iter().flat_map(Pattern::bindings).collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Binding(name) => write!(f, "{}", name),
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Tuple(parts) => {
+                let parts: Vec<_> = parts.iter().map(ToString::to_string).collect();
+                write!(f, "({})", parts.join(", "))
+            }
+        }
+    }
 }
 
 /// Simple instruction with name and parameters
@@ -286,6 +423,35 @@ pub struct EventHandler {
     pub event_name: String,
     pub param: Option<EventParam>,
     pub body: Vec<HandlerStmt>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+/// Keyboard shortcut of a blueprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shortcut {
+    /// Key chord as written, e.g. `Ctrl+S`
+    pub chord: String,
+    /// Span of the chord's string literal
+    pub chord_span: Span,
+    pub body: Vec<HandlerStmt>,
+    pub span: Span,
+}
+
+/// Value a blueprint provides to the fragments it creates, however deeply nested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provide {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// Value a blueprint reads from the nearest blueprint above providing `name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Consume {
+    pub name: String,
+    pub type_expr: TypeExpr,
+    pub span: Span,
 }
 
 /// Event parameter
@@ -307,11 +473,57 @@ pub enum HandlerStmt {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Backend {
     pub name: String,
+    #[serde(default)]
+    pub lifetime: BackendLifetime,
+    /// All fields are recorded in an undo history: `undoable backend Editor`
+    #[serde(default)]
+    pub undoable: bool,
     pub params: Vec<Parameter>,
     pub members: Vec<BackendMember>,
+    /// Doc comment before the declaration (`///` lines or `/** */`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     pub span: Span,
 }
 
+impl Backend {
+    /// Fields recorded in the undo history
+    pub fn undoable_fields(&self) -> impl Iterator<Item = &Field> {
+        self.members.iter().filter_map(move |member| match member {
+            BackendMember::Field(field) if self.undoable || field.undoable => Some(field),
+            _ => None,
+        })
+    }
+
+    /// Whether the backend keeps an undo history and gets `undo`/`redo` commands
+    pub fn has_undo(&self) -> bool {
+        self.undoable_fields().next().is_some()
+    }
+}
+
+/// How long a backend instance lives, set by a modifier: `singleton backend Session`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendLifetime {
+    /// One instance per fragment using the backend (the default)
+    #[default]
+    Fragment,
+    /// One instance per screen (root fragment), shared by all its fragments
+    Screen,
+    /// One instance for the whole application
+    Singleton,
+}
+
+impl BackendLifetime {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendLifetime::Fragment => "fragment",
+            BackendLifetime::Screen => "screen",
+            BackendLifetime::Singleton => "singleton",
+        }
+    }
+}
+
 /// Backend member
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -328,6 +540,9 @@ pub struct Field {
     pub name: String,
     pub type_expr: TypeExpr,
     pub init: Option<Expr>,
+    /// Recorded in the backend's undo history: `undoable text : String = ""`
+    #[serde(default)]
+    pub undoable: bool,
     pub span: Span,
 }
 
@@ -337,6 +552,13 @@ pub struct Method {
     pub name: String,
     pub params: Vec<Parameter>,
     pub return_type: TypeExpr,
+    /// Expression computing the result: `method total() : i32 = price * quantity`;
+    /// without one, the method is implemented in the host language
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Expr>,
+    /// Only valid on commands, kept so the checker can report it
+    #[serde(default)]
+    pub policy: Option<CommandPolicy>,
     pub span: Span,
 }
 
@@ -345,9 +567,37 @@ pub struct Method {
 pub struct Command {
     pub name: String,
     pub params: Vec<Parameter>,
+    /// Type of the command's result: `command load(id : UUID) : User`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_type: Option<TypeExpr>,
+    /// Concurrency policy; without one, invocations run concurrently
+    #[serde(default)]
+    pub policy: Option<CommandPolicy>,
     pub span: Span,
 }
 
+/// What happens when a command is invoked while a previous invocation is in flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandPolicy {
+    /// Start the new invocation and discard the result of the previous one
+    Latest,
+    /// Run the new invocation after the previous ones finish
+    Queue,
+    /// Ignore the new invocation
+    Drop,
+}
+
+impl CommandPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandPolicy::Latest => "latest",
+            CommandPolicy::Queue => "queue",
+            CommandPolicy::Drop => "drop",
+        }
+    }
+}
+
 /// Contract declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contract {
@@ -362,14 +612,40 @@ pub struct ContractMethod {
     pub name: String,
     pub params: Vec<Parameter>,
     pub return_type: Option<TypeExpr>,
+    /// Transport bindings: `.. get { "/users/{id}" }`, `.. name { "getUser" }`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instructions: Vec<FieldInstruction>,
     pub span: Span,
 }
 
+/// Instructions binding a contract method to an HTTP route: `.. get { "/users/{id}" }`
+pub const HTTP_METHODS: &[&str] = &["get", "post", "put", "patch", "delete"];
+
+impl ContractMethod {
+    /// String value of a binding like `.. name { "getUser" }`
+    pub fn binding(&self, instruction: &str) -> Option<&str> {
+        self.instructions
+            .iter()
+            .find(|i| i.name == instruction)?
+            .string_value()
+    }
+
+    /// HTTP method and path the method is bound to, e.g. `("get", "/users/{id}")`
+    pub fn route(&self) -> Option<(&str, &str)> {
+        HTTP_METHODS
+            .iter()
+            .find_map(|method| Some((*method, self.binding(method)?)))
+    }
+}
+
 /// Scheme declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scheme {
     pub name: String,
     pub members: Vec<SchemeMember>,
+    /// Doc comment before the declaration (`///` lines or `/** */`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     pub span: Span,
 }
 
@@ -399,21 +675,60 @@ pub struct VirtualField {
     pub span: Span,
 }
 
-/// Field instruction (used in schemes)
+/// Field instruction (used in schemes, contract methods and enum variants)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldInstruction {
     pub name: String,
     pub params: Vec<(String, Expr)>,
 }
 
+impl FieldInstruction {
+    /// Value of an instruction with a single string literal, like `.. label { "Draft" }`
+    pub fn string_value(&self) -> Option<&str> {
+        match self.params.as_slice() {
+            [(_, Expr::String(value))] => Some(value),
+            _ => None,
+        }
+    }
+}
+
 /// Enum declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Enum {
     pub name: String,
     pub variants: Vec<String>,
+    /// Instructions of variants as `(variant, instruction)` pairs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instructions: Vec<(String, FieldInstruction)>,
+    /// Types of the data variants carry as `(variant, types)` pairs:
+    /// `Err(i32)`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub payloads: Vec<(String, Vec<TypeExpr>)>,
     pub span: Span,
 }
 
+/// Instructions an enum variant accepts
+pub const ENUM_VARIANT_INSTRUCTIONS: &[&str] = &["label"];
+
+impl Enum {
+    /// Display name of a variant given by `.. label { "In progress" }`
+    pub fn label(&self, variant: &str) -> Option<&str> {
+        self.instructions
+            .iter()
+            .find(|(v, i)| v == variant && i.name == "label")?
+            .1
+            .string_value()
+    }
+
+    /// Types of the data a variant carries, empty for a variant without data
+    pub fn payload(&self, variant: &str) -> &[TypeExpr] {
+        self.payloads
+            .iter()
+            .find(|(v, _)| v == variant)
+            .map_or(&[], |(_, types)| types)
+    }
+}
+
 /// Theme declaration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
@@ -471,6 +786,7 @@ pub struct Parameter {
     pub name: String,
     pub type_expr: TypeExpr,
     pub default: Option<Expr>,
+    pub span: Span,
 }
 
 /// Argument
@@ -495,6 +811,37 @@ pub enum TypeExpr {
     Set(Box<TypeExpr>),
     Map(Box<TypeExpr>, Box<TypeExpr>),
     Tree(Box<TypeExpr>),
+    /// `(T, U) -> R`, the type of callbacks
+    Function {
+        params: Vec<TypeExpr>,
+        ret: Box<TypeExpr>,
+    },
+}
+
+impl std::fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeExpr::Named(name) => write!(f, "{}", name),
+            TypeExpr::Nullable(inner) => write!(f, "{}?", inner),
+            TypeExpr::Ref(inner) => write!(f, "ref {}", inner),
+            TypeExpr::Draft(inner) => write!(f, "draft {}", inner),
+            TypeExpr::Asset(inner) => write!(f, "asset {}", inner),
+            TypeExpr::Blueprint(params) if params.is_empty() => write!(f, "Blueprint"),
+            TypeExpr::Blueprint(params) => {
+                let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+                write!(f, "Blueprint<{}>", params.join(", "))
+            }
+            TypeExpr::Accessor(inner) => write!(f, "Accessor<{}>", inner),
+            TypeExpr::List(elem) => write!(f, "List<{}>", elem),
+            TypeExpr::Set(elem) => write!(f, "Set<{}>", elem),
+            TypeExpr::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+            TypeExpr::Tree(elem) => write!(f, "Tree<{}>", elem),
+            TypeExpr::Function { params, ret } => {
+                let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+                write!(f, "({}) -> {}", params.join(", "), ret)
+            }
+        }
+    }
 }
 
 /// Expression
@@ -536,6 +883,9 @@ pub enum Expr {
     FieldAccess {
         base: Box<Expr>,
         field: String,
+        /// Span of the whole access, from the start of `base` to the field
+        #[serde(default)]
+        span: Span,
     },
     OptionalChain {
         base: Box<Expr>,
@@ -547,6 +897,29 @@ pub enum Expr {
         callee: Box<Expr>,
         args: Vec<Expr>,
     },
+
+    /// `start..end` or `start..=end`, the integers from `start` up to `end`
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+
+    /// `(x) -> expr`, an inline callback
+    Lambda {
+        params: Vec<LambdaParam>,
+        body: Box<Expr>,
+        /// Span of the whole lambda; its parameters are in a scope created
+        /// at this span
+        span: Span,
+    },
+}
+
+/// Parameter of a lambda, with its type when written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LambdaParam {
+    pub name: String,
+    pub type_expr: Option<TypeExpr>,
 }
 
 /// Template element for string interpolation
@@ -558,7 +931,7 @@ pub enum TemplateElement {
 }
 
 /// Binary operators
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BinaryOp {
     // Arithmetic
@@ -586,7 +959,7 @@ pub enum BinaryOp {
 }
 
 /// Unary operators
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum UnaryOp {
     Not,
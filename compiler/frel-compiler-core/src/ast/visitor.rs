@@ -95,6 +95,9 @@ pub trait Visitor {
     /// Visit an event handler
     fn visit_event_handler(&mut self, handler: &EventHandler) -> Self::Result;
 
+    /// Visit a keyboard shortcut
+    fn visit_shortcut(&mut self, shortcut: &Shortcut) -> Self::Result;
+
     /// Visit an event parameter
     fn visit_event_param(&mut self, param: &EventParam) -> Self::Result;
 
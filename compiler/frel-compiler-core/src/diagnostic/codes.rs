@@ -11,6 +11,7 @@
 // - E05xx: Reactive errors (ownership/reactivity)
 // - E06xx: Backend errors (composition)
 // - E07xx: Blueprint errors (compilation)
+// - E08xx: Localization errors (translation catalogs)
 
 use super::Severity;
 use serde::{Deserialize, Serialize};
@@ -33,6 +34,8 @@ pub enum Category {
     Backend,
     /// E07xx - Blueprint compilation errors
     Blueprint,
+    /// E08xx - Localization errors
+    Localization,
 }
 
 impl Category {
@@ -45,6 +48,7 @@ impl Category {
             Category::Reactive => "reactive",
             Category::Backend => "backend",
             Category::Blueprint => "blueprint",
+            Category::Localization => "localization",
         }
     }
 
@@ -58,6 +62,7 @@ impl Category {
             Category::Reactive => "E05",
             Category::Backend => "E06",
             Category::Blueprint => "E07",
+            Category::Localization => "E08",
         }
     }
 }
@@ -199,6 +204,30 @@ pub const E0207: ErrorCode = ErrorCode::new(
     "Every Frel file must start with a module declaration.",
 );
 
+pub const E0208: ErrorCode = ErrorCode::new(
+    "E0208",
+    "edition_required",
+    Category::Parse,
+    Severity::Error,
+    "The construct was added in a newer edition than the one the file is compiled with.",
+);
+
+pub const E0209: ErrorCode = ErrorCode::new(
+    "E0209",
+    "unknown_edition",
+    Category::Parse,
+    Severity::Error,
+    "The edition declared by the file is not one the compiler knows.",
+);
+
+pub const E0210: ErrorCode = ErrorCode::new(
+    "E0210",
+    "feature_disabled",
+    Category::Parse,
+    Severity::Error,
+    "The construct is experimental and its feature gate is not enabled.",
+);
+
 // ============================================================================
 // Resolution Errors (E03xx)
 // ============================================================================
@@ -251,6 +280,46 @@ pub const E0306: ErrorCode = ErrorCode::new(
     "The qualified name could not be resolved. Check that all path segments exist.",
 );
 
+pub const E0307: ErrorCode = ErrorCode::new(
+    "E0307",
+    "unused_local",
+    Category::Resolution,
+    Severity::Warning,
+    "The local declaration is never read. Remove it, or prefix its name with `_`.",
+);
+
+pub const E0308: ErrorCode = ErrorCode::new(
+    "E0308",
+    "unused_parameter",
+    Category::Resolution,
+    Severity::Warning,
+    "The blueprint parameter is never read by the blueprint body.",
+);
+
+pub const E0309: ErrorCode = ErrorCode::new(
+    "E0309",
+    "unused_declaration",
+    Category::Resolution,
+    Severity::Warning,
+    "The backend or scheme is not used by its module and no other module imports it.",
+);
+
+pub const E0310: ErrorCode = ErrorCode::new(
+    "E0310",
+    "unused_import",
+    Category::Resolution,
+    Severity::Warning,
+    "Nothing the import brings into scope is used by the file.",
+);
+
+pub const E0311: ErrorCode = ErrorCode::new(
+    "E0311",
+    "hardcoded_secret",
+    Category::Resolution,
+    Severity::Warning,
+    "A string literal looks like a credential: an API key, a token or a private key. Source code is shipped to every client.",
+);
+
 // ============================================================================
 // Type Errors (E04xx)
 // ============================================================================
@@ -311,6 +380,78 @@ pub const E0407: ErrorCode = ErrorCode::new(
     "Parameter and backend field have the same name but different types. Types must match when merging.",
 );
 
+pub const E0408: ErrorCode = ErrorCode::new(
+    "E0408",
+    "invalid_variant_instruction",
+    Category::Type,
+    Severity::Error,
+    "Enum variants accept a display name instruction, such as `.. label { \"In progress\" }`.",
+);
+
+pub const E0409: ErrorCode = ErrorCode::new(
+    "E0409",
+    "invalid_builtin_argument",
+    Category::Type,
+    Severity::Error,
+    "A constant argument of a built-in function has a value it does not accept, such as a currency code that is not three uppercase letters.",
+);
+
+pub const E0410: ErrorCode = ErrorCode::new(
+    "E0410",
+    "non_exhaustive_select",
+    Category::Type,
+    Severity::Error,
+    "A select on an enum must match every variant, or have an `else` branch.",
+);
+
+pub const E0411: ErrorCode = ErrorCode::new(
+    "E0411",
+    "unreachable_select_branch",
+    Category::Type,
+    Severity::Warning,
+    "The branch matches an enum variant an earlier branch of the select already matches, so it never runs.",
+);
+
+pub const E0412: ErrorCode = ErrorCode::new(
+    "E0412",
+    "recursive_scheme",
+    Category::Type,
+    Severity::Error,
+    "A scheme cannot contain itself by value, directly or through the schemes of its fields; make a field of the cycle nullable or a ref, or hold it in a collection.",
+);
+
+pub const E0413: ErrorCode = ErrorCode::new(
+    "E0413",
+    "invalid_constant",
+    Category::Type,
+    Severity::Error,
+    "An expression computed at compile time divides by zero or overflows a 64-bit integer.",
+);
+
+pub const E0414: ErrorCode = ErrorCode::new(
+    "E0414",
+    "integer_out_of_range",
+    Category::Type,
+    Severity::Error,
+    "An integer literal is outside the range of the integer type expected for it.",
+);
+
+pub const E0415: ErrorCode = ErrorCode::new(
+    "E0415",
+    "lambda_arity_mismatch",
+    Category::Type,
+    Severity::Error,
+    "A lambda takes a different number of parameters than the function type expected for it.",
+);
+
+pub const E0416: ErrorCode = ErrorCode::new(
+    "E0416",
+    "pattern_mismatch",
+    Category::Type,
+    Severity::Error,
+    "A pattern does not fit the value it destructures, such as `(key, value)` on the items of a list, or payload patterns for a variant that carries no data.",
+);
+
 // ============================================================================
 // Reactive Errors (E05xx)
 // ============================================================================
@@ -364,7 +505,7 @@ pub const E0602: ErrorCode = ErrorCode::new(
     "circular_include",
     Category::Backend,
     Severity::Error,
-    "Backends cannot include each other in a cycle.",
+    "Backends and themes cannot include themselves, directly or through the ones they include.",
 );
 
 pub const E0603: ErrorCode = ErrorCode::new(
@@ -383,6 +524,62 @@ pub const E0604: ErrorCode = ErrorCode::new(
     "Methods are pure and should be called from expressions, not event handlers.",
 );
 
+pub const E0605: ErrorCode = ErrorCode::new(
+    "E0605",
+    "shared_item_state",
+    Category::Backend,
+    Severity::Warning,
+    "A singleton backend is shared by every item of a repeat, so per-item state written to it is overwritten.",
+);
+
+pub const E0606: ErrorCode = ErrorCode::new(
+    "E0606",
+    "policy_on_method",
+    Category::Backend,
+    Severity::Error,
+    "Concurrency policies apply to async commands; methods are pure and run synchronously.",
+);
+
+pub const E0607: ErrorCode = ErrorCode::new(
+    "E0607",
+    "unsnapshotable_undo_field",
+    Category::Backend,
+    Severity::Error,
+    "Undoable fields are copied into the undo history, so their values must be plain data.",
+);
+
+pub const E0608: ErrorCode = ErrorCode::new(
+    "E0608",
+    "undo_name_conflict",
+    Category::Backend,
+    Severity::Error,
+    "Backends with undoable fields get generated `undo` and `redo` commands.",
+);
+
+pub const E0609: ErrorCode = ErrorCode::new(
+    "E0609",
+    "invalid_contract_binding",
+    Category::Backend,
+    Severity::Error,
+    "Contract methods bind to a transport with `name` and HTTP route instructions, such as `.. get { \"/items/{id}\" }`.",
+);
+
+pub const E0610: ErrorCode = ErrorCode::new(
+    "E0610",
+    "contract_method_missing",
+    Category::Backend,
+    Severity::Error,
+    "A backend including a contract must declare each of its methods, and the contract of an arena must have a method loading its scheme.",
+);
+
+pub const E0611: ErrorCode = ErrorCode::new(
+    "E0611",
+    "contract_method_mismatch",
+    Category::Backend,
+    Severity::Error,
+    "A backend method implementing a contract method must take the same parameter types and return the same type.",
+);
+
 // ============================================================================
 // Blueprint Errors (E07xx)
 // ============================================================================
@@ -427,6 +624,130 @@ pub const E0705: ErrorCode = ErrorCode::new(
     "The value is not a valid keyword for this instruction parameter.",
 );
 
+pub const E0706: ErrorCode = ErrorCode::new(
+    "E0706",
+    "invalid_virtualize",
+    Category::Blueprint,
+    Severity::Error,
+    "`virtualize` needs a container with a `repeat` and either an item height or an estimate.",
+);
+
+pub const E0707: ErrorCode = ErrorCode::new(
+    "E0707",
+    "invalid_shortcut",
+    Category::Blueprint,
+    Severity::Error,
+    "The key chord of a shortcut is malformed, or the shortcut is not declared at blueprint scope.",
+);
+
+pub const E0708: ErrorCode = ErrorCode::new(
+    "E0708",
+    "duplicate_shortcut",
+    Category::Blueprint,
+    Severity::Warning,
+    "Another shortcut in the project uses the same key chord; only one of them runs.",
+);
+
+pub const E0709: ErrorCode = ErrorCode::new(
+    "E0709",
+    "duplicate_autofocus",
+    Category::Blueprint,
+    Severity::Error,
+    "More than one fragment of a blueprint instance has `autofocus`.",
+);
+
+pub const E0710: ErrorCode = ErrorCode::new(
+    "E0710",
+    "missing_provider",
+    Category::Blueprint,
+    Severity::Error,
+    "A consumed context value is provided by no blueprint.",
+);
+
+pub const E0711: ErrorCode = ErrorCode::new(
+    "E0711",
+    "invalid_context",
+    Category::Blueprint,
+    Severity::Error,
+    "A context value is declared inside a fragment or provided twice by a blueprint.",
+);
+
+pub const E0712: ErrorCode = ErrorCode::new(
+    "E0712",
+    "missing_fallback",
+    Category::Blueprint,
+    Severity::Error,
+    "An `on_error` boundary has no `fallback`, or its fallback is empty.",
+);
+
+pub const E0713: ErrorCode = ErrorCode::new(
+    "E0713",
+    "loading_without_commands",
+    Category::Blueprint,
+    Severity::Error,
+    "A `loading` placeholder is next to no command invocation it could wait for.",
+);
+
+pub const E0714: ErrorCode = ErrorCode::new(
+    "E0714",
+    "portal_layout_relative",
+    Category::Blueprint,
+    Severity::Error,
+    "A fragment rendered in an overlay with `portal` is placed in its parent's layout.",
+);
+
+pub const E0715: ErrorCode = ErrorCode::new(
+    "E0715",
+    "unsafe_interpolation",
+    Category::Blueprint,
+    Severity::Warning,
+    "A string template interpolating values is used as a URL as is. Wrap it in `url()` to check its scheme, or in `raw()` to trust it.",
+);
+
+pub const E0716: ErrorCode = ErrorCode::new(
+    "E0716",
+    "unknown_event",
+    Category::Blueprint,
+    Severity::Warning,
+    "The event handler is for an event no fragment sends, so it never runs.",
+);
+
+pub const E0717: ErrorCode = ErrorCode::new(
+    "E0717",
+    "event_parameter_mismatch",
+    Category::Blueprint,
+    Severity::Error,
+    "The parameter of an event handler does not match the value the event passes.",
+);
+
+// ============================================================================
+// Localization Errors (E08xx)
+// ============================================================================
+
+pub const E0801: ErrorCode = ErrorCode::new(
+    "E0801",
+    "missing_translation",
+    Category::Localization,
+    Severity::Warning,
+    "A localized message has no entry in a translation file.",
+);
+
+pub const E0802: ErrorCode = ErrorCode::new(
+    "E0802",
+    "extra_translation",
+    Category::Localization,
+    Severity::Warning,
+    "A translation file has an entry that does not match any localized message.",
+);
+
+pub const E0803: ErrorCode = ErrorCode::new(
+    "E0803",
+    "placeholder_mismatch",
+    Category::Localization,
+    Severity::Error,
+    "A translation uses different {placeholders} than the reference translation.",
+);
+
 // ============================================================================
 // Error code lookup
 // ============================================================================
@@ -448,6 +769,9 @@ pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
         "E0205" => Some(&E0205),
         "E0206" => Some(&E0206),
         "E0207" => Some(&E0207),
+        "E0208" => Some(&E0208),
+        "E0209" => Some(&E0209),
+        "E0210" => Some(&E0210),
         // Resolution
         "E0301" => Some(&E0301),
         "E0302" => Some(&E0302),
@@ -455,6 +779,11 @@ pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
         "E0304" => Some(&E0304),
         "E0305" => Some(&E0305),
         "E0306" => Some(&E0306),
+        "E0307" => Some(&E0307),
+        "E0308" => Some(&E0308),
+        "E0309" => Some(&E0309),
+        "E0310" => Some(&E0310),
+        "E0311" => Some(&E0311),
         // Type
         "E0401" => Some(&E0401),
         "E0402" => Some(&E0402),
@@ -463,6 +792,15 @@ pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
         "E0405" => Some(&E0405),
         "E0406" => Some(&E0406),
         "E0407" => Some(&E0407),
+        "E0408" => Some(&E0408),
+        "E0409" => Some(&E0409),
+        "E0410" => Some(&E0410),
+        "E0411" => Some(&E0411),
+        "E0412" => Some(&E0412),
+        "E0413" => Some(&E0413),
+        "E0414" => Some(&E0414),
+        "E0415" => Some(&E0415),
+        "E0416" => Some(&E0416),
         // Reactive
         "E0501" => Some(&E0501),
         "E0502" => Some(&E0502),
@@ -473,37 +811,72 @@ pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
         "E0602" => Some(&E0602),
         "E0603" => Some(&E0603),
         "E0604" => Some(&E0604),
+        "E0605" => Some(&E0605),
+        "E0606" => Some(&E0606),
+        "E0607" => Some(&E0607),
+        "E0608" => Some(&E0608),
+        "E0609" => Some(&E0609),
+        "E0610" => Some(&E0610),
+        "E0611" => Some(&E0611),
         // Blueprint
         "E0701" => Some(&E0701),
         "E0702" => Some(&E0702),
         "E0703" => Some(&E0703),
         "E0704" => Some(&E0704),
         "E0705" => Some(&E0705),
+        "E0706" => Some(&E0706),
+        "E0707" => Some(&E0707),
+        "E0708" => Some(&E0708),
+        "E0709" => Some(&E0709),
+        "E0710" => Some(&E0710),
+        "E0711" => Some(&E0711),
+        "E0712" => Some(&E0712),
+        "E0713" => Some(&E0713),
+        "E0714" => Some(&E0714),
+        "E0715" => Some(&E0715),
+        "E0716" => Some(&E0716),
+        "E0717" => Some(&E0717),
+        // Localization
+        "E0801" => Some(&E0801),
+        "E0802" => Some(&E0802),
+        "E0803" => Some(&E0803),
         _ => None,
     }
 }
 
 /// Get all error codes for a category
 pub fn by_category(category: Category) -> Vec<&'static ErrorCode> {
-    let all = [
-        // Syntax
-        &E0101, &E0102, &E0103, &E0104, &E0105,
-        // Parse
-        &E0201, &E0202, &E0203, &E0204, &E0205, &E0206, &E0207,
-        // Resolution
-        &E0301, &E0302, &E0303, &E0304, &E0305, &E0306,
-        // Type
-        &E0401, &E0402, &E0403, &E0404, &E0405, &E0406, &E0407,
-        // Reactive
-        &E0501, &E0502, &E0503, &E0504,
-        // Backend
-        &E0601, &E0602, &E0603, &E0604,
-        // Blueprint
-        &E0701, &E0702, &E0703, &E0704, &E0705,
-    ];
-    all.into_iter().filter(|c| c.category == category).collect()
+    ALL.iter().copied().filter(|c| c.category == category).collect()
 }
 
+/// Look up an error code by its name, e.g. `duplicate_shortcut`
+pub fn lookup_name(name: &str) -> Option<&'static ErrorCode> {
+    ALL.iter().copied().find(|c| c.name == name)
+}
+
+/// All error codes, by category and number
+static ALL: &[&ErrorCode] = &[
+    // Syntax
+    &E0101, &E0102, &E0103, &E0104, &E0105,
+    // Parse
+    &E0201, &E0202, &E0203, &E0204, &E0205, &E0206, &E0207, &E0208, &E0209, &E0210,
+    // Resolution
+    &E0301, &E0302, &E0303, &E0304, &E0305, &E0306, &E0307, &E0308, &E0309, &E0310,
+    &E0311,
+    // Type
+    &E0401, &E0402, &E0403, &E0404, &E0405, &E0406, &E0407, &E0408, &E0409, &E0410, &E0411,
+    &E0412, &E0413, &E0414, &E0415, &E0416,
+    // Reactive
+    &E0501, &E0502, &E0503, &E0504,
+    // Backend
+    &E0601, &E0602, &E0603, &E0604, &E0605, &E0606, &E0607, &E0608, &E0609, &E0610, &E0611,
+    // Blueprint
+    &E0701, &E0702, &E0703, &E0704, &E0705, &E0706, &E0707, &E0708, &E0709,
+    &E0710, &E0711, &E0712, &E0713, &E0714, &E0715, &E0716, &E0717,
+    // Localization
+    &E0801, &E0802, &E0803,
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
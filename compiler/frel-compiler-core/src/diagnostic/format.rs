@@ -4,8 +4,8 @@
 // It is separate from the core diagnostic structures to allow for different
 // output formats (terminal, JSON, LSP protocol, etc.)
 
-use super::{Diagnostic, Severity};
-use crate::source::{LineCol, LineIndex};
+use super::{Diagnostic, Severity, Suggestion};
+use crate::source::{LineCol, LineIndex, Span};
 
 /// Format a single diagnostic for terminal output
 pub fn format_diagnostic(
@@ -14,98 +14,7 @@ pub fn format_diagnostic(
     filename: &str,
     index: &LineIndex,
 ) -> String {
-    let mut output = String::new();
-    let LineCol { line, col } = index.line_col(diag.span.start);
-
-    // Header: error[E0001]: message
-    let code_str = diag
-        .code
-        .as_ref()
-        .map(|c| format!("[{}]", c))
-        .unwrap_or_default();
-    output.push_str(&format!(
-        "{}{}: {}\n",
-        diag.severity.as_str(),
-        code_str,
-        diag.message
-    ));
-
-    // Location: --> file:line:col
-    output.push_str(&format!("  --> {}:{}:{}\n", filename, line, col));
-
-    // Source context
-    if let Some(line_text) = index.line_text((line - 1) as usize, source) {
-        let line_num_width = line.to_string().len();
-
-        // Empty line with bar
-        output.push_str(&format!("{:width$} |\n", "", width = line_num_width));
-
-        // Line with source
-        output.push_str(&format!("{} | {}\n", line, line_text));
-
-        // Underline
-        let line_start = index.line_start((line - 1) as usize).unwrap_or(0);
-        let underline_start = (diag.span.start - line_start) as usize;
-        let underline_len = ((diag.span.end - diag.span.start) as usize).max(1);
-
-        output.push_str(&format!(
-            "{:width$} | {:>start$}{}\n",
-            "",
-            "",
-            "^".repeat(underline_len),
-            width = line_num_width,
-            start = underline_start
-        ));
-    }
-
-    // Additional labels
-    for label in &diag.labels {
-        let LineCol {
-            line: label_line,
-            col: label_col,
-        } = index.line_col(label.span.start);
-        if !label.message.is_empty() {
-            output.push_str(&format!(
-                "  = note: {} (at {}:{})\n",
-                label.message, label_line, label_col
-            ));
-        }
-    }
-
-    // Related information (cross-file references)
-    for related in &diag.related {
-        let related_location = if let Some(ref file) = related.file {
-            let LineCol {
-                line: rel_line,
-                col: rel_col,
-            } = index.line_col(related.span.start);
-            format!("{}:{}:{}", file, rel_line, rel_col)
-        } else {
-            let LineCol {
-                line: rel_line,
-                col: rel_col,
-            } = index.line_col(related.span.start);
-            format!("{}:{}", rel_line, rel_col)
-        };
-        output.push_str(&format!(
-            "  = note: {} (at {})\n",
-            related.message, related_location
-        ));
-    }
-
-    // Help text
-    if let Some(help) = &diag.help {
-        output.push_str(&format!("  = help: {}\n", help));
-    }
-
-    // Suggestions
-    for suggestion in &diag.suggestions {
-        if !suggestion.message.is_empty() {
-            output.push_str(&format!("  = suggestion: {}\n", suggestion.message));
-        }
-    }
-
-    output
+    render_diagnostic(diag, source, filename, index, &RenderOptions::plain())
 }
 
 /// Format multiple diagnostics for terminal output
@@ -125,6 +34,21 @@ pub fn format_diagnostics(
     output
 }
 
+/// Format a diagnostic as one line: `file:line:col: error[E0001]: message`
+pub fn format_short(diag: &Diagnostic, filename: &str, index: &LineIndex) -> String {
+    let LineCol { line, col } = index.line_col(diag.span.start);
+    let code = diag.code.as_ref().map(|c| format!("[{}]", c)).unwrap_or_default();
+    format!(
+        "{}:{}:{}: {}{}: {}",
+        filename,
+        line,
+        col,
+        diag.severity.as_str(),
+        code,
+        diag.message
+    )
+}
+
 /// Format a summary line for diagnostics
 pub fn format_summary(errors: usize, warnings: usize) -> String {
     match (errors, warnings) {
@@ -150,6 +74,7 @@ pub mod colors {
     pub const BLUE: &str = "\x1b[34m";
     pub const CYAN: &str = "\x1b[36m";
     pub const GREEN: &str = "\x1b[32m";
+    pub const MAGENTA: &str = "\x1b[35m";
 
     /// Get the color for a severity level
     pub fn for_severity(severity: Severity) -> &'static str {
@@ -162,6 +87,201 @@ pub mod colors {
     }
 }
 
+/// Color theme for terminal diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// Bright colors for dark backgrounds
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds
+    Light,
+    /// Bold text only, no colors
+    Mono,
+}
+
+impl Theme {
+    /// Get the escape sequences used by this theme
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Dark => Palette {
+                error: colors::RED,
+                warning: colors::YELLOW,
+                info: colors::BLUE,
+                hint: colors::CYAN,
+                gutter: colors::BLUE,
+                note: colors::CYAN,
+                help: colors::GREEN,
+                emphasis: colors::BOLD,
+                reset: colors::RESET,
+            },
+            Theme::Light => Palette {
+                error: colors::RED,
+                warning: colors::MAGENTA,
+                info: colors::BLUE,
+                hint: colors::BLUE,
+                gutter: colors::BLUE,
+                note: colors::BLUE,
+                help: colors::GREEN,
+                emphasis: colors::BOLD,
+                reset: colors::RESET,
+            },
+            Theme::Mono => Palette {
+                error: colors::BOLD,
+                warning: colors::BOLD,
+                info: colors::BOLD,
+                hint: colors::BOLD,
+                gutter: "",
+                note: "",
+                help: "",
+                emphasis: colors::BOLD,
+                reset: colors::RESET,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            "mono" => Ok(Theme::Mono),
+            _ => Err(format!("unknown theme '{}' (expected dark, light or mono)", s)),
+        }
+    }
+}
+
+/// Escape sequences for each part of a rendered diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub info: &'static str,
+    pub hint: &'static str,
+    /// Line numbers, bars and the `-->` arrow
+    pub gutter: &'static str,
+    /// `= note:` prefixes
+    pub note: &'static str,
+    /// `= help:` and `= suggestion:` prefixes
+    pub help: &'static str,
+    /// Header message
+    pub emphasis: &'static str,
+    pub reset: &'static str,
+}
+
+impl Palette {
+    /// Palette that emits no escape sequences at all
+    pub fn plain() -> Self {
+        Self {
+            error: "",
+            warning: "",
+            info: "",
+            hint: "",
+            gutter: "",
+            note: "",
+            help: "",
+            emphasis: "",
+            reset: "",
+        }
+    }
+
+    /// Get the color for a severity level
+    pub fn for_severity(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Info => self.info,
+            Severity::Hint => self.hint,
+        }
+    }
+}
+
+/// When to emit ANSI colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Decide whether to color output written to a stream
+    ///
+    /// An explicit `always` wins over `NO_COLOR`, following
+    /// <https://no-color.org>.
+    pub fn should_color(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !no_color_requested(),
+        }
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color choice '{}' (expected auto, always or never)", s)),
+        }
+    }
+}
+
+/// Check if the `NO_COLOR` environment variable asks for uncolored output
+pub fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Options controlling how diagnostics are rendered for a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    /// Color theme (ignored unless `color` is set)
+    pub theme: Theme,
+    /// Whether to emit ANSI escape sequences
+    pub color: bool,
+    /// Terminal width used to wrap help text; `None` disables wrapping
+    pub width: Option<usize>,
+}
+
+impl RenderOptions {
+    /// Uncolored output without wrapping
+    pub fn plain() -> Self {
+        Self::default()
+    }
+
+    /// Colored output using the given theme
+    pub fn colored(theme: Theme) -> Self {
+        Self {
+            theme,
+            color: true,
+            width: None,
+        }
+    }
+
+    /// Wrap help text to the given terminal width
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Get the palette to render with
+    pub fn palette(&self) -> Palette {
+        if self.color {
+            self.theme.palette()
+        } else {
+            Palette::plain()
+        }
+    }
+}
+
 /// Format a diagnostic with ANSI colors
 pub fn format_diagnostic_colored(
     diag: &Diagnostic,
@@ -169,9 +289,47 @@ pub fn format_diagnostic_colored(
     filename: &str,
     index: &LineIndex,
 ) -> String {
+    render_diagnostic(diag, source, filename, index, &RenderOptions::colored(Theme::Dark))
+}
+
+/// Format a diagnostic according to the given render options
+pub fn render_diagnostic(
+    diag: &Diagnostic,
+    source: &str,
+    filename: &str,
+    index: &LineIndex,
+    options: &RenderOptions,
+) -> String {
+    render_diagnostic_with_sources(diag, source, filename, index, options, &|_: &str| None)
+}
+
+/// Supplies the contents of other files referenced by related information
+pub trait SourceLoader {
+    /// Load a file's source, or `None` if it is unavailable
+    fn load(&self, file: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String>> SourceLoader for F {
+    fn load(&self, file: &str) -> Option<String> {
+        self(file)
+    }
+}
+
+/// Format a diagnostic, showing snippets of related information in other files
+///
+/// Related entries whose file cannot be loaded fall back to a plain note.
+pub fn render_diagnostic_with_sources(
+    diag: &Diagnostic,
+    source: &str,
+    filename: &str,
+    index: &LineIndex,
+    options: &RenderOptions,
+    loader: &dyn SourceLoader,
+) -> String {
+    let palette = options.palette();
     let mut output = String::new();
     let LineCol { line, col } = index.line_col(diag.span.start);
-    let severity_color = colors::for_severity(diag.severity);
+    let severity_color = palette.for_severity(diag.severity);
 
     // Header: error[E0001]: message
     let code_str = diag
@@ -181,120 +339,444 @@ pub fn format_diagnostic_colored(
         .unwrap_or_default();
     output.push_str(&format!(
         "{}{}{}{}{}: {}{}{}\n",
-        colors::BOLD,
+        palette.emphasis,
         severity_color,
         diag.severity.as_str(),
         code_str,
-        colors::RESET,
-        colors::BOLD,
+        palette.reset,
+        palette.emphasis,
         diag.message,
-        colors::RESET,
+        palette.reset,
     ));
 
     // Location: --> file:line:col
     output.push_str(&format!(
         "  {}-->{} {}:{}:{}\n",
-        colors::BLUE, colors::RESET, filename, line, col
+        palette.gutter, palette.reset, filename, line, col
     ));
 
-    // Source context
-    if let Some(line_text) = index.line_text((line - 1) as usize, source) {
-        let line_num_width = line.to_string().len();
+    // Source context: the primary span and the labels in this file
+    let primary_message = diag
+        .labels
+        .iter()
+        .find(|label| label.span == diag.span)
+        .map(|label| label.message.as_str())
+        .unwrap_or_default();
+    let mut annotations = vec![Annotation {
+        span: diag.span,
+        marker: '^',
+        color: severity_color,
+        message: primary_message,
+    }];
+    annotations.extend(diag.labels.iter().filter(|label| label.span != diag.span).map(|label| {
+        Annotation {
+            span: label.span,
+            marker: '-',
+            color: palette.gutter,
+            message: &label.message,
+        }
+    }));
+    push_snippet(&mut output, options, source, index, &annotations);
 
-        // Empty line with bar
+    // Related information
+    for related in &diag.related {
+        let Some(file) = related.file.as_deref() else {
+            let LineCol {
+                line: rel_line,
+                col: rel_col,
+            } = index.line_col(related.span.start);
+            output.push_str(&format!(
+                "  {}= note:{} {} (at {}:{})\n",
+                palette.note, palette.reset, related.message, rel_line, rel_col
+            ));
+            continue;
+        };
+
+        let loaded = if file == filename { None } else { loader.load(file) };
+        let loaded_index = loaded.as_deref().map(LineIndex::new);
+        let (rel_source, rel_index) = match (&loaded, &loaded_index) {
+            (Some(text), Some(loaded_index)) => (text.as_str(), loaded_index),
+            _ if file == filename => (source, index),
+            _ => {
+                // Without the file we cannot turn the span into a position
+                output.push_str(&format!(
+                    "  {}= note:{} {} (in {})\n",
+                    palette.note, palette.reset, related.message, file
+                ));
+                continue;
+            }
+        };
+
+        let LineCol {
+            line: rel_line,
+            col: rel_col,
+        } = rel_index.line_col(related.span.start);
         output.push_str(&format!(
-            "{}{:width$} |{}\n",
-            colors::BLUE,
-            "",
-            colors::RESET,
-            width = line_num_width
+            "  {}= note:{} {}\n",
+            palette.note, palette.reset, related.message
         ));
-
-        // Line with source
         output.push_str(&format!(
-            "{}{} |{} {}\n",
-            colors::BLUE, line, colors::RESET, line_text
+            "  {}-->{} {}:{}:{}\n",
+            palette.gutter, palette.reset, file, rel_line, rel_col
         ));
+        let annotation = Annotation {
+            span: related.span,
+            marker: '-',
+            color: palette.note,
+            message: "",
+        };
+        push_snippet(&mut output, options, rel_source, rel_index, &[annotation]);
+    }
 
-        // Underline
+    // Help text
+    if let Some(help) = &diag.help {
+        push_wrapped(&mut output, "help", palette.help, palette.reset, help, options.width);
+    }
+
+    // Suggestions
+    for suggestion in &diag.suggestions {
+        if !suggestion.message.is_empty() {
+            push_wrapped(
+                &mut output,
+                "suggestion",
+                palette.help,
+                palette.reset,
+                &suggestion.message,
+                options.width,
+            );
+        }
+        push_suggestion(&mut output, options, source, index, suggestion);
+    }
+
+    output
+}
+
+/// A span to underline in a snippet
+struct Annotation<'a> {
+    span: Span,
+    marker: char,
+    color: &'a str,
+    /// Shown after the markers; empty for none
+    message: &'a str,
+}
+
+/// Push the source lines holding the annotations, each underlined with its
+/// markers and message
+///
+/// Lines are shown in source order under a shared gutter; gaps between them
+/// are elided with `...`.
+fn push_snippet(
+    output: &mut String,
+    options: &RenderOptions,
+    source: &str,
+    index: &LineIndex,
+    annotations: &[Annotation],
+) {
+    let palette = options.palette();
+    let mut by_line: Vec<(u32, &Annotation)> = annotations
+        .iter()
+        .map(|annotation| (index.line_col(annotation.span.start).line, annotation))
+        .collect();
+    by_line.sort_by_key(|(line, _)| *line);
+    let Some(&(last_line, _)) = by_line.last() else {
+        return;
+    };
+    let line_num_width = last_line.to_string().len();
+    let max_columns = options
+        .width
+        .map(|width| width.saturating_sub(line_num_width + 3))
+        .unwrap_or(MAX_SOURCE_COLUMNS)
+        .max(MIN_SOURCE_COLUMNS);
+
+    // Empty line with bar
+    output.push_str(&format!(
+        "{}{:width$} |{}\n",
+        palette.gutter,
+        "",
+        palette.reset,
+        width = line_num_width
+    ));
+
+    let mut previous: Option<u32> = None;
+    for &(line, annotation) in &by_line {
+        let Some(line_text) = index.line_text((line - 1) as usize, source) else {
+            continue;
+        };
+        let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+
+        // Byte range of the span within this line (multi-line spans stop at the line end)
         let line_start = index.line_start((line - 1) as usize).unwrap_or(0);
-        let underline_start = (diag.span.start - line_start) as usize;
-        let underline_len = ((diag.span.end - diag.span.start) as usize).max(1);
+        let span_start = (annotation.span.start - line_start) as usize;
+        let span_end = (annotation.span.end.saturating_sub(line_start) as usize).min(line_text.len());
+        let snippet = render_source_line(line_text, span_start, span_end, max_columns);
+
+        // Line with source, once for all the annotations on it
+        if previous != Some(line) {
+            if previous.is_some_and(|previous| line > previous + 1) {
+                output.push_str(&format!("{}...{}\n", palette.gutter, palette.reset));
+            }
+            output.push_str(&format!(
+                "{}{:>width$} |{} {}\n",
+                palette.gutter,
+                line,
+                palette.reset,
+                snippet.text,
+                width = line_num_width
+            ));
+        }
+        previous = Some(line);
 
+        // Underline
+        let message = if annotation.message.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", annotation.message)
+        };
         output.push_str(&format!(
-            "{}{:width$} |{} {:>start$}{}{}{}\n",
-            colors::BLUE,
+            "{}{:width$} |{} {:>start$}{}{}{}{}\n",
+            palette.gutter,
             "",
-            colors::RESET,
+            palette.reset,
             "",
-            severity_color,
-            "^".repeat(underline_len),
-            colors::RESET,
+            annotation.color,
+            annotation.marker.to_string().repeat(snippet.caret_len),
+            message,
+            palette.reset,
             width = line_num_width,
-            start = underline_start
+            start = snippet.caret_start
         ));
     }
+}
 
-    // Additional labels
-    for label in &diag.labels {
-        let LineCol {
-            line: label_line,
-            col: label_col,
-        } = index.line_col(label.span.start);
-        if !label.message.is_empty() {
-            output.push_str(&format!(
-                "  {}= note:{} {} (at {}:{})\n",
-                colors::CYAN, colors::RESET, label.message, label_line, label_col
-            ));
-        }
+/// Push the line a suggestion edits, with the edit applied and marked
+///
+/// Insertions are marked with `+`, replacements with `~`; a deletion shows
+/// the original line with the removed text marked `-`. Edits spanning lines
+/// are only described by their message.
+fn push_suggestion(
+    output: &mut String,
+    options: &RenderOptions,
+    source: &str,
+    index: &LineIndex,
+    suggestion: &Suggestion,
+) {
+    let span = suggestion.span;
+    let line = index.line_col(span.start).line;
+    if index.line_col(span.end).line != line || suggestion.replacement.contains('\n') {
+        return;
     }
+    let Some(line_text) = index.line_text((line - 1) as usize, source) else {
+        return;
+    };
+    let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+    let line_start = index.line_start((line - 1) as usize).unwrap_or(0);
+    let start = (span.start - line_start) as usize;
+    let end = ((span.end - line_start) as usize).min(line_text.len());
+    let Some((before, after)) = line_text.get(..start).zip(line_text.get(end..)) else {
+        return;
+    };
+
+    let (edited, marker, marked) = if suggestion.replacement.is_empty() {
+        (line_text.to_string(), '-', span)
+    } else {
+        let edited = format!("{}{}{}", before, suggestion.replacement, after);
+        let marker = if span.is_empty() { '+' } else { '~' };
+        let end = span.start + suggestion.replacement.len() as u32;
+        (edited, marker, Span::new(span.start, end))
+    };
+    let edited_source = format!("{}{}", &source[..line_start as usize], edited);
+    let edited_index = LineIndex::new(&edited_source);
+    let annotation = Annotation {
+        span: marked,
+        marker,
+        color: options.palette().help,
+        message: "",
+    };
+    push_snippet(output, options, &edited_source, &edited_index, &[annotation]);
+}
 
-    // Related information
-    for related in &diag.related {
-        let related_location = if let Some(ref file) = related.file {
-            let LineCol {
-                line: rel_line,
-                col: rel_col,
-            } = index.line_col(related.span.start);
-            format!("{}:{}:{}", file, rel_line, rel_col)
+/// Columns a tab advances to (the next multiple of this)
+const TAB_WIDTH: usize = 4;
+
+/// Longest source line shown when the terminal width is unknown
+const MAX_SOURCE_COLUMNS: usize = 160;
+
+/// Source lines are never clamped narrower than this
+const MIN_SOURCE_COLUMNS: usize = 40;
+
+/// Columns of context kept before the span when a long line is clamped
+const CONTEXT_COLUMNS: usize = 16;
+
+/// A source line prepared for display, with the caret position in columns
+struct SourceLine {
+    text: String,
+    caret_start: usize,
+    caret_len: usize,
+}
+
+/// Lay out a source line in display columns and place the caret under a span
+///
+/// Tabs are expanded to the next tab stop and wide characters take two
+/// columns, so the caret lines up with what the terminal shows. Lines longer
+/// than `max_columns` are clamped with `…` on the cut sides, keeping the
+/// start of the span in view.
+fn render_source_line(
+    line: &str,
+    span_start: usize,
+    span_end: usize,
+    max_columns: usize,
+) -> SourceLine {
+    // (byte offset, rendered text, display width) for each character
+    let mut cells: Vec<(usize, String, usize)> = Vec::new();
+    let mut total = 0;
+    for (offset, c) in line.char_indices() {
+        let (text, width) = if c == '\t' {
+            let width = TAB_WIDTH - total % TAB_WIDTH;
+            (" ".repeat(width), width)
         } else {
-            let LineCol {
-                line: rel_line,
-                col: rel_col,
-            } = index.line_col(related.span.start);
-            format!("{}:{}", rel_line, rel_col)
+            (c.to_string(), char_width(c))
         };
-        output.push_str(&format!(
-            "  {}= note:{} {} (at {})\n",
-            colors::CYAN, colors::RESET, related.message, related_location
-        ));
+        cells.push((offset, text, width));
+        total += width;
     }
 
-    // Help text
-    if let Some(help) = &diag.help {
-        output.push_str(&format!(
-            "  {}= help:{} {}\n",
-            colors::GREEN, colors::RESET, help
-        ));
+    let column_at = |byte: usize| -> usize {
+        cells
+            .iter()
+            .take_while(|(offset, _, _)| *offset < byte)
+            .map(|(_, _, width)| width)
+            .sum()
+    };
+    let start_col = column_at(span_start);
+    let end_col = column_at(span_end).max(start_col + 1);
+
+    if total <= max_columns {
+        return SourceLine {
+            text: cells.into_iter().map(|(_, text, _)| text).collect(),
+            caret_start: start_col,
+            caret_len: end_col - start_col,
+        };
     }
 
-    // Suggestions
-    for suggestion in &diag.suggestions {
-        if !suggestion.message.is_empty() {
-            output.push_str(&format!(
-                "  {}= suggestion:{} {}\n",
-                colors::GREEN, colors::RESET, suggestion.message
-            ));
+    // Leave room for an ellipsis on each side
+    let budget = max_columns - 2;
+    let window_start = if end_col <= budget {
+        0
+    } else {
+        start_col.saturating_sub(CONTEXT_COLUMNS).min(total - budget)
+    };
+    let window_end = (window_start + budget).min(total);
+
+    let mut text = String::new();
+    if window_start > 0 {
+        text.push('…');
+    }
+    let mut col = 0;
+    for (_, cell, width) in &cells {
+        let cell_end = col + width;
+        if col >= window_start && cell_end <= window_end {
+            text.push_str(cell);
+        } else if col < window_end && cell_end > window_start {
+            // Character straddles the window edge: keep the columns, not the glyph
+            let visible = cell_end.min(window_end) - col.max(window_start);
+            text.push_str(&" ".repeat(visible));
         }
+        col = cell_end;
+    }
+    if window_end < total {
+        text.push('…');
     }
 
-    output
+    let offset = if window_start > 0 { 1 } else { 0 };
+    SourceLine {
+        text,
+        caret_start: start_col - window_start + offset,
+        caret_len: (end_col.min(window_end).saturating_sub(start_col)).max(1),
+    }
+}
+
+/// Number of terminal columns a character occupies
+///
+/// Covers combining marks and the common East Asian wide and emoji ranges,
+/// which is enough to keep carets aligned without a Unicode width table.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Narrowest text column worth wrapping to; below this lines are left long
+const MIN_WRAP_COLUMNS: usize = 20;
+
+/// Push a `  = kind: text` line, wrapping the text to `width` columns
+///
+/// Continuation lines are indented to line up with the start of the text.
+fn push_wrapped(
+    output: &mut String,
+    kind: &str,
+    color: &str,
+    reset: &str,
+    text: &str,
+    width: Option<usize>,
+) {
+    let indent = "  = ".len() + kind.len() + ": ".len();
+    let lines = match width {
+        Some(width) if width >= indent + MIN_WRAP_COLUMNS => wrap_text(text, width - indent),
+        _ => vec![text.to_string()],
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if i == 0 {
+            output.push_str(&format!("  {}= {}:{} {}\n", color, kind, reset, line));
+        } else {
+            output.push_str(&format!("{:indent$}{}\n", "", line, indent = indent));
+        }
+    }
+}
+
+/// Greedily wrap text on whitespace so lines fit in `columns` characters
+///
+/// Words longer than a line are kept whole rather than split.
+fn wrap_text(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > columns {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::source::Span;
+    use crate::diagnostic::{Label, RelatedInfo, Suggestion};
 
     #[test]
     fn test_format_summary() {
@@ -320,4 +802,202 @@ mod tests {
         assert!(output.contains("error[E0201]: test error"));
         assert!(output.contains("--> test.frel:1:11"));
     }
+
+    #[test]
+    fn test_format_short() {
+        let source = "module app\n\nblueprint Test { }";
+        let diag = Diagnostic::error("test error", Span::new(22, 26)).with_code("E0201");
+        let index = LineIndex::new(source);
+
+        assert_eq!(
+            format_short(&diag, "test.frel", &index),
+            "test.frel:3:11: error[E0201]: test error"
+        );
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Always.should_color(false));
+        assert!(!ColorChoice::Never.should_color(true));
+        assert!(!ColorChoice::Auto.should_color(false));
+        assert_eq!("never".parse::<ColorChoice>(), Ok(ColorChoice::Never));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn test_render_themes() {
+        let source = "blueprint Test { }";
+        let diag = Diagnostic::warning("test warning", Span::new(10, 14));
+        let index = LineIndex::new(source);
+
+        let plain = render_diagnostic(&diag, source, "test.frel", &index, &RenderOptions::plain());
+        assert_eq!(plain, format_diagnostic(&diag, source, "test.frel", &index));
+        assert!(!plain.contains('\x1b'));
+
+        let light = RenderOptions::colored(Theme::Light);
+        let output = render_diagnostic(&diag, source, "test.frel", &index, &light);
+        assert!(output.contains(colors::MAGENTA));
+        assert!(!output.contains(colors::YELLOW));
+
+        let mono = RenderOptions::colored(Theme::Mono);
+        let output = render_diagnostic(&diag, source, "test.frel", &index, &mono);
+        assert!(output.contains(colors::BOLD));
+        for color in [colors::RED, colors::YELLOW, colors::BLUE, colors::CYAN, colors::GREEN] {
+            assert!(!output.contains(color));
+        }
+    }
+
+    #[test]
+    fn test_wrap_help_text() {
+        let source = "blueprint Test { }";
+        let diag = Diagnostic::error("test error", Span::new(10, 14))
+            .with_help("declare the missing field in the scheme before using it here");
+        let index = LineIndex::new(source);
+
+        let options = RenderOptions::plain().with_width(40);
+        let output = render_diagnostic(&diag, source, "test.frel", &index, &options);
+        let help: Vec<&str> = output.lines().skip_while(|l| !l.contains("= help:")).collect();
+        assert_eq!(help[0], "  = help: declare the missing field in");
+        assert_eq!(help[1], "          the scheme before using it");
+        assert_eq!(help[2], "          here");
+        assert!(help.iter().all(|line| line.len() <= 40));
+
+        // Too narrow to wrap usefully: keep the text on one line
+        let options = RenderOptions::plain().with_width(12);
+        let output = render_diagnostic(&diag, source, "test.frel", &index, &options);
+        assert!(output.contains("= help: declare the missing field in the scheme before using it here\n"));
+    }
+
+    fn caret_line(output: &str) -> &str {
+        output.lines().find(|l| l.contains('^')).unwrap()
+    }
+
+    #[test]
+    fn test_caret_after_tab() {
+        let source = "\tx: Strng";
+        let diag = Diagnostic::error("unknown type", Span::new(4, 9));
+        let index = LineIndex::new(source);
+
+        let output = format_diagnostic(&diag, source, "test.frel", &index);
+        assert!(output.contains("1 |     x: Strng\n"));
+        assert_eq!(caret_line(&output), "  |        ^^^^^");
+    }
+
+    #[test]
+    fn test_caret_after_wide_chars() {
+        let source = "label = \"日本\" + x";
+        let start = source.find('x').unwrap() as u32;
+        let diag = Diagnostic::error("unknown name", Span::new(start, start + 1));
+        let index = LineIndex::new(source);
+
+        let output = format_diagnostic(&diag, source, "test.frel", &index);
+        // The two wide characters take four columns
+        assert_eq!(caret_line(&output), "  |                  ^");
+    }
+
+    #[test]
+    fn test_multiline_span_stops_at_line_end() {
+        let source = "scheme A {\n}";
+        let diag = Diagnostic::error("bad scheme", Span::new(7, 12));
+        let index = LineIndex::new(source);
+
+        let output = format_diagnostic(&diag, source, "test.frel", &index);
+        assert_eq!(caret_line(&output), "  |        ^^^");
+    }
+
+    #[test]
+    fn test_long_line_is_clamped() {
+        let source = format!("{}oops{}", "a".repeat(300), "b".repeat(300));
+        let diag = Diagnostic::error("bad", Span::new(300, 304));
+        let index = LineIndex::new(&source);
+
+        let options = RenderOptions::plain().with_width(80);
+        let output = render_diagnostic(&diag, &source, "test.frel", &index, &options);
+        let source_line = output.lines().find(|l| l.starts_with("1 |")).unwrap();
+        assert!(source_line.starts_with("1 | …"));
+        assert!(source_line.ends_with('…'));
+        assert!(source_line.chars().count() <= 80);
+
+        // The caret still points at the span
+        let caret = caret_line(&output);
+        let column = caret.find('^').unwrap();
+        let shown: String = source_line.chars().skip(column).take(4).collect();
+        assert_eq!(shown, "oops");
+        assert_eq!(caret.trim_end().len() - column, 4);
+    }
+
+    #[test]
+    fn test_related_snippet_from_other_file() {
+        let source = "import test.data.User";
+        let other = "module test.data\n\nscheme User { }";
+        let diag = Diagnostic::error("duplicate declaration 'User'", Span::new(17, 21))
+            .with_related(RelatedInfo::in_file(Span::new(25, 29), "data.frel", "first defined here"));
+        let index = LineIndex::new(source);
+        let loader = |file: &str| (file == "data.frel").then(|| other.to_string());
+
+        let output = render_diagnostic_with_sources(
+            &diag,
+            source,
+            "app.frel",
+            &index,
+            &RenderOptions::plain(),
+            &loader,
+        );
+        assert!(output.contains("  = note: first defined here\n  --> data.frel:3:8\n"));
+        assert!(output.contains("3 | scheme User { }\n  |        ----\n"));
+    }
+
+    #[test]
+    fn test_related_missing_file_falls_back() {
+        let source = "import test.data.User";
+        let diag = Diagnostic::error("duplicate declaration 'User'", Span::new(17, 21))
+            .with_related(RelatedInfo::in_file(Span::new(25, 29), "gone.frel", "first defined here"));
+        let index = LineIndex::new(source);
+
+        let output = render_diagnostic(&diag, source, "app.frel", &index, &RenderOptions::plain());
+        assert!(output.contains("  = note: first defined here (in gone.frel)\n"));
+        assert!(!output.contains("gone.frel:"));
+    }
+
+    #[test]
+    fn test_labels_share_the_snippet() {
+        let source = "column {\n    text { \"a\" }\n    row {\n    text { \"b\" }";
+        let open = source.find("row").unwrap() as u32;
+        let diag = Diagnostic::error("unclosed block", Span::new(open + 4, open + 5))
+            .with_label(Label::new(Span::new(0, 6), "outer block"))
+            .with_label(Label::new(Span::new(open + 4, open + 5), "opened here"));
+        let index = LineIndex::new(source);
+
+        let output = format_diagnostic(&diag, source, "test.frel", &index);
+        let expected = [
+            "  |",
+            "1 | column {",
+            "  | ------ outer block",
+            "...",
+            "3 |     row {",
+            "  |         ^ opened here",
+        ];
+        assert!(output.contains(&expected.join("\n")), "{}", output);
+        assert!(!output.contains("= note"));
+    }
+
+    #[test]
+    fn test_suggestion_snippet() {
+        let source = "x: Strng = \"\"";
+        let index = LineIndex::new(source);
+        let render = |suggestion: Suggestion| {
+            let diag = Diagnostic::error("unknown type", Span::new(3, 8)).with_suggestion(suggestion);
+            format_diagnostic(&diag, source, "test.frel", &index)
+        };
+
+        let output = render(Suggestion::new(Span::new(3, 8), "String", "did you mean `String`?"));
+        assert!(output.contains("  = suggestion: did you mean `String`?\n  |\n"));
+        assert!(output.contains("1 | x: String = \"\"\n  |    ~~~~~~\n"));
+
+        let output = render(Suggestion::insert(8, "?", "make it optional"));
+        assert!(output.contains("1 | x: Strng? = \"\"\n  |         +\n"));
+
+        let output = render(Suggestion::delete(Span::new(8, 13), "remove the default"));
+        assert!(output.contains("1 | x: Strng = \"\"\n  |         -----\n"));
+    }
 }
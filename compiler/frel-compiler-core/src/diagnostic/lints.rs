@@ -0,0 +1,228 @@
+// Lint levels for Frel diagnostics
+//
+// Error codes whose default severity is not an error are lints: a project or
+// a build can report them at another level.
+//
+// - `allow` drops the diagnostics of the lint
+// - `warn` reports them as warnings
+// - `deny` reports them as errors, failing the build
+//
+// Levels are set per code or code name (`E0802`, `extra_translation`) in the
+// `[lints]` table of `frel.toml` and by the `-A`, `-W` and `-D` flags, which
+// win over it. The `warnings` group sets the level of every diagnostic that is
+// still a warning once the codes are applied: `-D warnings`, or
+// `--deny-warnings`, turns them into errors for CI builds.
+//
+// Errors cannot be lowered, so setting the level of an error code is rejected.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{codes, Diagnostics, Severity};
+
+/// Level a lint is reported at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+}
+
+/// Name of the group of all diagnostics reported as warnings
+pub const WARNINGS: &str = "warnings";
+
+/// Levels of the lints of a build, over their default severities
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "BTreeMap<String, LintLevel>")]
+pub struct LintLevels {
+    /// Levels by error code
+    codes: BTreeMap<&'static str, LintLevel>,
+    /// Level of the `warnings` group
+    warnings: Option<LintLevel>,
+}
+
+impl LintLevels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of a lint, given by code, code name or `warnings`
+    pub fn set(&mut self, lint: &str, level: LintLevel) -> Result<(), String> {
+        if lint == WARNINGS {
+            self.warnings = Some(level);
+            return Ok(());
+        }
+        let code = codes::lookup(lint)
+            .or_else(|| codes::lookup_name(lint))
+            .ok_or_else(|| format!("unknown lint `{}`", lint))?;
+        if code.default_severity == Severity::Error {
+            return Err(format!(
+                "`{}` ({}) is an error, not a lint, and cannot be set to `{}`",
+                lint,
+                code.name,
+                level.as_str()
+            ));
+        }
+        self.codes.insert(code.code, level);
+        Ok(())
+    }
+
+    /// Levels given on the command line by `-A`, `-W`, `-D` and
+    /// `--deny-warnings`, applied in that order
+    pub fn from_flags(
+        allow: &[String],
+        warn: &[String],
+        deny: &[String],
+        deny_warnings: bool,
+    ) -> Result<Self, String> {
+        let mut levels = LintLevels::new();
+        for (lints, level) in [
+            (allow, LintLevel::Allow),
+            (warn, LintLevel::Warn),
+            (deny, LintLevel::Deny),
+        ] {
+            for lint in lints {
+                levels.set(lint, level)?;
+            }
+        }
+        if deny_warnings {
+            levels.deny_warnings();
+        }
+        Ok(levels)
+    }
+
+    /// Report every warning as an error
+    pub fn deny_warnings(&mut self) {
+        self.warnings = Some(LintLevel::Deny);
+    }
+
+    /// Apply the levels of `other` over these
+    pub fn extend(&mut self, other: &LintLevels) {
+        self.codes.extend(&other.codes);
+        self.warnings = other.warnings.or(self.warnings);
+    }
+
+    /// Level set for an error code, if any
+    pub fn level(&self, code: &str) -> Option<LintLevel> {
+        self.codes.get(code).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty() && self.warnings.is_none()
+    }
+}
+
+impl TryFrom<BTreeMap<String, LintLevel>> for LintLevels {
+    type Error = String;
+
+    fn try_from(table: BTreeMap<String, LintLevel>) -> Result<Self, String> {
+        let mut levels = LintLevels::new();
+        for (lint, level) in table {
+            levels.set(&lint, level)?;
+        }
+        Ok(levels)
+    }
+}
+
+impl Diagnostics {
+    /// Report the diagnostics of lints at their levels: drop allowed ones,
+    /// then turn the remaining warnings into errors if `warnings` is denied
+    pub fn apply_lints(&mut self, levels: &LintLevels) {
+        if levels.is_empty() {
+            return;
+        }
+        self.retain_mut(|diagnostic| {
+            let level = diagnostic.code.as_deref().and_then(|code| levels.level(code));
+            match level {
+                Some(LintLevel::Allow) => return false,
+                Some(LintLevel::Warn) => diagnostic.severity = Severity::Warning,
+                Some(LintLevel::Deny) => diagnostic.severity = Severity::Error,
+                None => {}
+            }
+            if diagnostic.severity == Severity::Warning {
+                match levels.warnings {
+                    Some(LintLevel::Allow) => return false,
+                    Some(LintLevel::Deny) => diagnostic.severity = Severity::Error,
+                    Some(LintLevel::Warn) | None => {}
+                }
+            }
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::{codes, Diagnostic};
+    use crate::source::Span;
+
+    fn diagnostics() -> Diagnostics {
+        let mut diagnostics = Diagnostics::new();
+        for code in [&codes::E0802, &codes::E0708, &codes::E0605, &codes::E0301] {
+            diagnostics.add(Diagnostic::from_code(code, Span::default(), code.name));
+        }
+        diagnostics.warning("no code", Span::default());
+        diagnostics
+    }
+
+    fn severities(diagnostics: &Diagnostics) -> Vec<(String, Severity)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.message.clone(), d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let mut levels = LintLevels::new();
+        levels.set("E0802", LintLevel::Allow).unwrap();
+        levels.set("duplicate_shortcut", LintLevel::Deny).unwrap();
+        assert_eq!(levels.level("E0708"), Some(LintLevel::Deny));
+        assert_eq!(
+            levels.set("E0301", LintLevel::Allow),
+            Err("`E0301` (undefined_name) is an error, not a lint, and cannot be set to `allow`"
+                .to_string())
+        );
+        assert_eq!(levels.set("E9999", LintLevel::Warn), Err("unknown lint `E9999`".to_string()));
+
+        let mut diagnostics = diagnostics();
+        diagnostics.apply_lints(&levels);
+        assert_eq!(
+            severities(&diagnostics),
+            [
+                ("duplicate_shortcut".to_string(), Severity::Error),
+                ("shared_item_state".to_string(), Severity::Warning),
+                ("undefined_name".to_string(), Severity::Error),
+                ("no code".to_string(), Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let mut levels = LintLevels::new();
+        levels.set("shared_item_state", LintLevel::Allow).unwrap();
+        let mut overrides = LintLevels::new();
+        overrides.deny_warnings();
+        levels.extend(&overrides);
+
+        let mut diagnostics = diagnostics();
+        diagnostics.apply_lints(&levels);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(diagnostics.error_count(), 4);
+    }
+}
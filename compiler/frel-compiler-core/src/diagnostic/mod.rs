@@ -9,18 +9,31 @@
 // - Related information for cross-file references
 // - LSP-compatible tags for IDE integration
 // - JSON serialization for tooling consumption
+// - SARIF export for code scanning (see sarif.rs)
+// - Lint levels raising or lowering warnings (see lints.rs)
 // - Output-agnostic design via DiagnosticSink trait
 
 pub mod codes;
 pub mod format;
+pub mod lints;
+pub mod sarif;
 pub mod sink;
 
-use crate::source::{LineIndex, Span};
+use crate::source::{FileId, LineIndex, Span};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 pub use codes::{Category, ErrorCode};
-pub use format::{format_diagnostic, format_diagnostic_colored, format_diagnostics, format_summary};
-pub use sink::{CollectingSink, CountingSink, DiagnosticSink, NullSink, StreamingSink};
+pub use format::{
+    format_diagnostic, format_diagnostic_colored, format_diagnostics, format_short,
+    format_summary, render_diagnostic, render_diagnostic_with_sources, ColorChoice,
+    RenderOptions, SourceLoader, Theme,
+};
+pub use lints::{LintLevel, LintLevels};
+pub use sarif::{SarifBuilder, SarifLog};
+pub use sink::{
+    CollectingSink, CountingSink, DiagnosticSink, MaxErrorsSink, NullSink, StreamingSink,
+};
 
 /// Diagnostic severity level
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -102,6 +115,40 @@ impl Suggestion {
     }
 }
 
+/// The candidate closest to `name`, if it is close enough to be a typo of it
+///
+/// Used to suggest keywords and names; the allowed distance grows with the
+/// length of `name`, one edit for every three characters.
+pub fn similar_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
 /// Related information pointing to another location
 ///
 /// Used for "defined here", "previous occurrence", etc.
@@ -317,6 +364,53 @@ impl Diagnostic {
     pub fn deprecated(self) -> Self {
         self.with_tag(DiagnosticTag::Deprecated)
     }
+
+    /// Point every span of the diagnostic into `file`
+    ///
+    /// Related information naming its file by path is left as is.
+    pub fn set_file(&mut self, file: FileId) {
+        self.span = self.span.in_file(file);
+        for label in &mut self.labels {
+            label.span = label.span.in_file(file);
+        }
+        for suggestion in &mut self.suggestions {
+            suggestion.span = suggestion.span.in_file(file);
+        }
+        for related in self.related.iter_mut().filter(|related| related.file.is_none()) {
+            related.span = related.span.in_file(file);
+        }
+    }
+
+    /// Make the spans relative to the file of the primary span alone
+    ///
+    /// Labels in another file become related information naming that file by
+    /// `path`, as do related entries; suggestions there are dropped since
+    /// they can't be applied to this file.
+    fn localize(&mut self, path: &dyn Fn(FileId) -> Option<String>) {
+        let home = self.span.file;
+        self.span.file = FileId::default();
+        let mut moved = Vec::new();
+        for mut label in std::mem::take(&mut self.labels) {
+            if label.span.file == home {
+                label.span.file = FileId::default();
+                self.labels.push(label);
+            } else {
+                moved.push(RelatedInfo::new(label.span, label.message));
+            }
+        }
+        self.related.extend(moved);
+        self.suggestions.retain_mut(|suggestion| {
+            let keep = suggestion.span.file == home;
+            suggestion.span.file = FileId::default();
+            keep
+        });
+        for related in &mut self.related {
+            if related.file.is_none() && related.span.file != home {
+                related.file = path(related.span.file);
+            }
+            related.span.file = FileId::default();
+        }
+    }
 }
 
 /// Collection of diagnostics accumulated during compilation
@@ -332,6 +426,11 @@ impl Diagnostics {
         }
     }
 
+    /// Keep the diagnostics `keep` returns true for, changing them in place
+    pub fn retain_mut(&mut self, keep: impl FnMut(&mut Diagnostic) -> bool) {
+        self.diagnostics.retain_mut(keep);
+    }
+
     /// Add a diagnostic
     pub fn add(&mut self, diagnostic: Diagnostic) {
         self.diagnostics.push(diagnostic);
@@ -410,11 +509,40 @@ impl Diagnostics {
 
     /// Format diagnostics with colors for terminal output
     pub fn format_terminal_colored(&self, source: &str, filename: &str) -> String {
+        self.format_terminal_with(source, filename, &RenderOptions::colored(Theme::Dark))
+    }
+
+    /// Format diagnostics for terminal output with the given theme, color and width
+    pub fn format_terminal_with(
+        &self,
+        source: &str,
+        filename: &str,
+        options: &RenderOptions,
+    ) -> String {
+        self.format_terminal_with_sources(source, filename, options, &|_: &str| None)
+    }
+
+    /// Format diagnostics for terminal output, loading other files referenced
+    /// by related information so their snippets can be shown
+    pub fn format_terminal_with_sources(
+        &self,
+        source: &str,
+        filename: &str,
+        options: &RenderOptions,
+        loader: &dyn SourceLoader,
+    ) -> String {
         let line_index = LineIndex::new(source);
         let mut output = String::new();
 
         for diag in &self.diagnostics {
-            output.push_str(&format_diagnostic_colored(diag, source, filename, &line_index));
+            output.push_str(&render_diagnostic_with_sources(
+                diag,
+                source,
+                filename,
+                &line_index,
+                options,
+                loader,
+            ));
             output.push('\n');
         }
 
@@ -428,6 +556,65 @@ impl Diagnostics {
     pub fn merge(&mut self, other: Diagnostics) {
         self.diagnostics.extend(other.diagnostics);
     }
+
+    /// Point every span into `file`, see [`Diagnostic::set_file`]
+    pub fn set_file(&mut self, file: FileId) {
+        for diagnostic in &mut self.diagnostics {
+            diagnostic.set_file(file);
+        }
+    }
+
+    /// Split the diagnostics of a multi-file compilation by the file of
+    /// their primary span
+    ///
+    /// Each group has its spans relative to its file alone, the shape the
+    /// output of a single file has. Labels and related information in
+    /// another file name it by `path`.
+    pub fn split_by_file(
+        self,
+        path: impl Fn(FileId) -> Option<String>,
+    ) -> BTreeMap<FileId, Diagnostics> {
+        let mut files: BTreeMap<FileId, Diagnostics> = BTreeMap::new();
+        for mut diagnostic in self.diagnostics {
+            let file = diagnostic.span.file;
+            diagnostic.localize(&path);
+            files.entry(file).or_default().add(diagnostic);
+        }
+        files
+    }
+
+    /// Apply the first suggestion of every diagnostic to `source`
+    ///
+    /// Suggestions overlapping one already applied are skipped. Returns the
+    /// fixed source and the number of suggestions applied.
+    pub fn apply_fixes(&self, source: &str) -> (String, usize) {
+        let mut fixes: Vec<&Suggestion> = self
+            .diagnostics
+            .iter()
+            .filter_map(|diag| diag.suggestions.first())
+            .filter(|fix| fix.span.end as usize <= source.len())
+            .collect();
+        fixes.sort_by_key(|fix| (fix.span.start, fix.span.end));
+
+        let mut output = String::with_capacity(source.len());
+        let mut applied: Option<Span> = None;
+        let mut count = 0;
+        for fix in fixes {
+            if let Some(last) = applied {
+                // Two insertions at the same point would both apply
+                if fix.span.start < last.end || (fix.span == last && fix.span.is_empty()) {
+                    continue;
+                }
+            }
+            let copied_from = applied.map_or(0, |last| last.end as usize);
+            output.push_str(&source[copied_from..fix.span.start as usize]);
+            output.push_str(&fix.replacement);
+            applied = Some(fix.span);
+            count += 1;
+        }
+        output.push_str(&source[applied.map_or(0, |last| last.end as usize)..]);
+        (output, count)
+    }
 }
 
 impl IntoIterator for Diagnostics {
@@ -530,6 +717,71 @@ mod tests {
         assert!(json.contains("E0201"));
     }
 
+    /// The JSON shape read by the server, the language server and the CLI's
+    /// `--error-format json`: renaming a field or changing an encoding breaks
+    /// them and needs a new `OUTPUT_VERSION`
+    #[test]
+    fn test_json_schema() {
+        let diagnostic = Diagnostic::warning("`count` is never read", Span::new(12, 17))
+            .with_code("E0309")
+            .with_label(Label::new(Span::new(20, 25), "written here"))
+            .with_suggestion(Suggestion::delete(Span::new(12, 30), "remove `count`"))
+            .with_help("read it, or remove it")
+            .with_related(RelatedInfo::in_file(Span::new(3, 8), "lib.frel", "declared here"))
+            .unnecessary()
+            .deprecated()
+            .with_data(serde_json::json!({ "name": "count" }));
+
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "severity": "warning",
+                "code": "E0309",
+                "message": "`count` is never read",
+                "span": { "start": 12, "end": 17 },
+                "labels": [{ "span": { "start": 20, "end": 25 }, "message": "written here" }],
+                "suggestions": [{
+                    "span": { "start": 12, "end": 30 },
+                    "replacement": "",
+                    "message": "remove `count`",
+                }],
+                "help": "read it, or remove it",
+                "related": [{
+                    "span": { "start": 3, "end": 8 },
+                    "file": "lib.frel",
+                    "message": "declared here",
+                }],
+                "tags": ["unnecessary", "deprecated"],
+                "data": { "name": "count" },
+            })
+        );
+        let parsed: Diagnostic = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.span, diagnostic.span);
+        assert_eq!(parsed.related[0].file.as_deref(), Some("lib.frel"));
+
+        // Optional fields are left out when empty
+        let minimal = serde_json::to_value(Diagnostic::error("failed", Span::new(0, 1))).unwrap();
+        assert_eq!(
+            minimal,
+            serde_json::json!({
+                "severity": "error",
+                "message": "failed",
+                "span": { "start": 0, "end": 1 },
+            })
+        );
+
+        let severities = [Severity::Error, Severity::Warning, Severity::Info, Severity::Hint];
+        let names: Vec<_> = severities
+            .iter()
+            .map(|severity| serde_json::to_value(severity).unwrap())
+            .collect();
+        assert_eq!(names, ["error", "warning", "info", "hint"]);
+        assert!(severities
+            .iter()
+            .all(|severity| serde_json::to_value(severity).unwrap() == severity.as_str()));
+    }
+
     #[test]
     fn test_diagnostics_merge() {
         let mut diags1 = Diagnostics::new();
@@ -541,4 +793,65 @@ mod tests {
         diags1.merge(diags2);
         assert_eq!(diags1.len(), 2);
     }
+
+    #[test]
+    fn test_apply_fixes() {
+        let source = "bluprint Main { Row(a b) }";
+        let diags: Diagnostics = [
+            Diagnostic::error("unknown keyword", Span::new(0, 8))
+                .with_suggestion(Suggestion::new(Span::new(0, 8), "blueprint", "replace"))
+                .with_suggestion(Suggestion::new(Span::new(0, 8), "backend", "replace")),
+            Diagnostic::error("expected `)`", Span::new(22, 23))
+                .with_suggestion(Suggestion::insert(21, ",", "insert `,`")),
+            Diagnostic::error("expected `)`", Span::new(22, 23))
+                .with_suggestion(Suggestion::insert(21, ",", "insert `,`")),
+            Diagnostic::error("overlapping", Span::new(4, 6))
+                .with_suggestion(Suggestion::delete(Span::new(4, 6), "delete")),
+            Diagnostic::error("no fix", Span::new(9, 13)),
+        ]
+        .into_iter()
+        .collect();
+
+        let (fixed, count) = diags.apply_fixes(source);
+        assert_eq!(fixed, "blueprint Main { Row(a, b) }");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_similar_name() {
+        let keywords = ["blueprint", "backend", "scheme", "enum"];
+        assert_eq!(similar_name("bluprint", keywords), Some("blueprint"));
+        assert_eq!(similar_name("backnd", keywords), Some("backend"));
+        assert_eq!(similar_name("enun", keywords), Some("enum"));
+        assert_eq!(similar_name("widget", keywords), None);
+        assert_eq!(similar_name("enum", keywords), None);
+    }
+
+    #[test]
+    fn test_split_by_file() {
+        let home = FileId(1);
+        let other = FileId(2);
+        let diags: Diagnostics = [
+            Diagnostic::error("duplicate definition", Span::new(10, 14).in_file(home))
+                .with_label(Label::new(Span::new(3, 7).in_file(other), "first defined here"))
+                .with_suggestion(Suggestion::delete(Span::new(3, 7).in_file(other), "delete")),
+            Diagnostic::error("unknown name", Span::new(0, 4).in_file(other)),
+        ]
+        .into_iter()
+        .collect();
+
+        let files = diags.split_by_file(|file| Some(format!("file{}.frel", file.0)));
+        assert_eq!(files.len(), 2);
+
+        let first = &files[&home].as_slice()[0];
+        assert_eq!(first.span, Span::new(10, 14));
+        assert!(first.labels.is_empty() && first.suggestions.is_empty());
+        assert_eq!(first.related[0].span, Span::new(3, 7));
+        assert_eq!(first.related[0].file.as_deref(), Some("file2.frel"));
+        assert_eq!(files[&other].as_slice()[0].span, Span::new(0, 4));
+
+        // File IDs are per compilation and left out of serialized spans
+        let json = serde_json::to_string(&Span::new(10, 14).in_file(home)).unwrap();
+        assert_eq!(json, r#"{"start":10,"end":14}"#);
+    }
 }
@@ -0,0 +1,499 @@
+// SARIF export for diagnostics
+//
+// Converts diagnostics into a SARIF 2.1.0 log, the format read by GitHub code
+// scanning and other static analysis dashboards:
+//
+//   {"version":"2.1.0","runs":[{"tool":{...},"results":[...]}]}
+//
+// Error codes become rules, labels and related information become related
+// locations, and suggestions become fixes. Spans are converted to 1-based
+// lines and columns, so the source of every file a diagnostic points into
+// must be available, either given with the diagnostics or from a loader.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::format::SourceLoader;
+use super::{codes, Diagnostic, DiagnosticTag, Diagnostics, Severity};
+use crate::source::{LineIndex, Span};
+
+/// Version of SARIF written
+pub const SARIF_VERSION: &str = "2.1.0";
+
+/// Schema of the SARIF version written
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Base URI id of paths made relative to the project root
+const ROOT_BASE_ID: &str = "SRCROOT";
+
+/// A SARIF log with a single run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarifLog {
+    pub version: String,
+    #[serde(rename = "$schema")]
+    pub schema: String,
+    pub runs: Vec<SarifRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRun {
+    pub tool: SarifTool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub original_uri_base_ids: BTreeMap<String, SarifArtifactLocation>,
+    /// Columns count characters, as `LineCol` does
+    pub column_kind: String,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarifTool {
+    pub driver: SarifDriver,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifDriver {
+    pub name: String,
+    pub version: String,
+    /// One rule for every error code reported, in code order
+    pub rules: Vec<SarifRule>,
+}
+
+/// An error code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRule {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub full_description: Option<SarifMessage>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_configuration: Option<SarifConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub properties: Option<SarifProperties>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarifConfiguration {
+    pub level: String,
+}
+
+/// A diagnostic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rule_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rule_index: Option<usize>,
+    pub level: String,
+    pub message: SarifMessage,
+    pub locations: Vec<SarifLocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub related_locations: Vec<SarifLocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub fixes: Vec<SarifFix>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub properties: Option<SarifProperties>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarifMessage {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLocation {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<usize>,
+    pub physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub message: Option<SarifMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifPhysicalLocation {
+    pub artifact_location: SarifArtifactLocation,
+    /// Absent if the file's source wasn't available
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region: Option<SarifRegion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifArtifactLocation {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub uri_base_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRegion {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+/// A suggestion, as the replacement of a region
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifFix {
+    pub description: SarifMessage,
+    pub artifact_changes: Vec<SarifArtifactChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifArtifactChange {
+    pub artifact_location: SarifArtifactLocation,
+    pub replacements: Vec<SarifReplacement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifReplacement {
+    pub deleted_region: SarifRegion,
+    pub inserted_content: SarifMessage,
+}
+
+/// Tags of a result, or the category of a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarifProperties {
+    pub tags: Vec<String>,
+}
+
+impl SarifLog {
+    /// The log as one line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("SARIF log serializes")
+    }
+}
+
+/// Collects the diagnostics of several files into a SARIF log
+///
+/// Sources not given with the diagnostics are read through the loader,
+/// once per file.
+pub struct SarifBuilder<'a> {
+    tool: String,
+    version: String,
+    root: Option<String>,
+    loader: &'a dyn SourceLoader,
+    indexes: BTreeMap<String, Option<LineIndex>>,
+    rules: BTreeMap<String, SarifRule>,
+    results: Vec<SarifResult>,
+}
+
+impl<'a> SarifBuilder<'a> {
+    /// A builder for the diagnostics of the named tool
+    pub fn new(
+        tool: impl Into<String>,
+        version: impl Into<String>,
+        loader: &'a dyn SourceLoader,
+    ) -> Self {
+        Self {
+            tool: tool.into(),
+            version: version.into(),
+            root: None,
+            loader,
+            indexes: BTreeMap::new(),
+            rules: BTreeMap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Write paths below `root` relative to it, against the `SRCROOT` base
+    pub fn with_root(mut self, root: impl Into<String>) -> Self {
+        let root = root.into().replace('\\', "/");
+        self.root = Some(root.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Add the diagnostics of `file`; `source` is its text, read through the
+    /// loader if absent
+    pub fn add_diagnostics(&mut self, file: &str, source: Option<&str>, diagnostics: &Diagnostics) {
+        if let Some(source) = source {
+            self.indexes.insert(file.to_string(), Some(LineIndex::new(source)));
+        }
+        for diagnostic in diagnostics.iter() {
+            self.add(file, diagnostic);
+        }
+    }
+
+    /// Add a diagnostic reported in `file`
+    pub fn add(&mut self, file: &str, diagnostic: &Diagnostic) {
+        let rule_id = diagnostic.code.clone();
+        if let Some(code) = &rule_id {
+            self.rules.entry(code.clone()).or_insert_with(|| rule(code));
+        }
+
+        let mut message = diagnostic.message.clone();
+        if let Some(help) = &diagnostic.help {
+            message.push_str("\nhelp: ");
+            message.push_str(help);
+        }
+
+        // Labels point into the diagnostic's file, related information
+        // into its own file if it names one
+        let mut related = Vec::new();
+        for label in diagnostic.labels.iter().filter(|l| !l.message.is_empty()) {
+            related.push((file.to_string(), label.span, label.message.clone()));
+        }
+        for info in &diagnostic.related {
+            let related_file = info.file.clone().unwrap_or_else(|| file.to_string());
+            related.push((related_file, info.span, info.message.clone()));
+        }
+        let related_locations = related
+            .into_iter()
+            .enumerate()
+            .map(|(id, (related_file, span, text))| SarifLocation {
+                id: Some(id),
+                physical_location: self.physical_location(&related_file, span),
+                message: Some(SarifMessage { text }),
+            })
+            .collect();
+
+        // A fix needs a region to replace, so suggestions in files without
+        // a source are left out
+        let mut fixes = Vec::new();
+        for suggestion in &diagnostic.suggestions {
+            let Some(region) = self.region(file, suggestion.span) else {
+                continue;
+            };
+            fixes.push(SarifFix {
+                description: SarifMessage { text: suggestion.message.clone() },
+                artifact_changes: vec![SarifArtifactChange {
+                    artifact_location: self.artifact_location(file),
+                    replacements: vec![SarifReplacement {
+                        deleted_region: region,
+                        inserted_content: SarifMessage {
+                            text: suggestion.replacement.clone(),
+                        },
+                    }],
+                }],
+            });
+        }
+
+        let tags: Vec<String> = diagnostic.tags.iter().map(|tag| tag_name(*tag).into()).collect();
+        let location = self.physical_location(file, diagnostic.span);
+        self.results.push(SarifResult {
+            rule_id,
+            rule_index: None,
+            level: level(diagnostic.severity).to_string(),
+            message: SarifMessage { text: message },
+            locations: vec![SarifLocation {
+                id: None,
+                physical_location: location,
+                message: None,
+            }],
+            related_locations,
+            fixes,
+            properties: (!tags.is_empty()).then_some(SarifProperties { tags }),
+        });
+    }
+
+    /// The log of the diagnostics added
+    pub fn finish(self) -> SarifLog {
+        let index: BTreeMap<&String, usize> =
+            self.rules.keys().enumerate().map(|(i, code)| (code, i)).collect();
+        let results = self
+            .results
+            .iter()
+            .cloned()
+            .map(|mut result| {
+                result.rule_index = result.rule_id.as_ref().map(|code| index[code]);
+                result
+            })
+            .collect();
+
+        let mut base_ids = BTreeMap::new();
+        if let Some(root) = &self.root {
+            let uri = match root.starts_with('/') {
+                true => format!("file://{}/", root),
+                false => format!("file:///{}/", root),
+            };
+            let location = SarifArtifactLocation { uri, uri_base_id: None };
+            base_ids.insert(ROOT_BASE_ID.to_string(), location);
+        }
+
+        SarifLog {
+            version: SARIF_VERSION.to_string(),
+            schema: SARIF_SCHEMA.to_string(),
+            runs: vec![SarifRun {
+                tool: SarifTool {
+                    driver: SarifDriver {
+                        name: self.tool,
+                        version: self.version,
+                        rules: self.rules.into_values().collect(),
+                    },
+                },
+                original_uri_base_ids: base_ids,
+                column_kind: "unicodeCodePoints".to_string(),
+                results,
+            }],
+        }
+    }
+
+    fn physical_location(&mut self, file: &str, span: Span) -> SarifPhysicalLocation {
+        SarifPhysicalLocation {
+            artifact_location: self.artifact_location(file),
+            region: self.region(file, span),
+        }
+    }
+
+    fn artifact_location(&self, file: &str) -> SarifArtifactLocation {
+        let path = file.replace('\\', "/");
+        let relative = self
+            .root
+            .as_ref()
+            .and_then(|root| path.strip_prefix(root.as_str()))
+            .and_then(|rest| rest.strip_prefix('/'));
+        match relative {
+            Some(relative) => SarifArtifactLocation {
+                uri: relative.to_string(),
+                uri_base_id: Some(ROOT_BASE_ID.to_string()),
+            },
+            None => SarifArtifactLocation { uri: path, uri_base_id: None },
+        }
+    }
+
+    /// Lines and columns of a span in `file`, if its source is available
+    fn region(&mut self, file: &str, span: Span) -> Option<SarifRegion> {
+        let loader = self.loader;
+        let index = self
+            .indexes
+            .entry(file.to_string())
+            .or_insert_with(|| loader.load(file).map(|source| LineIndex::new(&source)))
+            .as_ref()?;
+        let (start, end) = (index.line_col(span.start), index.line_col(span.end));
+        Some(SarifRegion {
+            start_line: start.line,
+            start_column: start.col,
+            end_line: end.line,
+            end_column: end.col,
+        })
+    }
+}
+
+/// The rule of an error code, described by the code's registry entry if it
+/// has one
+fn rule(code: &str) -> SarifRule {
+    let Some(entry) = codes::lookup(code) else {
+        return SarifRule {
+            id: code.to_string(),
+            name: None,
+            full_description: None,
+            default_configuration: None,
+            properties: None,
+        };
+    };
+    SarifRule {
+        id: code.to_string(),
+        name: Some(entry.name.to_string()),
+        full_description: Some(SarifMessage {
+            text: entry.explanation.to_string(),
+        }),
+        default_configuration: Some(SarifConfiguration {
+            level: level(entry.default_severity).to_string(),
+        }),
+        properties: Some(SarifProperties {
+            tags: vec![entry.category.as_str().to_string()],
+        }),
+    }
+}
+
+/// SARIF has no hints; info and hint diagnostics are both notes
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Hint => "note",
+    }
+}
+
+fn tag_name(tag: DiagnosticTag) -> &'static str {
+    match tag {
+        DiagnosticTag::Unnecessary => "unnecessary",
+        DiagnosticTag::Deprecated => "deprecated",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::{Label, RelatedInfo, Suggestion};
+
+    #[test]
+    fn test_sarif_log() {
+        let source = "module app\n\nblueprint Main {\n    Mising { }\n}\n";
+        let other = "module app.ui\n\nblueprint Missing { }\n";
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.add(
+            Diagnostic::from_code(&codes::E0301, Span::new(33, 39), "cannot find `Mising`")
+                .with_label(Label::new(Span::new(12, 26), "in this blueprint"))
+                .with_related(RelatedInfo::in_file(
+                    Span::new(25, 32),
+                    "/work/ui.frel",
+                    "similar name defined here",
+                ))
+                .with_suggestion(Suggestion::new(Span::new(33, 39), "Missing", "did you mean"))
+                .with_help("check the imports"),
+        );
+        diagnostics.add(Diagnostic::hint("unused import", Span::new(0, 6)).unnecessary());
+
+        let load = |file: &str| (file == "/work/ui.frel").then(|| other.to_string());
+        let mut builder = SarifBuilder::new("frelc", "0.1.0", &load).with_root("/work");
+        builder.add_diagnostics("/work/app.frel", Some(source), &diagnostics);
+        builder.add("/elsewhere/gone.frel", &Diagnostic::warning("stale", Span::new(0, 1)));
+        let log = builder.finish();
+
+        let json: serde_json::Value = serde_json::from_str(&log.to_json()).unwrap();
+        let run = &json["runs"][0];
+        assert_eq!(json["version"], "2.1.0");
+        assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], "file:///work/");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "E0301");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["name"], "undefined_name");
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "E0301");
+        assert_eq!(result["ruleIndex"], 0);
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["message"]["text"], "cannot find `Mising`\nhelp: check the imports");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "app.frel");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "SRCROOT");
+        assert_eq!(
+            location["region"],
+            serde_json::json!({ "startLine": 4, "startColumn": 5, "endLine": 4, "endColumn": 11 })
+        );
+
+        let related = &result["relatedLocations"];
+        assert_eq!(related[0]["message"]["text"], "in this blueprint");
+        assert_eq!(related[0]["physicalLocation"]["region"]["startLine"], 3);
+        assert_eq!(related[1]["physicalLocation"]["artifactLocation"]["uri"], "ui.frel");
+        assert_eq!(related[1]["physicalLocation"]["region"]["startColumn"], 11);
+
+        let fix = &result["fixes"][0];
+        assert_eq!(fix["description"]["text"], "did you mean");
+        let replacement = &fix["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["insertedContent"]["text"], "Missing");
+        assert_eq!(replacement["deletedRegion"]["startColumn"], 5);
+
+        let hint = &run["results"][1];
+        assert_eq!(hint["level"], "note");
+        assert_eq!(hint["properties"]["tags"][0], "unnecessary");
+        assert!(hint.get("ruleId").is_none());
+
+        // Without a source the location has no region and paths outside the
+        // root stay as they are
+        let stale = &run["results"][2]["locations"][0]["physicalLocation"];
+        assert_eq!(stale["artifactLocation"]["uri"], "/elsewhere/gone.frel");
+        assert!(stale.get("region").is_none());
+    }
+}
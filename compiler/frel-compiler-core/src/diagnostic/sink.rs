@@ -117,6 +117,69 @@ where
     }
 }
 
+/// Passes diagnostics on until a number of errors has been passed, then
+/// drops everything after
+///
+/// Used for `--max-errors`: the first errors of a long build are usually the
+/// ones worth reading.
+pub struct MaxErrorsSink<S>
+where
+    S: DiagnosticSink,
+{
+    inner: S,
+    max_errors: usize,
+    error_count: usize,
+    suppressed: usize,
+}
+
+impl<S> MaxErrorsSink<S>
+where
+    S: DiagnosticSink,
+{
+    pub fn new(inner: S, max_errors: usize) -> Self {
+        Self {
+            inner,
+            max_errors,
+            error_count: 0,
+            suppressed: 0,
+        }
+    }
+
+    /// Number of diagnostics dropped after the limit was reached
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    /// Get back the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> DiagnosticSink for MaxErrorsSink<S>
+where
+    S: DiagnosticSink,
+{
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.severity == Severity::Error {
+            self.error_count += 1;
+        }
+        if self.inner.error_count() >= self.max_errors {
+            self.suppressed += 1;
+        } else {
+            self.inner.emit(diagnostic);
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        self.error_count > 0
+    }
+
+    fn error_count(&self) -> usize {
+        self.error_count
+    }
+}
+
 /// Filters diagnostics to only those from a specific file
 ///
 /// Useful for incremental compilation where we only want to update
@@ -295,6 +358,22 @@ mod tests {
         assert_eq!(received.len(), 2);
     }
 
+    #[test]
+    fn test_max_errors_sink() {
+        let mut sink = MaxErrorsSink::new(CollectingSink::new(), 2);
+        sink.emit(make_error());
+        sink.emit(make_warning());
+        sink.emit(make_error());
+        sink.emit(make_warning());
+        sink.emit(make_error());
+
+        assert_eq!(sink.error_count(), 3);
+        assert_eq!(sink.suppressed(), 2);
+        let passed = sink.into_inner();
+        assert_eq!(passed.error_count(), 2);
+        assert_eq!(passed.diagnostics().len(), 3);
+    }
+
     #[test]
     fn test_counting_sink() {
         let mut sink = CountingSink::new();
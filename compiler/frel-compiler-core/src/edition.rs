@@ -0,0 +1,419 @@
+// Language editions, experimental features and project configuration
+//
+// An edition fixes the syntax a file may use, so projects can adopt new
+// constructs when they are ready. A project declares its edition in
+// `frel.toml`, and a file can override it with an `edition` line before its
+// module declaration:
+//
+//     # frel.toml
+//     edition = "2025"
+//
+//     edition 2026
+//     module app.editor
+//
+// Without a declaration the latest edition is used. Syntax newer than the
+// file's edition still parses, but reports "requires edition X" (E0208)
+// instead of a generic parse error.
+//
+// Experimental constructs belong to no edition. They ship behind a gate that
+// each compilation enables explicitly (`--enable-feature command-results`);
+// using one without its gate reports E0210. The parser and the semantic
+// passes consult the same `Features`, carried by the parsed file.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, LintLevels};
+use crate::source::Span;
+use crate::vfs::SourceProvider;
+
+/// Name of the project configuration file
+pub const CONFIG_FILE: &str = "frel.toml";
+
+/// A language edition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Edition {
+    /// The original language
+    E2025,
+    /// Backend lifetimes, undo history, command policies, named injection and
+    /// keyboard shortcuts
+    #[default]
+    E2026,
+}
+
+impl Edition {
+    /// The newest edition, used when none is declared
+    pub const LATEST: Edition = Edition::E2026;
+
+    /// All editions, oldest first
+    pub const ALL: [Edition; 2] = [Edition::E2025, Edition::E2026];
+
+    /// The edition's name as written in source and `frel.toml`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2025 => "2025",
+            Edition::E2026 => "2026",
+        }
+    }
+
+    /// Look up an edition by name
+    pub fn parse(name: &str) -> Option<Edition> {
+        Self::ALL
+            .into_iter()
+            .find(|edition| edition.as_str() == name)
+    }
+
+    /// Names of all editions, for diagnostics
+    pub fn names() -> String {
+        Self::ALL.map(Edition::as_str).join(", ")
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Edition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Edition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Edition::parse(&name).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unknown edition \"{}\" (expected one of {})",
+                name,
+                Edition::names()
+            ))
+        })
+    }
+}
+
+/// Syntax introduced after the first edition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `singleton backend Session`, `screen backend Form`
+    BackendLifetime,
+    /// `undoable backend Editor`, `undoable text : String`
+    Undoable,
+    /// `latest command search(query: String)`
+    CommandPolicy,
+    /// `with slot: Backend`
+    NamedInjection,
+    /// `shortcut "Ctrl+S" { save() }`
+    Shortcuts,
+    /// `provide theme = dark`, `consume theme: Theme`
+    Context,
+    /// `on_error { ... } fallback { ... }`
+    ErrorBoundary,
+    /// `loading { ... }`
+    LoadingSlot,
+    /// `method total() : i32 = price * quantity`
+    MethodBodies,
+    /// `(item) -> select(item.id)`, `on_select: (String) -> unit`
+    Lambdas,
+    /// `repeat on 0..count { i -> ... }`
+    Ranges,
+    /// `repeat on scores { (name, score) -> ... }`, `Ok(value) => ...`
+    Destructuring,
+    /// `enum Result { Ok(String) Err(i32) }`
+    EnumPayloads,
+}
+
+impl Feature {
+    /// First edition supporting the feature
+    pub fn edition(self) -> Edition {
+        match self {
+            Feature::BackendLifetime
+            | Feature::Undoable
+            | Feature::CommandPolicy
+            | Feature::NamedInjection
+            | Feature::Shortcuts
+            | Feature::Context
+            | Feature::ErrorBoundary
+            | Feature::LoadingSlot
+            | Feature::MethodBodies
+            | Feature::Lambdas
+            | Feature::Ranges
+            | Feature::Destructuring
+            | Feature::EnumPayloads => Edition::E2026,
+        }
+    }
+
+    /// Description used in "requires edition" diagnostics
+    pub fn description(self) -> &'static str {
+        match self {
+            Feature::BackendLifetime => "backend lifetime modifiers",
+            Feature::Undoable => "`undoable` backends and fields",
+            Feature::CommandPolicy => "command concurrency policies",
+            Feature::NamedInjection => "named backend injection (`with name: Backend`)",
+            Feature::Shortcuts => "keyboard shortcuts",
+            Feature::Context => "context values (`provide` and `consume`)",
+            Feature::ErrorBoundary => "error boundaries (`on_error`)",
+            Feature::LoadingSlot => "loading placeholders (`loading`)",
+            Feature::MethodBodies => "method bodies (`method name() : Type = expr`)",
+            Feature::Lambdas => "lambdas and function types (`(x) -> expr`)",
+            Feature::Ranges => "ranges (`start..end`)",
+            Feature::Destructuring => "destructuring patterns (`(key, value) ->`)",
+            Feature::EnumPayloads => "enum variants with data (`Ok(String)`)",
+        }
+    }
+}
+
+/// Experimental syntax, accepted only when its gate is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Experimental {
+    /// `command load(id : UUID) : User`
+    CommandResults,
+}
+
+impl Experimental {
+    /// All experimental features
+    pub const ALL: [Experimental; 1] = [Experimental::CommandResults];
+
+    /// The gate's name, as passed to `--enable-feature`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Experimental::CommandResults => "command-results",
+        }
+    }
+
+    /// Look up a feature by its gate name
+    pub fn parse(name: &str) -> Option<Experimental> {
+        Self::ALL
+            .into_iter()
+            .find(|feature| feature.as_str() == name)
+    }
+
+    /// Names of all experimental features, for diagnostics
+    pub fn names() -> String {
+        Self::ALL.map(Experimental::as_str).join(", ")
+    }
+
+    /// Description used in "is experimental" diagnostics
+    pub fn description(self) -> &'static str {
+        match self {
+            Experimental::CommandResults => "command results",
+        }
+    }
+
+    /// Error for using the feature at `span` while its gate is disabled
+    pub fn disabled(self, span: Span) -> Diagnostic {
+        Diagnostic::from_code(
+            &codes::E0210,
+            span,
+            format!(
+                "{} are experimental and the `{}` feature is not enabled",
+                self.description(),
+                self
+            ),
+        )
+        .with_help(format!("compile with `--enable-feature {}`", self))
+    }
+}
+
+impl fmt::Display for Experimental {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Experimental {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Experimental::parse(name).ok_or_else(|| {
+            format!(
+                "unknown feature \"{}\" (expected one of {})",
+                name,
+                Experimental::names()
+            )
+        })
+    }
+}
+
+/// Experimental features enabled for a compilation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Features {
+    enabled: BTreeSet<Experimental>,
+}
+
+impl Features {
+    /// Enable `feature`
+    pub fn enable(&mut self, feature: Experimental) {
+        self.enabled.insert(feature);
+    }
+
+    /// Whether `feature` may be used
+    pub fn is_enabled(&self, feature: Experimental) -> bool {
+        self.enabled.contains(&feature)
+    }
+
+    /// Enabled features, in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = Experimental> + '_ {
+        self.enabled.iter().copied()
+    }
+}
+
+impl FromIterator<Experimental> for Features {
+    fn from_iter<I: IntoIterator<Item = Experimental>>(iter: I) -> Self {
+        Features {
+            enabled: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Contents of `frel.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectConfig {
+    /// Edition of the project's files; the latest if not set
+    pub edition: Option<Edition>,
+    /// File watching of the compiler server (`[watch]`)
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// Levels of lints over their default severities (`[lints]`)
+    #[serde(default)]
+    pub lints: LintLevels,
+}
+
+/// The `[watch]` table of `frel.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Quiet time in milliseconds before a burst of saves is rebuilt
+    pub debounce_ms: Option<u64>,
+    /// Glob patterns, relative to the project root, of paths not watched
+    pub ignore: Vec<String>,
+}
+
+impl ProjectConfig {
+    /// Parse the contents of a `frel.toml`
+    pub fn parse(text: &str) -> Result<ProjectConfig, Diagnostics> {
+        toml::from_str(text).map_err(|e| {
+            let span = e.span().map_or(Span::default(), |range| {
+                Span::new(range.start as u32, range.end as u32)
+            });
+            let mut diagnostics = Diagnostics::new();
+            diagnostics.add(Diagnostic::error(
+                format!("invalid {}: {}", CONFIG_FILE, e.message()),
+                span,
+            ));
+            diagnostics
+        })
+    }
+
+    /// Load `frel.toml` from `dir`; a missing file gives the default configuration
+    pub fn load(files: &dyn SourceProvider, dir: &Path) -> Result<ProjectConfig, Diagnostics> {
+        match files.read(&dir.join(CONFIG_FILE)) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProjectConfig::default()),
+            Err(e) => {
+                let mut diagnostics = Diagnostics::new();
+                diagnostics.add(Diagnostic::error(
+                    format!("cannot read {}: {}", CONFIG_FILE, e),
+                    Span::default(),
+                ));
+                Err(diagnostics)
+            }
+        }
+    }
+
+    /// Find the `frel.toml` governing `path`, searching it and its ancestors
+    pub fn find(files: &dyn SourceProvider, path: &Path) -> Option<PathBuf> {
+        path.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|file| files.is_file(file))
+    }
+
+    /// The configured edition, or the latest
+    pub fn edition(&self) -> Edition {
+        self.edition.unwrap_or(Edition::LATEST)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edition_names() {
+        assert_eq!(Edition::parse("2025"), Some(Edition::E2025));
+        assert_eq!(Edition::parse("2030"), None);
+        assert_eq!(Edition::default(), Edition::LATEST);
+        assert!(Edition::E2025 < Edition::E2026);
+        assert_eq!(Edition::names(), "2025, 2026");
+    }
+
+    #[test]
+    fn test_features() {
+        assert_eq!(
+            "command-results".parse::<Experimental>(),
+            Ok(Experimental::CommandResults)
+        );
+        assert_eq!(
+            "generics".parse::<Experimental>(),
+            Err("unknown feature \"generics\" (expected one of command-results)".to_string())
+        );
+
+        let features: Features = [Experimental::CommandResults].into_iter().collect();
+        assert!(features.is_enabled(Experimental::CommandResults));
+        assert!(!Features::default().is_enabled(Experimental::CommandResults));
+
+        let error = Experimental::CommandResults.disabled(Span::new(3, 7));
+        assert_eq!(error.code.as_deref(), Some("E0210"));
+        assert_eq!(
+            error.help.as_deref(),
+            Some("compile with `--enable-feature command-results`")
+        );
+    }
+
+    #[test]
+    fn test_project_config() {
+        let config = ProjectConfig::parse("edition = \"2025\"\n").unwrap();
+        assert_eq!(config.edition(), Edition::E2025);
+        assert_eq!(ProjectConfig::parse("").unwrap().edition(), Edition::LATEST);
+
+        let errors = ProjectConfig::parse("edition = \"2030\"\n").unwrap_err();
+        let error = errors.iter().next().unwrap();
+        assert!(
+            error
+                .message
+                .contains("unknown edition \"2030\" (expected one of 2025, 2026)"),
+            "{}",
+            error.message
+        );
+        assert_eq!(error.span, Span::new(10, 16));
+    }
+
+    #[test]
+    fn test_project_config_watch() {
+        let config = ProjectConfig::parse("").unwrap();
+        assert_eq!(config.watch.debounce_ms, None);
+        assert!(config.watch.ignore.is_empty());
+
+        let text = "[watch]\ndebounce_ms = 200\nignore = [\"dist/**\", \"*.tmp\"]\n";
+        let config = ProjectConfig::parse(text).unwrap();
+        assert_eq!(config.watch.debounce_ms, Some(200));
+        assert_eq!(config.watch.ignore, vec!["dist/**", "*.tmp"]);
+    }
+
+    #[test]
+    fn test_project_config_lints() {
+        let text = "[lints]\nextra_translation = \"allow\"\nwarnings = \"deny\"\n";
+        let config = ProjectConfig::parse(text).unwrap();
+        assert_eq!(config.lints.level("E0802"), Some(crate::LintLevel::Allow));
+
+        let errors = ProjectConfig::parse("[lints]\nE0301 = \"warn\"\n").unwrap_err();
+        let error = errors.iter().next().unwrap();
+        assert!(error.message.contains("is an error, not a lint"), "{}", error.message);
+    }
+}
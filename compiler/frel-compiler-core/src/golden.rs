@@ -0,0 +1,214 @@
+// Golden files of the tests over `test-data`
+//
+// A test compares its output with an expected file next to each source; set
+// `FREL_UPDATE_GOLDEN=1` to rewrite the expected files after an intended
+// change. JSON output is compared as written unless the nearest `test.toml`
+// of the source's directory, up to the test root, normalizes it first:
+//
+//     [normalize]
+//     strip_spans = true   # drop `span` and `*_span` fields
+//     sort_keys = true     # write object keys in alphabetical order
+//     round_floats = 3     # round floats to 3 decimal places
+//
+// Normalized output is compared as JSON values, so the layout of the expected
+// file and the order of its keys never fail a test, and updates write it
+// normalized.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Name of the per-directory test configuration
+pub const CONFIG_FILE: &str = "test.toml";
+
+/// Configuration of a test directory
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    normalize: Normalize,
+}
+
+/// Normalization applied to JSON output before it is compared
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Normalize {
+    /// Drop `span` and `*_span` fields, which change with whitespace
+    pub strip_spans: bool,
+    /// Order object keys alphabetically
+    pub sort_keys: bool,
+    /// Round floats to this many decimal places
+    pub round_floats: Option<u32>,
+}
+
+impl Normalize {
+    /// Whether the output is compared as written
+    pub fn is_empty(&self) -> bool {
+        *self == Normalize::default()
+    }
+
+    /// Normalize `value` in place
+    pub fn apply(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                if self.strip_spans {
+                    fields.retain(|key, _| key != "span" && !key.ends_with("_span"));
+                }
+                if self.sort_keys {
+                    fields.sort_keys();
+                }
+                fields.values_mut().for_each(|field| self.apply(field));
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply(item)),
+            Value::Number(number) if number.is_f64() => {
+                if let (Some(places), Some(x)) = (self.round_floats, number.as_f64()) {
+                    let scale = 10f64.powi(places as i32);
+                    if let Some(rounded) = serde_json::Number::from_f64((x * scale).round() / scale)
+                    {
+                        *number = rounded;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A directory of golden tests
+pub struct Golden {
+    root: PathBuf,
+    update: bool,
+}
+
+impl Golden {
+    /// Tests under `test-data/<name>`
+    pub fn new(name: &str) -> Self {
+        Self {
+            root: Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../test-data")).join(name),
+            update: std::env::var_os("FREL_UPDATE_GOLDEN").is_some(),
+        }
+    }
+
+    /// Sources with `extension` in the directory and below, sorted
+    pub fn sources(&self, extension: &str) -> Vec<PathBuf> {
+        fn collect(dir: &Path, extension: &str, sources: &mut Vec<PathBuf>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    collect(&path, extension, sources);
+                } else if path.extension().is_some_and(|ext| ext == extension) {
+                    sources.push(path);
+                }
+            }
+        }
+        let mut sources = Vec::new();
+        collect(&self.root, extension, &mut sources);
+        sources.sort();
+        assert!(
+            !sources.is_empty(),
+            "no .{} files in {}",
+            extension,
+            self.root.display()
+        );
+        sources
+    }
+
+    /// Path of `source` relative to the root, as tests name their sources
+    /// so that expected files don't depend on the checkout
+    pub fn name(&self, source: &Path) -> String {
+        let relative = source.strip_prefix(&self.root).unwrap();
+        relative.to_string_lossy().replace('\\', "/")
+    }
+
+    /// Normalization of the expected files in `dir`, from the nearest
+    /// `test.toml` up to the root
+    pub fn normalize(&self, dir: &Path) -> Normalize {
+        let config = dir
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file());
+        let Some(path) = config else {
+            return Normalize::default();
+        };
+        let text = std::fs::read_to_string(&path).unwrap();
+        let config: Config = toml::from_str(&text)
+            .unwrap_or_else(|error| panic!("invalid {}: {}", path.display(), error));
+        config.normalize
+    }
+
+    /// Compare `actual` with the JSON file at `expected_path`, or rewrite the
+    /// file when updating
+    pub fn check_json(&self, expected_path: &Path, actual: &impl Serialize) {
+        let normalize = self.normalize(expected_path.parent().unwrap());
+        let mut value = serde_json::to_value(actual).unwrap();
+        normalize.apply(&mut value);
+        let json = serde_json::to_string_pretty(&value).unwrap() + "\n";
+        if self.update {
+            std::fs::write(expected_path, &json).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(expected_path).unwrap_or_default();
+        let matches = if normalize.is_empty() {
+            json == expected
+        } else {
+            serde_json::from_str(&expected).is_ok_and(|mut expected: Value| {
+                normalize.apply(&mut expected);
+                expected == value
+            })
+        };
+        assert!(
+            matches,
+            "{} differs from the output; rerun with FREL_UPDATE_GOLDEN=1 if the change is intended",
+            expected_path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize() {
+        let normalize = Normalize {
+            strip_spans: true,
+            sort_keys: true,
+            round_floats: Some(2),
+        };
+        let mut value = json!({
+            "name": "count",
+            "span": { "start": 4, "end": 9 },
+            "init": { "float": 0.33333, "name_span": [1, 2] },
+            "items": [{ "b": 1, "a": 2.0, "span": null }],
+        });
+        normalize.apply(&mut value);
+        assert_eq!(
+            value.to_string(),
+            r#"{"init":{"float":0.33},"items":[{"a":2.0,"b":1}],"name":"count"}"#
+        );
+
+        // Integers are never rounded
+        let mut value = json!({ "int": 7 });
+        normalize.apply(&mut value);
+        assert_eq!(value, json!({ "int": 7 }));
+        assert!(Normalize::default().is_empty());
+    }
+
+    #[test]
+    fn test_config() {
+        let config: Config =
+            toml::from_str("[normalize]\nstrip_spans = true\nround_floats = 3\n").unwrap();
+        assert_eq!(
+            config.normalize,
+            Normalize {
+                strip_spans: true,
+                sort_keys: false,
+                round_floats: Some(3),
+            }
+        );
+        assert!(toml::from_str::<Config>("[normalize]\nstrip = true\n").is_err());
+    }
+}
@@ -0,0 +1,1396 @@
+// Lowering from the AST to the IR
+//
+// Lowering resolves and type checks the file, then walks it once, following
+// the scopes name resolution created: blueprint and backend bodies, the
+// parameter scopes of method bodies, and the loop scopes of `repeat`. Diagnostics of the analysis are dropped; they are
+// reported by `compile`.
+
+use std::collections::HashMap;
+
+use super::*;
+use crate::ast;
+use crate::semantic::{
+    builtins, loading_commands, members, resolve_with_registry, typecheck_with_registry, KeyChord,
+    ScopeGraph, ScopeId, ScopeKind, SignatureRegistry, Symbol, SymbolKind, SymbolTable, TypeQuery,
+};
+use crate::semantic::instructions::instruction_registry;
+
+/// Lower a file, resolving imports as unresolved
+pub fn lower(file: &ast::File) -> Module {
+    lower_with_registry(file, &SignatureRegistry::new())
+}
+
+/// Lower a file, resolving its imports through `registry`
+pub fn lower_with_registry(file: &ast::File, registry: &SignatureRegistry) -> Module {
+    let _profile = crate::profile::enter("lower");
+    let resolved = resolve_with_registry(file, registry);
+    let checked = typecheck_with_registry(
+        file,
+        &resolved.scopes,
+        &resolved.symbols,
+        &resolved.imports,
+        registry,
+    );
+    let lowerer = Lowerer {
+        file,
+        scopes: &resolved.scopes,
+        symbols: &resolved.symbols,
+        types: TypeQuery {
+            scopes: &resolved.scopes,
+            symbols: &resolved.symbols,
+            imports: &resolved.imports,
+            symbol_types: &checked.symbol_types,
+        },
+    };
+    let mut module = lowerer.module();
+    module.fold_constants();
+    module
+}
+
+/// Backend member made visible in a blueprint by `with`
+struct WithMember {
+    backend: String,
+    slot: Option<String>,
+    is_field: bool,
+}
+
+/// Where names are looked up while lowering an expression
+#[derive(Clone, Copy)]
+struct Env<'e> {
+    /// Innermost scope
+    scope: ScopeId,
+    /// Body scope of the enclosing declaration
+    body: ScopeId,
+    /// Members of the backends used with `with`, by name
+    members: &'e HashMap<String, WithMember>,
+}
+
+/// Error boundaries and loading placeholders of a blueprint, outside control statements
+#[derive(Default)]
+struct Nested {
+    boundaries: Vec<ErrorBoundary>,
+    loading: Vec<LoadingSlot>,
+}
+
+struct Lowerer<'a> {
+    file: &'a ast::File,
+    scopes: &'a ScopeGraph,
+    symbols: &'a SymbolTable,
+    types: TypeQuery<'a>,
+}
+
+impl Lowerer<'_> {
+    fn module(&self) -> Module {
+        let type_names = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.scope == ScopeId::ROOT && symbol.kind.is_type_definition())
+            .map(|symbol| (symbol.id, self.qualified(symbol)))
+            .collect();
+
+        Module {
+            path: self.file.module.clone(),
+            imports: self
+                .file
+                .imports
+                .iter()
+                .map(|import| Import {
+                    path: import.path.clone(),
+                    import_all: import.import_all,
+                })
+                .collect(),
+            declarations: self
+                .file
+                .declarations
+                .iter()
+                .map(|decl| self.decl(decl))
+                .collect(),
+            type_names,
+        }
+    }
+
+    fn decl(&self, decl: &ast::TopLevelDecl) -> Decl {
+        match decl {
+            ast::TopLevelDecl::Blueprint(bp) => Decl::Blueprint(Box::new(self.blueprint(bp))),
+            ast::TopLevelDecl::Backend(be) => Decl::Backend(self.backend(be)),
+            ast::TopLevelDecl::Contract(ct) => Decl::Contract(self.contract(ct)),
+            ast::TopLevelDecl::Scheme(sc) => Decl::Scheme(self.scheme(sc)),
+            ast::TopLevelDecl::Enum(en) => Decl::Enum(Enum {
+                name: en.name.clone(),
+                qualified_name: self.local_name(&en.name),
+                variants: en
+                    .variants
+                    .iter()
+                    .map(|variant| EnumVariant {
+                        name: variant.clone(),
+                        label: en.label(variant).map(str::to_string),
+                        arity: en.payload(variant).len(),
+                    })
+                    .collect(),
+            }),
+            ast::TopLevelDecl::Theme(th) => Decl::Theme(self.theme(th)),
+            ast::TopLevelDecl::Arena(ar) => Decl::Arena(Arena {
+                name: ar.name.clone(),
+                qualified_name: self.local_name(&ar.name),
+                scheme: ar.scheme_name.clone(),
+                contract: ar.contract.clone(),
+            }),
+        }
+    }
+
+    // =========================================================================
+    // Names
+    // =========================================================================
+
+    /// Qualified name of a declaration of this module
+    fn local_name(&self, name: &str) -> String {
+        format!("{}.{}", self.file.module, name)
+    }
+
+    /// Qualified name of a top-level symbol, local or imported
+    fn qualified(&self, symbol: &Symbol) -> String {
+        let module = symbol.source_module.as_deref().unwrap_or(&self.file.module);
+        format!("{}.{}", module, symbol.name)
+    }
+
+    /// Qualified name of a blueprint used at a call site
+    ///
+    /// Standard blueprints and unresolved names go to their explicit import,
+    /// the only wildcard import, or else the current module.
+    fn blueprint_name(&self, name: &str) -> String {
+        if let Some(symbol) = self.root_symbol(name) {
+            return self.qualified(symbol);
+        }
+
+        let explicit = self.file.imports.iter().find_map(|import| {
+            let (module, imported) = import.path.rsplit_once('.')?;
+            (!import.import_all && imported == name).then_some(module)
+        });
+        let wildcards: Vec<&str> = self
+            .file
+            .imports
+            .iter()
+            .filter(|import| import.import_all)
+            .map(|import| import.path.as_str())
+            .collect();
+        let module = match (explicit, wildcards.as_slice()) {
+            (Some(module), _) => module,
+            (None, [module]) => module,
+            _ => &self.file.module,
+        };
+        format!("{}.{}", module, name)
+    }
+
+    fn root_symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .lookup_local(ScopeId::ROOT, name)
+            .and_then(|id| self.symbols.get(id))
+    }
+
+    /// Body scope of a top-level declaration
+    fn body_scope(&self, name: &str) -> Option<ScopeId> {
+        self.root_symbol(name)?.body_scope
+    }
+
+    /// What `name` refers to in `env`
+    fn classify(&self, name: &str, env: Env) -> RefKind {
+        let Some(symbol) = self
+            .symbols
+            .lookup_in_scope_chain(env.scope, name, self.scopes)
+            .and_then(|id| self.symbols.get(id))
+        else {
+            return RefKind::Unresolved;
+        };
+        if symbol.scope == ScopeId::ROOT {
+            return RefKind::Decl {
+                qualified_name: self.qualified(symbol),
+            };
+        }
+        match symbol.kind {
+            SymbolKind::Parameter
+                if self
+                    .scopes
+                    .get(symbol.scope)
+                    .is_some_and(|scope| scope.kind == ScopeKind::Lambda) =>
+            {
+                RefKind::LambdaParam
+            }
+            SymbolKind::Parameter => RefKind::Param,
+            SymbolKind::LocalVar if symbol.scope == env.body => RefKind::Field,
+            SymbolKind::LocalVar => RefKind::LoopItem,
+            _ => match env.members.get(name) {
+                Some(member) if member.is_field => RefKind::BackendField {
+                    backend: member.backend.clone(),
+                    slot: member.slot.clone(),
+                },
+                Some(member) => RefKind::BackendMember {
+                    backend: member.backend.clone(),
+                    slot: member.slot.clone(),
+                },
+                None if symbol.scope == env.body => RefKind::Field,
+                None => RefKind::Unresolved,
+            },
+        }
+    }
+
+    /// Whether a called name is a built-in function: one no declaration or
+    /// import shadows
+    fn is_builtin(&self, name: &str, env: Env) -> bool {
+        builtins::lookup(name).is_some()
+            && !self.types.imports.contains_key(name)
+            && self.classify(name, env) == RefKind::Unresolved
+    }
+
+    /// The member of a built-in type `base.name` names, if `base` is a value
+    /// of one (or may be null, with `?.`)
+    fn member(&self, base: &ast::Expr, name: &str, optional: bool, env: Env) -> Option<&'static members::Member> {
+        match self.types.expr_type(base, None, env.scope) {
+            Type::Nullable(inner) if optional => members::lookup(&inner, name),
+            ty => members::lookup(&ty, name),
+        }
+    }
+
+    // =========================================================================
+    // Blueprints
+    // =========================================================================
+
+    fn blueprint(&self, bp: &ast::Blueprint) -> Blueprint {
+        let scope = self.body_scope(&bp.name).unwrap_or(ScopeId::ROOT);
+        let members = self.with_members(bp);
+        let env = Env {
+            scope,
+            body: scope,
+            members: &members,
+        };
+        let qualified_name = self.local_name(&bp.name);
+
+        let fields = bp
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::BlueprintStmt::LocalDecl(decl) => {
+                    let ty = self.types.resolve_type(&decl.type_expr, scope);
+                    let init = self.expr(&decl.init, Some(&ty), env);
+                    Some(Field {
+                        name: decl.name.clone(),
+                        ty,
+                        init,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut call_sites = Vec::new();
+        let mut top_children = Vec::new();
+        let mut nested = Nested::default();
+        self.call_sites(
+            &bp.body,
+            "",
+            env,
+            Some(&mut top_children),
+            Some(&mut nested),
+            &mut call_sites,
+        );
+
+        // Repeated paths get a `#n` suffix
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for call_site in &mut call_sites {
+            let id = format!("{}/{}", qualified_name, call_site.id);
+            let count = seen.entry(id.clone()).or_insert(0);
+            *count += 1;
+            call_site.id = if *count == 1 {
+                id
+            } else {
+                format!("{}#{}", id, count)
+            };
+        }
+
+        let backends = owned_backends(bp);
+        let shortcuts = bp
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::BlueprintStmt::Shortcut(shortcut) => {
+                    self.shortcut(bp, shortcut, &backends, env)
+                }
+                _ => None,
+            })
+            .collect();
+
+        // Context values declared inside fragments are reported by semantic analysis
+        let consumes = bp
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::BlueprintStmt::Consume(consume) => Some(consume.name.clone()),
+                _ => None,
+            })
+            .collect();
+        let provides = bp
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::BlueprintStmt::Provide(provide) => Some(Provide {
+                    name: provide.name.clone(),
+                    value: self.expr(&provide.value, None, env),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Blueprint {
+            name: bp.name.clone(),
+            qualified_name,
+            params: bp.params.iter().map(|p| self.param(p, env)).collect(),
+            backends,
+            backend_slots: self.backend_slots(bp, scope),
+            fields,
+            call_sites,
+            top_children,
+            shortcuts,
+            consumes,
+            provides,
+            boundaries: nested.boundaries,
+            loading: nested.loading,
+        }
+    }
+
+    /// A shortcut with a valid chord; semantic analysis reports the others
+    fn shortcut(
+        &self,
+        bp: &ast::Blueprint,
+        shortcut: &ast::Shortcut,
+        backends: &[BackendUse],
+        env: Env,
+    ) -> Option<Shortcut> {
+        let chord = KeyChord::parse(&shortcut.chord).ok()?;
+        let actions = shortcut
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::HandlerStmt::Assignment { name, value } => match self.classify(name, env) {
+                    RefKind::Param | RefKind::Field | RefKind::BackendField { .. } => {
+                        Some(Action::Assign {
+                            name: name.clone(),
+                            value: self.expr(value, None, env),
+                        })
+                    }
+                    _ => None,
+                },
+                ast::HandlerStmt::CommandCall { name, args } => match self.classify(name, env) {
+                    RefKind::BackendMember { backend, slot } => Some(Action::Command {
+                        receiver: receiver(bp, backends, &backend, slot)?,
+                        name: name.clone(),
+                        args: args.iter().map(|arg| self.expr(arg, None, env)).collect(),
+                    }),
+                    _ => None,
+                },
+            })
+            .collect();
+        Some(Shortcut {
+            chord: chord.to_string(),
+            actions,
+        })
+    }
+
+    fn param(&self, param: &ast::Parameter, env: Env) -> Param {
+        let ty = self.types.resolve_type(&param.type_expr, env.scope);
+        let default = param
+            .default
+            .as_ref()
+            .map(|default| self.expr(default, Some(&ty), env));
+        Param {
+            name: param.name.clone(),
+            ty,
+            default,
+        }
+    }
+
+    /// Slots of a blueprint a parent can inject a backend instance into
+    fn backend_slots(&self, bp: &ast::Blueprint, scope: ScopeId) -> Vec<BackendSlot> {
+        let params = bp.params.iter().filter_map(|param| {
+            match self.types.resolve_type(&param.type_expr, scope) {
+                Type::Backend(id) => Some(BackendSlot {
+                    name: param.name.clone(),
+                    backend: self.symbols.get(id)?.name.clone(),
+                }),
+                _ => None,
+            }
+        });
+        let withs = bp.body.iter().filter_map(|stmt| match stmt {
+            ast::BlueprintStmt::With(ast::WithClause {
+                name: Some(name),
+                backend,
+                ..
+            }) => Some(BackendSlot {
+                name: name.clone(),
+                backend: backend.clone(),
+            }),
+            _ => None,
+        });
+        params.chain(withs).collect()
+    }
+
+    /// Members of the backends a blueprint uses with `with`
+    ///
+    /// `with param` uses the backend the parameter is typed as.
+    fn with_members(&self, bp: &ast::Blueprint) -> HashMap<String, WithMember> {
+        let mut members = HashMap::new();
+        for stmt in &bp.body {
+            let ast::BlueprintStmt::With(with) = stmt else {
+                continue;
+            };
+            let param_type = bp.params.iter().find_map(|p| match &p.type_expr {
+                ast::TypeExpr::Named(ty) if with.name.is_none() && p.name == with.backend => {
+                    Some(ty)
+                }
+                _ => None,
+            });
+            let backend = param_type.unwrap_or(&with.backend);
+            let Some(scope) = self.body_scope(backend) else {
+                continue;
+            };
+            for member in self.symbols.symbols_in_scope(scope) {
+                members
+                    .entry(member.name.clone())
+                    .or_insert_with(|| WithMember {
+                        backend: backend.clone(),
+                        slot: with.name.clone(),
+                        is_field: matches!(
+                            member.kind,
+                            SymbolKind::Field | SymbolKind::VirtualField
+                        ),
+                    });
+            }
+        }
+        members
+    }
+
+    /// Call sites of `stmts`, in order; `prefix` is their path through control statements
+    ///
+    /// Indices of direct children are added to `top_children`, error
+    /// boundaries and loading placeholders to `nested` when given.
+    fn call_sites(
+        &self,
+        stmts: &[ast::BlueprintStmt],
+        prefix: &str,
+        env: Env,
+        mut top_children: Option<&mut Vec<usize>>,
+        mut nested: Option<&mut Nested>,
+        result: &mut Vec<CallSite>,
+    ) {
+        let branch = |segment: &str, stmt: &ast::BlueprintStmt, result: &mut Vec<CallSite>| {
+            if let ast::BlueprintStmt::FragmentCreation(fc) = stmt {
+                let path = format!("{}{}{}", prefix, segment, fc.name);
+                result.push(self.call_site(fc, path, env));
+            }
+        };
+
+        for stmt in stmts {
+            match stmt {
+                ast::BlueprintStmt::FragmentCreation(fc) => {
+                    if let Some(top_children) = top_children.as_deref_mut() {
+                        top_children.push(result.len());
+                    }
+                    let path = format!("{}{}", prefix, fc.name);
+                    result.push(self.call_site(fc, path, env));
+                }
+                ast::BlueprintStmt::Control(ast::ControlStmt::When {
+                    then_stmt,
+                    else_stmt,
+                    ..
+                }) => {
+                    branch("when/", then_stmt, result);
+                    if let Some(else_stmt) = else_stmt {
+                        branch("else/", else_stmt, result);
+                    }
+                }
+                ast::BlueprintStmt::Control(ast::ControlStmt::Repeat {
+                    item, body, ..
+                }) => {
+                    // The loop variables are defined in a child scope of the loop
+                    let scope = item
+                        .bindings()
+                        .first()
+                        .and_then(|name| self.symbols.lookup_in_children(env.scope, name, self.scopes))
+                        .map_or(env.scope, |(_, scope)| scope);
+                    let env = Env { scope, ..env };
+                    self.call_sites(body, &format!("{}repeat/", prefix), env, None, None, result);
+                }
+                ast::BlueprintStmt::Control(ast::ControlStmt::Select {
+                    branches,
+                    else_branch,
+                    ..
+                }) => {
+                    for select in branches {
+                        // Payload bindings are defined in a scope at the branch's span
+                        let scope = self
+                            .scopes
+                            .child_at(env.scope, ScopeKind::Block, select.span)
+                            .unwrap_or(env.scope);
+                        if let ast::BlueprintStmt::FragmentCreation(fc) = select.body.as_ref() {
+                            let path = format!("{}select/{}", prefix, fc.name);
+                            result.push(self.call_site(fc, path, Env { scope, ..env }));
+                        }
+                    }
+                    if let Some(else_branch) = else_branch {
+                        branch("select/else/", else_branch, result);
+                    }
+                }
+                ast::BlueprintStmt::Control(ast::ControlStmt::OnError {
+                    body,
+                    error_name,
+                    fallback,
+                    ..
+                }) => {
+                    let mut boundary = ErrorBoundary {
+                        error: error_name.clone(),
+                        ..ErrorBoundary::default()
+                    };
+                    let body_prefix = format!("{}on_error/", prefix);
+                    self.call_sites(body, &body_prefix, env, Some(&mut boundary.body), None, result);
+
+                    // The error message is defined in a child scope of the fallback
+                    let scope = error_name
+                        .as_ref()
+                        .and_then(|name| self.symbols.lookup_in_children(env.scope, name, self.scopes))
+                        .map_or(env.scope, |(_, scope)| scope);
+                    let fallback_env = Env { scope, ..env };
+                    let fallback_prefix = format!("{}on_error/fallback/", prefix);
+                    if let Some(stmts) = fallback {
+                        self.call_sites(
+                            stmts,
+                            &fallback_prefix,
+                            fallback_env,
+                            Some(&mut boundary.fallback),
+                            None,
+                            result,
+                        );
+                    }
+                    if let Some(nested) = nested.as_deref_mut() {
+                        nested.boundaries.push(boundary);
+                    }
+                }
+                ast::BlueprintStmt::Control(ast::ControlStmt::Loading { body, .. }) => {
+                    // Calls of names other than backend commands are reported by name resolution
+                    let commands = loading_commands(stmts)
+                        .into_iter()
+                        .filter(|name| {
+                            matches!(self.classify(name, env), RefKind::BackendMember { .. })
+                        })
+                        .map(str::to_string)
+                        .collect();
+                    let mut slot = LoadingSlot {
+                        commands,
+                        ..LoadingSlot::default()
+                    };
+                    let body_prefix = format!("{}loading/", prefix);
+                    self.call_sites(body, &body_prefix, env, Some(&mut slot.body), None, result);
+                    if let Some(nested) = nested.as_deref_mut() {
+                        nested.loading.push(slot);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn call_site(&self, fc: &ast::FragmentCreation, path: String, env: Env) -> CallSite {
+        let callee = self.file.declarations.iter().find_map(|decl| match decl {
+            ast::TopLevelDecl::Blueprint(bp) if bp.name == fc.name => Some(bp),
+            _ => None,
+        });
+        let slots = callee
+            .map(|bp| self.backend_slots(bp, self.body_scope(&bp.name).unwrap_or(ScopeId::ROOT)))
+            .unwrap_or_default();
+
+        let args = fc
+            .args
+            .iter()
+            .map(|arg| Arg {
+                name: arg.name.clone(),
+                value: self.expr(&arg.value, None, env),
+                injected_backend: slots
+                    .iter()
+                    .find(|slot| Some(&slot.name) == arg.name.as_ref())
+                    .map(|slot| slot.backend.clone()),
+            })
+            .collect();
+
+        let body = match &fc.body {
+            Some(ast::FragmentBody::Default(stmts)) => stmts.as_slice(),
+            _ => &[],
+        };
+        let content = body.iter().find_map(|stmt| match stmt {
+            ast::BlueprintStmt::ContentExpr(expr) => Some(self.expr(expr, None, env)),
+            _ => None,
+        });
+
+        // Instructions are written inside the fragment's body or as postfix
+        let inner = body.iter().filter_map(|stmt| match stmt {
+            ast::BlueprintStmt::Instruction(instr) => Some(instr),
+            _ => None,
+        });
+        let postfix = fc.postfix.iter().filter_map(|item| match item {
+            ast::PostfixItem::Instruction(instr) => Some(instr),
+            _ => None,
+        });
+        let instructions: Vec<_> = inner.chain(postfix).collect();
+
+        let virtualize = instructions.iter().find_map(|instr| match instr {
+            ast::InstructionExpr::Simple(inst) if inst.name == "virtualize" => Some(
+                inst.params
+                    .iter()
+                    .map(|(name, value)| (name.clone(), self.expr(value, None, env)))
+                    .collect(),
+            ),
+            _ => None,
+        });
+
+        let portal = instructions.iter().find_map(|instr| match instr {
+            ast::InstructionExpr::Simple(inst) if inst.name == "portal" => Some(
+                inst.params
+                    .first()
+                    .map(|(_, value)| self.expr(value, None, env))
+                    .unwrap_or_else(default_overlay),
+            ),
+            ast::InstructionExpr::Reference(ast::Expr::Identifier(name)) if name == "portal" => {
+                Some(default_overlay())
+            }
+            _ => None,
+        });
+
+        let mut focus = Focus::default();
+        for instr in &instructions {
+            match instr {
+                ast::InstructionExpr::Simple(inst) => match inst.name.as_str() {
+                    "tab_index" => {
+                        focus.tab_index =
+                            inst.params.first().map(|(_, value)| self.expr(value, None, env));
+                    }
+                    "autofocus" => focus.autofocus = true,
+                    "focus_group" => {
+                        // Aliases generate the code of the keyword they stand for
+                        let group = inst.params.first().and_then(|(param, value)| match value {
+                            ast::Expr::Identifier(group) => {
+                                instruction_registry().canonical_keyword(&inst.name, param, group)
+                            }
+                            _ => None,
+                        });
+                        focus.group = match group {
+                            Some("trap") => Some(FocusGroup::Trap),
+                            Some("arrows") => Some(FocusGroup::Arrows),
+                            _ => focus.group,
+                        };
+                    }
+                    _ => {}
+                },
+                // Instructions without parameters: `.. autofocus`
+                ast::InstructionExpr::Reference(ast::Expr::Identifier(name)) => {
+                    match name.as_str() {
+                        "autofocus" => focus.autofocus = true,
+                        "focus_trap" => focus.group = Some(FocusGroup::Trap),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        CallSite {
+            id: path,
+            blueprint: self.blueprint_name(&fc.name),
+            args,
+            content,
+            virtualize,
+            focus,
+            portal,
+        }
+    }
+
+    // =========================================================================
+    // Other declarations
+    // =========================================================================
+
+    /// Environment of a declaration body without `with` members
+    fn decl_env<'e>(&self, name: &str, members: &'e HashMap<String, WithMember>) -> Env<'e> {
+        let scope = self.body_scope(name).unwrap_or(ScopeId::ROOT);
+        Env {
+            scope,
+            body: scope,
+            members,
+        }
+    }
+
+    fn backend(&self, be: &ast::Backend) -> Backend {
+        let members = HashMap::new();
+        let env = self.decl_env(&be.name, &members);
+
+        let mut fields = Vec::new();
+        let mut commands = Vec::new();
+        let mut methods = Vec::new();
+        for member in &be.members {
+            match member {
+                ast::BackendMember::Field(field) => {
+                    let ty = self.types.resolve_type(&field.type_expr, env.scope);
+                    let init = field
+                        .init
+                        .as_ref()
+                        .map(|init| self.expr(init, Some(&ty), env));
+                    fields.push(BackendField {
+                        name: field.name.clone(),
+                        ty,
+                        init,
+                        undoable: be.undoable || field.undoable,
+                    });
+                }
+                ast::BackendMember::Command(cmd) => {
+                    // Typed by the type checker, which skips gated results
+                    let result = self
+                        .symbols
+                        .lookup_local(env.scope, &cmd.name)
+                        .and_then(|id| self.types.symbol_types.get(&id));
+                    let result = match result {
+                        Some(Type::Function { ret, .. }) => (**ret).clone(),
+                        _ => Type::Unit,
+                    };
+                    commands.push(Command {
+                        name: cmd.name.clone(),
+                        params: cmd.params.iter().map(|p| self.param(p, env)).collect(),
+                        result,
+                        policy: cmd.policy,
+                    })
+                }
+                ast::BackendMember::Method(method) => {
+                    let result = self.types.resolve_type(&method.return_type, env.scope);
+                    // The body reads the parameters from the scope the
+                    // resolver created for them
+                    let params_scope = self
+                        .scopes
+                        .child_at(env.scope, ScopeKind::Parameters, method.span);
+                    let body_env = Env {
+                        scope: params_scope.unwrap_or(env.scope),
+                        ..env
+                    };
+                    let body = method
+                        .body
+                        .as_ref()
+                        .map(|body| self.expr(body, Some(&result), body_env));
+                    methods.push(BackendMethod {
+                        name: method.name.clone(),
+                        params: method.params.iter().map(|p| self.param(p, env)).collect(),
+                        result,
+                        body,
+                    })
+                }
+                ast::BackendMember::Include(_) => {}
+            }
+        }
+
+        Backend {
+            name: be.name.clone(),
+            qualified_name: self.local_name(&be.name),
+            lifetime: be.lifetime,
+            fields,
+            commands,
+            methods,
+        }
+    }
+
+    fn contract(&self, ct: &ast::Contract) -> Contract {
+        let members = HashMap::new();
+        let env = self.decl_env(&ct.name, &members);
+
+        let methods = ct
+            .methods
+            .iter()
+            .map(|method| {
+                let result = method
+                    .return_type
+                    .as_ref()
+                    .map(|ty| self.types.resolve_type(ty, env.scope))
+                    .unwrap_or(Type::Unit);
+                let remote_name = method.binding("name").unwrap_or(&method.name);
+                // Unbound methods are posted to their remote name
+                let route = match method.route() {
+                    Some((verb, path)) => HttpRoute {
+                        method: verb.to_uppercase(),
+                        path: path.to_string(),
+                    },
+                    None => HttpRoute {
+                        method: "POST".to_string(),
+                        path: format!("/{}", remote_name),
+                    },
+                };
+                ContractMethod {
+                    name: method.name.clone(),
+                    params: method.params.iter().map(|p| self.param(p, env)).collect(),
+                    result,
+                    remote_name: remote_name.to_string(),
+                    route,
+                }
+            })
+            .collect();
+
+        Contract {
+            name: ct.name.clone(),
+            qualified_name: self.local_name(&ct.name),
+            methods,
+        }
+    }
+
+    fn scheme(&self, sc: &ast::Scheme) -> Scheme {
+        let scope = self.body_scope(&sc.name).unwrap_or(ScopeId::ROOT);
+        Scheme {
+            name: sc.name.clone(),
+            qualified_name: self.local_name(&sc.name),
+            fields: sc
+                .members
+                .iter()
+                .filter_map(|member| match member {
+                    ast::SchemeMember::Field(field) => Some(SchemeField {
+                        name: field.name.clone(),
+                        ty: self.types.resolve_type(&field.type_expr, scope),
+                        rules: field_rules(&field.instructions),
+                    }),
+                    ast::SchemeMember::Virtual(_) => None,
+                })
+                .collect(),
+        }
+    }
+
+    fn theme(&self, th: &ast::Theme) -> Theme {
+        let members = HashMap::new();
+        let env = self.decl_env(&th.name, &members);
+
+        let mut fields = Vec::new();
+        let mut variants = Vec::new();
+        for member in &th.members {
+            match member {
+                ast::ThemeMember::Field(field) => {
+                    let ty = self.types.resolve_type(&field.type_expr, env.scope);
+                    let init = field
+                        .init
+                        .as_ref()
+                        .map(|init| self.expr(init, Some(&ty), env));
+                    fields.push(ThemeField {
+                        name: field.name.clone(),
+                        ty,
+                        is_asset: field.is_asset,
+                        init,
+                    });
+                }
+                ast::ThemeMember::Variant(variant) => variants.push(ThemeVariant {
+                    name: variant.name.clone(),
+                    overrides: variant
+                        .overrides
+                        .iter()
+                        .map(|(name, expr)| (name.clone(), self.expr(expr, None, env)))
+                        .collect(),
+                }),
+                ast::ThemeMember::Include(_) | ast::ThemeMember::InstructionSet(_) => {}
+            }
+        }
+
+        Theme {
+            name: th.name.clone(),
+            qualified_name: self.local_name(&th.name),
+            fields,
+            variants,
+        }
+    }
+
+    // =========================================================================
+    // Expressions
+    // =========================================================================
+
+    fn expr(&self, expr: &ast::Expr, expected: Option<&Type>, env: Env) -> Expr {
+        let ty = self.types.expr_type(expr, expected, env.scope);
+        let lower = |expr: &ast::Expr| Box::new(self.expr(expr, None, env));
+
+        let kind = match expr {
+            ast::Expr::Null => ExprKind::Null,
+            ast::Expr::Bool(b) => ExprKind::Bool(*b),
+            ast::Expr::Int(i) => ExprKind::Int(*i),
+            ast::Expr::Float(f) => ExprKind::Float(*f),
+            ast::Expr::Color(c) => ExprKind::Color(*c),
+            ast::Expr::String(s) => ExprKind::String(s.clone()),
+            ast::Expr::StringTemplate(elements) => ExprKind::Template(
+                elements
+                    .iter()
+                    .map(|element| match element {
+                        ast::TemplateElement::Text(text) => TemplatePart::Text(text.clone()),
+                        ast::TemplateElement::Interpolation(expr) => {
+                            TemplatePart::Interpolation(self.expr(expr, None, env))
+                        }
+                    })
+                    .collect(),
+            ),
+            ast::Expr::List(items) => {
+                let element = expected.and_then(Type::element_type);
+                ExprKind::List(
+                    items
+                        .iter()
+                        .map(|item| self.expr(item, element, env))
+                        .collect(),
+                )
+            }
+            ast::Expr::Object(fields) => ExprKind::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), self.expr(value, None, env)))
+                    .collect(),
+            ),
+            ast::Expr::Identifier(name) => ExprKind::Read(Ref {
+                name: name.clone(),
+                kind: self.classify(name, env),
+            }),
+            ast::Expr::QualifiedName(parts) => ExprKind::Path(parts.clone()),
+            ast::Expr::Binary { op, left, right } => ExprKind::Binary {
+                op: *op,
+                left: lower(left),
+                right: lower(right),
+            },
+            ast::Expr::Unary { op, expr } => ExprKind::Unary {
+                op: *op,
+                expr: lower(expr),
+            },
+            ast::Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => ExprKind::Ternary {
+                condition: lower(condition),
+                then_expr: lower(then_expr),
+                else_expr: lower(else_expr),
+            },
+            ast::Expr::FieldAccess { base, field, .. } | ast::Expr::OptionalChain { base, field } => {
+                let optional = matches!(expr, ast::Expr::OptionalChain { .. });
+                if self.member(base, field, optional, env).is_some_and(|m| !m.is_method()) {
+                    ExprKind::Member {
+                        base: lower(base),
+                        name: field.clone(),
+                        args: None,
+                        optional,
+                    }
+                } else {
+                    ExprKind::Field {
+                        base: lower(base),
+                        field: field.clone(),
+                        optional,
+                    }
+                }
+            }
+            ast::Expr::Call { callee, args } => {
+                let args = args.iter().map(|arg| self.expr(arg, None, env)).collect();
+                let optional = matches!(callee.as_ref(), ast::Expr::OptionalChain { .. });
+                match callee.as_ref() {
+                    ast::Expr::Identifier(name) if self.is_builtin(name, env) => {
+                        ExprKind::BuiltinCall {
+                            name: name.clone(),
+                            args,
+                        }
+                    }
+                    ast::Expr::FieldAccess { base, field, .. } | ast::Expr::OptionalChain { base, field }
+                        if self.member(base, field, optional, env).is_some_and(members::Member::is_method) =>
+                    {
+                        ExprKind::Member {
+                            base: lower(base),
+                            name: field.clone(),
+                            args: Some(args),
+                            optional,
+                        }
+                    }
+                    _ => ExprKind::Call {
+                        callee: lower(callee),
+                        args,
+                    },
+                }
+            }
+            ast::Expr::Range {
+                start,
+                end,
+                inclusive,
+            } => ExprKind::Range {
+                start: lower(start),
+                end: lower(end),
+                inclusive: *inclusive,
+            },
+            ast::Expr::Lambda { params, body, span } => {
+                let scope = self.scopes.child_at(env.scope, ScopeKind::Lambda, *span);
+                let body_env = Env {
+                    scope: scope.unwrap_or(env.scope),
+                    ..env
+                };
+                let ret = match &ty {
+                    Type::Function { ret, .. } => Some(ret.as_ref()),
+                    _ => None,
+                };
+                ExprKind::Lambda {
+                    params: params.iter().map(|p| p.name.clone()).collect(),
+                    body: Box::new(self.expr(body, ret, body_env)),
+                }
+            }
+        };
+        Expr { kind, ty }
+    }
+}
+
+/// Backend instances a blueprint owns, as (slot, backend)
+///
+/// `with param` is skipped: the parent passes the instance.
+fn owned_backends(bp: &ast::Blueprint) -> Vec<BackendUse> {
+    bp.body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ast::BlueprintStmt::With(ast::WithClause {
+                name: None,
+                backend,
+                ..
+            }) if bp.params.iter().any(|p| &p.name == backend) => None,
+            ast::BlueprintStmt::With(with) => Some(BackendUse {
+                slot: with.name.clone(),
+                backend: with.backend.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Instance a command of a `with` backend is invoked on
+///
+/// Unnamed instances are owned by the blueprint, or passed in the parameter
+/// of `with param`.
+fn receiver(
+    bp: &ast::Blueprint,
+    backends: &[BackendUse],
+    backend: &str,
+    slot: Option<String>,
+) -> Option<Receiver> {
+    if let Some(slot) = slot {
+        return Some(Receiver::Field(slot));
+    }
+    if let Some(index) = backends
+        .iter()
+        .position(|owned| owned.slot.is_none() && owned.backend == backend)
+    {
+        return Some(Receiver::Owned(index));
+    }
+    bp.params
+        .iter()
+        .find(|param| {
+            matches!(&param.type_expr, ast::TypeExpr::Named(ty) if ty == backend)
+                && bp.body.iter().any(|stmt| {
+                    matches!(stmt, ast::BlueprintStmt::With(with)
+                        if with.name.is_none() && with.backend == param.name)
+                })
+        })
+        .map(|param| Receiver::Field(param.name.clone()))
+}
+
+/// Container name of a bare `portal`
+fn default_overlay() -> Expr {
+    Expr {
+        kind: ExprKind::String(DEFAULT_OVERLAY.to_string()),
+        ty: Type::String,
+    }
+}
+
+/// Validation rules of a scheme field's instructions
+fn field_rules(instructions: &[ast::FieldInstruction]) -> Vec<FieldRule> {
+    let mut rules = Vec::new();
+    for instr in instructions {
+        let param = |name: &str| {
+            instr
+                .params
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value)
+        };
+        let number = |name: &str| param(name).and_then(literal_number);
+        let count = |name: &str| number(name).filter(|n| *n >= 0.0).map(|n| n as u64);
+        match instr.name.as_str() {
+            "min_len" => rules.extend(count("value").map(FieldRule::MinLen)),
+            "max_len" => rules.extend(count("value").map(FieldRule::MaxLen)),
+            "blank" => {
+                if let Some(ast::Expr::Bool(false)) = param("value") {
+                    rules.push(FieldRule::NotBlank);
+                }
+            }
+            "pattern" => {
+                if let Some(ast::Expr::String(pattern)) = param("value") {
+                    rules.push(FieldRule::Pattern(pattern.clone()));
+                }
+            }
+            "min" => rules.extend(number("value").map(FieldRule::Min)),
+            "max" => rules.extend(number("value").map(FieldRule::Max)),
+            "range" => {
+                rules.extend(number("min").map(FieldRule::Min));
+                rules.extend(number("max").map(FieldRule::Max));
+            }
+            "precision" => rules.extend(count("value").map(FieldRule::Precision)),
+            "min_items" => rules.extend(count("value").map(FieldRule::MinItems)),
+            "max_items" => rules.extend(count("value").map(FieldRule::MaxItems)),
+            _ => {}
+        }
+    }
+    rules
+}
+
+fn literal_number(expr: &ast::Expr) -> Option<f64> {
+    match expr {
+        ast::Expr::Int(n) => Some(*n as f64),
+        ast::Expr::Float(n) => Some(*n),
+        ast::Expr::Unary {
+            op: ast::UnaryOp::Neg,
+            expr,
+        } => literal_number(expr).map(|n| -n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower_source(source: &str) -> Module {
+        lower(&crate::parse_file(source).file.unwrap())
+    }
+
+    fn blueprint<'m>(module: &'m Module, name: &str) -> &'m Blueprint {
+        module.blueprints().find(|bp| bp.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_lower_references() {
+        let module = lower_source(
+            r#"module app
+
+backend Catalog {
+    items : List<String> = []
+    filter : String = ""
+}
+
+blueprint Row(label : String) {
+    text { label }
+}
+
+blueprint Main {
+    with Catalog
+    prefix : String = ">"
+    repeat on items { item ->
+        Row(label = prefix + item + filter)
+    }
+}
+"#,
+        );
+        let main = blueprint(&module, "Main");
+        assert_eq!(main.qualified_name, "app.Main");
+        assert_eq!(
+            main.backends,
+            [BackendUse {
+                slot: None,
+                backend: "Catalog".to_string()
+            }]
+        );
+
+        let row = &main.call_sites[0];
+        assert_eq!(row.id, "app.Main/repeat/Row");
+        assert_eq!(row.blueprint, "app.Row");
+        assert!(main.top_children.is_empty());
+
+        let label = &row.args[0].value;
+        assert_eq!(label.ty, Type::String);
+        let kinds: Vec<(&str, &RefKind)> =
+            label.reads().map(|r| (r.name.as_str(), &r.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("prefix", &RefKind::Field),
+                ("item", &RefKind::LoopItem),
+                (
+                    "filter",
+                    &RefKind::BackendField {
+                        backend: "Catalog".to_string(),
+                        slot: None
+                    }
+                ),
+            ]
+        );
+        assert_eq!(label.dependencies(), ["filter", "item", "prefix"]);
+    }
+
+    #[test]
+    fn test_lower_backend_injection() {
+        let module = lower_source(
+            r#"module app
+
+singleton backend Session {
+    user : String = ""
+}
+
+blueprint Header(session : Session) {
+    with session
+    text { user }
+}
+
+blueprint Main {
+    with shared : Session
+    Header(session = shared)
+}
+"#,
+        );
+        let header = blueprint(&module, "Header");
+        assert!(header.backends.is_empty());
+        assert_eq!(header.backend_slot("session"), Some("Session"));
+        let Some(ExprKind::Read(user)) = header.call_sites[0].content.as_ref().map(|c| &c.kind)
+        else {
+            panic!("expected a read");
+        };
+        assert_eq!(
+            user.kind,
+            RefKind::BackendField {
+                backend: "Session".to_string(),
+                slot: None
+            }
+        );
+
+        let main = blueprint(&module, "Main");
+        assert_eq!(main.backend_slot("shared"), Some("Session"));
+        let arg = &main.call_sites[0].args[0];
+        assert_eq!(arg.injected_backend.as_deref(), Some("Session"));
+        let Type::Backend(session) = arg.value.ty else {
+            panic!("expected a backend, found {}", arg.value.ty);
+        };
+        assert_eq!(module.type_name(session), Some("app.Session"));
+    }
+
+    #[test]
+    fn test_lower_command_results() {
+        let source = "module app\n\nbackend Users {\n    command load(id : i32) : String\n    command reset()\n}\n";
+        let features = [crate::Experimental::CommandResults].into_iter().collect();
+        let file =
+            crate::parse_file_with_features(source, "app.frel", crate::Edition::LATEST, features)
+                .file
+                .unwrap();
+        let module = lower(&file);
+        let Decl::Backend(users) = &module.declarations[0] else {
+            panic!("expected backend");
+        };
+        assert_eq!(users.commands[0].result, Type::String);
+        assert_eq!(users.commands[1].result, Type::Unit);
+
+        // Without the gate the result is reported by the parser and not typed
+        let module = lower_source(source);
+        let Decl::Backend(users) = &module.declarations[0] else {
+            panic!("expected backend");
+        };
+        assert_eq!(users.commands[0].result, Type::Unit);
+    }
+
+    #[test]
+    fn test_lower_contract_routes() {
+        let module = lower_source(
+            "module app\n\ncontract Users {\n    get_user(id : u32) : String .. get { \"/users/{id}\" }\n    save_user(name : String) .. name { \"saveUser\" }\n}\n",
+        );
+        let Decl::Contract(users) = &module.declarations[0] else {
+            panic!("expected contract");
+        };
+        let get_user = &users.methods[0];
+        assert_eq!(get_user.result, Type::String);
+        assert_eq!(get_user.params[0].ty, Type::U32);
+        assert_eq!(get_user.remote_name, "get_user");
+        assert_eq!(
+            get_user.route,
+            HttpRoute {
+                method: "GET".to_string(),
+                path: "/users/{id}".to_string(),
+            }
+        );
+
+        // Unbound methods are posted to their remote name
+        let save_user = &users.methods[1];
+        assert_eq!(save_user.result, Type::Unit);
+        assert_eq!(save_user.remote_name, "saveUser");
+        assert_eq!(save_user.route.method, "POST");
+        assert_eq!(save_user.route.path, "/saveUser");
+    }
+
+    #[test]
+    fn test_lower_scheme_rules() {
+        let module = lower_source(
+            "module app\n\nscheme Product {\n    name : String .. min_len { 3 } .. blank { false } .. multiline { true }\n    price : Decimal .. range { min: 0.01 max: 99999.99 } .. precision { 2 }\n    delta : i32 .. min { -5 }\n}\n",
+        );
+        let Decl::Scheme(product) = &module.declarations[0] else {
+            panic!("expected scheme");
+        };
+        let rules: Vec<&[FieldRule]> = product.fields.iter().map(|f| f.rules.as_slice()).collect();
+        assert_eq!(
+            rules,
+            [
+                &[FieldRule::MinLen(3), FieldRule::NotBlank][..],
+                &[
+                    FieldRule::Min(0.01),
+                    FieldRule::Max(99999.99),
+                    FieldRule::Precision(2)
+                ][..],
+                &[FieldRule::Min(-5.0)][..],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let module = lower_source(
+            r#"module app
+
+theme Spacing {
+    base : i32 = 4
+    large : i32 = base * 4
+    label : String = "gap ${base * 2}px"
+    tight : i32 = base - 1
+    wide : i32 = tight * 2
+    variant Dense {
+        tight = base / 2
+    }
+}
+
+blueprint Main(count : i32) {
+    day : i32 = 60 * 60 * 24
+    width : String = "${1.5}px"
+    total : i32 = count + 2 * 3
+}
+"#,
+        );
+        let Decl::Theme(spacing) = &module.declarations[0] else {
+            panic!("expected theme");
+        };
+        let inits: Vec<&ExprKind> = spacing
+            .fields
+            .iter()
+            .map(|field| &field.init.as_ref().unwrap().kind)
+            .collect();
+        assert_eq!(
+            inits[..4],
+            [
+                &ExprKind::Int(4),
+                &ExprKind::Int(16),
+                &ExprKind::String("gap 8px".to_string()),
+                &ExprKind::Int(3),
+            ]
+        );
+        // Dense overrides `tight`, so `wide` reads it at runtime
+        assert!(matches!(inits[4], ExprKind::Binary { .. }));
+        assert_eq!(spacing.variants[0].overrides[0].1.kind, ExprKind::Int(2));
+
+        let main = blueprint(&module, "Main");
+        let inits: Vec<&ExprKind> = main.fields.iter().map(|field| &field.init.kind).collect();
+        assert_eq!(inits[0], &ExprKind::Int(86400));
+        assert!(matches!(inits[1], ExprKind::Template(_)));
+        let ExprKind::Binary { right, .. } = inits[2] else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(right.kind, ExprKind::Int(6));
+    }
+}
@@ -0,0 +1,953 @@
+// Typed intermediate representation for code generation
+//
+// The IR is lowered from a file's AST after name resolution and type
+// checking, so code generation plugins don't resolve anything themselves:
+//
+// - References say what they read: a parameter, a local field, a loop item,
+//   a lambda parameter, a field of a backend instance or a declaration. Call
+//   sites and declarations carry qualified names.
+// - Expressions and declared members carry their types.
+// - Sugar is expanded: `with` becomes the backend instances a blueprint owns,
+//   the slots a parent can inject into and backend field references. Call
+//   sites are flattened out of control statements, with their content,
+//   `virtualize` options and focus instructions lifted onto them.
+// - Constant expressions are folded (`Module::fold_constants`): `60 * 60`,
+//   `"${2 * 8}px"` and theme fields computed from other theme fields are
+//   lowered as the literals of their values.
+//
+// Lowering never fails: a name that doesn't resolve is lowered as
+// `RefKind::Unresolved` with an error type, so files with errors still lower.
+
+mod lower;
+
+pub use lower::{lower, lower_with_registry};
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BackendLifetime, BinaryOp, CommandPolicy, UnaryOp};
+use crate::semantic::const_eval::{eval_binary, eval_unary};
+use crate::semantic::{builtins, ConstValue, SymbolId, ThemeMemberKind, Type, UnusedMember};
+
+/// A lowered module file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// Module path, e.g. `app.editor`
+    pub path: String,
+    pub imports: Vec<Import>,
+    pub declarations: Vec<Decl>,
+    /// Qualified names of the declarations referred to by `Type`s in this module
+    pub type_names: HashMap<SymbolId, String>,
+}
+
+impl Module {
+    /// Qualified name of the declaration a composite type refers to
+    pub fn type_name(&self, id: SymbolId) -> Option<&str> {
+        self.type_names.get(&id).map(String::as_str)
+    }
+
+    /// Blueprints declared in the module
+    pub fn blueprints(&self) -> impl Iterator<Item = &Blueprint> {
+        self.declarations.iter().filter_map(|decl| match decl {
+            Decl::Blueprint(blueprint) => Some(blueprint.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// Remove unused theme fields and variants, and the variant overrides of
+    /// the removed fields
+    ///
+    /// Instruction sets are not lowered, so there is nothing to remove for them.
+    pub fn strip_theme_members(&mut self, unused: &[UnusedMember]) {
+        for decl in &mut self.declarations {
+            let Decl::Theme(theme) = decl else {
+                continue;
+            };
+            let is_unused = |name: &str, kind: ThemeMemberKind| {
+                unused.iter().any(|member| {
+                    member.theme == theme.qualified_name && member.name == name && member.kind == kind
+                })
+            };
+            theme.fields.retain(|field| !is_unused(&field.name, ThemeMemberKind::Field));
+            theme.variants.retain(|variant| !is_unused(&variant.name, ThemeMemberKind::Variant));
+            for variant in &mut theme.variants {
+                variant
+                    .overrides
+                    .retain(|(field, _)| !is_unused(field, ThemeMemberKind::Field));
+            }
+        }
+    }
+
+    /// Call `f` on every outermost expression of the module: initializers,
+    /// defaults, arguments, contents and the values of actions
+    pub fn for_each_expr_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+        let params = |params: &mut [Param], f: &mut dyn FnMut(&mut Expr)| {
+            params.iter_mut().filter_map(|p| p.default.as_mut()).for_each(&mut *f)
+        };
+        let actions = |actions: &mut [Action], f: &mut dyn FnMut(&mut Expr)| {
+            for action in actions {
+                match action {
+                    Action::Assign { value, .. } => f(value),
+                    Action::Command { args, .. } => args.iter_mut().for_each(&mut *f),
+                }
+            }
+        };
+        for decl in &mut self.declarations {
+            match decl {
+                Decl::Blueprint(bp) => {
+                    params(&mut bp.params, f);
+                    bp.fields.iter_mut().for_each(|field| f(&mut field.init));
+                    bp.provides.iter_mut().for_each(|provide| f(&mut provide.value));
+                    for shortcut in &mut bp.shortcuts {
+                        actions(&mut shortcut.actions, f);
+                    }
+                    for call_site in &mut bp.call_sites {
+                        call_site.args.iter_mut().for_each(|arg| f(&mut arg.value));
+                        let options = call_site.virtualize.iter_mut().flatten().map(|(_, e)| e);
+                        call_site
+                            .content
+                            .iter_mut()
+                            .chain(options)
+                            .chain(call_site.focus.tab_index.as_mut())
+                            .chain(call_site.portal.as_mut())
+                            .for_each(&mut *f);
+                    }
+                }
+                Decl::Backend(backend) => {
+                    let inits = backend.fields.iter_mut().filter_map(|field| field.init.as_mut());
+                    inits.for_each(&mut *f);
+                    for command in &mut backend.commands {
+                        params(&mut command.params, f);
+                    }
+                    for method in &mut backend.methods {
+                        params(&mut method.params, f);
+                        method.body.iter_mut().for_each(&mut *f);
+                    }
+                }
+                Decl::Contract(contract) => {
+                    for method in &mut contract.methods {
+                        params(&mut method.params, f);
+                    }
+                }
+                Decl::Theme(theme) => {
+                    let inits = theme.fields.iter_mut().filter_map(|field| field.init.as_mut());
+                    inits.for_each(&mut *f);
+                    for variant in &mut theme.variants {
+                        variant.overrides.iter_mut().for_each(|(_, e)| f(e));
+                    }
+                }
+                Decl::Scheme(_) | Decl::Enum(_) | Decl::Arena(_) => {}
+            }
+        }
+    }
+
+    /// Replace the constant expressions of the module by their values (see
+    /// `Expr::fold`)
+    ///
+    /// A theme field is a constant too when no variant of the module
+    /// overrides it: its theme's initializers and overrides read its value
+    /// instead of the field. Fields are taken in declaration order.
+    pub fn fold_constants(&mut self) {
+        let _profile = crate::profile::enter("fold");
+        let overridden: HashSet<String> = self
+            .declarations
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::Theme(theme) => Some(theme),
+                _ => None,
+            })
+            .flat_map(|theme| &theme.variants)
+            .flat_map(|variant| variant.overrides.iter().map(|(name, _)| name.clone()))
+            .collect();
+
+        for decl in &mut self.declarations {
+            let Decl::Theme(theme) = decl else {
+                continue;
+            };
+            let mut values = HashMap::new();
+            for field in &mut theme.fields {
+                let Some(init) = field.init.as_mut() else {
+                    continue;
+                };
+                init.substitute(&values);
+                init.fold();
+                if init.is_literal() && !overridden.contains(&field.name) {
+                    values.insert(field.name.clone(), init.kind.clone());
+                }
+            }
+            for variant in &mut theme.variants {
+                variant.overrides.iter_mut().for_each(|(_, e)| e.substitute(&values));
+            }
+        }
+        self.for_each_expr_mut(&mut Expr::fold);
+    }
+}
+
+/// An import, as written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// Module path, including the declaration name unless `import_all`
+    pub path: String,
+    /// `import foo.bar.*`
+    pub import_all: bool,
+}
+
+/// A top-level declaration
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decl {
+    Blueprint(Box<Blueprint>),
+    Backend(Backend),
+    Contract(Contract),
+    Scheme(Scheme),
+    Enum(Enum),
+    Theme(Theme),
+    Arena(Arena),
+}
+
+/// A blueprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blueprint {
+    pub name: String,
+    pub qualified_name: String,
+    pub params: Vec<Param>,
+    /// Backend instances the blueprint owns; a parent may replace named ones
+    pub backends: Vec<BackendUse>,
+    /// Slots a parent fills with one of its backend instances: backend-typed
+    /// parameters and `with slot: Backend`
+    pub backend_slots: Vec<BackendSlot>,
+    /// Local fields, in declaration order
+    pub fields: Vec<Field>,
+    /// Fragment creations, including those inside control statements
+    pub call_sites: Vec<CallSite>,
+    /// Indices of the call sites instantiated with the blueprint, outside control statements
+    pub top_children: Vec<usize>,
+    /// Keyboard shortcuts, with valid chords only
+    pub shortcuts: Vec<Shortcut>,
+    /// Context values read from the nearest provider above into closure fields
+    pub consumes: Vec<String>,
+    /// Context values provided to the fragments below, in declaration order
+    pub provides: Vec<Provide>,
+    /// Error boundaries outside control statements, created after `top_children`
+    pub boundaries: Vec<ErrorBoundary>,
+    /// Loading placeholders outside control statements, created after `boundaries`
+    pub loading: Vec<LoadingSlot>,
+}
+
+impl Blueprint {
+    /// Backend of the slot `name`, if a parent can inject one
+    pub fn backend_slot(&self, name: &str) -> Option<&str> {
+        self.backend_slots
+            .iter()
+            .find(|slot| slot.name == name)
+            .map(|slot| slot.backend.as_str())
+    }
+}
+
+/// An error boundary of a blueprint: `on_error { ... } fallback { error -> ... }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorBoundary {
+    /// Indices of the call sites created in the body
+    pub body: Vec<usize>,
+    /// Indices of the call sites created in place of the body once it failed
+    pub fallback: Vec<usize>,
+    /// Field of the blueprint's closure the error message is stored in
+    pub error: Option<String>,
+}
+
+/// A loading placeholder of a blueprint: `loading { ... }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadingSlot {
+    /// Indices of the call sites created while a command is in flight
+    pub body: Vec<usize>,
+    /// Backend commands invoked next to the placeholder, in order of first use
+    pub commands: Vec<String>,
+}
+
+/// A context value of a blueprint: `provide theme = dark`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provide {
+    pub name: String,
+    pub value: Expr,
+}
+
+/// A keyboard shortcut of a blueprint: `shortcut "Ctrl+S" { save() }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortcut {
+    /// Canonical key chord, e.g. `Ctrl+Shift+S`
+    pub chord: String,
+    pub actions: Vec<Action>,
+}
+
+/// A statement of a handler body
+///
+/// Statements whose target doesn't resolve to a field or a backend command
+/// are dropped; name resolution reports them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// `name = value`, writing a field of the closure or a backend field it shares
+    Assign { name: String, value: Expr },
+    /// `name(args)`, invoking a command of a backend instance
+    Command {
+        receiver: Receiver,
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+/// Backend instance a command is invoked on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Receiver {
+    /// Instance held by a closure field: a named `with` slot or a backend-typed parameter
+    Field(String),
+    /// Instance created by `with Backend`, by index into `Blueprint::backends`
+    Owned(usize),
+}
+
+/// A parameter of a blueprint or command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+    pub default: Option<Expr>,
+}
+
+/// A backend instance owned by a blueprint: `with Backend` or `with slot: Backend`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendUse {
+    pub slot: Option<String>,
+    pub backend: String,
+}
+
+/// A slot a parent can inject a backend instance into
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendSlot {
+    pub name: String,
+    pub backend: String,
+}
+
+/// A local field of a blueprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub init: Expr,
+}
+
+/// A fragment created by a blueprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    /// Stable ID: the blueprint's qualified name and the call site's path
+    /// through control statements, e.g. `app.Main/when/text`
+    pub id: String,
+    /// Qualified name of the blueprint the fragment is created from
+    pub blueprint: String,
+    pub args: Vec<Arg>,
+    /// Content expression of the body, e.g. `text { "Hello" }`
+    pub content: Option<Expr>,
+    /// Window options of a virtualized repeat container
+    pub virtualize: Option<Vec<(String, Expr)>>,
+    /// Keyboard focus instructions of the fragment
+    pub focus: Focus,
+    /// Overlay container the fragment is mounted in instead of its parent:
+    /// `portal { "tooltips" }`, or [`DEFAULT_OVERLAY`] for a bare `portal`
+    pub portal: Option<Expr>,
+}
+
+/// Overlay container of a `portal` without a container name
+pub const DEFAULT_OVERLAY: &str = "overlay";
+
+/// Keyboard focus of a fragment: `tab_index`, `autofocus` and `focus_group`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Focus {
+    /// Position in the tab order; negative values keep the fragment out of
+    /// tab navigation
+    pub tab_index: Option<Expr>,
+    /// The fragment takes focus when created
+    pub autofocus: bool,
+    pub group: Option<FocusGroup>,
+}
+
+impl Focus {
+    pub fn is_empty(&self) -> bool {
+        *self == Focus::default()
+    }
+}
+
+/// How the keyboard moves focus within a fragment's subtree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusGroup {
+    /// Tab and Shift+Tab cycle within the subtree (`focus_trap`)
+    Trap,
+    /// The subtree is a single tab stop, arrow keys move between its children
+    Arrows,
+}
+
+impl FocusGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FocusGroup::Trap => "trap",
+            FocusGroup::Arrows => "arrows",
+        }
+    }
+}
+
+/// An argument of a call site
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    /// Parameter name; `None` for positional arguments
+    pub name: Option<String>,
+    pub value: Expr,
+    /// Backend of the child's slot, when the argument injects a backend instance
+    pub injected_backend: Option<String>,
+}
+
+/// A backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    pub name: String,
+    pub qualified_name: String,
+    pub lifetime: BackendLifetime,
+    pub fields: Vec<BackendField>,
+    pub commands: Vec<Command>,
+    pub methods: Vec<BackendMethod>,
+}
+
+impl Backend {
+    /// Fields recorded in the undo history
+    pub fn undoable_fields(&self) -> impl Iterator<Item = &BackendField> {
+        self.fields.iter().filter(|field| field.undoable)
+    }
+
+    /// Whether the backend keeps an undo history and gets `undo`/`redo` commands
+    pub fn has_undo(&self) -> bool {
+        self.undoable_fields().next().is_some()
+    }
+}
+
+/// A field of a backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendField {
+    pub name: String,
+    pub ty: Type,
+    pub init: Option<Expr>,
+    /// Recorded in the undo history, by itself or because the backend is undoable
+    pub undoable: bool,
+}
+
+/// A command of a backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub params: Vec<Param>,
+    /// Type of the result; `Unit` unless the command declares one
+    pub result: Type,
+    pub policy: Option<CommandPolicy>,
+}
+
+/// A method of a backend: a pure function of the backend's state
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendMethod {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub result: Type,
+    /// The expression computing the result; implemented by the host without one
+    pub body: Option<Expr>,
+}
+
+/// A contract; bound to a transport at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub name: String,
+    pub qualified_name: String,
+    pub methods: Vec<ContractMethod>,
+}
+
+/// A method of a contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractMethod {
+    pub name: String,
+    pub params: Vec<Param>,
+    /// Type of the result; `Unit` unless the method declares one
+    pub result: Type,
+    /// Name the service knows the method by: `.. name { "getUser" }`, or the
+    /// method name
+    pub remote_name: String,
+    pub route: HttpRoute,
+}
+
+/// HTTP request a contract method is sent as by request/response transports
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRoute {
+    /// Upper-case HTTP method
+    pub method: String,
+    /// Path with `{param}` placeholders, relative to the transport's base URL
+    pub path: String,
+}
+
+/// A scheme
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    pub name: String,
+    pub qualified_name: String,
+    /// Stored fields; virtual fields are computed by the runtime
+    pub fields: Vec<SchemeField>,
+}
+
+/// A stored field of a scheme
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeField {
+    pub name: String,
+    pub ty: Type,
+    /// Validation rules from the field's instructions
+    pub rules: Vec<FieldRule>,
+}
+
+/// A validation rule of a scheme field
+///
+/// Only instructions with literal arguments become rules; `range` becomes a
+/// `Min` and a `Max`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldRule {
+    MinLen(u64),
+    MaxLen(u64),
+    /// `.. blank { false }`
+    NotBlank,
+    Pattern(String),
+    Min(f64),
+    Max(f64),
+    /// Digits after the decimal point
+    Precision(u64),
+    MinItems(u64),
+    MaxItems(u64),
+}
+
+impl FieldRule {
+    /// Error code of a failed check, the name of the instruction
+    pub fn code(&self) -> &'static str {
+        match self {
+            FieldRule::MinLen(_) => "min_len",
+            FieldRule::MaxLen(_) => "max_len",
+            FieldRule::NotBlank => "blank",
+            FieldRule::Pattern(_) => "pattern",
+            FieldRule::Min(_) => "min",
+            FieldRule::Max(_) => "max",
+            FieldRule::Precision(_) => "precision",
+            FieldRule::MinItems(_) => "min_items",
+            FieldRule::MaxItems(_) => "max_items",
+        }
+    }
+}
+
+/// An enum
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub qualified_name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+/// A variant of an enum; its ordinal is its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    /// Display name given by `.. label { "..." }`
+    pub label: Option<String>,
+    /// Number of values the variant carries, 0 for a variant without data
+    pub arity: usize,
+}
+
+/// A theme
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub qualified_name: String,
+    pub fields: Vec<ThemeField>,
+    pub variants: Vec<ThemeVariant>,
+}
+
+/// A field of a theme
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeField {
+    pub name: String,
+    pub ty: Type,
+    pub is_asset: bool,
+    pub init: Option<Expr>,
+}
+
+/// A theme variant, overriding some of the theme's fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeVariant {
+    pub name: String,
+    pub overrides: Vec<(String, Expr)>,
+}
+
+/// An arena
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arena {
+    pub name: String,
+    pub qualified_name: String,
+    /// Scheme of the instances, as written
+    pub scheme: String,
+    pub contract: Option<String>,
+}
+
+/// A typed expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Type,
+}
+
+/// Kinds of expressions
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Color(u32),
+    String(String),
+    Template(Vec<TemplatePart>),
+    List(Vec<Expr>),
+    Object(Vec<(String, Expr)>),
+    /// Read of a name
+    Read(Ref),
+    /// Dotted name kept as written, e.g. an enum variant `Status.Active`
+    Path(Vec<String>),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+    },
+    /// `base.field`, or `base?.field` if `optional`
+    Field {
+        base: Box<Expr>,
+        field: String,
+        optional: bool,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// Call of a built-in function, e.g. `format(date, "yyyy-MM-dd")`
+    BuiltinCall {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// Property or method of a value of a built-in type, e.g. `name.length`
+    /// or `name?.toUpper()`; `args` is `None` for a property. The member is
+    /// the one `semantic::members` has for the type of `base`.
+    Member {
+        base: Box<Expr>,
+        name: String,
+        args: Option<Vec<Expr>>,
+        optional: bool,
+    },
+    /// `start..end`, or `start..=end` if `inclusive`
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+    /// `(x) -> body`; the body reads the parameters as `RefKind::LambdaParam`
+    Lambda {
+        params: Vec<String>,
+        body: Box<Expr>,
+    },
+}
+
+/// A part of a string template
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Text(String),
+    Interpolation(Expr),
+}
+
+/// A resolved name
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ref {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+/// What a name refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefKind {
+    /// A blueprint parameter, including `with` slots
+    Param,
+    /// A local field of the blueprint
+    Field,
+    /// The item variable of a `repeat`
+    LoopItem,
+    /// A parameter of an enclosing lambda
+    LambdaParam,
+    /// A field of a backend instance used with `with`; `slot` names the instance
+    BackendField {
+        backend: String,
+        slot: Option<String>,
+    },
+    /// A command or method of a backend instance used with `with`
+    BackendMember {
+        backend: String,
+        slot: Option<String>,
+    },
+    /// A top-level declaration
+    Decl { qualified_name: String },
+    /// A name that doesn't resolve; reported by name resolution
+    Unresolved,
+}
+
+impl Expr {
+    /// Value of the expression if it is a constant: literals, operators,
+    /// templates, collections and calls of built-in functions on constants
+    pub fn const_value(&self) -> Option<ConstValue> {
+        let value = match &self.kind {
+            ExprKind::Null => ConstValue::Null,
+            ExprKind::Bool(b) => ConstValue::Bool(*b),
+            ExprKind::Int(i) => ConstValue::Int(*i),
+            ExprKind::Float(x) => ConstValue::Float(*x),
+            ExprKind::Color(c) => ConstValue::Color(*c),
+            ExprKind::String(s) => ConstValue::String(s.clone()),
+            ExprKind::Template(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(s) => text.push_str(s),
+                        TemplatePart::Interpolation(expr) => {
+                            text.push_str(&expr.const_value()?.to_string())
+                        }
+                    }
+                }
+                ConstValue::String(text)
+            }
+            ExprKind::List(items) => {
+                ConstValue::List(items.iter().map(Expr::const_value).collect::<Option<_>>()?)
+            }
+            ExprKind::Object(fields) => ConstValue::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| Some((name.clone(), value.const_value()?)))
+                    .collect::<Option<_>>()?,
+            ),
+            ExprKind::Binary { op, left, right } => {
+                let left = left.const_value()?;
+                // Short-circuit like the runtime does
+                match (op, &left) {
+                    (BinaryOp::And, ConstValue::Bool(false))
+                    | (BinaryOp::Or, ConstValue::Bool(true)) => return Some(left),
+                    (BinaryOp::Elvis, value) if *value != ConstValue::Null => return Some(left),
+                    _ => {}
+                }
+                eval_binary(*op, left, right.const_value()?).ok()?
+            }
+            ExprKind::Unary { op, expr } => eval_unary(*op, expr.const_value()?).ok()?,
+            ExprKind::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => match condition.const_value()? {
+                ConstValue::Bool(true) => then_expr.const_value()?,
+                ConstValue::Bool(false) => else_expr.const_value()?,
+                _ => return None,
+            },
+            ExprKind::BuiltinCall { name, args } => {
+                let args = args.iter().map(Expr::const_value).collect::<Option<Vec<_>>>()?;
+                builtins::lookup(name)?.eval(&args).ok()?
+            }
+            ExprKind::Read(_)
+            | ExprKind::Path(_)
+            | ExprKind::Field { .. }
+            | ExprKind::Member { .. }
+            | ExprKind::Call { .. }
+            | ExprKind::Range { .. }
+            | ExprKind::Lambda { .. } => return None,
+        };
+        Some(value)
+    }
+
+    /// Names read by the expression, sorted and without duplicates
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.reads().map(|r| r.name.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// References read by the expression, in source order
+    ///
+    /// Parameters of the lambdas of the expression are not read from anywhere
+    /// and left out.
+    pub fn reads(&self) -> impl Iterator<Item = &Ref> {
+        let mut refs = Vec::new();
+        self.collect_reads(&mut refs);
+        refs.into_iter()
+    }
+
+    fn collect_reads<'a>(&'a self, refs: &mut Vec<&'a Ref>) {
+        match &self.kind {
+            ExprKind::Read(Ref {
+                kind: RefKind::LambdaParam,
+                ..
+            }) => {}
+            ExprKind::Read(r) => refs.push(r),
+            ExprKind::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Interpolation(expr) = part {
+                        expr.collect_reads(refs);
+                    }
+                }
+            }
+            ExprKind::List(items) => items.iter().for_each(|item| item.collect_reads(refs)),
+            ExprKind::Object(fields) => fields.iter().for_each(|(_, v)| v.collect_reads(refs)),
+            ExprKind::Binary { left, right, .. } => {
+                left.collect_reads(refs);
+                right.collect_reads(refs);
+            }
+            ExprKind::Unary { expr, .. } => expr.collect_reads(refs),
+            ExprKind::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                condition.collect_reads(refs);
+                then_expr.collect_reads(refs);
+                else_expr.collect_reads(refs);
+            }
+            ExprKind::Field { base, .. } => base.collect_reads(refs),
+            ExprKind::Call { callee, args } => {
+                callee.collect_reads(refs);
+                args.iter().for_each(|arg| arg.collect_reads(refs));
+            }
+            ExprKind::BuiltinCall { args, .. } => args.iter().for_each(|arg| arg.collect_reads(refs)),
+            ExprKind::Member { base, args, .. } => {
+                base.collect_reads(refs);
+                args.iter().flatten().for_each(|arg| arg.collect_reads(refs));
+            }
+            ExprKind::Range { start, end, .. } => {
+                start.collect_reads(refs);
+                end.collect_reads(refs);
+            }
+            ExprKind::Lambda { body, .. } => body.collect_reads(refs),
+            ExprKind::Null
+            | ExprKind::Bool(_)
+            | ExprKind::Int(_)
+            | ExprKind::Float(_)
+            | ExprKind::Color(_)
+            | ExprKind::String(_)
+            | ExprKind::Path(_) => {}
+        }
+    }
+
+    /// Whether the expression is a literal of a single value
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self.kind,
+            ExprKind::Null
+                | ExprKind::Bool(_)
+                | ExprKind::Int(_)
+                | ExprKind::Float(_)
+                | ExprKind::Color(_)
+                | ExprKind::String(_)
+        )
+    }
+
+    /// Replace the operators, templates, conditionals and built-in calls of
+    /// the expression whose value is a constant by a literal of the value
+    ///
+    /// Values without a literal are left to be computed at runtime: lists,
+    /// objects, instants and floats that are not finite. So are templates
+    /// interpolating a float or a color, whose text the runtime writes its
+    /// own way.
+    pub fn fold(&mut self) {
+        self.children_mut().into_iter().for_each(Expr::fold);
+        let foldable = match &self.kind {
+            ExprKind::Template(parts) => parts.iter().all(|part| match part {
+                TemplatePart::Text(_) => true,
+                TemplatePart::Interpolation(expr) => matches!(
+                    expr.kind,
+                    ExprKind::Null | ExprKind::Bool(_) | ExprKind::Int(_) | ExprKind::String(_)
+                ),
+            }),
+            ExprKind::Binary { .. }
+            | ExprKind::Unary { .. }
+            | ExprKind::Ternary { .. }
+            | ExprKind::BuiltinCall { .. } => true,
+            _ => false,
+        };
+        if !foldable {
+            return;
+        }
+        let kind = match self.const_value() {
+            Some(ConstValue::Null) => ExprKind::Null,
+            Some(ConstValue::Bool(b)) => ExprKind::Bool(b),
+            Some(ConstValue::Int(i)) => ExprKind::Int(i),
+            Some(ConstValue::Float(x)) if x.is_finite() => ExprKind::Float(x),
+            Some(ConstValue::Color(c)) => ExprKind::Color(c),
+            Some(ConstValue::String(s)) => ExprKind::String(s),
+            _ => return,
+        };
+        self.kind = kind;
+    }
+
+    /// Replace reads of local fields by the given values
+    fn substitute(&mut self, values: &HashMap<String, ExprKind>) {
+        if let ExprKind::Read(Ref {
+            name,
+            kind: RefKind::Field,
+        }) = &self.kind
+        {
+            if let Some(value) = values.get(name) {
+                self.kind = value.clone();
+            }
+            return;
+        }
+        for child in self.children_mut() {
+            child.substitute(values);
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match &mut self.kind {
+            ExprKind::Template(parts) => parts
+                .iter_mut()
+                .filter_map(|part| match part {
+                    TemplatePart::Interpolation(expr) => Some(expr),
+                    TemplatePart::Text(_) => None,
+                })
+                .collect(),
+            ExprKind::List(items) => items.iter_mut().collect(),
+            ExprKind::Object(fields) => fields.iter_mut().map(|(_, value)| value).collect(),
+            ExprKind::Binary { left, right, .. } => vec![left, right],
+            ExprKind::Unary { expr, .. } => vec![expr],
+            ExprKind::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => vec![condition, then_expr, else_expr],
+            ExprKind::Field { base, .. } => vec![base],
+            ExprKind::Call { callee, args } => std::iter::once(&mut **callee).chain(args).collect(),
+            ExprKind::BuiltinCall { args, .. } => args.iter_mut().collect(),
+            ExprKind::Member { base, args, .. } => {
+                std::iter::once(&mut **base).chain(args.iter_mut().flatten()).collect()
+            }
+            ExprKind::Range { start, end, .. } => vec![start, end],
+            ExprKind::Lambda { body, .. } => vec![body],
+            ExprKind::Null
+            | ExprKind::Bool(_)
+            | ExprKind::Int(_)
+            | ExprKind::Float(_)
+            | ExprKind::Color(_)
+            | ExprKind::String(_)
+            | ExprKind::Read(_)
+            | ExprKind::Path(_) => Vec::new(),
+        }
+    }
+}
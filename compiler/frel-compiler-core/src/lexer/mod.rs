@@ -3,9 +3,11 @@
 // This module provides tokenization of Frel source code:
 // - token.rs: Token and TokenKind definitions
 // - scan.rs: Lexer implementation
+// - secrets.rs: Credentials in string literals (E0311)
 
 mod scan;
+pub mod secrets;
 pub mod token;
 
 pub use scan::Lexer;
-pub use token::{Token, TokenKind};
+pub use token::{Comment, CommentKind, Token, TokenKind, Trivia, TriviaKind};
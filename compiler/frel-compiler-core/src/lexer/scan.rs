@@ -7,9 +7,9 @@
 // - Tracks source positions for error reporting
 
 use crate::diagnostic::{Diagnostic, Diagnostics};
-use crate::source::Span;
+use crate::source::{FileId, Span};
 
-use super::{Token, TokenKind};
+use super::{Comment, CommentKind, Token, TokenKind, Trivia, TriviaKind};
 
 /// Lexer state
 pub struct Lexer<'a> {
@@ -19,6 +19,14 @@ pub struct Lexer<'a> {
     diagnostics: Diagnostics,
     /// Stack for tracking string template nesting
     template_depth: usize,
+    /// Comments skipped between tokens, in source order
+    comments: Vec<Comment>,
+    /// Whether tokens carry their leading and trailing trivia
+    preserve_trivia: bool,
+    /// Trivia skipped since the last token
+    trivia: Vec<Trivia>,
+    /// File the spans of tokens, comments and diagnostics point into
+    file: FileId,
 }
 
 impl<'a> Lexer<'a> {
@@ -30,15 +38,50 @@ impl<'a> Lexer<'a> {
             current_pos: 0,
             diagnostics: Diagnostics::new(),
             template_depth: 0,
+            comments: Vec::new(),
+            preserve_trivia: false,
+            trivia: Vec::new(),
+            file: FileId::default(),
         }
     }
 
+    /// Stamp every span the lexer produces with `file`
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = file;
+        self
+    }
+
+    /// Attach whitespace and comments to the tokens around them, so that the
+    /// full spans of the tokens cover the source without gaps
+    ///
+    /// Trivia up to the end of a line trails the token before it; trivia at
+    /// the start of a line leads the token after it.
+    pub fn with_trivia(mut self) -> Self {
+        self.preserve_trivia = true;
+        self
+    }
+
     /// Tokenize the entire source and return tokens + diagnostics
-    pub fn tokenize(mut self) -> (Vec<Token>, Diagnostics) {
-        let mut tokens = Vec::new();
+    pub fn tokenize(self) -> (Vec<Token>, Diagnostics) {
+        let (tokens, _, diagnostics) = self.tokenize_with_comments();
+        (tokens, diagnostics)
+    }
+
+    /// Tokenize the entire source, also returning the comments between tokens
+    pub fn tokenize_with_comments(mut self) -> (Vec<Token>, Vec<Comment>, Diagnostics) {
+        let mut tokens: Vec<Token> = Vec::new();
 
         loop {
-            let token = self.next_token();
+            let mut token = self.next_token();
+            let trivia = std::mem::take(&mut self.trivia);
+            if !trivia.is_empty() {
+                match tokens.last_mut() {
+                    Some(previous) if previous.kind != TokenKind::Newline => {
+                        previous.trailing = trivia
+                    }
+                    _ => token.leading = trivia,
+                }
+            }
             let is_eof = token.kind == TokenKind::Eof;
             tokens.push(token);
             if is_eof {
@@ -46,7 +89,21 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        (tokens, self.diagnostics)
+        if !self.file.is_default() {
+            let file = self.file;
+            for token in &mut tokens {
+                token.span = token.span.in_file(file);
+                for trivia in token.leading.iter_mut().chain(&mut token.trailing) {
+                    trivia.span = trivia.span.in_file(file);
+                }
+            }
+            for comment in &mut self.comments {
+                comment.span = comment.span.in_file(file);
+            }
+            self.diagnostics.set_file(file);
+        }
+
+        (tokens, self.comments, self.diagnostics)
     }
 
     /// Get the next token
@@ -184,7 +241,11 @@ impl<'a> Lexer<'a> {
         loop {
             match self.peek_char() {
                 Some((_, ' ')) | Some((_, '\t')) | Some((_, '\r')) => {
-                    self.advance();
+                    let start = self.current_pos;
+                    while matches!(self.peek_char(), Some((_, ' ' | '\t' | '\r'))) {
+                        self.advance();
+                    }
+                    self.push_trivia(TriviaKind::Whitespace, start);
                 }
                 Some((_, '/')) => {
                     if self.peek_char_nth(1) == Some('/') {
@@ -201,6 +262,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn skip_line_comment(&mut self) {
+        let start = self.current_pos;
         // Skip //
         self.advance();
         self.advance();
@@ -211,6 +273,7 @@ impl<'a> Lexer<'a> {
             }
             self.advance();
         }
+        self.push_comment(CommentKind::Line, start);
     }
 
     fn skip_block_comment(&mut self) {
@@ -247,6 +310,24 @@ impl<'a> Lexer<'a> {
                 }
             }
         }
+        self.push_comment(CommentKind::Block, start);
+    }
+
+    fn push_comment(&mut self, kind: CommentKind, start: usize) {
+        self.comments.push(Comment {
+            kind,
+            span: Span::new(start as u32, self.current_pos as u32),
+        });
+        self.push_trivia(TriviaKind::Comment(kind), start);
+    }
+
+    fn push_trivia(&mut self, kind: TriviaKind, start: usize) {
+        if self.preserve_trivia {
+            self.trivia.push(Trivia {
+                kind,
+                span: Span::new(start as u32, self.current_pos as u32),
+            });
+        }
     }
 
     // --- Operators ---
@@ -376,7 +457,12 @@ impl<'a> Lexer<'a> {
         self.advance();
         if self.peek_char().map(|(_, c)| c) == Some('.') {
             self.advance();
-            TokenKind::DotDot
+            if self.peek_char().map(|(_, c)| c) == Some('=') {
+                self.advance();
+                TokenKind::DotDotEq
+            } else {
+                TokenKind::DotDot
+            }
         } else {
             TokenKind::Dot
         }
@@ -904,6 +990,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_retained_comments() {
+        let source = "foo // comment\nbar /* a /* nested */ block */ baz";
+        let (tokens, comments, _) = Lexer::new(source).tokenize_with_comments();
+        assert_eq!(tokens.len(), 5);
+        let texts: Vec<(CommentKind, &str)> = comments
+            .iter()
+            .map(|c| (c.kind, c.span.text(source)))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                (CommentKind::Line, "// comment"),
+                (CommentKind::Block, "/* a /* nested */ block */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trivia_round_trip() {
+        let source = "module test // the module\n\n  /* doc */ blueprint Main {\r\n\ttext { 1 }  \n}";
+        let (tokens, _) = Lexer::new(source).with_trivia().tokenize();
+
+        let text: String = tokens
+            .iter()
+            .map(|token| token.full_span().text(source))
+            .collect();
+        assert_eq!(text, source);
+
+        let test = &tokens[1];
+        assert_eq!(test.text(source), "test");
+        let trailing: Vec<&str> = test.trailing.iter().map(|t| t.span.text(source)).collect();
+        assert_eq!(trailing, [" ", "// the module"]);
+
+        let blueprint = tokens.iter().find(|t| t.text(source) == "blueprint").unwrap();
+        let leading: Vec<TriviaKind> = blueprint.leading.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            leading,
+            [
+                TriviaKind::Whitespace,
+                TriviaKind::Comment(CommentKind::Block),
+                TriviaKind::Whitespace
+            ]
+        );
+
+        // Without trivia, tokens only cover their own text
+        let (tokens, _) = Lexer::new(source).tokenize();
+        assert!(tokens.iter().all(|t| t.leading.is_empty() && t.trailing.is_empty()));
+    }
+
     #[test]
     fn test_dotdot() {
         assert_eq!(
@@ -919,6 +1055,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ranges() {
+        assert_eq!(
+            lex("0..count 1..=n"),
+            vec![
+                TokenKind::IntLiteral,
+                TokenKind::DotDot,
+                TokenKind::Identifier,
+                TokenKind::IntLiteral,
+                TokenKind::DotDotEq,
+                TokenKind::Identifier,
+                TokenKind::Eof
+            ]
+        );
+    }
+
     #[test]
     fn test_arrow() {
         assert_eq!(
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 /// Contextual keywords - only reserved at top-level positions.
 /// These can be used as identifiers (field names, parameters, etc.) inside declarations.
 pub mod contextual {
+    pub const EDITION: &str = "edition";
     pub const MODULE: &str = "module";
     pub const IMPORT: &str = "import";
     pub const BLUEPRINT: &str = "blueprint";
@@ -17,6 +18,40 @@ pub mod contextual {
     pub const ENUM: &str = "enum";
     pub const THEME: &str = "theme";
     pub const ARENA: &str = "arena";
+
+    /// Keywords starting a top-level declaration
+    pub const DECLARATIONS: [&str; 7] = [BLUEPRINT, BACKEND, CONTRACT, SCHEME, ENUM, THEME, ARENA];
+
+    // Backend lifetime modifiers, only reserved before `backend`
+    pub const FRAGMENT: &str = "fragment";
+    pub const SCREEN: &str = "screen";
+    pub const SINGLETON: &str = "singleton";
+
+    // Undo history modifier, only reserved before `backend` or a backend field
+    pub const UNDOABLE: &str = "undoable";
+
+    // Command concurrency policies, only reserved before `command` or `method`
+    pub const LATEST: &str = "latest";
+    pub const QUEUE: &str = "queue";
+    pub const DROP: &str = "drop";
+
+    // Keyboard shortcuts, only reserved before a string literal starting a
+    // blueprint statement
+    pub const SHORTCUT: &str = "shortcut";
+
+    // Context values, only reserved before a name starting a blueprint
+    // statement: `provide theme = dark`, `consume theme: Theme`
+    pub const PROVIDE: &str = "provide";
+    pub const CONSUME: &str = "consume";
+
+    // Error boundaries, only reserved before `{` starting a blueprint
+    // statement, and after the boundary's body
+    pub const ON_ERROR: &str = "on_error";
+    pub const FALLBACK: &str = "fallback";
+
+    // Loading placeholders, only reserved before `{` starting a blueprint
+    // statement
+    pub const LOADING: &str = "loading";
 }
 
 /// A token with its kind and source span
@@ -24,17 +59,69 @@ pub mod contextual {
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    /// Whitespace and comments between the start of the line and the token
+    /// (only collected by `Lexer::with_trivia`)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub leading: Vec<Trivia>,
+    /// Whitespace and comments after the token up to the end of its line
+    /// (only collected by `Lexer::with_trivia`)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub trailing: Vec<Trivia>,
 }
 
 impl Token {
     pub fn new(kind: TokenKind, span: Span) -> Self {
-        Self { kind, span }
+        Self {
+            kind,
+            span,
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        }
     }
 
     /// Get the text of this token from source
     pub fn text<'a>(&self, source: &'a str) -> &'a str {
         self.span.text(source)
     }
+
+    /// Span of the token with its leading and trailing trivia
+    pub fn full_span(&self) -> Span {
+        let start = self.leading.first().map_or(self.span.start, |t| t.span.start);
+        let end = self.trailing.last().map_or(self.span.end, |t| t.span.end);
+        Span::new(start, end)
+    }
+}
+
+/// Source text between tokens that doesn't affect parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+/// Kind of trivia; newlines are `Newline` tokens, not trivia
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriviaKind {
+    /// Spaces, tabs and carriage returns
+    Whitespace,
+    Comment(CommentKind),
+}
+
+/// A comment, kept aside from the token stream for tooling (formatter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Span of the whole comment, including `//` or `/* */`
+    pub span: Span,
+}
+
+/// Comment syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line
+    Line,
+    /// `/* ... */`, possibly nested and spanning lines
+    Block,
 }
 
 /// Token kinds produced by the lexer
@@ -102,6 +189,7 @@ pub enum TokenKind {
     Arrow,       // ->
     FatArrow,    // =>
     DotDot,      // ..
+    DotDotEq,    // ..=
 
     // Punctuation
     LParen,      // (
@@ -273,6 +361,7 @@ impl TokenKind {
             Arrow => "'->'",
             FatArrow => "'=>'",
             DotDot => "'..'",
+            DotDotEq => "'..='",
             LParen => "'('",
             RParen => "')'",
             LBrace => "'{'",
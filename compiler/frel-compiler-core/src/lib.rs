@@ -2,37 +2,64 @@
 //
 // This crate contains the core compiler components:
 // - Hand-written lexer and recursive descent parser
-// - Abstract Syntax Tree (AST)
+// - Abstract Syntax Tree (AST) and a structural diff between two ASTs
 // - Diagnostic system with structured error reporting
-// - Type system and type checker (TODO)
-// - Semantic analysis (TODO)
+// - Language editions gating newer syntax, configured in frel.toml
+// - Feature gates for experimental syntax
+// - Semantic analysis: name resolution, type checking, module signatures
+// - Typed IR lowered from the AST, consumed by code generation plugins
+// - Internal profiler emitting folded stacks for flamegraphs
+// - Versioned JSON envelope of compiler output for build tools
+// - Session bundles recording failing builds for replay
+// - Input source maps remapping diagnostics to template files
+// - Sandboxed file and environment access for plugins
+// - File access through the disk or memory, for tests and WASM builds
 //
 // The compiler is language-agnostic and produces an IR that can be
 // consumed by host-language specific code generation plugins.
 
 pub mod ast;
 pub mod diagnostic;
+pub mod edition;
 pub mod error;
+#[cfg(test)]
+mod golden;
+pub mod ir;
 pub mod lexer;
+pub mod output;
 pub mod parser;
+pub mod profile;
+pub mod sandbox;
 pub mod semantic;
+pub mod session;
 pub mod source;
+pub mod source_map;
+pub mod vfs;
 
 pub use diagnostic::{
     Category, Diagnostic, DiagnosticSink, DiagnosticTag, Diagnostics, ErrorCode, Label,
-    RelatedInfo, Severity, Suggestion,
+    LintLevel, LintLevels, RelatedInfo, SarifBuilder, SarifLog, Severity, Suggestion,
 };
+pub use edition::{Edition, Experimental, Features, ProjectConfig, WatchConfig};
 pub use error::{Error, Result};
 pub use lexer::{Token, TokenKind};
+pub use output::{Artifact, ArtifactKind, CompilerOutput, FileDiagnostic, Timings, OUTPUT_VERSION};
 pub use parser::ParseResult;
+pub use sandbox::PluginContext;
 pub use semantic::{
-    analyze, analyze_module, build_signature, dump_semantic, resolve_with_registry, typecheck,
-    typecheck_with_registry, ExportedDecl, LookupResult, Module, ModuleAnalysisResult,
-    ModuleSignature, ResolveResult, ResolvedType, Scope, ScopeGraph, ScopeId, ScopeKind,
-    SemanticResult, SignatureRegistry, SignatureResult, Symbol, SymbolId, SymbolKind, SymbolTable,
-    Type, TypeCheckResult, TypeChecker, SIGNATURE_VERSION,
+    analyze, analyze_complexity, analyze_module, build_signature, check_translations, dump_semantic,
+    eval_const, evaluate_theme, extract_messages, extract_state_machines, parse_translations, pseudo_locale,
+    resolve_with_registry, run_passes, typecheck, typecheck_with_registry, unused_theme_members,
+    BlueprintComplexity, ComplexityReport, ConstValue, ExportedDecl,
+    load_signature, save_signature, signature_file_path, LoadDirResult, LookupResult, MessageCatalog, Module, ModuleAnalysisResult, ModuleSignature, ResolveResult, ResolvedType,
+    Scope, ScopeGraph, ScopeGraphExport, ScopeId, ScopeKind, SemanticPass, SemanticResult, SignatureRegistry, SignatureResult, StateMachine,
+    Symbol, SymbolId, SymbolKind, SymbolTable, ThemeMemberKind, ThemeUsage, ThemeValue, ThemeValues,
+    Type, TypeCheckResult, TypeChecker, UnusedMember, SIGNATURE_VERSION,
 };
-pub use source::{LineIndex, Span, Spanned};
+pub use source::{FileId, LineIndex, Span, Spanned};
+pub use vfs::{DiskFs, MemoryFs, OutputSink, SourceProvider};
+
+use std::sync::Arc;
 
 /// Compiler version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -47,17 +74,146 @@ pub fn parse_file_with_path(source: &str, path: &str) -> ParseResult {
     parser::parse_with_path(source, path)
 }
 
-/// Compile a Frel source file to IR
-/// Returns the AST and any diagnostics (errors, warnings)
-pub fn compile(source: &str) -> ParseResult {
-    // TODO: Add semantic analysis and type checking
-    parse_file(source)
+/// Parse a Frel source file with the project's edition
+///
+/// An `edition` line in the file takes precedence over `edition`.
+pub fn parse_file_with_edition(source: &str, path: &str, edition: Edition) -> ParseResult {
+    parser::parse_with_edition(source, path, edition)
+}
+
+/// Parse a Frel source file with the project's edition and the experimental
+/// features enabled for the compilation
+pub fn parse_file_with_features(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+) -> ParseResult {
+    parser::parse_with_features(source, path, edition, features)
+}
+
+/// Parse one file of a multi-file module
+///
+/// Every span of the AST and the diagnostics points into `file`, so spans of
+/// the module's files stay apart once they're analyzed together.
+pub fn parse_file_in_module(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+    file: FileId,
+) -> ParseResult {
+    parser::parse_in_file(source, path, edition, features, file)
+}
+
+/// Result of compiling a single file
+#[derive(Debug)]
+pub struct CompileResult {
+    /// The parsed file, if the parser produced one
+    pub ast: Option<ast::File>,
+    /// Name resolution and type information; `None` if the file has parse errors
+    pub semantic: Option<ModuleAnalysisResult>,
+    /// Signature of the file's module; `None` if the file has parse errors
+    pub signature: Option<ModuleSignature>,
+    /// The file lowered for code generation; `None` if the file has parse errors
+    pub ir: Option<ir::Module>,
+    /// Parse, resolution and type diagnostics
+    pub diagnostics: Diagnostics,
+}
+
+impl CompileResult {
+    /// Check if compilation succeeded (no errors)
+    pub fn success(&self) -> bool {
+        !self.diagnostics.has_errors()
+    }
+}
+
+/// Compile a Frel source file: parse, resolve names and type check
+///
+/// Semantic analysis only runs on files without parse errors. Imports of
+/// other modules are reported as unresolved; use `compile_with_registry`
+/// to resolve them against their signatures.
+pub fn compile(source: &str) -> CompileResult {
+    compile_parsed(parse_file(source), &SignatureRegistry::new(), &[], &PluginContext::new())
 }
 
 /// Compile a Frel source file with a known file path
-pub fn compile_with_path(source: &str, path: &str) -> ParseResult {
-    // TODO: Add semantic analysis and type checking
-    parse_file_with_path(source, path)
+pub fn compile_with_path(source: &str, path: &str) -> CompileResult {
+    compile_parsed(parse_file_with_path(source, path), &SignatureRegistry::new(), &[], &PluginContext::new())
+}
+
+/// Compile a Frel source file with the project's edition
+pub fn compile_with_edition(source: &str, path: &str, edition: Edition) -> CompileResult {
+    compile_parsed(parse_file_with_edition(source, path, edition), &SignatureRegistry::new(), &[], &PluginContext::new())
+}
+
+/// Compile a Frel source file with the project's edition and experimental features
+pub fn compile_with_features(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+) -> CompileResult {
+    let parsed = parse_file_with_features(source, path, edition, features);
+    compile_parsed(parsed, &SignatureRegistry::new(), &[], &PluginContext::new())
+}
+
+/// Compile a Frel source file, then run external semantic passes on it
+///
+/// The passes' diagnostics are added to the result's; passes don't run on
+/// files with errors. They reach files and the environment through `context`.
+pub fn compile_with_passes(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+    passes: &[Arc<dyn SemanticPass>],
+    context: &PluginContext,
+) -> CompileResult {
+    let parsed = parse_file_with_features(source, path, edition, features);
+    compile_parsed(parsed, &SignatureRegistry::new(), passes, context)
+}
+
+/// Compile a Frel source file, resolving its imports through `registry`
+pub fn compile_with_registry(source: &str, path: &str, registry: &SignatureRegistry) -> CompileResult {
+    compile_parsed(parse_file_with_path(source, path), registry, &[], &PluginContext::new())
+}
+
+fn compile_parsed(
+    parsed: ParseResult,
+    registry: &SignatureRegistry,
+    passes: &[Arc<dyn SemanticPass>],
+    context: &PluginContext,
+) -> CompileResult {
+    let mut diagnostics = parsed.diagnostics;
+    let file = match parsed.file {
+        Some(file) if !diagnostics.has_errors() => file,
+        ast => {
+            return CompileResult {
+                ast,
+                semantic: None,
+                signature: None,
+                ir: None,
+                diagnostics,
+            }
+        }
+    };
+
+    // The signature's resolve errors are reported again by the analysis
+    let mut module = Module::from_files(file.module.clone(), vec![file]);
+    let signature = build_signature(&module).signature;
+    let semantic = analyze_module(&module, registry);
+    diagnostics.merge(semantic.diagnostics.clone());
+    diagnostics.merge(run_passes(passes, &module, &semantic, context));
+    let ir = ir::lower_with_registry(&module.files[0], registry);
+
+    CompileResult {
+        ast: module.files.pop(),
+        semantic: Some(semantic),
+        signature: Some(signature),
+        ir: Some(ir),
+        diagnostics,
+    }
 }
 
 #[cfg(test)]
@@ -125,4 +281,40 @@ blueprint B { }
         // Should report error for the @ but also parse blueprint B
         assert!(result.diagnostics.has_errors());
     }
+
+    #[test]
+    fn test_compile_runs_semantic_analysis() {
+        let source = "module test\n\nblueprint Main {\n    count : i32 = 0\n    column {\n        text { count + 1 }\n    }\n}\n";
+        let result = compile(source);
+        assert!(result.success(), "{:?}", result.diagnostics);
+        assert!(result.semantic.is_some());
+        assert_eq!(result.signature.unwrap().path, "test");
+
+        let result = compile("module test\n\nblueprint Main {\n    text { missing }\n    text { true - 1 }\n}\n");
+        let messages: Vec<&str> = result.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.iter().any(|m| m.contains("`missing`")), "{:?}", messages);
+        assert!(messages.iter().any(|m| m.starts_with("cannot apply")), "{:?}", messages);
+        assert!(result.ast.is_some());
+    }
+
+    #[test]
+    fn test_compile_skips_analysis_after_parse_errors() {
+        let result = compile("module test\nblueprint { }");
+        assert!(!result.success());
+        assert!(result.semantic.is_none());
+        assert!(result.signature.is_none());
+        assert!(result.ir.is_none());
+    }
+
+    #[test]
+    fn test_compile_with_registry() {
+        let data = compile("module test.data\n\nscheme User {\n    name : String\n}\n");
+        let registry = SignatureRegistry::new();
+        registry.register(data.signature.unwrap());
+
+        let source = "module test.app\n\nimport test.data.User\n\nblueprint Main(user: User) {\n    text { user.name }\n}\n";
+        assert!(!compile(source).success());
+        let result = compile_with_registry(source, "app.frel", &registry);
+        assert!(result.success(), "{:?}", result.diagnostics);
+    }
 }
@@ -0,0 +1,231 @@
+// Machine-readable compiler output
+//
+// Build tools read one JSON envelope instead of scraping human output. It is
+// printed by `frelc compile --message-format json` and by the compiler server
+// for its builds:
+//
+//   {"version":1,"artifacts":[...],"diagnostics":[...],"timings":{...}}
+//
+// `version` is bumped on incompatible changes only; new fields may be added
+// within a version, so readers should ignore fields they don't know. The
+// diagnostics of the files in `test-data/diagnostics` are kept as golden
+// JSON next to them, so a change to their shape shows up in review.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::source::{LineCol, LineIndex};
+
+/// Version of the output envelope
+pub const OUTPUT_VERSION: u32 = 1;
+
+/// The JSON envelope of a compilation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompilerOutput {
+    pub version: u32,
+    /// Files written by the compilation
+    pub artifacts: Vec<Artifact>,
+    pub diagnostics: Vec<FileDiagnostic>,
+    pub timings: Timings,
+}
+
+/// A file written by the compiler
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: String,
+    pub kind: ArtifactKind,
+    /// Module the file was generated from
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub module: Option<String>,
+}
+
+/// What an artifact contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Generated code for the target
+    Code,
+    /// Exported scope graph, as DOT or JSON
+    ScopeGraph,
+    /// DUMP format of the AST
+    AstDump,
+    /// Manifest of fingerprinted file names
+    Manifest,
+}
+
+/// A diagnostic with the file it was reported in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiagnostic {
+    pub file: String,
+    /// Start of the primary span; absent if the source wasn't available
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub start: Option<LineCol>,
+    /// End of the primary span
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub end: Option<LineCol>,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+impl FileDiagnostic {
+    /// A diagnostic of `file`; `index` of the file's source gives it line and column
+    pub fn new(file: &str, index: Option<&LineIndex>, diagnostic: Diagnostic) -> Self {
+        let span = diagnostic.span;
+        Self {
+            file: file.to_string(),
+            start: index.map(|index| index.line_col(span.start)),
+            end: index.map(|index| index.line_col(span.end)),
+            diagnostic,
+        }
+    }
+}
+
+/// Wall-clock durations of a compilation, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    pub total_ms: f64,
+    /// Durations of the compilation's phases, e.g. `compile` or `codegen`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub phases: BTreeMap<String, f64>,
+}
+
+impl Timings {
+    /// Record the duration of a phase
+    pub fn record(&mut self, phase: &str, duration: Duration) {
+        self.phases.insert(phase.to_string(), millis(duration));
+    }
+}
+
+impl CompilerOutput {
+    pub fn new() -> Self {
+        Self {
+            version: OUTPUT_VERSION,
+            artifacts: Vec::new(),
+            diagnostics: Vec::new(),
+            timings: Timings::default(),
+        }
+    }
+
+    /// Add the diagnostics of `file`; `source` gives them line and column
+    pub fn add_diagnostics(&mut self, file: &str, source: Option<&str>, diagnostics: &Diagnostics) {
+        let index = source.map(LineIndex::new);
+        for diagnostic in diagnostics.iter() {
+            self.diagnostics
+                .push(FileDiagnostic::new(file, index.as_ref(), diagnostic.clone()));
+        }
+    }
+
+    /// Add a written file
+    pub fn add_artifact(
+        &mut self,
+        path: impl Into<String>,
+        kind: ArtifactKind,
+        module: Option<&str>,
+    ) {
+        self.artifacts.push(Artifact {
+            path: path.into(),
+            kind,
+            module: module.map(str::to_string),
+        });
+    }
+
+    /// Set the total duration of the compilation
+    pub fn finish(&mut self, total: Duration) {
+        self.timings.total_ms = millis(total);
+    }
+
+    /// Whether no error was reported
+    pub fn success(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .all(|d| d.diagnostic.severity != crate::Severity::Error)
+    }
+
+    /// The envelope as one line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("compiler output serializes")
+    }
+}
+
+impl Default for CompilerOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Span;
+
+    #[test]
+    fn test_envelope_json() {
+        let mut diagnostics = Diagnostics::new();
+        let mut diagnostic = Diagnostic::error("unknown blueprint `Missing`", Span::new(33, 40));
+        diagnostic.code = Some("E0301".to_string());
+        diagnostics.add(diagnostic);
+
+        let mut output = CompilerOutput::new();
+        let source = "module app\n\nblueprint Main {\n    Missing { }\n}\n";
+        output.add_diagnostics("app.frel", Some(source), &diagnostics);
+        output.add_artifact("app.js", ArtifactKind::Code, Some("app"));
+        output.finish(Duration::from_millis(3));
+        assert!(!output.success());
+
+        let json: serde_json::Value = serde_json::from_str(&output.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "artifacts": [{ "path": "app.js", "kind": "code", "module": "app" }],
+                "diagnostics": [{
+                    "file": "app.frel",
+                    "start": { "line": 4, "col": 5 },
+                    "end": { "line": 4, "col": 12 },
+                    "severity": "error",
+                    "code": "E0301",
+                    "message": "unknown blueprint `Missing`",
+                    "span": { "start": 33, "end": 40 },
+                }],
+                "timings": { "total_ms": 3.0 },
+            })
+        );
+
+        let parsed: CompilerOutput = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.artifacts, output.artifacts);
+        assert_eq!(
+            parsed.diagnostics[0].diagnostic.message,
+            "unknown blueprint `Missing`"
+        );
+    }
+
+    /// Compare the diagnostics of every `test-data/diagnostics/*.frel`, as
+    /// they appear in the envelope, with its sibling `.json`; set
+    /// `FREL_UPDATE_GOLDEN=1` to rewrite the expected files.
+    #[test]
+    fn test_diagnostics_golden() {
+        let golden = crate::golden::Golden::new("diagnostics");
+        for source in golden.sources("frel") {
+            let name = golden.name(&source);
+            let text = std::fs::read_to_string(&source).unwrap();
+            let result = crate::compile_with_path(&text, &name);
+            let mut output = CompilerOutput::new();
+            output.add_diagnostics(&name, Some(&text), &result.diagnostics);
+
+            let expected_path = source.with_extension("json");
+            golden.check_json(&expected_path, &output.diagnostics);
+
+            // Readers of older output keep working
+            let expected = std::fs::read_to_string(&expected_path).unwrap();
+            let parsed: Vec<FileDiagnostic> = serde_json::from_str(&expected).unwrap();
+            assert_eq!(parsed.len(), output.diagnostics.len());
+        }
+    }
+}
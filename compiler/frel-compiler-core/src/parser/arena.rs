@@ -9,11 +9,13 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse arena declaration
     pub(super) fn parse_arena(&mut self) -> Option<Arena> {
+        let _profile = crate::profile::enter("parse_arena");
         let start = self.current_span().start;
         self.expect_contextual(contextual::ARENA)?;
         let name = self.expect_identifier()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
+        self.mark_item();
         self.expect(TokenKind::For)?;
         let scheme_name = self.expect_identifier()?;
 
@@ -25,8 +27,9 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
+        let span = self.span(start, end_span.end);
         Some(Arena {
             name,
             scheme_name,
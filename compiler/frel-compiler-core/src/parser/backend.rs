@@ -1,6 +1,9 @@
 // Backend parser for Frel
 
-use crate::ast::{Backend, BackendMember, Command, Field, Method};
+use crate::ast::{
+    Backend, BackendLifetime, BackendMember, Command, CommandPolicy, Field, Method,
+};
+use crate::edition::{Experimental, Feature};
 use crate::lexer::token::contextual;
 use crate::lexer::TokenKind;
 
@@ -9,14 +12,43 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse backend declaration
     pub(super) fn parse_backend(&mut self) -> Option<Backend> {
+        let _profile = crate::profile::enter("parse_backend");
         let start = self.current_span().start;
+        let doc = self.doc_comment(start);
+        // Optional modifiers: `singleton backend Session`, `undoable backend Editor`
+        let mut lifetime = BackendLifetime::default();
+        let mut undoable = false;
+        loop {
+            let feature = match self.current_text() {
+                contextual::FRAGMENT => {
+                    lifetime = BackendLifetime::Fragment;
+                    Feature::BackendLifetime
+                }
+                contextual::SCREEN => {
+                    lifetime = BackendLifetime::Screen;
+                    Feature::BackendLifetime
+                }
+                contextual::SINGLETON => {
+                    lifetime = BackendLifetime::Singleton;
+                    Feature::BackendLifetime
+                }
+                contextual::UNDOABLE => {
+                    undoable = true;
+                    Feature::Undoable
+                }
+                _ => break,
+            };
+            let span = self.advance().span;
+            self.require_edition(feature, span);
+        }
         self.expect_contextual(contextual::BACKEND)?;
         let name = self.expect_identifier()?;
         let params = self.parse_param_list_opt()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut members = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(member) = self.parse_backend_member() {
                 members.push(member);
             } else {
@@ -27,18 +59,52 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
+        let span = self.span(start, end_span.end);
         Some(Backend {
             name,
+            lifetime,
+            undoable,
             params,
             members,
+            doc,
             span,
         })
     }
 
+    /// Concurrency policy at the current token, if followed by `command` or `method`
+    fn command_policy(&self) -> Option<CommandPolicy> {
+        if !self.check(TokenKind::Identifier) {
+            return None;
+        }
+        let policy = match self.current_text() {
+            contextual::LATEST => CommandPolicy::Latest,
+            contextual::QUEUE => CommandPolicy::Queue,
+            contextual::DROP => CommandPolicy::Drop,
+            _ => return None,
+        };
+        matches!(self.peek()?.kind, TokenKind::Command | TokenKind::Method).then_some(policy)
+    }
+
     /// Parse a backend member
     fn parse_backend_member(&mut self) -> Option<BackendMember> {
+        // Optional concurrency policy: `latest command search(query: String)`
+        let start = self.current_span().start;
+        let policy = self.command_policy();
+        if policy.is_some() {
+            let span = self.advance().span;
+            self.require_edition(Feature::CommandPolicy, span);
+        }
+        // Optional undo history for a field: `undoable text : String = ""`
+        let undoable = policy.is_none()
+            && self.check_identifier(contextual::UNDOABLE)
+            && self.peek_kind() == Some(TokenKind::Identifier);
+        if undoable {
+            let span = self.advance().span;
+            self.require_edition(Feature::Undoable, span);
+        }
+
         match self.current_kind() {
             TokenKind::Include => {
                 self.advance();
@@ -46,31 +112,58 @@ impl<'a> Parser<'a> {
                 Some(BackendMember::Include(name))
             }
             TokenKind::Method => {
-                let start = self.current_span().start;
                 self.advance();
                 let name = self.expect_identifier()?;
                 let params = self.parse_param_list()?;
                 self.expect(TokenKind::Colon)?;
                 let return_type = self.parse_type_expr()?;
-                let span = crate::source::Span::new(start, self.previous_span().end);
+                // Optional body: `method total() : i32 = price * quantity`
+                let body = match self.consume(TokenKind::Eq) {
+                    Some(eq) => {
+                        self.require_edition(Feature::MethodBodies, eq.span);
+                        Some(self.parse_expr()?)
+                    }
+                    None => None,
+                };
+                let span = self.span(start, self.previous_span().end);
                 Some(BackendMember::Method(Method {
                     name,
                     params,
                     return_type,
+                    body,
+                    policy,
                     span,
                 }))
             }
             TokenKind::Command => {
-                let start = self.current_span().start;
                 self.advance();
                 let name = self.expect_identifier()?;
                 let params = self.parse_param_list()?;
-                let span = crate::source::Span::new(start, self.previous_span().end);
-                Some(BackendMember::Command(Command { name, params, span }))
+                // Optional result: `command load(id : UUID) : User`
+                let return_type = if self.check(TokenKind::Colon) {
+                    self.advance();
+                    let type_start = self.current_span().start;
+                    let return_type = self.parse_type_expr()?;
+                    // The type may end with the newline after it
+                    let text = &self.source[type_start as usize..self.previous_span().end as usize];
+                    let type_end = type_start + text.trim_end().len() as u32;
+                    let type_span = self.span(type_start, type_end);
+                    self.require_experimental(Experimental::CommandResults, type_span);
+                    Some(return_type)
+                } else {
+                    None
+                };
+                let span = self.span(start, self.previous_span().end);
+                Some(BackendMember::Command(Command {
+                    name,
+                    params,
+                    return_type,
+                    policy,
+                    span,
+                }))
             }
             TokenKind::Identifier => {
                 // Field: name : type [= init]
-                let start = self.current_span().start;
                 let name = self.expect_identifier()?;
                 self.expect(TokenKind::Colon)?;
                 let type_expr = self.parse_type_expr()?;
@@ -79,11 +172,12 @@ impl<'a> Parser<'a> {
                 } else {
                     None
                 };
-                let span = crate::source::Span::new(start, self.previous_span().end);
+                let span = self.span(start, self.previous_span().end);
                 Some(BackendMember::Field(Field {
                     name,
                     type_expr,
                     init,
+                    undoable,
                     span,
                 }))
             }
@@ -97,7 +191,7 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::ast::BackendMember;
+    use crate::ast::{BackendLifetime, BackendMember, CommandPolicy};
     use crate::parser::parse;
 
     #[test]
@@ -118,6 +212,154 @@ backend Counter {
         assert_eq!(file.declarations.len(), 1);
     }
 
+    #[test]
+    fn test_parse_method_body() {
+        let result = parse(
+            r#"
+module test
+
+backend Cart {
+    price: f64 = 0.0
+    method total(quantity: i32): f64 = price * quantity
+    method tax(): f64
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let crate::ast::TopLevelDecl::Backend(backend) = &file.declarations[0] else {
+            panic!("expected backend");
+        };
+        let bodies: Vec<_> = backend
+            .members
+            .iter()
+            .filter_map(|m| match m {
+                BackendMember::Method(method) => Some(method.body.is_some()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bodies, [true, false]);
+    }
+
+    #[test]
+    fn test_parse_backend_lifetime() {
+        let result = parse(
+            r#"
+module test
+
+singleton backend Session {
+    user: String = ""
+}
+
+screen backend Cart {
+    singleton: bool = false
+}
+
+backend Counter {
+    count: i32 = 0
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let lifetimes: Vec<_> = result
+            .file
+            .unwrap()
+            .declarations
+            .iter()
+            .filter_map(|decl| match decl {
+                crate::ast::TopLevelDecl::Backend(backend) => Some(backend.lifetime),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            lifetimes,
+            [
+                BackendLifetime::Singleton,
+                BackendLifetime::Screen,
+                BackendLifetime::Fragment
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_command_policy() {
+        let result = parse(
+            r#"
+module test
+
+backend Search {
+    queue: List<String> = []
+    latest command search(query: String)
+    queue command save()
+    drop command refresh()
+    command log()
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let crate::ast::TopLevelDecl::Backend(backend) = &file.declarations[0] else {
+            panic!("expected backend");
+        };
+        let policies: Vec<_> = backend
+            .members
+            .iter()
+            .filter_map(|m| match m {
+                BackendMember::Command(c) => Some(c.policy),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            policies,
+            [
+                Some(CommandPolicy::Latest),
+                Some(CommandPolicy::Queue),
+                Some(CommandPolicy::Drop),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_undoable() {
+        let result = parse(
+            r#"
+module test
+
+undoable screen backend Editor {
+    text: String = ""
+}
+
+backend Form {
+    undoable name: String = ""
+    undoable: bool = false
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let backends: Vec<_> = file
+            .declarations
+            .iter()
+            .filter_map(|d| match d {
+                crate::ast::TopLevelDecl::Backend(b) => Some(b),
+                _ => None,
+            })
+            .collect();
+
+        assert!(backends[0].undoable);
+        assert_eq!(backends[0].lifetime, BackendLifetime::Screen);
+
+        // `undoable` is also a valid field name
+        assert!(!backends[1].undoable);
+        let fields: Vec<_> = backends[1]
+            .undoable_fields()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(fields, ["name"]);
+        assert_eq!(backends[1].members.len(), 2);
+    }
+
     #[test]
     fn test_contextual_keywords_as_field_names() {
         // Test that contextual keywords (theme, backend, module, etc.) can be used as field names
@@ -4,14 +4,18 @@
 // - Blueprint declarations
 // - Blueprint statements (with, local decl, fragment creation, control, instructions, events)
 // - Fragment creation with slots
-// - Control statements (when, repeat, select)
+// - Control statements (when, repeat, select, on_error, loading)
+// - Patterns destructuring repeat items and select payloads
 // - Event handlers
+// - Keyboard shortcuts
+// - Context values (provide, consume)
 
 use crate::ast::{
-    Arg, Blueprint, BlueprintStmt, BlueprintValue, ControlStmt, EventHandler,
-    EventParam, FragmentBody, FragmentCreation, HandlerStmt, LocalDecl, PostfixItem,
-    SelectBranch, SlotBinding,
+    Arg, Blueprint, BlueprintStmt, BlueprintValue, Consume, ControlStmt, EventHandler,
+    EventParam, Expr, FragmentBody, FragmentCreation, HandlerStmt, LocalDecl, Pattern,
+    PostfixItem, Provide, SelectBranch, Shortcut, SlotBinding, WithClause,
 };
+use crate::edition::Feature;
 use crate::lexer::token::contextual;
 use crate::lexer::TokenKind;
 
@@ -25,19 +29,28 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse blueprint declaration
     pub(super) fn parse_blueprint(&mut self) -> Option<Blueprint> {
+        let _profile = crate::profile::enter("parse_blueprint");
         let start = self.current_span().start;
+        let doc = self.doc_comment(start);
         self.expect_contextual(contextual::BLUEPRINT)?;
         let name = self.expect_identifier()?;
         let params = self.parse_param_list_opt()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let body = self.parse_blueprint_body()?;
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
-        Some(Blueprint { name, params, body, span })
+        let span = self.span(start, end_span.end);
+        Some(Blueprint {
+            name,
+            params,
+            body,
+            doc,
+            span,
+        })
     }
 
     /// Parse blueprint body (list of statements)
@@ -45,6 +58,7 @@ impl<'a> Parser<'a> {
         let mut stmts = Vec::new();
 
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(stmt) = self.parse_blueprint_stmt() {
                 stmts.push(stmt);
             } else {
@@ -59,16 +73,29 @@ impl<'a> Parser<'a> {
     /// Parse a single blueprint statement
     fn parse_blueprint_stmt(&mut self) -> Option<BlueprintStmt> {
         match self.current_kind() {
-            // With statement: with BackendName
+            // With statement: with BackendName, or with slot: BackendName
             TokenKind::With => {
+                let start = self.current_span().start;
                 self.advance();
-                let name = self.expect_identifier()?;
+                let first = self.expect_identifier()?;
+                let (name, backend) = if self.consume(TokenKind::Colon).is_some() {
+                    let span = self.span(start, self.previous_span().end);
+                    self.require_edition(Feature::NamedInjection, span);
+                    (Some(first), self.expect_identifier()?)
+                } else {
+                    (None, first)
+                };
                 // Optional constructor args
                 if self.check(TokenKind::LParen) {
                     // TODO: Parse backend args if needed
                     self.parse_arg_list()?;
                 }
-                Some(BlueprintStmt::With(name))
+                let span = self.span(start, self.previous_span().end);
+                Some(BlueprintStmt::With(WithClause {
+                    name,
+                    backend,
+                    span,
+                }))
             }
 
             // Control statements
@@ -76,6 +103,19 @@ impl<'a> Parser<'a> {
             TokenKind::Repeat => self.parse_repeat_stmt(),
             TokenKind::Select => self.parse_select_stmt(),
 
+            // Keyboard shortcut: shortcut "Ctrl+S" { save() }
+            TokenKind::Identifier if self.is_shortcut_start() => self.parse_shortcut(),
+
+            // Context values: provide theme = dark, consume theme: Theme
+            TokenKind::Identifier if self.is_provide_start() => self.parse_provide(),
+            TokenKind::Identifier if self.is_consume_start() => self.parse_consume(),
+
+            // Error boundary: on_error { body } fallback { error -> body }
+            TokenKind::Identifier if self.is_on_error_start() => self.parse_on_error(),
+
+            // Loading placeholder: loading { body }
+            TokenKind::Identifier if self.is_loading_start() => self.parse_loading(),
+
             // Event handlers: on_click, on_input, etc.
             TokenKind::Identifier if self.is_event_handler_start() => self.parse_event_handler(),
 
@@ -88,7 +128,7 @@ impl<'a> Parser<'a> {
                 self.expect(TokenKind::Eq)?;
                 let init = self.parse_expr()?;
                 let end = self.previous_span().end;
-                let span = crate::source::Span::new(start, end);
+                let span = self.span(start, end);
                 Some(BlueprintStmt::LocalDecl(LocalDecl {
                     name,
                     type_expr,
@@ -144,15 +184,18 @@ impl<'a> Parser<'a> {
             // This appears in control structures like `when condition { ... }`
             TokenKind::LBrace => {
                 // Create an anonymous/inline fragment to hold the statements
-                self.advance();
+                let open = self.advance().span;
+                let start = open.start;
                 let body = self.parse_blueprint_body()?;
-                self.expect(TokenKind::RBrace)?;
+                let close = self.expect(TokenKind::RBrace)?.span;
+                self.mark_block(open, close);
                 // Wrap in FragmentCreation with empty name to represent a block
                 Some(BlueprintStmt::FragmentCreation(FragmentCreation {
                     name: String::new(), // anonymous block
                     args: vec![],
                     body: Some(FragmentBody::Default(body)),
                     postfix: vec![],
+                    span: self.span(start, self.previous_end()),
                 }))
             }
 
@@ -209,6 +252,7 @@ impl<'a> Parser<'a> {
     /// Parse fragment creation
     /// Fragment creations require at least one of: args (), body {}, or postfix ..
     fn parse_fragment_creation(&mut self) -> Option<BlueprintStmt> {
+        let start = self.current_span().start;
         let name = self.expect_identifier()?;
 
         // Check what follows
@@ -235,6 +279,7 @@ impl<'a> Parser<'a> {
                     args,
                     body,
                     postfix,
+                    span: self.span(start, self.previous_end()),
                 }))
             }
 
@@ -246,6 +291,7 @@ impl<'a> Parser<'a> {
                     args: vec![],
                     body: None,
                     postfix,
+                    span: self.span(start, self.previous_end()),
                 }))
             }
 
@@ -260,12 +306,13 @@ impl<'a> Parser<'a> {
 
     /// Parse fragment body (default, slot-based, or inline blueprint with params)
     fn parse_fragment_body(&mut self) -> Option<FragmentBody> {
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         // Check if it's a slot-based body: { at slotName: ... }
         if self.check(TokenKind::At) {
             let slots = self.parse_slot_bindings()?;
-            self.expect(TokenKind::RBrace)?;
+            let close = self.expect(TokenKind::RBrace)?.span;
+            self.mark_block(open, close);
             Some(FragmentBody::Slots(slots))
         } else if self.check(TokenKind::Identifier) && self.has_arrow_after_params() {
             // Inline blueprint with parameters: { param -> body } or { p1, p2 -> body }
@@ -276,12 +323,14 @@ impl<'a> Parser<'a> {
             }
             self.expect(TokenKind::Arrow)?;
             let body = self.parse_blueprint_body()?;
-            self.expect(TokenKind::RBrace)?;
+            let close = self.expect(TokenKind::RBrace)?.span;
+            self.mark_block(open, close);
             Some(FragmentBody::InlineBlueprint { params, body })
         } else {
             // Default body: regular blueprint statements
             let body = self.parse_blueprint_body()?;
-            self.expect(TokenKind::RBrace)?;
+            let close = self.expect(TokenKind::RBrace)?.span;
+            self.mark_block(open, close);
             Some(FragmentBody::Default(body))
         }
     }
@@ -291,6 +340,7 @@ impl<'a> Parser<'a> {
         let mut bindings = Vec::new();
 
         while self.check(TokenKind::At) {
+            self.mark_item();
             self.advance();
             let slot_name = self.expect_identifier()?;
             self.expect(TokenKind::Colon)?;
@@ -313,7 +363,7 @@ impl<'a> Parser<'a> {
     fn parse_blueprint_value(&mut self) -> Option<BlueprintValue> {
         // Check for inline blueprint: { [params ->] body }
         if self.check(TokenKind::LBrace) {
-            self.advance();
+            let open = self.advance().span;
 
             // Check if this is { param -> body } or { param1, param2 -> body }
             // We need to look ahead to see if there's an arrow after identifier(s)
@@ -325,12 +375,14 @@ impl<'a> Parser<'a> {
                 }
                 self.expect(TokenKind::Arrow)?;
                 let body = self.parse_blueprint_body()?;
-                self.expect(TokenKind::RBrace)?;
+                let close = self.expect(TokenKind::RBrace)?.span;
+                self.mark_block(open, close);
                 Some(BlueprintValue::Inline { params, body })
             } else {
                 // No params, just body
                 let body = self.parse_blueprint_body()?;
-                self.expect(TokenKind::RBrace)?;
+                let close = self.expect(TokenKind::RBrace)?.span;
+                self.mark_block(open, close);
                 Some(BlueprintValue::Inline {
                     params: vec![],
                     body,
@@ -406,7 +458,9 @@ impl<'a> Parser<'a> {
     fn parse_postfix_items(&mut self) -> Option<Vec<PostfixItem>> {
         let mut items = Vec::new();
 
-        while self.consume(TokenKind::DotDot).is_some() {
+        while self.check(TokenKind::DotDot) {
+            let start = self.advance().span.start;
+            self.outline.postfix.push(start);
             // Check if this is an event handler (on_*)
             if self.is_event_handler_start() {
                 let handler = self.parse_postfix_event_handler()?;
@@ -422,9 +476,10 @@ impl<'a> Parser<'a> {
 
     /// Parse event handler in postfix position: on_click { [param ->] body }
     fn parse_postfix_event_handler(&mut self) -> Option<EventHandler> {
+        let start = self.current_span().start;
         let event_name = self.expect_identifier()?;
 
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         // Optional parameter inside braces: { param -> body } or { param: Type -> body }
         let param = if self.check(TokenKind::Identifier) && self.has_arrow_after_typed_param() {
@@ -442,6 +497,7 @@ impl<'a> Parser<'a> {
 
         let mut body = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(stmt) = self.parse_handler_stmt() {
                 body.push(stmt);
             } else {
@@ -449,12 +505,14 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.expect(TokenKind::RBrace)?;
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
 
         Some(EventHandler {
             event_name,
             param,
             body,
+            span: self.span(start, self.previous_end()),
         })
     }
 
@@ -525,7 +583,7 @@ impl<'a> Parser<'a> {
         }))
     }
 
-    /// Parse repeat statement: repeat on expr [by keyExpr] { item -> body }
+    /// Parse repeat statement: repeat on expr [by keyExpr] { pattern -> body }
     fn parse_repeat_stmt(&mut self) -> Option<BlueprintStmt> {
         self.expect(TokenKind::Repeat)?;
         self.expect(TokenKind::On)?;
@@ -538,15 +596,16 @@ impl<'a> Parser<'a> {
         };
 
         // Expect { item -> body }
-        self.expect(TokenKind::LBrace)?;
-        let item_name = self.expect_identifier()?;
+        let open = self.expect(TokenKind::LBrace)?.span;
+        let item = self.parse_pattern()?;
         self.expect(TokenKind::Arrow)?;
         let body = self.parse_blueprint_body()?;
-        self.expect(TokenKind::RBrace)?;
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
 
         Some(BlueprintStmt::Control(ControlStmt::Repeat {
             iterable,
-            item_name,
+            item,
             key_expr,
             body,
         }))
@@ -554,7 +613,7 @@ impl<'a> Parser<'a> {
 
     /// Parse select statement: select [on expr] { branches }
     fn parse_select_stmt(&mut self) -> Option<BlueprintStmt> {
-        self.expect(TokenKind::Select)?;
+        let keyword = self.expect(TokenKind::Select)?.span;
 
         let discriminant = if self.consume(TokenKind::On).is_some() {
             Some(self.parse_expr()?)
@@ -562,31 +621,202 @@ impl<'a> Parser<'a> {
             None
         };
 
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut branches = Vec::new();
         let mut else_branch = None;
 
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if self.consume(TokenKind::Else).is_some() {
                 self.expect(TokenKind::FatArrow)?;
                 else_branch = Some(Box::new(self.parse_blueprint_stmt()?));
                 break;
             }
 
-            let condition = self.parse_expr()?;
+            let start = self.current_span().start;
+            let (condition, payload) = if discriminant.is_some() && self.is_variant_pattern_start() {
+                self.parse_variant_pattern()?
+            } else {
+                (self.parse_expr()?, Vec::new())
+            };
             self.expect(TokenKind::FatArrow)?;
             let body = Box::new(self.parse_blueprint_stmt()?);
+            let span = self.span(start, self.previous_end());
 
-            branches.push(SelectBranch { condition, body });
+            branches.push(SelectBranch {
+                condition,
+                payload,
+                body,
+                span,
+            });
         }
 
-        self.expect(TokenKind::RBrace)?;
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
 
         Some(BlueprintStmt::Control(ControlStmt::Select {
             discriminant,
             branches,
             else_branch,
+            span: self.span(keyword.start, self.previous_end()),
+        }))
+    }
+
+    /// Parse a pattern: `name`, `_` or `(pattern, ...)`
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        if !self.check(TokenKind::LParen) {
+            let name = self.expect_identifier()?;
+            return Some(if name == "_" {
+                Pattern::Wildcard
+            } else {
+                Pattern::Binding(name)
+            });
+        }
+
+        let open = self.advance().span;
+        self.require_edition(Feature::Destructuring, open);
+        let mut parts = Vec::new();
+        while !self.check(TokenKind::RParen) {
+            parts.push(self.parse_pattern()?);
+            if self.consume(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        Some(Pattern::Tuple(parts))
+    }
+
+    /// Check if a select branch destructures a variant: `Name(..) =>` or
+    /// `Enum.Name(..) =>`, with only patterns between the parentheses
+    fn is_variant_pattern_start(&self) -> bool {
+        let mut offset = 0;
+        if !self.check(TokenKind::Identifier) {
+            return false;
+        }
+        if self.peek_kind() == Some(TokenKind::Dot) {
+            if self.peek_n(2).map(|t| t.kind) != Some(TokenKind::Identifier) {
+                return false;
+            }
+            offset = 2;
+        }
+        offset += 1;
+        if self.peek_n(offset).map(|t| t.kind) != Some(TokenKind::LParen) {
+            return false;
+        }
+        let mut depth = 0;
+        loop {
+            match self.peek_n(offset).map(|t| t.kind) {
+                Some(TokenKind::LParen) => depth += 1,
+                Some(TokenKind::RParen) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Some(TokenKind::Identifier | TokenKind::Comma | TokenKind::Newline) => {}
+                _ => return false,
+            }
+            offset += 1;
+        }
+        loop {
+            offset += 1;
+            match self.peek_n(offset).map(|t| t.kind) {
+                Some(TokenKind::Newline) => {}
+                kind => return kind == Some(TokenKind::FatArrow),
+            }
+        }
+    }
+
+    /// Parse the variant and payload patterns of a select branch: `Ok(value)`
+    fn parse_variant_pattern(&mut self) -> Option<(Expr, Vec<Pattern>)> {
+        let start = self.current_span().start;
+        let name = self.expect_identifier()?;
+        let condition = if self.consume(TokenKind::Dot).is_some() {
+            let field = self.expect_identifier()?;
+            Expr::FieldAccess {
+                base: Box::new(Expr::Identifier(name)),
+                field,
+                span: self.span(start, self.previous_end()),
+            }
+        } else {
+            Expr::Identifier(name)
+        };
+
+        let open = self.expect(TokenKind::LParen)?.span;
+        self.require_edition(Feature::Destructuring, open);
+        let mut payload = Vec::new();
+        while !self.check(TokenKind::RParen) {
+            payload.push(self.parse_pattern()?);
+            if self.consume(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        Some((condition, payload))
+    }
+
+    /// Check if current position is the start of an error boundary
+    fn is_on_error_start(&self) -> bool {
+        self.check_identifier(contextual::ON_ERROR) && self.peek_kind() == Some(TokenKind::LBrace)
+    }
+
+    /// Parse error boundary: on_error { body } [fallback { [error ->] body }]
+    ///
+    /// A missing fallback is accepted here; semantic analysis reports it.
+    fn parse_on_error(&mut self) -> Option<BlueprintStmt> {
+        let keyword = self.advance().span;
+        self.require_edition(Feature::ErrorBoundary, keyword);
+
+        let open = self.expect(TokenKind::LBrace)?.span;
+        let body = self.parse_blueprint_body()?;
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
+
+        let mut error_name = None;
+        let fallback = if self.check_identifier(contextual::FALLBACK)
+            && self.peek_kind() == Some(TokenKind::LBrace)
+        {
+            self.advance();
+            let open = self.expect(TokenKind::LBrace)?.span;
+            if self.check(TokenKind::Identifier) && self.peek_kind() == Some(TokenKind::Arrow) {
+                error_name = Some(self.expect_identifier()?);
+                self.advance();
+            }
+            let fallback = self.parse_blueprint_body()?;
+            let close = self.expect(TokenKind::RBrace)?.span;
+            self.mark_block(open, close);
+            Some(fallback)
+        } else {
+            None
+        };
+
+        Some(BlueprintStmt::Control(ControlStmt::OnError {
+            body,
+            error_name,
+            fallback,
+            span: self.span(keyword.start, self.previous_span().end),
+        }))
+    }
+
+    /// Check if current position is the start of a loading placeholder
+    fn is_loading_start(&self) -> bool {
+        self.check_identifier(contextual::LOADING) && self.peek_kind() == Some(TokenKind::LBrace)
+    }
+
+    /// Parse loading placeholder: loading { body }
+    fn parse_loading(&mut self) -> Option<BlueprintStmt> {
+        let keyword = self.advance().span;
+        self.require_edition(Feature::LoadingSlot, keyword);
+
+        let open = self.expect(TokenKind::LBrace)?.span;
+        let body = self.parse_blueprint_body()?;
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
+
+        Some(BlueprintStmt::Control(ControlStmt::Loading {
+            body,
+            span: self.span(keyword.start, close.end),
         }))
     }
 
@@ -596,6 +826,7 @@ impl<'a> Parser<'a> {
 
     /// Parse event handler: on_click [param ->] { body }
     fn parse_event_handler(&mut self) -> Option<BlueprintStmt> {
+        let start = self.current_span().start;
         let event_name = self.expect_identifier()?;
 
         // Optional parameter: param -> or param: Type ->
@@ -612,10 +843,97 @@ impl<'a> Parser<'a> {
             None
         };
 
-        self.expect(TokenKind::LBrace)?;
+        let body = self.parse_handler_body()?;
+
+        Some(BlueprintStmt::EventHandler(EventHandler {
+            event_name,
+            param,
+            body,
+            span: self.span(start, self.previous_end()),
+        }))
+    }
+
+    /// Check if current position is the start of a keyboard shortcut
+    fn is_shortcut_start(&self) -> bool {
+        self.check_identifier(contextual::SHORTCUT)
+            && self.peek_kind() == Some(TokenKind::StringLiteral)
+    }
+
+    /// Parse keyboard shortcut: shortcut "Ctrl+S" { body }
+    ///
+    /// The chord is kept as written; semantic analysis validates it.
+    fn parse_shortcut(&mut self) -> Option<BlueprintStmt> {
+        let keyword = self.advance().span;
+        self.require_edition(Feature::Shortcuts, keyword);
+
+        let literal = self.advance();
+        let chord = self.parse_string_content(literal.text(self.source));
+        let body = self.parse_handler_body()?;
+
+        Some(BlueprintStmt::Shortcut(Shortcut {
+            chord,
+            chord_span: literal.span,
+            body,
+            span: self.span(keyword.start, self.previous_span().end),
+        }))
+    }
+
+    /// Check if current position is the start of a context value provider
+    fn is_provide_start(&self) -> bool {
+        self.is_context_start(contextual::PROVIDE, TokenKind::Eq)
+    }
+
+    /// Check if current position is the start of a context value consumer
+    fn is_consume_start(&self) -> bool {
+        self.is_context_start(contextual::CONSUME, TokenKind::Colon)
+    }
+
+    /// Check if current position is `keyword name` followed by `separator`
+    fn is_context_start(&self, keyword: &str, separator: TokenKind) -> bool {
+        self.check_identifier(keyword)
+            && self.peek_kind() == Some(TokenKind::Identifier)
+            && self.peek_n(2).map(|t| t.kind) == Some(separator)
+    }
+
+    /// Parse context value provider: provide name = expr
+    fn parse_provide(&mut self) -> Option<BlueprintStmt> {
+        let keyword = self.advance().span;
+        self.require_edition(Feature::Context, keyword);
+
+        let name = self.expect_identifier()?;
+        self.expect(TokenKind::Eq)?;
+        let value = self.parse_expr()?;
+
+        Some(BlueprintStmt::Provide(Provide {
+            name,
+            value,
+            span: self.span(keyword.start, self.previous_span().end),
+        }))
+    }
+
+    /// Parse context value consumer: consume name: Type
+    fn parse_consume(&mut self) -> Option<BlueprintStmt> {
+        let keyword = self.advance().span;
+        self.require_edition(Feature::Context, keyword);
+
+        let name = self.expect_identifier()?;
+        self.expect(TokenKind::Colon)?;
+        let type_expr = self.parse_type_expr()?;
+
+        Some(BlueprintStmt::Consume(Consume {
+            name,
+            type_expr,
+            span: self.span(keyword.start, self.previous_span().end),
+        }))
+    }
+
+    /// Parse a handler body: { stmt* }
+    fn parse_handler_body(&mut self) -> Option<Vec<HandlerStmt>> {
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut body = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(stmt) = self.parse_handler_stmt() {
                 body.push(stmt);
             } else {
@@ -623,13 +941,9 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.expect(TokenKind::RBrace)?;
-
-        Some(BlueprintStmt::EventHandler(EventHandler {
-            event_name,
-            param,
-            body,
-        }))
+        let close = self.expect(TokenKind::RBrace)?.span;
+        self.mark_block(open, close);
+        Some(body)
     }
 
     /// Parse a handler statement (assignment or command call)
@@ -695,7 +1009,8 @@ impl<'a> Parser<'a> {
         let base_offset = token.span.start + content_start_offset as u32;
 
         // Parse the layout content
-        let mut layout_parser = LayoutParser::new(content, base_offset, &mut self.diagnostics);
+        let mut layout_parser =
+            LayoutParser::new(content, base_offset, &mut self.diagnostics).in_file(self.file);
         let grid = layout_parser.parse()?;
 
         Some(BlueprintStmt::Layout(grid))
@@ -720,6 +1035,9 @@ fn extract_layout_content(text: &str) -> &str {
 
 #[cfg(test)]
 mod tests {
+    use crate::ast::{
+        BlueprintStmt, ControlStmt, Expr, HandlerStmt, Pattern, PostfixItem, TopLevelDecl,
+    };
     use crate::parser::parse;
 
     #[test]
@@ -801,6 +1119,87 @@ blueprint List {
         assert!(!result.diagnostics.has_errors());
     }
 
+    #[test]
+    fn test_blueprint_with_range_repeat() {
+        let result = parse(
+            r#"
+module test
+
+blueprint Stars {
+    count : i32 = 5
+    repeat on 0..count { i ->
+        text { "*" } .. bold .. italic
+    }
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors());
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("Expected blueprint");
+        };
+        let Some(BlueprintStmt::Control(ControlStmt::Repeat { iterable, body, .. })) = bp.body.get(1) else {
+            panic!("Expected repeat");
+        };
+        assert!(matches!(iterable, Expr::Range { inclusive: false, .. }));
+
+        // `..` after an instruction reference starts the next instruction
+        let Some(BlueprintStmt::FragmentCreation(text)) = body.first() else {
+            panic!("Expected fragment");
+        };
+        assert_eq!(text.postfix.len(), 2);
+        assert!(text.postfix.iter().all(|item| matches!(item, PostfixItem::Instruction(_))));
+    }
+
+    #[test]
+    fn test_destructuring_patterns() {
+        let result = parse(
+            r#"
+module test
+
+blueprint Scores(scores : Map<String, i32>, result : Outcome) {
+    repeat on scores { (name, _) ->
+        text { name }
+    }
+    select on result {
+        Ok(value) => text { value }
+        Outcome.Err(code, _) => text { "failed" }
+        check(result.id) => text { "checked" }
+    }
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("Expected blueprint");
+        };
+        let Some(BlueprintStmt::Control(ControlStmt::Repeat { item, .. })) = bp.body.first() else {
+            panic!("Expected repeat");
+        };
+        assert_eq!(
+            *item,
+            Pattern::Tuple(vec![Pattern::Binding("name".to_string()), Pattern::Wildcard])
+        );
+        assert_eq!(item.to_string(), "(name, _)");
+        assert_eq!(item.bindings(), ["name"]);
+
+        let Some(BlueprintStmt::Control(ControlStmt::Select { branches, .. })) = bp.body.get(1) else {
+            panic!("Expected select");
+        };
+        assert!(matches!(&branches[0].condition, Expr::Identifier(name) if name == "Ok"));
+        assert_eq!(branches[0].payload, [Pattern::Binding("value".to_string())]);
+        assert!(matches!(&branches[1].condition, Expr::FieldAccess { field, .. } if field == "Err"));
+        assert_eq!(branches[1].payload.len(), 2);
+
+        // A call with an expression argument stays a condition
+        let Expr::Call { args, .. } = &branches[2].condition else {
+            panic!("Expected call");
+        };
+        assert_eq!(args.len(), 1);
+        assert!(branches[2].payload.is_empty());
+    }
+
     #[test]
     fn test_blueprint_with_event() {
         let result = parse(
@@ -817,6 +1216,141 @@ blueprint Button {
         assert!(!result.diagnostics.has_errors());
     }
 
+    #[test]
+    fn test_blueprint_with_shortcut() {
+        let source = r#"
+module test
+
+blueprint Editor {
+    with EditorBackend
+    shortcut "Ctrl+S" { save() }
+    shortcut
+}
+"#;
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("expected a blueprint");
+        };
+        let BlueprintStmt::Shortcut(shortcut) = &bp.body[1] else {
+            panic!("expected a shortcut, got {:?}", bp.body[1]);
+        };
+        assert_eq!(shortcut.chord, "Ctrl+S");
+        assert_eq!(shortcut.chord_span.text(source), "\"Ctrl+S\"");
+        assert!(matches!(
+            shortcut.body.as_slice(),
+            [HandlerStmt::CommandCall { name, .. }] if name == "save"
+        ));
+        // `shortcut` is only a keyword before a chord
+        assert!(matches!(bp.body[2], BlueprintStmt::ContentExpr(_)));
+    }
+
+    #[test]
+    fn test_blueprint_with_context() {
+        let source = r#"
+module test
+
+blueprint Page {
+    consume depth: i32
+    provide depth = depth + 1
+    provide: i32 = 0
+}
+"#;
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("expected a blueprint");
+        };
+        let BlueprintStmt::Consume(consume) = &bp.body[0] else {
+            panic!("expected a consumer, got {:?}", bp.body[0]);
+        };
+        assert_eq!(consume.name, "depth");
+        assert_eq!(consume.span.text(source).trim_end(), "consume depth: i32");
+        let BlueprintStmt::Provide(provide) = &bp.body[1] else {
+            panic!("expected a provider, got {:?}", bp.body[1]);
+        };
+        assert_eq!(provide.name, "depth");
+        assert_eq!(provide.span.text(source).trim_end(), "provide depth = depth + 1");
+        // `provide` is only a keyword before a name
+        assert!(matches!(&bp.body[2], BlueprintStmt::LocalDecl(decl) if decl.name == "provide"));
+    }
+
+    #[test]
+    fn test_blueprint_with_error_boundary() {
+        let source = r#"
+module test
+
+blueprint Page {
+    on_error {
+        Profile()
+    } fallback { error ->
+        text { error }
+    }
+    on_error { Profile() }
+    button { } .. on_error { retry() }
+}
+"#;
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("expected a blueprint");
+        };
+        let BlueprintStmt::Control(ControlStmt::OnError {
+            body,
+            error_name,
+            fallback,
+            span,
+        }) = &bp.body[0]
+        else {
+            panic!("expected an error boundary, got {:?}", bp.body[0]);
+        };
+        assert_eq!(body.len(), 1);
+        assert_eq!(error_name.as_deref(), Some("error"));
+        assert_eq!(fallback.as_ref().map(Vec::len), Some(1));
+        assert!(span.text(source).trim_end().ends_with("text { error }\n    }"));
+        // The missing fallback is left to semantic analysis
+        assert!(matches!(
+            &bp.body[1],
+            BlueprintStmt::Control(ControlStmt::OnError { fallback: None, .. })
+        ));
+        // After `..` it is still an event handler
+        let BlueprintStmt::FragmentCreation(button) = &bp.body[2] else {
+            panic!("expected a fragment, got {:?}", bp.body[2]);
+        };
+        assert!(matches!(&button.postfix[0], PostfixItem::EventHandler(h) if h.event_name == "on_error"));
+    }
+
+    #[test]
+    fn test_blueprint_with_loading() {
+        let source = r#"
+module test
+
+blueprint Search {
+    button { } .. on_click { search() }
+    loading {
+        spinner()
+    }
+    loading(size = 2)
+}
+"#;
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let TopLevelDecl::Blueprint(bp) = &file.declarations[0] else {
+            panic!("expected a blueprint");
+        };
+        let BlueprintStmt::Control(ControlStmt::Loading { body, span }) = &bp.body[1] else {
+            panic!("expected a loading placeholder, got {:?}", bp.body[1]);
+        };
+        assert_eq!(body.len(), 1);
+        assert_eq!(span.text(source), "loading {\n        spinner()\n    }");
+        // Without `{` it is still a fragment
+        assert!(matches!(&bp.body[2], BlueprintStmt::FragmentCreation(fc) if fc.name == "loading"));
+    }
+
     #[test]
     fn test_blueprint_with_backend() {
         let result = parse(
@@ -47,6 +47,7 @@ impl<'a> Parser<'a> {
 
     /// Parse a single parameter
     fn parse_param(&mut self) -> Option<Parameter> {
+        let start = self.current_span().start;
         let name = self.expect_identifier()?;
         self.expect(TokenKind::Colon)?;
         let type_expr = self.parse_type_expr()?;
@@ -61,6 +62,7 @@ impl<'a> Parser<'a> {
             name,
             type_expr,
             default,
+            span: self.span(start, self.previous_span().end),
         })
     }
 
@@ -77,8 +79,7 @@ impl<'a> Parser<'a> {
             vec![]
         };
 
-        let end = self.previous_span().end;
-        let span = crate::source::Span::new(start, end);
+        let span = self.span(start, self.previous_end());
         Some(Instruction { name, params, span })
     }
 
@@ -90,6 +91,7 @@ impl<'a> Parser<'a> {
     /// - Ternary: `<condition> ? <inst> else <inst>`
     /// - Reference: `theme.status_badge` (field access or identifier)
     pub(super) fn parse_instruction_expr(&mut self) -> Option<InstructionExpr> {
+        let _profile = crate::profile::enter("parse_instruction_expr");
         // Check for `when` conditional
         if self.consume(TokenKind::When).is_some() {
             return self.parse_when_instruction_expr();
@@ -128,8 +130,7 @@ impl<'a> Parser<'a> {
             self.advance(); // consume '{'
             let params = self.parse_instruction_params()?;
             self.expect(TokenKind::RBrace)?;
-            let end = self.previous_span().end;
-            let span = crate::source::Span::new(start, end);
+            let span = self.span(start, self.previous_end());
             return Some(InstructionExpr::Simple(Instruction { name, params, span }));
         }
 
@@ -9,13 +9,15 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse contract declaration
     pub(super) fn parse_contract(&mut self) -> Option<Contract> {
+        let _profile = crate::profile::enter("parse_contract");
         let start = self.current_span().start;
         self.expect_contextual(contextual::CONTRACT)?;
         let name = self.expect_identifier()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut methods = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(method) = self.parse_contract_method() {
                 methods.push(method);
             } else {
@@ -25,8 +27,9 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
+        let span = self.span(start, end_span.end);
         Some(Contract { name, methods, span })
     }
 
@@ -42,11 +45,20 @@ impl<'a> Parser<'a> {
             None
         };
 
-        let span = crate::source::Span::new(start, self.previous_span().end);
+        // Transport bindings: .. get { "/users/{id}" } .. name { "getUser" }
+        let mut instructions = Vec::new();
+        while self.consume(TokenKind::DotDot).is_some() {
+            if let Some(instr) = self.parse_field_instruction() {
+                instructions.push(instr);
+            }
+        }
+
+        let span = self.span(start, self.previous_span().end);
         Some(ContractMethod {
             name,
             params,
             return_type,
+            instructions,
             span,
         })
     }
@@ -70,4 +82,33 @@ contract API {
         );
         assert!(!result.diagnostics.has_errors());
     }
+
+    #[test]
+    fn test_parse_contract_method_bindings() {
+        let result = parse(
+            r#"
+module test
+
+contract API {
+    fetch(id: i32): String .. get { "/items/{id}" } .. name { "fetchItem" }
+    save(data: String)
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors());
+
+        let file = result.file.unwrap();
+        let crate::ast::TopLevelDecl::Contract(contract) = &file.declarations[0] else {
+            panic!("expected a contract");
+        };
+        let names: Vec<_> = contract.methods[0]
+            .instructions
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["get", "name"]);
+        assert_eq!(contract.methods[0].route(), Some(("get", "/items/{id}")));
+        assert_eq!(contract.methods[0].binding("name"), Some("fetchItem"));
+        assert!(contract.methods[1].instructions.is_empty());
+    }
 }
@@ -1,6 +1,7 @@
 // Enum parser for Frel
 
 use crate::ast::Enum;
+use crate::edition::Feature;
 use crate::lexer::token::contextual;
 use crate::lexer::TokenKind;
 
@@ -9,15 +10,37 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse enum declaration
     pub(super) fn parse_enum(&mut self) -> Option<Enum> {
+        let _profile = crate::profile::enter("parse_enum");
         let start = self.current_span().start;
         self.expect_contextual(contextual::ENUM)?;
         let name = self.expect_identifier()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut variants = Vec::new();
+        let mut instructions = Vec::new();
+        let mut payloads = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if self.check(TokenKind::Identifier) {
-                variants.push(self.expect_identifier()?);
+                let variant = self.expect_identifier()?;
+                // Variant data: Err(i32)
+                if let Some(open) = self.consume(TokenKind::LParen) {
+                    self.require_edition(Feature::EnumPayloads, open.span);
+                    let mut types = Vec::new();
+                    while !self.check(TokenKind::RParen) {
+                        types.push(self.parse_type_expr()?);
+                        if self.consume(TokenKind::Comma).is_none() {
+                            break;
+                        }
+                    }
+                    self.expect(TokenKind::RParen)?;
+                    payloads.push((variant.clone(), types));
+                }
+                // Variant instructions: Draft .. label { "In draft" }
+                while self.consume(TokenKind::DotDot).is_some() {
+                    instructions.push((variant.clone(), self.parse_field_instruction()?));
+                }
+                variants.push(variant);
             } else {
                 self.error_expected("enum variant");
                 break;
@@ -26,9 +49,16 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
-        Some(Enum { name, variants, span })
+        let span = self.span(start, end_span.end);
+        Some(Enum {
+            name,
+            variants,
+            instructions,
+            payloads,
+            span,
+        })
     }
 }
 
@@ -55,4 +85,54 @@ enum Status {
             assert_eq!(e.variants.len(), 3);
         }
     }
+
+    #[test]
+    fn test_parse_enum_labels() {
+        let result = parse(
+            r#"
+module test
+
+enum Status {
+    Pending .. label { "Waiting for review" }
+    Active
+    Completed .. label { "Done" }
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors());
+        let file = result.file.unwrap();
+        let crate::ast::TopLevelDecl::Enum(e) = &file.declarations[0] else {
+            panic!("expected enum");
+        };
+        assert_eq!(e.variants, ["Pending", "Active", "Completed"]);
+        assert_eq!(e.label("Pending"), Some("Waiting for review"));
+        assert_eq!(e.label("Active"), None);
+        assert_eq!(e.label("Completed"), Some("Done"));
+    }
+
+    #[test]
+    fn test_parse_enum_payloads() {
+        let result = parse(
+            r#"
+module test
+
+enum Fetch {
+    Idle
+    Loaded(String, i32) .. label { "Done" }
+    Failed(List<String>)
+}
+"#,
+        );
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let crate::ast::TopLevelDecl::Enum(e) = &file.declarations[0] else {
+            panic!("expected enum");
+        };
+        assert_eq!(e.variants, ["Idle", "Loaded", "Failed"]);
+        assert!(e.payload("Idle").is_empty());
+        let loaded: Vec<_> = e.payload("Loaded").iter().map(ToString::to_string).collect();
+        assert_eq!(loaded, ["String", "i32"]);
+        assert_eq!(e.payload("Failed")[0].to_string(), "List<String>");
+        assert_eq!(e.label("Loaded"), Some("Done"));
+    }
 }
@@ -7,13 +7,17 @@
 // - Logical AND (&&)
 // - Equality (== !=)
 // - Comparison (< <= > >=)
+// - Range (.. ..=)
 // - Additive (+ -)
 // - Multiplicative (* / %)
 // - Exponential (**)
 // - Unary (! - +)
 // - Postfix (. ?. ())
+//
+// Lambdas, `(x) -> expr`, are primaries whose body extends as far as possible.
 
-use crate::ast::{BinaryOp, Expr, TemplateElement, UnaryOp};
+use crate::ast::{BinaryOp, Expr, LambdaParam, TemplateElement, UnaryOp};
+use crate::edition::Feature;
 use crate::lexer::TokenKind;
 
 use super::Parser;
@@ -28,6 +32,7 @@ enum Precedence {
     And,          // &&
     Equality,     // == !=
     Comparison,   // < <= > >=
+    Range,        // .. ..=
     Additive,     // + -
     Multiplicative, // * / %
     Exponential,  // **
@@ -45,7 +50,8 @@ impl Precedence {
             Precedence::Or => Precedence::And,
             Precedence::And => Precedence::Equality,
             Precedence::Equality => Precedence::Comparison,
-            Precedence::Comparison => Precedence::Additive,
+            Precedence::Comparison => Precedence::Range,
+            Precedence::Range => Precedence::Additive,
             Precedence::Additive => Precedence::Multiplicative,
             Precedence::Multiplicative => Precedence::Exponential,
             Precedence::Exponential => Precedence::Unary,
@@ -63,6 +69,7 @@ fn infix_precedence(kind: TokenKind) -> Option<Precedence> {
         TokenKind::AmpAmp => Precedence::And,
         TokenKind::EqEq | TokenKind::BangEq => Precedence::Equality,
         TokenKind::Lt | TokenKind::LtEq | TokenKind::Gt | TokenKind::GtEq => Precedence::Comparison,
+        TokenKind::DotDot | TokenKind::DotDotEq => Precedence::Range,
         TokenKind::Plus | TokenKind::Minus => Precedence::Additive,
         TokenKind::Star | TokenKind::Slash | TokenKind::Percent => Precedence::Multiplicative,
         TokenKind::StarStar => Precedence::Exponential,
@@ -95,6 +102,7 @@ fn binary_op(kind: TokenKind) -> Option<BinaryOp> {
 impl<'a> Parser<'a> {
     /// Parse an expression
     pub(super) fn parse_expr(&mut self) -> Option<Expr> {
+        let _profile = crate::profile::enter("parse_expr");
         self.parse_expr_precedence(Precedence::None)
     }
 
@@ -102,23 +110,28 @@ impl<'a> Parser<'a> {
     ///
     /// This allows instruction expressions to use `?` with a different syntax
     /// (`condition ? inst else inst`) than expression ternary (`condition ? expr : expr`).
+    /// It also stops before `..`, which starts the next postfix item.
     pub(super) fn parse_expr_before_question(&mut self) -> Option<Expr> {
+        let no_range = std::mem::replace(&mut self.no_range, true);
         // Parse with minimum precedence of Ternary, which stops before `?`
-        self.parse_expr_precedence(Precedence::Ternary)
+        let expr = self.parse_expr_precedence(Precedence::Ternary);
+        self.no_range = no_range;
+        expr
     }
 
     /// Parse expression with minimum precedence (Pratt parsing)
     fn parse_expr_precedence(&mut self, min_prec: Precedence) -> Option<Expr> {
         // Parse prefix/primary expression
+        let start = self.current_span().start;
         let mut left = self.parse_prefix()?;
 
         // Parse infix operators while precedence is high enough
         while let Some(prec) = infix_precedence(self.current_kind()) {
-            if prec <= min_prec {
+            if prec <= min_prec || (prec == Precedence::Range && self.no_range) {
                 break;
             }
 
-            left = self.parse_infix(left, prec)?;
+            left = self.parse_infix(left, start, prec)?;
         }
 
         Some(left)
@@ -155,8 +168,8 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Parse infix expression
-    fn parse_infix(&mut self, left: Expr, prec: Precedence) -> Option<Expr> {
+    /// Parse infix expression; `left` starts at `start`
+    fn parse_infix(&mut self, left: Expr, start: u32, prec: Precedence) -> Option<Expr> {
         match self.current_kind() {
             // Ternary: a ? b : c
             TokenKind::Question => {
@@ -178,6 +191,7 @@ impl<'a> Parser<'a> {
                 Some(Expr::FieldAccess {
                     base: Box::new(left),
                     field,
+                    span: self.span(start, self.previous_end()),
                 })
             }
 
@@ -202,6 +216,18 @@ impl<'a> Parser<'a> {
                 })
             }
 
+            // Range: a..b, a..=b
+            TokenKind::DotDot | TokenKind::DotDotEq => {
+                let op = self.advance();
+                self.require_edition(Feature::Ranges, op.span);
+                let end = self.parse_expr_precedence(prec)?;
+                Some(Expr::Range {
+                    start: Box::new(left),
+                    end: Box::new(end),
+                    inclusive: op.kind == TokenKind::DotDotEq,
+                })
+            }
+
             // Binary operators
             kind => {
                 if let Some(op) = binary_op(kind) {
@@ -297,6 +323,9 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Lambda: (x) -> expr
+            TokenKind::LParen if self.is_lambda_start() => self.parse_lambda(),
+
             // Parenthesized expression
             TokenKind::LParen => {
                 self.advance();
@@ -409,6 +438,62 @@ impl<'a> Parser<'a> {
         Some((name, value))
     }
 
+    /// Check if the `(` at the cursor opens lambda parameters: its matching
+    /// `)` is followed by `->`
+    fn is_lambda_start(&self) -> bool {
+        let mut depth = 0;
+        let mut offset = 0;
+        loop {
+            match self.peek_n(offset).map(|t| t.kind) {
+                Some(TokenKind::LParen) => depth += 1,
+                Some(TokenKind::RParen) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Some(TokenKind::Eof) | None => return false,
+                _ => {}
+            }
+            offset += 1;
+        }
+        loop {
+            offset += 1;
+            match self.peek_n(offset).map(|t| t.kind) {
+                Some(TokenKind::Newline) => {}
+                kind => return kind == Some(TokenKind::Arrow),
+            }
+        }
+    }
+
+    /// Parse a lambda: `(a, b: i32) -> expr`
+    fn parse_lambda(&mut self) -> Option<Expr> {
+        let start = self.current_span().start;
+        self.expect(TokenKind::LParen)?;
+        let mut params = Vec::new();
+        while !self.check(TokenKind::RParen) {
+            let name = self.expect_identifier()?;
+            let type_expr = if self.consume(TokenKind::Colon).is_some() {
+                Some(self.parse_type_expr()?)
+            } else {
+                None
+            };
+            params.push(LambdaParam { name, type_expr });
+            if self.consume(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        let arrow = self.expect(TokenKind::Arrow)?;
+        self.require_edition(Feature::Lambdas, arrow.span);
+        let body = self.parse_expr()?;
+        Some(Expr::Lambda {
+            params,
+            body: Box::new(body),
+            span: self.span(start, self.previous_end()),
+        })
+    }
+
     /// Parse string template: "text ${expr} more"
     fn parse_string_template(&mut self) -> Option<Expr> {
         let mut elements = Vec::new();
@@ -566,7 +651,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse string content (remove quotes, handle escapes)
-    fn parse_string_content(&self, s: &str) -> String {
+    pub(super) fn parse_string_content(&self, s: &str) -> String {
         let inner = &s[1..s.len() - 1]; // Remove quotes
         self.unescape_string(inner)
     }
@@ -604,7 +689,7 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod tests {
     use crate::parser::parse;
-    use crate::ast::Expr;
+    use crate::ast::{BinaryOp, Expr};
 
     fn parse_expr(source: &str) -> Option<Expr> {
         // Wrap in a backend to test expression parsing
@@ -744,4 +829,41 @@ mod tests {
             panic!("Expected color");
         }
     }
+
+    #[test]
+    fn test_lambda() {
+        if let Some(Expr::Lambda { params, body, .. }) = parse_expr("(a, b: i32) -> a + b") {
+            let names: Vec<_> = params.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, ["a", "b"]);
+            assert!(params[0].type_expr.is_none() && params[1].type_expr.is_some());
+            assert!(matches!(*body, Expr::Binary { .. }));
+        } else {
+            panic!("Expected lambda");
+        }
+        assert!(matches!(parse_expr("() -> 1"), Some(Expr::Lambda { params, .. }) if params.is_empty()));
+
+        // Parentheses not followed by `->` still group
+        assert!(matches!(parse_expr("(a + b) * 2"), Some(Expr::Binary { .. })));
+        assert!(matches!(parse_expr("f((x) -> x)"), Some(Expr::Call { args, .. }) if matches!(args[0], Expr::Lambda { .. })));
+    }
+
+    #[test]
+    fn test_range() {
+        if let Some(Expr::Range { start, end, inclusive }) = parse_expr("0..count - 1") {
+            assert!(matches!(*start, Expr::Int(0)));
+            assert!(matches!(*end, Expr::Binary { op: BinaryOp::Sub, .. }));
+            assert!(!inclusive);
+        } else {
+            panic!("Expected range");
+        }
+        assert!(matches!(parse_expr("1..=n"), Some(Expr::Range { inclusive: true, .. })));
+
+        // Ranges bind looser than arithmetic and tighter than comparisons
+        assert!(matches!(
+            parse_expr("a + 1..b * 2"),
+            Some(Expr::Range { start, end, .. })
+                if matches!(*start, Expr::Binary { op: BinaryOp::Add, .. })
+                    && matches!(*end, Expr::Binary { op: BinaryOp::Mul, .. })
+        ));
+    }
 }
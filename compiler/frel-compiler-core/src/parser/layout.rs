@@ -11,7 +11,7 @@ use crate::ast::{
     HAlign, InstructionExpr, LayoutCell, LayoutRow, LayoutSize, LayoutStmt, MergeDirection, VAlign,
 };
 use crate::diagnostic::{Diagnostic, Diagnostics};
-use crate::source::Span;
+use crate::source::{FileId, Span};
 
 /// Layout parser state
 pub struct LayoutParser<'a> {
@@ -23,6 +23,8 @@ pub struct LayoutParser<'a> {
     current_line: usize,
     /// Base span offset (start of layout block in original source)
     base_offset: u32,
+    /// File the layout block is in
+    file: FileId,
     /// Accumulated diagnostics
     diagnostics: &'a mut Diagnostics,
 }
@@ -49,10 +51,17 @@ impl<'a> LayoutParser<'a> {
             lines,
             current_line: 0,
             base_offset,
+            file: FileId::default(),
             diagnostics,
         }
     }
 
+    /// Point the spans of the layout into `file`
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = file;
+        self
+    }
+
     /// Parse the layout content and return a LayoutStmt
     pub fn parse(&mut self) -> Option<LayoutStmt> {
         let mut instructions = Vec::new();
@@ -126,7 +135,8 @@ impl<'a> LayoutParser<'a> {
                             column_sizes.len(),
                             expected_cols
                         ),
-                        Span::new(self.base_offset, self.base_offset + self.content.len() as u32),
+                        Span::new(self.base_offset, self.base_offset + self.content.len() as u32)
+                            .in_file(self.file),
                     )
                     .with_code("E0301"),
                 );
@@ -431,7 +441,7 @@ impl<'a> LayoutParser<'a> {
             offset += line.len() as u32 + 1; // +1 for newline
         }
         let line_len = self.lines.get(line_idx).map(|l| l.len()).unwrap_or(0) as u32;
-        Span::new(self.base_offset + offset, self.base_offset + offset + line_len)
+        Span::new(self.base_offset + offset, self.base_offset + offset + line_len).in_file(self.file)
     }
 }
 
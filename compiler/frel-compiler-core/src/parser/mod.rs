@@ -20,10 +20,11 @@ mod theme;
 mod types;
 
 use crate::ast;
-use crate::diagnostic::{Diagnostic, Diagnostics, Label};
+use crate::diagnostic::{similar_name, Diagnostic, Diagnostics, Label, Suggestion};
+use crate::edition::{Edition, Experimental, Feature, Features};
 use crate::lexer::token::contextual;
 use crate::lexer::{Lexer, Token, TokenKind};
-use crate::source::Span;
+use crate::source::{FileId, Span};
 
 /// Parser state
 pub struct Parser<'a> {
@@ -31,6 +32,20 @@ pub struct Parser<'a> {
     tokens: Vec<Token>,
     cursor: usize,
     diagnostics: Diagnostics,
+    comments: Vec<ast::Comment>,
+    /// Blocks and statement starts found so far
+    outline: ast::Outline,
+    /// Edition the file is parsed with, the project's until an `edition` line
+    edition: Edition,
+    /// Span of the file's `edition` line, if any
+    edition_span: Option<Span>,
+    /// Experimental features the file may use
+    features: Features,
+    /// File of a multi-file module the spans point into
+    file: FileId,
+    /// Set while parsing the expression of an instruction, where `..` starts
+    /// the next postfix item instead of a range
+    no_range: bool,
 }
 
 /// Result of parsing - either success or failure with partial AST
@@ -42,20 +57,52 @@ pub struct ParseResult {
 impl<'a> Parser<'a> {
     /// Create a new parser from source code
     pub fn new(source: &'a str) -> Self {
-        let lexer = Lexer::new(source);
-        let (tokens, lex_diags) = lexer.tokenize();
+        Self::in_file(source, FileId::default())
+    }
+
+    /// Create a parser for one file of a multi-file module, stamping every
+    /// span of the AST and the diagnostics with `file`
+    pub fn in_file(source: &'a str, file: FileId) -> Self {
+        let _profile = crate::profile::enter("lex");
+        let lexer = Lexer::new(source).in_file(file);
+        let (tokens, comments, lex_diags) = lexer.tokenize_with_comments();
 
         Self {
             source,
             tokens,
             cursor: 0,
             diagnostics: lex_diags,
+            comments,
+            outline: ast::Outline::default(),
+            edition: Edition::LATEST,
+            edition_span: None,
+            features: Features::default(),
+            file,
+            no_range: false,
         }
     }
 
+    /// Parse with `edition` unless the file declares its own
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// Accept the experimental syntax of `features`
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
     /// Parse the source and return the AST with diagnostics
     pub fn parse(mut self) -> ParseResult {
+        let _profile = crate::profile::enter("parse");
+        let secrets = crate::lexer::secrets::check_secrets(self.source, &self.tokens, &self.comments);
         let file = self.parse_file();
+        self.diagnostics.merge(secrets);
+        if !self.file.is_default() {
+            self.diagnostics.set_file(self.file);
+        }
         ParseResult {
             file,
             diagnostics: self.diagnostics,
@@ -94,6 +141,20 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// End of the last token consumed, not counting the newlines skipped after it
+    fn previous_end(&self) -> u32 {
+        self.tokens[..self.cursor]
+            .iter()
+            .rev()
+            .find(|token| token.kind != TokenKind::Newline)
+            .map_or(0, |token| token.span.end)
+    }
+
+    /// Span from `start` to `end` in the file being parsed
+    fn span(&self, start: u32, end: u32) -> Span {
+        Span::new(start, end).in_file(self.file)
+    }
+
     /// Peek at the next token (after current)
     fn peek(&self) -> Option<&Token> {
         self.tokens.get(self.cursor + 1)
@@ -166,12 +227,36 @@ impl<'a> Parser<'a> {
     fn expect(&mut self, kind: TokenKind) -> Option<Token> {
         if self.check(kind) {
             Some(self.advance())
+        } else if matches!(kind, TokenKind::RParen | TokenKind::RBracket) && self.at_value_start() {
+            // `f(a b)`: another element follows, the separator is missing
+            let end = self.previous_span().end;
+            self.error_expected_with_suggestion(
+                &format!("',' or {}", kind.display_name()),
+                Suggestion::insert(end, ",", "insert ','"),
+            );
+            None
         } else {
             self.error_expected(kind.display_name());
             None
         }
     }
 
+    /// Check if the current token is a name or literal, which can start a list element
+    fn at_value_start(&self) -> bool {
+        matches!(
+            self.current_kind(),
+            TokenKind::Identifier
+                | TokenKind::IntLiteral
+                | TokenKind::FloatLiteral
+                | TokenKind::ColorLiteral
+                | TokenKind::StringLiteral
+                | TokenKind::StringTemplateStart
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null
+        )
+    }
+
     /// Expect an identifier, return its text
     fn expect_identifier(&mut self) -> Option<String> {
         if self.check(TokenKind::Identifier) {
@@ -205,7 +290,28 @@ impl<'a> Parser<'a> {
 
     /// Check if current token can start a top-level declaration
     fn at_top_level_start(&self) -> bool {
-        self.check(TokenKind::Identifier) && TokenKind::is_top_level_start_str(self.current_text())
+        self.check(TokenKind::Identifier)
+            && (TokenKind::is_top_level_start_str(self.current_text())
+                || self.at_backend_modifiers())
+    }
+
+    /// Check if the current token starts modifiers followed by `backend`
+    fn at_backend_modifiers(&self) -> bool {
+        let mut n = 0;
+        while let Some(token) = self.peek_n(n) {
+            if token.kind != TokenKind::Identifier {
+                return false;
+            }
+            match token.text(self.source) {
+                contextual::BACKEND => return n > 0,
+                contextual::FRAGMENT
+                | contextual::SCREEN
+                | contextual::SINGLETON
+                | contextual::UNDOABLE => n += 1,
+                _ => return false,
+            }
+        }
+        false
     }
 
     /// Check if the next token (after current identifier) continues an expression
@@ -256,15 +362,14 @@ impl<'a> Parser<'a> {
         );
     }
 
-    /// Report an "expected X" error with suggestion
-    #[allow(dead_code)]
-    fn error_expected_with_suggestion(&mut self, what: &str, suggestion: &str) {
+    /// Report an "expected X" error with a fix that tools can apply
+    fn error_expected_with_suggestion(&mut self, what: &str, suggestion: Suggestion) {
         let span = self.current_span();
         let got = self.current_kind().display_name();
         self.diagnostics.add(
             Diagnostic::error(format!("expected {}, found {}", what, got), span)
                 .with_code("E0200")
-                .with_help(suggestion),
+                .with_suggestion(suggestion),
         );
     }
 
@@ -314,6 +419,72 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Report `feature` at `span` if the file's edition predates it
+    ///
+    /// The construct is still parsed, so one missing edition bump doesn't
+    /// cascade into unrelated errors.
+    fn require_edition(&mut self, feature: Feature, span: Span) {
+        let required = feature.edition();
+        if self.edition >= required {
+            return;
+        }
+        let directive = format!("edition {}", required);
+        let suggestion = match self.edition_span {
+            Some(line) => Suggestion::new(line, directive.clone(), format!("use `{}`", directive)),
+            None => Suggestion::insert(
+                self.module_start(),
+                format!("{}\n", directive),
+                format!("add `{}`", directive),
+            ),
+        };
+        self.diagnostics.add(
+            Diagnostic::error(
+                format!(
+                    "{} require edition {}, but the file uses edition {}",
+                    feature.description(),
+                    required,
+                    self.edition
+                ),
+                span,
+            )
+            .with_code("E0208")
+            .with_help(format!(
+                "declare `{}` before the module, or set `edition = \"{}\"` in {}",
+                directive,
+                required,
+                crate::edition::CONFIG_FILE
+            ))
+            .with_suggestion(suggestion),
+        );
+    }
+
+    /// Report `feature` at `span` unless its gate is enabled
+    ///
+    /// Like edition-gated syntax, the construct is still parsed.
+    fn require_experimental(&mut self, feature: Experimental, span: Span) {
+        if !self.features.is_enabled(feature) {
+            self.diagnostics.add(feature.disabled(span));
+        }
+    }
+
+    /// Record that a declaration, statement or member starts at the current token
+    fn mark_item(&mut self) {
+        self.outline.items.push(self.current_span().start);
+    }
+
+    /// Record the braces around a body of statements or members
+    fn mark_block(&mut self, open: Span, close: Span) {
+        self.outline.blocks.push(Span::new(open.start, close.end));
+    }
+
+    /// Start of the first token of the file, where an `edition` line goes
+    fn module_start(&self) -> u32 {
+        self.tokens
+            .iter()
+            .find(|token| token.kind != TokenKind::Newline)
+            .map_or(0, |token| token.span.start)
+    }
+
     // =========================================================================
     // Top-level parsing
     // =========================================================================
@@ -322,12 +493,20 @@ impl<'a> Parser<'a> {
     fn parse_file(&mut self) -> Option<ast::File> {
         self.skip_newlines();
 
+        // Optional edition line: `edition 2026`
+        self.mark_item();
+        let edition = self.parse_edition_decl();
+
         // Parse module declaration
+        if self.edition_span.is_some() {
+            self.mark_item();
+        }
         let module = self.parse_module_decl()?;
 
         // Parse imports
         let mut imports = Vec::new();
         while self.check_identifier(contextual::IMPORT) {
+            self.mark_item();
             if let Some(import) = self.parse_import() {
                 imports.push(import);
             } else {
@@ -338,6 +517,7 @@ impl<'a> Parser<'a> {
         // Parse declarations
         let mut declarations = Vec::new();
         while !self.at_end() {
+            self.mark_item();
             if let Some(decl) = self.parse_top_level_decl() {
                 declarations.push(decl);
             } else {
@@ -348,11 +528,90 @@ impl<'a> Parser<'a> {
         Some(ast::File {
             module,
             source_path: None,
+            file: self.file,
+            edition,
+            features: self.features.clone(),
             imports,
             declarations,
+            comments: std::mem::take(&mut self.comments),
+            outline: std::mem::take(&mut self.outline),
         })
     }
 
+    /// Doc comment of the declaration starting at `start`
+    ///
+    /// Either a run of `///` lines or a `/** */` block, with nothing but
+    /// whitespace and single line breaks between it and the declaration.
+    fn doc_comment(&self, start: u32) -> Option<String> {
+        let end = self.comments.partition_point(|c| c.span.end <= start);
+        let mut lines = Vec::new();
+        let mut next = start;
+        for comment in self.comments[..end].iter().rev() {
+            let gap = Span::new(comment.span.end, next).text(self.source);
+            if !gap.trim().is_empty() || gap.matches('\n').count() > 1 {
+                break;
+            }
+            let text = comment.span.text(self.source);
+            match comment.kind {
+                ast::CommentKind::Line if is_doc_line(text) => {
+                    let line = &text[3..];
+                    lines.push(line.strip_prefix(' ').unwrap_or(line).trim_end());
+                    next = comment.span.start;
+                }
+                ast::CommentKind::Block if lines.is_empty() && is_doc_block(text) => {
+                    return Some(doc_block_text(&text[3..text.len() - 2]));
+                }
+                _ => break,
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        lines.reverse();
+        Some(lines.join("\n"))
+    }
+
+    /// Parse an edition declaration: edition 2026
+    fn parse_edition_decl(&mut self) -> Option<Edition> {
+        if !self.check_identifier(contextual::EDITION)
+            || self.peek_kind() != Some(TokenKind::IntLiteral)
+        {
+            return None;
+        }
+        let start = self.advance().span.start;
+        let value = self.advance();
+        let span = self.span(start, value.span.end);
+        self.edition_span = Some(span);
+
+        let name = value.text(self.source);
+        match Edition::parse(name) {
+            Some(edition) => {
+                self.edition = edition;
+                Some(edition)
+            }
+            None => {
+                let mut diagnostic = Diagnostic::error(
+                    format!(
+                        "unknown edition {} (expected one of {})",
+                        name,
+                        Edition::names()
+                    ),
+                    value.span,
+                )
+                .with_code("E0209");
+                if let Some(similar) = similar_name(name, Edition::ALL.map(Edition::as_str)) {
+                    diagnostic = diagnostic.with_suggestion(Suggestion::new(
+                        value.span,
+                        similar,
+                        format!("did you mean `{}`?", similar),
+                    ));
+                }
+                self.diagnostics.add(diagnostic);
+                None
+            }
+        }
+    }
+
     /// Parse module declaration: module foo.bar.baz
     fn parse_module_decl(&mut self) -> Option<String> {
         self.expect_contextual(contextual::MODULE)?;
@@ -397,7 +656,7 @@ impl<'a> Parser<'a> {
         Some(ast::Import {
             path: parts.join("."),
             import_all,
-            span: Span::new(start, end),
+            span: self.span(start, end),
         })
     }
 
@@ -412,6 +671,9 @@ impl<'a> Parser<'a> {
                 contextual::BACKEND => {
                     return self.parse_backend().map(ast::TopLevelDecl::Backend)
                 }
+                _ if self.at_backend_modifiers() => {
+                    return self.parse_backend().map(ast::TopLevelDecl::Backend)
+                }
                 contextual::CONTRACT => {
                     return self.parse_contract().map(ast::TopLevelDecl::Contract)
                 }
@@ -422,16 +684,83 @@ impl<'a> Parser<'a> {
                 _ => {}
             }
         }
-        self.error_expected("declaration (blueprint, backend, scheme, enum, contract, theme, or arena)");
+        let what = "declaration (blueprint, backend, scheme, enum, contract, theme, or arena)";
+        // A misspelled keyword: `bluprint Main { ... }`
+        if self.check(TokenKind::Identifier) {
+            let span = self.current_span();
+            if let Some(keyword) = similar_name(self.current_text(), contextual::DECLARATIONS) {
+                let message = format!("replace with '{}'", keyword);
+                self.error_expected_with_suggestion(what, Suggestion::new(span, keyword, message));
+                return None;
+            }
+        }
+        self.error_expected(what);
         None
     }
 }
 
+fn is_doc_line(text: &str) -> bool {
+    text.starts_with("///") && !text.starts_with("////")
+}
+
+fn is_doc_block(text: &str) -> bool {
+    text.starts_with("/**") && !text.starts_with("/**/") && text.ends_with("*/")
+}
+
+/// Text of a `/** */` comment body, without the ` * ` line prefixes
+fn doc_block_text(body: &str) -> String {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+    let first = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|l| !l.is_empty()).map_or(first, |i| i + 1);
+    lines[first..last].join("\n")
+}
+
 /// Parse Frel source code
 pub fn parse(source: &str) -> ParseResult {
     Parser::new(source).parse()
 }
 
+/// Parse source code with a known file path and the project's edition
+pub fn parse_with_edition(source: &str, path: &str, edition: Edition) -> ParseResult {
+    parse_with_features(source, path, edition, Features::default())
+}
+
+/// Parse source code with a known file path, the project's edition and
+/// enabled experimental features
+pub fn parse_with_features(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+) -> ParseResult {
+    parse_in_file(source, path, edition, features, FileId::default())
+}
+
+/// Parse one file of a multi-file module, with its spans in `file`
+pub fn parse_in_file(
+    source: &str,
+    path: &str,
+    edition: Edition,
+    features: Features,
+    file: FileId,
+) -> ParseResult {
+    let mut result = Parser::in_file(source, file)
+        .with_edition(edition)
+        .with_features(features)
+        .parse();
+    if let Some(ref mut file) = result.file {
+        file.source_path = Some(path.to_string());
+    }
+    result
+}
+
 /// Parse source code with a known file path (for better diagnostics)
 pub fn parse_with_path(source: &str, path: &str) -> ParseResult {
     let mut result = Parser::new(source).parse();
@@ -445,6 +774,24 @@ pub fn parse_with_path(source: &str, path: &str) -> ParseResult {
 mod tests {
     use super::*;
 
+    /// Compare the AST of every `test-data/parser/**/*.frel` that has a
+    /// sibling `.ast.json` with it, normalized per the directory's
+    /// `test.toml`; set `FREL_UPDATE_GOLDEN=1` to rewrite the expected files.
+    #[test]
+    fn test_ast_golden() {
+        let golden = crate::golden::Golden::new("parser");
+        for source in golden.sources("frel") {
+            let expected_path = source.with_extension("ast.json");
+            if !expected_path.exists() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&source).unwrap();
+            let result = parse_with_path(&text, &golden.name(&source));
+            assert!(!result.diagnostics.has_errors(), "{}", source.display());
+            golden.check_json(&expected_path, &result.file.unwrap());
+        }
+    }
+
     #[test]
     fn test_parse_module() {
         let result = parse("module test.example");
@@ -453,6 +800,21 @@ mod tests {
         assert_eq!(file.module, "test.example");
     }
 
+    #[test]
+    fn test_parse_in_file() {
+        let file = FileId(3);
+        let source = "module test\n\nbackend A { count : i32 = 0 }\n\nblueprint {}\n";
+        let result = Parser::in_file(source, file).parse();
+        let ast = result.file.unwrap();
+        assert_eq!(ast.file, file);
+        let ast::TopLevelDecl::Backend(backend) = &ast.declarations[0] else {
+            panic!("expected a backend");
+        };
+        assert_eq!(backend.span.file, file);
+        assert!(result.diagnostics.has_errors());
+        assert!(result.diagnostics.iter().all(|diag| diag.span.file == file));
+    }
+
     #[test]
     fn test_parse_import() {
         // Single-declaration import
@@ -481,4 +843,148 @@ mod tests {
         let result = parse("foo.bar\nblueprint Test {}");
         assert!(result.diagnostics.has_errors());
     }
+
+    #[test]
+    fn test_fix_suggestions() {
+        let source = "module test\n\nbluprint Main {\n    text { \"a\" }\n}\n\nbackend Store {\n    tags: List<String> = [\"a\" \"b\"]\n}\n";
+        let result = parse(source);
+        let (fixed, count) = result.diagnostics.apply_fixes(source);
+        assert_eq!(count, 2, "{:?}", result.diagnostics);
+        assert!(fixed.contains("blueprint Main {"));
+        assert!(fixed.contains("[\"a\", \"b\"]"));
+
+        let fixed = parse(&fixed);
+        assert!(!fixed.diagnostics.has_errors(), "{:?}", fixed.diagnostics);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let source = r#"module test
+
+/// The main screen
+///
+/// Shows a greeting.
+blueprint Main {
+    text { "hello" }
+}
+
+/**
+ * Session state
+ */
+singleton backend Session {
+    user : String = ""
+}
+
+/// Detached by a blank line
+
+// Not a doc comment
+scheme User {
+    name : String
+}
+
+/// Followed by a plain comment
+// plain
+blueprint Other { }
+"#;
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let file = result.file.unwrap();
+        let docs: Vec<Option<&str>> = file
+            .declarations
+            .iter()
+            .map(|decl| match decl {
+                ast::TopLevelDecl::Blueprint(b) => b.doc.as_deref(),
+                ast::TopLevelDecl::Backend(b) => b.doc.as_deref(),
+                ast::TopLevelDecl::Scheme(s) => s.doc.as_deref(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            [
+                Some("The main screen\n\nShows a greeting."),
+                Some("Session state"),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edition_gating() {
+        let source = "module test\n\nsingleton backend Session {\n    latest command refresh()\n}\n";
+
+        // The latest edition accepts everything
+        let result = parse(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        assert_eq!(result.file.unwrap().edition, None);
+
+        // An older project edition reports each newer construct, but still parses it
+        let result = Parser::new(source).with_edition(Edition::E2025).parse();
+        let errors: Vec<_> = result.diagnostics.iter().collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].code.as_deref(), Some("E0208"));
+        assert_eq!(
+            errors[0].message,
+            "backend lifetime modifiers require edition 2026, but the file uses edition 2025"
+        );
+        assert_eq!(errors[0].span.text(source), "singleton");
+        assert_eq!(errors[0].suggestions[0].replacement, "edition 2026\n");
+        assert_eq!(errors[1].span.text(source), "latest");
+        let file = result.file.unwrap();
+        assert!(matches!(
+            &file.declarations[0],
+            ast::TopLevelDecl::Backend(b) if b.lifetime == ast::BackendLifetime::Singleton
+        ));
+
+        // The file's own edition overrides the project's, in both directions
+        let pinned = format!("edition 2025\n{}", source);
+        let result = parse(&pinned);
+        let errors: Vec<_> = result.diagnostics.iter().collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        let fix = &errors[0].suggestions[0];
+        assert_eq!(fix.span.text(&pinned), "edition 2025");
+        assert_eq!(fix.replacement, "edition 2026");
+        assert_eq!(result.file.unwrap().edition, Some(Edition::E2025));
+
+        let bumped = format!("edition 2026\n{}", source);
+        let result = Parser::new(&bumped).with_edition(Edition::E2025).parse();
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+
+        // Unknown editions
+        let result = parse("edition 20266\nmodule test\n");
+        let error = result.diagnostics.iter().next().unwrap();
+        assert_eq!(error.code.as_deref(), Some("E0209"));
+        assert_eq!(error.message, "unknown edition 20266 (expected one of 2025, 2026)");
+        assert_eq!(error.suggestions[0].replacement, "2026");
+    }
+
+    #[test]
+    fn test_feature_gating() {
+        let source = "module test\n\nbackend Users {\n    command load(id : i32) : String\n}\n";
+
+        // Experimental syntax is parsed, but reported without its gate
+        let result = parse(source);
+        let errors: Vec<_> = result.diagnostics.iter().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code.as_deref(), Some("E0210"));
+        assert_eq!(
+            errors[0].message,
+            "command results are experimental and the `command-results` feature is not enabled"
+        );
+        assert_eq!(errors[0].span.text(source), "String");
+
+        let features = [Experimental::CommandResults].into_iter().collect();
+        let result = parse_with_features(source, "test.frel", Edition::LATEST, features);
+        assert!(!result.diagnostics.has_errors());
+        let file = result.file.unwrap();
+        assert!(file.features.is_enabled(Experimental::CommandResults));
+        let ast::TopLevelDecl::Backend(backend) = &file.declarations[0] else {
+            panic!("expected backend");
+        };
+        let ast::BackendMember::Command(load) = &backend.members[0] else {
+            panic!("expected command");
+        };
+        assert!(load.return_type.is_some());
+    }
 }
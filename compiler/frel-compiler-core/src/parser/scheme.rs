@@ -9,13 +9,16 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse scheme declaration
     pub(super) fn parse_scheme(&mut self) -> Option<Scheme> {
+        let _profile = crate::profile::enter("parse_scheme");
         let start = self.current_span().start;
+        let doc = self.doc_comment(start);
         self.expect_contextual(contextual::SCHEME)?;
         let name = self.expect_identifier()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut members = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(member) = self.parse_scheme_member() {
                 members.push(member);
             } else {
@@ -25,9 +28,15 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
-        Some(Scheme { name, members, span })
+        let span = self.span(start, end_span.end);
+        Some(Scheme {
+            name,
+            members,
+            doc,
+            span,
+        })
     }
 
     /// Parse a scheme member
@@ -40,7 +49,7 @@ impl<'a> Parser<'a> {
             let type_expr = self.parse_type_expr()?;
             self.expect(TokenKind::Eq)?;
             let expr = self.parse_expr()?;
-            let span = crate::source::Span::new(start, self.previous_span().end);
+            let span = self.span(start, self.previous_span().end);
             Some(SchemeMember::Virtual(VirtualField {
                 name,
                 type_expr,
@@ -61,7 +70,7 @@ impl<'a> Parser<'a> {
                 }
             }
 
-            let span = crate::source::Span::new(start, self.previous_span().end);
+            let span = self.span(start, self.previous_span().end);
             Some(SchemeMember::Field(SchemeField {
                 name,
                 type_expr,
@@ -75,7 +84,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse a field instruction (.. identity, .. range { min: 0 max: 100 })
-    fn parse_field_instruction(&mut self) -> Option<FieldInstruction> {
+    pub(super) fn parse_field_instruction(&mut self) -> Option<FieldInstruction> {
         let name = self.expect_identifier()?;
 
         let params = if self.consume(TokenKind::LBrace).is_some() {
@@ -9,13 +9,15 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse theme declaration
     pub(super) fn parse_theme(&mut self) -> Option<Theme> {
+        let _profile = crate::profile::enter("parse_theme");
         let start = self.current_span().start;
         self.expect_contextual(contextual::THEME)?;
         let name = self.expect_identifier()?;
-        self.expect(TokenKind::LBrace)?;
+        let open = self.expect(TokenKind::LBrace)?.span;
 
         let mut members = Vec::new();
         while !self.check(TokenKind::RBrace) && !self.at_end() {
+            self.mark_item();
             if let Some(member) = self.parse_theme_member() {
                 members.push(member);
             } else {
@@ -25,8 +27,9 @@ impl<'a> Parser<'a> {
 
         let end_span = self.current_span();
         self.expect(TokenKind::RBrace)?;
+        self.mark_block(open, end_span);
 
-        let span = crate::source::Span::new(start, end_span.end);
+        let span = self.span(start, end_span.end);
         Some(Theme { name, members, span })
     }
 
@@ -41,10 +44,11 @@ impl<'a> Parser<'a> {
             TokenKind::Set => {
                 self.advance();
                 let name = self.expect_identifier()?;
-                self.expect(TokenKind::LBrace)?;
+                let open = self.expect(TokenKind::LBrace)?.span;
 
                 let mut instructions = Vec::new();
                 while !self.check(TokenKind::RBrace) && !self.at_end() {
+                    self.mark_item();
                     if let Some(instr) = self.parse_instruction() {
                         instructions.push(instr);
                     } else {
@@ -52,7 +56,8 @@ impl<'a> Parser<'a> {
                     }
                 }
 
-                self.expect(TokenKind::RBrace)?;
+                let close = self.expect(TokenKind::RBrace)?.span;
+                self.mark_block(open, close);
 
                 Some(ThemeMember::InstructionSet(InstructionSet {
                     name,
@@ -62,17 +67,19 @@ impl<'a> Parser<'a> {
             TokenKind::Variant => {
                 self.advance();
                 let name = self.expect_identifier()?;
-                self.expect(TokenKind::LBrace)?;
+                let open = self.expect(TokenKind::LBrace)?.span;
 
                 let mut overrides = Vec::new();
                 while !self.check(TokenKind::RBrace) && !self.at_end() {
+                    self.mark_item();
                     let field_name = self.expect_identifier()?;
                     self.expect(TokenKind::Eq)?;
                     let value = self.parse_expr()?;
                     overrides.push((field_name, value));
                 }
 
-                self.expect(TokenKind::RBrace)?;
+                let close = self.expect(TokenKind::RBrace)?.span;
+                self.mark_block(open, close);
 
                 Some(ThemeMember::Variant(ThemeVariant { name, overrides }))
             }
@@ -91,7 +98,7 @@ impl<'a> Parser<'a> {
                     None
                 };
 
-                let span = crate::source::Span::new(start, self.previous_span().end);
+                let span = self.span(start, self.previous_span().end);
                 Some(ThemeMember::Field(ThemeField {
                     name,
                     is_asset,
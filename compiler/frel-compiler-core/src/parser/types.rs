@@ -6,8 +6,10 @@
 // - Reference types (ref T, draft T)
 // - Collection types (List<T>, Map<K, V>)
 // - Blueprint types (Blueprint<P1, P2>)
+// - Function types ((T, U) -> R)
 
 use crate::ast::TypeExpr;
+use crate::edition::Feature;
 use crate::lexer::TokenKind;
 
 use super::Parser;
@@ -15,6 +17,7 @@ use super::Parser;
 impl<'a> Parser<'a> {
     /// Parse a type expression
     pub(super) fn parse_type_expr(&mut self) -> Option<TypeExpr> {
+        let _profile = crate::profile::enter("parse_type_expr");
         let base = self.parse_type_base()?;
 
         // Check for nullable suffix
@@ -97,6 +100,22 @@ impl<'a> Parser<'a> {
                     _ => Some(TypeExpr::Named(name)),
                 }
             }
+            TokenKind::LParen => {
+                self.advance();
+                let params = if self.check(TokenKind::RParen) {
+                    vec![]
+                } else {
+                    self.parse_type_list()?
+                };
+                self.expect(TokenKind::RParen)?;
+                let arrow = self.expect(TokenKind::Arrow)?;
+                self.require_edition(Feature::Lambdas, arrow.span);
+                let ret = self.parse_type_expr()?;
+                Some(TypeExpr::Function {
+                    params,
+                    ret: Box::new(ret),
+                })
+            }
             _ => {
                 self.error_expected("type");
                 None
@@ -175,4 +194,12 @@ mod tests {
             panic!("Expected Nullable");
         }
     }
+
+    #[test]
+    fn test_function_type() {
+        let t = parse_type("(String, i32) -> bool").unwrap();
+        assert_eq!(t.to_string(), "(String, i32) -> bool");
+        let t = parse_type("() -> unit").unwrap();
+        assert!(matches!(t, crate::ast::TypeExpr::Function { params, .. } if params.is_empty()));
+    }
 }
@@ -0,0 +1,185 @@
+// Internal profiler for the Frel compiler
+//
+// A hierarchical profiler for finding hot paths in the compiler itself.
+// Parser productions and semantic passes open a span with `enter`; the time
+// spent in each stack of spans, excluding nested spans, is accumulated and
+// written in the folded-stack format read by flamegraph tools
+// (`inferno-flamegraph`, `flamegraph.pl`):
+//
+//     parse;parse_blueprint;parse_expr 1520
+//
+// Values are microseconds. Profiling is per thread and off until `start` is
+// called; while off, `enter` only checks a thread-local flag.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+#[derive(Default)]
+struct Profiler {
+    stack: Vec<Frame>,
+    /// Self time per stack, keyed by the `;`-joined span names
+    stacks: HashMap<String, Duration>,
+}
+
+struct Frame {
+    name: &'static str,
+    start: Instant,
+    /// Time spent in nested spans
+    nested: Duration,
+}
+
+/// Open span; closed when dropped
+#[must_use = "the span is closed when the guard is dropped"]
+pub struct Guard {
+    active: bool,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.active {
+            PROFILER.with(|profiler| profiler.borrow_mut().exit());
+        }
+    }
+}
+
+impl Profiler {
+    fn exit(&mut self) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = frame.start.elapsed();
+        let mut key = self
+            .stack
+            .iter()
+            .map(|f| f.name)
+            .collect::<Vec<_>>()
+            .join(";");
+        if !key.is_empty() {
+            key.push(';');
+        }
+        key.push_str(frame.name);
+
+        *self.stacks.entry(key).or_default() += elapsed.saturating_sub(frame.nested);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.nested += elapsed;
+        }
+    }
+}
+
+/// Start recording spans on this thread, discarding earlier results
+pub fn start() {
+    PROFILER.with(|profiler| *profiler.borrow_mut() = Profiler::default());
+    ENABLED.with(|enabled| enabled.set(true));
+}
+
+/// Stop recording and return what was recorded since `start`
+pub fn finish() -> Profile {
+    ENABLED.with(|enabled| enabled.set(false));
+    let profiler = PROFILER.with(|profiler| std::mem::take(&mut *profiler.borrow_mut()));
+    let mut stacks: Vec<(String, Duration)> = profiler.stacks.into_iter().collect();
+    stacks.sort();
+    Profile { stacks }
+}
+
+/// Check if spans are being recorded on this thread
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Open a span named `name`, nested in the currently open span
+pub fn enter(name: &'static str) -> Guard {
+    if !is_enabled() {
+        return Guard { active: false };
+    }
+    PROFILER.with(|profiler| {
+        profiler.borrow_mut().stack.push(Frame {
+            name,
+            start: Instant::now(),
+            nested: Duration::ZERO,
+        })
+    });
+    Guard { active: true }
+}
+
+/// Self time per stack of spans
+#[derive(Debug, Default)]
+pub struct Profile {
+    stacks: Vec<(String, Duration)>,
+}
+
+impl Profile {
+    /// Stacks and their self time, sorted by stack
+    pub fn stacks(&self) -> &[(String, Duration)] {
+        &self.stacks
+    }
+
+    /// Total time of all spans
+    pub fn total(&self) -> Duration {
+        self.stacks.iter().map(|(_, time)| *time).sum()
+    }
+
+    /// Render in the folded-stack format, one `stack microseconds` line per stack
+    pub fn to_folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, time) in &self.stacks {
+            out.push_str(stack);
+            out.push(' ');
+            out.push_str(&time.as_micros().to_string());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_spans() {
+        start();
+        {
+            let _outer = enter("outer");
+            for _ in 0..2 {
+                let _inner = enter("inner");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        let profile = finish();
+
+        let names: Vec<&str> = profile.stacks().iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(names, ["outer", "outer;inner"]);
+        assert!(profile.stacks()[1].1 >= Duration::from_millis(2));
+        assert!(profile.total() >= Duration::from_millis(2));
+
+        let folded = profile.to_folded();
+        assert!(folded.starts_with("outer "), "{}", folded);
+        assert!(folded.contains("\nouter;inner "), "{}", folded);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let guard = enter("ignored");
+        assert!(!guard.active);
+        drop(guard);
+        assert!(finish().stacks().is_empty());
+    }
+
+    #[test]
+    fn test_compiler_spans() {
+        start();
+        let result = crate::parse_file("module test\n\nblueprint Main {\n    text { 1 + 2 }\n}\n");
+        crate::analyze(&result.file.unwrap());
+        let folded = finish().to_folded();
+
+        assert!(folded.contains("\nparse;parse_blueprint;parse_expr "), "{}", folded);
+        assert!(folded.contains("\nresolve "), "{}", folded);
+        assert!(folded.contains("\ntypecheck "), "{}", folded);
+    }
+}
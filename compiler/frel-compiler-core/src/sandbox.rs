@@ -0,0 +1,189 @@
+// Plugin sandbox for Frel
+//
+// Code generators and semantic passes may come from third parties. The plugin
+// API gives them no ambient authority: what they may read, write and look up
+// is granted by the host through the `PluginContext` passed to them:
+//
+// - reading the files under the project root
+// - reading and writing the files under the build directory
+// - the environment variables granted, by default those starting with `FREL_`
+//
+// Paths are relative to the directory they are in. Absolute paths, `..`, and
+// symbolic links leading out of the directory are denied with
+// `io::ErrorKind::PermissionDenied`, as is any file access through a context
+// without the directory, e.g. in tests or the playground.
+//
+// Rust cannot stop a plugin from calling `std::fs` or `std::env` itself. With
+// the context, a plugin never needs to, so reviewing a plugin for such calls
+// is enough to know what it can touch.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Prefix of the environment variables granted by `with_process_env`
+pub const ENV_PREFIX: &str = "FREL_";
+
+/// Capabilities granted to a plugin
+#[derive(Debug, Clone, Default)]
+pub struct PluginContext {
+    project_root: Option<PathBuf>,
+    build_dir: Option<PathBuf>,
+    env: BTreeMap<String, String>,
+}
+
+impl PluginContext {
+    /// A context granting nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant reading the files under `root`
+    pub fn with_project_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.project_root = Some(root.into());
+        self
+    }
+
+    /// Grant reading and writing the files under `dir`
+    pub fn with_build_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.build_dir = Some(dir.into());
+        self
+    }
+
+    /// Grant an environment variable with `value`
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Grant the variables of the compiler's environment starting with `FREL_`
+    pub fn with_process_env(mut self) -> Self {
+        for (name, value) in std::env::vars() {
+            if name.starts_with(ENV_PREFIX) {
+                self.env.insert(name, value);
+            }
+        }
+        self
+    }
+
+    /// Value of a granted environment variable
+    pub fn env(&self, name: &str) -> Option<&str> {
+        self.env.get(name).map(String::as_str)
+    }
+
+    /// Read a file of the project, by its path relative to the project root
+    pub fn read(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        fs::read_to_string(confine(self.project_root.as_deref(), path.as_ref(), "project root")?)
+    }
+
+    /// Read a file of the build directory, by its path relative to it
+    pub fn read_build(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        fs::read_to_string(confine(self.build_dir.as_deref(), path.as_ref(), "build directory")?)
+    }
+
+    /// Write a file of the build directory, creating its directories
+    pub fn write_build(&self, path: impl AsRef<Path>, contents: &str) -> io::Result<()> {
+        let full = confine(self.build_dir.as_deref(), path.as_ref(), "build directory")?;
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(full, contents)
+    }
+}
+
+/// `path` under `root`, if it stays there
+fn confine(root: Option<&Path>, path: &Path, what: &str) -> io::Result<PathBuf> {
+    let denied = |reason: &str| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{}: {}", path.display(), reason),
+        )
+    };
+    let root = root.ok_or_else(|| denied(&format!("no {} is granted to plugins", what)))?;
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(denied(&format!("not a path inside the {}", what)));
+    }
+
+    // Symbolic links must not lead out of the root; the nearest existing
+    // ancestor is where the path would be created
+    let full = root.join(path);
+    let canonical_root = root.canonicalize()?;
+    let existing = full
+        .ancestors()
+        .find(|p| p.exists())
+        .map(Path::canonicalize)
+        .transpose()?;
+    if existing.is_none_or(|p| !p.starts_with(&canonical_root)) {
+        return Err(denied(&format!("leads out of the {}", what)));
+    }
+    Ok(full)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("frel-sandbox-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("project")).unwrap();
+        fs::create_dir_all(dir.join("build")).unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        fs::write(dir.join("project/frel.toml"), "edition = \"2025\"").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_access_is_confined() {
+        let dir = scratch("files");
+        let context = PluginContext::new()
+            .with_project_root(dir.join("project"))
+            .with_build_dir(dir.join("build"));
+
+        assert_eq!(context.read("frel.toml").unwrap(), "edition = \"2025\"");
+        context.write_build("app/main.js", "main()").unwrap();
+        assert_eq!(context.read_build("./app/main.js").unwrap(), "main()");
+
+        for path in ["../secret.txt", "app/../../secret.txt"] {
+            let error = context.read(path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::PermissionDenied, "{}", path);
+        }
+        let absolute = dir.join("secret.txt");
+        assert_eq!(
+            context.read(&absolute).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            context.read("app/main.js").unwrap_err().kind(),
+            io::ErrorKind::NotFound,
+            "the build directory is not the project root"
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&dir, dir.join("project/escape")).unwrap();
+            assert_eq!(
+                context.read("escape/secret.txt").unwrap_err().kind(),
+                io::ErrorKind::PermissionDenied
+            );
+        }
+
+        let nothing = PluginContext::new();
+        assert_eq!(
+            nothing.read("frel.toml").unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_environment_is_granted() {
+        let context = PluginContext::new().with_env("FREL_API_URL", "https://api.example.com");
+        assert_eq!(context.env("FREL_API_URL"), Some("https://api.example.com"));
+        assert_eq!(context.env("HOME"), None);
+    }
+}
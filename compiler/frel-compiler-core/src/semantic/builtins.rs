@@ -0,0 +1,734 @@
+// Built-in functions for Frel
+//
+// This module provides:
+// - BUILTINS: The functions available in every module without an import,
+//   with the signatures the type checker checks calls against
+// - Builtin::eval: Constant evaluation of calls whose arguments are constants
+// - parse_pattern/parse_instant: The date pattern and ISO 8601 syntax the
+//   host runtimes implement as well
+// - is_currency_code/is_locale_tag: Checks of constant currency and locale
+//   arguments
+// - safe_url: The URL check of `url()`, which the host runtimes implement as
+//   well
+//
+// Built-ins are found after the scope chain and imports, so a declaration
+// with the same name shadows them. Host runtimes implement each one under
+// its name; the JavaScript runtime in `Builtins`.
+//
+// Host values: an Instant is a point in time, LocalDate/LocalTime/
+// LocalDateTime are ISO 8601 strings, a Duration is a number of
+// milliseconds and a Decimal is its decimal text. At compile time an Instant
+// is a ConstValue::Instant.
+
+use super::const_eval::{ConstEvalError, ConstValue};
+use super::types::Type;
+
+/// A built-in function
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: &'static [BuiltinParam],
+    result: fn() -> Type,
+    eval: fn(&[ConstValue]) -> Result<ConstValue, ConstEvalError>,
+    /// One-line description, shown on hover
+    pub doc: &'static str,
+}
+
+/// A parameter of a built-in function
+pub struct BuiltinParam {
+    pub name: &'static str,
+    /// Types an argument may have
+    pub types: &'static [Type],
+    /// Whether the argument can be left out (only trailing parameters)
+    pub optional: bool,
+    /// Check of a constant argument
+    pub check: Option<ArgumentCheck>,
+}
+
+/// Check of a constant argument; returns why the value is invalid
+pub type ArgumentCheck = fn(&ConstValue) -> Result<(), String>;
+
+/// All built-in functions
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "format",
+        params: &[
+            BuiltinParam {
+                name: "value",
+                types: &[Type::Instant, Type::LocalDate, Type::LocalTime, Type::LocalDateTime],
+                optional: false,
+                check: None,
+            },
+            BuiltinParam {
+                name: "pattern",
+                types: &[Type::String],
+                optional: false,
+                check: None,
+            },
+            BuiltinParam {
+                name: "zone",
+                types: &[Type::Timezone, Type::String],
+                optional: true,
+                check: None,
+            },
+        ],
+        result: || Type::String,
+        eval: eval_format,
+        doc: "Format a date or time with a pattern like \"yyyy-MM-dd HH:mm\"; an Instant is shown in `zone` (default: the host's time zone)",
+    },
+    Builtin {
+        name: "parseInstant",
+        params: &[BuiltinParam {
+            name: "text",
+            types: &[Type::String],
+            optional: false,
+            check: None,
+        }],
+        result: || Type::Nullable(Box::new(Type::Instant)),
+        eval: eval_parse_instant,
+        doc: "Parse an ISO 8601 date-time with an offset (or a date, at midnight UTC); null if the text is not one",
+    },
+    Builtin {
+        name: "humanize",
+        params: &[BuiltinParam {
+            name: "duration",
+            types: &[Type::Duration],
+            optional: false,
+            check: None,
+        }],
+        result: || Type::String,
+        eval: |_| Err(locale_dependent("humanize")),
+        doc: "Describe a duration in the largest whole unit, like \"3 hours\", in the host's locale",
+    },
+    Builtin {
+        name: "formatNumber",
+        params: &[
+            BuiltinParam {
+                name: "value",
+                types: NUMBER_TYPES,
+                optional: false,
+                check: None,
+            },
+            DECIMALS_PARAM,
+            LOCALE_PARAM,
+        ],
+        result: || Type::String,
+        eval: |_| Err(locale_dependent("formatNumber")),
+        doc: "Format a number with grouping in `locale` (default: `Builtins.locale`), with `decimals` fraction digits; a Decimal keeps its own digits by default",
+    },
+    Builtin {
+        name: "formatCurrency",
+        params: &[
+            BuiltinParam {
+                name: "amount",
+                types: NUMBER_TYPES,
+                optional: false,
+                check: None,
+            },
+            BuiltinParam {
+                name: "currency",
+                types: &[Type::String],
+                optional: false,
+                check: Some(check_currency),
+            },
+            LOCALE_PARAM,
+        ],
+        result: || Type::String,
+        eval: |_| Err(locale_dependent("formatCurrency")),
+        doc: "Format an amount of an ISO 4217 currency like \"EUR\" in `locale`, with the currency's fraction digits",
+    },
+    Builtin {
+        name: "formatPercent",
+        params: &[
+            BuiltinParam {
+                name: "ratio",
+                types: NUMBER_TYPES,
+                optional: false,
+                check: None,
+            },
+            DECIMALS_PARAM,
+            LOCALE_PARAM,
+        ],
+        result: || Type::String,
+        eval: |_| Err(locale_dependent("formatPercent")),
+        doc: "Format a ratio as a percentage (0.25 is \"25%\") in `locale`, with `decimals` fraction digits (default: 0)",
+    },
+    Builtin {
+        name: "url",
+        params: &[BuiltinParam {
+            name: "text",
+            types: &[Type::String],
+            optional: false,
+            check: None,
+        }],
+        result: || Type::String,
+        eval: eval_url,
+        doc: "Keep a URL that is relative or has a safe scheme (http, https, mailto, tel, a data: image); \"about:blank\" otherwise",
+    },
+    Builtin {
+        name: "raw",
+        params: &[BuiltinParam {
+            name: "text",
+            types: &[Type::String],
+            optional: false,
+            check: None,
+        }],
+        result: || Type::String,
+        eval: |args| Ok(args[0].clone()),
+        doc: "Pass a string to a URL parameter as is, marking its interpolations as trusted",
+    },
+];
+
+/// Numbers the formatting functions accept; a Decimal is formatted exactly
+const NUMBER_TYPES: &[Type] = &[Type::F64, Type::Decimal];
+
+/// Largest number of fraction digits a number is formatted with
+pub const MAX_DECIMALS: i64 = 20;
+
+const DECIMALS_PARAM: BuiltinParam = BuiltinParam {
+    name: "decimals",
+    types: &[Type::I32],
+    optional: true,
+    check: Some(check_decimals),
+};
+
+const LOCALE_PARAM: BuiltinParam = BuiltinParam {
+    name: "locale",
+    types: &[Type::String],
+    optional: true,
+    check: Some(check_locale),
+};
+
+/// Look up a built-in function by name
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+impl Builtin {
+    /// Type of the call's result
+    pub fn result(&self) -> Type {
+        (self.result)()
+    }
+
+    /// Smallest number of arguments a call needs
+    pub fn min_args(&self) -> usize {
+        self.params.iter().filter(|p| !p.optional).count()
+    }
+
+    /// Signature for messages, e.g. `humanize(duration: Duration) -> String`
+    pub fn signature(&self) -> String {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|p| {
+                let types: Vec<_> = p.types.iter().map(Type::to_string).collect();
+                format!(
+                    "{}{}: {}",
+                    p.name,
+                    if p.optional { "?" } else { "" },
+                    types.join(" | ")
+                )
+            })
+            .collect();
+        format!("{}({}) -> {}", self.name, params.join(", "), self.result())
+    }
+
+    /// Evaluate a call whose arguments are constants
+    pub fn eval(&self, args: &[ConstValue]) -> Result<ConstValue, ConstEvalError> {
+        if args.len() < self.min_args() || args.len() > self.params.len() {
+            return Err(ConstEvalError::TypeMismatch(format!(
+                "{} takes {} arguments, found {}",
+                self.name,
+                self.params.len(),
+                args.len()
+            )));
+        }
+        (self.eval)(args)
+    }
+}
+
+// ============================================================================
+// Date patterns
+// ============================================================================
+
+/// A part of a date pattern
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternPart {
+    /// A field letter repeated `width` times, e.g. `yyyy`
+    Field { letter: char, width: usize },
+    /// Text copied as is; `'quoted'` in the pattern
+    Literal(String),
+}
+
+/// Field letters of date patterns
+///
+/// `y` year, `M` month, `d` day, `E` weekday name, `H` hour (0-23), `h` hour
+/// (1-12), `m` minute, `s` second, `S` millisecond, `a` AM/PM. `MMM` and
+/// longer are month names. Other letters are copied as is.
+pub const PATTERN_LETTERS: &str = "yMdEHhmsSa";
+
+/// Split a date pattern into fields and literal text
+pub fn parse_pattern(pattern: &str) -> Vec<PatternPart> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // '' is a quote, 'text' is literal text
+            if chars.peek() == Some(&'\'') {
+                chars.next();
+                literal.push('\'');
+                continue;
+            }
+            while let Some(q) = chars.next() {
+                if q != '\'' {
+                    literal.push(q);
+                } else if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    literal.push('\'');
+                } else {
+                    break;
+                }
+            }
+        } else if PATTERN_LETTERS.contains(c) {
+            let mut width = 1;
+            while chars.peek() == Some(&c) {
+                chars.next();
+                width += 1;
+            }
+            if !literal.is_empty() {
+                parts.push(PatternPart::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(PatternPart::Field { letter: c, width });
+        } else {
+            literal.push(c);
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(PatternPart::Literal(literal));
+    }
+    parts
+}
+
+// ============================================================================
+// Argument checks
+// ============================================================================
+
+/// Whether a text is an ISO 4217 currency code: three uppercase letters
+pub fn is_currency_code(text: &str) -> bool {
+    text.len() == 3 && text.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// Whether a text is a well-formed BCP 47 language tag, e.g. `de`, `en-US`
+/// or `zh-Hant-TW`
+///
+/// Only the form is checked: a language of 2-3 or 5-8 letters followed by
+/// subtags of 1-8 letters and digits. Hosts fall back to a supported locale
+/// for tags they don't know.
+pub fn is_locale_tag(text: &str) -> bool {
+    let mut subtags = text.split('-');
+    let Some(language) = subtags.next() else {
+        return false;
+    };
+    matches!(language.len(), 2..=3 | 5..=8)
+        && language.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|tag| {
+            (1..=8).contains(&tag.len()) && tag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+fn check_currency(value: &ConstValue) -> Result<(), String> {
+    match value {
+        ConstValue::String(code) if !is_currency_code(code) => Err(format!(
+            "`{}` is not a currency code; expected three uppercase letters like \"EUR\"",
+            code
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_locale(value: &ConstValue) -> Result<(), String> {
+    match value {
+        ConstValue::String(tag) if !is_locale_tag(tag) => Err(format!(
+            "`{}` is not a locale; expected a language tag like \"en\" or \"de-AT\"",
+            tag
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_decimals(value: &ConstValue) -> Result<(), String> {
+    match value {
+        ConstValue::Int(n) if !(0..=MAX_DECIMALS).contains(n) => Err(format!(
+            "{} fraction digits are out of range; expected 0 to {}",
+            n, MAX_DECIMALS
+        )),
+        _ => Ok(()),
+    }
+}
+
+// ============================================================================
+// Constant evaluation
+// ============================================================================
+
+/// Error of a built-in whose result depends on the host's locale
+fn locale_dependent(name: &str) -> ConstEvalError {
+    ConstEvalError::NotConstant(format!("{} (its text depends on the host's locale)", name))
+}
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn eval_format(args: &[ConstValue]) -> Result<ConstValue, ConstEvalError> {
+    let (ConstValue::Instant(ms), ConstValue::String(pattern)) = (&args[0], &args[1]) else {
+        return Err(ConstEvalError::NotConstant(
+            "format of a value that is not an Instant".to_string(),
+        ));
+    };
+    match args.get(2) {
+        Some(ConstValue::String(zone)) if zone == "UTC" || zone == "Etc/UTC" => {}
+        _ => {
+            return Err(ConstEvalError::NotConstant(
+                "format outside UTC (it depends on the host's time zone)".to_string(),
+            ))
+        }
+    }
+
+    let days = ms.div_euclid(MS_PER_DAY);
+    let in_day = ms.rem_euclid(MS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let hour = in_day / 3_600_000;
+    let mut text = String::new();
+    for part in parse_pattern(pattern) {
+        match part {
+            PatternPart::Literal(literal) => text.push_str(&literal),
+            PatternPart::Field { letter, width } => {
+                let value = match letter {
+                    'y' if width == 2 => year.rem_euclid(100),
+                    'y' => year,
+                    'M' if width <= 2 => month,
+                    'd' => day,
+                    'H' => hour,
+                    'h' => (hour + 11) % 12 + 1,
+                    'm' => in_day / 60_000 % 60,
+                    's' => in_day / 1000 % 60,
+                    'S' => in_day % 1000,
+                    _ => {
+                        return Err(ConstEvalError::NotConstant(format!(
+                            "format with `{}` (names depend on the host's locale)",
+                            letter.to_string().repeat(width)
+                        )))
+                    }
+                };
+                let width = if letter == 'S' { 3 } else { width };
+                text.push_str(&format!("{:0width$}", value, width = width));
+            }
+        }
+    }
+    Ok(ConstValue::String(text))
+}
+
+fn eval_parse_instant(args: &[ConstValue]) -> Result<ConstValue, ConstEvalError> {
+    match &args[0] {
+        ConstValue::String(text) => Ok(parse_instant(text)
+            .map(ConstValue::Instant)
+            .unwrap_or(ConstValue::Null)),
+        other => Err(ConstEvalError::TypeMismatch(format!(
+            "parseInstant expects a string, found {}",
+            other.kind()
+        ))),
+    }
+}
+
+fn eval_url(args: &[ConstValue]) -> Result<ConstValue, ConstEvalError> {
+    match &args[0] {
+        ConstValue::String(text) => Ok(ConstValue::String(safe_url(text))),
+        other => Err(ConstEvalError::TypeMismatch(format!(
+            "url expects a string, found {}",
+            other.kind()
+        ))),
+    }
+}
+
+/// Schemes `url()` keeps; `data:` is kept for images only
+pub const SAFE_URL_SCHEMES: &[&str] = &["http", "https", "mailto", "tel"];
+
+/// `text` if it is a relative URL, has a safe scheme or is a `data:` image,
+/// `about:blank` otherwise
+///
+/// As browsers do, tabs and line breaks are removed and leading and trailing
+/// spaces and control characters trimmed first, so `java\tscript:` is not
+/// taken for a relative URL.
+pub fn safe_url(text: &str) -> String {
+    let url: String = text
+        .trim_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    let scheme_end = url.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')));
+    let Some(end) = scheme_end.filter(|&end| end > 0 && url[end..].starts_with(':')) else {
+        // No scheme: a relative URL
+        return url;
+    };
+    let scheme = url[..end].to_ascii_lowercase();
+    let data_image = scheme == "data" && url[end + 1..].to_ascii_lowercase().starts_with("image/");
+    if SAFE_URL_SCHEMES.contains(&scheme.as_str()) || data_image {
+        url
+    } else {
+        "about:blank".to_string()
+    }
+}
+
+/// Milliseconds since the Unix epoch of an ISO 8601 date-time with an offset
+/// (`2024-03-15T10:30:00Z`, `2024-03-15T10:30:00.250+01:00`) or a date
+/// (`2024-03-15`, midnight UTC)
+pub fn parse_instant(text: &str) -> Option<i64> {
+    let number = |s: &str| -> Option<i64> {
+        (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())).then(|| s.parse().ok())?
+    };
+    let (date, time) = match text.split_once(['T', 't']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut date_parts = date.splitn(3, '-');
+    let (y, m, d) = (date_parts.next()?, date_parts.next()?, date_parts.next()?);
+    if y.len() != 4 || m.len() != 2 || d.len() != 2 {
+        return None;
+    }
+    let (year, month, day) = (number(y)?, number(m)?, number(d)?);
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut ms = days_from_civil(year, month, day) * MS_PER_DAY;
+
+    let Some(time) = time else {
+        return Some(ms);
+    };
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let at = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(at);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (oh, om) = offset[1..].split_once(':')?;
+        if oh.len() != 2 || om.len() != 2 {
+            return None;
+        }
+        (clock, sign * (number(oh)? * 60 + number(om)?))
+    };
+
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction)) if (1..=9).contains(&fraction.len()) => {
+            let digits = &fraction[..fraction.len().min(3)];
+            (clock, number(digits)? * 10_i64.pow(3 - digits.len() as u32))
+        }
+        Some(_) => return None,
+        None => (clock, 0),
+    };
+    let fields: Vec<_> = clock.split(':').collect();
+    if !(2..=3).contains(&fields.len()) || fields.iter().any(|f| f.len() != 2) {
+        return None;
+    }
+    let hour = number(fields[0])?;
+    let minute = number(fields[1])?;
+    let second = fields.get(2).map_or(Some(0), |s| number(s))?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    ms += ((hour * 60 + minute - offset) * 60 + second) * 1000 + fraction;
+    Some(ms)
+}
+
+/// ISO 8601 text of an instant in UTC, e.g. `2024-03-15T10:30:00.000Z`
+pub fn format_instant(ms: i64) -> String {
+    let (year, month, day) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+    let in_day = ms.rem_euclid(MS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        in_day / 3_600_000,
+        in_day / 60_000 % 60,
+        in_day / 1000 % 60,
+        in_day % 1000
+    )
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: Vec<ConstValue>) -> Result<ConstValue, ConstEvalError> {
+        lookup(name).unwrap().eval(&args)
+    }
+
+    #[test]
+    fn test_parse_instant() {
+        assert_eq!(parse_instant("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_instant("2024-03-15"), Some(1_710_460_800_000));
+        assert_eq!(
+            parse_instant("2024-03-15T10:30:05.25+01:30"),
+            Some(1_710_460_800_000 + (9 * 3600 + 5) * 1000 + 250)
+        );
+        assert_eq!(parse_instant("1969-12-31T23:59:59Z"), Some(-1000));
+        assert_eq!(parse_instant("2024-02-30"), None);
+        assert_eq!(parse_instant("2024-03-15T10:30"), None);
+        assert_eq!(parse_instant("2024-03-15T25:00Z"), None);
+        assert_eq!(parse_instant("yesterday"), None);
+        assert_eq!(format_instant(-1000), "1969-12-31T23:59:59.000Z");
+    }
+
+    #[test]
+    fn test_safe_url() {
+        assert_eq!(safe_url("https://example.com/a.png"), "https://example.com/a.png");
+        assert_eq!(safe_url("/images/a.png?v=1:2"), "/images/a.png?v=1:2");
+        assert_eq!(safe_url(" MailTo:ada@example.com"), "MailTo:ada@example.com");
+        assert_eq!(safe_url("data:image/png;base64,AAAA"), "data:image/png;base64,AAAA");
+        assert_eq!(safe_url("javascript:alert(1)"), "about:blank");
+        assert_eq!(safe_url("\u{1}java\tscript:alert(1)"), "about:blank");
+        assert_eq!(safe_url("data:text/html,<script>"), "about:blank");
+        assert_eq!(
+            call("url", vec![ConstValue::String("JAVASCRIPT:x".into())]),
+            Ok(ConstValue::String("about:blank".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(
+            parse_pattern("dd.MM 'at' HH''"),
+            [
+                PatternPart::Field {
+                    letter: 'd',
+                    width: 2
+                },
+                PatternPart::Literal(".".to_string()),
+                PatternPart::Field {
+                    letter: 'M',
+                    width: 2
+                },
+                PatternPart::Literal(" at ".to_string()),
+                PatternPart::Field {
+                    letter: 'H',
+                    width: 2
+                },
+                PatternPart::Literal("'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let instant = call(
+            "parseInstant",
+            vec![ConstValue::String("2024-03-05T14:07:09.042Z".to_string())],
+        )
+        .unwrap();
+        let format = |pattern: &str, zone: Option<&str>| {
+            let mut args = vec![instant.clone(), ConstValue::String(pattern.to_string())];
+            args.extend(zone.map(|z| ConstValue::String(z.to_string())));
+            call("format", args)
+        };
+
+        assert_eq!(
+            format("yyyy-MM-dd HH:mm:ss.SSS", Some("UTC")),
+            Ok(ConstValue::String("2024-03-05 14:07:09.042".to_string()))
+        );
+        assert_eq!(
+            format("d/M/yy h 'o''clock'", Some("UTC")),
+            Ok(ConstValue::String("5/3/24 2 o'clock".to_string()))
+        );
+        // Local time and names are left to the host
+        assert!(matches!(
+            format("yyyy", None),
+            Err(ConstEvalError::NotConstant(_))
+        ));
+        assert!(matches!(
+            format("MMM d", Some("UTC")),
+            Err(ConstEvalError::NotConstant(_))
+        ));
+        assert_eq!(
+            call("parseInstant", vec![ConstValue::String("soon".to_string())]),
+            Ok(ConstValue::Null)
+        );
+        assert!(matches!(
+            call("humanize", vec![ConstValue::Int(1000)]),
+            Err(ConstEvalError::NotConstant(_))
+        ));
+    }
+
+    #[test]
+    fn test_argument_checks() {
+        assert!(is_currency_code("EUR"));
+        assert!(!is_currency_code("eur"));
+        assert!(!is_currency_code("EURO"));
+        assert!(is_locale_tag("de"));
+        assert!(is_locale_tag("en-US"));
+        assert!(is_locale_tag("zh-Hant-TW"));
+        assert!(!is_locale_tag(""));
+        assert!(!is_locale_tag("en_US"));
+        assert!(!is_locale_tag("english-"));
+
+        let decimals = DECIMALS_PARAM.check.unwrap();
+        assert!(decimals(&ConstValue::Int(2)).is_ok());
+        assert!(decimals(&ConstValue::Int(-1)).is_err());
+        assert!(matches!(
+            call(
+                "formatCurrency",
+                vec![ConstValue::Int(5), ConstValue::String("EUR".to_string())]
+            ),
+            Err(ConstEvalError::NotConstant(_))
+        ));
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            lookup("format").unwrap().signature(),
+            "format(value: Instant | LocalDate | LocalTime | LocalDateTime, pattern: String, zone?: Timezone | String) -> String"
+        );
+        assert_eq!(
+            lookup("formatCurrency").unwrap().signature(),
+            "formatCurrency(amount: f64 | Decimal, currency: String, locale?: String) -> String"
+        );
+        assert!(lookup("print").is_none());
+    }
+}
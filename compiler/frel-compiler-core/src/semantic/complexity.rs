@@ -0,0 +1,533 @@
+// Blueprint complexity report for Frel
+//
+// Estimates how expensive each blueprint is to build and keep up to date,
+// so slow blueprints can be spotted before runtime profiling:
+// - fragments created by the blueprint itself and, transitively, by the
+//   blueprints it instantiates (declared in the same file)
+// - the deepest nesting of `repeat`, including through instantiated blueprints
+// - reactive bindings (expressions reading names) and the names they depend on
+//
+// The cost weights every fragment by `ASSUMED_ITEMS` for each `repeat` it is
+// nested in, since repeat bodies are built once per item.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::ast::{
+    self, BlueprintStmt, BlueprintValue, ControlStmt, Expr, FragmentBody, InstructionExpr,
+    PostfixItem, TemplateElement,
+};
+use crate::source::Span;
+
+/// Items assumed per `repeat` when estimating cost
+pub const ASSUMED_ITEMS: usize = 10;
+
+/// Repeat nesting above which a blueprint is flagged
+pub const REPEAT_DEPTH_LIMIT: usize = 2;
+
+/// Estimated fragment count above which a blueprint is flagged
+pub const COST_LIMIT: usize = 1000;
+
+/// Reactive binding count above which a blueprint is flagged
+pub const BINDING_LIMIT: usize = 50;
+
+/// Complexity estimate of one blueprint
+#[derive(Debug, Clone, Serialize)]
+pub struct BlueprintComplexity {
+    pub name: String,
+    pub span: Span,
+    /// Fragments created directly by the blueprint
+    pub fragments: usize,
+    /// Fragments including those of instantiated blueprints, repeats counted once
+    pub total_fragments: usize,
+    /// Deepest `repeat` nesting, including instantiated blueprints
+    pub repeat_depth: usize,
+    /// Expressions that read at least one name
+    pub bindings: usize,
+    /// Distinct names read by those expressions
+    pub dependencies: usize,
+    /// Estimated fragments with `ASSUMED_ITEMS` items per repeat
+    pub cost: usize,
+    /// Why the blueprint is likely to be slow, empty if it is not
+    pub warnings: Vec<String>,
+}
+
+impl BlueprintComplexity {
+    /// Check if the blueprint crossed any threshold
+    pub fn is_slow(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+/// Complexity of the blueprints of a file, most expensive first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComplexityReport {
+    pub blueprints: Vec<BlueprintComplexity>,
+}
+
+impl ComplexityReport {
+    /// Blueprints likely to be slow
+    pub fn slow(&self) -> impl Iterator<Item = &BlueprintComplexity> {
+        self.blueprints.iter().filter(|bp| bp.is_slow())
+    }
+
+    /// Add the blueprints of another report, e.g. of another file of the module
+    pub fn merge(&mut self, other: ComplexityReport) {
+        self.blueprints.extend(other.blueprints);
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        self.blueprints
+            .sort_by(|a, b| b.cost.cmp(&a.cost).then_with(|| a.name.cmp(&b.name)));
+    }
+}
+
+/// Estimate the complexity of every blueprint in a file
+pub fn analyze_complexity(file: &ast::File) -> ComplexityReport {
+    let blueprints: Vec<(&ast::Blueprint, Usage)> = file
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Blueprint(bp) => Some((bp, Usage::collect(&bp.body))),
+            _ => None,
+        })
+        .collect();
+    let usages: HashMap<&str, &Usage> = blueprints
+        .iter()
+        .map(|(bp, usage)| (bp.name.as_str(), usage))
+        .collect();
+
+    let mut totals = Totals {
+        usages: &usages,
+        done: HashMap::new(),
+        visiting: Vec::new(),
+    };
+
+    let mut report = ComplexityReport {
+        blueprints: blueprints
+            .iter()
+            .map(|(bp, usage)| {
+                let total = totals.get(&bp.name);
+                let mut complexity = BlueprintComplexity {
+                    name: bp.name.clone(),
+                    span: bp.span,
+                    fragments: usage.creates.len(),
+                    total_fragments: total.fragments,
+                    repeat_depth: total.repeat_depth,
+                    bindings: usage.bindings,
+                    dependencies: usage.names.len(),
+                    cost: total.cost,
+                    warnings: Vec::new(),
+                };
+                complexity.warnings = warnings(&complexity);
+                complexity
+            })
+            .collect(),
+    };
+    report.sort();
+    report
+}
+
+fn warnings(bp: &BlueprintComplexity) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if bp.repeat_depth > REPEAT_DEPTH_LIMIT {
+        warnings.push(format!("`repeat` nested {} levels deep", bp.repeat_depth));
+    }
+    if bp.cost > COST_LIMIT {
+        warnings.push(format!(
+            "about {} fragments with {} items per `repeat`",
+            bp.cost, ASSUMED_ITEMS
+        ));
+    }
+    if bp.bindings > BINDING_LIMIT {
+        warnings.push(format!(
+            "{} reactive bindings over {} names",
+            bp.bindings, bp.dependencies
+        ));
+    }
+    warnings
+}
+
+/// Totals of a blueprint including the blueprints it instantiates
+#[derive(Clone, Copy, Default)]
+struct Total {
+    fragments: usize,
+    repeat_depth: usize,
+    cost: usize,
+}
+
+struct Totals<'a> {
+    usages: &'a HashMap<&'a str, &'a Usage<'a>>,
+    done: HashMap<&'a str, Total>,
+    /// Blueprints being totalled, to stop at recursive instantiation
+    visiting: Vec<&'a str>,
+}
+
+impl<'a> Totals<'a> {
+    fn get(&mut self, name: &'a str) -> Total {
+        if let Some(total) = self.done.get(name) {
+            return *total;
+        }
+        let Some(usage) = self.usages.get(name).copied() else {
+            return Total::default();
+        };
+        if self.visiting.contains(&name) {
+            return Total::default();
+        }
+
+        self.visiting.push(name);
+        let mut total = Total {
+            repeat_depth: usage.repeat_depth,
+            ..Total::default()
+        };
+        for (created, depth) in &usage.creates {
+            let nested = self.get(created);
+            let weight = ASSUMED_ITEMS.saturating_pow(*depth as u32);
+            total.fragments = total.fragments.saturating_add(1 + nested.fragments);
+            total.cost = total
+                .cost
+                .saturating_add(weight.saturating_mul(1 + nested.cost));
+            total.repeat_depth = total.repeat_depth.max(depth + nested.repeat_depth);
+        }
+        self.visiting.pop();
+
+        self.done.insert(name, total);
+        total
+    }
+}
+
+/// What a blueprint body creates and reads, with the `repeat` depth of each creation
+#[derive(Default)]
+struct Usage<'a> {
+    creates: Vec<(&'a str, usize)>,
+    repeat_depth: usize,
+    bindings: usize,
+    names: BTreeSet<&'a str>,
+}
+
+impl<'a> Usage<'a> {
+    fn collect(body: &'a [BlueprintStmt]) -> Self {
+        let mut usage = Usage::default();
+        usage.visit_stmts(body, 0);
+        usage
+    }
+
+    fn visit_stmts(&mut self, stmts: &'a [BlueprintStmt], depth: usize) {
+        for stmt in stmts {
+            self.visit_stmt(stmt, depth);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a BlueprintStmt, depth: usize) {
+        match stmt {
+            BlueprintStmt::LocalDecl(decl) => self.visit_expr(&decl.init),
+            BlueprintStmt::Provide(provide) => self.visit_expr(&provide.value),
+            BlueprintStmt::FragmentCreation(frag) => {
+                self.creates.push((&frag.name, depth));
+                for arg in &frag.args {
+                    self.visit_expr(&arg.value);
+                }
+                for postfix in &frag.postfix {
+                    if let PostfixItem::Instruction(instr) = postfix {
+                        self.visit_instruction(instr);
+                    }
+                }
+                match &frag.body {
+                    Some(FragmentBody::Default(body))
+                    | Some(FragmentBody::InlineBlueprint { body, .. }) => {
+                        self.visit_stmts(body, depth)
+                    }
+                    Some(FragmentBody::Slots(slots)) => {
+                        for slot in slots {
+                            self.visit_slot(slot, depth);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::When {
+                condition,
+                then_stmt,
+                else_stmt,
+            }) => {
+                self.visit_expr(condition);
+                self.visit_stmt(then_stmt, depth);
+                if let Some(else_stmt) = else_stmt {
+                    self.visit_stmt(else_stmt, depth);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Repeat {
+                iterable,
+                key_expr,
+                body,
+                ..
+            }) => {
+                self.visit_expr(iterable);
+                if let Some(key_expr) = key_expr {
+                    self.visit_expr(key_expr);
+                }
+                self.repeat_depth = self.repeat_depth.max(depth + 1);
+                self.visit_stmts(body, depth + 1);
+            }
+            BlueprintStmt::Control(ControlStmt::Select {
+                discriminant,
+                branches,
+                else_branch,
+                ..
+            }) => {
+                if let Some(discriminant) = discriminant {
+                    self.visit_expr(discriminant);
+                }
+                for branch in branches {
+                    self.visit_expr(&branch.condition);
+                    self.visit_stmt(&branch.body, depth);
+                }
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(else_branch, depth);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::OnError { body, fallback, .. }) => {
+                self.visit_stmts(body, depth);
+                if let Some(fallback) = fallback {
+                    self.visit_stmts(fallback, depth);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Loading { body, .. }) => {
+                self.visit_stmts(body, depth);
+            }
+            BlueprintStmt::Instruction(instr) => self.visit_instruction(instr),
+            BlueprintStmt::Layout(layout) => {
+                for instr in &layout.instructions {
+                    self.visit_instruction(instr);
+                }
+            }
+            BlueprintStmt::SlotBinding(slot) => self.visit_slot(slot, depth),
+            BlueprintStmt::ContentExpr(expr) => self.visit_expr(expr),
+            BlueprintStmt::With(_)
+            | BlueprintStmt::EventHandler(_)
+            | BlueprintStmt::Shortcut(_)
+            | BlueprintStmt::Consume(_) => {}
+        }
+    }
+
+    fn visit_slot(&mut self, slot: &'a ast::SlotBinding, depth: usize) {
+        match &slot.blueprint {
+            BlueprintValue::Inline { body, .. } => self.visit_stmts(body, depth),
+            BlueprintValue::Reference(name) => self.creates.push((name, depth)),
+        }
+    }
+
+    fn visit_instruction(&mut self, instr: &'a InstructionExpr) {
+        match instr {
+            InstructionExpr::Simple(instr) => {
+                for (_, value) in &instr.params {
+                    self.visit_expr(value);
+                }
+            }
+            InstructionExpr::When {
+                condition,
+                then_instr,
+                else_instr,
+            } => {
+                self.visit_expr(condition);
+                self.visit_instruction(then_instr);
+                if let Some(else_instr) = else_instr {
+                    self.visit_instruction(else_instr);
+                }
+            }
+            InstructionExpr::Ternary {
+                condition,
+                then_instr,
+                else_instr,
+            } => {
+                self.visit_expr(condition);
+                self.visit_instruction(then_instr);
+                self.visit_instruction(else_instr);
+            }
+            InstructionExpr::Reference(expr) => self.visit_expr(expr),
+        }
+    }
+
+    /// Count an expression as a binding if it reads any name
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        let mut names = BTreeSet::new();
+        collect_names(expr, &mut names);
+        if !names.is_empty() {
+            self.bindings += 1;
+            self.names.extend(names);
+        }
+    }
+}
+
+/// Names read by an expression
+fn collect_names<'a>(expr: &'a Expr, names: &mut BTreeSet<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => {
+            names.insert(name);
+        }
+        Expr::Binary { left, right, .. } => {
+            collect_names(left, names);
+            collect_names(right, names);
+        }
+        Expr::Unary { expr, .. } => collect_names(expr, names),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            collect_names(condition, names);
+            collect_names(then_expr, names);
+            collect_names(else_expr, names);
+        }
+        Expr::FieldAccess { base, .. } | Expr::OptionalChain { base, .. } => {
+            collect_names(base, names)
+        }
+        Expr::Call { callee, args } => {
+            collect_names(callee, names);
+            for arg in args {
+                collect_names(arg, names);
+            }
+        }
+        Expr::List(items) => {
+            for item in items {
+                collect_names(item, names);
+            }
+        }
+        Expr::Object(fields) => {
+            for (_, value) in fields {
+                collect_names(value, names);
+            }
+        }
+        Expr::StringTemplate(elements) => {
+            for element in elements {
+                if let TemplateElement::Interpolation(expr) = element {
+                    collect_names(expr, names);
+                }
+            }
+        }
+        Expr::Range { start, end, .. } => {
+            collect_names(start, names);
+            collect_names(end, names);
+        }
+        Expr::Lambda { body, .. } => collect_names(body, names),
+        Expr::Null
+        | Expr::Bool(_)
+        | Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Color(_)
+        | Expr::String(_)
+        | Expr::QualifiedName(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(source: &str) -> ComplexityReport {
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        analyze_complexity(&result.file.unwrap())
+    }
+
+    fn get<'a>(report: &'a ComplexityReport, name: &str) -> &'a BlueprintComplexity {
+        report.blueprints.iter().find(|bp| bp.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_counts() {
+        let report = analyze(
+            r#"module test
+
+blueprint Row {
+    label : String = ""
+    text { label } .. width { 100 }
+}
+
+blueprint List {
+    items : List<String> = []
+    selected : String = ""
+    column {
+        text { "Items: ${items.length}" }
+        repeat on items { item ->
+            Row()
+            text { item } .. when item == selected { font { weight: 700 } }
+        }
+    }
+}
+"#,
+        );
+
+        let row = get(&report, "Row");
+        assert_eq!(row.fragments, 1);
+        assert_eq!(row.bindings, 1);
+        assert_eq!(row.repeat_depth, 0);
+
+        let list = get(&report, "List");
+        assert_eq!(list.fragments, 4);
+        assert_eq!(list.total_fragments, 5);
+        assert_eq!(list.repeat_depth, 1);
+        // items.length, items, item, item == selected
+        assert_eq!(list.bindings, 4);
+        assert_eq!(list.dependencies, 3);
+        // column + text + 10 * (Row + its text) + 10 * text
+        assert_eq!(list.cost, 32);
+
+        assert_eq!(report.blueprints[0].name, "List");
+        assert_eq!(report.slow().count(), 0);
+    }
+
+    #[test]
+    fn test_nested_repeats_are_slow() {
+        let report = analyze(
+            r#"module test
+
+blueprint Cell {
+    values : List<i32> = []
+    repeat on values { value ->
+        text { value }
+    }
+}
+
+blueprint Grid {
+    rows : List<List<i32>> = []
+    repeat on rows { row ->
+        repeat on row { cell ->
+            Cell()
+        }
+    }
+}
+"#,
+        );
+
+        let grid = get(&report, "Grid");
+        assert_eq!(grid.repeat_depth, 3);
+        assert_eq!(grid.cost, 100 * 11);
+        assert!(grid.is_slow());
+        assert_eq!(grid.warnings.len(), 2, "{:?}", grid.warnings);
+        assert!(grid.warnings[0].contains("3 levels"), "{:?}", grid.warnings);
+
+        let slow: Vec<_> = report.slow().map(|bp| bp.name.as_str()).collect();
+        assert_eq!(slow, ["Grid"]);
+    }
+
+    #[test]
+    fn test_recursive_blueprint() {
+        let report = analyze(
+            r#"module test
+
+blueprint Tree {
+    children : List<String> = []
+    repeat on children { child ->
+        Tree()
+    }
+}
+"#,
+        );
+        let tree = get(&report, "Tree");
+        assert_eq!(tree.total_fragments, 1);
+        assert_eq!(tree.repeat_depth, 1);
+    }
+}
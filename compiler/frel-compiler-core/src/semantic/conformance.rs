@@ -0,0 +1,318 @@
+// Contract conformance for Frel
+//
+// A backend including a contract implements it, and an arena loads its scheme
+// through the contract named after `with`:
+//
+//     contract UserAPI { get_user(id: u32) : User }
+//     backend LocalUsers { include UserAPI  method get_user(id: u32) : User }
+//     arena UserArena { for User with UserAPI }
+//
+// A backend must declare each method of the contracts it includes, as a method
+// or a command (E0610), taking the same parameter types and returning the same
+// type (E0611). An arena's contract must have a method returning its scheme,
+// as is, nullable or in a collection, to fill the arena with (E0610).
+//
+// Types are compared as written. Only contracts declared in the module are
+// checked; those imported from other modules have no declarations to compare.
+
+use std::collections::HashMap;
+
+use crate::ast::{self, BackendMember, Parameter, TypeExpr};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+use crate::source::Span;
+
+/// Report backends not implementing the contracts they include and arenas
+/// whose contract cannot load their scheme, in a module's files
+pub fn check_conformance(files: &[ast::File]) -> Diagnostics {
+    let _profile = crate::profile::enter("conformance");
+    let mut diagnostics = Diagnostics::new();
+
+    let contracts: HashMap<&str, (&ast::File, &ast::Contract)> = files
+        .iter()
+        .flat_map(|file| file.declarations.iter().map(move |decl| (file, decl)))
+        .filter_map(|(file, decl)| match decl {
+            ast::TopLevelDecl::Contract(contract) => Some((contract.name.as_str(), (file, contract))),
+            _ => None,
+        })
+        .collect();
+    if contracts.is_empty() {
+        return diagnostics;
+    }
+
+    for file in files {
+        for decl in &file.declarations {
+            match decl {
+                ast::TopLevelDecl::Backend(backend) => {
+                    for member in &backend.members {
+                        if let BackendMember::Include(name) = member {
+                            if let Some(&(contract_file, contract)) = contracts.get(name.as_str()) {
+                                let site = Site { file, contract_file };
+                                check_backend(backend, contract, site, &mut diagnostics);
+                            }
+                        }
+                    }
+                }
+                ast::TopLevelDecl::Arena(arena) => {
+                    let contract = arena.contract.as_deref().and_then(|name| contracts.get(name));
+                    if let Some(&(contract_file, contract)) = contract {
+                        let site = Site { file, contract_file };
+                        check_arena(arena, contract, site, &mut diagnostics);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Files of the declaration checked and of its contract
+#[derive(Clone, Copy)]
+struct Site<'a> {
+    file: &'a ast::File,
+    contract_file: &'a ast::File,
+}
+
+impl Site<'_> {
+    /// Related information pointing at the contract's declaration
+    fn related(&self, span: Span, message: String) -> Option<RelatedInfo> {
+        match &self.contract_file.source_path {
+            path if *path == self.file.source_path => Some(RelatedInfo::new(span, message)),
+            Some(path) => Some(RelatedInfo::in_file(span, path, message)),
+            None => None,
+        }
+    }
+}
+
+/// A method or command of a backend
+struct Implementation<'a> {
+    params: &'a [Parameter],
+    return_type: Option<&'a TypeExpr>,
+    span: Span,
+}
+
+fn check_backend(
+    backend: &ast::Backend,
+    contract: &ast::Contract,
+    site: Site,
+    diagnostics: &mut Diagnostics,
+) {
+    for method in &contract.methods {
+        let declared = backend.members.iter().find_map(|member| match member {
+            BackendMember::Method(m) if m.name == method.name => Some(Implementation {
+                params: &m.params,
+                return_type: Some(&m.return_type),
+                span: m.span,
+            }),
+            BackendMember::Command(c) if c.name == method.name => Some(Implementation {
+                params: &c.params,
+                return_type: c.return_type.as_ref(),
+                span: c.span,
+            }),
+            _ => None,
+        });
+        let expected = signature(&method.params, method.return_type.as_ref());
+
+        let Some(declared) = declared else {
+            let mut diagnostic = Diagnostic::from_code(
+                &codes::E0610,
+                backend.span,
+                format!(
+                    "backend `{}` does not implement `{}` of contract `{}`",
+                    backend.name, method.name, contract.name
+                ),
+            )
+            .with_help(format!("declare `{}`", declaration(method)));
+            if let Some(related) = site.related(method.span, format!("`{}` declared here", method.name)) {
+                diagnostic = diagnostic.with_related(related);
+            }
+            diagnostics.add(diagnostic);
+            continue;
+        };
+
+        let found = signature(declared.params, declared.return_type);
+        if found != expected {
+            let mut diagnostic = Diagnostic::from_code(
+                &codes::E0611,
+                declared.span,
+                format!(
+                    "`{}` does not match contract `{}`: expected `{}`, found `{}`",
+                    method.name, contract.name, expected, found
+                ),
+            );
+            if let Some(related) = site.related(method.span, format!("`{}` declared here", method.name)) {
+                diagnostic = diagnostic.with_related(related);
+            }
+            diagnostics.add(diagnostic);
+        }
+    }
+}
+
+fn check_arena(arena: &ast::Arena, contract: &ast::Contract, site: Site, diagnostics: &mut Diagnostics) {
+    let loads = contract.methods.iter().any(|method| {
+        method
+            .return_type
+            .as_ref()
+            .is_some_and(|ty| holds_scheme(ty, &arena.scheme_name))
+    });
+    if loads {
+        return;
+    }
+
+    let mut diagnostic = Diagnostic::from_code(
+        &codes::E0610,
+        arena.span,
+        format!(
+            "contract `{}` of arena `{}` has no method loading a `{}`",
+            contract.name, arena.name, arena.scheme_name
+        ),
+    )
+    .with_help(format!(
+        "declare a method returning the `{}` entities to store, e.g. `get(id: Uuid) : {}`",
+        arena.scheme_name, arena.scheme_name
+    ));
+    if let Some(related) = site.related(contract.span, format!("`{}` declared here", contract.name)) {
+        diagnostic = diagnostic.with_related(related);
+    }
+    diagnostics.add(diagnostic);
+}
+
+/// Whether `ty` is `scheme`, nullable or in a list or set
+fn holds_scheme(ty: &TypeExpr, scheme: &str) -> bool {
+    match ty {
+        TypeExpr::Named(name) => name == scheme,
+        TypeExpr::Nullable(inner) | TypeExpr::List(inner) | TypeExpr::Set(inner) => {
+            holds_scheme(inner, scheme)
+        }
+        _ => false,
+    }
+}
+
+/// Backend member implementing a contract method: a method if it returns a
+/// value, a command otherwise
+fn declaration(method: &ast::ContractMethod) -> String {
+    let params: Vec<String> = method
+        .params
+        .iter()
+        .map(|p| format!("{}: {}", p.name, p.type_expr))
+        .collect();
+    match &method.return_type {
+        Some(ty) => format!("method {}({}) : {}", method.name, params.join(", "), ty),
+        None => format!("command {}({})", method.name, params.join(", ")),
+    }
+}
+
+/// Parameter and return types as written, e.g. `(u32, String) : User`
+fn signature(params: &[Parameter], return_type: Option<&TypeExpr>) -> String {
+    let params: Vec<String> = params.iter().map(|p| p.type_expr.to_string()).collect();
+    match return_type {
+        Some(ty) => format!("({}) : {}", params.join(", "), ty),
+        None => format!("({})", params.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<(String, String)> {
+        let file = crate::parse_file(source).file.expect("parse failed");
+        check_conformance(std::slice::from_ref(&file))
+            .iter()
+            .map(|d| (d.code.clone().unwrap_or_default(), d.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_backend_conformance() {
+        let source = r#"
+module test
+
+scheme User {
+    name : String
+}
+
+contract UserAPI {
+    get_user(id: u32) : User
+    rename(id: u32, name: String)
+    delete_user(id: u32)
+}
+
+backend LocalUsers {
+    include UserAPI
+    method get_user(id: u32) : User
+    command rename(id: u32, name: String)
+    command delete_user(id: u32)
+}
+
+backend BrokenUsers {
+    include UserAPI
+    method get_user(id: i32) : User
+    command rename(id: u32)
+}
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    "E0611".to_string(),
+                    "`get_user` does not match contract `UserAPI`: expected `(u32) : User`, found `(i32) : User`"
+                        .to_string()
+                ),
+                (
+                    "E0611".to_string(),
+                    "`rename` does not match contract `UserAPI`: expected `(u32, String)`, found `(u32)`"
+                        .to_string()
+                ),
+                (
+                    "E0610".to_string(),
+                    "backend `BrokenUsers` does not implement `delete_user` of contract `UserAPI`"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arena_conformance() {
+        let source = r#"
+module test
+
+scheme User {
+    name : String
+}
+
+contract UserAPI {
+    find_user(id: u32) : User?
+}
+
+contract Search {
+    search(text: String) : List<User>
+}
+
+contract Stats {
+    count() : u32
+}
+
+arena Users {
+    for User with UserAPI
+}
+
+arena Found {
+    for User with Search
+}
+
+arena Counted {
+    for User with Stats
+}
+"#;
+        let diagnostics = messages(source);
+        assert_eq!(
+            diagnostics,
+            vec![(
+                "E0610".to_string(),
+                "contract `Stats` of arena `Counted` has no method loading a `User`".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+// Constant expression evaluation for Frel
+//
+// This module provides:
+// - ConstValue: A concrete value computed at compile time
+// - ConstEnv: Lookup of named constants referenced by an expression
+// - eval_const: Evaluation of literal/operator expressions to a ConstValue
+//
+// Only expressions without side effects or runtime state can be evaluated:
+// literals, operators, string templates, collections, references to other
+// constants and calls of built-in functions that don't depend on the host.
+// Other calls and anything depending on runtime data are rejected.
+
+use super::builtins;
+use crate::ast::{BinaryOp, Expr, TemplateElement, UnaryOp};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt;
+
+/// A concrete value computed at compile time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum ConstValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// RGBA color, 8 bits per channel
+    Color(u32),
+    String(String),
+    /// Milliseconds since the Unix epoch
+    Instant(i64),
+    List(Vec<ConstValue>),
+    Object(IndexMap<String, ConstValue>),
+}
+
+impl ConstValue {
+    /// Short name of the value's kind, used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ConstValue::Null => "null",
+            ConstValue::Bool(_) => "bool",
+            ConstValue::Int(_) => "integer",
+            ConstValue::Float(_) => "float",
+            ConstValue::Color(_) => "color",
+            ConstValue::String(_) => "string",
+            ConstValue::Instant(_) => "instant",
+            ConstValue::List(_) => "list",
+            ConstValue::Object(_) => "object",
+        }
+    }
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Null => write!(f, "null"),
+            ConstValue::Bool(b) => write!(f, "{}", b),
+            ConstValue::Int(i) => write!(f, "{}", i),
+            ConstValue::Float(x) => write!(f, "{}", x),
+            ConstValue::Color(c) => write!(f, "#{:08X}", c),
+            ConstValue::String(s) => write!(f, "{}", s),
+            ConstValue::Instant(ms) => write!(f, "{}", super::builtins::format_instant(*ms)),
+            ConstValue::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            ConstValue::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Reasons an expression cannot be evaluated to a constant
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstEvalError {
+    /// The expression needs runtime evaluation (e.g. a call)
+    NotConstant(String),
+    /// A referenced name is not a known constant
+    UnknownName(String),
+    /// A constant has no value (e.g. an asset field without an override)
+    NoValue(String),
+    /// Constants reference each other in a cycle
+    Cycle(String),
+    /// An operator or field access was applied to the wrong kind of value
+    TypeMismatch(String),
+    DivisionByZero,
+    Overflow,
+}
+
+impl fmt::Display for ConstEvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstEvalError::NotConstant(what) => write!(f, "{} is not a constant expression", what),
+            ConstEvalError::UnknownName(name) => write!(f, "unknown constant '{}'", name),
+            ConstEvalError::NoValue(name) => write!(f, "'{}' has no value", name),
+            ConstEvalError::Cycle(name) => write!(f, "'{}' depends on itself", name),
+            ConstEvalError::TypeMismatch(msg) => write!(f, "{}", msg),
+            ConstEvalError::DivisionByZero => write!(f, "division by zero"),
+            ConstEvalError::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for ConstEvalError {}
+
+/// Named constants available to an expression
+pub trait ConstEnv {
+    /// Evaluate the constant with the given name
+    ///
+    /// Qualified names are passed joined with `.` (e.g. `Theme.padding`).
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError>;
+}
+
+/// Environment without any named constants
+pub struct EmptyEnv;
+
+impl ConstEnv for EmptyEnv {
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+        Err(ConstEvalError::UnknownName(name.to_string()))
+    }
+}
+
+/// Evaluate a constant expression
+pub fn eval_const(expr: &Expr, env: &mut dyn ConstEnv) -> Result<ConstValue, ConstEvalError> {
+    match expr {
+        Expr::Null => Ok(ConstValue::Null),
+        Expr::Bool(b) => Ok(ConstValue::Bool(*b)),
+        Expr::Int(i) => Ok(ConstValue::Int(*i)),
+        Expr::Float(x) => Ok(ConstValue::Float(*x)),
+        Expr::Color(c) => Ok(ConstValue::Color(*c)),
+        Expr::String(s) => Ok(ConstValue::String(s.clone())),
+        Expr::StringTemplate(elements) => {
+            let mut text = String::new();
+            for element in elements {
+                match element {
+                    TemplateElement::Text(s) => text.push_str(s),
+                    TemplateElement::Interpolation(expr) => {
+                        text.push_str(&eval_const(expr, env)?.to_string())
+                    }
+                }
+            }
+            Ok(ConstValue::String(text))
+        }
+        Expr::List(items) => items
+            .iter()
+            .map(|item| eval_const(item, env))
+            .collect::<Result<_, _>>()
+            .map(ConstValue::List),
+        Expr::Object(fields) => fields
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), eval_const(value, env)?)))
+            .collect::<Result<_, _>>()
+            .map(ConstValue::Object),
+        Expr::Identifier(name) => env.lookup(name),
+        Expr::QualifiedName(parts) => env.lookup(&parts.join(".")),
+        Expr::Binary { op, left, right } => {
+            let left = eval_const(left, env)?;
+            // Short-circuit like the runtime does
+            match (op, &left) {
+                (BinaryOp::And, ConstValue::Bool(false)) => return Ok(left),
+                (BinaryOp::Or, ConstValue::Bool(true)) => return Ok(left),
+                (BinaryOp::Elvis, value) if *value != ConstValue::Null => return Ok(left),
+                _ => {}
+            }
+            let right = eval_const(right, env)?;
+            eval_binary(*op, left, right)
+        }
+        Expr::Unary { op, expr } => eval_unary(*op, eval_const(expr, env)?),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => match eval_const(condition, env)? {
+            ConstValue::Bool(true) => eval_const(then_expr, env),
+            ConstValue::Bool(false) => eval_const(else_expr, env),
+            other => Err(ConstEvalError::TypeMismatch(format!(
+                "condition must be a bool, found {}",
+                other.kind()
+            ))),
+        },
+        Expr::FieldAccess { base, field, .. } => {
+            // `Theme.field` style references are constants, not object access
+            if let Some(path) = qualified_path(expr) {
+                if let Ok(value) = env.lookup(&path) {
+                    return Ok(value);
+                }
+            }
+            access_field(eval_const(base, env)?, field)
+        }
+        Expr::OptionalChain { base, field } => match eval_const(base, env)? {
+            ConstValue::Null => Ok(ConstValue::Null),
+            value => access_field(value, field),
+        },
+        Expr::Call { callee, args } => match callee.as_ref() {
+            Expr::Identifier(name) => {
+                let builtin = builtins::lookup(name)
+                    .ok_or_else(|| ConstEvalError::NotConstant("a function call".to_string()))?;
+                let args = args
+                    .iter()
+                    .map(|arg| eval_const(arg, env))
+                    .collect::<Result<Vec<_>, _>>()?;
+                builtin.eval(&args)
+            }
+            _ => Err(ConstEvalError::NotConstant("a function call".to_string())),
+        },
+        Expr::Range { .. } => Err(ConstEvalError::NotConstant("a range".to_string())),
+        Expr::Lambda { .. } => Err(ConstEvalError::NotConstant("a lambda".to_string())),
+    }
+}
+
+/// Dotted path of a chain of identifiers and field accesses, if it is one
+pub(crate) fn qualified_path(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(name) => Some(name.clone()),
+        Expr::QualifiedName(parts) => Some(parts.join(".")),
+        Expr::FieldAccess { base, field, .. } => Some(format!("{}.{}", qualified_path(base)?, field)),
+        _ => None,
+    }
+}
+
+fn access_field(value: ConstValue, field: &str) -> Result<ConstValue, ConstEvalError> {
+    match value {
+        ConstValue::Object(mut fields) => fields
+            .shift_remove(field)
+            .ok_or_else(|| ConstEvalError::UnknownName(field.to_string())),
+        other => Err(ConstEvalError::TypeMismatch(format!(
+            "cannot access field '{}' on {}",
+            field,
+            other.kind()
+        ))),
+    }
+}
+
+pub(crate) fn eval_unary(op: UnaryOp, value: ConstValue) -> Result<ConstValue, ConstEvalError> {
+    match (op, value) {
+        (UnaryOp::Not, ConstValue::Bool(b)) => Ok(ConstValue::Bool(!b)),
+        (UnaryOp::Neg, ConstValue::Int(i)) => i
+            .checked_neg()
+            .map(ConstValue::Int)
+            .ok_or(ConstEvalError::Overflow),
+        (UnaryOp::Neg, ConstValue::Float(x)) => Ok(ConstValue::Float(-x)),
+        (UnaryOp::Pos, value @ (ConstValue::Int(_) | ConstValue::Float(_))) => Ok(value),
+        (op, value) => Err(ConstEvalError::TypeMismatch(format!(
+            "operator {:?} cannot be applied to {}",
+            op,
+            value.kind()
+        ))),
+    }
+}
+
+pub(crate) fn eval_binary(
+    op: BinaryOp,
+    left: ConstValue,
+    right: ConstValue,
+) -> Result<ConstValue, ConstEvalError> {
+    use ConstValue::{Bool, Float, Int};
+
+    let mismatch = |left: &ConstValue, right: &ConstValue| {
+        Err(ConstEvalError::TypeMismatch(format!(
+            "operator {:?} cannot be applied to {} and {}",
+            op,
+            left.kind(),
+            right.kind()
+        )))
+    };
+
+    match op {
+        BinaryOp::Add
+        | BinaryOp::Sub
+        | BinaryOp::Mul
+        | BinaryOp::Div
+        | BinaryOp::Mod
+        | BinaryOp::Pow => match (&left, &right) {
+            (ConstValue::String(a), ConstValue::String(b)) if matches!(op, BinaryOp::Add) => {
+                Ok(ConstValue::String(format!("{}{}", a, b)))
+            }
+            (Int(a), Int(b)) => eval_int(op, *a, *b).map(Int),
+            (Int(_) | Float(_), Int(_) | Float(_)) => {
+                Ok(Float(eval_float(op, as_float(&left), as_float(&right))))
+            }
+            _ => mismatch(&left, &right),
+        },
+        BinaryOp::Eq => Ok(Bool(left == right)),
+        BinaryOp::Ne => Ok(Bool(left != right)),
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let ordering = match (&left, &right) {
+                (Int(a), Int(b)) => a.partial_cmp(b),
+                (Int(_) | Float(_), Int(_) | Float(_)) => {
+                    as_float(&left).partial_cmp(&as_float(&right))
+                }
+                (ConstValue::String(a), ConstValue::String(b)) => a.partial_cmp(b),
+                _ => return mismatch(&left, &right),
+            };
+            let result = match ordering {
+                Some(ordering) => match op {
+                    BinaryOp::Lt => ordering.is_lt(),
+                    BinaryOp::Le => ordering.is_le(),
+                    BinaryOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                },
+                // NaN compares false
+                None => false,
+            };
+            Ok(Bool(result))
+        }
+        BinaryOp::And | BinaryOp::Or => match (&left, &right) {
+            (Bool(_), Bool(b)) => Ok(Bool(*b)),
+            _ => mismatch(&left, &right),
+        },
+        // Only reached when the left side is null
+        BinaryOp::Elvis => Ok(right),
+    }
+}
+
+fn eval_int(op: BinaryOp, a: i64, b: i64) -> Result<i64, ConstEvalError> {
+    match op {
+        BinaryOp::Add => a.checked_add(b).ok_or(ConstEvalError::Overflow),
+        BinaryOp::Sub => a.checked_sub(b).ok_or(ConstEvalError::Overflow),
+        BinaryOp::Mul => a.checked_mul(b).ok_or(ConstEvalError::Overflow),
+        BinaryOp::Div if b == 0 => Err(ConstEvalError::DivisionByZero),
+        BinaryOp::Div => a.checked_div(b).ok_or(ConstEvalError::Overflow),
+        BinaryOp::Mod if b == 0 => Err(ConstEvalError::DivisionByZero),
+        BinaryOp::Mod => a.checked_rem(b).ok_or(ConstEvalError::Overflow),
+        BinaryOp::Pow => u32::try_from(b)
+            .ok()
+            .and_then(|exp| a.checked_pow(exp))
+            .ok_or(ConstEvalError::Overflow),
+        _ => unreachable!("not an arithmetic operator"),
+    }
+}
+
+fn eval_float(op: BinaryOp, a: f64, b: f64) -> f64 {
+    match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Mod => a % b,
+        BinaryOp::Pow => a.powf(b),
+        _ => unreachable!("not an arithmetic operator"),
+    }
+}
+
+fn as_float(value: &ConstValue) -> f64 {
+    match value {
+        ConstValue::Int(i) => *i as f64,
+        ConstValue::Float(x) => *x,
+        _ => f64::NAN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BackendMember, TopLevelDecl};
+
+    fn eval(source: &str) -> Result<ConstValue, ConstEvalError> {
+        // Wrap in a backend field to parse the expression
+        let full_source = format!("module test\nbackend Test {{ x: i32 = {} }}", source);
+        let file = crate::parse_file(&full_source)
+            .file
+            .expect("expression should parse");
+        let TopLevelDecl::Backend(backend) = &file.declarations[0] else {
+            panic!("expected backend");
+        };
+        let BackendMember::Field(field) = &backend.members[0] else {
+            panic!("expected field");
+        };
+        eval_const(field.init.as_ref().unwrap(), &mut EmptyEnv)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(ConstValue::Int(7)));
+        assert_eq!(eval("2 ** 10"), Ok(ConstValue::Int(1024)));
+        assert_eq!(eval("7 / 2"), Ok(ConstValue::Int(3)));
+        assert_eq!(eval("1.5 * 2"), Ok(ConstValue::Float(3.0)));
+        assert_eq!(eval("-(4 - 6)"), Ok(ConstValue::Int(2)));
+        assert_eq!(eval("1 / 0"), Err(ConstEvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_logic_and_strings() {
+        assert_eq!(eval("1 < 2 && !false"), Ok(ConstValue::Bool(true)));
+        assert_eq!(
+            eval("true ? \"a\" : \"b\""),
+            Ok(ConstValue::String("a".to_string()))
+        );
+        assert_eq!(
+            eval("\"a\" + \"b\""),
+            Ok(ConstValue::String("ab".to_string()))
+        );
+        assert_eq!(eval("null ?: 4"), Ok(ConstValue::Int(4)));
+        assert!(matches!(
+            eval("1 + true"),
+            Err(ConstEvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_collections_and_calls() {
+        assert_eq!(
+            eval("[1, #FF0000]"),
+            Ok(ConstValue::List(vec![
+                ConstValue::Int(1),
+                ConstValue::Color(0xFF0000FF)
+            ]))
+        );
+        assert_eq!(eval("{ a: 1, b: 2 }.b"), Ok(ConstValue::Int(2)));
+        assert!(matches!(eval("now()"), Err(ConstEvalError::NotConstant(_))));
+        assert_eq!(
+            eval("missing"),
+            Err(ConstEvalError::UnknownName("missing".to_string()))
+        );
+    }
+}
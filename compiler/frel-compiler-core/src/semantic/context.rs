@@ -0,0 +1,521 @@
+// Context values for Frel
+//
+// A blueprint provides a value to every fragment it creates, however deeply
+// nested, and a blueprint below reads it by name instead of having it passed
+// down as a parameter at each level:
+//
+//     blueprint Page { provide accent = #FF3366 ... }
+//     blueprint Badge { consume accent: Color ... }
+//
+// At runtime a consumer reads the value of the nearest closure above it that
+// provides the name, and follows its changes. At compile time the same
+// providers are found by walking up the blueprints of the module creating the
+// consumer: a creator either provides the name itself, or the walk goes on with
+// the blueprints creating it. Each provider found must agree with the consumed
+// type (E0401). A name no blueprint of the project provides is an error
+// (E0710), as are `provide` and `consume` inside a fragment or control
+// statement, and a name provided twice by one blueprint (E0711).
+//
+// Module signatures record the names a module provides, so a consumer created
+// from another module is not reported as missing its provider.
+
+use std::collections::{HashMap, HashSet};
+
+use super::signature::SignatureRegistry;
+use super::typecheck::TypeCheckResult;
+use super::types::Type;
+use crate::ast::{self, BlueprintStmt, BlueprintValue, ControlStmt, FragmentBody};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+use crate::source::Span;
+
+/// A `provide` statement and the blueprint declaring it
+#[derive(Debug, Clone, Copy)]
+pub struct Provider<'a> {
+    pub file: &'a ast::File,
+    pub blueprint: &'a ast::Blueprint,
+    pub provide: &'a ast::Provide,
+}
+
+/// Names provided at the top level of the file's blueprints, for the module signature
+pub fn provided_names(file: &ast::File) -> Vec<String> {
+    blueprints(file)
+        .flat_map(|blueprint| provides(blueprint).map(|provide| provide.name.clone()))
+        .collect()
+}
+
+/// Types of the file's context values, by statement span: the value of each
+/// `provide` and the declared type of each `consume`
+pub fn context_types(file: &ast::File, types: &TypeCheckResult) -> HashMap<Span, Type> {
+    let mut found = HashMap::new();
+    for blueprint in blueprints(file) {
+        for stmt in &blueprint.body {
+            let (span, ty) = match stmt {
+                BlueprintStmt::Provide(provide) => {
+                    (provide.span, types.expr_types.get(&provide.span))
+                }
+                BlueprintStmt::Consume(consume) => {
+                    (consume.span, types.type_resolutions.get(&consume.span))
+                }
+                _ => continue,
+            };
+            if let Some(ty) = ty {
+                found.insert(span, ty.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Providers of `name` nearest above the instances of `blueprint` in `files`
+///
+/// A blueprint providing the name it consumes reads the value from above,
+/// like any other consumer.
+pub fn nearest_providers<'a>(
+    files: &'a [ast::File],
+    blueprint: &str,
+    name: &str,
+) -> Vec<Provider<'a>> {
+    Graph::new(files).nearest_providers(blueprint, name)
+}
+
+/// Report misplaced and duplicated context values of a module's files, consumers
+/// without a provider and providers disagreeing with the consumed type
+///
+/// `types` holds the result of [`context_types`] for each file. Names provided
+/// by other modules come from their signatures in `registry`.
+pub fn check_context(
+    files: &[ast::File],
+    types: &[HashMap<Span, Type>],
+    module_path: &str,
+    registry: &SignatureRegistry,
+) -> Diagnostics {
+    let _profile = crate::profile::enter("context");
+    let mut diagnostics = Diagnostics::new();
+
+    for file in files {
+        for blueprint in blueprints(file) {
+            check_placement(blueprint, &mut diagnostics);
+        }
+    }
+
+    let mut provided: HashSet<String> = files.iter().flat_map(provided_names).collect();
+    for path in registry.module_paths() {
+        if path == module_path {
+            continue;
+        }
+        if let Some(signature) = registry.get(&path) {
+            provided.extend(signature.contexts.iter().cloned());
+        }
+    }
+
+    let graph = Graph::new(files);
+    for (index, file) in files.iter().enumerate() {
+        for blueprint in blueprints(file) {
+            for consume in consumes(blueprint) {
+                if !provided.contains(&consume.name) {
+                    diagnostics.add(
+                        Diagnostic::from_code(
+                            &codes::E0710,
+                            consume.span,
+                            format!(
+                                "no blueprint provides `{}` consumed by `{}`",
+                                consume.name, blueprint.name
+                            ),
+                        )
+                        .with_help(format!(
+                            "add `provide {} = ...` to a blueprint creating `{}`",
+                            consume.name, blueprint.name
+                        )),
+                    );
+                    continue;
+                }
+
+                let Some(consumed) = types.get(index).and_then(|t| t.get(&consume.span)) else {
+                    continue;
+                };
+                for provider in graph.nearest_providers(&blueprint.name, &consume.name) {
+                    let provider_index = files
+                        .iter()
+                        .position(|f| std::ptr::eq(f, provider.file))
+                        .unwrap_or(index);
+                    let Some(value) = types
+                        .get(provider_index)
+                        .and_then(|t| t.get(&provider.provide.span))
+                    else {
+                        continue;
+                    };
+                    if agrees(value, consumed) {
+                        continue;
+                    }
+
+                    let message = format!("`{}` provided here", consume.name);
+                    let related = match &provider.file.source_path {
+                        path if *path == file.source_path => {
+                            Some(RelatedInfo::new(provider.provide.span, message))
+                        }
+                        Some(path) => Some(RelatedInfo::in_file(
+                            provider.provide.span,
+                            path,
+                            message,
+                        )),
+                        None => None,
+                    };
+                    diagnostics.add(
+                        Diagnostic::from_code(
+                            &codes::E0401,
+                            consume.span,
+                            format!(
+                                "`{}` of `{}` is `{}`, but `{}` provides `{}`",
+                                consume.name,
+                                blueprint.name,
+                                consumed,
+                                provider.blueprint.name,
+                                value
+                            ),
+                        )
+                        .with_related_all(related),
+                    );
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Whether a provided value can be read as the consumed type
+///
+/// Number literals take the smallest type holding them, so any integer agrees
+/// with an integer type and any float with a float type.
+fn agrees(provided: &Type, consumed: &Type) -> bool {
+    if !provided.is_known() || !consumed.is_known() || provided == consumed {
+        return true;
+    }
+    if (provided.is_integer() && consumed.is_integer())
+        || (provided.is_float() && consumed.is_float())
+    {
+        return true;
+    }
+    match (provided, consumed) {
+        (Type::Nullable(provided), Type::Nullable(consumed)) => agrees(provided, consumed),
+        (_, Type::Nullable(consumed)) => agrees(provided, consumed),
+        _ => false,
+    }
+}
+
+/// Report context values inside fragments and names provided twice by `blueprint`
+fn check_placement(blueprint: &ast::Blueprint, diagnostics: &mut Diagnostics) {
+    walk(&blueprint.body, true, &mut |stmt, top| {
+        let (keyword, name, span) = match stmt {
+            BlueprintStmt::Provide(provide) => ("provide", &provide.name, provide.span),
+            BlueprintStmt::Consume(consume) => ("consume", &consume.name, consume.span),
+            _ => return,
+        };
+        if !top {
+            diagnostics.add(Diagnostic::from_code(
+                &codes::E0711,
+                span,
+                format!(
+                    "`{} {}` must be declared at the top level of `{}`",
+                    keyword, name, blueprint.name
+                ),
+            ));
+        }
+    });
+
+    let mut seen: HashMap<&str, Span> = HashMap::new();
+    for provide in provides(blueprint) {
+        if let Some(first) = seen.get(provide.name.as_str()) {
+            diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0711,
+                    provide.span,
+                    format!(
+                        "`{}` is provided more than once by `{}`",
+                        provide.name, blueprint.name
+                    ),
+                )
+                .with_related(RelatedInfo::new(*first, "first provided here")),
+            );
+        } else {
+            seen.insert(&provide.name, provide.span);
+        }
+    }
+}
+
+/// Blueprints of a module and the blueprints creating each of them
+struct Graph<'a> {
+    blueprints: Vec<(&'a ast::File, &'a ast::Blueprint)>,
+    /// Indices into `blueprints` of the creators of a blueprint, by name
+    creators: HashMap<&'a str, Vec<usize>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(files: &'a [ast::File]) -> Self {
+        let blueprints: Vec<_> = files
+            .iter()
+            .flat_map(|file| blueprints(file).map(move |blueprint| (file, blueprint)))
+            .collect();
+
+        let mut creators: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, (_, blueprint)) in blueprints.iter().enumerate() {
+            let mut created = Vec::new();
+            walk(&blueprint.body, true, &mut |stmt, _| match stmt {
+                BlueprintStmt::FragmentCreation(frag) => created.push(frag.name.as_str()),
+                BlueprintStmt::SlotBinding(ast::SlotBinding {
+                    blueprint: BlueprintValue::Reference(name),
+                    ..
+                }) => created.push(name.as_str()),
+                _ => {}
+            });
+            created.sort_unstable();
+            created.dedup();
+            for name in created {
+                creators.entry(name).or_default().push(index);
+            }
+        }
+
+        Self {
+            blueprints,
+            creators,
+        }
+    }
+
+    fn nearest_providers(&self, blueprint: &str, name: &str) -> Vec<Provider<'a>> {
+        let mut found = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending: Vec<&str> = vec![blueprint];
+        while let Some(created) = pending.pop() {
+            for &index in self.creators.get(created).into_iter().flatten() {
+                if !visited.insert(index) {
+                    continue;
+                }
+                let (file, creator) = self.blueprints[index];
+                match provides(creator).find(|provide| provide.name == name) {
+                    Some(provide) => found.push(Provider {
+                        file,
+                        blueprint: creator,
+                        provide,
+                    }),
+                    None => pending.push(&creator.name),
+                }
+            }
+        }
+        found.sort_by_key(|provider| (provider.blueprint.span.start, provider.provide.span.start));
+        found
+    }
+}
+
+fn blueprints(file: &ast::File) -> impl Iterator<Item = &ast::Blueprint> {
+    file.declarations.iter().filter_map(|decl| match decl {
+        ast::TopLevelDecl::Blueprint(blueprint) => Some(blueprint),
+        _ => None,
+    })
+}
+
+fn provides(blueprint: &ast::Blueprint) -> impl Iterator<Item = &ast::Provide> {
+    blueprint.body.iter().filter_map(|stmt| match stmt {
+        BlueprintStmt::Provide(provide) => Some(provide),
+        _ => None,
+    })
+}
+
+fn consumes(blueprint: &ast::Blueprint) -> impl Iterator<Item = &ast::Consume> {
+    blueprint.body.iter().filter_map(|stmt| match stmt {
+        BlueprintStmt::Consume(consume) => Some(consume),
+        _ => None,
+    })
+}
+
+/// Call `visit` with `stmts` and every statement they contain, and whether the
+/// statement is at the top level of the blueprint
+fn walk<'a>(
+    stmts: &'a [BlueprintStmt],
+    top: bool,
+    visit: &mut impl FnMut(&'a BlueprintStmt, bool),
+) {
+    for stmt in stmts {
+        visit(stmt, top);
+        match stmt {
+            BlueprintStmt::FragmentCreation(frag) => match &frag.body {
+                Some(FragmentBody::Default(body))
+                | Some(FragmentBody::InlineBlueprint { body, .. }) => walk(body, false, visit),
+                Some(FragmentBody::Slots(slots)) => {
+                    for slot in slots {
+                        walk_slot(slot, visit);
+                    }
+                }
+                None => {}
+            },
+            BlueprintStmt::SlotBinding(slot) => walk_slot(slot, visit),
+            BlueprintStmt::Control(ControlStmt::When {
+                then_stmt,
+                else_stmt,
+                ..
+            }) => {
+                walk(std::slice::from_ref(then_stmt), false, visit);
+                if let Some(else_stmt) = else_stmt {
+                    walk(std::slice::from_ref(else_stmt), false, visit);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Repeat { body, .. }) => walk(body, false, visit),
+            BlueprintStmt::Control(ControlStmt::Select {
+                branches,
+                else_branch,
+                ..
+            }) => {
+                for branch in branches {
+                    walk(std::slice::from_ref(&branch.body), false, visit);
+                }
+                if let Some(else_branch) = else_branch {
+                    walk(std::slice::from_ref(else_branch), false, visit);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::OnError { body, fallback, .. }) => {
+                walk(body, false, visit);
+                if let Some(fallback) = fallback {
+                    walk(fallback, false, visit);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Loading { body, .. }) => walk(body, false, visit),
+            _ => {}
+        }
+    }
+}
+
+fn walk_slot<'a>(slot: &'a ast::SlotBinding, visit: &mut impl FnMut(&'a BlueprintStmt, bool)) {
+    if let BlueprintValue::Inline { body, .. } = &slot.blueprint {
+        walk(body, false, visit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{analyze_module, build_signature, Module};
+
+    fn module(sources: &[(&str, &str)]) -> Module {
+        let files = sources
+            .iter()
+            .map(|(path, source)| {
+                let result = crate::parse_file_with_path(source, path);
+                assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+                result.file.unwrap()
+            })
+            .collect();
+        Module::from_files("app".to_string(), files)
+    }
+
+    fn messages(diagnostics: &Diagnostics) -> Vec<(&str, &str)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or(""), d.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_nearest_providers() {
+        let module = module(&[(
+            "app.frel",
+            r#"module app
+
+blueprint App {
+    provide depth = 0
+    column { Page() }
+}
+
+blueprint Other {
+    provide depth = 10
+    Page()
+}
+
+blueprint Page {
+    consume depth: i32
+    provide depth = depth + 1
+    Card()
+}
+
+blueprint Card {
+    repeat on [1, 2] { n ->
+        Badge()
+    }
+}
+
+blueprint Badge {
+    consume depth: i32
+    text { "${depth}" }
+}
+"#,
+        )]);
+
+        let names = |blueprint: &str| -> Vec<String> {
+            nearest_providers(&module.files, blueprint, "depth")
+                .iter()
+                .map(|provider| provider.blueprint.name.clone())
+                .collect()
+        };
+        // Page shadows the providers above it for the fragments it creates
+        assert_eq!(names("Badge"), ["Page"]);
+        assert_eq!(names("Page"), ["App", "Other"]);
+        assert!(names("App").is_empty());
+
+        let result = analyze_module(&module, &SignatureRegistry::new());
+        assert!(result.success(), "{:?}", result.diagnostics);
+    }
+
+    #[test]
+    fn test_context_errors() {
+        let module = module(&[(
+            "app.frel",
+            r#"module app
+
+blueprint App {
+    provide user = "ada"
+    provide user = "grace"
+    Profile()
+}
+
+blueprint Profile {
+    consume user: i32
+    consume session: String
+    column {
+        consume locale: String
+        text { "${user} ${session} ${locale}" }
+    }
+}
+"#,
+        )]);
+        let result = analyze_module(&module, &SignatureRegistry::new());
+        assert_eq!(
+            messages(&result.diagnostics),
+            [
+                ("E0711", "`user` is provided more than once by `App`"),
+                ("E0711", "`consume locale` must be declared at the top level of `Profile`"),
+                ("E0401", "`user` of `Profile` is `i32`, but `App` provides `String`"),
+                ("E0710", "no blueprint provides `session` consumed by `Profile`"),
+            ]
+        );
+        let mismatch = result.diagnostics.iter().nth(2).unwrap();
+        assert_eq!(mismatch.related[0].message, "`user` provided here");
+    }
+
+    #[test]
+    fn test_provider_in_other_module() {
+        let theme = module(&[(
+            "theme.frel",
+            "module app\n\nblueprint Themed {\n    provide accent = #FF3366\n    Badge()\n}\n",
+        )]);
+        let registry = SignatureRegistry::new();
+        let mut signature = build_signature(&theme).signature;
+        signature.path = "app.theme".to_string();
+        assert_eq!(signature.contexts, ["accent"]);
+        registry.register(signature);
+
+        let badge = module(&[(
+            "badge.frel",
+            "module app\n\nblueprint Badge {\n    consume accent: Color\n}\n",
+        )]);
+        let result = analyze_module(&badge, &registry);
+        assert!(result.success(), "{:?}", result.diagnostics);
+    }
+}
@@ -4,9 +4,11 @@
 // The format is indentation-based and suitable for debugging and review.
 
 use super::scope::{ScopeGraph, ScopeId};
-use super::symbol::SymbolId;
+use super::symbol::{SymbolId, SymbolTable};
 use super::types::Type;
 use super::SemanticResult;
+use crate::ast::{self, DumpOptions, DumpVisitor};
+use crate::source::Span;
 use std::collections::HashMap;
 
 /// Dump a SemanticResult to a human-readable string
@@ -15,6 +17,46 @@ pub fn dump(result: &SemanticResult) -> String {
     dumper.dump()
 }
 
+/// Types of the nodes analyzed, by span, written as in the dump; for
+/// `ast::DumpOptions::types`
+pub fn node_types(
+    expr_types: &HashMap<Span, Type>,
+    symbols: &SymbolTable,
+) -> HashMap<Span, String> {
+    expr_types
+        .iter()
+        .map(|(span, ty)| (*span, format_type(ty, symbols)))
+        .collect()
+}
+
+/// Symbol kinds and types of the identifiers analyzed, by statement span and
+/// name, written as `Field: i32`; for `ast::DumpOptions::names`
+pub fn name_annotations(
+    names: &HashMap<(Span, String), (SymbolId, Type)>,
+    symbols: &SymbolTable,
+) -> HashMap<(Span, String), String> {
+    names
+        .iter()
+        .filter_map(|(key, (id, ty))| {
+            let symbol = symbols.get(*id)?;
+            let annotation = format!("{:?}: {}", symbol.kind, format_type(ty, symbols));
+            Some((key.clone(), annotation))
+        })
+        .collect()
+}
+
+/// Dump the AST of a file with the types of its statements and the symbols
+/// and types of its identifiers inline, e.g. `LOCAL total TYPE i32 INIT
+/// count /* Field: i32 */ + 1 -> i32`
+pub fn dump_typed(file: &ast::File, result: &SemanticResult) -> String {
+    let options = DumpOptions {
+        types: node_types(&result.expr_types, &result.symbols),
+        names: name_annotations(&result.names, &result.symbols),
+        ..DumpOptions::default()
+    };
+    DumpVisitor::dump_with(file, options)
+}
+
 struct SemanticDumper<'a> {
     result: &'a SemanticResult,
     output: String,
@@ -227,45 +269,59 @@ impl<'a> SemanticDumper<'a> {
     }
 
     fn format_type(&self, ty: &Type) -> String {
-        match ty {
-            Type::Scheme(id) => self.format_composite_type("scheme", *id),
-            Type::Backend(id) => self.format_composite_type("backend", *id),
-            Type::Blueprint(id) => self.format_composite_type("blueprint", *id),
-            Type::Contract(id) => self.format_composite_type("contract", *id),
-            Type::Theme(id) => self.format_composite_type("theme", *id),
-            Type::Enum(id) => self.format_composite_type("enum", *id),
-            Type::Nullable(inner) => format!("{}?", self.format_type(inner)),
-            Type::Ref(inner) => format!("ref {}", self.format_type(inner)),
-            Type::Draft(inner) => format!("draft {}", self.format_type(inner)),
-            Type::Asset(inner) => format!("asset {}", self.format_type(inner)),
-            Type::List(elem) => format!("list<{}>", self.format_type(elem)),
-            Type::Set(elem) => format!("set<{}>", self.format_type(elem)),
-            Type::Map(k, v) => format!("map<{}, {}>", self.format_type(k), self.format_type(v)),
-            Type::Tree(elem) => format!("tree<{}>", self.format_type(elem)),
-            Type::Function { params, ret } => {
-                let param_strs: Vec<_> = params.iter().map(|p| self.format_type(p)).collect();
-                format!("fn({}) -> {}", param_strs.join(", "), self.format_type(ret))
-            }
-            Type::BlueprintInstance { blueprint, params } => {
-                let param_strs: Vec<_> = params.iter().map(|p| self.format_type(p)).collect();
-                format!(
-                    "{}({})",
-                    self.format_composite_type("blueprint", *blueprint),
-                    param_strs.join(", ")
-                )
-            }
-            Type::Accessor(inner) => format!("accessor<{}>", self.format_type(inner)),
-            // Simple types use Display
-            _ => ty.to_string(),
-        }
+        format_type(ty, &self.result.symbols)
     }
+}
 
-    fn format_composite_type(&self, kind: &str, id: SymbolId) -> String {
-        if let Some(symbol) = self.result.symbols.get(id) {
-            format!("{} \"{}\"", kind, symbol.name)
-        } else {
-            format!("{}#{}", kind, id.0)
+/// A type, with composite types named by their symbols
+fn format_type(ty: &Type, symbols: &SymbolTable) -> String {
+    match ty {
+        Type::Scheme(id) => format_composite_type("scheme", *id, symbols),
+        Type::Backend(id) => format_composite_type("backend", *id, symbols),
+        Type::Blueprint(id) => format_composite_type("blueprint", *id, symbols),
+        Type::Contract(id) => format_composite_type("contract", *id, symbols),
+        Type::Theme(id) => format_composite_type("theme", *id, symbols),
+        Type::Enum(id) => format_composite_type("enum", *id, symbols),
+        Type::Nullable(inner) => format!("{}?", format_type(inner, symbols)),
+        Type::Ref(inner) => format!("ref {}", format_type(inner, symbols)),
+        Type::Draft(inner) => format!("draft {}", format_type(inner, symbols)),
+        Type::Asset(inner) => format!("asset {}", format_type(inner, symbols)),
+        Type::List(elem) => format!("list<{}>", format_type(elem, symbols)),
+        Type::Set(elem) => format!("set<{}>", format_type(elem, symbols)),
+        Type::Map(k, v) => format!(
+            "map<{}, {}>",
+            format_type(k, symbols),
+            format_type(v, symbols)
+        ),
+        Type::Tree(elem) => format!("tree<{}>", format_type(elem, symbols)),
+        Type::Range(elem) => format!("range<{}>", format_type(elem, symbols)),
+        Type::Function { params, ret } => {
+            let param_strs: Vec<_> = params.iter().map(|p| format_type(p, symbols)).collect();
+            format!(
+                "fn({}) -> {}",
+                param_strs.join(", "),
+                format_type(ret, symbols)
+            )
         }
+        Type::BlueprintInstance { blueprint, params } => {
+            let param_strs: Vec<_> = params.iter().map(|p| format_type(p, symbols)).collect();
+            format!(
+                "{}({})",
+                format_composite_type("blueprint", *blueprint, symbols),
+                param_strs.join(", ")
+            )
+        }
+        Type::Accessor(inner) => format!("accessor<{}>", format_type(inner, symbols)),
+        // Simple types use Display
+        _ => ty.to_string(),
+    }
+}
+
+fn format_composite_type(kind: &str, id: SymbolId, symbols: &SymbolTable) -> String {
+    if let Some(symbol) = symbols.get(id) {
+        format!("{} \"{}\"", kind, symbol.name)
+    } else {
+        format!("{}#{}", kind, id.0)
     }
 }
 
@@ -339,4 +395,41 @@ backend A { }
         assert!(output.contains("errors=1"));
         assert!(output.contains("DIAGNOSTICS"));
     }
+
+    #[test]
+    fn test_dump_typed_golden() {
+        let dir = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../test-data/typed"));
+        let update = std::env::var_os("FREL_UPDATE_GOLDEN").is_some();
+        let mut sources: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "frel"))
+            .collect();
+        sources.sort();
+        assert!(!sources.is_empty());
+
+        for source in sources {
+            let parse_result = parser::parse(&std::fs::read_to_string(&source).unwrap());
+            let file = parse_result.file.unwrap();
+            let result = analyze(&file);
+            assert!(
+                result.success(),
+                "{}: {:?}",
+                source.display(),
+                result.diagnostics
+            );
+            let output = dump_typed(&file, &result);
+            let expected_path = source.with_extension("typed.txt");
+            if update {
+                std::fs::write(&expected_path, &output).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&expected_path).unwrap_or_default();
+            assert!(
+                output == expected,
+                "{} differs from the typed dump; rerun with FREL_UPDATE_GOLDEN=1 if the change is intended",
+                expected_path.display()
+            );
+        }
+    }
 }
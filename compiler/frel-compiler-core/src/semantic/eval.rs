@@ -0,0 +1,741 @@
+// Expression evaluation for debugging
+//
+// This module provides:
+// - EvalContext: The project, module and backend an expression is evaluated in
+// - evaluate_expression: Type check an expression with the type checker and
+//   compute its value with constant evaluation
+// - execute_statement: Type check a handler statement and execute it with an
+//   `Interpreter`, whose variables later evaluations read (`evaluate_with`)
+//
+// The expression is checked as the content of a synthetic blueprint added to
+// the context module, with the module's imports; the blueprint uses the
+// context backend with `with`. The named constants the value can refer to
+// are theme fields and the initializers of the backend's fields.
+
+use std::collections::{HashMap, HashSet};
+
+use super::const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
+use super::interpret::{CommandCall, Interpreter};
+use super::resolve::UNDO_COMMANDS;
+use super::scope::{ScopeGraph, ScopeId};
+use super::signature::SignatureRegistry;
+use super::symbol::{SymbolKind, SymbolTable};
+use super::theme_values::{evaluate_theme, ThemeValues};
+use super::types::Type;
+use super::{
+    resolve_with_registry, typecheck_with_registry, ResolveResult, TypeCheckResult, TypeQuery,
+};
+use crate::ast::{self, BackendMember, BlueprintStmt, Expr, TopLevelDecl};
+use crate::diagnostic::{Diagnostics, Label, Suggestion};
+use crate::parser;
+use crate::source::Span;
+
+/// Module of the synthetic file an expression is checked in
+pub const EVAL_MODULE: &str = "eval";
+
+/// Path the synthetic file is reported under
+pub const EVAL_PATH: &str = "<eval>";
+
+/// Blueprint whose content is the evaluated expression
+const EVAL_BLUEPRINT: &str = "__Eval";
+
+/// Where an expression is evaluated
+pub struct EvalContext<'a> {
+    /// Parsed files of the project, for the values of themes and backends
+    pub files: &'a [ast::File],
+    /// Signatures of the project's modules
+    pub registry: &'a SignatureRegistry,
+    /// Module whose declarations and imports are in scope
+    pub module: Option<&'a str>,
+    /// Backend whose fields are in scope, by name as seen from the module or
+    /// qualified by its module path
+    pub backend: Option<&'a str>,
+}
+
+/// Type and value of an evaluated expression
+#[derive(Debug)]
+pub struct Evaluation {
+    pub ty: Type,
+    /// The type with declarations shown by name
+    pub type_name: String,
+    /// The value, or why the expression is not a constant
+    pub value: Result<ConstValue, ConstEvalError>,
+}
+
+/// An expression that failed to parse or type check
+#[derive(Debug)]
+pub struct EvalFailure {
+    /// The expression, which the diagnostics' spans point into
+    pub source: String,
+    pub diagnostics: Diagnostics,
+}
+
+/// What executing a handler statement did
+#[derive(Debug)]
+pub enum Execution {
+    /// A variable was assigned a value
+    Assigned {
+        name: String,
+        /// Type of the value, with declarations shown by name
+        type_name: String,
+        value: ConstValue,
+    },
+    /// A command was called; the interpreter recorded the call
+    Called(CommandCall),
+}
+
+/// Type check an expression in `context` and evaluate it
+pub fn evaluate_expression(expr: &str, context: &EvalContext) -> Result<Evaluation, EvalFailure> {
+    evaluate_with(expr, context, &Interpreter::new())
+}
+
+/// Type check an expression in `context` and evaluate it, reading the
+/// variables of `interpreter` before the constants
+pub fn evaluate_with(
+    expr: &str,
+    context: &EvalContext,
+    interpreter: &Interpreter,
+) -> Result<Evaluation, EvalFailure> {
+    let checked = Checked::new(expr, context, Snippet::Expression)?;
+    checked.errors()?;
+    let Some(value_expr) = content_expr(&checked.snippet) else {
+        unreachable!("checked snippets have a single expression");
+    };
+    let ty = checked.types().expr_type(value_expr, None, checked.scope);
+    let value = interpreter.eval(value_expr, &mut checked.env(context));
+
+    Ok(Evaluation {
+        type_name: type_name(&ty, &checked.resolved.symbols),
+        ty,
+        value,
+    })
+}
+
+/// Type check a handler statement in `context` and execute it with
+/// `interpreter`: `count = count + 1` or `increment()`
+///
+/// Returns `None` for input that is not a statement, to be evaluated as an
+/// expression instead: input that doesn't parse as one, and calls of names
+/// other than commands (a bare name parses as a call).
+pub fn execute_statement(
+    stmt: &str,
+    context: &EvalContext,
+    interpreter: &mut Interpreter,
+) -> Result<Option<Execution>, EvalFailure> {
+    let Ok(checked) = Checked::new(stmt, context, Snippet::Statement) else {
+        return Ok(None);
+    };
+    let Some(statement) = handler_stmt(&checked.snippet) else {
+        unreachable!("checked snippets have a single statement");
+    };
+    if let ast::HandlerStmt::CommandCall { name, .. } = statement {
+        let is_command = checked
+            .resolved
+            .symbols
+            .lookup_in_scope_chain(checked.scope, name, &checked.resolved.scopes)
+            .and_then(|id| checked.resolved.symbols.get(id))
+            .is_some_and(|symbol| symbol.kind == SymbolKind::Command)
+            || UNDO_COMMANDS.contains(&name.as_str());
+        if !is_command {
+            return Ok(None);
+        }
+    }
+    checked.errors()?;
+
+    let mut env = checked.env(context);
+    interpreter
+        .exec(statement, &mut env)
+        .map_err(|error| checked.failure(error.to_string()))?;
+    let execution = match statement {
+        ast::HandlerStmt::Assignment { name, value } => {
+            let ty = checked.types().expr_type(value, None, checked.scope);
+            Execution::Assigned {
+                name: name.clone(),
+                type_name: type_name(&ty, &checked.resolved.symbols),
+                value: interpreter.variables[name].clone(),
+            }
+        }
+        ast::HandlerStmt::CommandCall { .. } => {
+            Execution::Called(interpreter.calls[interpreter.calls.len() - 1].clone())
+        }
+    };
+    Ok(Some(execution))
+}
+
+/// How input is placed in the synthetic blueprint
+#[derive(Clone, Copy)]
+enum Snippet {
+    /// The single element of a list: `with Backend` followed by `(...)` would
+    /// take a parenthesized expression as arguments
+    Expression,
+    /// The single statement of an event handler
+    Statement,
+}
+
+/// Input resolved and type checked in the synthetic blueprint
+struct Checked {
+    /// The input, which the diagnostics' spans point into
+    input: String,
+    /// The synthetic file, without the context module's declarations
+    snippet: ast::File,
+    module: String,
+    resolved: ResolveResult,
+    checked: TypeCheckResult,
+    /// Body scope of the synthetic blueprint
+    scope: ScopeId,
+    /// Diagnostics of the input, relocated onto it
+    diagnostics: Diagnostics,
+}
+
+impl Checked {
+    /// Parse and check `input` in `context`; fails if it doesn't parse as
+    /// the snippet
+    fn new(input: &str, context: &EvalContext, kind: Snippet) -> Result<Self, EvalFailure> {
+        let module = context.module_path();
+        let (source, offset) = context.source(&module, input, kind);
+        let relocated = |diagnostics| relocate(&diagnostics, offset, input.len() as u32);
+        let fail = |diagnostics| EvalFailure {
+            source: input.to_string(),
+            diagnostics: relocated(diagnostics),
+        };
+
+        let parsed = parser::parse_with_path(&source, EVAL_PATH);
+        let snippet = match parsed.file {
+            Some(file) if !parsed.diagnostics.has_errors() => file,
+            _ => return Err(fail(parsed.diagnostics)),
+        };
+        let single = match kind {
+            Snippet::Expression => content_expr(&snippet).is_some(),
+            Snippet::Statement => handler_stmt(&snippet).is_some(),
+        };
+        if !single {
+            let mut diagnostics = Diagnostics::new();
+            let message = match kind {
+                Snippet::Expression => "expected a single expression",
+                Snippet::Statement => "expected a single statement",
+            };
+            diagnostics.error(message, Default::default());
+            return Err(fail(diagnostics));
+        }
+
+        // Signatures don't carry member types, so the context module's
+        // declarations are checked along with the blueprint instead of imported.
+        // Errors already in the module are not the input's.
+        let mut file = ast::File {
+            declarations: context
+                .files
+                .iter()
+                .filter(|file| file.module == module)
+                .flat_map(|file| file.declarations.iter().cloned())
+                .collect(),
+            ..snippet.clone()
+        };
+        let baseline = analyze(&file, context.registry).2;
+        file.declarations
+            .extend(snippet.declarations.iter().cloned());
+        let (resolved, checked, all) = analyze(&file, context.registry);
+        let mut diagnostics = Diagnostics::new();
+        for diagnostic in all.iter() {
+            let known = baseline
+                .iter()
+                .any(|d| d.message == diagnostic.message && d.span == diagnostic.span);
+            if !known {
+                diagnostics.add(diagnostic.clone());
+            }
+        }
+
+        let scope = resolved
+            .symbols
+            .lookup_local(ScopeId::ROOT, EVAL_BLUEPRINT)
+            .and_then(|id| resolved.symbols.get(id))
+            .and_then(|symbol| symbol.body_scope)
+            .unwrap_or(ScopeId::ROOT);
+        Ok(Self {
+            input: input.to_string(),
+            snippet,
+            module,
+            resolved,
+            checked,
+            scope,
+            diagnostics: relocated(diagnostics),
+        })
+    }
+
+    /// Fail if checking the input found errors
+    fn errors(&self) -> Result<(), EvalFailure> {
+        if !self.diagnostics.has_errors() {
+            return Ok(());
+        }
+        Err(EvalFailure {
+            source: self.input.clone(),
+            diagnostics: self.diagnostics.clone(),
+        })
+    }
+
+    /// A failure with one error covering the input
+    fn failure(&self, message: String) -> EvalFailure {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.error(message, Span::new(0, self.input.len() as u32));
+        EvalFailure {
+            source: self.input.clone(),
+            diagnostics,
+        }
+    }
+
+    fn types(&self) -> TypeQuery<'_> {
+        TypeQuery {
+            scopes: &self.resolved.scopes,
+            symbols: &self.resolved.symbols,
+            imports: &self.resolved.imports,
+            symbol_types: &self.checked.symbol_types,
+        }
+    }
+
+    /// The constants the input can read
+    fn env<'a>(&'a self, context: &EvalContext<'a>) -> EvalEnv<'a> {
+        let mut env = EvalEnv {
+            files: context.files,
+            module: &self.module,
+            scopes: &self.resolved.scopes,
+            symbols: &self.resolved.symbols,
+            scope: self.scope,
+            fields: HashMap::new(),
+            themes: HashMap::new(),
+            in_progress: HashSet::new(),
+        };
+        env.collect_backend_fields(&self.snippet);
+        env
+    }
+}
+
+/// Resolve and type check a file, returning the diagnostics of both
+fn analyze(
+    file: &ast::File,
+    registry: &SignatureRegistry,
+) -> (ResolveResult, TypeCheckResult, Diagnostics) {
+    let mut resolved = resolve_with_registry(file, registry);
+    let mut checked = typecheck_with_registry(
+        file,
+        &resolved.scopes,
+        &resolved.symbols,
+        &resolved.imports,
+        registry,
+    );
+    let mut diagnostics = std::mem::take(&mut resolved.diagnostics);
+    diagnostics.merge(std::mem::take(&mut checked.diagnostics));
+    (resolved, checked, diagnostics)
+}
+
+impl EvalContext<'_> {
+    /// Module the expression is evaluated in: the context module, else the
+    /// module of a qualified backend
+    fn module_path(&self) -> String {
+        let backend_module = self
+            .backend
+            .and_then(|backend| backend.rsplit_once('.'))
+            .map(|(module, _)| module);
+        self.module
+            .or(backend_module)
+            .unwrap_or(EVAL_MODULE)
+            .to_string()
+    }
+
+    /// The synthetic file `input` is checked in, without the declarations of
+    /// the context module, and the offset of `input` in it
+    fn source(&self, module: &str, input: &str, kind: Snippet) -> (String, u32) {
+        let mut imports: Vec<String> = Vec::new();
+        let mut import = |path: String| {
+            if !imports.contains(&path) {
+                imports.push(path);
+            }
+        };
+        for file in self.files.iter().filter(|file| file.module == module) {
+            for imported in &file.imports {
+                match imported.import_all {
+                    true => import(format!("{}.*", imported.path)),
+                    false => import(imported.path.clone()),
+                }
+            }
+        }
+        let backend = self.backend.map(|backend| match backend.rsplit_once('.') {
+            Some((backend_module, name)) => {
+                if backend_module != module {
+                    import(backend.to_string());
+                }
+                name
+            }
+            None => backend,
+        });
+
+        let mut source = format!("module {}\n\n", module);
+        for path in &imports {
+            source.push_str(&format!("import {}\n", path));
+        }
+        source.push_str(&format!("\nblueprint {} {{\n", EVAL_BLUEPRINT));
+        if let Some(backend) = backend {
+            source.push_str(&format!("    with {}\n", backend));
+        }
+        let (open, close) = match kind {
+            Snippet::Expression => ("[", "]"),
+            Snippet::Statement => ("on_click { ", " }"),
+        };
+        source.push_str(&format!("    {}", open));
+        let offset = source.len() as u32;
+        source.push_str(input);
+        source.push_str(&format!("{}\n", close));
+        source.push_str("}\n");
+        (source, offset)
+    }
+}
+
+/// Move diagnostics of the synthetic file onto the expression at `offset`
+///
+/// Diagnostics of the checks, which have no span in the expression, cover
+/// all of it; labels and suggestions outside the expression are dropped.
+fn relocate(diagnostics: &Diagnostics, offset: u32, len: u32) -> Diagnostics {
+    let inside = |span: Span| {
+        (span.start >= offset && span.end <= offset + len)
+            .then(|| Span::new(span.start - offset, span.end - offset))
+    };
+    let mut relocated = Diagnostics::new();
+    for diagnostic in diagnostics.iter() {
+        let mut diagnostic = diagnostic.clone();
+        diagnostic.span = inside(diagnostic.span).unwrap_or(Span::new(0, len));
+        diagnostic.labels = diagnostic
+            .labels
+            .iter()
+            .filter_map(|label| Some(Label::new(inside(label.span)?, label.message.clone())))
+            .collect();
+        diagnostic.suggestions = diagnostic
+            .suggestions
+            .iter()
+            .filter_map(|suggestion| {
+                Some(Suggestion {
+                    span: inside(suggestion.span)?,
+                    ..suggestion.clone()
+                })
+            })
+            .collect();
+        diagnostic.related.retain(|info| info.file.is_some());
+        relocated.add(diagnostic);
+    }
+    relocated
+}
+
+/// The expression of the synthetic blueprint, if the input was exactly one
+fn content_expr(file: &ast::File) -> Option<&Expr> {
+    let [TopLevelDecl::Blueprint(blueprint)] = file.declarations.as_slice() else {
+        return None;
+    };
+    let mut content = blueprint.body.iter().filter_map(|stmt| match stmt {
+        BlueprintStmt::With(_) => None,
+        BlueprintStmt::ContentExpr(Expr::List(items)) if items.len() == 1 => Some(Some(&items[0])),
+        _ => Some(None),
+    });
+    match (content.next(), content.next()) {
+        (Some(expr), None) => expr,
+        _ => None,
+    }
+}
+
+/// The statement of the synthetic blueprint's handler, if the input was
+/// exactly one
+fn handler_stmt(file: &ast::File) -> Option<&ast::HandlerStmt> {
+    let [TopLevelDecl::Blueprint(blueprint)] = file.declarations.as_slice() else {
+        return None;
+    };
+    let mut handlers = blueprint.body.iter().filter_map(|stmt| match stmt {
+        BlueprintStmt::With(_) => None,
+        BlueprintStmt::EventHandler(handler) => match handler.body.as_slice() {
+            [stmt] => Some(Some(stmt)),
+            _ => Some(None),
+        },
+        _ => Some(None),
+    });
+    match (handlers.next(), handlers.next()) {
+        (Some(stmt), None) => stmt,
+        _ => None,
+    }
+}
+
+/// Type as written in Frel, naming declarations instead of their symbol ids
+fn type_name(ty: &Type, symbols: &SymbolTable) -> String {
+    match ty {
+        Type::Scheme(id)
+        | Type::Backend(id)
+        | Type::Blueprint(id)
+        | Type::Contract(id)
+        | Type::Theme(id)
+        | Type::Enum(id) => symbols
+            .get(*id)
+            .map(|symbol| symbol.name.clone())
+            .unwrap_or_else(|| ty.to_string()),
+        Type::Nullable(inner) => format!("{}?", type_name(inner, symbols)),
+        Type::List(elem) => format!("list<{}>", type_name(elem, symbols)),
+        Type::Set(elem) => format!("set<{}>", type_name(elem, symbols)),
+        _ => ty.to_string(),
+    }
+}
+
+/// Constants of an evaluation: theme fields and backend field initializers
+struct EvalEnv<'a> {
+    files: &'a [ast::File],
+    /// Module the expression is evaluated in
+    module: &'a str,
+    scopes: &'a ScopeGraph,
+    symbols: &'a SymbolTable,
+    /// Body scope of the synthetic blueprint
+    scope: ScopeId,
+    /// Initializers of the context backend's fields, by field name
+    fields: HashMap<String, Option<&'a Expr>>,
+    /// Evaluated themes, by name
+    themes: HashMap<String, Option<ThemeValues>>,
+    /// Fields being evaluated, to detect cycles
+    in_progress: HashSet<String>,
+}
+
+impl<'a> EvalEnv<'a> {
+    /// Collect the fields of the backend the synthetic blueprint uses
+    fn collect_backend_fields(&mut self, file: &ast::File) {
+        let [TopLevelDecl::Blueprint(blueprint)] = file.declarations.as_slice() else {
+            return;
+        };
+        for stmt in &blueprint.body {
+            if let BlueprintStmt::With(with) = stmt {
+                if let Some((module, _)) = self.declaring_module(&with.backend, SymbolKind::Backend)
+                {
+                    self.add_backend(&module, &with.backend, &mut HashSet::new());
+                }
+            }
+        }
+    }
+
+    /// Add the fields of a backend and of the backends it includes
+    fn add_backend(&mut self, module: &str, name: &str, visited: &mut HashSet<String>) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        let files = self.files;
+        let backend = files
+            .iter()
+            .filter(|file| file.module == module)
+            .flat_map(|file| &file.declarations)
+            .find_map(|decl| match decl {
+                TopLevelDecl::Backend(backend) if backend.name == name => Some(backend),
+                _ => None,
+            });
+        for member in backend.into_iter().flat_map(|backend| &backend.members) {
+            match member {
+                BackendMember::Include(included) => self.add_backend(module, included, visited),
+                BackendMember::Field(field) => {
+                    self.fields.insert(field.name.clone(), field.init.as_ref());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Module declaring the symbol `name` of kind `kind` as seen from the
+    /// synthetic blueprint, and the name it is declared under there
+    fn declaring_module(&self, name: &str, kind: SymbolKind) -> Option<(String, String)> {
+        let id = self
+            .symbols
+            .lookup_in_scope_chain(self.scope, name, self.scopes)?;
+        let symbol = self.symbols.get(id).filter(|symbol| symbol.kind == kind)?;
+        let module = symbol.source_module.as_deref().unwrap_or(self.module);
+        Some((module.to_string(), symbol.name.clone()))
+    }
+
+    fn theme(&mut self, name: &str) -> Option<&ThemeValues> {
+        if !self.themes.contains_key(name) {
+            let values =
+                self.declaring_module(name, SymbolKind::Theme)
+                    .and_then(|(module, theme)| {
+                        self.files
+                            .iter()
+                            .filter(|file| file.module == module)
+                            .find_map(|file| evaluate_theme(file, &theme, None).ok())
+                    });
+            self.themes.insert(name.to_string(), values);
+        }
+        self.themes[name].as_ref()
+    }
+}
+
+impl ConstEnv for EvalEnv<'_> {
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+        if let Some(init) = self.fields.get(name).copied() {
+            let init = init.ok_or_else(|| ConstEvalError::NoValue(name.to_string()))?;
+            if !self.in_progress.insert(name.to_string()) {
+                return Err(ConstEvalError::Cycle(name.to_string()));
+            }
+            let value = eval_const(init, self);
+            self.in_progress.remove(name);
+            return value;
+        }
+
+        let (theme, field) = match name.split_once('.') {
+            Some((theme, field)) => (theme, Some(field)),
+            None => (name, None),
+        };
+        let unknown = || ConstEvalError::UnknownName(name.to_string());
+        let values = self.theme(theme).ok_or_else(unknown)?;
+        match field {
+            Some(field) => match (values.get(field), values.errors.get(field)) {
+                (Some(value), _) => Ok(value.clone()),
+                (None, Some(error)) => Err(error.clone()),
+                (None, None) => Err(unknown()),
+            },
+            None => Ok(ConstValue::Object(
+                values
+                    .fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), field.value.clone()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{build_signature, Module};
+
+    const THEME: &str = r#"module app.theme
+
+theme AppTheme {
+    unit: i32 = 4
+    padding: i32 = unit * 3
+    accent: Color = #3366FF
+}
+"#;
+
+    const STORE: &str = r#"module app.store
+
+import app.theme.AppTheme
+
+backend Store {
+    count: i32 = 2
+    double: i32 = count * 2
+    label: String = "items"
+    title: String
+    command add(amount: i32)
+}
+"#;
+
+    fn project() -> (Vec<ast::File>, SignatureRegistry) {
+        let files: Vec<ast::File> = [THEME, STORE]
+            .iter()
+            .map(|source| parser::parse(source).file.unwrap())
+            .collect();
+        let registry = SignatureRegistry::new();
+        for file in &files {
+            registry.register(build_signature(&Module::from_file(file.clone())).signature);
+        }
+        (files, registry)
+    }
+
+    fn eval(expr: &str, module: Option<&str>, backend: Option<&str>) -> Evaluation {
+        let (files, registry) = project();
+        let context = EvalContext {
+            files: &files,
+            registry: &registry,
+            module,
+            backend,
+        };
+        evaluate_expression(expr, &context)
+            .unwrap_or_else(|failure| panic!("{:?}\n{}", failure.diagnostics, failure.source))
+    }
+
+    #[test]
+    fn test_theme_math() {
+        let result = eval("AppTheme.padding * 2 + 1", Some("app.theme"), None);
+        assert_eq!(result.value, Ok(ConstValue::Int(25)));
+        assert_eq!(result.type_name, "i32");
+
+        let result = eval("AppTheme.accent", Some("app.store"), None);
+        assert_eq!(result.value, Ok(ConstValue::Color(0x3366FFFF)));
+    }
+
+    #[test]
+    fn test_backend_fields() {
+        let result = eval("\"${double} ${label}\"", None, Some("app.store.Store"));
+        assert_eq!(result.value, Ok(ConstValue::String("4 items".to_string())));
+        assert_eq!(result.ty, Type::String);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (files, registry) = project();
+        let context = EvalContext {
+            files: &files,
+            registry: &registry,
+            module: Some("app.theme"),
+            backend: None,
+        };
+        let failure = evaluate_expression("missing + 1", &context).unwrap_err();
+        assert_eq!(failure.source, "missing + 1");
+        let diagnostic = failure.diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.span, Span::new(0, 11));
+        assert!(evaluate_expression("1, 2", &context).is_err());
+        assert!(evaluate_expression("1]\n}\nblueprint Other {\n[2", &context).is_err());
+
+        let result = eval("title", None, Some("app.store.Store"));
+        assert_eq!(
+            result.value,
+            Err(ConstEvalError::NoValue("title".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        let (files, registry) = project();
+        let context = EvalContext {
+            files: &files,
+            registry: &registry,
+            module: None,
+            backend: Some("app.store.Store"),
+        };
+        let mut interpreter = Interpreter::new();
+        let mut execute = |stmt: &str| execute_statement(stmt, &context, &mut interpreter);
+
+        let Ok(Some(Execution::Assigned {
+            name,
+            type_name,
+            value,
+        })) = execute("count = count + 5")
+        else {
+            panic!("expected an assignment");
+        };
+        assert_eq!((name.as_str(), type_name.as_str()), ("count", "i32"));
+        assert_eq!(value, ConstValue::Int(7));
+        assert!(matches!(
+            execute("label = \"${count} items\""),
+            Ok(Some(Execution::Assigned { .. }))
+        ));
+        // Expressions and calls of functions other than commands are not statements
+        assert!(matches!(execute("count"), Ok(None)));
+        assert!(matches!(execute("count + 1"), Ok(None)));
+        assert!(matches!(execute("upper(label)"), Ok(None)));
+        let Ok(Some(Execution::Called(call))) = execute("add(count * 2)") else {
+            panic!("expected a command call");
+        };
+        assert_eq!(call.args, [ConstValue::Int(14)]);
+        let failure = execute("count = \"many\"").unwrap_err();
+        assert_eq!(
+            failure.diagnostics.iter().next().unwrap().span,
+            Span::new(0, 14)
+        );
+
+        // Later evaluations read the assigned variables; initializers read
+        // the initial values
+        let result =
+            evaluate_with("\"${count} ${label} ${double}\"", &context, &interpreter).unwrap();
+        assert_eq!(
+            result.value,
+            Ok(ConstValue::String("7 7 items 4".to_string()))
+        );
+        assert_eq!(interpreter.variables.len(), 2);
+    }
+}
@@ -0,0 +1,132 @@
+// Event registry for Frel compiler
+//
+// This module defines the events a fragment can handle and the value each
+// passes to its handler, as specified in the instructions chapter. The type
+// checker warns about handlers of unknown events and checks the parameter a
+// handler declares against the event's value.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Registry of all known events
+pub struct EventRegistry {
+    events: HashMap<&'static str, EventDef>,
+}
+
+/// Definition of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventDef {
+    /// Name of the handler instruction, e.g. `on_click`
+    pub name: &'static str,
+    /// Type of the value passed to the handler, as written in a handler
+    /// parameter; `None` if the event passes no value
+    pub payload: Option<&'static str>,
+}
+
+/// Events passing a `PointerEvent`
+const POINTER_EVENTS: &[&str] = &[
+    "on_click",
+    "on_double_click",
+    "on_long_press",
+    "on_right_click",
+    "on_context_menu",
+    "on_pointer_move",
+    "on_pointer_enter",
+    "on_pointer_leave",
+    "on_primary_down",
+    "on_primary_up",
+    "on_secondary_down",
+    "on_secondary_up",
+    "on_hover_start",
+    "on_hover_end",
+    "on_drag_start",
+    "on_drag_end",
+    "on_drag_enter",
+    "on_drag_leave",
+    "on_drop",
+];
+
+/// Events passing a `KeyEvent`
+const KEY_EVENTS: &[&str] = &["on_key", "on_key_down", "on_key_up", "on_key_press"];
+
+/// Events passing no value
+const PLAIN_EVENTS: &[&str] = &[
+    "on_enter",
+    "on_escape",
+    "on_focus",
+    "on_blur",
+    "on_cancel",
+    "on_save",
+];
+
+impl EventRegistry {
+    /// Create a new event registry with all known events
+    pub fn new() -> Self {
+        let mut registry = Self {
+            events: HashMap::new(),
+        };
+        for name in POINTER_EVENTS {
+            registry.register(name, Some("PointerEvent"));
+        }
+        for name in KEY_EVENTS {
+            registry.register(name, Some("KeyEvent"));
+        }
+        for name in PLAIN_EVENTS {
+            registry.register(name, None);
+        }
+        registry.register("on_wheel", Some("WheelEvent"));
+        registry.register("on_scroll", Some("WheelEvent"));
+        registry.register("on_input", Some("InputEvent"));
+        registry.register("on_resize", Some("Rect?"));
+        registry
+    }
+
+    /// Get an event definition by handler name
+    pub fn get(&self, name: &str) -> Option<&EventDef> {
+        self.events.get(name)
+    }
+
+    /// Check if an event is known
+    pub fn is_known(&self, name: &str) -> bool {
+        self.events.contains_key(name)
+    }
+
+    /// Names of all known events, sorted
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.events.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    fn register(&mut self, name: &'static str, payload: Option<&'static str>) {
+        self.events.insert(name, EventDef { name, payload });
+    }
+}
+
+impl Default for EventRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static EVENT_REGISTRY: OnceLock<EventRegistry> = OnceLock::new();
+
+/// Get the global event registry instance
+pub fn event_registry() -> &'static EventRegistry {
+    EVENT_REGISTRY.get_or_init(EventRegistry::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_payloads() {
+        let registry = EventRegistry::new();
+        assert_eq!(registry.get("on_click").unwrap().payload, Some("PointerEvent"));
+        assert_eq!(registry.get("on_key").unwrap().payload, Some("KeyEvent"));
+        assert_eq!(registry.get("on_resize").unwrap().payload, Some("Rect?"));
+        assert_eq!(registry.get("on_focus").unwrap().payload, None);
+        assert!(!registry.is_known("on_tap"));
+    }
+}
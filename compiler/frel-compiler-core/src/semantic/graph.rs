@@ -0,0 +1,231 @@
+// Scope graph export for Frel
+//
+// Renders the scope graph and symbol table of an analysis, with the name
+// resolutions between them, for debugging resolution issues:
+// - DOT (Graphviz): scopes as nested clusters, symbols as nodes inside the
+//   scope defining them, resolution edges from the scope of each reference
+//   to the symbol it resolved to
+// - JSON: the same data as serializable scopes, symbols and resolutions
+//
+// Resolutions recorded without a source span (fragment names) cannot be
+// placed in a scope and are left out.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use super::scope::{ScopeGraph, ScopeId};
+use super::signature::{SerializableScopeGraph, SerializableSymbolTable};
+use super::symbol::{SymbolId, SymbolTable};
+use crate::source::Span;
+
+/// A name reference and the symbol it resolved to
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    pub span: Span,
+    /// Innermost scope containing the reference
+    pub scope: ScopeId,
+    pub symbol: SymbolId,
+}
+
+/// Scopes, symbols and resolutions of an analysis, ready to render
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopeGraphExport {
+    pub scopes: SerializableScopeGraph,
+    pub symbols: SerializableSymbolTable,
+    /// Resolutions in source order
+    pub resolutions: Vec<Resolution>,
+}
+
+impl ScopeGraphExport {
+    /// Collect the scopes, symbols and resolutions of an analysis
+    pub fn new(
+        scopes: &ScopeGraph,
+        symbols: &SymbolTable,
+        resolutions: &HashMap<Span, SymbolId>,
+    ) -> Self {
+        let mut resolutions: Vec<Resolution> = resolutions
+            .iter()
+            .filter(|(span, _)| !span.is_empty())
+            .map(|(span, symbol)| Resolution {
+                span: *span,
+                scope: innermost_scope(scopes, *span),
+                symbol: *symbol,
+            })
+            .collect();
+        resolutions.sort_by_key(|r| (r.span.start, r.span.end, r.symbol.0));
+
+        Self {
+            scopes: SerializableScopeGraph::from(scopes),
+            symbols: SerializableSymbolTable::from(symbols),
+            resolutions,
+        }
+    }
+
+    /// Render as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Render as a Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("digraph scopes {\n");
+        out.push_str("    compound=true;\n");
+        out.push_str("    node [shape=box, fontname=\"monospace\", fontsize=10];\n");
+        out.push_str("    edge [fontname=\"monospace\", fontsize=9];\n");
+
+        if !self.scopes.is_empty() {
+            self.write_scope(&mut out, ScopeId::ROOT, 1);
+        }
+
+        // Symbols owning a body scope point at its cluster
+        for symbol in self.symbols.iter() {
+            if let Some(body) = symbol.body_scope {
+                let _ = writeln!(
+                    out,
+                    "    symbol_{} -> scope_{} [lhead=cluster_{}, style=dotted, arrowhead=none];",
+                    symbol.id.0, body.0, body.0
+                );
+            }
+        }
+
+        // One edge per scope and symbol, labelled with the number of references
+        let mut edges: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+        for resolution in &self.resolutions {
+            *edges
+                .entry((resolution.scope.0, resolution.symbol.0))
+                .or_default() += 1;
+        }
+        for ((scope, symbol), count) in edges {
+            let label = if count > 1 {
+                format!(", label=\"{}x\"", count)
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                out,
+                "    scope_{} -> symbol_{} [ltail=cluster_{}, color=blue{}];",
+                scope, symbol, scope, label
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn write_scope(&self, out: &mut String, id: ScopeId, depth: usize) {
+        let Some(scope) = self.scopes.get(id) else {
+            return;
+        };
+        let indent = "    ".repeat(depth);
+        let label = match &scope.name {
+            Some(name) => format!("{} {}", scope.kind.as_str(), name),
+            None => scope.kind.as_str().to_string(),
+        };
+
+        let _ = writeln!(out, "{}subgraph cluster_{} {{", indent, id.0);
+        let _ = writeln!(out, "{}    label=\"{}\";", indent, escape(&label));
+        // Anchor for edges to and from the cluster
+        let _ = writeln!(out, "{}    scope_{} [shape=point];", indent, id.0);
+        for symbol in self.symbols.symbols_in_scope(id) {
+            let mut label = format!("{}\\n{}", escape(&symbol.name), symbol.kind.as_str());
+            let mut style = "";
+            if let Some(module) = &symbol.source_module {
+                let _ = write!(label, "\\nfrom {}", escape(module));
+                style = ", style=dashed";
+            }
+            let _ = writeln!(
+                out,
+                "{}    symbol_{} [label=\"{}\"{}];",
+                indent, symbol.id.0, label, style
+            );
+        }
+        for child in &scope.children {
+            self.write_scope(out, *child, depth + 1);
+        }
+        let _ = writeln!(out, "{}}}", indent);
+    }
+}
+
+/// The innermost scope whose span contains `span`
+fn innermost_scope(scopes: &ScopeGraph, span: Span) -> ScopeId {
+    scopes
+        .iter()
+        .filter(|scope| {
+            !scope.span.is_empty() && scope.span.start <= span.start && span.end <= scope.span.end
+        })
+        .min_by_key(|scope| (scope.span.len(), std::cmp::Reverse(scope.id.0)))
+        .map_or(ScopeId::ROOT, |scope| scope.id)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"module test
+
+backend Counter {
+    count : i32 = 0
+}
+
+blueprint Main {
+    with Counter
+    text { "x" } .. on_click { count = count + 1 }
+}
+"#;
+
+    fn export() -> ScopeGraphExport {
+        let result = crate::parse_file(SOURCE);
+        let analysis = crate::analyze(&result.file.unwrap());
+        ScopeGraphExport::new(&analysis.scopes, &analysis.symbols, &analysis.resolutions)
+    }
+
+    #[test]
+    fn test_resolutions() {
+        let export = export();
+        let name = |r: &Resolution| export.symbols.get(r.symbol).unwrap().name.as_str();
+
+        let counter = export
+            .resolutions
+            .iter()
+            .find(|r| name(r) == "Counter")
+            .unwrap();
+        let scope = export.scopes.get(counter.scope).unwrap();
+        assert_eq!(scope.name.as_deref(), Some("Main"));
+
+        let json: serde_json::Value = serde_json::from_str(&export.to_json()).unwrap();
+        assert!(json["scopes"]["scopes"].is_array());
+        assert_eq!(
+            json["resolutions"].as_array().unwrap().len(),
+            export.resolutions.len()
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let export = export();
+        let dot = export.to_dot();
+        assert!(dot.starts_with("digraph scopes {\n"), "{}", dot);
+        assert!(dot.contains("label=\"blueprint Main\";"), "{}", dot);
+        assert!(dot.contains("[label=\"count\\nfield\"]"), "{}", dot);
+
+        let counter = export.symbols.iter().find(|s| s.name == "Counter").unwrap();
+        let main = export
+            .scopes
+            .iter()
+            .find(|s| s.name.as_deref() == Some("Main"))
+            .unwrap();
+        let edge = format!("scope_{} -> symbol_{} ", main.id.0, counter.id.0);
+        assert!(dot.contains(&edge), "{}", dot);
+        assert_eq!(
+            dot.matches("subgraph cluster_").count(),
+            export.scopes.len()
+        );
+    }
+}
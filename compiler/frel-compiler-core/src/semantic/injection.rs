@@ -0,0 +1,251 @@
+// Injection-prone interpolations for Frel
+//
+// Some parameters pass their value to the host as is, as the instruction
+// registry records (`InstructionRegistry::sink`): the URL of
+// `background { image: ... }` and the content of `image { ... }`. A string
+// template interpolating a value there lets the value choose the whole URL;
+// `"${base}/avatar.png"` with `base = "javascript:..."` runs a script. Such a
+// template must be wrapped in a call saying how it is made safe (E0715):
+//
+//     avatar : String = url("${host}/avatar.png")   // scheme checked at runtime
+//     image { avatar } .. background { image: raw("${host}/bg.png") }   // trusted
+//
+// Templates are followed through conditionals, `?:` and the local
+// declarations at the top level of the blueprint. The value of a call is
+// never taken for a template.
+
+use std::collections::HashMap;
+
+use super::instructions::{instruction_registry, ValueSink};
+use super::loading::blocks;
+use crate::ast::{self, BinaryOp, BlueprintStmt, Expr, InstructionExpr, PostfixItem, TemplateElement};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics};
+use crate::source::Span;
+
+/// Report string templates passed to URL parameters without a wrapper
+pub fn check_injection(file: &ast::File) -> Diagnostics {
+    let _profile = crate::profile::enter("injection");
+    let mut diagnostics = Diagnostics::new();
+
+    for decl in &file.declarations {
+        match decl {
+            ast::TopLevelDecl::Blueprint(bp) => {
+                let locals: HashMap<&str, &Expr> = bp
+                    .body
+                    .iter()
+                    .filter_map(|stmt| match stmt {
+                        BlueprintStmt::LocalDecl(decl) => Some((decl.name.as_str(), &decl.init)),
+                        _ => None,
+                    })
+                    .collect();
+                let checker = Checker { locals };
+                checker.check_block(&bp.body, &mut diagnostics);
+            }
+            ast::TopLevelDecl::Theme(theme) => {
+                let checker = Checker {
+                    locals: HashMap::new(),
+                };
+                for member in &theme.members {
+                    if let ast::ThemeMember::InstructionSet(set) = member {
+                        for instruction in &set.instructions {
+                            checker.check_instruction(instruction, &mut diagnostics);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+struct Checker<'a> {
+    /// Initializers of the blueprint's local declarations
+    locals: HashMap<&'a str, &'a Expr>,
+}
+
+impl Checker<'_> {
+    fn check_block(&self, stmts: &[BlueprintStmt], diagnostics: &mut Diagnostics) {
+        for stmt in stmts {
+            match stmt {
+                BlueprintStmt::FragmentCreation(frag) => {
+                    if let Some(ValueSink::Url) = instruction_registry().sink(&frag.name, "content") {
+                        if let Some(ast::FragmentBody::Default(body)) = &frag.body {
+                            for content in body {
+                                if let BlueprintStmt::ContentExpr(expr) = content {
+                                    self.check_value(expr, &frag.name, "content", frag.span, diagnostics);
+                                }
+                            }
+                        }
+                    }
+                    for item in &frag.postfix {
+                        if let PostfixItem::Instruction(instr) = item {
+                            self.check_instruction_expr(instr, diagnostics);
+                        }
+                    }
+                }
+                BlueprintStmt::Instruction(instr) => self.check_instruction_expr(instr, diagnostics),
+                BlueprintStmt::Layout(layout) => {
+                    for instr in &layout.instructions {
+                        self.check_instruction_expr(instr, diagnostics);
+                    }
+                }
+                _ => {}
+            }
+            for block in blocks(stmt) {
+                self.check_block(block, diagnostics);
+            }
+        }
+    }
+
+    fn check_instruction_expr(&self, instr: &InstructionExpr, diagnostics: &mut Diagnostics) {
+        match instr {
+            InstructionExpr::Simple(instruction) => self.check_instruction(instruction, diagnostics),
+            InstructionExpr::When {
+                then_instr,
+                else_instr,
+                ..
+            } => {
+                self.check_instruction_expr(then_instr, diagnostics);
+                if let Some(else_instr) = else_instr {
+                    self.check_instruction_expr(else_instr, diagnostics);
+                }
+            }
+            InstructionExpr::Ternary {
+                then_instr,
+                else_instr,
+                ..
+            } => {
+                self.check_instruction_expr(then_instr, diagnostics);
+                self.check_instruction_expr(else_instr, diagnostics);
+            }
+            InstructionExpr::Reference(_) => {}
+        }
+    }
+
+    fn check_instruction(&self, instruction: &ast::Instruction, diagnostics: &mut Diagnostics) {
+        for (param, value) in &instruction.params {
+            if let Some(ValueSink::Url) = instruction_registry().sink(&instruction.name, param) {
+                self.check_value(value, &instruction.name, param, instruction.span, diagnostics);
+            }
+        }
+    }
+
+    fn check_value(
+        &self,
+        value: &Expr,
+        owner: &str,
+        param: &str,
+        span: Span,
+        diagnostics: &mut Diagnostics,
+    ) {
+        if !self.interpolates(value, 0) {
+            return;
+        }
+        let target = if param == "content" {
+            format!("the content of `{}`", owner)
+        } else {
+            format!("`{}` of `{}`", param, owner)
+        };
+        diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0715,
+                span,
+                format!("string template used as a URL in {}", target),
+            )
+            .with_help("wrap it in `url(...)` to check its scheme at runtime, or in `raw(...)` to trust it"),
+        );
+    }
+
+    /// Whether `expr` can be a string template with interpolations; a call,
+    /// such as `url(...)`, never is
+    fn interpolates(&self, expr: &Expr, depth: usize) -> bool {
+        match expr {
+            Expr::StringTemplate(elements) => elements
+                .iter()
+                .any(|element| matches!(element, TemplateElement::Interpolation(_))),
+            Expr::Ternary {
+                then_expr,
+                else_expr,
+                ..
+            } => self.interpolates(then_expr, depth) || self.interpolates(else_expr, depth),
+            Expr::Binary {
+                op: BinaryOp::Elvis,
+                left,
+                right,
+            } => self.interpolates(left, depth) || self.interpolates(right, depth),
+            // Local declarations can only refer to the ones before them, but
+            // an erroneous file may not
+            Expr::Identifier(name) if depth < self.locals.len() => self
+                .locals
+                .get(name.as_str())
+                .is_some_and(|init| self.interpolates(init, depth + 1)),
+            _ => false,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unwrapped_templates() {
+        let source = r#"module app
+
+backend Profile {
+    host : String = "cdn.example.com"
+    name : String = "ada"
+}
+
+theme AppTheme {
+    set hero {
+        background { image: "https://cdn.example.com/hero.png" }
+    }
+}
+
+blueprint Avatar {
+    with Profile
+    avatar : String = "https://${host}/${name}.png"
+    banner : String = "https://cdn.example.com/banner.png"
+
+    image { avatar }
+    image { "https://${host}/logo.png" }
+    logo : String = url("https://${host}/logo.png")
+    image { logo }
+    image { (raw("https://${host}/${name}.png")) }
+    image { banner }
+    column {
+        box { } .. background { image: name == "" ? banner : "${host}/bg.png" }
+        box { } .. background { image: raw("${host}/bg.png") }
+    }
+}
+"#;
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        let diagnostics = check_injection(&result.file.unwrap());
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.span.text(source)))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("string template used as a URL in the content of `image`", "image { avatar }"),
+                (
+                    "string template used as a URL in the content of `image`",
+                    "image { \"https://${host}/logo.png\" }"
+                ),
+                (
+                    "string template used as a URL in `image` of `background`",
+                    "background { image: name == \"\" ? banner : \"${host}/bg.png\" }"
+                ),
+            ]
+        );
+
+        let analysis = crate::semantic::analyze(&crate::parse_file(source).file.unwrap());
+        assert!(!analysis.diagnostics.has_errors(), "{:?}", analysis.diagnostics);
+    }
+}
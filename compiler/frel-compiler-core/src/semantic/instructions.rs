@@ -11,6 +11,10 @@ pub struct InstructionRegistry {
     instructions: HashMap<&'static str, InstructionDef>,
     /// Set of all known shorthand instruction names (no params)
     shorthands: HashMap<&'static str, ()>,
+    /// Parameters whose value the host uses as is, by (instruction, parameter)
+    sinks: HashMap<(&'static str, &'static str), ValueSink>,
+    /// Alternative names of keywords as (alias, keyword), by (instruction, parameter)
+    aliases: HashMap<(&'static str, &'static str), &'static [(&'static str, &'static str)]>,
 }
 
 /// Definition of an instruction
@@ -42,12 +46,22 @@ pub enum ParamKind {
     KeywordOrExpr(&'static [&'static str]),
 }
 
+/// How the host uses the value of a parameter as is, so an interpolated
+/// string could inject something into the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueSink {
+    /// A URL the host loads; a `javascript:` URL runs a script
+    Url,
+}
+
 impl InstructionRegistry {
     /// Create a new instruction registry with all known instructions
     pub fn new() -> Self {
         let mut registry = Self {
             instructions: HashMap::new(),
             shorthands: HashMap::new(),
+            sinks: HashMap::new(),
+            aliases: HashMap::new(),
         };
         registry.register_all();
         registry
@@ -58,6 +72,18 @@ impl InstructionRegistry {
         self.instructions.get(name)
     }
 
+    /// Check if an instruction has a parameter with the given name
+    pub fn has_param(&self, instr_name: &str, param_name: &str) -> bool {
+        self.instructions
+            .get(instr_name)
+            .is_some_and(|instr| {
+                instr
+                    .params
+                    .iter()
+                    .any(|p| Self::params_match(p.name, param_name))
+            })
+    }
+
     /// Check if a name is a known shorthand instruction
     pub fn is_shorthand(&self, name: &str) -> bool {
         self.shorthands.contains_key(name)
@@ -68,22 +94,39 @@ impl InstructionRegistry {
         self.instructions.contains_key(name) || self.shorthands.contains_key(name)
     }
 
-    /// Check if a value is a valid keyword for a specific instruction parameter
+    /// Check if a value is a valid keyword, or an alias of one, for a specific
+    /// instruction parameter
     pub fn is_valid_keyword(&self, instr_name: &str, param_name: &str, value: &str) -> bool {
-        if let Some(instr) = self.instructions.get(instr_name) {
-            for param in &instr.params {
-                // Match if param names are equal, or if the registry uses "" for positional
-                // params and the parser uses "value" as the default name
-                if Self::params_match(param.name, param_name) {
-                    return match &param.kind {
-                        ParamKind::Expression => false, // No keywords, must be expression
-                        ParamKind::Keywords(keywords) => keywords.contains(&value),
-                        ParamKind::KeywordOrExpr(keywords) => keywords.contains(&value),
-                    };
-                }
-            }
-        }
-        false
+        self.canonical_keyword(instr_name, param_name, value).is_some()
+    }
+
+    /// The keyword a value of an instruction parameter stands for: the value
+    /// itself if it is a keyword, the keyword it is an alias of, or `None`
+    pub fn canonical_keyword(
+        &self,
+        instr_name: &str,
+        param_name: &str,
+        value: &str,
+    ) -> Option<&'static str> {
+        let keywords = self.valid_keywords(instr_name, param_name)?;
+        keywords.iter().find(|keyword| **keyword == value).copied().or_else(|| {
+            self.aliases(instr_name, param_name)
+                .iter()
+                .find(|(alias, _)| *alias == value)
+                .map(|(_, keyword)| *keyword)
+        })
+    }
+
+    /// Aliases of the keywords of an instruction parameter, as (alias, keyword)
+    pub fn aliases(
+        &self,
+        instr_name: &str,
+        param_name: &str,
+    ) -> &'static [(&'static str, &'static str)] {
+        self.aliases
+            .iter()
+            .find(|((instr, param), _)| *instr == instr_name && Self::params_match(param, param_name))
+            .map_or(&[], |(_, aliases)| *aliases)
     }
 
     /// Get the list of valid keywords for an instruction parameter
@@ -115,6 +158,17 @@ impl InstructionRegistry {
         true
     }
 
+    /// How the host uses the value of a parameter as is, if it does
+    ///
+    /// The content of standard blueprints is registered under the blueprint
+    /// name, with the parameter `content`: `image { url }`.
+    pub fn sink(&self, instr_name: &str, param_name: &str) -> Option<ValueSink> {
+        self.sinks
+            .iter()
+            .find(|((instr, param), _)| *instr == instr_name && Self::params_match(param, param_name))
+            .map(|(_, sink)| *sink)
+    }
+
     /// Check if parameter names match.
     /// The registry uses "" for positional params, but the parser uses "value" as the default name.
     fn params_match(registry_name: &str, parsed_name: &str) -> bool {
@@ -204,6 +258,10 @@ impl InstructionRegistry {
             ParamDef { name: "horizontal", kind: ParamKind::Keywords(&["start", "center", "end"]) },
             ParamDef { name: "vertical", kind: ParamKind::Keywords(&["top", "center", "baseline", "bottom"]) },
         ]);
+        for instr in ["align_self", "align_items"] {
+            self.register_aliases(instr, "horizontal", &[("left", "start"), ("right", "end")]);
+            self.register_aliases(instr, "vertical", &[("middle", "center")]);
+        }
         self.register_instruction("align_relative", vec![
             ParamDef { name: "horizontal", kind: ParamKind::Keywords(&["before", "start", "center", "end", "after"]) },
             ParamDef { name: "vertical", kind: ParamKind::Keywords(&["above", "start", "center", "end", "below"]) },
@@ -248,6 +306,13 @@ impl InstructionRegistry {
             ParamDef { name: "", kind: ParamKind::Keywords(&["horizontal", "vertical", "both"]) },
         ]);
 
+        // List virtualization: only the items of a repeat near the viewport are materialized
+        self.register_instruction("virtualize", vec![
+            ParamDef { name: "item_height", kind: ParamKind::Expression },
+            ParamDef { name: "estimate", kind: ParamKind::Expression },
+            ParamDef { name: "overscan", kind: ParamKind::Expression },
+        ]);
+
         // Background
         self.register_instruction("background", vec![
             ParamDef { name: "color", kind: ParamKind::Expression },
@@ -255,6 +320,8 @@ impl InstructionRegistry {
             ParamDef { name: "gradient", kind: ParamKind::Expression },
             ParamDef { name: "image", kind: ParamKind::Expression },
         ]);
+        self.register_sink("background", "image", ValueSink::Url);
+        self.register_sink("image", "content", ValueSink::Url);
 
         // Corner radius
         self.register_instruction("corner_radius", vec![
@@ -296,9 +363,23 @@ impl InstructionRegistry {
         ]);
         self.register_shorthand("focusable");
         self.register_shorthand("not_focusable");
-        self.register_shorthand("autofocus");
+
+        // Tab order and focus groups
+        self.register_instruction("tab_index", vec![
+            ParamDef { name: "", kind: ParamKind::Expression },
+        ]);
+        self.register_instruction("autofocus", vec![]);
+        self.register_instruction("focus_group", vec![
+            ParamDef { name: "", kind: ParamKind::Keywords(&["trap", "arrows"]) },
+        ]);
+        self.register_aliases("focus_group", "", &[("roving", "arrows")]);
         self.register_shorthand("focus_trap");
 
+        // Overlay rendering: the fragment is mounted in an overlay container, not its parent
+        self.register_instruction("portal", vec![
+            ParamDef { name: "", kind: ParamKind::Expression },
+        ]);
+
         // Pointer events
         self.register_instruction("pointer_events", vec![
             ParamDef { name: "", kind: ParamKind::Keywords(&["enabled", "disabled"]) },
@@ -347,15 +428,7 @@ impl InstructionRegistry {
         ]);
 
         // Event handlers - these take closures, not keyword params
-        for event in &[
-            "on_click", "on_double_click", "on_long_press",
-            "on_right_click", "on_context_menu",
-            "on_hover_start", "on_hover_end",
-            "on_key_down", "on_key_up", "on_key_press",
-            "on_scroll", "on_resize",
-            "on_focus", "on_blur",
-            "on_drag_start", "on_drag_end", "on_drag_enter", "on_drag_leave", "on_drop",
-        ] {
+        for event in super::events::EventRegistry::new().names() {
             self.register_instruction(event, vec![
                 ParamDef { name: "", kind: ParamKind::Expression },
             ]);
@@ -369,6 +442,27 @@ impl InstructionRegistry {
     fn register_shorthand(&mut self, name: &'static str) {
         self.shorthands.insert(name, ());
     }
+
+    fn register_sink(&mut self, name: &'static str, param: &'static str, sink: ValueSink) {
+        self.sinks.insert((name, param), sink);
+    }
+
+    /// Register alternative names of keywords, each (alias, keyword)
+    ///
+    /// An alias is accepted wherever its keyword is, and code is generated
+    /// for the keyword.
+    fn register_aliases(
+        &mut self,
+        name: &'static str,
+        param: &'static str,
+        aliases: &'static [(&'static str, &'static str)],
+    ) {
+        debug_assert!(aliases.iter().all(|(alias, keyword)| {
+            let keywords = self.valid_keywords(name, param).unwrap_or_default();
+            keywords.contains(keyword) && !keywords.contains(alias)
+        }));
+        self.aliases.insert((name, param), aliases);
+    }
 }
 
 impl Default for InstructionRegistry {
@@ -425,6 +519,34 @@ mod tests {
         assert!(registry.is_valid_keyword("width", "", "expand"));
     }
 
+    #[test]
+    fn test_virtualize_params() {
+        let registry = InstructionRegistry::new();
+        assert!(registry.has_param("virtualize", "item_height"));
+        assert!(registry.has_param("virtualize", "overscan"));
+        assert!(!registry.has_param("virtualize", "value"));
+        assert!(!registry.has_param("unknown", "item_height"));
+    }
+
+    #[test]
+    fn test_focus_instructions() {
+        let registry = InstructionRegistry::new();
+        assert!(registry.is_known("autofocus"));
+        assert!(registry.accepts_expression("tab_index", ""));
+        assert!(registry.is_valid_keyword("focus_group", "", "trap"));
+        assert!(registry.is_valid_keyword("focus_group", "", "arrows"));
+        assert!(!registry.accepts_expression("focus_group", ""));
+        assert!(registry.is_shorthand("focus_trap"));
+    }
+
+    #[test]
+    fn test_value_sinks() {
+        let registry = InstructionRegistry::new();
+        assert_eq!(registry.sink("background", "image"), Some(ValueSink::Url));
+        assert_eq!(registry.sink("image", "content"), Some(ValueSink::Url));
+        assert_eq!(registry.sink("background", "color"), None);
+    }
+
     #[test]
     fn test_valid_keywords_lookup() {
         let registry = InstructionRegistry::new();
@@ -432,4 +554,17 @@ mod tests {
         assert!(keywords.is_some());
         assert!(keywords.unwrap().contains(&"pointer"));
     }
+
+    #[test]
+    fn test_keyword_aliases() {
+        let registry = InstructionRegistry::new();
+        assert!(registry.is_valid_keyword("align_items", "horizontal", "left"));
+        assert_eq!(registry.canonical_keyword("align_items", "horizontal", "left"), Some("start"));
+        assert_eq!(registry.canonical_keyword("align_self", "vertical", "middle"), Some("center"));
+        assert_eq!(registry.canonical_keyword("align_self", "vertical", "top"), Some("top"));
+        assert_eq!(registry.canonical_keyword("align_self", "vertical", "left"), None);
+        // Positional parameters are named `value` by the parser
+        assert_eq!(registry.canonical_keyword("focus_group", "value", "roving"), Some("arrows"));
+        assert!(registry.aliases("cursor", "").is_empty());
+    }
 }
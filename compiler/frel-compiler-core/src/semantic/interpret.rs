@@ -0,0 +1,207 @@
+// Interpreter for Frel expressions and handler statements
+//
+// This module provides:
+// - Interpreter: Variables assigned by handler statements and the commands
+//   they called, so expressions and handlers run one after the other see
+//   each other's effects
+// - CommandCall: A command called by a handler, with its argument values
+//
+// Expressions are evaluated by constant evaluation (`eval_const`); the
+// interpreter's variables shadow the constants of the environment it is
+// given. Commands are implemented by the host, so calling one only records
+// it. Nothing is type checked here: check the statements first.
+
+use indexmap::IndexMap;
+
+use super::const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
+use crate::ast::{Expr, HandlerStmt};
+
+/// A command called by a handler statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandCall {
+    pub name: String,
+    pub args: Vec<ConstValue>,
+}
+
+/// State of a sequence of evaluated expressions and executed statements
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    /// Values assigned, by name, in the order first assigned
+    pub variables: IndexMap<String, ConstValue>,
+    /// Commands called, in order
+    pub calls: Vec<CommandCall>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate an expression, reading the variables before `env`
+    pub fn eval(&self, expr: &Expr, env: &mut dyn ConstEnv) -> Result<ConstValue, ConstEvalError> {
+        eval_const(
+            expr,
+            &mut Frame {
+                variables: &self.variables,
+                env,
+            },
+        )
+    }
+
+    /// Execute a handler statement: assign the variable, or record the call
+    ///
+    /// A statement that fails changes nothing.
+    pub fn exec(
+        &mut self,
+        stmt: &HandlerStmt,
+        env: &mut dyn ConstEnv,
+    ) -> Result<(), ConstEvalError> {
+        match stmt {
+            HandlerStmt::Assignment { name, value } => {
+                let value = self.eval(value, env)?;
+                self.variables.insert(name.clone(), value);
+            }
+            HandlerStmt::CommandCall { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, env))
+                    .collect::<Result<_, _>>()?;
+                self.calls.push(CommandCall {
+                    name: name.clone(),
+                    args,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute the statements of a handler body in order, stopping at the
+    /// first that fails
+    pub fn run(
+        &mut self,
+        body: &[HandlerStmt],
+        env: &mut dyn ConstEnv,
+    ) -> Result<(), ConstEvalError> {
+        body.iter().try_for_each(|stmt| self.exec(stmt, env))
+    }
+}
+
+/// The variables of an interpreter over the constants of an environment
+struct Frame<'a> {
+    variables: &'a IndexMap<String, ConstValue>,
+    env: &'a mut dyn ConstEnv,
+}
+
+impl ConstEnv for Frame<'_> {
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+        match self.variables.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => self.env.lookup(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BlueprintStmt, TopLevelDecl};
+    use crate::semantic::const_eval::EmptyEnv;
+
+    /// The body of the first event handler of the first blueprint
+    fn handler(source: &str) -> Vec<HandlerStmt> {
+        let file = crate::parse_file(source).file.expect("parse failed");
+        let TopLevelDecl::Blueprint(blueprint) = &file.declarations[0] else {
+            panic!("expected blueprint");
+        };
+        blueprint
+            .body
+            .iter()
+            .find_map(|stmt| match stmt {
+                BlueprintStmt::EventHandler(handler) => Some(handler.body.clone()),
+                _ => None,
+            })
+            .expect("expected an event handler")
+    }
+
+    #[test]
+    fn test_run_handler() {
+        let body = handler(
+            r#"
+module test
+
+blueprint Main {
+    on_click {
+        count = count + 1
+        label = "clicked ${count} times"
+        save(count, label)
+        count = count * 10
+        reset
+    }
+}
+"#,
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .variables
+            .insert("count".to_string(), ConstValue::Int(1));
+        interpreter.run(&body, &mut EmptyEnv).unwrap();
+
+        assert_eq!(interpreter.variables["count"], ConstValue::Int(20));
+        assert_eq!(
+            interpreter.variables["label"],
+            ConstValue::String("clicked 2 times".to_string())
+        );
+        assert_eq!(
+            interpreter.calls,
+            [
+                CommandCall {
+                    name: "save".to_string(),
+                    args: vec![
+                        ConstValue::Int(2),
+                        ConstValue::String("clicked 2 times".to_string())
+                    ],
+                },
+                CommandCall {
+                    name: "reset".to_string(),
+                    args: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failing_statement() {
+        let body = handler(
+            r#"
+module test
+
+blueprint Main {
+    on_click {
+        total = total + 1
+        ratio = total / 0
+        total = 0
+    }
+}
+"#,
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .variables
+            .insert("total".to_string(), ConstValue::Int(4));
+        assert_eq!(
+            interpreter.run(&body, &mut EmptyEnv),
+            Err(ConstEvalError::DivisionByZero)
+        );
+        // The statements before the failing one took effect
+        assert_eq!(interpreter.variables["total"], ConstValue::Int(5));
+        assert!(!interpreter.variables.contains_key("ratio"));
+
+        let HandlerStmt::Assignment { value, .. } = &body[0] else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(
+            interpreter.eval(value, &mut EmptyEnv),
+            Ok(ConstValue::Int(6))
+        );
+    }
+}
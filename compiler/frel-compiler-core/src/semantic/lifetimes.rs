@@ -0,0 +1,348 @@
+// Backend lifetime analysis for Frel
+//
+// A singleton backend has one instance for the whole application. Fragments
+// created for each item of a `repeat` all share that instance, so a field they
+// write holds one value for every item instead of one per item. This pass
+// warns (E0605) when a singleton field is written:
+// - in an event handler inside a `repeat` body, or
+// - anywhere in a blueprint that is instantiated inside a `repeat` body,
+//   directly or through other blueprints
+//
+// Only backends and blueprints declared in the same file are considered.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{
+    self, BackendLifetime, BackendMember, BlueprintStmt, BlueprintValue, ControlStmt, FragmentBody,
+    HandlerStmt, PostfixItem,
+};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+
+/// Report singleton backend fields written per item of a `repeat`
+pub fn check_lifetimes(file: &ast::File) -> Diagnostics {
+    let _profile = crate::profile::enter("lifetimes");
+    let mut diagnostics = Diagnostics::new();
+
+    let backends: HashMap<&str, &ast::Backend> = file
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Backend(backend) => Some((backend.name.as_str(), backend)),
+            _ => None,
+        })
+        .collect();
+
+    let blueprints: Vec<(&ast::Blueprint, Usage)> = file
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Blueprint(bp) => Some((bp, Usage::collect(&bp.body))),
+            _ => None,
+        })
+        .collect();
+
+    // Blueprints instantiated once per item, directly or through other blueprints
+    let mut per_item: HashSet<&str> = blueprints
+        .iter()
+        .flat_map(|(_, usage)| usage.creates.iter())
+        .filter(|(_, in_repeat)| *in_repeat)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    loop {
+        let nested: Vec<&str> = blueprints
+            .iter()
+            .filter(|(bp, _)| per_item.contains(bp.name.as_str()))
+            .flat_map(|(_, usage)| usage.creates.iter().map(|(name, _)| name.as_str()))
+            .filter(|name| !per_item.contains(name))
+            .collect();
+        if nested.is_empty() {
+            break;
+        }
+        per_item.extend(nested);
+    }
+
+    for (bp, usage) in &blueprints {
+        let repeated = per_item.contains(bp.name.as_str());
+        let mut reported = HashSet::new();
+
+        for with in &usage.withs {
+            // `with param` refers to a backend-typed parameter, not a declaration
+            if with.name.is_none() && bp.params.iter().any(|p| p.name == with.backend) {
+                continue;
+            }
+            let Some(backend) = backends.get(with.backend.as_str()) else {
+                continue;
+            };
+            if backend.lifetime != BackendLifetime::Singleton {
+                continue;
+            }
+
+            for field in backend_fields(backend, &backends) {
+                let per_item_write = usage
+                    .writes
+                    .iter()
+                    .any(|(name, in_repeat)| *name == field && (repeated || *in_repeat));
+                if !per_item_write || usage.locals.contains(&field) || !reported.insert(field) {
+                    continue;
+                }
+
+                diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0605,
+                        with.span,
+                        format!(
+                            "`{}` writes `{}` of singleton backend `{}` for each item of a `repeat`, but all items share one instance",
+                            bp.name, field, backend.name
+                        ),
+                    )
+                    .with_help(format!(
+                        "declare `{}` as a `screen` or `fragment` backend, or keep the value per item in a local field",
+                        backend.name
+                    ))
+                    .with_related(RelatedInfo::new(
+                        backend.span,
+                        format!("`{}` is declared singleton here", backend.name),
+                    )),
+                );
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Fields of a backend, including fields of included backends in the same file
+fn backend_fields<'a>(
+    backend: &'a ast::Backend,
+    backends: &HashMap<&str, &'a ast::Backend>,
+) -> Vec<&'a str> {
+    let mut fields = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![backend];
+
+    while let Some(backend) = pending.pop() {
+        if !visited.insert(backend.name.as_str()) {
+            continue;
+        }
+        for member in &backend.members {
+            match member {
+                BackendMember::Field(field) => fields.push(field.name.as_str()),
+                BackendMember::Include(name) => pending.extend(backends.get(name.as_str())),
+                _ => {}
+            }
+        }
+    }
+
+    fields
+}
+
+/// What a blueprint body uses, with whether each use is inside a `repeat` body
+#[derive(Default)]
+struct Usage<'a> {
+    withs: Vec<&'a ast::WithClause>,
+    /// Fields declared by the blueprint itself, which shadow backend fields
+    locals: HashSet<&'a str>,
+    /// Fields assigned in event handlers
+    writes: Vec<(&'a str, bool)>,
+    /// Blueprints instantiated
+    creates: Vec<(&'a String, bool)>,
+}
+
+impl<'a> Usage<'a> {
+    fn collect(body: &'a [BlueprintStmt]) -> Self {
+        let mut usage = Usage::default();
+        for stmt in body {
+            match stmt {
+                BlueprintStmt::With(with) => usage.withs.push(with),
+                BlueprintStmt::LocalDecl(decl) => {
+                    usage.locals.insert(decl.name.as_str());
+                }
+                BlueprintStmt::Consume(consume) => {
+                    usage.locals.insert(consume.name.as_str());
+                }
+                _ => {}
+            }
+        }
+        usage.visit_stmts(body, false);
+        usage
+    }
+
+    fn visit_stmts(&mut self, stmts: &'a [BlueprintStmt], in_repeat: bool) {
+        for stmt in stmts {
+            self.visit_stmt(stmt, in_repeat);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a BlueprintStmt, in_repeat: bool) {
+        match stmt {
+            BlueprintStmt::FragmentCreation(frag) => {
+                self.creates.push((&frag.name, in_repeat));
+                for postfix in &frag.postfix {
+                    if let PostfixItem::EventHandler(handler) = postfix {
+                        self.visit_handler(&handler.body, in_repeat);
+                    }
+                }
+                match &frag.body {
+                    Some(FragmentBody::Default(body))
+                    | Some(FragmentBody::InlineBlueprint { body, .. }) => {
+                        self.visit_stmts(body, in_repeat)
+                    }
+                    Some(FragmentBody::Slots(slots)) => {
+                        for slot in slots {
+                            self.visit_slot(slot, in_repeat);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::When {
+                then_stmt,
+                else_stmt,
+                ..
+            }) => {
+                self.visit_stmt(then_stmt, in_repeat);
+                if let Some(else_stmt) = else_stmt {
+                    self.visit_stmt(else_stmt, in_repeat);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Repeat { body, .. }) => {
+                self.visit_stmts(body, true);
+            }
+            BlueprintStmt::Control(ControlStmt::Select {
+                branches,
+                else_branch,
+                ..
+            }) => {
+                for branch in branches {
+                    self.visit_stmt(&branch.body, in_repeat);
+                }
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(else_branch, in_repeat);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::OnError { body, fallback, .. }) => {
+                self.visit_stmts(body, in_repeat);
+                if let Some(fallback) = fallback {
+                    self.visit_stmts(fallback, in_repeat);
+                }
+            }
+            BlueprintStmt::Control(ControlStmt::Loading { body, .. }) => {
+                self.visit_stmts(body, in_repeat);
+            }
+            BlueprintStmt::EventHandler(handler) => self.visit_handler(&handler.body, in_repeat),
+            BlueprintStmt::Shortcut(shortcut) => self.visit_handler(&shortcut.body, in_repeat),
+            BlueprintStmt::SlotBinding(slot) => self.visit_slot(slot, in_repeat),
+            _ => {}
+        }
+    }
+
+    fn visit_slot(&mut self, slot: &'a ast::SlotBinding, in_repeat: bool) {
+        match &slot.blueprint {
+            BlueprintValue::Inline { body, .. } => self.visit_stmts(body, in_repeat),
+            BlueprintValue::Reference(name) => self.creates.push((name, in_repeat)),
+        }
+    }
+
+    fn visit_handler(&mut self, body: &'a [HandlerStmt], in_repeat: bool) {
+        for stmt in body {
+            if let HandlerStmt::Assignment { name, .. } = stmt {
+                self.writes.push((name.as_str(), in_repeat));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Diagnostics {
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        check_lifetimes(&result.file.unwrap())
+    }
+
+    #[test]
+    fn test_singleton_written_in_repeat() {
+        let diagnostics = check(
+            r#"module test
+
+singleton backend Selection {
+    selected : String = ""
+}
+
+blueprint List {
+    with Selection
+    items : List<String> = []
+    repeat on items { item ->
+        text { item } .. on_click { selected = item }
+    }
+}
+"#,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        let diag = diagnostics.iter().next().unwrap();
+        assert_eq!(diag.code.as_deref(), Some("E0605"));
+        assert!(diag.message.contains("`selected`"), "{}", diag.message);
+    }
+
+    #[test]
+    fn test_singleton_written_by_repeated_blueprint() {
+        let source = |lifetime: &str| {
+            format!(
+                r#"module test
+
+{} backend RowState {{
+    expanded : bool = false
+}}
+
+blueprint Row {{
+    with RowState
+    text {{ "row" }} .. on_click {{ expanded = true }}
+}}
+
+blueprint Card {{
+    Row()
+}}
+
+blueprint List {{
+    items : List<String> = []
+    repeat on items {{ item ->
+        Card()
+    }}
+}}
+"#,
+                lifetime
+            )
+        };
+
+        let diagnostics = check(&source("singleton"));
+        assert_eq!(diagnostics.len(), 1);
+        let diag = diagnostics.iter().next().unwrap();
+        assert!(
+            diag.message.starts_with("`Row` writes `expanded`"),
+            "{}",
+            diag.message
+        );
+
+        assert!(check(&source("screen")).is_empty());
+    }
+
+    #[test]
+    fn test_singleton_outside_repeat() {
+        let diagnostics = check(
+            r#"module test
+
+singleton backend Session {
+    user : String = ""
+}
+
+blueprint Login {
+    with Session
+    text { "log in" } .. on_click { user = "me" }
+}
+"#,
+        );
+        assert!(diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+// Loading placeholders for Frel
+//
+// A `loading` statement shows its body while a command invoked next to it is
+// running:
+//
+//     column {
+//         loading { text { "Searching..." } }
+//         button { "Search" } .. on_click { search() }
+//     }
+//
+// The commands it waits for are found in the statements of the block holding
+// it, however deeply nested: event handlers of the fragments, their bodies and
+// the bodies of control statements. Each backend command has an implicit
+// `<command>.loading` flag, true while an invocation is in flight; the
+// placeholder is shown while any of the flags of its commands is true. A
+// `loading` statement next to no command invocation would never be shown and
+// is an error (E0713).
+
+use crate::ast::{
+    self, BlueprintStmt, BlueprintValue, ControlStmt, FragmentBody, HandlerStmt, PostfixItem,
+};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics};
+
+/// Commands invoked by `stmts` and the statements they contain, in order of
+/// first use, without `loading` statements themselves
+pub fn loading_commands(stmts: &[BlueprintStmt]) -> Vec<&str> {
+    let mut found = Vec::new();
+    commands_in(stmts, &mut found);
+    found
+}
+
+/// Report `loading` statements next to no command invocation
+pub fn check_loading(file: &ast::File) -> Diagnostics {
+    let _profile = crate::profile::enter("loading");
+    let mut diagnostics = Diagnostics::new();
+
+    for decl in &file.declarations {
+        if let ast::TopLevelDecl::Blueprint(bp) = decl {
+            check_block(&bp.body, &mut diagnostics);
+        }
+    }
+
+    diagnostics
+}
+
+fn check_block(stmts: &[BlueprintStmt], diagnostics: &mut Diagnostics) {
+    let waits = !loading_commands(stmts).is_empty();
+    for stmt in stmts {
+        if let BlueprintStmt::Control(ControlStmt::Loading { span, .. }) = stmt {
+            if !waits {
+                diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0713,
+                        *span,
+                        "`loading` has no commands to wait for",
+                    )
+                    .with_help(
+                        "invoke a command in an event handler next to `loading`, or remove it",
+                    ),
+                );
+            }
+        }
+        for block in blocks(stmt) {
+            check_block(block, diagnostics);
+        }
+    }
+}
+
+fn commands_in<'a>(stmts: &'a [BlueprintStmt], found: &mut Vec<&'a str>) {
+    for stmt in stmts {
+        let handler = match stmt {
+            BlueprintStmt::Control(ControlStmt::Loading { .. }) => continue,
+            BlueprintStmt::EventHandler(handler) => Some(&handler.body),
+            BlueprintStmt::Shortcut(shortcut) => Some(&shortcut.body),
+            _ => None,
+        };
+        let postfix = match stmt {
+            BlueprintStmt::FragmentCreation(frag) => frag.postfix.as_slice(),
+            _ => &[],
+        };
+        let handlers = handler.into_iter().chain(postfix.iter().filter_map(|item| match item {
+            PostfixItem::EventHandler(handler) => Some(&handler.body),
+            PostfixItem::Instruction(_) => None,
+        }));
+        for body in handlers {
+            for handler_stmt in body {
+                if let HandlerStmt::CommandCall { name, .. } = handler_stmt {
+                    if !found.contains(&name.as_str()) {
+                        found.push(name);
+                    }
+                }
+            }
+        }
+        for block in blocks(stmt) {
+            commands_in(block, found);
+        }
+    }
+}
+
+/// Blocks of statements directly contained in `stmt`
+pub(crate) fn blocks(stmt: &BlueprintStmt) -> Vec<&[BlueprintStmt]> {
+    match stmt {
+        BlueprintStmt::FragmentCreation(frag) => match &frag.body {
+            Some(FragmentBody::Default(body)) | Some(FragmentBody::InlineBlueprint { body, .. }) => {
+                vec![body]
+            }
+            Some(FragmentBody::Slots(slots)) => slots.iter().filter_map(slot_body).collect(),
+            None => Vec::new(),
+        },
+        BlueprintStmt::SlotBinding(slot) => slot_body(slot).into_iter().collect(),
+        BlueprintStmt::Control(ControlStmt::When {
+            then_stmt,
+            else_stmt,
+            ..
+        }) => std::iter::once(std::slice::from_ref(then_stmt.as_ref()))
+            .chain(else_stmt.as_deref().map(std::slice::from_ref))
+            .collect(),
+        BlueprintStmt::Control(ControlStmt::Repeat { body, .. }) => vec![body],
+        BlueprintStmt::Control(ControlStmt::Select {
+            branches,
+            else_branch,
+            ..
+        }) => branches
+            .iter()
+            .map(|branch| std::slice::from_ref(branch.body.as_ref()))
+            .chain(else_branch.as_deref().map(std::slice::from_ref))
+            .collect(),
+        BlueprintStmt::Control(ControlStmt::OnError { body, fallback, .. }) => {
+            std::iter::once(body.as_slice()).chain(fallback.as_deref()).collect()
+        }
+        BlueprintStmt::Control(ControlStmt::Loading { body, .. }) => vec![body],
+        _ => Vec::new(),
+    }
+}
+
+fn slot_body(slot: &ast::SlotBinding) -> Option<&[BlueprintStmt]> {
+    match &slot.blueprint {
+        BlueprintValue::Inline { body, .. } => Some(body),
+        BlueprintValue::Reference(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> ast::File {
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        result.file.unwrap()
+    }
+
+    #[test]
+    fn test_loading_commands() {
+        let file = parse(
+            r#"module app
+
+backend Search {
+    query : String = ""
+    command search()
+    command clear()
+}
+
+blueprint Page {
+    with Search
+    column {
+        loading { text { "Searching..." } }
+        row {
+            button { "Search" } .. on_click { search() }
+            when query != "" {
+                button { "Clear" } .. on_click { clear() search() }
+            }
+        }
+    }
+}
+
+blueprint Idle {
+    loading { text { "Waiting..." } }
+    text { "Nothing to do" }
+}
+"#,
+        );
+        let ast::TopLevelDecl::Blueprint(bp) = &file.declarations[1] else {
+            panic!("expected a blueprint");
+        };
+        let Some(BlueprintStmt::FragmentCreation(column)) = bp.body.get(1) else {
+            panic!("expected a fragment");
+        };
+        let Some(FragmentBody::Default(body)) = &column.body else {
+            panic!("expected a body");
+        };
+        assert_eq!(loading_commands(body), ["search", "clear"]);
+
+        let messages: Vec<_> = check_loading(&file)
+            .iter()
+            .map(|d| (d.code.clone().unwrap_or_default(), d.message.clone()))
+            .collect();
+        assert_eq!(
+            messages,
+            [("E0713".to_string(), "`loading` has no commands to wait for".to_string())]
+        );
+    }
+}
@@ -0,0 +1,427 @@
+// Members of the built-in types for Frel
+//
+// This module provides:
+// - MEMBERS: The properties and methods of strings, collections, numbers,
+//   Instant and Duration, like `name.length` and `name.toUpper()`, with the
+//   signatures the type checker checks uses against
+// - lookup: The member a field access on a value of a built-in type names
+//
+// Schemes, backends, contracts and themes have declared fields instead; the
+// types listed in `Receiver` have these. Parameter and result types may
+// depend on the type of the value, e.g. `contains` of a `List<T>` takes a
+// `T`. Host runtimes map each member to their own string, collection and
+// date functions; the JavaScript code generator has the mapping of each.
+
+use super::types::Type;
+
+/// A property or method of a built-in type
+pub struct Member {
+    pub name: &'static str,
+    /// Kinds of values that have the member
+    pub receivers: &'static [Receiver],
+    /// Parameters of a method; `None` for a property
+    pub params: Option<&'static [MemberParam]>,
+    /// Type of the value (of the call, for a method) from the type of the receiver
+    result: fn(&Type) -> Type,
+    /// One-line description, shown on hover
+    pub doc: &'static str,
+}
+
+/// A parameter of a method of a built-in type
+pub struct MemberParam {
+    pub name: &'static str,
+    /// Type of the argument from the type of the receiver
+    ty: fn(&Type) -> Type,
+}
+
+/// Kinds of values with members
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Receiver {
+    String,
+    List,
+    Set,
+    Map,
+    Integer,
+    Float,
+    Instant,
+    Duration,
+}
+
+impl Receiver {
+    /// The kind of a value of type `ty`, if values of it have members
+    pub fn of(ty: &Type) -> Option<Receiver> {
+        match ty {
+            Type::Ref(inner) => Receiver::of(inner),
+            Type::String => Some(Receiver::String),
+            Type::List(_) => Some(Receiver::List),
+            Type::Set(_) => Some(Receiver::Set),
+            Type::Map(_, _) => Some(Receiver::Map),
+            Type::Instant => Some(Receiver::Instant),
+            Type::Duration => Some(Receiver::Duration),
+            ty if ty.is_integer() => Some(Receiver::Integer),
+            ty if ty.is_float() => Some(Receiver::Float),
+            _ => None,
+        }
+    }
+}
+
+/// All members of built-in types
+pub const MEMBERS: &[Member] = &[
+    // Strings and collections
+    Member {
+        name: "length",
+        receivers: &[Receiver::String, Receiver::List, Receiver::Set, Receiver::Map],
+        params: None,
+        result: |_| Type::I32,
+        doc: "Number of characters of a string, or of elements or entries of a collection",
+    },
+    Member {
+        name: "isEmpty",
+        receivers: &[Receiver::String, Receiver::List, Receiver::Set, Receiver::Map],
+        params: None,
+        result: |_| Type::Bool,
+        doc: "Whether the string or collection has a length of zero",
+    },
+    // Strings
+    Member {
+        name: "toUpper",
+        receivers: &[Receiver::String],
+        params: Some(&[]),
+        result: |_| Type::String,
+        doc: "The string in upper case",
+    },
+    Member {
+        name: "toLower",
+        receivers: &[Receiver::String],
+        params: Some(&[]),
+        result: |_| Type::String,
+        doc: "The string in lower case",
+    },
+    Member {
+        name: "trim",
+        receivers: &[Receiver::String],
+        params: Some(&[]),
+        result: |_| Type::String,
+        doc: "The string without leading and trailing whitespace",
+    },
+    Member {
+        name: "contains",
+        receivers: &[Receiver::String],
+        params: Some(&[TEXT_PARAM]),
+        result: |_| Type::Bool,
+        doc: "Whether `text` occurs in the string",
+    },
+    Member {
+        name: "startsWith",
+        receivers: &[Receiver::String],
+        params: Some(&[TEXT_PARAM]),
+        result: |_| Type::Bool,
+        doc: "Whether the string starts with `text`",
+    },
+    Member {
+        name: "endsWith",
+        receivers: &[Receiver::String],
+        params: Some(&[TEXT_PARAM]),
+        result: |_| Type::Bool,
+        doc: "Whether the string ends with `text`",
+    },
+    Member {
+        name: "indexOf",
+        receivers: &[Receiver::String],
+        params: Some(&[TEXT_PARAM]),
+        result: |_| Type::I32,
+        doc: "Index of the first occurrence of `text` in the string; -1 if there is none",
+    },
+    // Lists and sets
+    Member {
+        name: "contains",
+        receivers: &[Receiver::List, Receiver::Set],
+        params: Some(&[MemberParam {
+            name: "item",
+            ty: element,
+        }]),
+        result: |_| Type::Bool,
+        doc: "Whether `item` is an element of the collection",
+    },
+    Member {
+        name: "first",
+        receivers: &[Receiver::List],
+        params: None,
+        result: |ty| element(ty).make_nullable(),
+        doc: "First element of the list; null if it is empty",
+    },
+    Member {
+        name: "last",
+        receivers: &[Receiver::List],
+        params: None,
+        result: |ty| element(ty).make_nullable(),
+        doc: "Last element of the list; null if it is empty",
+    },
+    // Maps
+    Member {
+        name: "containsKey",
+        receivers: &[Receiver::Map],
+        params: Some(&[KEY_PARAM]),
+        result: |_| Type::Bool,
+        doc: "Whether the map has an entry for `key`",
+    },
+    Member {
+        name: "get",
+        receivers: &[Receiver::Map],
+        params: Some(&[KEY_PARAM]),
+        result: |ty| map_types(ty).1.make_nullable(),
+        doc: "Value of the entry for `key`; null if there is none",
+    },
+    Member {
+        name: "keys",
+        receivers: &[Receiver::Map],
+        params: None,
+        result: |ty| Type::List(Box::new(map_types(ty).0)),
+        doc: "Keys of the map's entries",
+    },
+    Member {
+        name: "values",
+        receivers: &[Receiver::Map],
+        params: None,
+        result: |ty| Type::List(Box::new(map_types(ty).1)),
+        doc: "Values of the map's entries",
+    },
+    // Numbers
+    Member {
+        name: "abs",
+        receivers: &[Receiver::Integer, Receiver::Float],
+        params: Some(&[]),
+        result: |ty| ty.clone(),
+        doc: "Absolute value of the number",
+    },
+    Member {
+        name: "toString",
+        receivers: &[Receiver::Integer, Receiver::Float],
+        params: Some(&[]),
+        result: |_| Type::String,
+        doc: "Decimal text of the number, without grouping; see `formatNumber` for display",
+    },
+    Member {
+        name: "round",
+        receivers: &[Receiver::Float],
+        params: Some(&[]),
+        result: |ty| ty.clone(),
+        doc: "The number rounded to the nearest whole number, halves up",
+    },
+    Member {
+        name: "floor",
+        receivers: &[Receiver::Float],
+        params: Some(&[]),
+        result: |ty| ty.clone(),
+        doc: "Largest whole number not greater than the number",
+    },
+    Member {
+        name: "ceil",
+        receivers: &[Receiver::Float],
+        params: Some(&[]),
+        result: |ty| ty.clone(),
+        doc: "Smallest whole number not less than the number",
+    },
+    // Instants
+    Member {
+        name: "epochMillis",
+        receivers: &[Receiver::Instant],
+        params: None,
+        result: |_| Type::I64,
+        doc: "Milliseconds since 1970-01-01T00:00:00Z",
+    },
+    Member {
+        name: "plus",
+        receivers: &[Receiver::Instant],
+        params: Some(&[DURATION_PARAM]),
+        result: |_| Type::Instant,
+        doc: "The instant `duration` later",
+    },
+    Member {
+        name: "minus",
+        receivers: &[Receiver::Instant],
+        params: Some(&[DURATION_PARAM]),
+        result: |_| Type::Instant,
+        doc: "The instant `duration` earlier",
+    },
+    Member {
+        name: "until",
+        receivers: &[Receiver::Instant],
+        params: Some(&[MemberParam {
+            name: "other",
+            ty: |_| Type::Instant,
+        }]),
+        result: |_| Type::Duration,
+        doc: "Duration from the instant to `other`; negative if `other` is earlier",
+    },
+    // Durations
+    Member {
+        name: "millis",
+        receivers: &[Receiver::Duration],
+        params: None,
+        result: |_| Type::I64,
+        doc: "Length of the duration in milliseconds",
+    },
+    Member {
+        name: "seconds",
+        receivers: &[Receiver::Duration],
+        params: None,
+        result: |_| Type::F64,
+        doc: "Length of the duration in seconds",
+    },
+    Member {
+        name: "minutes",
+        receivers: &[Receiver::Duration],
+        params: None,
+        result: |_| Type::F64,
+        doc: "Length of the duration in minutes",
+    },
+    Member {
+        name: "hours",
+        receivers: &[Receiver::Duration],
+        params: None,
+        result: |_| Type::F64,
+        doc: "Length of the duration in hours",
+    },
+];
+
+const TEXT_PARAM: MemberParam = MemberParam {
+    name: "text",
+    ty: |_| Type::String,
+};
+
+const KEY_PARAM: MemberParam = MemberParam {
+    name: "key",
+    ty: |ty| map_types(ty).0,
+};
+
+const DURATION_PARAM: MemberParam = MemberParam {
+    name: "duration",
+    ty: |_| Type::Duration,
+};
+
+/// Element type of a list or set receiver
+fn element(ty: &Type) -> Type {
+    match ty {
+        Type::Ref(inner) => element(inner),
+        ty => ty.element_type().cloned().unwrap_or(Type::Unknown),
+    }
+}
+
+/// Key and value types of a map receiver
+fn map_types(ty: &Type) -> (Type, Type) {
+    match ty {
+        Type::Ref(inner) => map_types(inner),
+        Type::Map(key, value) => ((**key).clone(), (**value).clone()),
+        _ => (Type::Unknown, Type::Unknown),
+    }
+}
+
+/// Look up the member `name` of values of type `ty`
+pub fn lookup(ty: &Type, name: &str) -> Option<&'static Member> {
+    let receiver = Receiver::of(ty)?;
+    MEMBERS
+        .iter()
+        .find(|member| member.name == name && member.receivers.contains(&receiver))
+}
+
+/// Members of values of type `ty`, in declaration order
+pub fn members_of(ty: &Type) -> impl Iterator<Item = &'static Member> {
+    let receiver = Receiver::of(ty);
+    MEMBERS
+        .iter()
+        .filter(move |member| receiver.is_some_and(|r| member.receivers.contains(&r)))
+}
+
+impl Member {
+    /// Whether the member is a method, used as `value.name(...)`
+    pub fn is_method(&self) -> bool {
+        self.params.is_some()
+    }
+
+    /// Type of the member of a value of type `receiver`
+    pub fn result(&self, receiver: &Type) -> Type {
+        (self.result)(receiver)
+    }
+
+    /// Types of the method's parameters for a value of type `receiver`
+    pub fn param_types(&self, receiver: &Type) -> Vec<Type> {
+        self.params
+            .unwrap_or_default()
+            .iter()
+            .map(|param| (param.ty)(receiver))
+            .collect()
+    }
+
+    /// Signature for messages, e.g. `List<String>.contains(item: String) -> bool`,
+    /// with types shown by `type_name`
+    pub fn signature(&self, receiver: &Type, type_name: impl Fn(&Type) -> String) -> String {
+        let params = self.params.map(|params| {
+            let params: Vec<_> = params
+                .iter()
+                .map(|param| format!("{}: {}", param.name, type_name(&(param.ty)(receiver))))
+                .collect();
+            format!("({})", params.join(", "))
+        });
+        format!(
+            "{}.{}{} -> {}",
+            type_name(receiver),
+            self.name,
+            params.unwrap_or_default(),
+            type_name(&self.result(receiver))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let list = Type::List(Box::new(Type::String));
+        assert!(lookup(&Type::String, "length").is_some());
+        assert!(lookup(&list, "length").is_some());
+        assert!(lookup(&Type::Ref(Box::new(Type::String)), "toUpper").is_some());
+        assert!(lookup(&list, "toUpper").is_none());
+        assert!(lookup(&Type::Bool, "length").is_none());
+        assert!(lookup(&Type::Decimal, "abs").is_none());
+        assert!(lookup(&Type::I32, "round").is_none());
+        assert!(lookup(&Type::F64, "round").is_some());
+    }
+
+    #[test]
+    fn test_types() {
+        let list = Type::List(Box::new(Type::I32));
+        let contains = lookup(&list, "contains").unwrap();
+        assert_eq!(contains.param_types(&list), vec![Type::I32]);
+        assert_eq!(
+            lookup(&list, "first").unwrap().result(&list),
+            Type::Nullable(Box::new(Type::I32))
+        );
+
+        let map = Type::Map(Box::new(Type::String), Box::new(Type::Bool));
+        assert_eq!(lookup(&map, "get").unwrap().param_types(&map), vec![Type::String]);
+        assert_eq!(
+            lookup(&map, "keys").unwrap().result(&map),
+            Type::List(Box::new(Type::String))
+        );
+        assert_eq!(lookup(&Type::U8, "abs").unwrap().result(&Type::U8), Type::U8);
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            lookup(&Type::String, "startsWith").unwrap().signature(&Type::String, Type::to_string),
+            "String.startsWith(text: String) -> bool"
+        );
+        assert_eq!(
+            lookup(&Type::String, "length").unwrap().signature(&Type::String, Type::to_string),
+            "String.length -> i32"
+        );
+        assert_eq!(
+            lookup(&Type::Instant, "until").unwrap().signature(&Type::Instant, Type::to_string),
+            "Instant.until(other: Instant) -> Duration"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+// Localized message catalogs for Frel
+//
+// This module provides:
+// - extract_messages: Collect the localized messages of a file. Localized
+//   strings are `asset String` theme fields, keyed as `Theme.field`
+// - check_translations: Compare a translation file against the catalog,
+//   reporting missing, extra and placeholder-mismatched entries
+// - pseudo_locale: Generate an accented, expanded translation for testing
+//   layouts against longer text
+//
+// Translation files are JSON objects mapping message keys to text, either flat
+// (`{"Theme.field": "..."}`) or nested by theme (`{"Theme": {"field": "..."}}`).
+// Text may contain `{name}` placeholders, which every translation of a message
+// must use consistently.
+
+use crate::ast::{self, ThemeMember, TypeExpr};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics};
+use crate::source::Span;
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Message keys mapped to translated text, in file order
+pub type Translations = IndexMap<String, String>;
+
+/// A localized message declared in source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Theme declaring the message
+    pub theme: String,
+    /// Field name within the theme
+    pub field: String,
+    /// Span of the field declaration
+    pub span: Span,
+}
+
+/// Localized messages of a file, keyed by `Theme.field`
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    pub messages: IndexMap<String, Message>,
+    /// Theme declaration spans, for reporting entries of unknown fields
+    theme_spans: IndexMap<String, Span>,
+}
+
+impl MessageCatalog {
+    /// Get a message by key
+    pub fn get(&self, key: &str) -> Option<&Message> {
+        self.messages.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// Collect the `asset String` theme fields of a file
+pub fn extract_messages(file: &ast::File) -> MessageCatalog {
+    let mut catalog = MessageCatalog::default();
+
+    for decl in &file.declarations {
+        let ast::TopLevelDecl::Theme(theme) = decl else {
+            continue;
+        };
+        catalog.theme_spans.insert(theme.name.clone(), theme.span);
+
+        for member in &theme.members {
+            let ThemeMember::Field(field) = member else {
+                continue;
+            };
+            if field.is_asset && is_string(&field.type_expr) {
+                catalog.messages.insert(
+                    format!("{}.{}", theme.name, field.name),
+                    Message {
+                        theme: theme.name.clone(),
+                        field: field.name.clone(),
+                        span: field.span,
+                    },
+                );
+            }
+        }
+    }
+
+    catalog
+}
+
+fn is_string(type_expr: &TypeExpr) -> bool {
+    match type_expr {
+        TypeExpr::Named(name) => name == "String",
+        TypeExpr::Asset(inner) => is_string(inner),
+        _ => false,
+    }
+}
+
+/// Parse a JSON translation file, flattening nested objects into dotted keys
+pub fn parse_translations(json: &str) -> Result<Translations, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let Value::Object(root) = value else {
+        return Err("translation file must contain a JSON object".to_string());
+    };
+
+    let mut translations = Translations::new();
+    flatten_translations("", root, &mut translations)?;
+    Ok(translations)
+}
+
+fn flatten_translations(
+    prefix: &str,
+    object: serde_json::Map<String, Value>,
+    translations: &mut Translations,
+) -> Result<(), String> {
+    for (key, value) in object {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::String(text) => {
+                translations.insert(key, text);
+            }
+            Value::Object(nested) => flatten_translations(&key, nested, translations)?,
+            _ => return Err(format!("translation of `{}` must be a string", key)),
+        }
+    }
+    Ok(())
+}
+
+/// Check a locale's translations against the catalog
+///
+/// With a reference translation (usually the source locale), each entry must
+/// use the same set of placeholders as the reference entry.
+pub fn check_translations(
+    catalog: &MessageCatalog,
+    translations: &Translations,
+    reference: Option<&Translations>,
+    locale: &str,
+) -> Diagnostics {
+    let mut diagnostics = Diagnostics::new();
+
+    for (key, message) in &catalog.messages {
+        let Some(text) = translations.get(key) else {
+            diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0801,
+                    message.span,
+                    format!("missing `{}` translation for `{}`", locale, key),
+                )
+                .with_help(format!(
+                    "add `\"{}\"` to the `{}` translation file",
+                    key, locale
+                )),
+            );
+            continue;
+        };
+
+        let Some(expected) = reference.and_then(|r| r.get(key)) else {
+            continue;
+        };
+        let expected = placeholders(expected);
+        let found = placeholders(text);
+        if expected != found {
+            diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0803,
+                    message.span,
+                    format!(
+                        "`{}` translation for `{}` uses placeholders {} but the reference uses {}",
+                        locale,
+                        key,
+                        format_placeholders(&found),
+                        format_placeholders(&expected)
+                    ),
+                )
+                .with_help("placeholders are filled in by name and cannot be renamed"),
+            );
+        }
+    }
+
+    for key in translations.keys() {
+        if catalog.messages.contains_key(key) {
+            continue;
+        }
+        // Point at the theme if it exists, the field is what's missing
+        let theme = key.split_once('.').map_or(key.as_str(), |(theme, _)| theme);
+        let span = catalog.theme_spans.get(theme).copied().unwrap_or_default();
+        diagnostics.add(Diagnostic::from_code(
+            &codes::E0802,
+            span,
+            format!(
+                "`{}` translation file has an entry for `{}`, which is not a localized message",
+                locale, key
+            ),
+        ));
+    }
+
+    diagnostics
+}
+
+/// Names of the `{name}` placeholders in a text
+pub fn placeholders(text: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) => {
+                names.insert(after[..close].trim().to_string());
+                rest = &after[close + 1..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+fn format_placeholders(names: &BTreeSet<String>) -> String {
+    if names.is_empty() {
+        return "none".to_string();
+    }
+    names
+        .iter()
+        .map(|name| format!("{{{}}}", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Generate a pseudo-locale for every message in the catalog
+///
+/// Text comes from the reference translation, falling back to the field name.
+pub fn pseudo_locale(catalog: &MessageCatalog, reference: Option<&Translations>) -> Translations {
+    catalog
+        .messages
+        .iter()
+        .map(|(key, message)| {
+            let text = reference
+                .and_then(|r| r.get(key))
+                .map(String::as_str)
+                .unwrap_or(&message.field);
+            (key.clone(), pseudo_localize(text))
+        })
+        .collect()
+}
+
+const ACCENTED_LOWER: [char; 26] = [
+    'á', 'ƀ', 'ç', 'đ', 'é', 'ƒ', 'ĝ', 'ĥ', 'í', 'ĵ', 'ķ', 'ĺ', 'ɱ', 'ñ', 'ó', 'þ', 'ǫ', 'ŕ', 'š',
+    'ţ', 'ú', 'ṽ', 'ŵ', 'ẋ', 'ý', 'ž',
+];
+
+const ACCENTED_UPPER: [char; 26] = [
+    'Á', 'Ɓ', 'Ç', 'Đ', 'É', 'Ƒ', 'Ĝ', 'Ĥ', 'Í', 'Ĵ', 'Ķ', 'Ĺ', 'Ṁ', 'Ñ', 'Ó', 'Þ', 'Ǫ', 'Ŕ', 'Š',
+    'Ţ', 'Ú', 'Ṽ', 'Ŵ', 'Ẋ', 'Ý', 'Ž',
+];
+
+/// Pseudo-localize a text: accent letters, pad by about 40% and bracket it
+///
+/// Placeholders are kept as-is so the result still formats correctly.
+pub fn pseudo_localize(text: &str) -> String {
+    let mut result = String::from("[");
+    let mut letters: usize = 0;
+    let mut in_placeholder = false;
+
+    for c in text.chars() {
+        match c {
+            '{' => in_placeholder = true,
+            '}' => in_placeholder = false,
+            _ => {}
+        }
+        if in_placeholder || !c.is_ascii_alphabetic() {
+            result.push(c);
+            continue;
+        }
+        letters += 1;
+        let index = (c.to_ascii_lowercase() as u8 - b'a') as usize;
+        result.push(if c.is_ascii_lowercase() {
+            ACCENTED_LOWER[index]
+        } else {
+            ACCENTED_UPPER[index]
+        });
+    }
+
+    let padding = (letters * 2).div_ceil(5).max(1);
+    result.push(' ');
+    result.extend(std::iter::repeat_n('~', padding));
+    result.push(']');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+
+    const SOURCE: &str = r#"module test
+
+theme Chat {
+    new_message : asset String
+    greeting : asset String
+    corner_radius : u32 = 10
+}
+"#;
+
+    fn catalog() -> MessageCatalog {
+        let result = crate::parse_file(SOURCE);
+        extract_messages(&result.file.expect("parse failed"))
+    }
+
+    fn translations(json: &str) -> Translations {
+        parse_translations(json).unwrap()
+    }
+
+    #[test]
+    fn test_extract_asset_strings() {
+        let catalog = catalog();
+        let keys: Vec<&String> = catalog.messages.keys().collect();
+        assert_eq!(keys, ["Chat.new_message", "Chat.greeting"]);
+    }
+
+    #[test]
+    fn test_nested_translations() {
+        let nested = translations(r#"{ "Chat": { "greeting": "Hi" } }"#);
+        assert_eq!(nested.get("Chat.greeting").map(String::as_str), Some("Hi"));
+        assert!(parse_translations(r#"{ "Chat.greeting": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn test_missing_and_extra_entries() {
+        let de = translations(r#"{ "Chat.greeting": "Hallo", "Chat.farewell": "Tschüss" }"#);
+        let diagnostics = check_translations(&catalog(), &de, None, "de");
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .map(|d| d.code.as_deref().unwrap())
+            .collect();
+        assert_eq!(codes, ["E0801", "E0802"]);
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn test_placeholder_mismatch() {
+        let en = translations(
+            r#"{ "Chat.new_message": "New from {sender}", "Chat.greeting": "Hi {name}" }"#,
+        );
+        let de = translations(
+            r#"{ "Chat.new_message": "Neu von {absender}", "Chat.greeting": "Hallo {name}" }"#,
+        );
+        let diagnostics = check_translations(&catalog(), &de, Some(&en), "de");
+        assert_eq!(diagnostics.len(), 1);
+        let diag = diagnostics.iter().next().unwrap();
+        assert_eq!(diag.severity, Severity::Error);
+        assert!(diag.message.contains("{absender}"), "{}", diag.message);
+    }
+
+    #[test]
+    fn test_pseudo_locale() {
+        assert_eq!(pseudo_localize("Hi {name}!"), "[Ĥí {name}! ~]");
+        let en = translations(r#"{ "Chat.greeting": "Hello" }"#);
+        let pseudo = pseudo_locale(&catalog(), Some(&en));
+        assert_eq!(
+            pseudo.get("Chat.greeting").map(String::as_str),
+            Some("[Ĥéĺĺó ~~]")
+        );
+        assert!(pseudo
+            .get("Chat.new_message")
+            .unwrap()
+            .starts_with("[ñéŵ_ɱéššáĝé"));
+        assert!(check_translations(&catalog(), &pseudo, Some(&en), "pseudo").is_empty());
+    }
+}
@@ -5,37 +5,105 @@
 // - Symbol table population
 // - Name resolution
 // - Type checking (Phase 1b)
+// - Backend lifetime checks
+// - Keyboard shortcut checks
+// - Context value (provide/consume) checks
+// - Injection-prone interpolation checks
+// - Recursive scheme checks
+// - Unused symbol checks
+// - Blueprint complexity report
+// - Scope graph export (DOT/JSON)
+// - State machines implicit in backends (`frel graph --state`)
+// - Persisted module signatures
+// - Rename refactoring
+// - Hook points for external semantic passes
+// - Expression evaluation for debugging (`frel eval`)
+// - Interpreter for expressions and handler statements (the `frel eval` REPL)
+// - Preview of virtual scheme fields against sample data (`frel eval --scheme`)
 //
 // The analysis is organized in layers that produce immutable output,
 // enabling incremental compilation and IDE support.
 
+pub mod builtins;
+pub mod complexity;
+pub mod conformance;
+pub mod const_eval;
+pub mod context;
 pub mod dump;
+pub mod eval;
+pub mod events;
+pub mod graph;
+pub mod injection;
 pub mod instructions;
+pub mod interpret;
+pub mod lifetimes;
+pub mod loading;
+pub mod members;
+pub mod messages;
 pub mod resolve;
 pub mod scope;
+pub mod shortcuts;
 pub mod signature;
 pub mod signature_builder;
+pub mod signature_store;
+pub mod state_machine;
 pub mod symbol;
+pub mod theme_usage;
+pub mod theme_values;
 pub mod typecheck;
 pub mod types;
+pub mod unused;
 pub mod module_analysis;
+pub mod passes;
+pub mod recursion;
+pub mod preview;
+pub mod rename;
 
+pub use complexity::{analyze_complexity, BlueprintComplexity, ComplexityReport};
+pub use conformance::check_conformance;
+pub use context::{check_context, nearest_providers, Provider};
+pub use const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
 pub use dump::dump as dump_semantic;
+pub use eval::{
+    evaluate_expression, evaluate_with, execute_statement, EvalContext, EvalFailure, Evaluation,
+    Execution,
+};
+pub use graph::{Resolution, ScopeGraphExport};
+pub use injection::check_injection;
+pub use interpret::{CommandCall, Interpreter};
+pub use lifetimes::check_lifetimes;
+pub use loading::{check_loading, loading_commands};
+pub use messages::{
+    check_translations, extract_messages, parse_translations, pseudo_locale, Message, MessageCatalog,
+    Translations,
+};
 pub use resolve::{resolve, resolve_with_registry, ResolveResult, Resolver};
 pub use scope::{Scope, ScopeGraph, ScopeId, ScopeKind};
+pub use shortcuts::{check_shortcuts, KeyChord};
 pub use signature::{
     ExportedDecl, ModuleSignature, SerializableScope, SerializableScopeGraph,
-    SerializableSymbol, SerializableSymbolTable, SignatureRegistry, SIGNATURE_VERSION,
+    SerializableSymbol, SerializableSymbolTable, ShortcutDecl, SignatureRegistry,
+    SIGNATURE_VERSION,
 };
 pub use signature_builder::{build_signature, SignatureResult};
+pub use state_machine::{extract_state_machines, StateMachine, Transition};
+pub use signature_store::{
+    load_signature, save_signature, signature_file_path, LoadDirResult, SIGNATURE_FILE_SUFFIX,
+};
 pub use module_analysis::{analyze_module, ModuleAnalysisResult};
+pub use passes::{run_passes, SemanticPass};
+pub use recursion::check_recursive_schemes;
+pub use preview::{preview_scheme, PreviewError, SchemePreview, VirtualValue};
 pub use symbol::{LookupResult, Symbol, SymbolId, SymbolKind, SymbolTable};
-pub use typecheck::{typecheck, typecheck_with_registry, TypeCheckResult, TypeChecker};
+pub use theme_usage::{unused_theme_members, ThemeMemberKind, ThemeUsage, UnusedMember};
+pub use theme_values::{evaluate_theme, ThemeEvalError, ThemeValue, ThemeValues};
+pub use typecheck::{typecheck, typecheck_with_registry, TypeCheckResult, TypeChecker, TypeQuery};
+pub use unused::check_unused;
 pub use types::{ResolvedType, Type};
 
 use crate::ast;
 use crate::diagnostic::Diagnostics;
-use crate::source::Span;
+use crate::source::{FileId, Span};
 
 /// Result of semantic analysis
 #[derive(Debug)]
@@ -52,6 +120,8 @@ pub struct SemanticResult {
     pub expr_types: std::collections::HashMap<Span, Type>,
     /// Resolved type expressions (span -> type)
     pub type_resolutions: std::collections::HashMap<Span, Type>,
+    /// Identifiers by statement span and name (-> symbol, type there)
+    pub names: std::collections::HashMap<(Span, String), (SymbolId, Type)>,
 }
 
 impl SemanticResult {
@@ -86,9 +156,27 @@ pub fn analyze(file: &ast::File) -> SemanticResult {
     // Phase 1b: Type resolution and checking
     let typecheck_result = typecheck::typecheck(file, &resolve_result.scopes, &resolve_result.symbols, &resolve_result.imports);
 
+    let context_types = context::context_types(file, &typecheck_result);
+
     // Merge diagnostics
     let mut diagnostics = resolve_result.diagnostics;
     diagnostics.merge(typecheck_result.diagnostics);
+    diagnostics.merge(lifetimes::check_lifetimes(file));
+    diagnostics.merge(loading::check_loading(file));
+    diagnostics.merge(injection::check_injection(file));
+    diagnostics.merge(conformance::check_conformance(std::slice::from_ref(file)));
+    diagnostics.merge(recursion::check_recursive_schemes(std::slice::from_ref(file)));
+    diagnostics.merge(shortcuts::check_shortcuts(
+        std::slice::from_ref(file),
+        &file.module,
+        &SignatureRegistry::new(),
+    ));
+    diagnostics.merge(context::check_context(
+        std::slice::from_ref(file),
+        std::slice::from_ref(&context_types),
+        &file.module,
+        &SignatureRegistry::new(),
+    ));
 
     SemanticResult {
         scopes: resolve_result.scopes,
@@ -97,6 +185,7 @@ pub fn analyze(file: &ast::File) -> SemanticResult {
         resolutions: resolve_result.resolutions,
         expr_types: typecheck_result.expr_types,
         type_resolutions: typecheck_result.type_resolutions,
+        names: typecheck_result.names,
     }
 }
 
@@ -121,13 +210,31 @@ impl Module {
 
     /// Create a new module from multiple files
     /// All files must have the same module path
-    pub fn from_files(path: String, files: Vec<ast::File>) -> Self {
+    ///
+    /// Files are ordered by source path so symbol and scope IDs don't depend
+    /// on the order the files were found in.
+    pub fn from_files(path: String, mut files: Vec<ast::File>) -> Self {
+        files.sort_by(|a, b| a.source_path.cmp(&b.source_path));
         Self { path, files }
     }
 
-    /// Add a file to this module
+    /// The file of the module that spans in `id` point into
+    ///
+    /// Spans in the default file belong to the first file, as they do in a
+    /// module of files parsed without file IDs.
+    pub fn file(&self, id: FileId) -> Option<&ast::File> {
+        self.files
+            .iter()
+            .find(|file| file.file == id)
+            .or_else(|| self.files.first().filter(|_| id.is_default()))
+    }
+
+    /// Add a file to this module, keeping the files ordered by source path
     pub fn add_file(&mut self, file: ast::File) {
-        self.files.push(file);
+        let index = self
+            .files
+            .partition_point(|f| f.source_path <= file.source_path);
+        self.files.insert(index, file);
     }
 }
 
@@ -203,4 +310,107 @@ backend A { }
         assert_eq!(result.error_count(), 1);
     }
 
+    /// Scopes, symbols, resolutions and expression types as stable JSON
+    fn serialize(
+        scopes: &ScopeGraph,
+        symbols: &SymbolTable,
+        resolutions: &std::collections::HashMap<Span, SymbolId>,
+        expr_types: &std::collections::HashMap<Span, Type>,
+    ) -> String {
+        let mut types: Vec<_> = expr_types
+            .iter()
+            .map(|(span, ty)| (span.start, span.end, ty.to_string()))
+            .collect();
+        types.sort();
+        format!(
+            "{}\n{:?}",
+            ScopeGraphExport::new(scopes, symbols, resolutions).to_json(),
+            types
+        )
+    }
+
+    #[test]
+    fn test_deterministic_ids() {
+        let source = r#"
+module test.app
+
+backend Store {
+    items : List<String> = []
+    selected : i32 = 0
+    command clear()
+}
+
+enum Mode { View Edit }
+
+blueprint Row(label: String) {
+    text { label }
+}
+
+blueprint Main {
+    with Store
+    count : i32 = selected + 1
+    column {
+        repeat on items { item ->
+            Row(item) .. on_click { selected = count }
+        }
+        when count > 1 {
+            text { "${count} items" }
+        }
+    }
+}
+"#;
+        let runs: Vec<String> = (0..2)
+            .map(|_| {
+                let result = analyze_source(source);
+                serialize(
+                    &result.scopes,
+                    &result.symbols,
+                    &result.resolutions,
+                    &result.expr_types,
+                )
+            })
+            .collect();
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[test]
+    fn test_deterministic_module_file_order() {
+        let parse = |source: &str, path: &str| {
+            parser::parse_with_path(source, path)
+                .file
+                .expect("file should parse")
+        };
+        let first = "module app\n\nbackend Store {\n    count : i32 = 0\n}\n";
+        let second = "module app\n\nblueprint Main {\n    with Store\n    text { count }\n}\n";
+
+        let orders = [
+            [("b.frel", second), ("a.frel", first)],
+            [("a.frel", first), ("b.frel", second)],
+        ];
+        let runs: Vec<(String, String)> = orders
+            .iter()
+            .map(|files| {
+                let mut module = Module::from_files(
+                    "app".to_string(),
+                    vec![parse(files[0].1, files[0].0)],
+                );
+                module.add_file(parse(files[1].1, files[1].0));
+
+                let signature = build_signature(&module);
+                let registry = SignatureRegistry::new();
+                registry.register(signature.signature.clone());
+                let result = analyze_module(&module, &registry);
+                (
+                    serde_json::to_string(&signature.signature).unwrap(),
+                    serialize(
+                        &result.scopes,
+                        &result.symbols,
+                        &result.resolutions,
+                        &result.expr_types,
+                    ),
+                )
+            })
+            .collect();
+        assert_eq!(runs[0], runs[1]);
+    }
 }
@@ -1,11 +1,19 @@
+use super::conformance;
+use super::context;
+use super::injection;
+use super::lifetimes;
+use super::loading;
+use super::recursion;
 use super::resolve;
 use super::scope::{ScopeGraph, ScopeId};
+use super::shortcuts;
 use super::signature::SignatureRegistry;
 use super::symbol::{SymbolId, SymbolTable};
 use super::typecheck;
+use super::unused;
 use super::types::Type;
 use super::Module;
-use crate::diagnostic::Diagnostics;
+use crate::diagnostic::{Diagnostic, Diagnostics, Label};
 use crate::source::Span;
 use std::collections::HashMap;
 
@@ -24,6 +32,8 @@ pub struct ModuleAnalysisResult {
     pub expr_types: HashMap<Span, Type>,
     /// Resolved type expressions (span -> type)
     pub type_resolutions: HashMap<Span, Type>,
+    /// Identifiers by statement span and name (-> symbol, type there)
+    pub names: HashMap<(Span, String), (SymbolId, Type)>,
 }
 
 impl ModuleAnalysisResult {
@@ -44,15 +54,22 @@ impl ModuleAnalysisResult {
 /// - Name resolution with import validation
 /// - Type resolution using the registry for imported types
 /// - Type checking
+/// - Keyboard shortcut conflicts with the other modules in the registry
+/// - Context values, matching consumers to the nearest providers
+/// - Unused locals, parameters, backends, schemes and imports
+/// - String templates used as URLs without `url()` or `raw()`
 ///
 /// The registry should contain signatures for all modules that this module imports.
 pub fn analyze_module(module: &Module, registry: &SignatureRegistry) -> ModuleAnalysisResult {
+    let _profile = crate::profile::enter("analyze_module");
     let mut combined_diagnostics = Diagnostics::new();
     let mut combined_resolutions = HashMap::new();
     let mut combined_scopes = ScopeGraph::new();
     let mut combined_symbols = SymbolTable::new();
     let mut combined_expr_types = HashMap::new();
+    let mut combined_names = HashMap::new();
     let mut combined_type_resolutions = HashMap::new();
+    let mut context_types = Vec::new();
 
     // Process each file in the module
     for file in &module.files {
@@ -67,6 +84,9 @@ pub fn analyze_module(module: &Module, registry: &SignatureRegistry) -> ModuleAn
             &resolve_result.imports,
             registry,
         );
+        context_types.push(context::context_types(file, &typecheck_result));
+        let unused_diagnostics =
+            unused::check_unused(file, &resolve_result, &module.path, registry);
 
         // Merge results
         if combined_symbols.is_empty() {
@@ -90,30 +110,64 @@ pub fn analyze_module(module: &Module, registry: &SignatureRegistry) -> ModuleAn
                         };
 
                         if !is_same_import {
-                            combined_diagnostics.error(
+                            let mut diagnostic = Diagnostic::error(
                                 format!(
                                     "duplicate definition of '{}' (also defined in another file)",
                                     symbol.name
                                 ),
                                 symbol.def_span,
                             );
+                            // Only files told apart by their spans can point at the other one
+                            if let Some(existing) =
+                                existing.filter(|e| e.def_span.file != symbol.def_span.file)
+                            {
+                                diagnostic = diagnostic
+                                    .with_label(Label::new(existing.def_span, "first defined here"));
+                            }
+                            combined_diagnostics.add(diagnostic);
                         }
                     }
                 }
             }
-            // Merge resolutions (spans are unique per file so no conflicts)
+            // Merge resolutions (spans carry their file so no conflicts)
             combined_resolutions.extend(resolve_result.resolutions);
         }
 
         // Merge diagnostics
         combined_diagnostics.merge(resolve_result.diagnostics);
         combined_diagnostics.merge(typecheck_result.diagnostics);
+        combined_diagnostics.merge(lifetimes::check_lifetimes(file));
+        combined_diagnostics.merge(loading::check_loading(file));
+        combined_diagnostics.merge(injection::check_injection(file));
+        combined_diagnostics.merge(unused_diagnostics);
 
         // Merge type information
         combined_expr_types.extend(typecheck_result.expr_types);
         combined_type_resolutions.extend(typecheck_result.type_resolutions);
+        combined_names.extend(typecheck_result.names);
     }
 
+    // Contracts are implemented by backends and arenas in any file of the module
+    combined_diagnostics.merge(conformance::check_conformance(&module.files));
+
+    // Schemes of any file of the module can contain each other
+    combined_diagnostics.merge(recursion::check_recursive_schemes(&module.files));
+
+    // Shortcuts are checked against every module in the registry
+    combined_diagnostics.merge(shortcuts::check_shortcuts(
+        &module.files,
+        &module.path,
+        registry,
+    ));
+
+    // Consumers are matched to the providers creating them in any file of the module
+    combined_diagnostics.merge(context::check_context(
+        &module.files,
+        &context_types,
+        &module.path,
+        registry,
+    ));
+
     ModuleAnalysisResult {
         scopes: combined_scopes,
         symbols: combined_symbols,
@@ -121,6 +175,7 @@ pub fn analyze_module(module: &Module, registry: &SignatureRegistry) -> ModuleAn
         resolutions: combined_resolutions,
         expr_types: combined_expr_types,
         type_resolutions: combined_type_resolutions,
+        names: combined_names,
     }
 }
 
@@ -153,7 +208,7 @@ enum Status {
         assert!(!sig_result.has_errors());
 
         // Register the signature
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_result.signature);
 
         // Now compile a module that imports from test.data
@@ -231,7 +286,7 @@ scheme User {
         let data_module = Module::from_file(file);
         let sig_result = build_signature(&data_module);
 
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_result.signature);
 
         // Try to import something that doesn't exist
@@ -313,7 +368,7 @@ scheme User {
 
         // First, build and register the module's signature (simulating previous compile)
         let sig_result = build_signature(&module);
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_result.signature);
 
         // Now recompile the same module with itself in the registry
@@ -359,7 +414,7 @@ scheme UserB {
         let sig_b = build_signature(&module_b);
 
         // Register both signatures
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_a.signature);
         registry.register(sig_b.signature);
 
@@ -428,7 +483,7 @@ scheme User {
         let module_a = Module::from_file(parse_a.file.unwrap());
         let sig_a = build_signature(&module_a);
 
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_a.signature);
 
         // Now create module_b with TWO files, both importing User from module_a
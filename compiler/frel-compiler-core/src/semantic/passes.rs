@@ -0,0 +1,111 @@
+// External semantic passes
+//
+// A semantic pass runs after type checking with read-only access to a
+// module's files and its analysis, and may report diagnostics. Other files
+// and the environment are only reached through the `PluginContext` it is
+// given (see sandbox.rs). Passes
+// implement domain-specific policy checks, e.g. design-system compliance or
+// security review rules, and are packaged outside the core crate: the
+// compiler only sees them as `SemanticPass` trait objects.
+
+use std::sync::Arc;
+
+use super::module_analysis::ModuleAnalysisResult;
+use super::Module;
+use crate::diagnostic::Diagnostics;
+use crate::sandbox::PluginContext;
+
+/// A check run on every analyzed module
+pub trait SemanticPass: Send + Sync {
+    /// Name of the pass, e.g. `design-tokens`
+    fn name(&self) -> &str;
+
+    /// Check a module, adding any findings to `diagnostics`
+    fn run(
+        &self,
+        module: &Module,
+        analysis: &ModuleAnalysisResult,
+        context: &PluginContext,
+        diagnostics: &mut Diagnostics,
+    );
+}
+
+/// Run `passes` in order on an analyzed module
+///
+/// Passes only see fully typed modules: nothing runs if the analysis reported
+/// errors.
+pub fn run_passes(
+    passes: &[Arc<dyn SemanticPass>],
+    module: &Module,
+    analysis: &ModuleAnalysisResult,
+    context: &PluginContext,
+) -> Diagnostics {
+    let mut diagnostics = Diagnostics::new();
+    if analysis.diagnostics.has_errors() {
+        return diagnostics;
+    }
+    let _profile = crate::profile::enter("semantic_passes");
+    for pass in passes {
+        pass.run(module, analysis, context, &mut diagnostics);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::TopLevelDecl;
+    use crate::parser;
+    use crate::semantic::{analyze_module, SignatureRegistry};
+
+    /// Design-system rule: blueprint names start with an uppercase letter
+    struct BlueprintNames;
+
+    impl SemanticPass for BlueprintNames {
+        fn name(&self) -> &str {
+            "blueprint-names"
+        }
+
+        fn run(
+            &self,
+            module: &Module,
+            _: &ModuleAnalysisResult,
+            _: &PluginContext,
+            diagnostics: &mut Diagnostics,
+        ) {
+            for file in &module.files {
+                for decl in &file.declarations {
+                    if let TopLevelDecl::Blueprint(bp) = decl {
+                        if !bp.name.starts_with(char::is_uppercase) {
+                            diagnostics.warning(
+                                format!("blueprint `{}` is not capitalized", bp.name),
+                                bp.span,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn run(source: &str) -> Diagnostics {
+        let file = parser::parse(source).file.unwrap();
+        let module = Module::from_file(file);
+        let analysis = analyze_module(&module, &SignatureRegistry::new());
+        let passes: Vec<Arc<dyn SemanticPass>> = vec![Arc::new(BlueprintNames)];
+        run_passes(&passes, &module, &analysis, &PluginContext::new())
+    }
+
+    #[test]
+    fn test_pass_reports_diagnostics() {
+        let diagnostics = run("module app\n\nblueprint Main { }\n\nblueprint card { }\n");
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["blueprint `card` is not capitalized"]);
+    }
+
+    #[test]
+    fn test_passes_skip_modules_with_errors() {
+        let diagnostics = run("module app\n\nblueprint card {\n    missing { }\n}\n");
+        assert!(diagnostics.is_empty());
+    }
+}
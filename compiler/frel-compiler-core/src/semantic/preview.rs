@@ -0,0 +1,589 @@
+// Preview of virtual scheme fields
+//
+// This module provides:
+// - preview_scheme: Evaluate the virtual fields of a scheme against sample
+//   data given as JSON, with constant evaluation
+//
+// The data's members are converted to values by the declared types of the
+// scheme's fields: nested schemes are objects with their own virtual fields
+// computed, enum variants are their names, instants ISO 8601 text or
+// milliseconds and colors `#RRGGBB` or `#RRGGBBAA` text. A field missing from
+// the data is null if nullable and has no value otherwise.
+//
+// Virtual fields can refer to the scheme's fields, to other virtual fields
+// and to enum variants; calls other than built-in functions are not
+// evaluated.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde_json::Value as Json;
+
+use super::const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
+use crate::ast::{self, SchemeMember, TopLevelDecl, TypeExpr};
+
+/// Values of a scheme's fields and virtual fields for some data
+#[derive(Debug)]
+pub struct SchemePreview {
+    /// Module declaring the scheme
+    pub module: String,
+    pub scheme: String,
+    /// The data's value of each field, or why it has none
+    pub fields: IndexMap<String, Result<ConstValue, ConstEvalError>>,
+    /// Value of each virtual field, in declaration order
+    pub virtuals: IndexMap<String, VirtualValue>,
+    /// Members of the data that are not fields of the scheme
+    pub unknown: Vec<String>,
+}
+
+/// Declared type and value of a virtual field
+#[derive(Debug)]
+pub struct VirtualValue {
+    /// The type as written
+    pub type_name: String,
+    /// The value, or why it cannot be computed
+    pub value: Result<ConstValue, ConstEvalError>,
+}
+
+/// Reasons a scheme cannot be previewed
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreviewError {
+    /// No scheme of this name in the project
+    UnknownScheme(String),
+    /// Schemes of this name are declared in several modules
+    AmbiguousScheme { name: String, modules: Vec<String> },
+    /// The data is not a JSON object
+    NotAnObject(&'static str),
+}
+
+impl fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewError::UnknownScheme(name) => write!(f, "unknown scheme '{}'", name),
+            PreviewError::AmbiguousScheme { name, modules } => write!(
+                f,
+                "scheme '{}' is declared in several modules ({}); qualify it with its module",
+                name,
+                modules.join(", ")
+            ),
+            PreviewError::NotAnObject(kind) => {
+                write!(f, "the data must be a JSON object, found {}", kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {}
+
+/// Evaluate the virtual fields of `scheme` for `data`
+///
+/// The scheme is named as declared, or qualified by its module path; an
+/// unqualified name is looked up in `module` if given, else in the whole
+/// project.
+pub fn preview_scheme(
+    files: &[ast::File],
+    module: Option<&str>,
+    scheme: &str,
+    data: &Json,
+) -> Result<SchemePreview, PreviewError> {
+    let (module, name) = match scheme.rsplit_once('.') {
+        Some((module, name)) => (Some(module), name),
+        None => (module, scheme),
+    };
+    let mut modules: Vec<&str> = files
+        .iter()
+        .filter(|file| module.is_none_or(|module| file.module == module))
+        .filter(|file| find_scheme(file, name).is_some())
+        .map(|file| file.module.as_str())
+        .collect();
+    modules.sort();
+    modules.dedup();
+    let module = match modules.as_slice() {
+        [] => return Err(PreviewError::UnknownScheme(scheme.to_string())),
+        [module] => *module,
+        _ => {
+            return Err(PreviewError::AmbiguousScheme {
+                name: name.to_string(),
+                modules: modules.iter().map(|m| m.to_string()).collect(),
+            })
+        }
+    };
+    let Json::Object(members) = data else {
+        return Err(PreviewError::NotAnObject(json_kind(data)));
+    };
+
+    let project = Project { files };
+    let decl = project.scheme(module, name).expect("scheme found above");
+    let mut env = SchemeEnv::new(&project, module, decl, members);
+    let virtuals = env.virtuals();
+    Ok(SchemePreview {
+        module: module.to_string(),
+        scheme: name.to_string(),
+        fields: env.fields,
+        virtuals,
+        unknown: members
+            .keys()
+            .filter(|key| {
+                !decl
+                    .members
+                    .iter()
+                    .any(|member| member_name(member) == *key)
+            })
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Declarations of the project, looked up by name
+struct Project<'a> {
+    files: &'a [ast::File],
+}
+
+impl<'a> Project<'a> {
+    /// A scheme declared in `module`, else the only one of this name elsewhere
+    fn scheme(&self, module: &str, name: &str) -> Option<&'a ast::Scheme> {
+        self.files
+            .iter()
+            .filter(|file| file.module == module)
+            .find_map(|file| find_scheme(file, name))
+            .or_else(|| self.unique(|file| find_scheme(file, name)))
+    }
+
+    /// An enum declared in `module`, else the only one of this name elsewhere
+    fn enumeration(&self, module: &str, name: &str) -> Option<&'a ast::Enum> {
+        let find = |file: &'a ast::File| {
+            file.declarations.iter().find_map(|decl| match decl {
+                TopLevelDecl::Enum(enumeration) if enumeration.name == name => Some(enumeration),
+                _ => None,
+            })
+        };
+        self.files
+            .iter()
+            .filter(|file| file.module == module)
+            .find_map(find)
+            .or_else(|| self.unique(find))
+    }
+
+    fn unique<T>(&self, find: impl Fn(&'a ast::File) -> Option<T>) -> Option<T> {
+        let mut found = self.files.iter().filter_map(find);
+        match (found.next(), found.next()) {
+            (Some(decl), None) => Some(decl),
+            _ => None,
+        }
+    }
+}
+
+fn find_scheme<'a>(file: &'a ast::File, name: &str) -> Option<&'a ast::Scheme> {
+    file.declarations.iter().find_map(|decl| match decl {
+        TopLevelDecl::Scheme(scheme) if scheme.name == name => Some(scheme),
+        _ => None,
+    })
+}
+
+fn member_name(member: &SchemeMember) -> &str {
+    match member {
+        SchemeMember::Field(field) => &field.name,
+        SchemeMember::Virtual(field) => &field.name,
+    }
+}
+
+/// Names of a virtual field's expression: the scheme's fields and virtual
+/// fields, and enum variants
+struct SchemeEnv<'a> {
+    project: &'a Project<'a>,
+    module: &'a str,
+    scheme: &'a ast::Scheme,
+    fields: IndexMap<String, Result<ConstValue, ConstEvalError>>,
+    /// Virtual fields evaluated so far
+    computed: IndexMap<String, Result<ConstValue, ConstEvalError>>,
+    /// Virtual fields being evaluated, to detect cycles
+    in_progress: HashSet<String>,
+}
+
+impl<'a> SchemeEnv<'a> {
+    fn new(
+        project: &'a Project<'a>,
+        module: &'a str,
+        scheme: &'a ast::Scheme,
+        data: &serde_json::Map<String, Json>,
+    ) -> Self {
+        let mut fields = IndexMap::new();
+        for member in &scheme.members {
+            if let SchemeMember::Field(field) = member {
+                let value = match data.get(&field.name) {
+                    Some(json) => convert(project, module, &field.type_expr, json).map_err(
+                        |error| match error {
+                            ConstEvalError::TypeMismatch(message) => ConstEvalError::TypeMismatch(
+                                format!("'{}': {}", field.name, message),
+                            ),
+                            error => error,
+                        },
+                    ),
+                    None if matches!(field.type_expr, TypeExpr::Nullable(_)) => {
+                        Ok(ConstValue::Null)
+                    }
+                    None => Err(ConstEvalError::NoValue(field.name.clone())),
+                };
+                fields.insert(field.name.clone(), value);
+            }
+        }
+        Self {
+            project,
+            module,
+            scheme,
+            fields,
+            computed: IndexMap::new(),
+            in_progress: HashSet::new(),
+        }
+    }
+
+    /// Evaluate every virtual field
+    fn virtuals(&mut self) -> IndexMap<String, VirtualValue> {
+        let scheme = self.scheme;
+        scheme
+            .members
+            .iter()
+            .filter_map(|member| match member {
+                SchemeMember::Virtual(field) => Some(field),
+                SchemeMember::Field(_) => None,
+            })
+            .map(|field| {
+                let value = VirtualValue {
+                    type_name: field.type_expr.to_string(),
+                    value: self.virtual_value(field),
+                };
+                (field.name.clone(), value)
+            })
+            .collect()
+    }
+
+    fn virtual_value(&mut self, field: &ast::VirtualField) -> Result<ConstValue, ConstEvalError> {
+        if let Some(value) = self.computed.get(&field.name) {
+            return value.clone();
+        }
+        if !self.in_progress.insert(field.name.clone()) {
+            return Err(ConstEvalError::Cycle(field.name.clone()));
+        }
+        let value = eval_const(&field.expr, self);
+        self.in_progress.remove(&field.name);
+        self.computed.insert(field.name.clone(), value.clone());
+        value
+    }
+
+    /// The object of the scheme: its fields with a value and its virtual
+    /// fields that could be computed
+    fn into_object(mut self) -> ConstValue {
+        let virtuals = self.virtuals();
+        let fields = self.fields.into_iter();
+        let virtuals = virtuals
+            .into_iter()
+            .map(|(name, field)| (name, field.value));
+        ConstValue::Object(
+            fields
+                .chain(virtuals)
+                .filter_map(|(name, value)| Some((name, value.ok()?)))
+                .collect(),
+        )
+    }
+}
+
+impl ConstEnv for SchemeEnv<'_> {
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+        let mut path = name.split('.');
+        let first = path.next().unwrap_or_default();
+        let scheme = self.scheme;
+        let mut value = if let Some(value) = self.fields.get(first) {
+            value.clone()?
+        } else if let Some(field) = scheme.members.iter().find_map(|member| match member {
+            SchemeMember::Virtual(field) if field.name == first => Some(field),
+            _ => None,
+        }) {
+            self.virtual_value(field)?
+        } else {
+            // `Status.Active` is the variant's name
+            let variant = path.next();
+            let enumeration = self.project.enumeration(self.module, first);
+            return match (enumeration, variant) {
+                (Some(enumeration), Some(variant))
+                    if enumeration.variants.iter().any(|v| v == variant) =>
+                {
+                    Ok(ConstValue::String(variant.to_string()))
+                }
+                _ => Err(ConstEvalError::UnknownName(name.to_string())),
+            };
+        };
+        for field in path {
+            value = match value {
+                ConstValue::Object(mut fields) => fields
+                    .shift_remove(field)
+                    .ok_or_else(|| ConstEvalError::UnknownName(name.to_string()))?,
+                ConstValue::Null => return Ok(ConstValue::Null),
+                other => {
+                    return Err(ConstEvalError::TypeMismatch(format!(
+                        "cannot access field '{}' on {}",
+                        field,
+                        other.kind()
+                    )))
+                }
+            };
+        }
+        Ok(value)
+    }
+}
+
+/// The value of `json` for a field of type `ty`
+fn convert(
+    project: &Project,
+    module: &str,
+    ty: &TypeExpr,
+    json: &Json,
+) -> Result<ConstValue, ConstEvalError> {
+    let mismatch = |expected: &str| {
+        Err(ConstEvalError::TypeMismatch(format!(
+            "expected {}, found {}",
+            expected,
+            json_kind(json)
+        )))
+    };
+    match (ty, json) {
+        (TypeExpr::Nullable(_), Json::Null) => Ok(ConstValue::Null),
+        (TypeExpr::Nullable(inner) | TypeExpr::Ref(inner) | TypeExpr::Draft(inner), _) => {
+            convert(project, module, inner, json)
+        }
+        (TypeExpr::List(elem) | TypeExpr::Set(elem), Json::Array(items)) => items
+            .iter()
+            .map(|item| convert(project, module, elem, item))
+            .collect::<Result<_, _>>()
+            .map(ConstValue::List),
+        (TypeExpr::List(_) | TypeExpr::Set(_), _) => mismatch("an array"),
+        (TypeExpr::Map(_, value), Json::Object(entries)) => entries
+            .iter()
+            .map(|(key, item)| Ok((key.clone(), convert(project, module, value, item)?)))
+            .collect::<Result<_, _>>()
+            .map(ConstValue::Object),
+        (TypeExpr::Map(..), _) => mismatch("an object"),
+        (TypeExpr::Named(name), _) => convert_named(project, module, name, json),
+        _ => Ok(from_json(json)),
+    }
+}
+
+fn convert_named(
+    project: &Project,
+    module: &str,
+    name: &str,
+    json: &Json,
+) -> Result<ConstValue, ConstEvalError> {
+    let mismatch = |expected: &str| {
+        Err(ConstEvalError::TypeMismatch(format!(
+            "expected {}, found {}",
+            expected,
+            json_kind(json)
+        )))
+    };
+    match (name, json) {
+        ("bool", Json::Bool(b)) => Ok(ConstValue::Bool(*b)),
+        ("bool", _) => mismatch("a bool"),
+        ("i8" | "i16" | "i32" | "i64" | "Duration", Json::Number(n)) if n.is_i64() => {
+            Ok(ConstValue::Int(n.as_i64().unwrap_or_default()))
+        }
+        ("u8" | "u16" | "u32" | "u64", Json::Number(n)) if n.is_u64() => n
+            .as_u64()
+            .and_then(|n| i64::try_from(n).ok())
+            .map(ConstValue::Int)
+            .ok_or(ConstEvalError::Overflow),
+        ("i8" | "i16" | "i32" | "i64" | "Duration", _) => mismatch("an integer"),
+        ("u8" | "u16" | "u32" | "u64", _) => mismatch("a non-negative integer"),
+        ("f32" | "f64" | "Decimal", Json::Number(n)) => {
+            Ok(ConstValue::Float(n.as_f64().unwrap_or_default()))
+        }
+        ("f32" | "f64" | "Decimal", _) => mismatch("a number"),
+        ("Instant", Json::Number(n)) if n.is_i64() => {
+            Ok(ConstValue::Instant(n.as_i64().unwrap_or_default()))
+        }
+        ("Instant", Json::String(text)) => super::builtins::parse_instant(text)
+            .map(ConstValue::Instant)
+            .ok_or_else(|| {
+                ConstEvalError::TypeMismatch(format!("'{}' is not an ISO 8601 instant", text))
+            }),
+        ("Instant", _) => mismatch("an ISO 8601 string or milliseconds"),
+        ("Color", Json::String(text)) => {
+            parse_color(text).map(ConstValue::Color).ok_or_else(|| {
+                ConstEvalError::TypeMismatch(format!(
+                    "'{}' is not a #RRGGBB or #RRGGBBAA color",
+                    text
+                ))
+            })
+        }
+        ("Color", _) => mismatch("a color string"),
+        (
+            "String" | "Secret" | "Uuid" | "Url" | "LocalDate" | "LocalTime" | "LocalDateTime"
+            | "Timezone",
+            Json::String(text),
+        ) => Ok(ConstValue::String(text.clone())),
+        (
+            "String" | "Secret" | "Uuid" | "Url" | "LocalDate" | "LocalTime" | "LocalDateTime"
+            | "Timezone",
+            _,
+        ) => mismatch("a string"),
+        _ => {
+            if let Some(scheme) = project.scheme(module, name) {
+                let Json::Object(members) = json else {
+                    return mismatch("an object");
+                };
+                return Ok(SchemeEnv::new(project, module, scheme, members).into_object());
+            }
+            if let Some(enumeration) = project.enumeration(module, name) {
+                return match json {
+                    Json::String(variant) if enumeration.variants.contains(variant) => {
+                        Ok(ConstValue::String(variant.clone()))
+                    }
+                    _ => Err(ConstEvalError::TypeMismatch(format!(
+                        "expected a variant of {} ({}), found {}",
+                        name,
+                        enumeration.variants.join(", "),
+                        json
+                    ))),
+                };
+            }
+            Ok(from_json(json))
+        }
+    }
+}
+
+/// A JSON value as is, for types without a conversion
+fn from_json(json: &Json) -> ConstValue {
+    match json {
+        Json::Null => ConstValue::Null,
+        Json::Bool(b) => ConstValue::Bool(*b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => ConstValue::Int(i),
+            None => ConstValue::Float(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(text) => ConstValue::String(text.clone()),
+        Json::Array(items) => ConstValue::List(items.iter().map(from_json).collect()),
+        Json::Object(members) => ConstValue::Object(
+            members
+                .iter()
+                .map(|(key, value)| (key.clone(), from_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// `#RRGGBB` or `#RRGGBBAA` as RGBA
+fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.strip_prefix('#')?;
+    let rgba = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some((rgba << 8) | 0xFF),
+        8 => Some(rgba),
+        _ => None,
+    }
+}
+
+fn json_kind(json: &Json) -> &'static str {
+    match json {
+        Json::Null => "null",
+        Json::Bool(_) => "a bool",
+        Json::Number(_) => "a number",
+        Json::String(_) => "a string",
+        Json::Array(_) => "an array",
+        Json::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const PEOPLE: &str = r#"module app.people
+
+enum Status { Active Retired }
+
+scheme Address {
+    city : String
+    country : String
+    virtual label : String = "${city}, ${country}"
+}
+
+scheme Person {
+    first : String
+    last : String
+    born : Instant
+    status : Status
+    address : Address?
+    nickname : String?
+    virtual full : String = "${first} ${last}"
+    virtual greeting : String = "Hello ${nickname ?: full}"
+    virtual active : bool = status == Status.Active
+    virtual city : String = address?.label ?: "nowhere"
+}
+"#;
+
+    fn preview(data: &str) -> SchemePreview {
+        let files = vec![parser::parse(PEOPLE).file.unwrap()];
+        let data: Json = serde_json::from_str(data).unwrap();
+        preview_scheme(&files, None, "Person", &data).unwrap()
+    }
+
+    fn value(preview: &SchemePreview, name: &str) -> Result<ConstValue, ConstEvalError> {
+        preview.virtuals[name].value.clone()
+    }
+
+    #[test]
+    fn test_virtual_fields() {
+        let result = preview(
+            r#"{
+                "first": "Ada",
+                "last": "Lovelace",
+                "born": "1815-12-10",
+                "status": "Retired",
+                "address": { "city": "London", "country": "UK" },
+                "title": "Countess"
+            }"#,
+        );
+        assert_eq!(result.module, "app.people");
+        let string = |s: &str| Ok(ConstValue::String(s.to_string()));
+        assert_eq!(value(&result, "full"), string("Ada Lovelace"));
+        assert_eq!(value(&result, "greeting"), string("Hello Ada Lovelace"));
+        assert_eq!(value(&result, "active"), Ok(ConstValue::Bool(false)));
+        assert_eq!(value(&result, "city"), string("London, UK"));
+        assert_eq!(result.virtuals["full"].type_name, "String");
+        assert_eq!(result.fields["nickname"], Ok(ConstValue::Null));
+        assert_eq!(result.unknown, vec!["title".to_string()]);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let result = preview(r#"{ "first": "Ada", "born": 12, "status": "Gone" }"#);
+        assert_eq!(
+            value(&result, "full"),
+            Err(ConstEvalError::NoValue("last".to_string()))
+        );
+        assert_eq!(result.fields["born"], Ok(ConstValue::Instant(12)));
+        assert_eq!(
+            value(&result, "active"),
+            Err(ConstEvalError::TypeMismatch(
+                "'status': expected a variant of Status (Active, Retired), found \"Gone\""
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            value(&result, "city"),
+            Ok(ConstValue::String("nowhere".to_string()))
+        );
+
+        let files = vec![parser::parse(PEOPLE).file.unwrap()];
+        let data = serde_json::json!([1, 2]);
+        assert_eq!(
+            preview_scheme(&files, None, "Person", &data).unwrap_err(),
+            PreviewError::NotAnObject("an array")
+        );
+        assert_eq!(
+            preview_scheme(&files, None, "app.other.Person", &serde_json::json!({})).unwrap_err(),
+            PreviewError::UnknownScheme("app.other.Person".to_string())
+        );
+    }
+}
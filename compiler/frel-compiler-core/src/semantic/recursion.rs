@@ -0,0 +1,309 @@
+// Recursive scheme checks for Frel
+//
+// A scheme holds the values of its fields, so a scheme containing itself by
+// value, directly or through the schemes of its fields, would be infinite:
+//
+//     scheme Person { name: String  home: Address }
+//     scheme Address { city: String  owner: Person }   // Person -> Address -> Person
+//
+// Recursion is fine through an indirection: a nullable field, a `ref`, or a
+// list, set, map or tree, which can be empty. Each strongly connected
+// component of the graph of by-value fields between the module's schemes is
+// reported once (E0412), with one cycle through it.
+//
+// Only schemes declared in the module are followed; imported ones cannot
+// refer back to the module's schemes by value.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::ast::{self, SchemeMember, TopLevelDecl, TypeExpr};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+
+/// Report schemes containing themselves by value, in a module's files
+pub fn check_recursive_schemes(files: &[ast::File]) -> Diagnostics {
+    let _profile = crate::profile::enter("recursion");
+    let mut diagnostics = Diagnostics::new();
+
+    let schemes: Vec<(&ast::File, &ast::Scheme)> = files
+        .iter()
+        .flat_map(|file| file.declarations.iter().map(move |decl| (file, decl)))
+        .filter_map(|(file, decl)| match decl {
+            TopLevelDecl::Scheme(scheme) => Some((file, scheme)),
+            _ => None,
+        })
+        .collect();
+    let index: HashMap<&str, usize> = schemes
+        .iter()
+        .enumerate()
+        .map(|(i, (_, scheme))| (scheme.name.as_str(), i))
+        .collect();
+
+    // The fields holding another scheme of the module by value
+    let edges: Vec<Vec<Edge>> = schemes
+        .iter()
+        .enumerate()
+        .map(|(from, (_, scheme))| {
+            scheme
+                .members
+                .iter()
+                .filter_map(|member| match member {
+                    SchemeMember::Field(field) => {
+                        let to = *index.get(held_by_value(&field.type_expr)?)?;
+                        Some(Edge { from, to, field })
+                    }
+                    SchemeMember::Virtual(_) => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    let successors: Vec<Vec<usize>> = edges
+        .iter()
+        .map(|edges| edges.iter().map(|edge| edge.to).collect())
+        .collect();
+    let mut components = components(&successors);
+    components.iter_mut().for_each(|component| component.sort());
+    components.sort();
+
+    for component in components {
+        let start = component[0];
+        let recursive = component.len() > 1 || successors[start].contains(&start);
+        if !recursive {
+            continue;
+        }
+        let cycle = cycle(start, &component, &edges);
+        diagnostics.add(report(&schemes, &cycle));
+    }
+    diagnostics
+}
+
+/// A field of a scheme holding another scheme by value
+struct Edge<'a> {
+    from: usize,
+    to: usize,
+    field: &'a ast::SchemeField,
+}
+
+/// The scheme a field of this type holds by value, if any
+fn held_by_value(ty: &TypeExpr) -> Option<&str> {
+    match ty {
+        TypeExpr::Named(name) => Some(name),
+        TypeExpr::Draft(inner) | TypeExpr::Asset(inner) => held_by_value(inner),
+        // Nullable, ref and collection fields can end the recursion
+        _ => None,
+    }
+}
+
+/// Strongly connected components of a graph given as each node's
+/// successors (Tarjan's algorithm)
+fn components(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        successors: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.next);
+            self.low[node] = self.next;
+            self.next += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            for &next in &self.successors[node] {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low[node] = self.low[node].min(self.low[next]);
+                    }
+                    Some(index) if self.on_stack[next] => {
+                        self.low[node] = self.low[node].min(index);
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.low[node]) == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let count = successors.len();
+    let mut tarjan = Tarjan {
+        successors,
+        index: vec![None; count],
+        low: vec![0; count],
+        on_stack: vec![false; count],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for node in 0..count {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.components
+}
+
+/// A shortest cycle from `start` back to it inside its component, as the
+/// fields followed
+fn cycle<'e, 'a>(
+    start: usize,
+    component: &[usize],
+    edges: &'e [Vec<Edge<'a>>],
+) -> Vec<&'e Edge<'a>> {
+    let mut reached: HashMap<usize, &Edge> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for edge in &edges[node] {
+            if edge.to == start {
+                let mut cycle = vec![edge];
+                while cycle[cycle.len() - 1].from != start {
+                    cycle.push(reached[&cycle[cycle.len() - 1].from]);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if component.contains(&edge.to) && !reached.contains_key(&edge.to) {
+                reached.insert(edge.to, edge);
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    Vec::new()
+}
+
+fn report(schemes: &[(&ast::File, &ast::Scheme)], cycle: &[&Edge]) -> Diagnostic {
+    let (file, first) = schemes[cycle[0].from];
+    let closing = cycle[cycle.len() - 1];
+
+    let message = match cycle {
+        [edge] => format!(
+            "scheme `{}` contains itself by value through `{}`",
+            first.name, edge.field.name
+        ),
+        _ => {
+            let path: Vec<String> = std::iter::once(cycle[0].from)
+                .chain(cycle.iter().map(|edge| edge.to))
+                .map(|i| format!("`{}`", schemes[i].1.name))
+                .collect();
+            format!(
+                "scheme `{}` contains itself by value: {}",
+                first.name,
+                path.join(" -> ")
+            )
+        }
+    };
+    let ty = &closing.field.type_expr;
+    let mut diagnostic =
+        Diagnostic::from_code(&codes::E0412, first.span, message).with_help(format!(
+            "make `{}.{}` nullable (`{}?`) or a reference (`ref {}`), or hold it in a list",
+            schemes[closing.from].1.name, closing.field.name, ty, ty
+        ));
+
+    for edge in cycle {
+        let (from_file, from) = schemes[edge.from];
+        let message = format!(
+            "`{}.{}` holds a `{}`",
+            from.name, edge.field.name, schemes[edge.to].1.name
+        );
+        let related = match &from_file.source_path {
+            path if *path == file.source_path => Some(RelatedInfo::new(edge.field.span, message)),
+            Some(path) => Some(RelatedInfo::in_file(edge.field.span, path, message)),
+            None => None,
+        };
+        if let Some(related) = related {
+            diagnostic = diagnostic.with_related(related);
+        }
+    }
+    diagnostic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<(String, String)> {
+        let file = crate::parse_file(source).file.expect("parse failed");
+        check_recursive_schemes(std::slice::from_ref(&file))
+            .iter()
+            .map(|d| (d.message.clone(), d.help.clone().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_recursive_schemes() {
+        let source = r#"
+module test
+
+scheme Node {
+    value : i32
+    next : Node
+}
+
+scheme Person {
+    name : String
+    home : Address
+}
+
+scheme Address {
+    city : String
+    owner : Person
+}
+"#;
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    "scheme `Node` contains itself by value through `next`".to_string(),
+                    "make `Node.next` nullable (`Node?`) or a reference (`ref Node`), or hold it in a list"
+                        .to_string()
+                ),
+                (
+                    "scheme `Person` contains itself by value: `Person` -> `Address` -> `Person`"
+                        .to_string(),
+                    "make `Address.owner` nullable (`Person?`) or a reference (`ref Person`), or hold it in a list"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indirect_recursion() {
+        let source = r#"
+module test
+
+scheme Category {
+    name : String
+    parent : Category?
+    children : List<Category>
+    related : Map<String, Category>
+    owner : ref Employee
+}
+
+scheme Employee {
+    name : String
+    manager : ref Employee
+    department : Category
+    reports : Tree<Employee>
+}
+"#;
+        assert!(messages(source).is_empty());
+    }
+}
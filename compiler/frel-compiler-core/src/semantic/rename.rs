@@ -0,0 +1,283 @@
+// Rename refactoring for Frel
+//
+// Expressions carry no spans of their own, so references are found from the
+// tokens of the source: every identifier with the symbol's name is looked up
+// from the innermost scope containing it, and kept if it resolves to the
+// symbol being renamed. Frel has no shadowing, so the lookup is unambiguous.
+//
+// Members imported into a blueprint scope by `with` are copies of the backend
+// members sharing their definition span; they are renamed together.
+//
+// Names after `.` are member accesses resolved during type checking and are
+// not renamed.
+
+use crate::lexer::{Lexer, TokenKind};
+use crate::source::Span;
+
+use super::resolve::ResolveResult;
+use super::scope::ScopeId;
+use super::symbol::{Symbol, SymbolId};
+
+/// The symbol named by the identifier token containing `offset`
+pub fn symbol_at(source: &str, result: &ResolveResult, offset: u32) -> Option<SymbolId> {
+    let (tokens, _) = Lexer::new(source).tokenize();
+    let token = tokens.iter().find(|token| {
+        token.kind == TokenKind::Identifier
+            && token.span.start <= offset
+            && offset <= token.span.end
+    })?;
+    lookup(
+        result,
+        token.span,
+        &source[token.span.start as usize..token.span.end as usize],
+    )
+}
+
+/// Find a declaration by name, optionally qualified by its scope: `count` or `Counter.count`
+pub fn find_symbol(result: &ResolveResult, name: &str) -> Result<SymbolId, String> {
+    let (qualifier, name) = match name.rsplit_once('.') {
+        Some((qualifier, name)) => (Some(qualifier), name),
+        None => (None, name),
+    };
+
+    let mut found: Vec<&Symbol> = Vec::new();
+    for symbol in result.symbols.iter() {
+        if symbol.name != name || found.iter().any(|s| same_symbol(s, symbol)) {
+            continue;
+        }
+        let scope_name = result
+            .scopes
+            .get(symbol.scope)
+            .and_then(|s| s.name.as_deref());
+        if qualifier.is_some_and(|q| scope_name != Some(q)) {
+            continue;
+        }
+        found.push(symbol);
+    }
+
+    match found.as_slice() {
+        [] => Err(format!("cannot find `{}` in this file", name)),
+        [symbol] => Ok(symbol.id),
+        _ => {
+            let scopes: Vec<String> = found
+                .iter()
+                .map(
+                    |s| match result.scopes.get(s.scope).and_then(|s| s.name.as_deref()) {
+                        Some(scope) => format!("`{}`", scope),
+                        None => "the module".to_string(),
+                    },
+                )
+                .collect();
+            Err(format!(
+                "`{}` is declared in {}; qualify it as `Scope.{}`",
+                name,
+                scopes.join(", "),
+                name
+            ))
+        }
+    }
+}
+
+/// Spans of the declaration and every reference of a symbol, in source order
+pub fn references(source: &str, result: &ResolveResult, target: SymbolId) -> Vec<Span> {
+    let Some(target) = result.symbols.get(target) else {
+        return Vec::new();
+    };
+    let (tokens, _) = Lexer::new(source).tokenize();
+
+    let mut spans = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Identifier
+            || source[token.span.start as usize..token.span.end as usize] != target.name
+        {
+            continue;
+        }
+        let member_access =
+            i > 0 && matches!(tokens[i - 1].kind, TokenKind::Dot | TokenKind::QuestionDot);
+        if member_access {
+            continue;
+        }
+        let resolved =
+            lookup(result, token.span, &target.name).and_then(|id| result.symbols.get(id));
+        if resolved.is_some_and(|symbol| same_symbol(symbol, target)) {
+            spans.push(token.span);
+        }
+    }
+    spans
+}
+
+/// Spans to replace to rename a symbol, after checking the new name
+pub fn rename(
+    source: &str,
+    result: &ResolveResult,
+    target: SymbolId,
+    new_name: &str,
+) -> Result<Vec<Span>, String> {
+    let symbol = result
+        .symbols
+        .get(target)
+        .ok_or_else(|| "unknown symbol".to_string())?;
+
+    if let Some(module) = &symbol.source_module {
+        return Err(format!(
+            "`{}` is declared in module `{}`, rename it there",
+            symbol.name, module
+        ));
+    }
+    if !is_identifier(new_name) {
+        return Err(format!("`{}` is not a valid name", new_name));
+    }
+    if TokenKind::keyword_from_str(new_name).is_some() {
+        return Err(format!("`{}` is a keyword", new_name));
+    }
+
+    // No shadowing: the new name must be free wherever the symbol is visible
+    for copy in result.symbols.iter().filter(|s| same_symbol(s, symbol)) {
+        let existing = result
+            .symbols
+            .lookup_in_scope_chain(copy.scope, new_name, &result.scopes)
+            .or_else(|| {
+                result
+                    .symbols
+                    .lookup_in_children(copy.scope, new_name, &result.scopes)
+                    .map(|(id, _)| id)
+            });
+        if existing.is_some() {
+            return Err(format!("`{}` is already defined in this scope", new_name));
+        }
+    }
+
+    Ok(references(source, result, target))
+}
+
+/// Replace the given spans of `source` with `new_name`
+pub fn apply_rename(source: &str, spans: &[Span], new_name: &str) -> String {
+    let mut output = source.to_string();
+    let mut spans = spans.to_vec();
+    spans.sort_by_key(|span| std::cmp::Reverse(span.start));
+    for span in spans {
+        output.replace_range(span.start as usize..span.end as usize, new_name);
+    }
+    output
+}
+
+/// Resolve `name` as seen from the innermost scope containing `span`
+fn lookup(result: &ResolveResult, span: Span, name: &str) -> Option<SymbolId> {
+    let scope = result
+        .scopes
+        .iter()
+        .filter(|scope| {
+            !scope.span.is_empty() && scope.span.start <= span.start && span.end <= scope.span.end
+        })
+        .min_by_key(|scope| (scope.span.len(), std::cmp::Reverse(scope.id.0)))
+        .map_or(ScopeId::ROOT, |scope| scope.id);
+
+    result
+        .symbols
+        .lookup_in_scope_chain(scope, name, &result.scopes)
+        .or_else(|| {
+            result
+                .symbols
+                .lookup_in_children(scope, name, &result.scopes)
+                .map(|(id, _)| id)
+        })
+}
+
+/// Whether two symbols are the same declaration, counting `with` copies
+fn same_symbol(a: &Symbol, b: &Symbol) -> bool {
+    if a.id == b.id {
+        return true;
+    }
+    if a.name != b.name || a.kind != b.kind {
+        return false;
+    }
+    match (a.resolved_import, b.resolved_import) {
+        (Some(x), Some(y)) => x == y && a.source_module == b.source_module,
+        (None, None) => !a.def_span.is_empty() && a.def_span == b.def_span,
+        _ => false,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"module test
+
+backend Counter {
+    count : i32 = 0
+    command reset()
+}
+
+blueprint Main {
+    with Counter
+    total : i32 = count * 2
+    text { "${count}" } .. on_click { count = count + 1 }
+}
+"#;
+
+    fn resolve(source: &str) -> ResolveResult {
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        crate::semantic::resolve(&result.file.unwrap())
+    }
+
+    #[test]
+    fn test_rename_backend_field() {
+        let result = resolve(SOURCE);
+        let count = find_symbol(&result, "count").unwrap();
+        let spans = rename(SOURCE, &result, count, "clicks").unwrap();
+        // Declaration, initializer, template and both sides of the assignment
+        assert_eq!(spans.len(), 5);
+
+        let renamed = apply_rename(SOURCE, &spans, "clicks");
+        assert!(renamed.contains("    clicks : i32 = 0"), "{}", renamed);
+        assert!(
+            renamed.contains("on_click { clicks = clicks + 1 }"),
+            "{}",
+            renamed
+        );
+        assert!(!renamed.contains("count"), "{}", renamed);
+        assert_eq!(
+            resolve(&renamed).diagnostics.error_count(),
+            result.diagnostics.error_count()
+        );
+    }
+
+    #[test]
+    fn test_rename_at_offset() {
+        let result = resolve(SOURCE);
+        let offset = SOURCE.find("with Counter").unwrap() as u32 + 6;
+        let counter = symbol_at(SOURCE, &result, offset).unwrap();
+        assert_eq!(result.symbols.get(counter).unwrap().name, "Counter");
+
+        let spans = rename(SOURCE, &result, counter, "Clicks").unwrap();
+        assert_eq!(spans.len(), 2);
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        let result = resolve(SOURCE);
+        let count = find_symbol(&result, "Counter.count").unwrap();
+        assert_eq!(
+            rename(SOURCE, &result, count, "total").unwrap_err(),
+            "`total` is already defined in this scope"
+        );
+        assert!(rename(SOURCE, &result, count, "blueprint").is_ok());
+        assert_eq!(
+            rename(SOURCE, &result, count, "when").unwrap_err(),
+            "`when` is a keyword"
+        );
+        assert_eq!(
+            rename(SOURCE, &result, count, "1x").unwrap_err(),
+            "`1x` is not a valid name"
+        );
+        assert!(find_symbol(&result, "missing").is_err());
+    }
+}
@@ -7,12 +7,19 @@
 // 4. Reports resolution errors (undefined, duplicate, shadowing)
 
 use crate::ast::{self, TopLevelDecl};
-use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+use crate::diagnostic::{codes, similar_name, Diagnostic, Diagnostics, RelatedInfo, Suggestion};
 use crate::source::Span;
 
+use super::builtins;
 use super::scope::{ScopeGraph, ScopeId, ScopeKind};
 use super::symbol::{SymbolId, SymbolKind, SymbolTable};
 
+/// Commands generated for backends with undoable fields
+pub const UNDO_COMMANDS: [&str; 2] = ["undo", "redo"];
+
+/// Blueprints provided by the host platform rather than declared in Frel
+pub const STANDARD_BLUEPRINTS: [&str; 6] = ["text", "image", "icon", "box", "column", "row"];
+
 /// Result of name resolution
 #[derive(Debug)]
 pub struct ResolveResult {
@@ -24,6 +31,10 @@ pub struct ResolveResult {
     pub diagnostics: Diagnostics,
     /// Map from name references to resolved symbols
     pub resolutions: std::collections::HashMap<Span, SymbolId>,
+    /// Every resolved reference, with the scope it occurs in; unlike
+    /// `resolutions`, this keeps references that share a span and those in
+    /// type expressions
+    pub references: Vec<(SymbolId, ScopeId)>,
     /// Imported names (name -> module path)
     pub imports: std::collections::HashMap<String, String>,
 }
@@ -35,6 +46,7 @@ impl ResolveResult {
             symbols,
             diagnostics,
             resolutions: std::collections::HashMap::new(),
+            references: Vec::new(),
             imports: std::collections::HashMap::new(),
         }
     }
@@ -46,6 +58,7 @@ pub struct Resolver {
     symbols: SymbolTable,
     diagnostics: Diagnostics,
     resolutions: std::collections::HashMap<Span, SymbolId>,
+    references: Vec<(SymbolId, ScopeId)>,
     /// Current scope being processed
     current_scope: ScopeId,
     /// Context span for error reporting (set to containing declaration's span)
@@ -61,6 +74,7 @@ impl Resolver {
             symbols: SymbolTable::new(),
             diagnostics: Diagnostics::new(),
             resolutions: std::collections::HashMap::new(),
+            references: Vec::new(),
             current_scope: ScopeId::ROOT,
             context_span: Span::default(),
             imports: std::collections::HashMap::new(),
@@ -69,6 +83,7 @@ impl Resolver {
 
     /// Resolve names in a file AST
     pub fn resolve(mut self, file: &ast::File) -> ResolveResult {
+        let _profile = crate::profile::enter("resolve");
         // Create root/module scope
         self.current_scope = self.scopes.create_root(Span::default());
 
@@ -86,6 +101,7 @@ impl Resolver {
             symbols: self.symbols,
             diagnostics: self.diagnostics,
             resolutions: self.resolutions,
+            references: self.references,
             imports: self.imports,
         }
     }
@@ -244,6 +260,8 @@ impl Resolver {
 
     /// Resolve references within declarations
     fn resolve_declarations(&mut self, file: &ast::File) {
+        self.check_include_cycles(file);
+
         for decl in &file.declarations {
             match decl {
                 TopLevelDecl::Blueprint(bp) => self.resolve_blueprint(bp),
@@ -257,6 +275,70 @@ impl Resolver {
         }
     }
 
+    /// Report backends and themes including themselves, directly or through
+    /// the ones they include (E0602)
+    fn check_include_cycles(&mut self, file: &ast::File) {
+        let mut backends = Vec::new();
+        let mut themes = Vec::new();
+        for decl in &file.declarations {
+            match decl {
+                TopLevelDecl::Backend(be) => {
+                    let includes = be.members.iter().filter_map(|member| match member {
+                        ast::BackendMember::Include(name) => Some(name.as_str()),
+                        _ => None,
+                    });
+                    backends.push((be.name.as_str(), be.span, includes.collect()));
+                }
+                TopLevelDecl::Theme(th) => {
+                    let includes = th.members.iter().filter_map(|member| match member {
+                        ast::ThemeMember::Include(name) => Some(name.as_str()),
+                        _ => None,
+                    });
+                    themes.push((th.name.as_str(), th.span, includes.collect()));
+                }
+                _ => {}
+            }
+        }
+        self.report_include_cycles("backend", &backends);
+        self.report_include_cycles("theme", &themes);
+    }
+
+    fn report_include_cycles(&mut self, kind: &str, decls: &[(&str, Span, Vec<&str>)]) {
+        let includes: Vec<Vec<usize>> = decls
+            .iter()
+            .map(|(_, _, names)| {
+                names
+                    .iter()
+                    .filter_map(|name| decls.iter().position(|(n, _, _)| n == name))
+                    .collect()
+            })
+            .collect();
+
+        for cycle in include_cycles(&includes) {
+            let (name, span, _) = &decls[cycle[0]];
+            let message = if cycle.len() == 1 {
+                format!("{} `{}` includes itself", kind, name)
+            } else {
+                let path: Vec<String> = cycle
+                    .iter()
+                    .chain(&cycle[..1])
+                    .map(|&i| format!("`{}`", decls[i].0))
+                    .collect();
+                format!("{} `{}` includes itself: {}", kind, name, path.join(" -> "))
+            };
+            let mut diagnostic = Diagnostic::from_code(&codes::E0602, *span, message)
+                .with_help("remove one of the includes of the cycle");
+            for (position, &i) in cycle.iter().enumerate().skip(1) {
+                let next = cycle.get(position + 1).unwrap_or(&cycle[0]);
+                diagnostic = diagnostic.with_related(RelatedInfo::new(
+                    decls[i].1,
+                    format!("`{}` includes `{}`", decls[i].0, decls[*next].0),
+                ));
+            }
+            self.diagnostics.add(diagnostic);
+        }
+    }
+
     fn resolve_blueprint(&mut self, bp: &ast::Blueprint) {
         let module_scope = ScopeId::ROOT;
 
@@ -273,7 +355,8 @@ impl Resolver {
 
         // Define parameters in body scope
         for param in &bp.params {
-            self.define_simple(&param.name, SymbolKind::Parameter, body_scope, Span::default());
+            self.define_simple(&param.name, SymbolKind::Parameter, body_scope, param.span);
+            self.resolve_type_expr(&param.type_expr, body_scope);
         }
 
         // Resolve body statements
@@ -286,14 +369,19 @@ impl Resolver {
 
     fn resolve_blueprint_stmt(&mut self, stmt: &ast::BlueprintStmt, params: &[ast::Parameter]) {
         match stmt {
-            ast::BlueprintStmt::With(name) => {
+            ast::BlueprintStmt::With(with) => {
                 // Resolve backend reference and import its members into the blueprint scope
                 // Look up from current scope to find both module-level backends and parameters
+                let name = &with.backend;
                 if let Some(symbol_id) = self.symbols.lookup_in_scope_chain(self.current_scope, name, &self.scopes) {
+                    self.record_reference(symbol_id, with.span);
                     if let Some(symbol) = self.symbols.get(symbol_id) {
                         // Get the body scope - either directly from the symbol (for backends)
                         // or by looking up the parameter's type (for parameters)
-                        let body_scope = if let Some(scope) = symbol.body_scope {
+                        let body_scope = if with.name.is_some() {
+                            // `with slot: Backend` names a backend, never a parameter
+                            symbol.body_scope.filter(|_| symbol.kind == SymbolKind::Backend)
+                        } else if let Some(scope) = symbol.body_scope {
                             Some(scope)
                         } else if symbol.kind == SymbolKind::Parameter {
                             // For parameters, look up the type from the AST and find its body scope
@@ -302,20 +390,33 @@ impl Resolver {
                             None
                         };
 
+                        if with.name.is_some() && body_scope.is_none() {
+                            self.diagnostics.add(Diagnostic::from_code(
+                                &codes::E0301,
+                                with.span,
+                                format!("`{}` is not a backend", name),
+                            ));
+                        }
+
                         if let Some(backend_body_scope) = body_scope {
                             // Collect members to import (avoid borrowing issues)
                             let members_to_import: Vec<_> = self.symbols
                                 .symbols_in_scope(backend_body_scope)
-                                .map(|s| (s.name.clone(), s.kind, s.def_span))
+                                .map(|s| (s.name.clone(), s.kind, s.def_span, s.source_module.clone(), s.resolved_import))
                                 .collect();
 
                             // Import each member into the current blueprint scope
                             // Skip members that match a parameter name (parameter takes precedence)
-                            for (member_name, member_kind, member_span) in members_to_import {
+                            for (member_name, member_kind, member_span, source_module, resolved_import) in members_to_import {
                                 // Check if a parameter with this name already exists
                                 let is_parameter = params.iter().any(|p| p.name == member_name);
                                 if !is_parameter {
-                                    self.define_simple(&member_name, member_kind, self.current_scope, member_span);
+                                    let id = self.define_simple(&member_name, member_kind, self.current_scope, member_span);
+                                    // Members of imported backends keep pointing at their definition
+                                    if let Some(member) = id.and_then(|id| self.symbols.get_mut(id)) {
+                                        member.source_module = source_module;
+                                        member.resolved_import = resolved_import;
+                                    }
                                 }
                                 // If it is a parameter, skip import - the parameter defines it.
                                 // Type compatibility is checked during the typecheck phase.
@@ -326,13 +427,20 @@ impl Resolver {
                     // Backend not found - report error
                     self.diagnostics.add(Diagnostic::from_code(
                         &codes::E0301,
-                        Span::default(),
+                        with.span,
                         format!("cannot find backend `{}` in this scope", name),
                     ));
                 }
+
+                // The slot is set by the parent like a parameter, and can be passed on
+                if let Some(slot) = &with.name {
+                    self.define_simple(slot, SymbolKind::Parameter, self.current_scope, with.span);
+                }
             }
             ast::BlueprintStmt::LocalDecl(decl) => {
                 // Resolve the initializer first (before adding to scope)
+                self.context_span = decl.span;
+                self.resolve_type_expr(&decl.type_expr, self.current_scope);
                 self.resolve_expr(&decl.init);
                 // Then define the local
                 self.define_simple(
@@ -343,9 +451,11 @@ impl Resolver {
                 );
             }
             ast::BlueprintStmt::FragmentCreation(frag) => {
+                // Names in the fragment are reported at it
+                let saved_span = std::mem::replace(&mut self.context_span, frag.span);
                 // Resolve the fragment blueprint name (skip for anonymous blocks which have empty names)
-                if !frag.name.is_empty() {
-                    self.resolve_name(&frag.name, Span::default());
+                if !frag.name.is_empty() && !self.is_standard_blueprint(&frag.name) {
+                    self.resolve_name(&frag.name, frag.span);
                 }
                 // Resolve arguments
                 for arg in &frag.args {
@@ -356,22 +466,45 @@ impl Resolver {
                     self.resolve_fragment_body(body, params);
                 }
                 // Resolve postfix items
+                self.context_span = frag.span;
                 for postfix in &frag.postfix {
                     match postfix {
                         ast::PostfixItem::Instruction(instr) => self.resolve_instruction_expr(instr),
                         ast::PostfixItem::EventHandler(handler) => self.resolve_event_handler(handler),
                     }
                 }
+                self.context_span = saved_span;
             }
             ast::BlueprintStmt::Control(ctrl) => self.resolve_control_stmt(ctrl, params),
             ast::BlueprintStmt::Instruction(instr) => self.resolve_instruction_expr(instr),
             ast::BlueprintStmt::EventHandler(handler) => self.resolve_event_handler(handler),
+            ast::BlueprintStmt::Shortcut(shortcut) => self.resolve_shortcut(shortcut),
+            ast::BlueprintStmt::Provide(provide) => self.resolve_expr(&provide.value),
+            ast::BlueprintStmt::Consume(consume) => {
+                // The consumed value is a field of the closure, set by the runtime
+                self.resolve_type_expr(&consume.type_expr, self.current_scope);
+                self.define_simple(
+                    &consume.name,
+                    SymbolKind::LocalVar,
+                    self.current_scope,
+                    consume.span,
+                );
+            }
             ast::BlueprintStmt::Layout(layout) => self.resolve_layout_stmt(layout),
             ast::BlueprintStmt::SlotBinding(binding) => self.resolve_slot_binding(binding, params),
             ast::BlueprintStmt::ContentExpr(expr) => self.resolve_expr(expr),
         }
     }
 
+    /// Check if `name` refers to a standard blueprint (not shadowed by a declaration)
+    fn is_standard_blueprint(&self, name: &str) -> bool {
+        STANDARD_BLUEPRINTS.contains(&name)
+            && self
+                .symbols
+                .lookup_in_scope_chain(self.current_scope, name, &self.scopes)
+                .is_none()
+    }
+
     /// Helper to get the body scope of a parameter's type
     fn get_parameter_type_body_scope(&self, param_name: &str, params: &[ast::Parameter]) -> Option<ScopeId> {
         // Find the parameter in the AST
@@ -477,7 +610,7 @@ impl Resolver {
             }
             ast::ControlStmt::Repeat {
                 iterable,
-                item_name,
+                item,
                 key_expr,
                 body,
             } => {
@@ -495,8 +628,11 @@ impl Resolver {
                 let old_scope = self.current_scope;
                 self.current_scope = loop_scope;
 
-                // Define the explicit loop variable (e.g., `item` in `repeat on items { item -> ... }`)
-                self.define_simple(item_name, SymbolKind::LocalVar, loop_scope, Span::default());
+                // Define the explicit loop variables (e.g., `item` in `repeat on items { item -> ... }`
+                // or `key` and `value` in `repeat on map { (key, value) -> ... }`)
+                for name in item.bindings() {
+                    self.define_simple(name, SymbolKind::LocalVar, loop_scope, Span::default());
+                }
 
                 // Now resolve key_expr with loop variable in scope
                 if let Some(key) = key_expr {
@@ -513,6 +649,7 @@ impl Resolver {
                 discriminant,
                 branches,
                 else_branch,
+                ..
             } => {
                 if let Some(disc) = discriminant {
                     self.resolve_expr(disc);
@@ -526,12 +663,63 @@ impl Resolver {
                     if !should_skip {
                         self.resolve_expr(&branch.condition);
                     }
+                    if branch.payload.is_empty() {
+                        self.resolve_blueprint_stmt(&branch.body, params);
+                        continue;
+                    }
+
+                    // Names bound by the payload patterns are in a scope created
+                    // at the branch's span
+                    let old_scope = self.current_scope;
+                    let branch_scope =
+                        self.scopes
+                            .create_scope(ScopeKind::Block, self.current_scope, branch.span);
+                    self.current_scope = branch_scope;
+                    for name in branch.payload.iter().flat_map(ast::Pattern::bindings) {
+                        self.define_simple(name, SymbolKind::LocalVar, branch_scope, branch.span);
+                    }
                     self.resolve_blueprint_stmt(&branch.body, params);
+                    self.current_scope = old_scope;
                 }
                 if let Some(else_stmt) = else_branch {
                     self.resolve_blueprint_stmt(else_stmt, params);
                 }
             }
+            ast::ControlStmt::OnError {
+                body,
+                error_name,
+                fallback,
+                ..
+            } => {
+                for stmt in body {
+                    self.resolve_blueprint_stmt(stmt, params);
+                }
+
+                let Some(fallback) = fallback else {
+                    return;
+                };
+
+                // A bound error message gets a scope of its own, like a loop variable
+                let old_scope = self.current_scope;
+                if let Some(name) = error_name {
+                    let fallback_scope = self.scopes.create_scope(
+                        ScopeKind::Block,
+                        self.current_scope,
+                        Span::default(),
+                    );
+                    self.current_scope = fallback_scope;
+                    self.define_simple(name, SymbolKind::LocalVar, fallback_scope, Span::default());
+                }
+                for stmt in fallback {
+                    self.resolve_blueprint_stmt(stmt, params);
+                }
+                self.current_scope = old_scope;
+            }
+            ast::ControlStmt::Loading { body, .. } => {
+                for stmt in body {
+                    self.resolve_blueprint_stmt(stmt, params);
+                }
+            }
         }
     }
 
@@ -613,10 +801,24 @@ impl Resolver {
         // Define event parameter if present
         if let Some(param) = &handler.param {
             self.define_simple(&param.name, SymbolKind::Parameter, handler_scope, Span::default());
+            if let Some(type_expr) = &param.type_expr {
+                self.resolve_type_expr(type_expr, handler_scope);
+            }
         }
 
-        // Resolve handler statements
-        for stmt in &handler.body {
+        self.resolve_handler_body(&handler.body);
+
+        self.current_scope = old_scope;
+    }
+
+    fn resolve_shortcut(&mut self, shortcut: &ast::Shortcut) {
+        // Shortcuts take no parameter, so the body resolves in the blueprint's scope
+        self.resolve_handler_body(&shortcut.body);
+    }
+
+    /// Resolve handler statements
+    fn resolve_handler_body(&mut self, body: &[ast::HandlerStmt]) {
+        for stmt in body {
             match stmt {
                 ast::HandlerStmt::Assignment { name, value } => {
                     // Resolve the value first
@@ -634,8 +836,6 @@ impl Resolver {
                 }
             }
         }
-
-        self.current_scope = old_scope;
     }
 
     fn resolve_backend(&mut self, be: &ast::Backend) {
@@ -654,16 +854,23 @@ impl Resolver {
         // Define parameters
         for param in &be.params {
             self.define_simple(&param.name, SymbolKind::Parameter, body_scope, Span::default());
+            self.resolve_type_expr(&param.type_expr, body_scope);
         }
 
         // Process members
         for member in &be.members {
             match member {
                 ast::BackendMember::Include(name) => {
-                    // Resolve included backend and import its members
+                    // Resolve included backend and import its members; an
+                    // included contract is implemented by the backend's own
+                    // members, checked by the conformance pass
                     if let Some(included_id) = self.symbols.lookup_in_scope_chain(ScopeId::ROOT, name, &self.scopes) {
+                        self.references.push((included_id, body_scope));
                         if let Some(included_symbol) = self.symbols.get(included_id) {
-                            if let Some(included_body_scope) = included_symbol.body_scope {
+                            if let Some(included_body_scope) = included_symbol
+                                .body_scope
+                                .filter(|_| included_symbol.kind != SymbolKind::Contract)
+                            {
                                 // Collect members to import (avoid borrowing issues)
                                 let members_to_import: Vec<_> = self.symbols
                                     .symbols_in_scope(included_body_scope)
@@ -687,6 +894,7 @@ impl Resolver {
                 }
                 ast::BackendMember::Field(field) => {
                     self.define_simple(&field.name, SymbolKind::Field, body_scope, field.span);
+                    self.resolve_type_expr(&field.type_expr, body_scope);
                     if let Some(init) = &field.init {
                         self.current_scope = body_scope;
                         self.context_span = field.span;
@@ -696,12 +904,43 @@ impl Resolver {
                 }
                 ast::BackendMember::Method(method) => {
                     self.define_simple(&method.name, SymbolKind::Method, body_scope, method.span);
+                    self.resolve_signature_types(&method.params, Some(&method.return_type), body_scope);
                 }
                 ast::BackendMember::Command(cmd) => {
                     self.define_simple(&cmd.name, SymbolKind::Command, body_scope, cmd.span);
+                    self.resolve_signature_types(&cmd.params, cmd.return_type.as_ref(), body_scope);
                 }
             }
         }
+
+        // Generated undo history commands, unless declared (reported as E0608)
+        if be.has_undo() {
+            for name in UNDO_COMMANDS {
+                if self.symbols.lookup_local(body_scope, name).is_none() {
+                    self.define_simple(name, SymbolKind::Command, body_scope, be.span);
+                }
+            }
+        }
+
+        // Method bodies, once all members are defined; the parameters shadow
+        // the members
+        for member in &be.members {
+            let ast::BackendMember::Method(method) = member else {
+                continue;
+            };
+            let Some(body) = &method.body else {
+                continue;
+            };
+            let params_scope =
+                self.scopes.create_scope(ScopeKind::Parameters, body_scope, method.span);
+            for param in &method.params {
+                self.define_simple(&param.name, SymbolKind::Parameter, params_scope, param.span);
+            }
+            self.current_scope = params_scope;
+            self.context_span = method.span;
+            self.resolve_expr(body);
+            self.current_scope = module_scope;
+        }
     }
 
     fn resolve_scheme(&mut self, sc: &ast::Scheme) {
@@ -721,9 +960,11 @@ impl Resolver {
             match member {
                 ast::SchemeMember::Field(field) => {
                     self.define_simple(&field.name, SymbolKind::Field, body_scope, field.span);
+                    self.resolve_type_expr(&field.type_expr, body_scope);
                 }
                 ast::SchemeMember::Virtual(virt) => {
                     self.define_simple(&virt.name, SymbolKind::VirtualField, body_scope, virt.span);
+                    self.resolve_type_expr(&virt.type_expr, body_scope);
                     // Resolve the virtual expression
                     self.current_scope = body_scope;
                     self.context_span = virt.span;
@@ -749,6 +990,7 @@ impl Resolver {
 
         for method in &ct.methods {
             self.define_simple(&method.name, SymbolKind::Method, body_scope, method.span);
+            self.resolve_signature_types(&method.params, method.return_type.as_ref(), body_scope);
         }
     }
 
@@ -772,6 +1014,7 @@ impl Resolver {
                 }
                 ast::ThemeMember::Field(field) => {
                     self.define_simple(&field.name, SymbolKind::Field, body_scope, field.span);
+                    self.resolve_type_expr(&field.type_expr, body_scope);
                     if let Some(init) = &field.init {
                         self.current_scope = body_scope;
                         self.context_span = field.span;
@@ -806,6 +1049,9 @@ impl Resolver {
         for variant in &en.variants {
             self.define_simple(variant, SymbolKind::EnumVariant, body_scope, Span::default());
         }
+        for type_expr in en.payloads.iter().flat_map(|(_, types)| types) {
+            self.resolve_type_expr(type_expr, ScopeId::ROOT);
+        }
     }
 
     fn resolve_arena(&mut self, ar: &ast::Arena) {
@@ -817,11 +1063,69 @@ impl Resolver {
         }
     }
 
+    /// Record a resolved reference to `id` from the current scope
+    fn record_reference(&mut self, id: SymbolId, span: Span) {
+        self.resolutions.insert(span, id);
+        self.references.push((id, self.current_scope));
+    }
+
+    /// Record the declarations a type expression names, from `scope`
+    ///
+    /// Unknown types are reported by the type checker, not here.
+    fn resolve_type_expr(&mut self, type_expr: &ast::TypeExpr, scope: ScopeId) {
+        match type_expr {
+            ast::TypeExpr::Named(name) => {
+                if let Some(id) = self.symbols.lookup_in_scope_chain(scope, name, &self.scopes) {
+                    self.references.push((id, scope));
+                }
+            }
+            ast::TypeExpr::Nullable(inner)
+            | ast::TypeExpr::Ref(inner)
+            | ast::TypeExpr::Draft(inner)
+            | ast::TypeExpr::Asset(inner)
+            | ast::TypeExpr::Accessor(inner)
+            | ast::TypeExpr::List(inner)
+            | ast::TypeExpr::Set(inner)
+            | ast::TypeExpr::Tree(inner) => self.resolve_type_expr(inner, scope),
+            ast::TypeExpr::Map(key, value) => {
+                self.resolve_type_expr(key, scope);
+                self.resolve_type_expr(value, scope);
+            }
+            ast::TypeExpr::Blueprint(params) => {
+                for param in params {
+                    self.resolve_type_expr(param, scope);
+                }
+            }
+            ast::TypeExpr::Function { params, ret } => {
+                for param in params {
+                    self.resolve_type_expr(param, scope);
+                }
+                self.resolve_type_expr(ret, scope);
+            }
+        }
+    }
+
+    /// Record the declarations named by the parameter and return types of a
+    /// method or command
+    fn resolve_signature_types(
+        &mut self,
+        params: &[ast::Parameter],
+        return_type: Option<&ast::TypeExpr>,
+        scope: ScopeId,
+    ) {
+        for param in params {
+            self.resolve_type_expr(&param.type_expr, scope);
+        }
+        if let Some(return_type) = return_type {
+            self.resolve_type_expr(return_type, scope);
+        }
+    }
+
     /// Resolve a name reference
     fn resolve_name(&mut self, name: &str, span: Span) -> Option<SymbolId> {
         // 4-layer lookup: local -> parent -> imports -> module
         if let Some(id) = self.symbols.lookup_in_scope_chain(self.current_scope, name, &self.scopes) {
-            self.resolutions.insert(span, id);
+            self.record_reference(id, span);
             return Some(id);
         }
 
@@ -838,6 +1142,17 @@ impl Resolver {
         None
     }
 
+    /// Whether a called name is a built-in function: one no declaration or
+    /// import shadows
+    fn is_builtin(&self, name: &str) -> bool {
+        builtins::lookup(name).is_some()
+            && !self.imports.contains_key(name)
+            && self
+                .symbols
+                .lookup_in_scope_chain(self.current_scope, name, &self.scopes)
+                .is_none()
+    }
+
     /// Resolve an expression
     fn resolve_expr(&mut self, expr: &ast::Expr) {
         match expr {
@@ -900,11 +1215,31 @@ impl Resolver {
                 self.resolve_expr(base);
             }
             ast::Expr::Call { callee, args } => {
-                self.resolve_expr(callee);
+                match callee.as_ref() {
+                    ast::Expr::Identifier(name) if self.is_builtin(name) => {}
+                    callee => self.resolve_expr(callee),
+                }
                 for arg in args {
                     self.resolve_expr(arg);
                 }
             }
+            ast::Expr::Range { start, end, .. } => {
+                self.resolve_expr(start);
+                self.resolve_expr(end);
+            }
+            ast::Expr::Lambda { params, body, span } => {
+                let outer = self.current_scope;
+                let scope = self.scopes.create_scope(ScopeKind::Lambda, outer, *span);
+                for param in params {
+                    self.define_simple(&param.name, SymbolKind::Parameter, scope, *span);
+                    if let Some(type_expr) = &param.type_expr {
+                        self.resolve_type_expr(type_expr, outer);
+                    }
+                }
+                self.current_scope = scope;
+                self.resolve_expr(body);
+                self.current_scope = outer;
+            }
         }
     }
 
@@ -958,6 +1293,49 @@ impl Resolver {
     }
 }
 
+/// Cycles of an include graph, given as the declarations each declaration
+/// includes; each cycle lists its members in include order
+fn include_cycles(includes: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        New,
+        Open,
+        Done,
+    }
+
+    fn visit(
+        node: usize,
+        includes: &[Vec<usize>],
+        state: &mut [Visit],
+        path: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        state[node] = Visit::Open;
+        path.push(node);
+        for &next in &includes[node] {
+            match state[next] {
+                Visit::New => visit(next, includes, state, path, cycles),
+                Visit::Open => {
+                    let start = path.iter().position(|&n| n == next).unwrap_or_default();
+                    cycles.push(path[start..].to_vec());
+                }
+                Visit::Done => {}
+            }
+        }
+        path.pop();
+        state[node] = Visit::Done;
+    }
+
+    let mut state = vec![Visit::New; includes.len()];
+    let mut cycles = Vec::new();
+    for node in 0..includes.len() {
+        if state[node] == Visit::New {
+            visit(node, includes, &mut state, &mut Vec::new(), &mut cycles);
+        }
+    }
+    cycles
+}
+
 impl Default for Resolver {
     fn default() -> Self {
         Self::new()
@@ -995,6 +1373,7 @@ impl<'a> ResolverWithRegistry<'a> {
     }
 
     fn resolve(mut self, file: &ast::File) -> ResolveResult {
+        let _profile = crate::profile::enter("resolve");
         // Create root/module scope
         self.inner.current_scope = self.inner.scopes.create_root(Span::default());
 
@@ -1012,6 +1391,7 @@ impl<'a> ResolverWithRegistry<'a> {
             symbols: self.inner.symbols,
             diagnostics: self.inner.diagnostics,
             resolutions: self.inner.resolutions,
+            references: self.inner.references,
             imports: self.inner.imports,
         }
     }
@@ -1024,22 +1404,17 @@ impl<'a> ResolverWithRegistry<'a> {
                 if let Some(module_sig) = self.registry.get(&import.path) {
                     for export in module_sig.all_exports() {
                         self.import_external_with_body(
-                            &export.name,
-                            export.kind,
+                            export,
                             import.span,
                             &import.path,
-                            export.body_scope,
-                            module_sig,
+                            &module_sig,
                         );
                         self.inner
                             .imports
                             .insert(export.name.clone(), import.path.clone());
                     }
                 } else {
-                    self.inner.diagnostics.error(
-                        format!("module '{}' not found", import.path),
-                        import.span,
-                    );
+                    self.report_missing_module(import, &import.path, None);
                 }
             } else {
                 // Single-declaration import: `import foo.bar.Baz`
@@ -1048,27 +1423,19 @@ impl<'a> ResolverWithRegistry<'a> {
                     if let Some(module_sig) = self.registry.get(module) {
                         if let Some(export) = module_sig.get_export(name) {
                             self.import_external_with_body(
-                                &export.name,
-                                export.kind,
+                                export,
                                 import.span,
                                 module,
-                                export.body_scope,
-                                module_sig,
+                                &module_sig,
                             );
                             self.inner
                                 .imports
                                 .insert(name.to_string(), module.to_string());
                         } else {
-                            self.inner.diagnostics.error(
-                                format!("'{}' is not exported from module '{}'", name, module),
-                                import.span,
-                            );
+                            self.report_missing_export(import, module, name, &module_sig);
                         }
                     } else {
-                        self.inner.diagnostics.error(
-                            format!("module '{}' not found", module),
-                            import.span,
-                        );
+                        self.report_missing_module(import, module, Some(name));
                     }
                 } else {
                     // Single-component path - not valid for single-declaration import
@@ -1084,27 +1451,129 @@ impl<'a> ResolverWithRegistry<'a> {
         }
     }
 
+    /// Report an import of an unknown module, suggesting a registered one
+    ///
+    /// For `import foo.bar.Baz`, modules that export `Baz` are preferred over
+    /// modules whose path is merely similar to `foo.bar`.
+    fn report_missing_module(&mut self, import: &ast::Import, module: &str, name: Option<&str>) {
+        let modules = self.registry.module_paths();
+        let mut diag = Diagnostic::from_code(
+            &codes::E0304,
+            import.span,
+            format!("module '{}' not found", module),
+        );
+
+        let suggested = match name {
+            // `import foo.bar.Baz` where `foo.bar.Baz` is itself a module
+            Some(_) if modules.contains(&import.path) => Some(format!("{}.*", import.path)),
+            Some(name) => {
+                let exporters = self.modules_exporting(name);
+                match similar_name(module, exporters.iter().map(String::as_str)) {
+                    Some(exporter) => Some(format!("{}.{}", exporter, name)),
+                    None if exporters.len() == 1 => Some(format!("{}.{}", exporters[0], name)),
+                    None => {
+                        if !exporters.is_empty() {
+                            diag = diag.with_help(format!(
+                                "`{}` is exported by: {}",
+                                name,
+                                exporters.join(", ")
+                            ));
+                        }
+                        similar_name(module, modules.iter().map(String::as_str))
+                            .map(|similar| format!("{}.{}", similar, name))
+                    }
+                }
+            }
+            None => similar_name(module, modules.iter().map(String::as_str))
+                .map(|similar| format!("{}.*", similar)),
+        };
+
+        if let Some(path) = suggested {
+            diag = diag.with_suggestion(Suggestion::new(
+                import.span,
+                format!("import {}", path),
+                format!("did you mean `{}`?", path),
+            ));
+        }
+        self.inner.diagnostics.add(diag);
+    }
+
+    /// Registered modules that export `name`, sorted
+    fn modules_exporting(&self, name: &str) -> Vec<String> {
+        self.registry
+            .module_paths()
+            .into_iter()
+            .filter(|m| self.registry.resolve_import(m, name).is_some())
+            .collect()
+    }
+
+    /// Report an import of a name the module doesn't export, listing its exports
+    fn report_missing_export(
+        &mut self,
+        import: &ast::Import,
+        module: &str,
+        name: &str,
+        module_sig: &super::signature::ModuleSignature,
+    ) {
+        let mut exports: Vec<&str> = module_sig.all_exports().iter().map(|e| e.name.as_str()).collect();
+        exports.sort();
+
+        let mut diag = Diagnostic::from_code(
+            &codes::E0304,
+            import.span,
+            format!("'{}' is not exported from module '{}'", name, module),
+        );
+        let suggested = similar_name(name, exports.iter().copied())
+            .map(|similar| format!("{}.{}", module, similar))
+            .or_else(|| {
+                // The name may live in another module
+                let exporters = self.modules_exporting(name);
+                similar_name(module, exporters.iter().map(String::as_str))
+                    .or(exporters.first().map(String::as_str))
+                    .map(|exporter| format!("{}.{}", exporter, name))
+            });
+        if let Some(path) = suggested {
+            diag = diag.with_suggestion(Suggestion::new(
+                import.span,
+                format!("import {}", path),
+                format!("did you mean `{}`?", path),
+            ));
+        }
+        diag = if exports.is_empty() {
+            diag.with_help(format!("module '{}' has no exports", module))
+        } else {
+            diag.with_help(format!("available exports: {}", exports.join(", ")))
+        };
+        self.inner.diagnostics.add(diag);
+    }
+
     /// Import an external declaration, including its body scope and member symbols
+    ///
+    /// Imported symbols are defined at the import span and record the ID of their
+    /// definition in the module signature.
     fn import_external_with_body(
         &mut self,
-        name: &str,
-        kind: SymbolKind,
+        export: &super::signature::ExportedDecl,
         span: Span,
         source_module: &str,
-        body_scope: Option<ScopeId>,
         module_sig: &super::signature::ModuleSignature,
     ) {
+        let name = export.name.as_str();
+
         // Define the external symbol
         let symbol_id = self.inner.symbols.define_external(
             name,
-            kind,
+            export.kind,
             ScopeId::ROOT,
             span,
             source_module.to_string(),
         );
+        if let Some(symbol) = symbol_id.and_then(|id| self.inner.symbols.get_mut(id)) {
+            symbol.resolved_import = Some(export.symbol_id);
+        }
 
         // If the symbol has a body scope, create a local copy with its members
-        if let (Some(symbol_id), Some(orig_body_scope)) = (symbol_id, body_scope) {
+        if let (Some(symbol_id), Some(orig_body_scope)) = (symbol_id, export.body_scope) {
             // Get the scope kind from the original scope
             let scope_kind = module_sig
                 .get_scope(orig_body_scope)
@@ -1128,17 +1597,20 @@ impl<'a> ResolverWithRegistry<'a> {
             let members: Vec<_> = module_sig
                 .symbols
                 .symbols_in_scope(orig_body_scope)
-                .map(|s| (s.name.clone(), s.kind))
+                .map(|s| (s.name.clone(), s.kind, s.id))
                 .collect();
 
-            for (member_name, member_kind) in members {
-                self.inner.symbols.define_external(
+            for (member_name, member_kind, member_origin) in members {
+                let member_id = self.inner.symbols.define_external(
                     &member_name,
                     member_kind,
                     local_body_scope,
                     span,
                     source_module.to_string(),
                 );
+                if let Some(member) = member_id.and_then(|id| self.inner.symbols.get_mut(id)) {
+                    member.resolved_import = Some(member_origin);
+                }
             }
         }
     }
@@ -1181,6 +1653,57 @@ backend Counter {
         assert!(result.symbols.len() >= 4);
     }
 
+    #[test]
+    fn test_include_cycles() {
+        let source = r#"
+module test
+
+backend Base {
+    count: i32 = 0
+}
+
+backend A {
+    include Base
+    include B
+}
+
+backend B {
+    include C
+}
+
+backend C {
+    include A
+}
+
+backend Loop {
+    include Loop
+}
+
+theme Light {
+    include Dark
+}
+
+theme Dark {
+    include Light
+}
+"#;
+        let result = parse_and_resolve(source);
+        let cycles: Vec<_> = result
+            .diagnostics
+            .iter()
+            .filter(|d| d.code.as_deref() == Some("E0602"))
+            .map(|d| (d.message.as_str(), d.related.len()))
+            .collect();
+        assert_eq!(
+            cycles,
+            [
+                ("backend `A` includes itself: `A` -> `B` -> `C` -> `A`", 2),
+                ("backend `Loop` includes itself", 0),
+                ("theme `Light` includes itself: `Light` -> `Dark` -> `Light`", 1),
+            ]
+        );
+    }
+
     #[test]
     fn test_resolve_blueprint_with_backend() {
         let source = r#"
@@ -1323,7 +1846,7 @@ backend EditorBackend {
             members.iter().map(|m| &m.name).collect::<Vec<_>>());
 
         // Register signature
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(sig_result.signature);
 
         // Now resolve the importing module
@@ -1359,6 +1882,23 @@ blueprint Editor {
         let has_save = imported_members.iter().any(|m| m.name == "save");
         assert!(has_content, "Should have content field");
         assert!(has_save, "Should have save command");
+
+        // Members brought in by `with` point at their definition in the signature
+        let blueprint_scope = result
+            .scopes
+            .iter()
+            .find(|s| s.name.as_deref() == Some("Editor"))
+            .unwrap()
+            .id;
+        let content = result.symbols.lookup_local(blueprint_scope, "content").unwrap();
+        let content = result.symbols.get(content).unwrap();
+        assert_eq!(content.source_module.as_deref(), Some("test.backend"));
+        let signature = registry.get("test.backend").unwrap();
+        let origin = signature.get_symbol(content.resolved_import.unwrap()).unwrap();
+        assert_eq!(origin.name, "content");
+
+        // The `with` clause resolves to the imported backend
+        assert!(result.resolutions.values().any(|id| *id == editor_backend_id.unwrap()));
     }
 
     #[test]
@@ -1396,4 +1936,62 @@ blueprint TodoList {
             result.diagnostics
         );
     }
+
+    #[test]
+    fn test_import_suggestions() {
+        use super::super::signature::SignatureRegistry;
+        use super::super::signature_builder::build_signature;
+        use crate::Module;
+
+        let registry = SignatureRegistry::new();
+        for source in [
+            "module app.core\n\nscheme User {\n    name : String\n}\n\nscheme Order {\n    id : i64\n}\n",
+            "module app.theme\n\nenum Palette {\n    Light\n    Dark\n}\n",
+        ] {
+            let file = parser::parse(source).file.unwrap();
+            registry.register(build_signature(&Module::from_file(file)).signature);
+        }
+
+        let source = "module app.main\n\nimport app.cor.User\nimport app.data.Palette\nimport app.core.Usr\nimport app.core.Palette\nimport app.them.*\nimport app.core.Missing\nimport app.theme\n";
+        let file = parser::parse(source).file.unwrap();
+        let result = resolve_with_registry(&file, &registry);
+
+        let reported: Vec<(&str, Option<&str>, Option<&str>)> = result
+            .diagnostics
+            .iter()
+            .map(|d| {
+                assert_eq!(d.code.as_deref(), Some("E0304"));
+                (
+                    d.message.as_str(),
+                    d.suggestions.first().map(|s| s.replacement.as_str()),
+                    d.help.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                ("module 'app.cor' not found", Some("import app.core.User"), None),
+                ("module 'app.data' not found", Some("import app.theme.Palette"), None),
+                (
+                    "'Usr' is not exported from module 'app.core'",
+                    Some("import app.core.User"),
+                    Some("available exports: Order, User")
+                ),
+                (
+                    "'Palette' is not exported from module 'app.core'",
+                    Some("import app.theme.Palette"),
+                    Some("available exports: Order, User")
+                ),
+                ("module 'app.them' not found", Some("import app.theme.*"), None),
+                (
+                    "'Missing' is not exported from module 'app.core'",
+                    None,
+                    Some("available exports: Order, User")
+                ),
+                // The whole path is a module
+                ("module 'app' not found", Some("import app.theme.*"), None),
+            ]
+        );
+    }
 }
@@ -37,6 +37,8 @@ pub enum ScopeKind {
     Block,
     /// Function/method parameter scope
     Parameters,
+    /// Parameter scope of a lambda expression
+    Lambda,
 }
 
 impl ScopeKind {
@@ -51,6 +53,7 @@ impl ScopeKind {
             ScopeKind::Enum => "enum",
             ScopeKind::Block => "block",
             ScopeKind::Parameters => "parameters",
+            ScopeKind::Lambda => "lambda",
         }
     }
 }
@@ -208,6 +211,16 @@ impl ScopeGraph {
         self.ancestors(inner).any(|id| id == outer)
     }
 
+    /// The child of `parent` of a kind created for the construct at `span`,
+    /// e.g. the parameter scope of a method
+    pub fn child_at(&self, parent: ScopeId, kind: ScopeKind, span: Span) -> Option<ScopeId> {
+        let children = &self.get(parent)?.children;
+        children.iter().copied().find(|&child| {
+            self.get(child)
+                .is_some_and(|scope| scope.kind == kind && scope.span == span)
+        })
+    }
+
     /// Get all scopes
     pub fn iter(&self) -> impl Iterator<Item = &Scope> {
         self.scopes.iter()
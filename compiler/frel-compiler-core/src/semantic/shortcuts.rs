@@ -0,0 +1,466 @@
+// Keyboard shortcut checks for Frel
+//
+// A blueprint declares keyboard shortcuts at its top level:
+//
+//     shortcut "Ctrl+S" { save() }
+//
+// The key chord is parsed at compile time: modifiers (`Ctrl`, `Alt`, `Shift`,
+// `Meta`) come before exactly one key, joined by `+`. Names are matched
+// without regard to case and written back in a canonical form, so
+// `"shift+ctrl+s"` and `"Ctrl+Shift+S"` are the same chord. A malformed chord,
+// or a shortcut nested inside a fragment or control statement, is an error
+// (E0707).
+//
+// Module signatures record the valid shortcuts, so a chord declared twice in
+// the project is reported (E0708) at each declaration, pointing at the other
+// ones. Only one of them runs: the runtime gives a key to the shortcut of the
+// most recently created fragment.
+
+use std::fmt;
+
+use super::signature::{ShortcutDecl, SignatureRegistry};
+use crate::ast::{self, BlueprintStmt, BlueprintValue, ControlStmt, FragmentBody};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo};
+
+/// Keys with a name, as (canonical name, accepted spellings in lower case)
+const NAMED_KEYS: &[(&str, &[&str])] = &[
+    ("Enter", &["enter", "return"]),
+    ("Escape", &["escape", "esc"]),
+    ("Space", &["space"]),
+    ("Tab", &["tab"]),
+    ("Backspace", &["backspace"]),
+    ("Delete", &["delete", "del"]),
+    ("Insert", &["insert", "ins"]),
+    ("Home", &["home"]),
+    ("End", &["end"]),
+    ("PageUp", &["pageup"]),
+    ("PageDown", &["pagedown"]),
+    ("ArrowUp", &["arrowup", "up"]),
+    ("ArrowDown", &["arrowdown", "down"]),
+    ("ArrowLeft", &["arrowleft", "left"]),
+    ("ArrowRight", &["arrowright", "right"]),
+];
+
+/// Punctuation keys, written as the character they produce without `Shift`
+const PUNCTUATION_KEYS: &str = "`-=[]\\;',./";
+
+/// Highest function key
+const MAX_FUNCTION_KEY: u32 = 24;
+
+/// A parsed key chord
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    /// Canonical key name: an upper-case letter, a digit, a punctuation
+    /// character, `F1`-`F24` or a name such as `Enter`
+    pub key: String,
+}
+
+impl KeyChord {
+    /// Parse a chord as written in a `shortcut` declaration
+    pub fn parse(text: &str) -> Result<KeyChord, String> {
+        if text.trim().is_empty() {
+            return Err("the key chord is empty".to_string());
+        }
+
+        let mut chord = KeyChord::default();
+        let mut key: Option<String> = None;
+        for part in text.split('+').map(str::trim) {
+            if part.is_empty() {
+                return Err(format!("`{}` has an empty key", text));
+            }
+            if key.is_some() {
+                return Err(format!(
+                    "`{}` has more than one key; modifiers go before the key",
+                    text
+                ));
+            }
+            let modifier = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut chord.ctrl,
+                "alt" | "option" => &mut chord.alt,
+                "shift" => &mut chord.shift,
+                "meta" | "cmd" | "command" => &mut chord.meta,
+                _ => {
+                    key = Some(parse_key(part).ok_or_else(|| format!("unknown key `{}`", part))?);
+                    continue;
+                }
+            };
+            if *modifier {
+                return Err(format!("`{}` repeats the modifier `{}`", text, part));
+            }
+            *modifier = true;
+        }
+
+        chord.key = key.ok_or_else(|| format!("`{}` has modifiers but no key", text))?;
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.meta, "Meta"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(set, _)| *set) {
+            write!(f, "{}+", name)?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// Canonical name of a key, if it is one
+fn parse_key(text: &str) -> Option<String> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return (c.is_ascii_alphanumeric() || PUNCTUATION_KEYS.contains(c))
+            .then(|| c.to_ascii_uppercase().to_string());
+    }
+
+    let lower = text.to_ascii_lowercase();
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        let canonical = lower.len() == number.to_string().len() + 1;
+        return (canonical && (1..=MAX_FUNCTION_KEY).contains(&number))
+            .then(|| format!("F{}", number));
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&lower.as_str()))
+        .map(|(name, _)| name.to_string())
+}
+
+/// Valid shortcuts declared at blueprint scope, for the module signature
+pub fn declared_shortcuts(file: &ast::File) -> Vec<ShortcutDecl> {
+    blueprint_shortcuts(file)
+        .filter_map(|(blueprint, shortcut)| {
+            let chord = KeyChord::parse(&shortcut.chord).ok()?;
+            Some(ShortcutDecl {
+                chord: chord.to_string(),
+                blueprint: format!("{}.{}", file.module, blueprint.name),
+                source_path: file.source_path.clone(),
+                span: shortcut.chord_span,
+            })
+        })
+        .collect()
+}
+
+/// Report malformed and misplaced shortcuts of a module's files, and chords
+/// declared more than once in the project
+///
+/// Shortcuts of other modules come from their signatures in `registry`.
+pub fn check_shortcuts(
+    files: &[ast::File],
+    module_path: &str,
+    registry: &SignatureRegistry,
+) -> Diagnostics {
+    let _profile = crate::profile::enter("shortcuts");
+    let mut diagnostics = Diagnostics::new();
+
+    for file in files {
+        for (_, shortcut) in blueprint_shortcuts(file) {
+            if let Err(reason) = KeyChord::parse(&shortcut.chord) {
+                diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0707,
+                        shortcut.chord_span,
+                        format!("invalid key chord: {}", reason),
+                    )
+                    .with_help(
+                        "write modifiers (Ctrl, Alt, Shift, Meta) before one key, joined by `+`, \
+                         e.g. \"Ctrl+Shift+S\"",
+                    ),
+                );
+            }
+        }
+        for decl in &file.declarations {
+            if let ast::TopLevelDecl::Blueprint(blueprint) = decl {
+                let mut nested = Vec::new();
+                for stmt in &blueprint.body {
+                    nested_shortcuts(stmt, &mut nested);
+                }
+                for shortcut in nested {
+                    diagnostics.add(Diagnostic::from_code(
+                        &codes::E0707,
+                        shortcut.span,
+                        format!(
+                            "shortcut `{}` must be declared at the top level of `{}`",
+                            shortcut.chord, blueprint.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    let local: Vec<ShortcutDecl> = files.iter().flat_map(declared_shortcuts).collect();
+    let mut others: Vec<ShortcutDecl> = Vec::new();
+    for path in registry.module_paths() {
+        if path == module_path {
+            continue;
+        }
+        if let Some(signature) = registry.get(&path) {
+            others.extend(signature.shortcuts.iter().cloned());
+        }
+    }
+
+    for (i, decl) in local.iter().enumerate() {
+        let conflicts: Vec<&ShortcutDecl> = local
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| *j != i && other.chord == decl.chord)
+            .map(|(_, other)| other)
+            .chain(others.iter().filter(|other| other.chord == decl.chord))
+            .collect();
+        if conflicts.is_empty() {
+            continue;
+        }
+
+        let names: Vec<String> = conflicts
+            .iter()
+            .map(|other| format!("`{}`", other.blueprint))
+            .collect();
+        let related = conflicts.iter().filter_map(|other| {
+            let message = format!("`{}` also declared by `{}`", other.chord, other.blueprint);
+            match &other.source_path {
+                path if *path == decl.source_path => Some(RelatedInfo::new(other.span, message)),
+                Some(path) => Some(RelatedInfo::in_file(other.span, path, message)),
+                None => None,
+            }
+        });
+        diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0708,
+                decl.span,
+                format!(
+                    "shortcut `{}` of `{}` is also declared by {}",
+                    decl.chord,
+                    decl.blueprint,
+                    names.join(", ")
+                ),
+            )
+            .with_related_all(related),
+        );
+    }
+
+    diagnostics
+}
+
+/// Shortcuts declared at the top level of the file's blueprints
+fn blueprint_shortcuts(
+    file: &ast::File,
+) -> impl Iterator<Item = (&ast::Blueprint, &ast::Shortcut)> {
+    file.declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Blueprint(blueprint) => Some(blueprint),
+            _ => None,
+        })
+        .flat_map(|blueprint| {
+            blueprint.body.iter().filter_map(move |stmt| match stmt {
+                BlueprintStmt::Shortcut(shortcut) => Some((blueprint, shortcut)),
+                _ => None,
+            })
+        })
+}
+
+/// Shortcuts nested in the statements a blueprint statement contains
+fn nested_shortcuts<'a>(stmt: &'a BlueprintStmt, found: &mut Vec<&'a ast::Shortcut>) {
+    match stmt {
+        BlueprintStmt::FragmentCreation(frag) => match &frag.body {
+            Some(FragmentBody::Default(body))
+            | Some(FragmentBody::InlineBlueprint { body, .. }) => visit(body, found),
+            Some(FragmentBody::Slots(slots)) => {
+                for slot in slots {
+                    if let BlueprintValue::Inline { body, .. } = &slot.blueprint {
+                        visit(body, found);
+                    }
+                }
+            }
+            None => {}
+        },
+        BlueprintStmt::SlotBinding(slot) => {
+            if let BlueprintValue::Inline { body, .. } = &slot.blueprint {
+                visit(body, found);
+            }
+        }
+        BlueprintStmt::Control(ControlStmt::When {
+            then_stmt,
+            else_stmt,
+            ..
+        }) => {
+            visit(std::slice::from_ref(then_stmt), found);
+            if let Some(else_stmt) = else_stmt {
+                visit(std::slice::from_ref(else_stmt), found);
+            }
+        }
+        BlueprintStmt::Control(ControlStmt::Repeat { body, .. }) => visit(body, found),
+        BlueprintStmt::Control(ControlStmt::Select {
+            branches,
+            else_branch,
+            ..
+        }) => {
+            for branch in branches {
+                visit(std::slice::from_ref(&branch.body), found);
+            }
+            if let Some(else_branch) = else_branch {
+                visit(std::slice::from_ref(else_branch), found);
+            }
+        }
+        BlueprintStmt::Control(ControlStmt::OnError { body, fallback, .. }) => {
+            visit(body, found);
+            if let Some(fallback) = fallback {
+                visit(fallback, found);
+            }
+        }
+        BlueprintStmt::Control(ControlStmt::Loading { body, .. }) => visit(body, found),
+        _ => {}
+    }
+}
+
+/// Shortcuts among `stmts` and the statements they contain
+fn visit<'a>(stmts: &'a [BlueprintStmt], found: &mut Vec<&'a ast::Shortcut>) {
+    for stmt in stmts {
+        if let BlueprintStmt::Shortcut(shortcut) = stmt {
+            found.push(shortcut);
+        }
+        nested_shortcuts(stmt, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{build_signature, Module};
+
+    fn parse(source: &str, path: &str) -> ast::File {
+        let result = crate::parse_file_with_path(source, path);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        result.file.unwrap()
+    }
+
+    fn codes(diagnostics: &Diagnostics) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_chords() {
+        let canonical = |text: &str| KeyChord::parse(text).map(|chord| chord.to_string());
+        assert_eq!(canonical("Ctrl+S"), Ok("Ctrl+S".to_string()));
+        assert_eq!(
+            canonical("shift + ctrl + s"),
+            Ok("Ctrl+Shift+S".to_string())
+        );
+        assert_eq!(
+            canonical("Cmd+Option+Up"),
+            Ok("Alt+Meta+ArrowUp".to_string())
+        );
+        assert_eq!(canonical("f12"), Ok("F12".to_string()));
+        assert_eq!(canonical("Esc"), Ok("Escape".to_string()));
+        assert_eq!(canonical("Ctrl+/"), Ok("Ctrl+/".to_string()));
+
+        for invalid in [
+            "",
+            "Ctrl+",
+            "Ctrl",
+            "Ctrl+Ctrl+S",
+            "S+Ctrl",
+            "Ctrl+Foo",
+            "F25",
+            "F01",
+            "Ctrl+S+T",
+        ] {
+            assert!(KeyChord::parse(invalid).is_err(), "{:?} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn test_invalid_and_nested_shortcuts() {
+        let file = parse(
+            r#"module app
+
+blueprint Editor {
+    shortcut "Ctrl+Banana" { save() }
+    column {
+        shortcut "Ctrl+S" { save() }
+    }
+}
+"#,
+            "editor.frel",
+        );
+        let diagnostics = check_shortcuts(
+            std::slice::from_ref(&file),
+            "app",
+            &SignatureRegistry::new(),
+        );
+        assert_eq!(codes(&diagnostics), ["E0707", "E0707"]);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages[0], "invalid key chord: unknown key `Banana`");
+        assert!(
+            messages[1].contains("top level of `Editor`"),
+            "{}",
+            messages[1]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_shortcuts_across_modules() {
+        let editor = parse(
+            "module app.editor\n\nblueprint Editor {\n    shortcut \"ctrl+s\" { save() }\n}\n",
+            "editor.frel",
+        );
+        let settings = parse(
+            "module app.settings\n\nblueprint Settings {\n    shortcut \"Ctrl+S\" { apply() }\n    shortcut \"Escape\" { close() }\n}\n",
+            "settings.frel",
+        );
+        let registry = SignatureRegistry::new();
+        for file in [&editor, &settings] {
+            let module = Module::from_file(file.clone());
+            registry.register(build_signature(&module).signature);
+        }
+
+        let diagnostics = check_shortcuts(std::slice::from_ref(&editor), "app.editor", &registry);
+        assert_eq!(codes(&diagnostics), ["E0708"]);
+        let warning = diagnostics.iter().next().unwrap();
+        assert_eq!(
+            warning.message,
+            "shortcut `Ctrl+S` of `app.editor.Editor` is also declared by `app.settings.Settings`"
+        );
+        assert_eq!(warning.related.len(), 1);
+        assert_eq!(warning.related[0].file.as_deref(), Some("settings.frel"));
+    }
+
+    #[test]
+    fn test_duplicate_shortcuts_in_module() {
+        let file = parse(
+            r#"module app
+
+blueprint Editor {
+    shortcut "Ctrl+S" { save() }
+}
+
+blueprint Toolbar {
+    shortcut "Ctrl+S" { save() }
+    shortcut "Ctrl+Shift+S" { save() }
+}
+"#,
+            "app.frel",
+        );
+        let diagnostics = check_shortcuts(
+            std::slice::from_ref(&file),
+            "app",
+            &SignatureRegistry::new(),
+        );
+        assert_eq!(codes(&diagnostics), ["E0708", "E0708"]);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.related.len() == 1 && d.related[0].file.is_none()));
+    }
+}
@@ -4,6 +4,10 @@
 // - ModuleSignature: The cacheable/serializable interface of a compiled module
 // - SignatureRegistry: Collection of module signatures for cross-module resolution
 //
+// The registry is shared between threads: signatures are immutable behind an
+// `Arc`, and registering a module swaps in a new one without touching the
+// signature that readers may still hold.
+//
 // The signature contains everything needed to compile code that imports from
 // this module, without needing the original source.
 
@@ -12,6 +16,7 @@ use super::symbol::{SymbolId, SymbolKind, SymbolTable};
 use crate::source::Span;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// Current signature format version
 pub const SIGNATURE_VERSION: u32 = 1;
@@ -25,6 +30,10 @@ pub struct ModuleSignature {
     /// Module path (e.g., "test.data")
     pub path: String,
 
+    /// Source file the scopes and symbols (and their spans) come from
+    #[serde(default)]
+    pub source_path: Option<String>,
+
     /// Exported declarations (top-level types visible to importers)
     pub exports: Vec<ExportedDecl>,
 
@@ -33,6 +42,23 @@ pub struct ModuleSignature {
 
     /// Symbol table for this module
     pub symbols: SerializableSymbolTable,
+
+    /// Keyboard shortcuts declared by the module's blueprints
+    #[serde(default)]
+    pub shortcuts: Vec<ShortcutDecl>,
+
+    /// Context value names provided by the module's blueprints
+    #[serde(default)]
+    pub contexts: Vec<String>,
+
+    /// Imports of the module's files, as `module.Name` or `module.*`
+    #[serde(default)]
+    pub imports: Vec<String>,
+
+    /// Declared types of the members of the module's declarations, e.g.
+    /// `Store.count` and `i32`, so importers see a change of type
+    #[serde(default)]
+    pub member_types: Vec<(String, String)>,
 }
 
 impl ModuleSignature {
@@ -46,9 +72,14 @@ impl ModuleSignature {
         Self {
             version: SIGNATURE_VERSION,
             path,
+            source_path: None,
             exports,
             scopes: SerializableScopeGraph::from(scopes),
             symbols: SerializableSymbolTable::from(symbols),
+            shortcuts: Vec::new(),
+            contexts: Vec::new(),
+            imports: Vec::new(),
+            member_types: Vec::new(),
         }
     }
 
@@ -105,6 +136,22 @@ impl ExportedDecl {
     }
 }
 
+/// A keyboard shortcut declared in a module, for project-wide conflict checks
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct ShortcutDecl {
+    /// Canonical key chord, e.g. `Ctrl+Shift+S`
+    pub chord: String,
+
+    /// Blueprint declaring the shortcut
+    pub blueprint: String,
+
+    /// File declaring the shortcut
+    pub source_path: Option<String>,
+
+    /// Span of the chord in that file
+    pub span: Span,
+}
+
 /// Serializable version of ScopeGraph
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableScopeGraph {
@@ -257,51 +304,80 @@ impl From<&super::symbol::Symbol> for SerializableSymbol {
 }
 
 /// Registry of module signatures for cross-module resolution
+///
+/// `Send + Sync`: all methods take `&self`, so one thread can replace a
+/// module's signature while others resolve against the registry.
 #[derive(Debug, Default)]
 pub struct SignatureRegistry {
     /// Module path -> ModuleSignature
-    signatures: HashMap<String, ModuleSignature>,
+    signatures: RwLock<HashMap<String, Arc<ModuleSignature>>>,
 }
 
 impl SignatureRegistry {
     pub fn new() -> Self {
-        Self {
-            signatures: HashMap::new(),
-        }
+        Self::default()
+    }
+
+    /// Register a module signature, replacing any earlier one for its path
+    pub fn register(&self, signature: ModuleSignature) {
+        self.write().insert(signature.path.clone(), Arc::new(signature));
     }
 
-    /// Register a module signature
-    pub fn register(&mut self, signature: ModuleSignature) {
-        self.signatures.insert(signature.path.clone(), signature);
+    /// Remove a module signature, returning it if it was registered
+    pub fn unregister(&self, module_path: &str) -> Option<Arc<ModuleSignature>> {
+        self.write().remove(module_path)
     }
 
     /// Get a module signature by path
-    pub fn get(&self, module_path: &str) -> Option<&ModuleSignature> {
-        self.signatures.get(module_path)
+    ///
+    /// The returned signature stays valid if the module is replaced meanwhile.
+    pub fn get(&self, module_path: &str) -> Option<Arc<ModuleSignature>> {
+        self.read().get(module_path).cloned()
     }
 
     /// Check if a module is registered
     pub fn contains(&self, module_path: &str) -> bool {
-        self.signatures.contains_key(module_path)
+        self.read().contains_key(module_path)
     }
 
     /// Resolve an import (module_path, name) -> ExportedDecl
-    pub fn resolve_import(&self, module_path: &str, name: &str) -> Option<&ExportedDecl> {
-        self.get(module_path)?.get_export(name)
+    pub fn resolve_import(&self, module_path: &str, name: &str) -> Option<ExportedDecl> {
+        self.get(module_path)?.get_export(name).cloned()
     }
 
-    /// Get all registered module paths
-    pub fn module_paths(&self) -> impl Iterator<Item = &String> {
-        self.signatures.keys()
+    /// Get all registered module paths, sorted
+    pub fn module_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.read().keys().cloned().collect();
+        paths.sort();
+        paths
     }
 
     /// Number of registered modules
     pub fn len(&self) -> usize {
-        self.signatures.len()
+        self.read().len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.signatures.is_empty()
+        self.read().is_empty()
+    }
+
+    // A panic while holding the lock can't leave the map half-updated, so a
+    // poisoned lock is still safe to use
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Arc<ModuleSignature>>> {
+        self.signatures.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Arc<ModuleSignature>>> {
+        self.signatures.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clone for SignatureRegistry {
+    /// Copy the registry; signatures are shared, not copied
+    fn clone(&self) -> Self {
+        Self {
+            signatures: RwLock::new(self.read().clone()),
+        }
     }
 }
 
@@ -314,12 +390,17 @@ mod tests {
         let sig = ModuleSignature {
             version: SIGNATURE_VERSION,
             path: "test".to_string(),
+            source_path: None,
             exports: vec![],
             scopes: SerializableScopeGraph { scopes: vec![] },
             symbols: SerializableSymbolTable {
                 symbols: vec![],
                 name_lookup: std::sync::OnceLock::new(),
             },
+            shortcuts: vec![],
+            contexts: vec![],
+            imports: vec![],
+            member_types: vec![],
         };
 
         assert!(sig.is_compatible());
@@ -330,6 +411,7 @@ mod tests {
         let sig = ModuleSignature {
             version: SIGNATURE_VERSION,
             path: "test.module".to_string(),
+            source_path: None,
             exports: vec![ExportedDecl::new(
                 "User".to_string(),
                 SymbolKind::Scheme,
@@ -341,6 +423,10 @@ mod tests {
                 symbols: vec![],
                 name_lookup: std::sync::OnceLock::new(),
             },
+            shortcuts: vec![],
+            contexts: vec![],
+            imports: vec![],
+            member_types: vec![],
         };
 
         // Test JSON serialization
@@ -354,11 +440,12 @@ mod tests {
 
     #[test]
     fn test_signature_registry() {
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
 
         let sig = ModuleSignature {
             version: SIGNATURE_VERSION,
             path: "test.data".to_string(),
+            source_path: None,
             exports: vec![
                 ExportedDecl::new("User".to_string(), SymbolKind::Scheme, SymbolId(0), Some(ScopeId(1))),
                 ExportedDecl::new("Order".to_string(), SymbolKind::Scheme, SymbolId(1), Some(ScopeId(2))),
@@ -368,6 +455,10 @@ mod tests {
                 symbols: vec![],
                 name_lookup: std::sync::OnceLock::new(),
             },
+            shortcuts: vec![],
+            contexts: vec![],
+            imports: vec![],
+            member_types: vec![],
         };
 
         registry.register(sig);
@@ -382,4 +473,53 @@ mod tests {
         let missing = registry.resolve_import("test.data", "Missing");
         assert!(missing.is_none());
     }
+
+    #[test]
+    fn test_registry_shared_between_threads() {
+        fn signature(path: &str, export: &str) -> ModuleSignature {
+            ModuleSignature {
+                version: SIGNATURE_VERSION,
+                path: path.to_string(),
+                source_path: None,
+                exports: vec![ExportedDecl::new(
+                    export.to_string(),
+                    SymbolKind::Scheme,
+                    SymbolId(0),
+                    None,
+                )],
+                scopes: SerializableScopeGraph { scopes: vec![] },
+                symbols: SerializableSymbolTable {
+                    symbols: vec![],
+                    name_lookup: std::sync::OnceLock::new(),
+                },
+                shortcuts: vec![],
+                contexts: vec![],
+                imports: vec![],
+                member_types: vec![],
+            }
+        }
+
+        let registry = Arc::new(SignatureRegistry::new());
+        registry.register(signature("test.data", "Old"));
+        let old = registry.get("test.data").unwrap();
+
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let registry = &registry;
+                scope.spawn(move || {
+                    let path = format!("test.m{}", i);
+                    registry.register(signature(&path, "User"));
+                    assert!(registry.resolve_import(&path, "User").is_some());
+                    assert!(registry.get("test.data").is_some());
+                });
+            }
+        });
+        registry.register(signature("test.data", "New"));
+
+        // Readers keep the signature they got; new lookups see the replacement
+        assert_eq!(old.exports[0].name, "Old");
+        assert!(registry.resolve_import("test.data", "New").is_some());
+        assert_eq!(registry.len(), 5);
+        assert_eq!(registry.module_paths()[0], "test.data");
+    }
 }
@@ -1,8 +1,11 @@
 use super::resolve;
 use super::scope::{ScopeGraph, ScopeId};
+use super::context::provided_names;
+use super::shortcuts::declared_shortcuts;
 use super::signature::{ExportedDecl, ModuleSignature};
 use super::symbol::SymbolTable;
 use super::Module;
+use crate::ast;
 use crate::diagnostic::Diagnostics;
 use crate::source::Span;
 
@@ -27,6 +30,7 @@ impl SignatureResult {
 /// type resolution. The resulting signature can be cached and used by other
 /// modules that import from this one.
 pub fn build_signature(module: &Module) -> SignatureResult {
+    let _profile = crate::profile::enter("build_signature");
     let mut diagnostics = Diagnostics::new();
     let mut combined_scopes = ScopeGraph::new();
     let mut combined_symbols = SymbolTable::new();
@@ -52,12 +56,22 @@ pub fn build_signature(module: &Module) -> SignatureResult {
     // Extract exported declarations (top-level type definitions)
     let exports = extract_exports(&combined_symbols);
 
-    let signature = ModuleSignature::new(
+    let mut signature = ModuleSignature::new(
         module.path.clone(),
         exports,
         &combined_scopes,
         &combined_symbols,
     );
+    // Only the first file's scopes and symbols are kept (see merge_resolve_result)
+    signature.source_path = module.files.first().and_then(|file| file.source_path.clone());
+    signature.shortcuts = module.files.iter().flat_map(declared_shortcuts).collect();
+    signature.contexts = module.files.iter().flat_map(provided_names).collect();
+    signature.contexts.sort();
+    signature.contexts.dedup();
+    signature.imports = module.files.iter().flat_map(import_paths).collect();
+    signature.imports.sort();
+    signature.imports.dedup();
+    signature.member_types = module.files.iter().flat_map(member_types).collect();
 
     SignatureResult {
         signature,
@@ -103,6 +117,86 @@ fn merge_resolve_result(
     }
 }
 
+/// Imports of a file, as `module.Name` or `module.*`
+fn import_paths(file: &ast::File) -> impl Iterator<Item = String> + '_ {
+    file.imports.iter().map(|import| {
+        if import.import_all {
+            format!("{}.*", import.path)
+        } else {
+            import.path.clone()
+        }
+    })
+}
+
+/// Declared types of the members of a file's declarations, as `Decl.member`
+/// and the type; methods and commands as `fn(params) -> return`
+fn member_types(file: &ast::File) -> Vec<(String, String)> {
+    fn callable(params: &[ast::Parameter], ret: Option<&ast::TypeExpr>) -> String {
+        let params: Vec<String> = params.iter().map(|p| p.type_expr.to_string()).collect();
+        match ret {
+            Some(ret) => format!("fn({}) -> {}", params.join(", "), ret),
+            None => format!("fn({})", params.join(", ")),
+        }
+    }
+    let mut types = Vec::new();
+    for decl in &file.declarations {
+        let name = decl.name();
+        let mut add = |member: &str, ty: String| types.push((format!("{}.{}", name, member), ty));
+        match decl {
+            ast::TopLevelDecl::Blueprint(blueprint) => {
+                for param in &blueprint.params {
+                    add(&param.name, param.type_expr.to_string());
+                }
+            }
+            ast::TopLevelDecl::Backend(backend) => {
+                for param in &backend.params {
+                    add(&param.name, param.type_expr.to_string());
+                }
+                for member in &backend.members {
+                    match member {
+                        ast::BackendMember::Field(field) => add(&field.name, field.type_expr.to_string()),
+                        ast::BackendMember::Method(method) => {
+                            add(&method.name, callable(&method.params, Some(&method.return_type)))
+                        }
+                        ast::BackendMember::Command(command) => {
+                            add(&command.name, callable(&command.params, command.return_type.as_ref()))
+                        }
+                        ast::BackendMember::Include(_) => {}
+                    }
+                }
+            }
+            ast::TopLevelDecl::Contract(contract) => {
+                for method in &contract.methods {
+                    add(&method.name, callable(&method.params, method.return_type.as_ref()));
+                }
+            }
+            ast::TopLevelDecl::Scheme(scheme) => {
+                for member in &scheme.members {
+                    match member {
+                        ast::SchemeMember::Field(field) => add(&field.name, field.type_expr.to_string()),
+                        ast::SchemeMember::Virtual(field) => add(&field.name, field.type_expr.to_string()),
+                    }
+                }
+            }
+            ast::TopLevelDecl::Enum(en) => {
+                for (variant, payload) in &en.payloads {
+                    let payload: Vec<String> = payload.iter().map(ToString::to_string).collect();
+                    add(variant, format!("({})", payload.join(", ")));
+                }
+            }
+            ast::TopLevelDecl::Theme(theme) => {
+                for member in &theme.members {
+                    if let ast::ThemeMember::Field(field) = member {
+                        add(&field.name, field.type_expr.to_string());
+                    }
+                }
+            }
+            ast::TopLevelDecl::Arena(_) => {}
+        }
+    }
+    types
+}
+
 /// Extract exported declarations from the symbol table
 fn extract_exports(symbols: &SymbolTable) -> Vec<ExportedDecl> {
     symbols
@@ -187,7 +281,7 @@ scheme User {
         let result = build_signature(&module);
 
         // Register the signature
-        let mut registry = SignatureRegistry::new();
+        let registry = SignatureRegistry::new();
         registry.register(result.signature);
 
         // Verify we can look up imports
@@ -247,5 +341,13 @@ backend EditorBackend {
         let save = members.iter().find(|m| m.name == "save");
         assert!(save.is_some(), "Should have save command");
         assert_eq!(save.unwrap().kind, SymbolKind::Command);
+
+        assert_eq!(
+            sig.member_types,
+            [
+                ("EditorBackend.content".to_string(), "String".to_string()),
+                ("EditorBackend.save".to_string(), "fn()".to_string()),
+            ]
+        );
     }
 }
@@ -0,0 +1,290 @@
+// Persisted module signatures
+//
+// Signatures are saved one module per file, `<module path>.sig.json`, so a
+// cold start can fill a SignatureRegistry without re-analyzing every source:
+//
+//     {"version": 1, "hash": "9f0c2a...", "signature": { ... }}
+//
+// `hash` covers the serialized signature and catches truncated or edited
+// files. Loading a directory reads its files in parallel; a file that can't
+// be loaded gets its own diagnostic and doesn't stop the others.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::signature::{ModuleSignature, SignatureRegistry, SIGNATURE_VERSION};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::source::Span;
+use crate::vfs::{OutputSink, SourceProvider};
+
+/// File name suffix of persisted signatures
+pub const SIGNATURE_FILE_SUFFIX: &str = ".sig.json";
+
+const REBUILD_HELP: &str = "rebuild the module to regenerate its signature";
+
+/// Result of loading a directory of signature files
+#[derive(Debug, Default)]
+pub struct LoadDirResult {
+    /// Registry holding every signature that loaded
+    pub registry: SignatureRegistry,
+    /// Paths of the loaded modules, sorted
+    pub loaded: Vec<String>,
+    /// Diagnostics of the files that could not be loaded, sorted by file
+    pub errors: Vec<(PathBuf, Diagnostics)>,
+}
+
+impl SignatureRegistry {
+    /// Load all signature files in `dir`
+    ///
+    /// A missing directory loads nothing. Files are read and validated in
+    /// parallel; invalid ones are reported in `errors` and skipped.
+    pub fn load_dir(files: &dyn SourceProvider, dir: &Path) -> LoadDirResult {
+        let mut result = LoadDirResult::default();
+
+        let paths = match files.files(dir) {
+            Ok(paths) => paths,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return result,
+            Err(e) => {
+                let message = format!("cannot read signature directory: {}", e);
+                result.errors.push((
+                    dir.to_path_buf(),
+                    single(Diagnostic::error(message, Span::default())),
+                ));
+                return result;
+            }
+        };
+        let paths: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| path.parent() == Some(dir) && module_path_of(path).is_some())
+            .collect();
+
+        for (path, loaded) in load_parallel(files, &paths) {
+            match loaded {
+                Ok(signature) => {
+                    result.loaded.push(signature.path.clone());
+                    result.registry.register(signature);
+                }
+                Err(diagnostics) => result.errors.push((path, diagnostics)),
+            }
+        }
+        result.loaded.sort();
+        result
+    }
+}
+
+/// Path of the file a module's signature is saved to in `dir`
+pub fn signature_file_path(dir: &Path, module_path: &str) -> PathBuf {
+    dir.join(format!("{}{}", module_path, SIGNATURE_FILE_SUFFIX))
+}
+
+/// Save a module's signature in `dir`, creating the directory if needed
+pub fn save_signature(
+    output: &dyn OutputSink,
+    dir: &Path,
+    signature: &ModuleSignature,
+) -> io::Result<PathBuf> {
+    let value = serde_json::to_value(signature)?;
+    let file = serde_json::json!({
+        "version": SIGNATURE_VERSION,
+        "hash": content_hash(&value.to_string()),
+        "signature": value,
+    });
+
+    let path = signature_file_path(dir, &signature.path);
+    output.write(&path, &file.to_string())?;
+    Ok(path)
+}
+
+/// Read a signature file, checking its version, hash and module path
+pub fn load_signature(
+    files: &dyn SourceProvider,
+    path: &Path,
+) -> Result<ModuleSignature, Diagnostics> {
+    let error = |message: String| Diagnostic::error(message, Span::default());
+
+    let text = files
+        .read(path)
+        .map_err(|e| single(error(format!("cannot read signature file: {}", e))))?;
+    let mut file: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| single(error(format!("invalid signature file: {}", e))))?;
+
+    let version = file.get("version").and_then(|v| v.as_u64());
+    if version != Some(SIGNATURE_VERSION as u64) {
+        let found = version.map_or("none".to_string(), |v| v.to_string());
+        return Err(single(
+            error(format!(
+                "unsupported signature version {} (expected {})",
+                found, SIGNATURE_VERSION
+            ))
+            .with_help(REBUILD_HELP),
+        ));
+    }
+
+    let value = file
+        .get_mut("signature")
+        .map(serde_json::Value::take)
+        .unwrap_or_default();
+    let hash = file.get("hash").and_then(|h| h.as_str());
+    if hash != Some(content_hash(&value.to_string()).as_str()) {
+        return Err(single(
+            error("signature hash mismatch: the file is corrupted or was edited".to_string())
+                .with_help(REBUILD_HELP),
+        ));
+    }
+
+    let signature: ModuleSignature = serde_json::from_value(value)
+        .map_err(|e| single(error(format!("invalid signature: {}", e))))?;
+    if !signature.is_compatible() {
+        return Err(single(
+            error(format!(
+                "unsupported signature version {} (expected {})",
+                signature.version, SIGNATURE_VERSION
+            ))
+            .with_help(REBUILD_HELP),
+        ));
+    }
+    if module_path_of(path) != Some(signature.path.as_str()) {
+        return Err(single(error(format!(
+            "file holds the signature of module '{}'",
+            signature.path
+        ))));
+    }
+    Ok(signature)
+}
+
+/// Load files on all available cores, keeping the order of `paths`
+fn load_parallel(
+    files: &dyn SourceProvider,
+    paths: &[PathBuf],
+) -> Vec<(PathBuf, Result<ModuleSignature, Diagnostics>)> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = paths.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| (path.clone(), load_signature(files, path)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("signature loader panicked"))
+            .collect()
+    })
+}
+
+/// Module path encoded in a signature file name
+fn module_path_of(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_suffix(SIGNATURE_FILE_SUFFIX)
+        .filter(|module| !module.is_empty())
+}
+
+fn single(diagnostic: Diagnostic) -> Diagnostics {
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.add(diagnostic);
+    diagnostics
+}
+
+/// FNV-1a hash as hex; unlike `DefaultHasher` it is stable across Rust releases
+fn content_hash(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{build_signature, Module};
+
+    use crate::vfs::MemoryFs;
+
+    const DIR: &str = "/project/build/signatures";
+
+    fn signature(path: &str, source: &str) -> ModuleSignature {
+        let file = crate::parse_file(source).file.unwrap();
+        build_signature(&Module::from_files(path.to_string(), vec![file])).signature
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let (files, dir) = (MemoryFs::new(), Path::new(DIR));
+        let data = signature(
+            "test.data",
+            "module test.data\n\nscheme User {\n    name : String\n}\n",
+        );
+        let app = signature("test.app", "module test.app\n\nblueprint Main { }\n");
+        save_signature(&files, dir, &data).unwrap();
+        save_signature(&files, dir, &app).unwrap();
+        files.insert(dir.join("notes.txt"), "ignored");
+        files.insert(dir.join("old/test.old.sig.json"), "ignored");
+
+        let result = SignatureRegistry::load_dir(&files, dir);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.loaded, ["test.app", "test.data"]);
+        let user = result.registry.resolve_import("test.data", "User").unwrap();
+        assert_eq!(user.symbol_id, data.get_export("User").unwrap().symbol_id);
+
+    }
+
+    #[test]
+    fn test_load_dir_reports_invalid_files() {
+        let (files, dir) = (MemoryFs::new(), Path::new(DIR));
+        save_signature(&files, dir, &signature("test.ok", "module test.ok\n")).unwrap();
+
+        let edited =
+            save_signature(&files, dir, &signature("test.edited", "module test.edited\n")).unwrap();
+        let text = files.get(&edited).unwrap();
+        files.insert(&edited, text.replace("test.edited", "test.other"));
+
+        let old = save_signature(&files, dir, &signature("test.old", "module test.old\n")).unwrap();
+        let mut file: serde_json::Value =
+            serde_json::from_str(&files.get(&old).unwrap()).unwrap();
+        file["version"] = 0.into();
+        files.insert(&old, file.to_string());
+
+        files.insert(signature_file_path(dir, "test.broken"), "{\"version\":");
+
+        let moved = save_signature(&files, dir, &signature("test.moved", "module test.moved\n")).unwrap();
+        let contents = files.take(&moved).unwrap();
+        files.insert(signature_file_path(dir, "test.renamed"), contents);
+
+        let result = SignatureRegistry::load_dir(&files, dir);
+        assert_eq!(result.loaded, ["test.ok"]);
+        let errors: Vec<(String, &str)> = result
+            .errors
+            .iter()
+            .map(|(path, diags)| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, diags.iter().next().unwrap().message.as_str())
+            })
+            .collect();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert_eq!(errors[0].0, "test.broken.sig.json");
+        assert!(errors[0].1.starts_with("invalid signature file"));
+        assert!(errors[1].1.contains("hash mismatch"));
+        assert!(errors[2].1.starts_with("unsupported signature version 0"));
+        assert_eq!(
+            errors[3].1,
+            "file holds the signature of module 'test.moved'"
+        );
+
+    }
+
+    #[test]
+    fn test_load_missing_dir() {
+        let result = SignatureRegistry::load_dir(&MemoryFs::new(), Path::new(DIR));
+        assert!(result.registry.is_empty());
+        assert!(result.errors.is_empty());
+    }
+}
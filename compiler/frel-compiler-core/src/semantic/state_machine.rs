@@ -0,0 +1,588 @@
+// State machines implicit in Frel backends
+//
+// A backend field of an enum type is often the state of a small machine,
+// changed by the handlers of the blueprints using the backend:
+//
+//     enum Phase { Idle Loading Failed }
+//     backend Search { phase : Phase = Phase.Idle  command search() }
+//
+//     blueprint SearchView {
+//         with Search
+//         select on phase {
+//             Phase.Idle => button { "Search" } .. on_click { phase = Phase.Loading  search() }
+//             Phase.Failed => button { "Retry" } .. on_click { phase = Phase.Idle }
+//             else => spinner { }
+//         }
+//     }
+//
+// The states are the enum's variants and the initial state the field's
+// initializer. Each handler assigning the field a variant is a transition,
+// labelled with the blueprint, the event and the commands the handler calls.
+// It leaves the states the handler is shown in: the branches of a
+// `select on` the field or of a `when field == Enum.Variant` enclosing it,
+// any other state otherwise. Commands are implemented by the host, so the
+// states they set are not known.
+//
+// Only the declarations of one file are read: the backend, the backends it
+// includes, the enums of its fields and the blueprints `with` it.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::loading::blocks;
+use crate::ast::{self, BinaryOp, BlueprintStmt, ControlStmt, Expr, HandlerStmt, PostfixItem};
+use crate::source::Span;
+
+/// A backend field of an enum type and the handlers changing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateMachine {
+    pub backend: String,
+    pub field: String,
+    pub enum_name: String,
+    /// The enum's variants, in declaration order
+    pub states: Vec<String>,
+    /// The variant the field is initialized with
+    pub initial: Option<String>,
+    pub transitions: Vec<Transition>,
+}
+
+/// A handler assigning the field a variant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// States the handler is shown in, other than `to`
+    pub from: Vec<String>,
+    pub to: String,
+    /// Blueprint declaring the handler
+    pub blueprint: String,
+    /// Event name, or `shortcut` and the chord
+    pub trigger: String,
+    /// Commands the handler calls, in order
+    pub commands: Vec<String>,
+    pub span: Span,
+}
+
+impl Transition {
+    /// `SearchView.on_click / search()`
+    pub fn label(&self) -> String {
+        let mut label = format!("{}.{}", self.blueprint, self.trigger);
+        if !self.commands.is_empty() {
+            let calls: Vec<String> = self.commands.iter().map(|c| format!("{}()", c)).collect();
+            let _ = write!(label, " / {}", calls.join(", "));
+        }
+        label
+    }
+}
+
+impl StateMachine {
+    /// Distinct `(from, to, label)` edges of the transitions
+    pub fn edges(&self) -> Vec<(&str, &str, String)> {
+        let mut edges: Vec<(&str, &str, String)> = Vec::new();
+        for transition in &self.transitions {
+            for from in &transition.from {
+                let edge = (from.as_str(), transition.to.as_str(), transition.label());
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+        edges
+    }
+
+    /// States that are neither initial nor entered by a handler, so only a
+    /// command can set them; empty when the initial state is not known
+    pub fn unreachable(&self) -> Vec<&str> {
+        let Some(initial) = &self.initial else {
+            return Vec::new();
+        };
+        self.states
+            .iter()
+            .filter(|state| *state != initial && !self.transitions.iter().any(|t| t.to == **state))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Render as a Mermaid state diagram
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::new();
+        out.push_str("stateDiagram-v2\n");
+        let _ = writeln!(
+            out,
+            "    %% {}.{}: {}",
+            self.backend, self.field, self.enum_name
+        );
+        for state in &self.states {
+            let _ = writeln!(out, "    {}", state);
+        }
+        if let Some(initial) = &self.initial {
+            let _ = writeln!(out, "    [*] --> {}", initial);
+        }
+        for (from, to, label) in self.edges() {
+            let _ = writeln!(
+                out,
+                "    {} --> {}: {}",
+                from,
+                to,
+                label.replace(':', "#58;")
+            );
+        }
+        let unreachable = self.unreachable();
+        if !unreachable.is_empty() {
+            out.push_str("    classDef unreachable stroke-dasharray: 5 5\n");
+            let _ = writeln!(out, "    class {} unreachable", unreachable.join(","));
+        }
+        out
+    }
+
+    /// Render as a Graphviz DOT graph
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph \"{}.{}\" {{", self.backend, self.field);
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [shape=box, style=rounded, fontname=\"monospace\", fontsize=10];\n");
+        out.push_str("    edge [fontname=\"monospace\", fontsize=9];\n");
+
+        let unreachable = self.unreachable();
+        for state in &self.states {
+            if unreachable.contains(&state.as_str()) {
+                let _ = writeln!(out, "    \"{}\" [style=\"rounded,dashed\"];", state);
+            } else {
+                let _ = writeln!(out, "    \"{}\";", state);
+            }
+        }
+        if let Some(initial) = &self.initial {
+            out.push_str("    __start [shape=point];\n");
+            let _ = writeln!(out, "    __start -> \"{}\";", initial);
+        }
+        for (from, to, label) in self.edges() {
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                from,
+                to,
+                escape(&label)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The state machines of a backend's enum fields, in field order; `None`
+/// when the file declares no such backend
+pub fn extract_state_machines(file: &ast::File, backend: &str) -> Option<Vec<StateMachine>> {
+    let _profile = crate::profile::enter("state_machines");
+
+    let mut backends: HashMap<&str, &ast::Backend> = HashMap::new();
+    let mut enums: HashMap<&str, &ast::Enum> = HashMap::new();
+    for decl in &file.declarations {
+        match decl {
+            ast::TopLevelDecl::Backend(b) => {
+                backends.insert(&b.name, b);
+            }
+            ast::TopLevelDecl::Enum(e) => {
+                enums.insert(&e.name, e);
+            }
+            _ => {}
+        }
+    }
+    backends.get(backend)?;
+
+    let mut fields = Vec::new();
+    collect_fields(backend, &backends, &mut Vec::new(), &mut fields);
+
+    let blueprints: Vec<&ast::Blueprint> = file
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Blueprint(bp) => Some(bp),
+            _ => None,
+        })
+        .filter(|bp| {
+            bp.body
+                .iter()
+                .any(|stmt| matches!(stmt, BlueprintStmt::With(with) if with.backend == backend))
+        })
+        .collect();
+
+    let machines = fields
+        .into_iter()
+        .filter_map(|field| {
+            let ast::TypeExpr::Named(enum_name) = &field.type_expr else {
+                return None;
+            };
+            let decl = *enums.get(enum_name.as_str())?;
+            let mut walker = Walker {
+                field: &field.name,
+                decl,
+                blueprint: "",
+                transitions: Vec::new(),
+            };
+            for bp in &blueprints {
+                walker.blueprint = &bp.name;
+                walker.walk(&bp.body, None);
+            }
+            Some(StateMachine {
+                backend: backend.to_string(),
+                field: field.name.clone(),
+                enum_name: decl.name.clone(),
+                states: decl.variants.clone(),
+                initial: field
+                    .init
+                    .as_ref()
+                    .and_then(|init| variant(init, decl))
+                    .map(str::to_string),
+                transitions: walker.transitions,
+            })
+        })
+        .collect();
+    Some(machines)
+}
+
+/// Fields of a backend and of the backends it includes, once each
+fn collect_fields<'a>(
+    name: &'a str,
+    backends: &HashMap<&str, &'a ast::Backend>,
+    visited: &mut Vec<&'a str>,
+    fields: &mut Vec<&'a ast::Field>,
+) {
+    let Some(backend) = backends.get(name) else {
+        return;
+    };
+    if visited.contains(&name) {
+        return;
+    }
+    visited.push(name);
+    for member in &backend.members {
+        match member {
+            ast::BackendMember::Include(include) => {
+                collect_fields(include, backends, visited, fields)
+            }
+            ast::BackendMember::Field(field) => fields.push(field),
+            _ => {}
+        }
+    }
+}
+
+/// The variant of `decl` an expression names: `Variant`, `Enum.Variant`
+fn variant<'e>(expr: &'e Expr, decl: &ast::Enum) -> Option<&'e str> {
+    let name = match expr {
+        Expr::Identifier(name) => name.as_str(),
+        Expr::QualifiedName(parts) if parts.len() == 2 && parts[0] == decl.name => &parts[1],
+        Expr::FieldAccess { base, field, .. } if matches!(base.as_ref(), Expr::Identifier(name) if *name == decl.name) => {
+            field.as_str()
+        }
+        _ => return None,
+    };
+    decl.variants.iter().any(|v| v == name).then_some(name)
+}
+
+struct Walker<'a> {
+    field: &'a str,
+    decl: &'a ast::Enum,
+    blueprint: &'a str,
+    transitions: Vec<Transition>,
+}
+
+impl<'a> Walker<'a> {
+    /// Walk statements shown in `states`, or in any state when `None`
+    fn walk(&mut self, stmts: &'a [BlueprintStmt], states: Option<Vec<&'a str>>) {
+        for stmt in stmts {
+            match stmt {
+                BlueprintStmt::FragmentCreation(frag) => {
+                    for item in &frag.postfix {
+                        if let PostfixItem::EventHandler(handler) = item {
+                            self.handler(
+                                handler.event_name.clone(),
+                                &handler.body,
+                                handler.span,
+                                &states,
+                            );
+                        }
+                    }
+                }
+                BlueprintStmt::EventHandler(handler) => self.handler(
+                    handler.event_name.clone(),
+                    &handler.body,
+                    handler.span,
+                    &states,
+                ),
+                BlueprintStmt::Shortcut(shortcut) => self.handler(
+                    format!("shortcut {}", shortcut.chord),
+                    &shortcut.body,
+                    shortcut.span,
+                    &states,
+                ),
+                BlueprintStmt::Control(ControlStmt::When {
+                    condition,
+                    then_stmt,
+                    else_stmt,
+                }) => {
+                    let then_states = self.tested(condition);
+                    let else_states = then_states.as_ref().map(|states| self.others(states));
+                    self.walk(
+                        std::slice::from_ref(then_stmt.as_ref()),
+                        narrow(&states, then_states),
+                    );
+                    if let Some(else_stmt) = else_stmt {
+                        self.walk(
+                            std::slice::from_ref(else_stmt.as_ref()),
+                            narrow(&states, else_states),
+                        );
+                    }
+                    continue;
+                }
+                BlueprintStmt::Control(ControlStmt::Select {
+                    discriminant: Some(Expr::Identifier(name)),
+                    branches,
+                    else_branch,
+                    ..
+                }) if name == self.field => {
+                    let mut matched = Vec::new();
+                    for branch in branches {
+                        // A guard may be true in any state
+                        let branch_states = variant(&branch.condition, self.decl).map(|v| vec![v]);
+                        matched.extend(branch_states.iter().flatten().copied());
+                        self.walk(
+                            std::slice::from_ref(branch.body.as_ref()),
+                            narrow(&states, branch_states),
+                        );
+                    }
+                    if let Some(else_branch) = else_branch {
+                        let else_states = Some(self.others(&matched));
+                        self.walk(
+                            std::slice::from_ref(else_branch.as_ref()),
+                            narrow(&states, else_states),
+                        );
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            for block in blocks(stmt) {
+                self.walk(block, states.clone());
+            }
+        }
+    }
+
+    /// Record a handler's transition, if it assigns the field a variant
+    fn handler(
+        &mut self,
+        trigger: String,
+        body: &[HandlerStmt],
+        span: Span,
+        states: &Option<Vec<&str>>,
+    ) {
+        // The last assignment is the state the handler leaves the field in
+        let to = body.iter().rev().find_map(|stmt| match stmt {
+            HandlerStmt::Assignment { name, value } if name == self.field => Some(value),
+            _ => None,
+        });
+        let Some(to) = to.and_then(|value| variant(value, self.decl)) else {
+            return;
+        };
+        let from: Vec<String> = match states {
+            Some(states) => states.iter().map(|s| s.to_string()).collect(),
+            None => self
+                .decl
+                .variants
+                .iter()
+                .filter(|v| *v != to)
+                .cloned()
+                .collect(),
+        };
+        // A handler shown in no state never runs
+        if from.is_empty() {
+            return;
+        }
+        let commands = body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                HandlerStmt::CommandCall { name, .. } => Some(name.clone()),
+                HandlerStmt::Assignment { .. } => None,
+            })
+            .collect();
+        self.transitions.push(Transition {
+            from,
+            to: to.to_string(),
+            blueprint: self.blueprint.to_string(),
+            trigger,
+            commands,
+            span,
+        });
+    }
+
+    /// The states in which a `when` condition is true, if it compares the
+    /// field with a variant
+    fn tested(&self, condition: &Expr) -> Option<Vec<&'a str>> {
+        let Expr::Binary { op, left, right } = condition else {
+            return None;
+        };
+        let tested = match (left.as_ref(), right.as_ref()) {
+            (Expr::Identifier(name), value) | (value, Expr::Identifier(name))
+                if name == self.field =>
+            {
+                variant(value, self.decl)?
+            }
+            _ => return None,
+        };
+        let tested = self.decl.variants.iter().find(|v| *v == tested)?.as_str();
+        match op {
+            BinaryOp::Eq => Some(vec![tested]),
+            BinaryOp::Ne => Some(self.others(&[tested])),
+            _ => None,
+        }
+    }
+
+    /// The variants other than `states`
+    fn others(&self, states: &[&str]) -> Vec<&'a str> {
+        self.decl
+            .variants
+            .iter()
+            .map(String::as_str)
+            .filter(|v| !states.contains(v))
+            .collect()
+    }
+}
+
+/// The states of the enclosing statements that are also in `inner`
+fn narrow<'a>(outer: &Option<Vec<&'a str>>, inner: Option<Vec<&'a str>>) -> Option<Vec<&'a str>> {
+    match (outer, inner) {
+        (outer, None) => outer.clone(),
+        (None, inner) => inner,
+        (Some(outer), Some(inner)) => Some(
+            outer
+                .iter()
+                .copied()
+                .filter(|s| inner.contains(s))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+module test
+
+enum Phase { Idle Loading Ready Failed Archived }
+
+backend Search {
+    query : String = ""
+    phase : Phase = Phase.Idle
+    command search(text : String)
+    command cancel()
+}
+
+blueprint SearchView {
+    with Search
+    select on phase {
+        Phase.Idle => button { "Search" } .. on_click { phase = Phase.Loading  search(query) }
+        Phase.Loading => button { "Cancel" } .. on_click { cancel()  phase = Phase.Idle }
+        else => column {
+            when phase == Phase.Failed {
+                button { "Retry" } .. on_click { phase = Phase.Loading  search(query) }
+            }
+        }
+    }
+    shortcut "Escape" { phase = Phase.Idle }
+}
+"#;
+
+    fn machine(source: &str) -> StateMachine {
+        let file = crate::parse_file(source).file.expect("parse failed");
+        let mut machines = extract_state_machines(&file, "Search").expect("no backend");
+        assert_eq!(machines.len(), 1);
+        machines.remove(0)
+    }
+
+    #[test]
+    fn test_extract_state_machine() {
+        let machine = machine(SOURCE);
+        assert_eq!(machine.field, "phase");
+        assert_eq!(machine.enum_name, "Phase");
+        assert_eq!(machine.initial.as_deref(), Some("Idle"));
+
+        let edges: Vec<(&str, &str, String)> = machine.edges();
+        let expected = [
+            ("Idle", "Loading", "SearchView.on_click / search()"),
+            ("Loading", "Idle", "SearchView.on_click / cancel()"),
+            ("Failed", "Loading", "SearchView.on_click / search()"),
+            ("Loading", "Idle", "SearchView.shortcut Escape"),
+            ("Ready", "Idle", "SearchView.shortcut Escape"),
+            ("Failed", "Idle", "SearchView.shortcut Escape"),
+            ("Archived", "Idle", "SearchView.shortcut Escape"),
+        ];
+        assert_eq!(
+            edges,
+            expected
+                .iter()
+                .map(|(from, to, label)| (*from, *to, label.to_string()))
+                .collect::<Vec<_>>()
+        );
+        // The host sets the others, by the commands
+        assert_eq!(machine.unreachable(), ["Ready", "Failed", "Archived"]);
+    }
+
+    #[test]
+    fn test_export() {
+        let machine = machine(SOURCE);
+        let mermaid = machine.to_mermaid();
+        assert!(mermaid.starts_with("stateDiagram-v2\n    %% Search.phase: Phase\n"));
+        assert!(mermaid.contains("    [*] --> Idle\n"));
+        assert!(mermaid.contains("    Idle --> Loading: SearchView.on_click / search()\n"));
+        assert!(mermaid.contains("    class Ready,Failed,Archived unreachable\n"));
+
+        let dot = machine.to_dot();
+        assert!(dot.starts_with("digraph \"Search.phase\" {\n"));
+        assert!(dot.contains("    __start -> \"Idle\";\n"));
+        assert!(dot.contains("    \"Archived\" [style=\"rounded,dashed\"];\n"));
+        assert!(dot.contains(
+            "    \"Failed\" -> \"Loading\" [label=\"SearchView.on_click / search()\"];\n"
+        ));
+    }
+
+    #[test]
+    fn test_unrelated_handlers() {
+        let machine = machine(
+            r#"
+module test
+
+enum Mode { View Edit }
+
+backend Base {
+    mode : Mode = Mode.View
+}
+
+backend Search {
+    include Base
+    level : i32 = 0
+}
+
+blueprint Other {
+    text { "" } .. on_click { mode = Mode.Edit }
+}
+
+blueprint Main {
+    with Search
+    button { "Edit" } .. on_click { mode = other }
+    button { "Up" } .. on_click { level = level + 1 }
+    when mode == Mode.Edit {
+        when mode == Mode.View {
+            button { "Never" } .. on_click { mode = Mode.Edit }
+        }
+    }
+}
+"#,
+        );
+        assert_eq!(machine.backend, "Search");
+        assert_eq!(machine.field, "mode");
+        assert!(machine.transitions.is_empty());
+        assert_eq!(machine.unreachable(), ["Edit"]);
+    }
+}
@@ -0,0 +1,290 @@
+// Unused theme members for Frel
+//
+// Theme members are read from outside their theme through a reference:
+// `theme.padding`, `.. theme.button_style`, `AppTheme.Dark`. Expressions carry
+// no spans, so, as for renaming, these reads are found from the tokens of the
+// sources: every identifier after `.` or `?.` outside a theme declaration is a
+// member name read somewhere in the project.
+//
+// A member is used when its name is read, or when a used member of the same
+// theme refers to it: fields read by the initializers of used fields, by used
+// instruction sets and by the overrides of used variants, and sets applied by
+// used sets. Members are matched by name only, so a member is kept whenever a
+// member of that name is read on any type; what is stripped is never used.
+
+use std::collections::{BTreeSet, HashSet};
+
+use serde::Serialize;
+
+use crate::ast::{self, Expr, TemplateElement, ThemeMember};
+use crate::lexer::{Lexer, TokenKind};
+use crate::source::Span;
+
+/// Member names read in the sources of a project
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ThemeUsage {
+    read: BTreeSet<String>,
+}
+
+impl ThemeUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the member names read in a source file outside its theme declarations
+    pub fn add_source(&mut self, source: &str, file: &ast::File) {
+        let themes: Vec<Span> = file
+            .declarations
+            .iter()
+            .filter_map(|decl| match decl {
+                ast::TopLevelDecl::Theme(theme) => Some(theme.span),
+                _ => None,
+            })
+            .collect();
+        let in_theme = |span: Span| themes.iter().any(|t| t.start <= span.start && span.end <= t.end);
+
+        let (tokens, _) = Lexer::new(source).tokenize();
+        for pair in tokens.windows(2) {
+            let (before, token) = (&pair[0], &pair[1]);
+            if token.kind == TokenKind::Identifier
+                && matches!(before.kind, TokenKind::Dot | TokenKind::QuestionDot)
+                && !in_theme(token.span)
+            {
+                self.read.insert(token.span.text(source).to_string());
+            }
+        }
+    }
+
+    /// Whether a member of this name is read anywhere
+    pub fn is_read(&self, name: &str) -> bool {
+        self.read.contains(name)
+    }
+}
+
+/// Kind of a theme member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMemberKind {
+    Field,
+    InstructionSet,
+    Variant,
+}
+
+impl ThemeMemberKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThemeMemberKind::Field => "field",
+            ThemeMemberKind::InstructionSet => "instruction set",
+            ThemeMemberKind::Variant => "variant",
+        }
+    }
+}
+
+/// A theme member no blueprint or declaration uses
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct UnusedMember {
+    /// Qualified name of the theme, e.g. `app.AppTheme`
+    pub theme: String,
+    pub name: String,
+    pub kind: ThemeMemberKind,
+}
+
+/// Members of the themes of `file` that are not used, in declaration order
+pub fn unused_theme_members(file: &ast::File, usage: &ThemeUsage) -> Vec<UnusedMember> {
+    let mut unused = Vec::new();
+    for decl in &file.declarations {
+        let ast::TopLevelDecl::Theme(theme) = decl else {
+            continue;
+        };
+        let used = used_members(theme, usage);
+        for member in &theme.members {
+            let (name, kind) = match member {
+                ThemeMember::Field(field) => (&field.name, ThemeMemberKind::Field),
+                ThemeMember::InstructionSet(set) => (&set.name, ThemeMemberKind::InstructionSet),
+                ThemeMember::Variant(variant) => (&variant.name, ThemeMemberKind::Variant),
+                ThemeMember::Include(_) => continue,
+            };
+            if !used.contains(name.as_str()) {
+                unused.push(UnusedMember {
+                    theme: format!("{}.{}", file.module, theme.name),
+                    name: name.clone(),
+                    kind,
+                });
+            }
+        }
+    }
+    unused
+}
+
+/// Names of the members of `theme` that are read, directly or through other used members
+fn used_members<'a>(theme: &'a ast::Theme, usage: &ThemeUsage) -> HashSet<&'a str> {
+    let mut used: HashSet<&str> = theme
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            ThemeMember::Field(field) => Some(field.name.as_str()),
+            ThemeMember::InstructionSet(set) => Some(set.name.as_str()),
+            ThemeMember::Variant(variant) => Some(variant.name.as_str()),
+            ThemeMember::Include(_) => None,
+        })
+        .filter(|name| usage.is_read(name))
+        .collect();
+
+    loop {
+        let mut referenced: HashSet<&str> = HashSet::new();
+        for member in &theme.members {
+            match member {
+                ThemeMember::Field(field) if used.contains(field.name.as_str()) => {
+                    if let Some(init) = &field.init {
+                        names_in(init, &mut referenced);
+                    }
+                }
+                ThemeMember::InstructionSet(set) if used.contains(set.name.as_str()) => {
+                    for instruction in &set.instructions {
+                        referenced.insert(&instruction.name);
+                        for (_, param) in &instruction.params {
+                            names_in(param, &mut referenced);
+                        }
+                    }
+                }
+                ThemeMember::Variant(variant) if used.contains(variant.name.as_str()) => {
+                    for (field, value) in &variant.overrides {
+                        if used.contains(field.as_str()) {
+                            names_in(value, &mut referenced);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let before = used.len();
+        used.extend(referenced);
+        if used.len() == before {
+            return used;
+        }
+    }
+}
+
+/// Names an expression refers to directly, without member accesses
+fn names_in<'a>(expr: &'a Expr, names: &mut HashSet<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => {
+            names.insert(name);
+        }
+        Expr::QualifiedName(parts) => {
+            if let Some(first) = parts.first() {
+                names.insert(first);
+            }
+        }
+        Expr::StringTemplate(elements) => {
+            for element in elements {
+                if let TemplateElement::Interpolation(inner) = element {
+                    names_in(inner, names);
+                }
+            }
+        }
+        Expr::List(items) => items.iter().for_each(|item| names_in(item, names)),
+        Expr::Object(fields) => fields.iter().for_each(|(_, value)| names_in(value, names)),
+        Expr::Binary { left, right, .. } => {
+            names_in(left, names);
+            names_in(right, names);
+        }
+        Expr::Unary { expr, .. } => names_in(expr, names),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            names_in(condition, names);
+            names_in(then_expr, names);
+            names_in(else_expr, names);
+        }
+        Expr::FieldAccess { base, .. } | Expr::OptionalChain { base, .. } => names_in(base, names),
+        Expr::Call { callee, args } => {
+            names_in(callee, names);
+            args.iter().for_each(|arg| names_in(arg, names));
+        }
+        Expr::Range { start, end, .. } => {
+            names_in(start, names);
+            names_in(end, names);
+        }
+        Expr::Lambda { body, .. } => names_in(body, names),
+        Expr::Null
+        | Expr::Bool(_)
+        | Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Color(_)
+        | Expr::String(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unused_theme_members() {
+        let theme = r#"module app.theme
+
+theme AppTheme {
+    base : u32 = 16
+    padding : u32 = base * 2
+    radius : u32 = 8
+    shadow : u32 = 4
+
+    set frame {
+        corner_radius { radius }
+    }
+    set card {
+        frame
+    }
+    set banner {
+        padding { shadow }
+    }
+
+    variant Compact {
+        padding = 8
+        shadow = 2
+    }
+    variant Dense {
+        radius = 2
+    }
+}
+"#;
+        let page = r#"module app
+
+import app.theme.AppTheme
+
+backend Look {
+    theme : ref AppTheme = AppTheme.Compact
+}
+
+blueprint Page {
+    with Look
+    box { } .. theme.card .. padding { theme.padding }
+}
+"#;
+        let mut usage = ThemeUsage::new();
+        let mut files = Vec::new();
+        for source in [theme, page] {
+            let result = crate::parse_file(source);
+            assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+            let file = result.file.unwrap();
+            usage.add_source(source, &file);
+            files.push(file);
+        }
+
+        let unused: Vec<_> = unused_theme_members(&files[0], &usage)
+            .into_iter()
+            .map(|member| (member.name, member.kind))
+            .collect();
+        assert_eq!(
+            unused,
+            [
+                ("shadow".to_string(), ThemeMemberKind::Field),
+                ("banner".to_string(), ThemeMemberKind::InstructionSet),
+                ("Dense".to_string(), ThemeMemberKind::Variant),
+            ]
+        );
+    }
+}
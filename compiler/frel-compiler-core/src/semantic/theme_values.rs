@@ -0,0 +1,413 @@
+// Theme value resolution for Frel
+//
+// This module provides:
+// - evaluate_theme: Resolve the final value of every field of a theme (or one
+//   of its variants) using constant evaluation
+// - ThemeValues: The resulting typed map, for tools such as design plugins
+//   and previews that need concrete colors, sizes and strings
+//
+// Included themes are flattened the same way the language does: their fields
+// and variants become part of the including theme, and a theme included
+// through several paths is only included once.
+
+use super::const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
+use super::types::Type;
+use crate::ast::{self, Expr, ThemeMember, TypeExpr};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A theme field's final value together with its declared type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThemeValue {
+    pub ty: Type,
+    pub value: ConstValue,
+}
+
+/// Evaluated fields of a theme or theme variant
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeValues {
+    /// Theme name
+    pub theme: String,
+    /// Variant whose overrides were applied, if any
+    pub variant: Option<String>,
+    /// Fields that evaluated successfully, in declaration order
+    pub fields: IndexMap<String, ThemeValue>,
+    /// Fields that could not be evaluated, in declaration order
+    #[serde(skip)]
+    pub errors: IndexMap<String, ConstEvalError>,
+}
+
+impl ThemeValues {
+    /// Get the value of a field
+    pub fn get(&self, name: &str) -> Option<&ConstValue> {
+        self.fields.get(name).map(|field| &field.value)
+    }
+
+    /// Check if every field evaluated successfully
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Reasons a theme cannot be evaluated at all
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeEvalError {
+    /// No theme with this name is declared in the file
+    UnknownTheme(String),
+    /// The theme includes a theme not declared in the file
+    UnknownInclude { theme: String, include: String },
+    /// Neither the theme nor its includes declare this variant
+    UnknownVariant { theme: String, variant: String },
+}
+
+impl fmt::Display for ThemeEvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeEvalError::UnknownTheme(name) => write!(f, "theme '{}' not found", name),
+            ThemeEvalError::UnknownInclude { theme, include } => {
+                write!(f, "theme '{}' includes unknown theme '{}'", theme, include)
+            }
+            ThemeEvalError::UnknownVariant { theme, variant } => {
+                write!(f, "theme '{}' has no variant '{}'", theme, variant)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeEvalError {}
+
+/// Evaluate the fields of a theme declared in `file`
+///
+/// With a variant, its overrides replace the initializers of the fields they
+/// name. Asset fields only have a value when a variant provides one.
+pub fn evaluate_theme(
+    file: &ast::File,
+    theme: &str,
+    variant: Option<&str>,
+) -> Result<ThemeValues, ThemeEvalError> {
+    let themes: HashMap<&str, &ast::Theme> = file
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            ast::TopLevelDecl::Theme(theme) => Some((theme.name.as_str(), theme)),
+            _ => None,
+        })
+        .collect();
+
+    let root = themes
+        .get(theme)
+        .copied()
+        .ok_or_else(|| ThemeEvalError::UnknownTheme(theme.to_string()))?;
+    let mut flat = FlatTheme::default();
+    flatten(root, &themes, &mut HashSet::new(), &mut flat)?;
+
+    let mut env = ThemeEnv {
+        fields: IndexMap::new(),
+        done: HashMap::new(),
+        in_progress: HashSet::new(),
+    };
+    for field in &flat.fields {
+        env.fields
+            .insert(field.name.clone(), (field_type(field), field.init.clone()));
+    }
+
+    let mut errors = IndexMap::new();
+    if let Some(variant) = variant {
+        let overrides =
+            flat.variants
+                .get(variant)
+                .ok_or_else(|| ThemeEvalError::UnknownVariant {
+                    theme: theme.to_string(),
+                    variant: variant.to_string(),
+                })?;
+        for (name, expr) in overrides {
+            match env.fields.get_mut(name) {
+                Some((_, init)) => *init = Some(expr.clone()),
+                None => {
+                    errors.insert(name.clone(), ConstEvalError::UnknownName(name.clone()));
+                }
+            }
+        }
+    }
+
+    let mut fields = IndexMap::new();
+    let names: Vec<String> = env.fields.keys().cloned().collect();
+    for name in names {
+        let ty = env.fields[&name].0.clone();
+        match env.lookup(&name) {
+            Ok(value) => {
+                fields.insert(name, ThemeValue { ty, value });
+            }
+            Err(error) => {
+                errors.insert(name, error);
+            }
+        }
+    }
+
+    Ok(ThemeValues {
+        theme: theme.to_string(),
+        variant: variant.map(String::from),
+        fields,
+        errors,
+    })
+}
+
+/// A theme's fields and variants with includes expanded
+#[derive(Default)]
+struct FlatTheme<'a> {
+    fields: Vec<&'a ast::ThemeField>,
+    variants: IndexMap<String, Vec<(String, Expr)>>,
+}
+
+fn flatten<'a>(
+    theme: &'a ast::Theme,
+    themes: &HashMap<&str, &'a ast::Theme>,
+    visited: &mut HashSet<String>,
+    flat: &mut FlatTheme<'a>,
+) -> Result<(), ThemeEvalError> {
+    // Diamond includes (and include cycles) are only expanded once
+    if !visited.insert(theme.name.clone()) {
+        return Ok(());
+    }
+
+    for member in &theme.members {
+        match member {
+            ThemeMember::Include(name) => {
+                let included =
+                    themes
+                        .get(name.as_str())
+                        .ok_or_else(|| ThemeEvalError::UnknownInclude {
+                            theme: theme.name.clone(),
+                            include: name.clone(),
+                        })?;
+                flatten(included, themes, visited, flat)?;
+            }
+            ThemeMember::Field(field) => flat.fields.push(field),
+            ThemeMember::Variant(variant) => flat
+                .variants
+                .entry(variant.name.clone())
+                .or_default()
+                .extend(variant.overrides.iter().cloned()),
+            ThemeMember::InstructionSet(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Lazily evaluates theme fields, following references between them
+struct ThemeEnv {
+    /// Field name -> (declared type, initializer after variant overrides)
+    fields: IndexMap<String, (Type, Option<Expr>)>,
+    done: HashMap<String, Result<ConstValue, ConstEvalError>>,
+    in_progress: HashSet<String>,
+}
+
+impl ConstEnv for ThemeEnv {
+    fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+        if let Some(result) = self.done.get(name) {
+            return result.clone();
+        }
+        let Some((ty, init)) = self.fields.get(name).cloned() else {
+            return Err(ConstEvalError::UnknownName(name.to_string()));
+        };
+        if !self.in_progress.insert(name.to_string()) {
+            return Err(ConstEvalError::Cycle(name.to_string()));
+        }
+
+        let result = match init {
+            Some(expr) => eval_const(&expr, self).and_then(|value| conform(value, &ty)),
+            None => Err(ConstEvalError::NoValue(name.to_string())),
+        };
+
+        self.in_progress.remove(name);
+        self.done.insert(name.to_string(), result.clone());
+        result
+    }
+}
+
+/// Resolve a field's declared type, wrapping asset fields in `Asset`
+fn field_type(field: &ast::ThemeField) -> Type {
+    let ty = type_of(&field.type_expr);
+    if field.is_asset {
+        Type::Asset(Box::new(ty))
+    } else {
+        ty
+    }
+}
+
+/// Resolve a type expression (user-defined types become `Unknown`)
+fn type_of(type_expr: &TypeExpr) -> Type {
+    match type_expr {
+        TypeExpr::Named(name) => Type::from_intrinsic_name(name).unwrap_or(Type::Unknown),
+        TypeExpr::Nullable(inner) => Type::Nullable(Box::new(type_of(inner))),
+        TypeExpr::Asset(inner) => Type::Asset(Box::new(type_of(inner))),
+        TypeExpr::List(inner) => Type::List(Box::new(type_of(inner))),
+        _ => Type::Unknown,
+    }
+}
+
+/// Check a value against its declared type, converting where the language does
+///
+/// Integers widen to floats, and integer literals such as `0x1E1E1E` are
+/// accepted as opaque RGB colors.
+fn conform(value: ConstValue, ty: &Type) -> Result<ConstValue, ConstEvalError> {
+    let mismatch = |value: &ConstValue| {
+        Err(ConstEvalError::TypeMismatch(format!(
+            "expected {}, found {} {}",
+            ty,
+            value.kind(),
+            value
+        )))
+    };
+
+    match (ty, value) {
+        (Type::Asset(inner), value) => conform(value, inner),
+        (Type::Nullable(_), ConstValue::Null) => Ok(ConstValue::Null),
+        (Type::Nullable(inner), value) => conform(value, inner),
+        (Type::List(inner), ConstValue::List(items)) => items
+            .into_iter()
+            .map(|item| conform(item, inner))
+            .collect::<Result<_, _>>()
+            .map(ConstValue::List),
+        (Type::Bool, value @ ConstValue::Bool(_)) => Ok(value),
+        (ty, ConstValue::Int(i)) if ty.is_integer() => {
+            if int_in_range(ty, i) {
+                Ok(ConstValue::Int(i))
+            } else {
+                Err(ConstEvalError::TypeMismatch(format!(
+                    "{} is out of range for {}",
+                    i, ty
+                )))
+            }
+        }
+        (ty, ConstValue::Int(i)) if ty.is_float() || *ty == Type::Decimal => {
+            Ok(ConstValue::Float(i as f64))
+        }
+        (ty, value @ ConstValue::Float(_)) if ty.is_float() || *ty == Type::Decimal => Ok(value),
+        (Type::Color, value @ ConstValue::Color(_)) => Ok(value),
+        (Type::Color, ConstValue::Int(rgb)) if (0..=0xFF_FFFF).contains(&rgb) => {
+            Ok(ConstValue::Color(((rgb as u32) << 8) | 0xFF))
+        }
+        (ty, value @ ConstValue::String(_))
+            if ty.is_text() || matches!(ty, Type::Url | Type::Graphics) =>
+        {
+            Ok(value)
+        }
+        (Type::Unknown, value) => Ok(value),
+        (_, value) => mismatch(&value),
+    }
+}
+
+fn int_in_range(ty: &Type, i: i64) -> bool {
+    match ty {
+        Type::I8 => i8::try_from(i).is_ok(),
+        Type::I16 => i16::try_from(i).is_ok(),
+        Type::I32 => i32::try_from(i).is_ok(),
+        Type::U8 => u8::try_from(i).is_ok(),
+        Type::U16 => u16::try_from(i).is_ok(),
+        Type::U32 => u32::try_from(i).is_ok(),
+        Type::U64 => i >= 0,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEMES: &str = r#"module test.themes
+
+theme BaseTheme {
+    padding : u32 = 16
+    cornerRadius : u32 = padding / 2
+
+    variant Compact {
+        padding = 8
+    }
+}
+
+theme AppTheme {
+    include BaseTheme
+
+    primaryColor : asset Color
+    title : asset String
+    scale : f64 = 1
+    label : String = "Pad ${padding}"
+
+    variant Dark {
+        primaryColor = #4A90D9
+        title = "Dark"
+    }
+}
+"#;
+
+    fn file() -> ast::File {
+        crate::parse_file(THEMES).file.expect("themes should parse")
+    }
+
+    #[test]
+    fn test_base_values() {
+        let values = evaluate_theme(&file(), "AppTheme", None).unwrap();
+
+        assert_eq!(values.get("padding"), Some(&ConstValue::Int(16)));
+        assert_eq!(values.get("cornerRadius"), Some(&ConstValue::Int(8)));
+        assert_eq!(values.get("scale"), Some(&ConstValue::Float(1.0)));
+        assert_eq!(
+            values.get("label"),
+            Some(&ConstValue::String("Pad 16".to_string()))
+        );
+        assert_eq!(values.fields["padding"].ty, Type::U32);
+
+        // Asset fields only get values from variants
+        assert_eq!(
+            values.errors.get("primaryColor"),
+            Some(&ConstEvalError::NoValue("primaryColor".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_variant_overrides() {
+        let dark = evaluate_theme(&file(), "AppTheme", Some("Dark")).unwrap();
+        assert!(dark.is_complete());
+        assert_eq!(
+            dark.get("primaryColor"),
+            Some(&ConstValue::Color(0x4A90D9FF))
+        );
+        assert_eq!(
+            dark.fields["primaryColor"].ty,
+            Type::Asset(Box::new(Type::Color))
+        );
+
+        // Variants of included themes apply, and dependent fields follow
+        let compact = evaluate_theme(&file(), "AppTheme", Some("Compact")).unwrap();
+        assert_eq!(compact.get("cornerRadius"), Some(&ConstValue::Int(4)));
+        assert_eq!(
+            compact.get("label"),
+            Some(&ConstValue::String("Pad 8".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            evaluate_theme(&file(), "Missing", None).unwrap_err(),
+            ThemeEvalError::UnknownTheme("Missing".to_string())
+        );
+        assert!(matches!(
+            evaluate_theme(&file(), "AppTheme", Some("Light")),
+            Err(ThemeEvalError::UnknownVariant { .. })
+        ));
+
+        let source = "module t\ntheme T {\n    a : u8 = b\n    b : u8 = a\n    c : u8 = 300\n}\n";
+        let file = crate::parse_file(source).file.unwrap();
+        let values = evaluate_theme(&file, "T", None).unwrap();
+        assert!(matches!(values.errors["a"], ConstEvalError::Cycle(_)));
+        assert!(matches!(
+            values.errors["c"],
+            ConstEvalError::TypeMismatch(_)
+        ));
+    }
+}
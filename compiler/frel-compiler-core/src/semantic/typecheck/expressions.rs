@@ -2,28 +2,54 @@
 //
 // This module handles inferring and checking types of expressions.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ast;
-use crate::diagnostic::{codes, Diagnostic, Diagnostics};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, Suggestion};
 use crate::source::Span;
 
-use super::super::scope::{ScopeGraph, ScopeId};
-use super::super::symbol::{SymbolId, SymbolTable};
+use super::super::builtins;
+use super::super::members;
+use super::super::const_eval::{eval_const, qualified_path, ConstEvalError, EmptyEnv};
+use super::super::scope::{ScopeGraph, ScopeId, ScopeKind};
+use super::super::symbol::{SymbolId, SymbolKind, SymbolTable};
 use super::super::types::Type;
 use super::operators::{
-    expect_bool, infer_binary_op_type, infer_unary_op_type, types_compatible,
+    common_numeric_type, expect_bool, expect_integer, infer_binary_op_type, infer_unary_op_type,
+    types_compatible,
 };
-use super::resolution::lookup_identifier_type;
+use super::narrowing::non_null_when;
+use super::resolution::{lookup_identifier_type, TypeResolver};
 
 /// Expression type checker
 pub struct ExprChecker<'a> {
     pub scopes: &'a ScopeGraph,
     pub symbols: &'a SymbolTable,
     pub symbol_types: &'a HashMap<SymbolId, Type>,
+    /// Imported names (name -> module path), which shadow built-in functions
+    pub imports: &'a HashMap<String, String>,
     pub current_scope: ScopeId,
     pub context_span: Span,
+    /// Paths known not to be null here (see `narrowing`)
+    pub narrowed: HashSet<String>,
+    /// Arguments methods and commands of the file need, without those with
+    /// defaults; calls of the others are only checked for too many arguments
+    pub required_args: Option<&'a HashMap<SymbolId, usize>>,
+    /// Whether the expression is in an event handler, where commands can be called
+    pub in_handler: bool,
+    /// Types of the parameters of the lambdas being checked
+    pub lambda_params: HashMap<SymbolId, Type>,
     pub expr_types: HashMap<Span, Type>,
+    /// Identifiers checked, by statement span and name: the symbol each names
+    /// and its type there
+    pub names: HashMap<(Span, String), (SymbolId, Type)>,
+    pub diagnostics: Diagnostics,
+}
+
+/// What an `ExprChecker` found, to merge into the type checker's results
+pub struct Checked {
+    pub expr_types: HashMap<Span, Type>,
+    pub names: HashMap<(Span, String), (SymbolId, Type)>,
     pub diagnostics: Diagnostics,
 }
 
@@ -32,6 +58,7 @@ impl<'a> ExprChecker<'a> {
         scopes: &'a ScopeGraph,
         symbols: &'a SymbolTable,
         symbol_types: &'a HashMap<SymbolId, Type>,
+        imports: &'a HashMap<String, String>,
         current_scope: ScopeId,
         context_span: Span,
     ) -> Self {
@@ -39,9 +66,15 @@ impl<'a> ExprChecker<'a> {
             scopes,
             symbols,
             symbol_types,
+            imports,
             current_scope,
             context_span,
+            narrowed: HashSet::new(),
+            required_args: None,
+            in_handler: false,
+            lambda_params: HashMap::new(),
             expr_types: HashMap::new(),
+            names: HashMap::new(),
             diagnostics: Diagnostics::new(),
         }
     }
@@ -54,8 +87,11 @@ impl<'a> ExprChecker<'a> {
         match expr {
             // For empty lists, use the expected element type
             ast::Expr::List(items) if items.is_empty() => {
-                if let Type::List(elem_ty) = expected {
-                    let ty = Type::List(elem_ty.clone());
+                if let Type::List(elem_ty) | Type::Set(elem_ty) = expected {
+                    let ty = match expected {
+                        Type::Set(_) => Type::Set(elem_ty.clone()),
+                        _ => Type::List(elem_ty.clone()),
+                    };
                     self.expr_types.insert(self.context_span, ty.clone());
                     ty
                 } else {
@@ -65,6 +101,25 @@ impl<'a> ExprChecker<'a> {
                     ty
                 }
             }
+            // A list literal where a set is expected is a set literal
+            ast::Expr::List(_) if matches!(expected, Type::Set(_)) => match self.infer_expr_type(expr) {
+                Type::List(elem_ty) => {
+                    let ty = Type::Set(elem_ty);
+                    self.expr_types.insert(self.context_span, ty.clone());
+                    ty
+                }
+                ty => ty,
+            },
+            // Lambdas take their parameter types from the expected function type
+            ast::Expr::Lambda { params, body, span } => {
+                let expected = match expected.nullable_inner().unwrap_or(expected) {
+                    Type::Function { params, ret } => Some((params.as_slice(), ret.as_ref())),
+                    _ => None,
+                };
+                let ty = self.check_lambda(params, body, *span, expected);
+                self.expr_types.insert(self.context_span, ty.clone());
+                ty
+            }
             // For null, use the expected nullable inner type
             ast::Expr::Null => {
                 let ty = if let Type::Nullable(inner) = expected {
@@ -75,8 +130,45 @@ impl<'a> ExprChecker<'a> {
                 self.expr_types.insert(self.context_span, ty.clone());
                 ty
             }
-            // For other expressions, infer normally
-            _ => self.infer_expr_type(expr),
+            // A float literal takes the expected `f32` or `Decimal` type
+            ast::Expr::Float(_)
+                if matches!(
+                    expected.nullable_inner().unwrap_or(expected),
+                    Type::F32 | Type::Decimal
+                ) =>
+            {
+                let ty = expected.nullable_inner().unwrap_or(expected).clone();
+                self.expr_types.insert(self.context_span, ty.clone());
+                ty
+            }
+            // For other expressions, infer normally; integer literals take the
+            // expected integer type, and must fit it
+            _ => {
+                let integer = expected.nullable_inner().unwrap_or(expected);
+                match (integer_literal(expr), integer.integer_range()) {
+                    (Some(value), Some((min, max))) => {
+                        if value < min || value > max {
+                            self.diagnostics.add(
+                                Diagnostic::from_code(
+                                    &codes::E0414,
+                                    self.context_span,
+                                    format!(
+                                        "integer literal `{}` is out of range for `{}`",
+                                        value, integer
+                                    ),
+                                )
+                                .with_help(format!(
+                                    "`{}` holds integers from {} to {}",
+                                    integer, min, max
+                                )),
+                            );
+                        }
+                        self.expr_types.insert(self.context_span, integer.clone());
+                        integer.clone()
+                    }
+                    _ => self.infer_expr_type(expr),
+                }
+            }
         }
     }
 
@@ -137,26 +229,21 @@ impl<'a> ExprChecker<'a> {
                 // For now, return Unknown as we don't have structural types yet
                 Type::Unknown
             }
-            ast::Expr::Identifier(name) => lookup_identifier_type(
-                name,
-                self.current_scope,
-                self.symbols,
-                self.scopes,
-                self.symbol_types,
-            ),
+            ast::Expr::Identifier(name) => {
+                let ty = self.identifier_type(name);
+                let ty = self.narrow(name, ty);
+                self.record_name(name, &ty);
+                ty
+            }
             ast::Expr::QualifiedName(parts) => {
                 if let Some(first) = parts.first() {
-                    let base_type = lookup_identifier_type(
-                        first,
-                        self.current_scope,
-                        self.symbols,
-                        self.scopes,
-                        self.symbol_types,
-                    );
+                    let base_type = self.identifier_type(first);
                     // Resolve field accesses
-                    let mut current = base_type;
-                    for field in parts.iter().skip(1) {
+                    let mut current = self.narrow(first, base_type);
+                    self.record_name(first, &current);
+                    for (i, field) in parts.iter().enumerate().skip(1) {
                         current = self.resolve_field_access(&current, field);
+                        current = self.narrow(&parts[..=i].join("."), current);
                     }
                     current
                 } else {
@@ -165,7 +252,13 @@ impl<'a> ExprChecker<'a> {
             }
             ast::Expr::Binary { op, left, right } => {
                 let left_type = self.infer_expr_type(left);
-                let right_type = self.infer_expr_type(right);
+                // `a != null && a.b` and `a == null || a.b`
+                let right_type = match op {
+                    ast::BinaryOp::And => self.infer_narrowed(right, non_null_when(left, true)),
+                    ast::BinaryOp::Or => self.infer_narrowed(right, non_null_when(left, false)),
+                    _ => self.infer_expr_type(right),
+                };
+                self.check_constant_operator(expr, &[left, right]);
                 infer_binary_op_type(
                     *op,
                     &left_type,
@@ -174,8 +267,9 @@ impl<'a> ExprChecker<'a> {
                     &mut self.diagnostics,
                 )
             }
-            ast::Expr::Unary { op, expr } => {
-                let operand_type = self.infer_expr_type(expr);
+            ast::Expr::Unary { op, expr: operand } => {
+                let operand_type = self.infer_expr_type(operand);
+                self.check_constant_operator(expr, &[operand]);
                 infer_unary_op_type(*op, &operand_type, self.context_span, &mut self.diagnostics)
             }
             ast::Expr::Ternary {
@@ -185,8 +279,8 @@ impl<'a> ExprChecker<'a> {
             } => {
                 let cond_type = self.infer_expr_type(condition);
                 expect_bool(&cond_type, self.context_span, &mut self.diagnostics);
-                let then_type = self.infer_expr_type(then_expr);
-                let else_type = self.infer_expr_type(else_expr);
+                let then_type = self.infer_narrowed(then_expr, non_null_when(condition, true));
+                let else_type = self.infer_narrowed(else_expr, non_null_when(condition, false));
                 // Result type is the common type of both branches
                 if types_compatible(&then_type, &else_type) {
                     then_type
@@ -202,9 +296,18 @@ impl<'a> ExprChecker<'a> {
                     Type::Error
                 }
             }
-            ast::Expr::FieldAccess { base, field } => {
+            ast::Expr::FieldAccess { base, field, span } => {
                 let base_type = self.infer_expr_type(base);
-                self.resolve_field_access(&base_type, field)
+                if let Type::Nullable(inner) = &base_type {
+                    self.report_nullable_access(base, field, inner, *span);
+                    Type::Error
+                } else {
+                    let ty = self.resolve_field_access(&base_type, field);
+                    match qualified_path(expr) {
+                        Some(path) => self.narrow(&path, ty),
+                        None => ty,
+                    }
+                }
             }
             ast::Expr::OptionalChain { base, field } => {
                 let base_type = self.infer_expr_type(base);
@@ -221,13 +324,66 @@ impl<'a> ExprChecker<'a> {
                 field_type.make_nullable()
             }
             ast::Expr::Call { callee, args } => {
+                if let ast::Expr::Identifier(name) = callee.as_ref() {
+                    if let Some(builtin) = self.builtin(name) {
+                        return self.check_builtin_call(builtin, args);
+                    }
+                }
+                if let ast::Expr::FieldAccess { base, field, .. } | ast::Expr::OptionalChain { base, field } =
+                    callee.as_ref()
+                {
+                    let optional = matches!(callee.as_ref(), ast::Expr::OptionalChain { .. });
+                    if let Some(ty) = self.check_member_call(base, field, optional, args) {
+                        return ty;
+                    }
+                }
                 let callee_type = self.infer_expr_type(callee);
-                // Type check arguments
-                for arg in args {
-                    self.infer_expr_type(arg);
+                let symbol = self.callee_symbol(callee);
+                let name = qualified_path(callee).unwrap_or_else(|| "the function".to_string());
+                if let Some(symbol) = symbol.and_then(|id| self.symbols.get(id)) {
+                    if symbol.kind == SymbolKind::Command && !self.in_handler {
+                        self.diagnostics.add(
+                            Diagnostic::from_code(
+                                &codes::E0603,
+                                self.context_span,
+                                format!("command `{}` called in an expression", name),
+                            )
+                            .with_help("commands change state; call it from an event handler"),
+                        );
+                    }
+                }
+                match &callee_type {
+                    Type::Function { params, .. } => self.check_call_args(&name, symbol, params, args),
+                    _ => {
+                        for arg in args {
+                            self.infer_expr_type(arg);
+                        }
+                    }
                 }
                 self.infer_call_result_type(&callee_type)
             }
+            ast::Expr::Range { start, end, .. } => {
+                // An integer literal bound takes the type of the other bound: `1..=size`
+                let (start_type, end_type) = if matches!(start.as_ref(), ast::Expr::Int(_)) {
+                    let end_type = self.infer_expr_type(end);
+                    (self.check_expr_type(start, &end_type), end_type)
+                } else {
+                    let start_type = self.infer_expr_type(start);
+                    let end_type = self.check_expr_type(end, &start_type);
+                    (start_type, end_type)
+                };
+                expect_integer(&start_type, self.context_span, &mut self.diagnostics);
+                expect_integer(&end_type, self.context_span, &mut self.diagnostics);
+                let element = match (start_type.is_integer(), end_type.is_integer()) {
+                    (true, true) if start_type == end_type => start_type,
+                    (true, true) => common_numeric_type(&start_type, &end_type),
+                    (true, false) => start_type,
+                    (false, true) => end_type,
+                    (false, false) => Type::Unknown,
+                };
+                Type::Range(Box::new(element))
+            }
+            ast::Expr::Lambda { params, body, span } => self.check_lambda(params, body, *span, None),
         };
 
         // Use context_span since Expr doesn't carry its own span
@@ -235,6 +391,289 @@ impl<'a> ExprChecker<'a> {
         ty
     }
 
+    /// The type of the name `name` refers to here
+    fn identifier_type(&self, name: &str) -> Type {
+        let local = self
+            .symbols
+            .lookup_in_scope_chain(self.current_scope, name, self.scopes)
+            .and_then(|id| self.lambda_params.get(&id));
+        match local {
+            Some(ty) => ty.clone(),
+            None => lookup_identifier_type(
+                name,
+                self.current_scope,
+                self.symbols,
+                self.scopes,
+                self.symbol_types,
+            ),
+        }
+    }
+
+    /// Check a lambda, taking the types of its parameters that aren't written
+    /// from the function type `expected` for it
+    ///
+    /// A lambda runs when it is called back, like an event handler, so its
+    /// body may call commands.
+    fn check_lambda(
+        &mut self,
+        params: &[ast::LambdaParam],
+        body: &ast::Expr,
+        span: Span,
+        expected: Option<(&[Type], &Type)>,
+    ) -> Type {
+        let arity_matches = expected.is_none_or(|(expected_params, _)| expected_params.len() == params.len());
+        let expected = match expected {
+            Some((expected_params, _)) if !arity_matches => {
+                self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0415,
+                        self.context_span,
+                        format!(
+                            "lambda takes {} parameter{}, but {} {} expected",
+                            params.len(),
+                            if params.len() == 1 { "" } else { "s" },
+                            expected_params.len(),
+                            if expected_params.len() == 1 { "is" } else { "are" },
+                        ),
+                    )
+                    .with_help(format!(
+                        "the callback is called with `({})`",
+                        expected_params
+                            .iter()
+                            .map(|ty| self.type_name(ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                );
+                None
+            }
+            expected => expected,
+        };
+        let Some(scope) = self.scopes.child_at(self.current_scope, ScopeKind::Lambda, span) else {
+            return Type::Unknown;
+        };
+
+        let mut mismatched = !arity_matches;
+        let mut param_types = Vec::new();
+        for (i, param) in params.iter().enumerate() {
+            let expected_param = expected.map(|(params, _)| &params[i]);
+            let ty = match &param.type_expr {
+                Some(type_expr) => {
+                    let mut resolver = TypeResolver::new(self.scopes, self.symbols, self.imports);
+                    resolver.current_scope = self.current_scope;
+                    let ty = resolver.resolve_type_expr(type_expr, self.context_span);
+                    self.diagnostics.merge(resolver.diagnostics);
+                    if let Some(expected_param) = expected_param {
+                        if !types_compatible(&ty, expected_param) {
+                            mismatched = true;
+                            self.diagnostics.add(Diagnostic::from_code(
+                                &codes::E0401,
+                                self.context_span,
+                                format!(
+                                    "lambda parameter `{}` has type `{}`, but the callback is called with `{}`",
+                                    param.name,
+                                    self.type_name(&ty),
+                                    self.type_name(expected_param)
+                                ),
+                            ));
+                        }
+                    }
+                    ty
+                }
+                None => expected_param.cloned().unwrap_or(Type::Unknown),
+            };
+            if let Some(id) = self.symbols.lookup_local(scope, &param.name) {
+                self.lambda_params.insert(id, ty.clone());
+            }
+            param_types.push(ty);
+        }
+
+        let outer = (self.current_scope, self.in_handler);
+        self.current_scope = scope;
+        self.in_handler = true;
+        let ret = match expected {
+            Some((_, ret)) if *ret != Type::Unknown && *ret != Type::Unit => {
+                self.check_expr_type(body, ret)
+            }
+            _ => self.infer_expr_type(body),
+        };
+        (self.current_scope, self.in_handler) = outer;
+        if mismatched {
+            // Reported above; don't report the function types as mismatched too
+            return Type::Error;
+        }
+        Type::function(param_types, ret)
+    }
+
+    /// Infer the type of `expr` with `paths` known not to be null
+    fn infer_narrowed(&mut self, expr: &ast::Expr, paths: Vec<String>) -> Type {
+        let saved = self.narrowed.clone();
+        self.narrowed.extend(paths);
+        let ty = self.infer_expr_type(expr);
+        self.narrowed = saved;
+        ty
+    }
+
+    /// The type of `path` where it may be narrowed to not null
+    fn narrow(&self, path: &str, ty: Type) -> Type {
+        match ty {
+            Type::Nullable(inner) if self.narrowed.contains(path) => *inner,
+            ty => ty,
+        }
+    }
+
+    /// Report `base.field` on a `base` that may be null, with fixes using
+    /// `?.` and, for fields with an obvious default, `?:`
+    fn report_nullable_access(&mut self, base: &ast::Expr, field: &str, inner: &Type, span: Span) {
+        let path = qualified_path(base);
+        let name = path.as_deref().unwrap_or("the value");
+        let span = if span == Span::default() { self.context_span } else { span };
+        let mut diagnostic = Diagnostic::from_code(
+            &codes::E0406,
+            span,
+            format!(
+                "cannot access field `{}` of `{}`, which may be null",
+                field, name
+            ),
+        );
+        if let Some(path) = &path {
+            diagnostic = diagnostic.with_suggestion(Suggestion::new(
+                span,
+                format!("{}?.{}", path, field),
+                "use `?.` to get `null` when it is null",
+            ));
+            // The default can only be given for a field type the access knows
+            let field_type = self.quiet().resolve_field_access(inner, field);
+            if let Some(default) = default_literal(&field_type) {
+                diagnostic = diagnostic.with_suggestion(Suggestion::new(
+                    span,
+                    format!("({}?.{} ?: {})", path, field, default),
+                    format!("use `?:` to default to `{}`", default),
+                ));
+            }
+        }
+        self.diagnostics.add(diagnostic.with_help(format!(
+            "or check it first: `when {} != null {{ ... }}`",
+            name
+        )));
+    }
+
+    /// Report an operator applied to constants whose value cannot be computed:
+    /// a division by zero or an overflow (E0413)
+    ///
+    /// Only the innermost such operator is reported: its operands must have values.
+    fn check_constant_operator(&mut self, expr: &ast::Expr, operands: &[&ast::Expr]) {
+        if operands.iter().any(|operand| eval_const(operand, &mut EmptyEnv).is_err()) {
+            return;
+        }
+        if let Err(error @ (ConstEvalError::DivisionByZero | ConstEvalError::Overflow)) =
+            eval_const(expr, &mut EmptyEnv)
+        {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0413,
+                self.context_span,
+                format!("{} in a constant expression", error),
+            ));
+        }
+    }
+
+    /// The types of the expressions and names checked, and the diagnostics
+    pub fn finish(self) -> Checked {
+        Checked {
+            expr_types: self.expr_types,
+            names: self.names,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Record the symbol an identifier names, with its type here
+    fn record_name(&mut self, name: &str, ty: &Type) {
+        if let Some(id) = self.symbols.lookup_in_scope_chain(self.current_scope, name, self.scopes) {
+            self.names.insert((self.context_span, name.to_string()), (id, ty.clone()));
+        }
+    }
+
+    /// A checker for the same place whose diagnostics are dropped
+    fn quiet(&self) -> ExprChecker<'a> {
+        let mut checker = ExprChecker::new(
+            self.scopes,
+            self.symbols,
+            self.symbol_types,
+            self.imports,
+            self.current_scope,
+            self.context_span,
+        );
+        checker.narrowed = self.narrowed.clone();
+        checker.in_handler = self.in_handler;
+        checker.lambda_params = self.lambda_params.clone();
+        checker
+    }
+
+    /// The method, command, contract method or enum variant a call calls, if known
+    fn callee_symbol(&self, callee: &ast::Expr) -> Option<SymbolId> {
+        match callee {
+            ast::Expr::Identifier(name) => {
+                self.symbols.lookup_in_scope_chain(self.current_scope, name, self.scopes)
+            }
+            ast::Expr::FieldAccess { base, field, .. } => match self.quiet().infer_expr_type(base) {
+                Type::Backend(id) | Type::Contract(id) | Type::Enum(id) => {
+                    let scope = self.symbols.get(id)?.body_scope?;
+                    self.symbols.lookup_local(scope, field)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Check the arguments of a call of `name`, a function taking `params`
+    pub fn check_call_args(
+        &mut self,
+        name: &str,
+        symbol: Option<SymbolId>,
+        params: &[Type],
+        args: &[ast::Expr],
+    ) {
+        let required = symbol
+            .and_then(|id| self.required_args?.get(&id).copied())
+            .unwrap_or(0)
+            .min(params.len());
+        if args.len() < required || args.len() > params.len() {
+            let expected = if required == params.len() {
+                required.to_string()
+            } else if required == 0 {
+                format!("at most {}", params.len())
+            } else {
+                format!("{} to {}", required, params.len())
+            };
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0702,
+                self.context_span,
+                format!("`{}` takes {} arguments, found {}", name, expected, args.len()),
+            ));
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let Some(param) = params.get(i) else {
+                self.infer_expr_type(arg);
+                continue;
+            };
+            let arg_type = self.check_expr_type(arg, param);
+            if !types_compatible(param, &arg_type) {
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0401,
+                    self.context_span,
+                    format!(
+                        "argument {} of `{}` expects `{}`, found `{}`",
+                        i + 1,
+                        name,
+                        self.type_name(param),
+                        self.type_name(&arg_type)
+                    ),
+                ));
+            }
+        }
+    }
+
     /// Resolve a field access on a type
     pub fn resolve_field_access(&mut self, base_type: &Type, field: &str) -> Type {
         match base_type {
@@ -290,9 +729,14 @@ impl<'a> ExprChecker<'a> {
                 // Enum variant access
                 if let Some(symbol) = self.symbols.get(*symbol_id) {
                     if let Some(body_scope) = symbol.body_scope {
-                        if self.symbols.lookup_local(body_scope, field).is_some() {
-                            // Return the enum type itself (variant has same type as enum)
-                            return base_type.clone();
+                        if let Some(variant_id) = self.symbols.lookup_local(body_scope, field) {
+                            // A variant has the enum type, unless it carries data:
+                            // then it is a constructor of the enum
+                            return self
+                                .symbol_types
+                                .get(&variant_id)
+                                .cloned()
+                                .unwrap_or_else(|| base_type.clone());
                         }
                     }
                 }
@@ -308,11 +752,42 @@ impl<'a> ExprChecker<'a> {
                 self.diagnostics.add(Diagnostic::from_code(
                     &codes::E0406,
                     self.context_span,
-                    "cannot access field on nullable type without optional chaining `?.`",
+                    format!(
+                        "cannot access field `{}` of `{}`, which may be null",
+                        field,
+                        self.type_name(base_type)
+                    ),
                 ));
                 Type::Error
             }
             Type::Error | Type::Unknown => Type::Error,
+            _ if members::Receiver::of(base_type).is_some() => {
+                match members::lookup(base_type, field) {
+                    Some(member) if !member.is_method() => member.result(base_type),
+                    Some(member) => {
+                        self.diagnostics.add(
+                            Diagnostic::from_code(
+                                &codes::E0401,
+                                self.context_span,
+                                format!(
+                                    "`{}` is a method of `{}`, not a property",
+                                    field,
+                                    self.type_name(base_type)
+                                ),
+                            )
+                            .with_help(format!(
+                                "call it: `{}`",
+                                member.signature(base_type, |ty| self.type_name(ty))
+                            )),
+                        );
+                        Type::Error
+                    }
+                    None => {
+                        self.report_unknown_member(base_type, field);
+                        Type::Error
+                    }
+                }
+            }
             _ => {
                 self.diagnostics.add(Diagnostic::from_code(
                     &codes::E0401,
@@ -324,6 +799,174 @@ impl<'a> ExprChecker<'a> {
         }
     }
 
+    /// Report a member that values of the built-in type `base_type` don't
+    /// have, listing those they have
+    fn report_unknown_member(&mut self, base_type: &Type, field: &str) {
+        let available: Vec<_> = members::members_of(base_type)
+            .map(|member| {
+                if member.is_method() {
+                    format!("`{}()`", member.name)
+                } else {
+                    format!("`{}`", member.name)
+                }
+            })
+            .collect();
+        self.diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0301,
+                self.context_span,
+                format!("no member `{}` on type `{}`", field, self.type_name(base_type)),
+            )
+            .with_help(format!("`{}` has {}", self.type_name(base_type), available.join(", "))),
+        );
+    }
+
+    /// Check a call of a method of a built-in type, e.g. `name.startsWith("A")`;
+    /// `None` if `base` has no member `method`, for the call to be checked
+    /// like other calls
+    fn check_member_call(
+        &mut self,
+        base: &ast::Expr,
+        method: &str,
+        optional: bool,
+        args: &[ast::Expr],
+    ) -> Option<Type> {
+        let base_type = self.quiet().infer_expr_type(base);
+        let receiver = match &base_type {
+            Type::Nullable(inner) if optional => inner.as_ref(),
+            ty => ty,
+        };
+        let member = members::lookup(receiver, method)?;
+        self.infer_expr_type(base);
+        let name = format!("{}.{}", self.type_name(receiver), method);
+
+        let Some(params) = member.params else {
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0401,
+                    self.context_span,
+                    format!("`{}` is a property of `{}`, not a method", method, self.type_name(receiver)),
+                )
+                .with_help(format!(
+                    "leave out the parentheses: `{}`",
+                    member.signature(receiver, |ty| self.type_name(ty))
+                )),
+            );
+            for arg in args {
+                self.infer_expr_type(arg);
+            }
+            return Some(Type::Error);
+        };
+        if args.len() != params.len() {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0702,
+                self.context_span,
+                format!(
+                    "`{}` takes {} arguments, found {}; expected `{}`",
+                    name,
+                    params.len(),
+                    args.len(),
+                    member.signature(receiver, |ty| self.type_name(ty))
+                ),
+            ));
+        }
+        let param_types = member.param_types(receiver);
+        for (i, arg) in args.iter().enumerate() {
+            let Some(param) = param_types.get(i) else {
+                self.infer_expr_type(arg);
+                continue;
+            };
+            let arg_type = self.check_expr_type(arg, param);
+            if !types_compatible(param, &arg_type) {
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0401,
+                    self.context_span,
+                    format!(
+                        "argument `{}` of `{}` expects `{}`, found `{}`",
+                        params[i].name,
+                        name,
+                        self.type_name(param),
+                        self.type_name(&arg_type)
+                    ),
+                ));
+            }
+        }
+
+        let ty = member.result(receiver);
+        let ty = if optional && base_type.is_nullable() { ty.make_nullable() } else { ty };
+        self.expr_types.insert(self.context_span, ty.clone());
+        Some(ty)
+    }
+
+    /// The built-in function a called name refers to, unless a declaration
+    /// or import shadows it
+    fn builtin(&self, name: &str) -> Option<&'static builtins::Builtin> {
+        if self.imports.contains_key(name)
+            || self
+                .symbols
+                .lookup_in_scope_chain(self.current_scope, name, self.scopes)
+                .is_some()
+        {
+            return None;
+        }
+        builtins::lookup(name)
+    }
+
+    /// Check the arguments of a built-in function call against its parameters
+    fn check_builtin_call(&mut self, builtin: &builtins::Builtin, args: &[ast::Expr]) -> Type {
+        if args.len() < builtin.min_args() || args.len() > builtin.params.len() {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0702,
+                self.context_span,
+                format!(
+                    "`{}` takes {} arguments, found {}; expected `{}`",
+                    builtin.name,
+                    if builtin.min_args() == builtin.params.len() {
+                        builtin.min_args().to_string()
+                    } else {
+                        format!("{} to {}", builtin.min_args(), builtin.params.len())
+                    },
+                    args.len(),
+                    builtin.signature()
+                ),
+            ));
+        }
+        for (arg, param) in args.iter().zip(builtin.params) {
+            let arg_type = self.infer_expr_type(arg);
+            if let Some(check) = param.check {
+                // Only literal arguments are known here
+                if let Ok(Err(reason)) = eval_const(arg, &mut EmptyEnv).map(|value| check(&value)) {
+                    self.diagnostics.add(Diagnostic::from_code(
+                        &codes::E0409,
+                        self.context_span,
+                        format!("argument `{}` of `{}`: {}", param.name, builtin.name, reason),
+                    ));
+                }
+            }
+            if !param.types.iter().any(|ty| types_compatible(ty, &arg_type)) {
+                let expected: Vec<_> = param.types.iter().map(|ty| format!("`{}`", ty)).collect();
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0401,
+                    self.context_span,
+                    format!(
+                        "argument `{}` of `{}` expects {}, found `{}`",
+                        param.name,
+                        builtin.name,
+                        expected.join(" or "),
+                        self.type_name(&arg_type)
+                    ),
+                ));
+            }
+        }
+        for arg in args.iter().skip(builtin.params.len()) {
+            self.infer_expr_type(arg);
+        }
+
+        let ty = builtin.result();
+        self.expr_types.insert(self.context_span, ty.clone());
+        ty
+    }
+
     /// Infer the result type of a function/method call
     fn infer_call_result_type(&self, callee_type: &Type) -> Type {
         match callee_type {
@@ -337,7 +980,7 @@ impl<'a> ExprChecker<'a> {
     }
 
     /// Format a type for display in error messages, resolving symbol names
-    fn type_name(&self, ty: &Type) -> String {
+    pub fn type_name(&self, ty: &Type) -> String {
         match ty {
             Type::Scheme(id) | Type::Backend(id) | Type::Blueprint(id) | Type::Contract(id) | Type::Theme(id) | Type::Enum(id) => {
                 if let Some(symbol) = self.symbols.get(*id) {
@@ -353,7 +996,40 @@ impl<'a> ExprChecker<'a> {
             Type::Set(inner) => format!("Set<{}>", self.type_name(inner)),
             Type::Map(k, v) => format!("Map<{}, {}>", self.type_name(k), self.type_name(v)),
             Type::Tree(inner) => format!("Tree<{}>", self.type_name(inner)),
+            Type::Range(inner) => format!("Range<{}>", self.type_name(inner)),
+            Type::Function { params, ret } => {
+                let params: Vec<_> = params.iter().map(|p| self.type_name(p)).collect();
+                format!("fn({}) -> {}", params.join(", "), self.type_name(ret))
+            }
             _ => ty.to_string(),
         }
     }
 }
+
+/// Literal of the empty value of `ty`, for `?:` defaults
+fn default_literal(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::String => Some("\"\""),
+        Type::Bool => Some("false"),
+        ty if ty.is_float() => Some("0.0"),
+        ty if ty.is_integer() => Some("0"),
+        Type::List(_) => Some("[]"),
+        _ => None,
+    }
+}
+
+/// The value of an integer literal, possibly signed: `42`, `-1`
+fn integer_literal(expr: &ast::Expr) -> Option<i128> {
+    match expr {
+        ast::Expr::Int(value) => Some((*value).into()),
+        ast::Expr::Unary {
+            op: ast::UnaryOp::Neg,
+            expr,
+        } => integer_literal(expr).map(|value| -value),
+        ast::Expr::Unary {
+            op: ast::UnaryOp::Pos,
+            expr,
+        } => integer_literal(expr),
+        _ => None,
+    }
+}
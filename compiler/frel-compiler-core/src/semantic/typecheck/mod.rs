@@ -5,22 +5,54 @@
 // 2. Infer types of expressions
 // 3. Check type compatibility for assignments and calls
 // 4. Validate command vs method context usage
+// 5. Narrow nullable paths under null checks (see `narrowing`)
+// 6. Report constants that divide by zero or overflow
+// 7. Report integer literals out of the range of their expected type
 
 mod expressions;
+mod narrowing;
 mod operators;
 mod resolution;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ast::{self, TypeExpr};
-use crate::diagnostic::{codes, Diagnostic, Diagnostics};
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, RelatedInfo, Suggestion};
+use crate::edition::{Experimental, Features};
 use crate::source::Span;
 
+use super::events::event_registry;
 use super::instructions::instruction_registry;
-use super::scope::{ScopeGraph, ScopeId};
-use super::symbol::{SymbolId, SymbolTable};
+use super::resolve::UNDO_COMMANDS;
+use super::scope::{ScopeGraph, ScopeId, ScopeKind};
+use super::symbol::{SymbolId, SymbolKind, SymbolTable};
+use super::const_eval::{eval_const, ConstEnv, ConstEvalError, ConstValue};
+use super::theme_values::{evaluate_theme, ThemeValues};
 use super::types::Type;
 
+/// Name of the list virtualization instruction
+const VIRTUALIZE: &str = "virtualize";
+
+/// Name of the instruction focusing a fragment when it is created
+const AUTOFOCUS: &str = "autofocus";
+
+/// Name of the instruction setting a fragment's position in the tab order
+const TAB_INDEX: &str = "tab_index";
+
+/// Name of the instruction rendering a fragment in an overlay container
+const PORTAL: &str = "portal";
+
+/// Instructions placing a fragment in its parent's layout; `width` and
+/// `height` only with `expand` or `container`
+const LAYOUT_RELATIVE: &[&str] = &[
+    "position",
+    "align_self",
+    "fill_width",
+    "fill_height",
+    "fill",
+    "expand",
+];
+
 pub use operators::types_compatible;
 use resolution::TypeResolver;
 
@@ -31,6 +63,11 @@ pub struct TypeCheckResult {
     pub expr_types: HashMap<Span, Type>,
     /// Resolved types for type expressions (by span)
     pub type_resolutions: HashMap<Span, Type>,
+    /// Types of declared symbols: fields, parameters, locals and loop items
+    pub symbol_types: HashMap<SymbolId, Type>,
+    /// Identifiers, by the span of their statement and name: the symbol each
+    /// names and its type there
+    pub names: HashMap<(Span, String), (SymbolId, Type)>,
     /// Diagnostics generated during type checking
     pub diagnostics: Diagnostics,
 }
@@ -40,6 +77,8 @@ impl TypeCheckResult {
         Self {
             expr_types: HashMap::new(),
             type_resolutions: HashMap::new(),
+            symbol_types: HashMap::new(),
+            names: HashMap::new(),
             diagnostics: Diagnostics::new(),
         }
     }
@@ -63,6 +102,8 @@ pub struct TypeChecker<'a> {
     symbol_types: HashMap<SymbolId, Type>,
     /// Types of expressions
     expr_types: HashMap<Span, Type>,
+    /// Identifiers by statement span and name
+    names: HashMap<(Span, String), (SymbolId, Type)>,
     /// Resolved type expressions
     type_resolutions: HashMap<Span, Type>,
     /// Diagnostics
@@ -73,6 +114,27 @@ pub struct TypeChecker<'a> {
     context_span: Span,
     /// Imported names (name -> module path)
     imports: &'a HashMap<String, String>,
+    /// Backend slots of each blueprint in the file: (slot name, backend type)
+    backend_slots: HashMap<String, Vec<(String, Type)>>,
+    /// Experimental features enabled for the file
+    features: Features,
+    /// Paths known not to be null in the statement being checked
+    narrowed: HashSet<String>,
+    /// Arguments of the methods and commands of the file without a default
+    required_args: HashMap<SymbolId, usize>,
+    /// Parameters of each blueprint in the file, for checking fragment arguments
+    blueprint_params: HashMap<String, Vec<BlueprintParam>>,
+    /// Whether the statement being checked is in an event handler
+    in_handler: bool,
+}
+
+/// A blueprint parameter as seen by a fragment creating the blueprint
+#[derive(Debug, Clone)]
+struct BlueprintParam {
+    name: String,
+    ty: Type,
+    /// Neither a default, a slot filled by the body, nor a backend member
+    required: bool,
 }
 
 impl<'a> TypeChecker<'a> {
@@ -86,16 +148,25 @@ impl<'a> TypeChecker<'a> {
             symbols,
             symbol_types: HashMap::new(),
             expr_types: HashMap::new(),
+            names: HashMap::new(),
             type_resolutions: HashMap::new(),
             diagnostics: Diagnostics::new(),
             current_scope: ScopeId::ROOT,
             context_span: Span::default(),
             imports,
+            backend_slots: HashMap::new(),
+            features: Features::default(),
+            narrowed: HashSet::new(),
+            required_args: HashMap::new(),
+            blueprint_params: HashMap::new(),
+            in_handler: false,
         }
     }
 
     /// Run type checking on a file AST
     pub fn check(mut self, file: &ast::File) -> TypeCheckResult {
+        let _profile = crate::profile::enter("typecheck");
+        self.features = file.features.clone();
         // First pass: resolve all type annotations
         self.resolve_declarations(file);
 
@@ -105,6 +176,8 @@ impl<'a> TypeChecker<'a> {
         TypeCheckResult {
             expr_types: self.expr_types,
             type_resolutions: self.type_resolutions,
+            symbol_types: self.symbol_types,
+            names: self.names,
             diagnostics: self.diagnostics,
         }
     }
@@ -122,7 +195,7 @@ impl<'a> TypeChecker<'a> {
                 ast::TopLevelDecl::Scheme(sc) => self.resolve_scheme_types(sc),
                 ast::TopLevelDecl::Contract(ct) => self.resolve_contract_types(ct),
                 ast::TopLevelDecl::Theme(th) => self.resolve_theme_types(th),
-                ast::TopLevelDecl::Enum(_) => {} // Enums don't have type annotations
+                ast::TopLevelDecl::Enum(en) => self.resolve_enum_types(en),
                 ast::TopLevelDecl::Arena(_) => {} // Arena references resolved during name resolution
             }
         }
@@ -150,6 +223,9 @@ impl<'a> TypeChecker<'a> {
                     for param in &cmd.params {
                         self.resolve_type_expr(&param.type_expr, cmd.span);
                     }
+                    if let Some(return_type) = self.command_result(cmd) {
+                        self.resolve_type_expr(return_type, cmd.span);
+                    }
                 }
                 ast::BackendMember::Include(_) => {}
             }
@@ -157,14 +233,80 @@ impl<'a> TypeChecker<'a> {
     }
 
     fn resolve_blueprint_types(&mut self, bp: &ast::Blueprint) {
+        // Backend slots a parent can fill at the call site: backend-typed
+        // parameters and `with slot: Backend`
+        let mut slots = Vec::new();
+
+        // Parameters a fragment must give: not the blueprint-typed ones, which
+        // the body fills, nor those setting a member of a backend used with `with`
+        let members: Vec<ScopeId> = bp
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::BlueprintStmt::With(with) => self
+                    .backend_type(&with.backend)
+                    .and_then(|ty| match ty {
+                        Type::Backend(id) => self.symbols.get(id)?.body_scope,
+                        _ => None,
+                    }),
+                _ => None,
+            })
+            .collect();
+        let mut params = Vec::new();
+
         // Use blueprint span for parameters since Parameter has no span
         for param in &bp.params {
-            self.resolve_type_expr(&param.type_expr, bp.span);
+            let ty = self.resolve_type_expr(&param.type_expr, bp.span);
+            let required = param.default.is_none()
+                && !matches!(param.type_expr, TypeExpr::Blueprint(_))
+                && !matches!(ty, Type::Backend(_))
+                && !members
+                    .iter()
+                    .any(|&scope| self.symbols.lookup_local(scope, &param.name).is_some());
+            if matches!(ty, Type::Backend(_)) {
+                slots.push((param.name.clone(), ty.clone()));
+            }
+            params.push(BlueprintParam {
+                name: param.name.clone(),
+                ty,
+                required,
+            });
         }
 
         for stmt in &bp.body {
+            if let ast::BlueprintStmt::With(ast::WithClause {
+                name: Some(slot),
+                backend,
+                ..
+            }) = stmt
+            {
+                if let Some(ty) = self.backend_type(backend) {
+                    slots.push((slot.clone(), ty));
+                }
+            }
             self.resolve_blueprint_stmt_types(stmt, bp.span);
         }
+
+        self.backend_slots.insert(bp.name.clone(), slots);
+        self.blueprint_params.insert(bp.name.clone(), params);
+    }
+
+    /// The type of a module-level backend, if `name` is one
+    fn backend_type(&self, name: &str) -> Option<Type> {
+        let id = self.symbols.lookup_local(ScopeId::ROOT, name)?;
+        let symbol = self.symbols.get(id)?;
+        (symbol.kind == SymbolKind::Backend).then_some(Type::Backend(id))
+    }
+
+    /// Type name for diagnostics, using declared names for backends
+    fn display_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::Backend(id) => match self.symbols.get(*id) {
+                Some(symbol) => format!("backend `{}`", symbol.name),
+                None => ty.to_string(),
+            },
+            _ => format!("`{}`", ty),
+        }
     }
 
     fn resolve_blueprint_stmt_types(&mut self, stmt: &ast::BlueprintStmt, context_span: Span) {
@@ -251,6 +393,16 @@ impl<'a> TypeChecker<'a> {
                     self.resolve_blueprint_stmt_types(else_stmt, context_span);
                 }
             }
+            ast::ControlStmt::OnError { body, fallback, .. } => {
+                for stmt in body.iter().chain(fallback.iter().flatten()) {
+                    self.resolve_blueprint_stmt_types(stmt, context_span);
+                }
+            }
+            ast::ControlStmt::Loading { body, .. } => {
+                for stmt in body {
+                    self.resolve_blueprint_stmt_types(stmt, context_span);
+                }
+            }
         }
     }
 
@@ -299,12 +451,124 @@ impl<'a> TypeChecker<'a> {
                 self.symbols.lookup_local(self.current_scope, &method.name)
             {
                 self.symbol_types.insert(method_symbol_id, method_type);
+                self.record_required_args(method_symbol_id, &method.params);
             }
+            self.check_contract_bindings(method);
         }
 
         self.current_scope = saved_scope;
     }
 
+    /// Check the transport bindings of a contract method (E0609)
+    fn check_contract_bindings(&mut self, method: &ast::ContractMethod) {
+        let mut errors = Vec::new();
+        for instr in &method.instructions {
+            let name = instr.name.as_str();
+            if name != "name" && !ast::HTTP_METHODS.contains(&name) {
+                errors.push(format!(
+                    "unknown contract binding `{}`; expected `name` or an HTTP method ({})",
+                    name,
+                    ast::HTTP_METHODS.join(", ")
+                ));
+            } else if method.binding(name).is_none() {
+                let example = match name {
+                    "name" => "remoteName",
+                    _ => "/items",
+                };
+                errors.push(format!(
+                    "`{}` takes a single string literal, e.g. `.. {} {{ \"{}\" }}`",
+                    name, name, example
+                ));
+            }
+        }
+
+        let routes = method
+            .instructions
+            .iter()
+            .filter(|i| ast::HTTP_METHODS.contains(&i.name.as_str()))
+            .count();
+        if routes > 1 {
+            errors.push(format!("`{}` is bound to more than one HTTP route", method.name));
+        }
+        if let Some((_, path)) = method.route() {
+            if !path.starts_with('/') {
+                errors.push(format!("path `{}` must start with `/`", path));
+            }
+            for placeholder in path_placeholders(path) {
+                if !method.params.iter().any(|p| p.name == placeholder) {
+                    errors.push(format!(
+                        "path placeholder `{{{}}}` is not a parameter of `{}`",
+                        placeholder, method.name
+                    ));
+                }
+            }
+        }
+
+        for message in errors {
+            self.diagnostics
+                .add(Diagnostic::from_code(&codes::E0609, method.span, message));
+        }
+    }
+
+    /// Give each variant carrying data the type of its constructor, from its
+    /// payload to the enum: `Result.Err(404)`
+    fn resolve_enum_types(&mut self, en: &ast::Enum) {
+        self.check_variant_instructions(en);
+
+        let Some(enum_id) = self.symbols.lookup_local(ScopeId::ROOT, &en.name) else {
+            return;
+        };
+        let Some(body_scope) = self.symbols.get(enum_id).and_then(|s| s.body_scope) else {
+            return;
+        };
+        for (variant, types) in &en.payloads {
+            let params: Vec<Type> = types
+                .iter()
+                .map(|type_expr| self.resolve_type_expr(type_expr, en.span))
+                .collect();
+            let Some(variant_id) = self.symbols.lookup_local(body_scope, variant) else {
+                continue;
+            };
+            self.required_args.insert(variant_id, params.len());
+            let constructor = Type::Function {
+                params,
+                ret: Box::new(Type::Enum(enum_id)),
+            };
+            self.symbol_types.insert(variant_id, constructor);
+        }
+    }
+
+    /// Enums have no type annotations; only the instructions of their variants are checked
+    fn check_variant_instructions(&mut self, en: &ast::Enum) {
+        let mut errors = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for (variant, instr) in &en.instructions {
+            if !ast::ENUM_VARIANT_INSTRUCTIONS.contains(&instr.name.as_str()) {
+                errors.push(format!(
+                    "unknown instruction `{}` on variant `{}`; expected {}",
+                    instr.name,
+                    variant,
+                    ast::ENUM_VARIANT_INSTRUCTIONS.join(", ")
+                ));
+            } else if instr.string_value().is_none() {
+                errors.push(format!(
+                    "`{}` takes a single string literal, e.g. `.. {} {{ \"{}\" }}`",
+                    instr.name, instr.name, variant
+                ));
+            } else if !seen.insert((variant.as_str(), instr.name.as_str())) {
+                errors.push(format!(
+                    "variant `{}` has more than one `{}`",
+                    variant, instr.name
+                ));
+            }
+        }
+
+        for message in errors {
+            self.diagnostics
+                .add(Diagnostic::from_code(&codes::E0408, en.span, message));
+        }
+    }
+
     fn resolve_theme_types(&mut self, th: &ast::Theme) {
         for member in &th.members {
             if let ast::ThemeMember::Field(field) = member {
@@ -336,13 +600,13 @@ impl<'a> TypeChecker<'a> {
                 ast::TopLevelDecl::Backend(be) => self.check_backend(be),
                 ast::TopLevelDecl::Blueprint(bp) => self.check_blueprint(bp, file),
                 ast::TopLevelDecl::Scheme(sc) => self.check_scheme(sc),
-                ast::TopLevelDecl::Theme(th) => self.check_theme(th),
+                ast::TopLevelDecl::Theme(th) => self.check_theme(th, file),
                 _ => {} // Other declarations don't need expression checking
             }
         }
     }
 
-    fn check_theme(&mut self, th: &ast::Theme) {
+    fn check_theme(&mut self, th: &ast::Theme, file: &ast::File) {
         // Enter the theme's body scope for field lookups
         let saved_scope = self.current_scope;
         if let Some(symbol_id) = self.symbols.lookup_local(ScopeId::ROOT, &th.name) {
@@ -366,6 +630,73 @@ impl<'a> TypeChecker<'a> {
         }
 
         self.current_scope = saved_scope;
+        self.check_theme_constants(th, file);
+    }
+
+    /// Report the theme fields and variant overrides whose value divides by
+    /// zero or overflows (E0413)
+    ///
+    /// Values are computed the way `evaluate_theme` does. A field reading a
+    /// field that fails is not reported again.
+    fn check_theme_constants(&mut self, th: &ast::Theme, file: &ast::File) {
+        /// Fields read by a value: those that evaluated, as they did
+        struct Evaluated<'v>(&'v ThemeValues);
+
+        impl ConstEnv for Evaluated<'_> {
+            fn lookup(&mut self, name: &str) -> Result<ConstValue, ConstEvalError> {
+                self.0
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| ConstEvalError::NoValue(name.to_string()))
+            }
+        }
+
+        let invalid = |expr: &ast::Expr, values: &ThemeValues| {
+            match eval_const(expr, &mut Evaluated(values)) {
+                Err(error @ (ConstEvalError::DivisionByZero | ConstEvalError::Overflow)) => {
+                    Some(error)
+                }
+                _ => None,
+            }
+        };
+
+        if let Ok(values) = evaluate_theme(file, &th.name, None) {
+            for member in &th.members {
+                let ast::ThemeMember::Field(field) = member else {
+                    continue;
+                };
+                let Some(error) = field.init.as_ref().and_then(|init| invalid(init, &values))
+                else {
+                    continue;
+                };
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0413,
+                    field.span,
+                    format!("{} in the value of theme field `{}`", error, field.name),
+                ));
+            }
+        }
+
+        for member in &th.members {
+            let ast::ThemeMember::Variant(variant) = member else {
+                continue;
+            };
+            let Ok(values) = evaluate_theme(file, &th.name, Some(&variant.name)) else {
+                continue;
+            };
+            for (name, expr) in &variant.overrides {
+                if let Some(error) = invalid(expr, &values) {
+                    self.diagnostics.add(Diagnostic::from_code(
+                        &codes::E0413,
+                        th.span,
+                        format!(
+                            "{} in the override of theme field `{}` in variant `{}`",
+                            error, name, variant.name
+                        ),
+                    ));
+                }
+            }
+        }
     }
 
     fn check_backend(&mut self, be: &ast::Backend) {
@@ -390,7 +721,10 @@ impl<'a> TypeChecker<'a> {
                         .lookup_in_scope_chain(ScopeId::ROOT, included_name, self.scopes)
                     {
                         if let Some(included_symbol) = self.symbols.get(included_id) {
-                            if let Some(included_body_scope) = included_symbol.body_scope {
+                            if let Some(included_body_scope) = included_symbol
+                                .body_scope
+                                .filter(|_| included_symbol.kind != SymbolKind::Contract)
+                            {
                                 // For each symbol in the included backend, copy its type
                                 let included_members: Vec<_> = self
                                     .symbols
@@ -425,6 +759,24 @@ impl<'a> TypeChecker<'a> {
                     }
                 }
                 ast::BackendMember::Method(method) => {
+                    if let Some(policy) = method.policy {
+                        self.diagnostics.add(
+                            Diagnostic::from_code(
+                                &codes::E0606,
+                                method.span,
+                                format!(
+                                    "`{}` policy on method `{}`; only commands run asynchronously",
+                                    policy.as_str(),
+                                    method.name
+                                ),
+                            )
+                            .with_help(format!(
+                                "declare `{}` as a command, or remove `{}`",
+                                method.name,
+                                policy.as_str()
+                            )),
+                        );
+                    }
                     // Resolve parameter types and return type
                     let param_types: Vec<Type> = method
                         .params
@@ -440,28 +792,38 @@ impl<'a> TypeChecker<'a> {
                         self.symbols.lookup_local(self.current_scope, &method.name)
                     {
                         self.symbol_types.insert(method_symbol_id, method_type);
+                        self.record_required_args(method_symbol_id, &method.params);
                     }
                 }
                 ast::BackendMember::Command(cmd) => {
-                    // Commands return Unit (no return type)
+                    // Commands return Unit unless they declare a result
                     let param_types: Vec<Type> = cmd
                         .params
                         .iter()
                         .map(|p| self.resolve_type_expr(&p.type_expr, cmd.span))
                         .collect();
+                    let ret = match self.command_result(cmd) {
+                        Some(return_type) => self.resolve_type_expr(return_type, cmd.span),
+                        None => Type::Unit,
+                    };
                     let cmd_type = Type::Function {
                         params: param_types,
-                        ret: Box::new(Type::Unit),
+                        ret: Box::new(ret),
                     };
                     if let Some(cmd_symbol_id) =
                         self.symbols.lookup_local(self.current_scope, &cmd.name)
                     {
                         self.symbol_types.insert(cmd_symbol_id, cmd_type);
+                        self.record_required_args(cmd_symbol_id, &cmd.params);
                     }
                 }
             }
         }
 
+        if be.has_undo() {
+            self.check_undo(be);
+        }
+
         // Second pass: check all field initializers
         for member in &be.members {
             if let ast::BackendMember::Field(field) = member {
@@ -477,17 +839,128 @@ impl<'a> TypeChecker<'a> {
                             .cloned()
                             .unwrap_or(Type::Unknown);
                         // Check the initializer against the expected type
-                        let _init_type = self.check_expr_type(init, &expected_type);
-                        // TODO: Check that init_type is compatible with expected_type
+                        let init_type = self.check_expr_type(init, &expected_type);
+                        self.expect_assignable(&field.name, &expected_type, &init_type);
                     }
                 }
             }
         }
 
+        // Third pass: check method bodies against the declared results
+        for member in &be.members {
+            if let ast::BackendMember::Method(method) = member {
+                if let Some(body) = &method.body {
+                    self.check_method_body(method, body);
+                }
+            }
+        }
+
         self.current_scope = saved_scope;
         self.context_span = Span::default();
     }
 
+    /// Check the body of a method within its parameter scope, created by
+    /// the resolver as a child of the backend's body scope
+    fn check_method_body(&mut self, method: &ast::Method, body: &ast::Expr) {
+        let body_scope = self.current_scope;
+        let params_scope = self
+            .scopes
+            .child_at(body_scope, ScopeKind::Parameters, method.span);
+        let Some(params_scope) = params_scope else {
+            return;
+        };
+
+        self.context_span = method.span;
+        for param in &method.params {
+            let ty = self.resolve_type_expr(&param.type_expr, method.span);
+            if let Some(id) = self.symbols.lookup_local(params_scope, &param.name) {
+                self.symbol_types.insert(id, ty);
+            }
+        }
+        let expected = self.resolve_type_expr(&method.return_type, method.span);
+
+        self.current_scope = params_scope;
+        let actual = self.check_expr_type(body, &expected);
+        self.current_scope = body_scope;
+
+        if !operators::types_compatible(&expected, &actual) {
+            let checker = self.expr_checker();
+            let (expected, actual) = (checker.type_name(&expected), checker.type_name(&actual));
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0401,
+                    method.span,
+                    format!(
+                        "method `{}` returns `{}`, but its body has type `{}`",
+                        method.name, expected, actual
+                    ),
+                )
+                .with_help(format!(
+                    "change the body, or declare the method as returning `{}`",
+                    actual
+                )),
+            );
+        }
+    }
+
+    /// Check a backend keeping an undo history, within its body scope
+    fn check_undo(&mut self, be: &ast::Backend) {
+        // Undoable fields are copied into the history
+        for field in be.undoable_fields() {
+            let Some(id) = self.symbols.lookup_local(self.current_scope, &field.name) else {
+                continue;
+            };
+            let Some(ty) = self.symbol_types.get(&id) else {
+                continue;
+            };
+            if !ty.is_snapshotable() {
+                let ty = self.display_type(ty);
+                self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0607,
+                        field.span,
+                        format!(
+                            "undoable field `{}` has type {}, which cannot be snapshotted",
+                            field.name, ty
+                        ),
+                    )
+                    .with_help("only plain data (intrinsic types, schemes, enums, refs and their collections) can be undone"),
+                );
+            }
+        }
+
+        // `undo` and `redo` are generated
+        for member in &be.members {
+            let (name, span) = match member {
+                ast::BackendMember::Field(field) => (&field.name, field.span),
+                ast::BackendMember::Method(method) => (&method.name, method.span),
+                ast::BackendMember::Command(cmd) => (&cmd.name, cmd.span),
+                ast::BackendMember::Include(_) => continue,
+            };
+            if UNDO_COMMANDS.contains(&name.as_str()) {
+                self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0608,
+                        span,
+                        format!(
+                            "`{}` conflicts with the command generated for the undo history of `{}`",
+                            name, be.name
+                        ),
+                    )
+                    .with_help(format!("rename `{}`", name)),
+                );
+            }
+        }
+        for name in UNDO_COMMANDS {
+            if let Some(id) = self.symbols.lookup_local(self.current_scope, name) {
+                self.symbol_types.entry(id).or_insert(Type::Function {
+                    params: vec![],
+                    ret: Box::new(Type::Unit),
+                });
+            }
+        }
+    }
+
     fn check_blueprint(&mut self, bp: &ast::Blueprint, file: &ast::File) {
         // Enter the blueprint's body scope for local/field lookups
         let saved_scope = self.current_scope;
@@ -512,13 +985,20 @@ impl<'a> TypeChecker<'a> {
         // First pass: resolve types for `with` imported symbols and LocalDecl
         for stmt in &bp.body {
             match stmt {
-                ast::BlueprintStmt::With(backend_name) => {
+                ast::BlueprintStmt::With(with) => {
+                    let backend_name = &with.backend;
                     // Import types from the backend
                     // Look up from current scope to find both module-level backends and parameters
                     if let Some(backend_id) = self
                         .symbols
                         .lookup_in_scope_chain(self.current_scope, backend_name, self.scopes)
                     {
+                        // The slot holds the backend instance
+                        if let Some(slot) = &with.name {
+                            if let Some(slot_id) = self.symbols.lookup_local(self.current_scope, slot) {
+                                self.symbol_types.insert(slot_id, Type::Backend(backend_id));
+                            }
+                        }
                         if let Some(backend_symbol) = self.symbols.get(backend_id) {
                             if let Some(backend_body_scope) = backend_symbol.body_scope {
                                 // For each symbol in the backend, copy its type to the blueprint's imported symbol
@@ -538,6 +1018,9 @@ impl<'a> TypeChecker<'a> {
                                             self.symbols.lookup_local(self.current_scope, &member_name)
                                         {
                                             self.symbol_types.insert(blueprint_member_id, member_type);
+                                            if let Some(&required) = self.required_args.get(&backend_member_id) {
+                                                self.required_args.insert(blueprint_member_id, required);
+                                            }
                                         }
                                     }
                                 }
@@ -562,6 +1045,15 @@ impl<'a> TypeChecker<'a> {
                         self.symbol_types.insert(local_symbol_id, decl_type);
                     }
                 }
+                ast::BlueprintStmt::Consume(consume) => {
+                    // Providers are checked against this type once the module is typed
+                    let consume_type = self.resolve_type_expr(&consume.type_expr, consume.span);
+                    if let Some(symbol_id) =
+                        self.symbols.lookup_local(self.current_scope, &consume.name)
+                    {
+                        self.symbol_types.insert(symbol_id, consume_type);
+                    }
+                }
                 _ => {}
             }
         }
@@ -570,11 +1062,122 @@ impl<'a> TypeChecker<'a> {
         for stmt in &bp.body {
             self.check_blueprint_stmt(stmt);
         }
+        self.check_autofocus(bp);
 
         self.current_scope = saved_scope;
         self.context_span = Span::default();
     }
 
+    /// Check the arguments of a fragment creating a blueprint of the file
+    /// against its parameters, returning their types
+    ///
+    /// Backend slots are checked by the caller.
+    fn check_fragment_args(
+        &mut self,
+        frag: &ast::FragmentCreation,
+        params: &[BlueprintParam],
+        slots: &[(String, Type)],
+    ) -> Vec<Type> {
+        let mut types = Vec::new();
+        let mut given: Vec<&str> = Vec::new();
+        let mut positional = 0;
+        let mut named = false;
+
+        for arg in &frag.args {
+            let param = match &arg.name {
+                None => {
+                    if named {
+                        self.diagnostics.add(Diagnostic::from_code(
+                            &codes::E0702,
+                            self.context_span,
+                            format!("positional argument of `{}` after named arguments", frag.name),
+                        ));
+                        types.push(self.infer_expr_type(&arg.value));
+                        continue;
+                    }
+                    positional += 1;
+                    params.get(positional - 1)
+                }
+                Some(name) => {
+                    named = true;
+                    let param = params.iter().find(|p| p.name == *name);
+                    if param.is_none() && !slots.iter().any(|(slot, _)| slot == name) {
+                        let mut diagnostic = Diagnostic::from_code(
+                            &codes::E0301,
+                            self.context_span,
+                            format!("no parameter `{}` on blueprint `{}`", name, frag.name),
+                        );
+                        if let Some(similar) =
+                            crate::diagnostic::similar_name(name, params.iter().map(|p| p.name.as_str()))
+                        {
+                            diagnostic = diagnostic.with_help(format!("did you mean `{}`?", similar));
+                        }
+                        self.diagnostics.add(diagnostic);
+                    }
+                    param
+                }
+            };
+            let Some(param) = param else {
+                types.push(self.infer_expr_type(&arg.value));
+                continue;
+            };
+
+            if given.contains(&param.name.as_str()) {
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0702,
+                    self.context_span,
+                    format!("parameter `{}` of `{}` is given twice", param.name, frag.name),
+                ));
+            }
+            given.push(&param.name);
+
+            let arg_type = self.check_expr_type(&arg.value, &param.ty);
+            let is_slot = slots.iter().any(|(slot, _)| *slot == param.name);
+            if !is_slot && !operators::types_compatible(&param.ty, &arg_type) {
+                let checker = self.expr_checker();
+                let message = format!(
+                    "`{}` of `{}` expects `{}`, found `{}`",
+                    param.name,
+                    frag.name,
+                    checker.type_name(&param.ty),
+                    checker.type_name(&arg_type)
+                );
+                self.diagnostics
+                    .add(Diagnostic::from_code(&codes::E0401, self.context_span, message));
+            }
+            types.push(arg_type);
+        }
+
+        if positional > params.len() {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0702,
+                self.context_span,
+                format!(
+                    "`{}` takes at most {} arguments, found {}",
+                    frag.name,
+                    params.len(),
+                    positional
+                ),
+            ));
+        }
+        let missing: Vec<String> = params
+            .iter()
+            .filter(|p| p.required && !given.contains(&p.name.as_str()))
+            .map(|p| format!("`{}`", p.name))
+            .collect();
+        if !missing.is_empty() {
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0702,
+                    self.context_span,
+                    format!("missing arguments of `{}`: {}", frag.name, missing.join(", ")),
+                )
+                .with_help("give them, or declare defaults for them in the blueprint"),
+            );
+        }
+        types
+    }
+
     /// Check for conflicts between blueprint parameters and backend fields with the same name.
     /// Reports errors if:
     /// - Types don't match
@@ -652,20 +1255,49 @@ impl<'a> TypeChecker<'a> {
                         .cloned()
                         .unwrap_or(Type::Unknown);
                     // Check the initializer against the expected type
-                    let _init_type = self.check_expr_type(&decl.init, &expected_type);
-                    // TODO: Check that init_type is compatible with expected_type
+                    let init_type = self.check_expr_type(&decl.init, &expected_type);
+                    self.expect_assignable(&decl.name, &expected_type, &init_type);
                 } else {
                     let _init_type = self.infer_expr_type(&decl.init);
                 }
             }
             ast::BlueprintStmt::FragmentCreation(frag) => {
-                for arg in &frag.args {
-                    self.infer_expr_type(&arg.value);
+                self.context_span = frag.span;
+                let slots = self.backend_slots.get(&frag.name).cloned().unwrap_or_default();
+                let arg_types = match self.blueprint_params.get(&frag.name).cloned() {
+                    Some(params) => self.check_fragment_args(frag, &params, &slots),
+                    None => frag.args.iter().map(|arg| self.infer_expr_type(&arg.value)).collect(),
+                };
+                for (arg, arg_type) in frag.args.iter().zip(arg_types) {
+
+                    // Injected backends must be instances of the slot's backend
+                    let slot = arg
+                        .name
+                        .as_ref()
+                        .and_then(|name| slots.iter().find(|(slot, _)| slot == name));
+                    if let Some((slot, expected)) = slot {
+                        if arg_type != Type::Unknown && arg_type != *expected {
+                            self.diagnostics.add(Diagnostic::from_code(
+                                &codes::E0401,
+                                self.context_span,
+                                format!(
+                                    "`{}` of `{}` expects {}, found {}",
+                                    slot,
+                                    frag.name,
+                                    self.display_type(expected),
+                                    self.display_type(&arg_type)
+                                ),
+                            ));
+                        }
+                    }
                 }
+                self.check_virtualize_container(frag);
+                self.check_portal(frag);
                 if let Some(body) = &frag.body {
                     self.check_fragment_body(body);
                 }
                 // Check postfix items (instructions, event handlers)
+                self.context_span = frag.span;
                 for postfix in &frag.postfix {
                     match postfix {
                         ast::PostfixItem::Instruction(instr) => self.check_instruction_expr(instr),
@@ -676,6 +1308,15 @@ impl<'a> TypeChecker<'a> {
             ast::BlueprintStmt::Control(ctrl) => self.check_control_stmt(ctrl),
             ast::BlueprintStmt::Instruction(instr) => self.check_instruction_expr(instr),
             ast::BlueprintStmt::EventHandler(handler) => self.check_event_handler(handler),
+            ast::BlueprintStmt::Shortcut(shortcut) => {
+                self.context_span = shortcut.span;
+                self.check_handler_body(&shortcut.body)
+            }
+            ast::BlueprintStmt::Provide(provide) => {
+                // The value's type is recorded at the statement's span for the context checks
+                self.context_span = provide.span;
+                self.infer_expr_type(&provide.value);
+            }
             ast::BlueprintStmt::ContentExpr(expr) => {
                 self.infer_expr_type(expr);
             }
@@ -707,6 +1348,198 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
+    /// Check the branches of a select on an enum
+    ///
+    /// A variant matched a second time is unreachable (E0411). Unless there is
+    /// an `else` branch, every variant must be matched (E0410); a branch with a
+    /// guard expression instead of a variant may match anything, so coverage
+    /// is not checked then.
+    fn check_select_variants(
+        &mut self,
+        enum_id: SymbolId,
+        branches: &[ast::SelectBranch],
+        has_else: bool,
+        span: Span,
+    ) {
+        let Some(enum_symbol) = self.symbols.get(enum_id) else {
+            return;
+        };
+        let Some(body_scope) = enum_symbol.body_scope else {
+            return;
+        };
+        let enum_name = enum_symbol.name.clone();
+
+        let mut matched: Vec<(&str, Span)> = Vec::new();
+        let mut guarded = false;
+        for branch in branches {
+            let Some(variant) = select_variant(&branch.condition, &enum_name) else {
+                guarded = true;
+                continue;
+            };
+            if let Some((_, first)) = matched.iter().find(|(name, _)| *name == variant) {
+                self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0411,
+                        branch.span,
+                        format!("unreachable branch: `{}` is already matched", variant),
+                    )
+                    .with_related(RelatedInfo::new(*first, format!("`{}` matched here", variant)))
+                    .unnecessary(),
+                );
+            } else {
+                matched.push((variant, branch.span));
+            }
+        }
+        if has_else || guarded {
+            return;
+        }
+
+        let missing: Vec<String> = self
+            .symbols
+            .symbols_in_scope(body_scope)
+            .filter(|s| s.kind == SymbolKind::EnumVariant)
+            .filter(|s| !matched.iter().any(|(name, _)| *name == s.name))
+            .map(|s| s.name.clone())
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let listed = missing
+            .iter()
+            .map(|variant| format!("`{}`", variant))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let new_branches: String = missing
+            .iter()
+            .map(|variant| format!("\n{} => {{ }}", variant))
+            .collect();
+        // After the last branch, or before the closing brace
+        let insert_at = branches.last().map_or(span.end.saturating_sub(1), |b| b.span.end);
+        self.diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0410,
+                span,
+                format!("select on `{}` does not match {}", enum_name, listed),
+            )
+            .with_suggestion(Suggestion::insert(
+                insert_at,
+                new_branches,
+                format!("add branches for {}", listed),
+            ))
+            .with_help("match the missing variants, or add an `else` branch"),
+        );
+    }
+
+    /// Give the names `pattern` binds in `scope` the types of the parts of
+    /// `ty`; a tuple pattern destructures the entries of a map
+    fn bind_pattern(&mut self, pattern: &ast::Pattern, ty: Type, scope: ScopeId, span: Span) {
+        let parts = match pattern {
+            ast::Pattern::Binding(name) => {
+                if let Some(id) = self.symbols.lookup_local(scope, name) {
+                    self.symbol_types.insert(id, ty);
+                }
+                return;
+            }
+            ast::Pattern::Wildcard => return,
+            ast::Pattern::Tuple(parts) => parts,
+        };
+
+        let types = match ty {
+            Type::Map(key, value) if parts.len() == 2 => vec![*key, *value],
+            Type::Map(_, _) => {
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0416,
+                    span,
+                    format!(
+                        "map entries have 2 parts, but the pattern `{}` has {}",
+                        pattern,
+                        parts.len()
+                    ),
+                ));
+                Vec::new()
+            }
+            Type::Unknown | Type::Error => Vec::new(),
+            other => {
+                self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0416,
+                        span,
+                        format!("pattern `{}` cannot destructure `{}`", pattern, other),
+                    )
+                    .with_help("only the entries of a map are destructured, as `(key, value)`"),
+                );
+                Vec::new()
+            }
+        };
+        let types = types.into_iter().chain(std::iter::repeat(Type::Unknown));
+        for (part, ty) in parts.iter().zip(types) {
+            self.bind_pattern(part, ty, scope, span);
+        }
+    }
+
+    /// Bind the payload patterns of a select branch to the types of the data
+    /// of the variant it matches
+    fn bind_payload(&mut self, disc_type: Option<&Type>, branch: &ast::SelectBranch, scope: ScopeId) {
+        let types = match disc_type {
+            Some(Type::Enum(enum_id)) => self.variant_payload(*enum_id, branch),
+            Some(Type::Unknown | Type::Error) => Vec::new(),
+            other => {
+                let message = match other {
+                    Some(ty) => format!("payload patterns need a select on an enum, found `{}`", ty),
+                    None => "payload patterns need a select on an enum".to_string(),
+                };
+                self.diagnostics.add(Diagnostic::from_code(&codes::E0416, branch.span, message));
+                Vec::new()
+            }
+        };
+        let types = types.into_iter().chain(std::iter::repeat(Type::Unknown));
+        for (pattern, ty) in branch.payload.iter().zip(types) {
+            self.bind_pattern(pattern, ty, scope, branch.span);
+        }
+    }
+
+    /// Types of the data of the variant of `enum_id` a select branch matches,
+    /// checking that the branch destructures all of it
+    fn variant_payload(&mut self, enum_id: SymbolId, branch: &ast::SelectBranch) -> Vec<Type> {
+        let Some(symbol) = self.symbols.get(enum_id) else {
+            return Vec::new();
+        };
+        let Some(variant) = select_variant(&branch.condition, &symbol.name) else {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0416,
+                branch.span,
+                "payload patterns need a variant to destructure",
+            ));
+            return Vec::new();
+        };
+        // An unknown variant is reported where the branch is checked
+        let Some(variant_id) = symbol
+            .body_scope
+            .and_then(|scope| self.symbols.lookup_local(scope, variant))
+        else {
+            return Vec::new();
+        };
+
+        let types = match self.symbol_types.get(&variant_id) {
+            Some(Type::Function { params, .. }) => params.clone(),
+            _ => Vec::new(),
+        };
+        if types.len() != branch.payload.len() {
+            let message = if types.is_empty() {
+                format!("variant `{}` carries no data to destructure", variant)
+            } else {
+                format!(
+                    "variant `{}` carries {} values, but the branch destructures {}",
+                    variant,
+                    types.len(),
+                    branch.payload.len()
+                )
+            };
+            self.diagnostics.add(Diagnostic::from_code(&codes::E0416, branch.span, message));
+        }
+        types
+    }
+
     fn check_control_stmt(&mut self, ctrl: &ast::ControlStmt) {
         match ctrl {
             ast::ControlStmt::When {
@@ -716,36 +1549,40 @@ impl<'a> TypeChecker<'a> {
             } => {
                 let cond_type = self.infer_expr_type(condition);
                 operators::expect_bool(&cond_type, self.context_span, &mut self.diagnostics);
-                self.check_blueprint_stmt(then_stmt);
+                self.with_narrowed(narrowing::non_null_when(condition, true), |checker| {
+                    checker.check_blueprint_stmt(then_stmt)
+                });
                 if let Some(else_stmt) = else_stmt {
-                    self.check_blueprint_stmt(else_stmt);
+                    self.with_narrowed(narrowing::non_null_when(condition, false), |checker| {
+                        checker.check_blueprint_stmt(else_stmt)
+                    });
                 }
             }
             ast::ControlStmt::Repeat {
                 iterable,
-                item_name,
+                item,
                 key_expr,
                 body,
             } => {
                 let iter_type = self.infer_expr_type(iterable);
                 operators::expect_iterable(&iter_type, self.context_span, &mut self.diagnostics);
 
-                // Get element type from iterable and assign to loop variables
-                let element_type = iter_type
-                    .element_type()
-                    .cloned()
-                    .unwrap_or(Type::Unknown);
+                // Get element type from iterable and assign to loop variables;
+                // the items of a map are its entries, destructured by `(key, value)`
+                let item_type = match (item, iter_type.base_type()) {
+                    (ast::Pattern::Tuple(_), Type::Map(_, _)) => iter_type.base_type().clone(),
+                    _ => iter_type.element_type().cloned().unwrap_or(Type::Unknown),
+                };
 
-                // Find the loop scope by looking up the item variable in children
+                // Find the loop scope by looking up the first loop variable in children
                 // (the loop scope is created as a child of current_scope during resolve)
                 let saved_scope = self.current_scope;
-                if let Some((item_id, loop_scope)) = self.symbols.lookup_in_children(
-                    self.current_scope,
-                    item_name,
-                    self.scopes,
-                ) {
-                    // Set the type of the loop variable
-                    self.symbol_types.insert(item_id, element_type);
+                let loop_scope = item.bindings().first().and_then(|name| {
+                    self.symbols.lookup_in_children(self.current_scope, name, self.scopes)
+                });
+                if let Some((_, loop_scope)) = loop_scope {
+                    // Set the types of the loop variables
+                    self.bind_pattern(item, item_type, loop_scope, self.context_span);
 
                     // Enter the loop scope for checking the body
                     self.current_scope = loop_scope;
@@ -763,6 +1600,7 @@ impl<'a> TypeChecker<'a> {
                 discriminant,
                 branches,
                 else_branch,
+                span,
             } => {
                 // Infer discriminant type if present
                 let disc_type = discriminant.as_ref().map(|d| self.infer_expr_type(d));
@@ -797,11 +1635,73 @@ impl<'a> TypeChecker<'a> {
                         // Regular expression condition
                         self.infer_expr_type(&branch.condition);
                     }
+                    if branch.payload.is_empty() {
+                        self.check_blueprint_stmt(&branch.body);
+                        continue;
+                    }
+
+                    // Payload bindings are in a scope created at the branch's span
+                    let saved_scope = self.current_scope;
+                    let branch_scope =
+                        self.scopes
+                            .child_at(self.current_scope, ScopeKind::Block, branch.span);
+                    if let Some(branch_scope) = branch_scope {
+                        self.bind_payload(disc_type.as_ref(), branch, branch_scope);
+                        self.current_scope = branch_scope;
+                    }
                     self.check_blueprint_stmt(&branch.body);
+                    self.current_scope = saved_scope;
                 }
                 if let Some(else_stmt) = else_branch {
                     self.check_blueprint_stmt(else_stmt);
                 }
+                if let Some(Type::Enum(enum_id)) = disc_type {
+                    self.check_select_variants(enum_id, branches, else_branch.is_some(), *span);
+                }
+            }
+            ast::ControlStmt::OnError {
+                body,
+                error_name,
+                fallback,
+                span,
+            } => {
+                for stmt in body {
+                    self.check_blueprint_stmt(stmt);
+                }
+
+                let Some(fallback) = fallback.as_ref().filter(|f| !f.is_empty()) else {
+                    let message = if fallback.is_some() {
+                        "error boundary has an empty fallback"
+                    } else {
+                        "error boundary has no fallback"
+                    };
+                    self.diagnostics.add(
+                        Diagnostic::from_code(&codes::E0712, *span, message).with_help(
+                            "render something in its place: `fallback { error -> text { error } }`",
+                        ),
+                    );
+                    return;
+                };
+
+                // The error message is bound in a child scope, as in `repeat`
+                let saved_scope = self.current_scope;
+                if let Some(name) = error_name {
+                    if let Some((error_id, fallback_scope)) =
+                        self.symbols.lookup_in_children(self.current_scope, name, self.scopes)
+                    {
+                        self.symbol_types.insert(error_id, Type::String);
+                        self.current_scope = fallback_scope;
+                    }
+                }
+                for stmt in fallback {
+                    self.check_blueprint_stmt(stmt);
+                }
+                self.current_scope = saved_scope;
+            }
+            ast::ControlStmt::Loading { body, .. } => {
+                for stmt in body {
+                    self.check_blueprint_stmt(stmt);
+                }
             }
         }
     }
@@ -814,6 +1714,25 @@ impl<'a> TypeChecker<'a> {
                 // Set context span for error reporting
                 self.context_span = inst.span;
 
+                if inst.name == VIRTUALIZE {
+                    self.check_virtualize(inst);
+                    return;
+                }
+                if inst.name == TAB_INDEX {
+                    for (_, expr) in &inst.params {
+                        let ty = self.infer_expr_type(expr);
+                        operators::expect_integer(&ty, self.context_span, &mut self.diagnostics);
+                    }
+                    return;
+                }
+                if inst.name == PORTAL {
+                    for (_, expr) in &inst.params {
+                        let ty = self.infer_expr_type(expr);
+                        operators::expect_string(&ty, self.context_span, &mut self.diagnostics);
+                    }
+                    return;
+                }
+
                 for (param_name, expr) in &inst.params {
                     // Check if this is a simple identifier that should be validated as a keyword
                     if let ast::Expr::Identifier(value) = expr {
@@ -829,14 +1748,28 @@ impl<'a> TypeChecker<'a> {
                                     registry.valid_keywords(&inst.name, param_name)
                                 {
                                     let expected = valid_keywords.join(", ");
-                                    self.diagnostics.add(Diagnostic::from_code(
+                                    let mut diagnostic = Diagnostic::from_code(
                                         &codes::E0705,
                                         self.context_span,
                                         format!(
                                             "invalid value '{}' for '{}' instruction, expected one of: {}",
                                             value, inst.name, expected
                                         ),
-                                    ));
+                                    );
+                                    let aliases = registry.aliases(&inst.name, param_name);
+                                    if !aliases.is_empty() {
+                                        let aliases: Vec<String> = aliases
+                                            .iter()
+                                            .map(|(alias, keyword)| {
+                                                format!("`{}` for `{}`", alias, keyword)
+                                            })
+                                            .collect();
+                                        diagnostic = diagnostic.with_help(format!(
+                                            "aliases are also accepted: {}",
+                                            aliases.join(", ")
+                                        ));
+                                    }
+                                    self.diagnostics.add(diagnostic);
                                 }
                             }
                         } else {
@@ -856,9 +1789,13 @@ impl<'a> TypeChecker<'a> {
             } => {
                 let cond_type = self.infer_expr_type(condition);
                 operators::expect_bool(&cond_type, self.context_span, &mut self.diagnostics);
-                self.check_instruction_expr(then_instr);
+                self.with_narrowed(narrowing::non_null_when(condition, true), |checker| {
+                    checker.check_instruction_expr(then_instr)
+                });
                 if let Some(else_instr) = else_instr {
-                    self.check_instruction_expr(else_instr);
+                    self.with_narrowed(narrowing::non_null_when(condition, false), |checker| {
+                        checker.check_instruction_expr(else_instr)
+                    });
                 }
             }
             ast::InstructionExpr::Ternary {
@@ -868,8 +1805,12 @@ impl<'a> TypeChecker<'a> {
             } => {
                 let cond_type = self.infer_expr_type(condition);
                 operators::expect_bool(&cond_type, self.context_span, &mut self.diagnostics);
-                self.check_instruction_expr(then_instr);
-                self.check_instruction_expr(else_instr);
+                self.with_narrowed(narrowing::non_null_when(condition, true), |checker| {
+                    checker.check_instruction_expr(then_instr)
+                });
+                self.with_narrowed(narrowing::non_null_when(condition, false), |checker| {
+                    checker.check_instruction_expr(else_instr)
+                });
             }
             ast::InstructionExpr::Reference(expr) => {
                 self.infer_expr_type(expr);
@@ -877,40 +1818,316 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
-    fn check_event_handler(&mut self, handler: &ast::EventHandler) {
-        for stmt in &handler.body {
-            match stmt {
-                ast::HandlerStmt::Assignment { value, .. } => {
-                    self.infer_expr_type(value);
-                    // TODO: Check that value is compatible with target
-                }
-                ast::HandlerStmt::CommandCall { args, .. } => {
-                    for arg in args {
-                        self.infer_expr_type(arg);
-                    }
-                    // TODO: Validate this is a command, not a method (E0603)
-                }
+    /// Check the parameters of `virtualize`: numbers, with exactly one way to size items
+    fn check_virtualize(&mut self, inst: &ast::Instruction) {
+        let registry = instruction_registry();
+        let mut sizes = 0;
+        for (param_name, expr) in &inst.params {
+            if !registry.has_param(VIRTUALIZE, param_name) {
+                self.diagnostics.add(Diagnostic::from_code(
+                    &codes::E0706,
+                    self.context_span,
+                    format!(
+                        "unknown parameter '{}' for 'virtualize', expected item_height, estimate or overscan",
+                        param_name
+                    ),
+                ));
+                continue;
+            }
+            if param_name != "overscan" {
+                sizes += 1;
             }
+            let ty = self.infer_expr_type(expr);
+            operators::expect_numeric(&ty, self.context_span, &mut self.diagnostics);
+        }
+
+        if sizes != 1 {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0706,
+                self.context_span,
+                "'virtualize' needs either a fixed 'item_height' or an 'estimate'",
+            ));
         }
     }
 
-    fn check_scheme(&mut self, sc: &ast::Scheme) {
-        // Enter the scheme's body scope for field lookups
-        let saved_scope = self.current_scope;
-        if let Some(symbol_id) = self.symbols.lookup_local(ScopeId::ROOT, &sc.name) {
-            if let Some(symbol) = self.symbols.get(symbol_id) {
-                if let Some(body_scope) = symbol.body_scope {
-                    self.current_scope = body_scope;
-                }
-            }
+    /// A fragment with `virtualize` must window the items of a `repeat` in its body
+    fn check_virtualize_container(&mut self, frag: &ast::FragmentCreation) {
+        let stmts = match &frag.body {
+            Some(ast::FragmentBody::Default(stmts)) => stmts.as_slice(),
+            _ => &[],
+        };
+        let Some(span) = fragment_instructions(frag).find_map(virtualize_span) else {
+            return;
+        };
+
+        let has_repeat = stmts.iter().any(|stmt| {
+            matches!(stmt, ast::BlueprintStmt::Control(ast::ControlStmt::Repeat { .. }))
+        });
+        if !has_repeat {
+            self.diagnostics.add(Diagnostic::from_code(
+                &codes::E0706,
+                span,
+                format!("'virtualize' on `{}` has no `repeat` to window", frag.name),
+            ));
         }
+    }
 
-        // First pass: resolve all field types and store in symbol_types
-        // This is needed so that field references in virtual field expressions can be resolved
-        for member in &sc.members {
-            match member {
-                ast::SchemeMember::Field(field) => {
-                    let field_type = self.resolve_type_expr(&field.type_expr, field.span);
+    /// A fragment with `portal` is laid out by its overlay container, so it
+    /// can't be placed in its parent's layout
+    fn check_portal(&mut self, frag: &ast::FragmentCreation) {
+        let Some(portal) = fragment_instructions(frag).find_map(portal_span) else {
+            return;
+        };
+
+        let mut found = Vec::new();
+        for instr in fragment_instructions(frag) {
+            layout_relative(instr, portal, &mut found);
+        }
+        for (name, span) in found {
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0714,
+                    span,
+                    format!(
+                        "`{}` places `{}` in its parent's layout, but `portal` renders it in an overlay",
+                        name, frag.name
+                    ),
+                )
+                .with_help("remove it, or align the fragment to its parent with `align_relative`"),
+            );
+        }
+    }
+
+    /// Declared result of a command, if its feature gate is enabled
+    ///
+    /// Without the gate the parser has already reported the result, so it is
+    /// not typed to avoid follow-up errors.
+    fn command_result<'c>(&self, cmd: &'c ast::Command) -> Option<&'c TypeExpr> {
+        cmd.return_type
+            .as_ref()
+            .filter(|_| self.features.is_enabled(Experimental::CommandResults))
+    }
+
+    /// Only one fragment of a blueprint instance may take focus when created
+    fn check_autofocus(&mut self, bp: &ast::Blueprint) {
+        let fragments = autofocus_fragments(&bp.body, &bp.name, false);
+
+        if let Some((name, _)) = fragments.iter().find(|(_, in_repeat)| *in_repeat) {
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0709,
+                    bp.span,
+                    format!(
+                        "`autofocus` on `{}` is inside a `repeat`, every item of `{}` would take focus",
+                        name, bp.name
+                    ),
+                )
+                .with_help(
+                    "move `autofocus` out of the `repeat` or set it on a single item with `when`",
+                ),
+            );
+        }
+
+        if fragments.len() > 1 {
+            let names = fragments
+                .iter()
+                .map(|(name, _)| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0709,
+                    bp.span,
+                    format!(
+                        "`autofocus` is set on more than one fragment of `{}`: {}",
+                        bp.name, names
+                    ),
+                )
+                .with_help(
+                    "only the last rendered fragment would take focus, keep `autofocus` on one of them",
+                ),
+            );
+        }
+    }
+
+    /// Check a handler against the event registry, then its body
+    fn check_event_handler(&mut self, handler: &ast::EventHandler) {
+        let name_span = Span {
+            end: handler.span.start + handler.event_name.len() as u32,
+            ..handler.span
+        };
+        match event_registry().get(&handler.event_name) {
+            None => {
+                let mut diagnostic = Diagnostic::from_code(
+                    &codes::E0716,
+                    name_span,
+                    format!("unknown event `{}`", handler.event_name),
+                );
+                let names = event_registry().names();
+                let similar = crate::diagnostic::similar_name(&handler.event_name, names);
+                if let Some(similar) = similar {
+                    diagnostic = diagnostic
+                        .with_suggestion(Suggestion::new(
+                            name_span,
+                            similar,
+                            format!("use `{}`", similar),
+                        ))
+                        .with_help(format!("did you mean `{}`?", similar));
+                }
+                self.diagnostics.add(diagnostic);
+            }
+            Some(event) => match (&handler.param, event.payload) {
+                (Some(param), None) => self.diagnostics.add(
+                    Diagnostic::from_code(
+                        &codes::E0717,
+                        handler.span,
+                        format!("`{}` passes no value, but the handler takes `{}`", event.name, param.name),
+                    )
+                    .with_help(format!("remove `{} ->`", param.name)),
+                ),
+                (Some(param), Some(payload)) => {
+                    let declared = param.type_expr.as_ref().map(ToString::to_string);
+                    if let Some(declared) = declared.filter(|declared| declared != payload) {
+                        self.diagnostics.add(
+                            Diagnostic::from_code(
+                                &codes::E0717,
+                                handler.span,
+                                format!(
+                                    "`{}` passes `{}`, but `{}` is declared as `{}`",
+                                    event.name, payload, param.name, declared
+                                ),
+                            )
+                            .with_help(format!("declare it as `{}: {}`", param.name, payload)),
+                        );
+                    }
+                }
+                (None, _) => {}
+            },
+        }
+        self.check_handler_body(&handler.body);
+    }
+
+    fn check_handler_body(&mut self, body: &[ast::HandlerStmt]) {
+        // Statements of the handler can make a narrowed path null again
+        let saved = self.narrowed.clone();
+        self.in_handler = true;
+        for stmt in body {
+            match stmt {
+                ast::HandlerStmt::Assignment { name, value } => {
+                    let target = resolution::lookup_identifier_type(
+                        name,
+                        self.current_scope,
+                        self.symbols,
+                        self.scopes,
+                        &self.symbol_types,
+                    );
+                    let value_type = self.check_expr_type(value, &target);
+                    self.expect_assignable(name, &target, &value_type);
+                    self.narrowed.retain(|path| !narrowing::invalidates(name, path));
+                }
+                ast::HandlerStmt::CommandCall { name, args } => {
+                    self.check_command_call(name, args);
+                    self.narrowed.clear();
+                }
+            }
+        }
+        self.in_handler = false;
+        self.narrowed = saved;
+    }
+
+    /// Check a call statement of an event handler: a command, with its arguments
+    fn check_command_call(&mut self, name: &str, args: &[ast::Expr]) {
+        let symbol = self
+            .symbols
+            .lookup_in_scope_chain(self.current_scope, name, self.scopes);
+        if let Some(method) = symbol
+            .and_then(|id| self.symbols.get(id))
+            .filter(|s| s.kind == SymbolKind::Method)
+        {
+            self.diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0604,
+                    self.context_span,
+                    format!("method `{}` called as a statement of an event handler", method.name),
+                )
+                .with_help("methods are pure and their result would be dropped; call a command instead"),
+            );
+        }
+        let callee_type = symbol.and_then(|id| self.symbol_types.get(&id)).cloned();
+        let mut checker = self.expr_checker();
+        match &callee_type {
+            Some(Type::Function { params, .. }) => checker.check_call_args(name, symbol, params, args),
+            _ => {
+                for arg in args {
+                    checker.infer_expr_type(arg);
+                }
+            }
+        }
+        self.merge_checker(checker.finish());
+    }
+
+    /// Report a value of type `actual` given to `name` of type `expected`
+    fn expect_assignable(&mut self, name: &str, expected: &Type, actual: &Type) {
+        if operators::types_compatible(expected, actual) {
+            return;
+        }
+        let checker = self.expr_checker();
+        let mut diagnostic = Diagnostic::from_code(
+            &codes::E0401,
+            self.context_span,
+            format!(
+                "`{}` has type `{}`, found `{}`",
+                name,
+                checker.type_name(expected),
+                checker.type_name(actual)
+            ),
+        );
+        if let Type::Nullable(inner) = actual {
+            if operators::types_compatible(expected, inner) {
+                diagnostic = diagnostic.with_help(format!(
+                    "the value may be null; give a default with `?:`, or declare `{}` as `{}?`",
+                    name,
+                    checker.type_name(expected)
+                ));
+            }
+        }
+        self.diagnostics.add(diagnostic);
+    }
+
+    /// Remember how many of `params` a call must give
+    fn record_required_args(&mut self, id: SymbolId, params: &[ast::Parameter]) {
+        let required = params.iter().filter(|p| p.default.is_none()).count();
+        self.required_args.insert(id, required);
+    }
+
+    /// Run `check` with `paths` known not to be null
+    fn with_narrowed(&mut self, paths: Vec<String>, check: impl FnOnce(&mut Self)) {
+        if paths.is_empty() {
+            return check(self);
+        }
+        let saved = self.narrowed.clone();
+        self.narrowed.extend(paths);
+        check(self);
+        self.narrowed = saved;
+    }
+
+    fn check_scheme(&mut self, sc: &ast::Scheme) {
+        // Enter the scheme's body scope for field lookups
+        let saved_scope = self.current_scope;
+        if let Some(symbol_id) = self.symbols.lookup_local(ScopeId::ROOT, &sc.name) {
+            if let Some(symbol) = self.symbols.get(symbol_id) {
+                if let Some(body_scope) = symbol.body_scope {
+                    self.current_scope = body_scope;
+                }
+            }
+        }
+
+        // First pass: resolve all field types and store in symbol_types
+        // This is needed so that field references in virtual field expressions can be resolved
+        for member in &sc.members {
+            match member {
+                ast::SchemeMember::Field(field) => {
+                    let field_type = self.resolve_type_expr(&field.type_expr, field.span);
                     if let Some(field_symbol_id) =
                         self.symbols.lookup_local(self.current_scope, &field.name)
                     {
@@ -942,8 +2159,8 @@ impl<'a> TypeChecker<'a> {
                         .cloned()
                         .unwrap_or(Type::Unknown);
                     // Check the expression against the expected type
-                    let _expr_type = self.check_expr_type(&virt.expr, &expected_type);
-                    // TODO: Check that expr_type is compatible with expected_type
+                    let expr_type = self.check_expr_type(&virt.expr, &expected_type);
+                    self.expect_assignable(&virt.name, &expected_type, &expr_type);
                 }
             }
         }
@@ -958,39 +2175,265 @@ impl<'a> TypeChecker<'a> {
 
     /// Check an expression against an expected type (bidirectional type checking)
     pub fn check_expr_type(&mut self, expr: &ast::Expr, expected: &Type) -> Type {
-        let mut checker = expressions::ExprChecker::new(
-            self.scopes,
-            self.symbols,
-            &self.symbol_types,
-            self.current_scope,
-            self.context_span,
-        );
+        let mut checker = self.expr_checker();
         let ty = checker.check_expr_type(expr, expected);
-
-        // Merge results back
-        self.expr_types.extend(checker.expr_types);
-        self.diagnostics.merge(checker.diagnostics);
+        self.merge_checker(checker.finish());
         ty
     }
 
     /// Infer the type of an expression
     pub fn infer_expr_type(&mut self, expr: &ast::Expr) -> Type {
+        let mut checker = self.expr_checker();
+        let ty = checker.infer_expr_type(expr);
+        self.merge_checker(checker.finish());
+        ty
+    }
+
+    /// An expression checker for the current scope and statement
+    fn expr_checker(&self) -> expressions::ExprChecker<'_> {
         let mut checker = expressions::ExprChecker::new(
             self.scopes,
             self.symbols,
             &self.symbol_types,
+            self.imports,
             self.current_scope,
             self.context_span,
         );
-        let ty = checker.infer_expr_type(expr);
+        checker.narrowed = self.narrowed.clone();
+        checker.required_args = Some(&self.required_args);
+        checker.in_handler = self.in_handler;
+        checker
+    }
 
-        // Merge results back
-        self.expr_types.extend(checker.expr_types);
-        self.diagnostics.merge(checker.diagnostics);
-        ty
+    /// Merge the types and diagnostics of an expression checker back
+    fn merge_checker(&mut self, checked: expressions::Checked) {
+        self.expr_types.extend(checked.expr_types);
+        self.names.extend(checked.names);
+        self.diagnostics.merge(checked.diagnostics);
+    }
+}
+
+/// Span of a `virtualize` instruction, including one inside a conditional
+fn virtualize_span(instr: &ast::InstructionExpr) -> Option<Span> {
+    match instr {
+        ast::InstructionExpr::Simple(inst) => (inst.name == VIRTUALIZE).then_some(inst.span),
+        ast::InstructionExpr::When {
+            then_instr,
+            else_instr,
+            ..
+        } => virtualize_span(then_instr)
+            .or_else(|| else_instr.as_deref().and_then(virtualize_span)),
+        ast::InstructionExpr::Ternary {
+            then_instr,
+            else_instr,
+            ..
+        } => virtualize_span(then_instr).or_else(|| virtualize_span(else_instr)),
+        ast::InstructionExpr::Reference(_) => None,
+    }
+}
+
+/// Instructions of a fragment, written inside its body or as postfix
+fn fragment_instructions(
+    frag: &ast::FragmentCreation,
+) -> impl Iterator<Item = &ast::InstructionExpr> {
+    let stmts = match &frag.body {
+        Some(ast::FragmentBody::Default(stmts)) => stmts.as_slice(),
+        _ => &[],
+    };
+    let inner = stmts.iter().filter_map(|stmt| match stmt {
+        ast::BlueprintStmt::Instruction(instr) => Some(instr),
+        _ => None,
+    });
+    let postfix = frag.postfix.iter().filter_map(|item| match item {
+        ast::PostfixItem::Instruction(instr) => Some(instr),
+        _ => None,
+    });
+    inner.chain(postfix)
+}
+
+/// Span of a `portal` instruction, including inside a conditional; a bare
+/// `.. portal` has no span of its own and gets the default one
+fn portal_span(instr: &ast::InstructionExpr) -> Option<Span> {
+    match instr {
+        ast::InstructionExpr::Simple(inst) => (inst.name == PORTAL).then_some(inst.span),
+        ast::InstructionExpr::When {
+            then_instr,
+            else_instr,
+            ..
+        } => portal_span(then_instr).or_else(|| else_instr.as_deref().and_then(portal_span)),
+        ast::InstructionExpr::Ternary {
+            then_instr,
+            else_instr,
+            ..
+        } => portal_span(then_instr).or_else(|| portal_span(else_instr)),
+        ast::InstructionExpr::Reference(expr) => {
+            matches!(expr, ast::Expr::Identifier(name) if name == PORTAL).then(Span::default)
+        }
+    }
+}
+
+/// Instructions placing a fragment in its parent's layout, with their spans;
+/// shorthands without a span of their own get `span`
+fn layout_relative<'i>(
+    instr: &'i ast::InstructionExpr,
+    span: Span,
+    found: &mut Vec<(&'i str, Span)>,
+) {
+    let relative = |name: &str, params: &[(String, ast::Expr)]| match name {
+        "width" | "height" => params.iter().any(|(_, value)| {
+            matches!(value, ast::Expr::Identifier(k) if k == "expand" || k == "container")
+        }),
+        _ => LAYOUT_RELATIVE.contains(&name) || name.starts_with("align_self_"),
+    };
+    match instr {
+        ast::InstructionExpr::Simple(inst) if relative(&inst.name, &inst.params) => {
+            found.push((&inst.name, inst.span));
+        }
+        ast::InstructionExpr::When {
+            then_instr,
+            else_instr,
+            ..
+        } => {
+            layout_relative(then_instr, span, found);
+            if let Some(else_instr) = else_instr {
+                layout_relative(else_instr, span, found);
+            }
+        }
+        ast::InstructionExpr::Ternary {
+            then_instr,
+            else_instr,
+            ..
+        } => {
+            layout_relative(then_instr, span, found);
+            layout_relative(else_instr, span, found);
+        }
+        ast::InstructionExpr::Reference(ast::Expr::Identifier(name)) if relative(name, &[]) => {
+            found.push((name, span));
+        }
+        _ => {}
+    }
+}
+
+/// Whether an instruction sets `autofocus`, including inside a conditional
+fn has_autofocus(instr: &ast::InstructionExpr) -> bool {
+    match instr {
+        ast::InstructionExpr::Simple(inst) => inst.name == AUTOFOCUS,
+        ast::InstructionExpr::When {
+            then_instr,
+            else_instr,
+            ..
+        } => has_autofocus(then_instr) || else_instr.as_deref().is_some_and(has_autofocus),
+        ast::InstructionExpr::Ternary {
+            then_instr,
+            else_instr,
+            ..
+        } => has_autofocus(then_instr) || has_autofocus(else_instr),
+        ast::InstructionExpr::Reference(expr) => {
+            matches!(expr, ast::Expr::Identifier(name) if name == AUTOFOCUS)
+        }
+    }
+}
+
+/// Fragments with `autofocus` that may be rendered at the same time, with
+/// whether they are inside a `repeat`
+///
+/// Branches of `when` and `select` exclude each other, so only the branch
+/// with the most of them counts. `owner` is the fragment the statements
+/// belong to, instructions directly in its body set `autofocus` on it.
+fn autofocus_fragments(
+    stmts: &[ast::BlueprintStmt],
+    owner: &str,
+    in_repeat: bool,
+) -> Vec<(String, bool)> {
+    stmts
+        .iter()
+        .flat_map(|stmt| autofocus_in_stmt(stmt, owner, in_repeat))
+        .collect()
+}
+
+fn autofocus_in_stmt(
+    stmt: &ast::BlueprintStmt,
+    owner: &str,
+    in_repeat: bool,
+) -> Vec<(String, bool)> {
+    let branches = |stmts: Vec<&ast::BlueprintStmt>| {
+        stmts
+            .into_iter()
+            .map(|stmt| autofocus_in_stmt(stmt, owner, in_repeat))
+            .max_by_key(Vec::len)
+            .unwrap_or_default()
+    };
+    match stmt {
+        ast::BlueprintStmt::Instruction(instr) if has_autofocus(instr) => {
+            vec![(owner.to_string(), in_repeat)]
+        }
+        ast::BlueprintStmt::FragmentCreation(frag) => {
+            let mut found = Vec::new();
+            let postfix = frag.postfix.iter().any(|item| {
+                matches!(item, ast::PostfixItem::Instruction(instr) if has_autofocus(instr))
+            });
+            if postfix {
+                found.push((frag.name.clone(), in_repeat));
+            }
+            match &frag.body {
+                Some(ast::FragmentBody::Default(stmts)) => {
+                    found.extend(autofocus_fragments(stmts, &frag.name, in_repeat));
+                }
+                Some(ast::FragmentBody::Slots(slots)) => {
+                    for slot in slots {
+                        if let ast::BlueprintValue::Inline { body, .. } = &slot.blueprint {
+                            found.extend(autofocus_fragments(body, &frag.name, in_repeat));
+                        }
+                    }
+                }
+                Some(ast::FragmentBody::InlineBlueprint { body, .. }) => {
+                    found.extend(autofocus_fragments(body, &frag.name, in_repeat));
+                }
+                None => {}
+            }
+            found
+        }
+        ast::BlueprintStmt::Control(ast::ControlStmt::When {
+            then_stmt,
+            else_stmt,
+            ..
+        }) => branches(std::iter::once(then_stmt.as_ref()).chain(else_stmt.as_deref()).collect()),
+        ast::BlueprintStmt::Control(ast::ControlStmt::Repeat { body, .. }) => {
+            autofocus_fragments(body, owner, true)
+        }
+        ast::BlueprintStmt::Control(ast::ControlStmt::Select {
+            branches: select_branches,
+            else_branch,
+            ..
+        }) => branches(
+            select_branches
+                .iter()
+                .map(|branch| branch.body.as_ref())
+                .chain(else_branch.as_deref())
+                .collect(),
+        ),
+        // The fallback replaces the body, so only one of them is shown
+        ast::BlueprintStmt::Control(ast::ControlStmt::OnError { body, fallback, .. }) => {
+            std::iter::once(body)
+                .chain(fallback)
+                .map(|stmts| autofocus_fragments(stmts, owner, in_repeat))
+                .max_by_key(Vec::len)
+                .unwrap_or_default()
+        }
+        ast::BlueprintStmt::Control(ast::ControlStmt::Loading { body, .. }) => {
+            autofocus_fragments(body, owner, in_repeat)
+        }
+        _ => Vec::new(),
     }
 }
 
+/// Names between braces in an HTTP path: `id` in `/users/{id}`
+fn path_placeholders(path: &str) -> impl Iterator<Item = &str> {
+    path.split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+}
+
 // =============================================================================
 // Public API
 // =============================================================================
@@ -1025,10 +2468,61 @@ pub fn typecheck_with_registry(
     TypeChecker::new(scopes, symbols, imports).check(file)
 }
 
+/// Type queries over checked declarations, for passes after type checking
+///
+/// Queries don't report errors; the type checker already did.
+pub struct TypeQuery<'a> {
+    pub scopes: &'a ScopeGraph,
+    pub symbols: &'a SymbolTable,
+    pub imports: &'a HashMap<String, String>,
+    pub symbol_types: &'a HashMap<SymbolId, Type>,
+}
+
+impl TypeQuery<'_> {
+    /// Resolve a type expression as written in `scope`
+    pub fn resolve_type(&self, type_expr: &TypeExpr, scope: ScopeId) -> Type {
+        let mut resolver = TypeResolver::new(self.scopes, self.symbols, self.imports);
+        resolver.current_scope = scope;
+        resolver.resolve_type_expr(type_expr, Span::default())
+    }
+
+    /// Type of an expression in `scope`, checked against `expected` if given
+    pub fn expr_type(&self, expr: &ast::Expr, expected: Option<&Type>, scope: ScopeId) -> Type {
+        let mut checker = expressions::ExprChecker::new(
+            self.scopes,
+            self.symbols,
+            self.symbol_types,
+            self.imports,
+            scope,
+            Span::default(),
+        );
+        match expected {
+            Some(expected) => checker.check_expr_type(expr, expected),
+            None => checker.infer_expr_type(expr),
+        }
+    }
+}
+
 // =============================================================================
 // Tests
 // =============================================================================
 
+/// Variant of `enum_name` a select branch matches: `Done`, `Status.Done`
+fn select_variant<'e>(condition: &'e ast::Expr, enum_name: &str) -> Option<&'e str> {
+    match condition {
+        ast::Expr::Identifier(name) => Some(name),
+        ast::Expr::QualifiedName(parts) if parts.len() == 2 && parts[0] == enum_name => {
+            Some(&parts[1])
+        }
+        ast::Expr::FieldAccess { base, field, .. }
+            if matches!(base.as_ref(), ast::Expr::Identifier(name) if name == enum_name) =>
+        {
+            Some(field)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1283,8 +2777,55 @@ blueprint StatusView {
     }
 
     #[test]
-    fn test_parameter_backend_merge_valid() {
-        // Valid merge: parameter and backend field have same name and type
+    fn test_select_on_enum_exhaustiveness() {
+        let source = r#"
+module test
+
+enum Status { Pending Active Completed Cancelled }
+
+blueprint StatusView {
+    status : Status = Status.Pending
+
+    select on status {
+        Pending => { x1 : i32 = 1 }
+        Status.Active => { x2 : i32 = 2 }
+        Pending => { x3 : i32 = 3 }
+    }
+    select on status {
+        Pending => { x4 : i32 = 4 }
+        else => { x5 : i32 = 5 }
+    }
+    select on status {
+        status == Status.Active => { x6 : i32 = 6 }
+    }
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let codes: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(codes, ["E0411", "E0410"], "{:?}", typecheck_result.diagnostics);
+
+        let mut diagnostics = typecheck_result.diagnostics.iter();
+        let unreachable = diagnostics.next().unwrap();
+        assert_eq!(unreachable.message, "unreachable branch: `Pending` is already matched");
+        assert_eq!(unreachable.span.text(source), "Pending => { x3 : i32 = 3 }");
+
+        let missing = diagnostics.next().unwrap();
+        assert_eq!(
+            missing.message,
+            "select on `Status` does not match `Completed`, `Cancelled`"
+        );
+        let (fixed, applied) = typecheck_result.diagnostics.apply_fixes(source);
+        assert_eq!(applied, 1);
+        assert!(fixed.contains("Pending => { x3 : i32 = 3 }\nCompleted => { }\nCancelled => { }\n"));
+        assert!(!parser::parse(&fixed).diagnostics.has_errors());
+    }
+
+    #[test]
+    fn test_injected_backend() {
         let source = r#"
 module test
 
@@ -1292,9 +2833,15 @@ backend CounterBackend {
     count : i32 = 0
 }
 
-blueprint Counter(count : i32) {
-    with CounterBackend
-    doubled : i32 = count * 2
+blueprint CounterLabel {
+    with counter: CounterBackend
+    label : String = "${count}"
+}
+
+blueprint Dashboard {
+    with shared: CounterBackend
+    CounterLabel(counter = shared)
+    CounterLabel(counter = shared)
 }
 "#;
         let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
@@ -1305,68 +2852,1200 @@ blueprint Counter(count : i32) {
         );
         assert!(
             !typecheck_result.has_errors(),
-            "Should not have typecheck errors for valid merge: {:?}",
+            "Should not have typecheck errors: {:?}",
             typecheck_result.diagnostics
         );
     }
 
     #[test]
-    fn test_parameter_backend_merge_type_mismatch() {
-        // Type mismatch: parameter has different type than backend field
+    fn test_injected_backend_type_mismatch() {
         let source = r#"
 module test
 
-backend DataBackend {
-    data : String = "hello"
+backend CounterBackend {
+    count : i32 = 0
 }
 
-blueprint DataView(data : i32) {
-    with DataBackend
-    doubled : i32 = data * 2
+backend TimerBackend {
+    elapsed : i32 = 0
+}
+
+blueprint CounterLabel {
+    with counter: CounterBackend
+}
+
+blueprint Dashboard {
+    with timer: TimerBackend
+    CounterLabel(counter = timer)
 }
 "#;
-        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
-        assert!(
-            !resolve_result.diagnostics.has_errors(),
-            "Should not have resolve errors: {:?}",
-            resolve_result.diagnostics
-        );
-        assert!(
-            typecheck_result.has_errors(),
-            "Should have typecheck error for type mismatch"
-        );
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
         assert!(
-            typecheck_result.diagnostics.iter().any(|d| d.code.as_deref() == Some("E0407")),
-            "Should have E0407 error for type mismatch: {:?}",
+            typecheck_result.diagnostics.iter().any(|d| d.code.as_deref() == Some("E0401")
+                && d.message.contains("expects backend `CounterBackend`, found backend `TimerBackend`")),
+            "Should report injected backend mismatch: {:?}",
             typecheck_result.diagnostics
         );
     }
 
     #[test]
-    fn test_parameter_backend_merge_both_defaults() {
-        // Both have defaults: parameter default takes precedence, no error
+    fn test_with_slot_requires_backend() {
         let source = r#"
 module test
 
-backend AmountBackend {
-    amount : i32 = 5
+scheme Point {
+    x : i32
 }
 
-blueprint AmountView(amount : i32 = 10) {
-    with AmountBackend
-    doubled : i32 = amount * 2
+blueprint View {
+    with point: Point
 }
 "#;
-        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
+        let (resolve_result, _) = resolve_and_typecheck_source(source);
         assert!(
-            !resolve_result.diagnostics.has_errors(),
-            "Should not have resolve errors: {:?}",
+            resolve_result.diagnostics.iter().any(|d| d.message.contains("`Point` is not a backend")),
+            "Should reject non-backend slot type: {:?}",
             resolve_result.diagnostics
         );
-        assert!(
-            !typecheck_result.has_errors(),
-            "Should not have errors - parameter default takes precedence: {:?}",
-            typecheck_result.diagnostics
+    }
+
+    #[test]
+    fn test_policy_only_on_commands() {
+        let source = r#"
+module test
+
+backend SearchBackend {
+    query : String = ""
+    latest command search(text: String)
+    drop method score(): i32
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let codes: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(codes, ["E0606"], "{:?}", typecheck_result.diagnostics);
+    }
+
+    #[test]
+    fn test_method_bodies() {
+        let source = r#"
+module test
+
+backend Cart {
+    method total(quantity: i32) : f64 = price * quantity + shipping()
+    method shipping() : f64 = price > 100.0 ? 0.0 : 4.5
+    method label() : i32 = "${count} items"
+    method checkout_now() : bool = checkout()
+    price : f64 = 0.0
+    count : i32 = 0
+    command checkout()
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or_default(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "E0401",
+                    "method `label` returns `i32`, but its body has type `String`"
+                ),
+                ("E0603", "command `checkout` called in an expression"),
+                (
+                    "E0401",
+                    "method `checkout_now` returns `bool`, but its body has type `unit`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let source = r#"
+module test
+
+backend Picker {
+    count : i32 = 0
+    command pick(id: String)
+}
+
+blueprint Row(label: String, on_pick: (String) -> unit, format: (i32) -> String) {
+    text { "${label}: ${format(count)}" }
+}
+
+blueprint List {
+    with Picker
+
+    doubled : (i32) -> i32 = (x) -> x * 2
+    halved : (i32) -> i32 = (x) -> "${x}"
+
+    Row(label = "a", on_pick = (id) -> pick(id), format = (n) -> "${n} items")
+    Row(label = "b", on_pick = (a, b) -> pick(a), format = (n: String) -> n)
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or_default(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "E0401",
+                    "`halved` has type `fn(i32) -> i32`, found `fn(i32) -> String`"
+                ),
+                ("E0415", "lambda takes 2 parameters, but 1 is expected"),
+                (
+                    "E0401",
+                    "lambda parameter `n` has type `String`, but the callback is called with `i32`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_undoable_backend() {
+        let source = r#"
+module test
+
+backend Other {
+    value : i32 = 0
+}
+
+undoable backend Editor {
+    text : String = ""
+    items : List<String> = []
+    other : Other
+    command redo()
+}
+
+backend Form {
+    undoable name : String = ""
+    session : Other
+}
+"#;
+        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
+        let codes: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(codes, ["E0607", "E0608"], "{:?}", typecheck_result.diagnostics);
+        let message = &typecheck_result.diagnostics.iter().next().unwrap().message;
+        assert!(message.contains("`other` has type backend `Other`"), "{}", message);
+
+        // `Form` only records `name`, and gets `undo` and `redo` generated
+        let form = resolve_result
+            .scopes
+            .iter()
+            .find(|s| s.name.as_deref() == Some("Form"))
+            .unwrap()
+            .id;
+        for name in UNDO_COMMANDS {
+            let id = resolve_result.symbols.lookup_local(form, name).unwrap();
+            assert_eq!(resolve_result.symbols.get(id).unwrap().kind, SymbolKind::Command);
+        }
+    }
+
+    #[test]
+    fn test_contract_bindings() {
+        let source = r#"
+module test
+
+contract Users {
+    get_user(id: u32): String .. get { "/users/{id}" }
+    rename(id: u32, name: String) .. patch { "/users/{user}" }
+    remove(id: u32) .. delete { "/users/{id}" } .. post { "/remove" } .. name { "removeUser" }
+    list() .. grpc { "Users" }
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    Some("E0609"),
+                    "path placeholder `{user}` is not a parameter of `rename`"
+                ),
+                (
+                    Some("E0609"),
+                    "`remove` is bound to more than one HTTP route"
+                ),
+                (
+                    Some("E0609"),
+                    "unknown contract binding `grpc`; expected `name` or an HTTP method (get, post, put, patch, delete)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variant_instructions() {
+        let source = r#"
+module test
+
+enum Status {
+    Draft .. label { "In draft" } .. label { "Draft" }
+    Placed .. label { 1 }
+    Shipped .. icon { "truck" }
+    Cancelled .. label { "Cancelled by customer" }
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Some("E0408"), "variant `Draft` has more than one `label`"),
+                (
+                    Some("E0408"),
+                    "`label` takes a single string literal, e.g. `.. label { \"Placed\" }`"
+                ),
+                (
+                    Some("E0408"),
+                    "unknown instruction `icon` on variant `Shipped`; expected label"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builtin_calls() {
+        let source = r#"
+module test
+
+scheme Event {
+    start : Instant
+    day : LocalDate
+    took : Duration
+}
+
+blueprint Main(event : Event) {
+    stamp : String = format(event.start, "yyyy-MM-dd HH:mm", "Europe/Paris")
+    date : String = format(event.day, "d MMM")
+    parsed : Instant? = parseInstant("2024-03-15T10:00:00Z")
+    took : String = humanize(event.took)
+    wrong : String = format("today", 1)
+    missing : String = humanize()
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    Some("E0401"),
+                    "argument `value` of `format` expects `Instant` or `LocalDate` or `LocalTime` or `LocalDateTime`, found `String`"
+                ),
+                (
+                    Some("E0401"),
+                    "argument `pattern` of `format` expects `String`, found `i32`"
+                ),
+                (
+                    Some("E0702"),
+                    "`humanize` takes 1 arguments, found 0; expected `humanize(duration: Duration) -> String`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_member_calls() {
+        let source = r#"
+module test
+
+scheme Order {
+    title : String
+    note : String?
+    tags : List<String>
+    prices : Map<String, f64>
+    placed : Instant
+    took : Duration
+}
+
+blueprint Main(order : Order) {
+    title : String = order.title.trim().toUpper()
+    long : bool = order.title.length > 20 && !order.tags.isEmpty
+    note : String = order.note?.toLower() ?: ""
+    tagged : bool = order.tags.contains("new")
+    first : String? = order.tags.first
+    price : f64? = order.prices.get("EUR")
+    until : Duration = order.placed.until(order.placed.plus(order.took))
+    hours : String = order.took.hours.round().toString()
+    wrong : bool = order.title.startsWith(1)
+    count : i32 = order.tags.length()
+    upper : String = order.title.toUpper
+    missing : String = order.title.reversed()
+    arity : bool = order.tags.contains()
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    Some("E0401"),
+                    "argument `text` of `String.startsWith` expects `String`, found `i32`"
+                ),
+                (Some("E0401"), "`length` is a property of `List<String>`, not a method"),
+                (Some("E0401"), "`toUpper` is a method of `String`, not a property"),
+                (Some("E0301"), "no member `reversed` on type `String`"),
+                (
+                    Some("E0702"),
+                    "`List<String>.contains` takes 1 arguments, found 0; expected `List<String>.contains(item: String) -> bool`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_range_repeat() {
+        let source = r#"
+module test
+
+blueprint Main(count : i32, size : u8) {
+    repeat on 0..count { i ->
+        text { (i * 2).toString() }
+    }
+    repeat on 1..=size { s ->
+        text { s.trim() }
+    }
+    repeat on 0.."ten" { i ->
+        text { "${i}" }
+    }
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Some("E0301"), "no member `trim` on type `u8`"),
+                (Some("E0401"), "expected an integer, found `String`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let source = r#"
+module test
+
+enum Status { Pending Active }
+
+blueprint Main(scores : Map<String, i32>, names : List<String>, status : Status) {
+    repeat on scores { (name, score) ->
+        text { name.trim() }
+        text { score.trim() }
+    }
+    repeat on scores { (_, score) ->
+        text { (score * 2).toString() }
+    }
+    repeat on names { (first, last) ->
+        text { first }
+    }
+    repeat on scores { (name, score, rank) ->
+        text { name }
+    }
+    select on status {
+        Pending(since) => text { "pending" }
+        Active => text { "active" }
+    }
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Some("E0301"), "no member `trim` on type `i32`"),
+                (Some("E0416"), "pattern `(first, last)` cannot destructure `String`"),
+                (
+                    Some("E0416"),
+                    "map entries have 2 parts, but the pattern `(name, score, rank)` has 3"
+                ),
+                (
+                    Some("E0416"),
+                    "variant `Pending` carries no data to destructure"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_payloads() {
+        let source = r#"
+module test
+
+enum Fetch { Idle Loaded(String, i32) Failed(i32) }
+
+blueprint Main(fetch : Fetch) {
+    idle : Fetch = Fetch.Idle
+    loaded : Fetch = Fetch.Loaded("body", 200)
+    failed : Fetch = Fetch.Failed("oops")
+    partial : Fetch = Fetch.Loaded("body")
+    bare : Fetch = Fetch.Failed
+
+    select on fetch {
+        Idle => text { "idle" }
+        Loaded(body, status) => text { body.trim() }
+        Fetch.Failed(code) => text { code.trim() }
+    }
+    select on fetch {
+        Loaded(body) => text { body }
+        Failed => text { "failed" }
+    }
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    Some("E0401"),
+                    "argument 1 of `Fetch.Failed` expects `i32`, found `String`"
+                ),
+                (Some("E0702"), "`Fetch.Loaded` takes 2 arguments, found 1"),
+                (
+                    Some("E0401"),
+                    "`bare` has type `Fetch`, found `fn(i32) -> Fetch`"
+                ),
+                (Some("E0301"), "no member `trim` on type `i32`"),
+                (
+                    Some("E0416"),
+                    "variant `Loaded` carries 2 values, but the branch destructures 1"
+                ),
+                (Some("E0410"), "select on `Fetch` does not match `Idle`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_number_formatting_builtins() {
+        let source = r#"
+module test
+
+scheme Order {
+    total : Decimal
+    count : i32
+    share : f64
+}
+
+blueprint Main(order : Order, locale : String) {
+    total : String = formatCurrency(order.total, "EUR", locale)
+    count : String = formatNumber(order.count)
+    share : String = formatPercent(order.share, 1, "de-AT")
+    code : String = formatCurrency(order.total, "euro")
+    digits : String = formatNumber(order.total, -2)
+    region : String = formatPercent(order.share, 0, "de_AT")
+}
+"#;
+        let result = typecheck_source(source);
+        let messages: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    Some("E0409"),
+                    "argument `currency` of `formatCurrency`: `euro` is not a currency code; expected three uppercase letters like \"EUR\""
+                ),
+                (
+                    Some("E0409"),
+                    "argument `decimals` of `formatNumber`: -2 fraction digits are out of range; expected 0 to 20"
+                ),
+                (
+                    Some("E0409"),
+                    "argument `locale` of `formatPercent`: `de_AT` is not a locale; expected a language tag like \"en\" or \"de-AT\""
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_virtualize() {
+        let source = r#"
+module test
+
+backend Catalog {
+    items : List<String> = []
+    rows : i32 = 40
+}
+
+blueprint Main {
+    with Catalog
+    column {
+        repeat on items { item ->
+            text { item }
+        }
+    } .. virtualize { item_height: rows overscan: 5 }
+    column {
+        .. virtualize { estimate: 32.5 }
+        repeat on items { item ->
+            text { item }
+        }
+    }
+    column {
+        text { "empty" }
+    } .. virtualize { item_height: 40 }
+    column {
+        repeat on items { item ->
+            text { item }
+        }
+    } .. virtualize { item_height: "tall" estimate: 40 window: 3 }
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or(""), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("E0706", "'virtualize' on `column` has no `repeat` to window"),
+                ("E0401", "expected a number, found `String`"),
+                (
+                    "E0706",
+                    "unknown parameter 'window' for 'virtualize', expected item_height, estimate or overscan"
+                ),
+                ("E0706", "'virtualize' needs either a fixed 'item_height' or an 'estimate'"),
+            ],
+            "{:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_focus_instructions() {
+        let source = r#"
+module test
+
+backend Form {
+    items : List<String> = []
+    order : i32 = 2
+    editing : bool = false
+}
+
+blueprint Dialog {
+    with Form
+    column {
+        when editing {
+            text { "name" } .. autofocus .. tab_index { order }
+        } else {
+            text { "view" } .. autofocus
+        }
+        text { "save" } .. tab_index { -1 }
+    } .. focus_group { trap } .. align_items { horizontal: left vertical: middle }
+}
+
+blueprint Toolbar {
+    with Form
+    row {
+        text { "bold" } .. autofocus
+        text { "italic" } .. tab_index { "first" }
+        repeat on items { item ->
+            text { item } .. autofocus
+        }
+    } .. focus_group { rows }
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or(""), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("E0401", "expected an integer, found `String`"),
+                (
+                    "E0705",
+                    "invalid value 'rows' for 'focus_group' instruction, expected one of: trap, arrows"
+                ),
+                (
+                    "E0709",
+                    "`autofocus` on `text` is inside a `repeat`, every item of `Toolbar` would take focus"
+                ),
+                ("E0709", "`autofocus` is set on more than one fragment of `Toolbar`: `text`, `text`"),
+            ],
+            "{:?}",
+            typecheck_result.diagnostics
+        );
+        let invalid = typecheck_result.diagnostics.iter().find(|d| d.code.as_deref() == Some("E0705"));
+        assert_eq!(
+            invalid.and_then(|d| d.help.as_deref()),
+            Some("aliases are also accepted: `roving` for `arrows`")
+        );
+    }
+
+    #[test]
+    fn test_error_boundary() {
+        let source = r#"
+module test
+
+blueprint Profile(name : String) {
+    text { name }
+}
+
+blueprint Page {
+    on_error {
+        Profile("Ada")
+    } fallback { error ->
+        Profile(error)
+        when error { Profile("retry") }
+    }
+    on_error { Profile("Grace") }
+    on_error { Profile("Hedy") } fallback { }
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or(""), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("E0401", "expected `bool`, found `String`"),
+                ("E0712", "error boundary has no fallback"),
+                ("E0712", "error boundary has an empty fallback"),
+            ],
+            "{:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_portal() {
+        let source = r#"
+module test
+
+blueprint Page {
+    column { text { "modal" } } .. portal .. fill_width .. align_relative { vertical: below }
+    text { "tip" } .. portal { "tooltips" } .. position { top: 4 left: 8 } .. width { 120 }
+    text { "menu" } .. portal { 1 } .. height { container }
+    text { "inline" } .. fill
+}
+"#;
+        let (_, typecheck_result) = resolve_and_typecheck_source(source);
+        let messages: Vec<_> = typecheck_result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or(""), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "E0714",
+                    "`fill_width` places `column` in its parent's layout, but `portal` renders it in an overlay"
+                ),
+                (
+                    "E0714",
+                    "`position` places `text` in its parent's layout, but `portal` renders it in an overlay"
+                ),
+                (
+                    "E0714",
+                    "`height` places `text` in its parent's layout, but `portal` renders it in an overlay"
+                ),
+                ("E0401", "expected `String`, found `i32`"),
+            ],
+            "{:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_parameter_backend_merge_valid() {
+        // Valid merge: parameter and backend field have same name and type
+        let source = r#"
+module test
+
+backend CounterBackend {
+    count : i32 = 0
+}
+
+blueprint Counter(count : i32) {
+    with CounterBackend
+    doubled : i32 = count * 2
+}
+"#;
+        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
+        assert!(
+            !resolve_result.diagnostics.has_errors(),
+            "Should not have resolve errors: {:?}",
+            resolve_result.diagnostics
+        );
+        assert!(
+            !typecheck_result.has_errors(),
+            "Should not have typecheck errors for valid merge: {:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_parameter_backend_merge_type_mismatch() {
+        // Type mismatch: parameter has different type than backend field
+        let source = r#"
+module test
+
+backend DataBackend {
+    data : String = "hello"
+}
+
+blueprint DataView(data : i32) {
+    with DataBackend
+    doubled : i32 = data * 2
+}
+"#;
+        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
+        assert!(
+            !resolve_result.diagnostics.has_errors(),
+            "Should not have resolve errors: {:?}",
+            resolve_result.diagnostics
+        );
+        assert!(
+            typecheck_result.has_errors(),
+            "Should have typecheck error for type mismatch"
+        );
+        assert!(
+            typecheck_result.diagnostics.iter().any(|d| d.code.as_deref() == Some("E0407")),
+            "Should have E0407 error for type mismatch: {:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_parameter_backend_merge_both_defaults() {
+        // Both have defaults: parameter default takes precedence, no error
+        let source = r#"
+module test
+
+backend AmountBackend {
+    amount : i32 = 5
+}
+
+blueprint AmountView(amount : i32 = 10) {
+    with AmountBackend
+    doubled : i32 = amount * 2
+}
+"#;
+        let (resolve_result, typecheck_result) = resolve_and_typecheck_source(source);
+        assert!(
+            !resolve_result.diagnostics.has_errors(),
+            "Should not have resolve errors: {:?}",
+            resolve_result.diagnostics
+        );
+        assert!(
+            !typecheck_result.has_errors(),
+            "Should not have errors - parameter default takes precedence: {:?}",
+            typecheck_result.diagnostics
+        );
+    }
+
+    #[test]
+    fn test_null_narrowing() {
+        let source = r#"
+module test
+
+scheme Address {
+    city : String
+}
+
+scheme User {
+    name : String
+    address : Address?
+}
+
+backend Session {
+    user : User? = null
+    label : String = ""
+}
+
+blueprint Profile {
+    with Session
+    city : String = user != null && user.address != null ? user.address.city : "nowhere"
+    name : String = user == null || user.name == "" ? "guest" : "known"
+    fallback : String = user?.name ?: label
+
+    when user != null {
+        text { user.name } .. on_click { label = user.name }
+    }
+    when user == null {
+        text { "signed out" }
+    } else {
+        text { user.name }
+    }
+    when !(user == null) {
+        text { user.name }
+    }
+}
+"#;
+        let result = typecheck_source(source);
+        assert!(!result.has_errors(), "Errors: {:?}", result.diagnostics);
+    }
+
+    #[test]
+    fn test_nullable_access_without_narrowing() {
+        let source = r#"
+module test
+
+scheme User {
+    name : String
+    tags : List<String>
+}
+
+backend Session {
+    user : User? = null
+    label : String = ""
+}
+
+blueprint Profile {
+    with Session
+    name : String = user.name
+    either : String = user != null || label == "" ? user.name : ""
+    when user != null {
+        text { "x" } .. on_click {
+            user = null
+            label = user.name
+        }
+    }
+    when user != null {
+        text { "x" } .. on_click {
+            signOut()
+            label = user.name
+        }
+    }
+}
+"#;
+        let result = typecheck_source(source);
+        let errors: Vec<_> = result
+            .diagnostics
+            .iter()
+            .filter(|d| d.code.as_deref() == Some("E0406"))
+            .collect();
+        assert_eq!(errors.len(), 4, "{:?}", result.diagnostics);
+        assert_eq!(errors[0].message, "cannot access field `name` of `user`, which may be null");
+        let fixes: Vec<_> = errors[0].suggestions.iter().map(|s| s.replacement.as_str()).collect();
+        assert_eq!(fixes, ["user?.name", "(user?.name ?: \"\")"]);
+        assert_eq!(&source[errors[0].span.start as usize..errors[0].span.end as usize], "user.name");
+    }
+
+    /// Messages of the diagnostics of `source` with `code`
+    fn messages(source: &str, code: &str) -> Vec<String> {
+        typecheck_source(source)
+            .diagnostics
+            .iter()
+            .filter(|d| d.code.as_deref() == Some(code))
+            .map(|d| d.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_numeric_widening() {
+        let source = r#"
+module test
+
+backend Meter {
+    small : i16 = 1
+    count : i32 = small
+    total : i64 = count
+    unsigned : u32 = 7
+    wide : i64 = unsigned
+    ratio : f32 = 0.5
+    precise : f64 = ratio
+    scaled : f64 = total
+    price : Decimal = count
+
+    truncated : i32 = 1.5
+    shortened : i32 = total
+    signless : u32 = count
+    rounded : f32 = precise
+    unsafe_unsigned : i32 = unsigned
+
+    method scale(factor : f64) : f64
+    method pick(index : i32) : i32
+}
+
+blueprint Page {
+    with Meter
+    fraction : f64 = scale(count)
+    picked : i32 = pick(total)
+
+    text { "x" } .. on_click {
+        precise = count
+        count = precise
+    }
+}
+"#;
+        assert_eq!(
+            messages(source, "E0401"),
+            [
+                "`truncated` has type `i32`, found `f64`",
+                "`shortened` has type `i32`, found `i64`",
+                "`signless` has type `u32`, found `i32`",
+                "`rounded` has type `f32`, found `f64`",
+                "`unsafe_unsigned` has type `i32`, found `u32`",
+                "argument 1 of `pick` expects `i32`, found `i64`",
+                "`count` has type `i32`, found `f64`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assignments_and_calls() {
+        let source = r#"
+module test
+
+scheme Article {
+    title : String
+}
+
+backend Editor {
+    article : Article? = null
+    count : i32 = "none"
+    title : String = ""
+    tags : Set<String> = []
+    draft_copy : draft Article? = null
+
+    method score(weight : f64, bonus : f64 = 0.0) : f64
+    command save(title : String)
+}
+
+blueprint Page {
+    with Editor
+    local : f64 = score(1.0)
+    name : String = article?.title
+    wrong : i32 = score("heavy", 1.0, 2.0)
+    saved : bool = save("x")
+
+    text { title } .. on_click {
+        count = "many"
+        title = article?.title ?: ""
+        save(1)
+        save("a")
+        score(1.0)
+    }
+}
+"#;
+        assert_eq!(
+            messages(source, "E0401"),
+            [
+                "`count` has type `i32`, found `String`",
+                "`name` has type `String`, found `String?`",
+                "argument 1 of `score` expects `f64`, found `String`",
+                "`wrong` has type `i32`, found `f64`",
+                "`saved` has type `bool`, found `unit`",
+                "`count` has type `i32`, found `String`",
+                "argument 1 of `save` expects `String`, found `i32`",
+            ]
+        );
+        assert_eq!(
+            messages(source, "E0702"),
+            ["`score` takes 1 to 2 arguments, found 3"]
+        );
+        assert_eq!(
+            messages(source, "E0603"),
+            ["command `save` called in an expression"]
+        );
+        assert_eq!(
+            messages(source, "E0604"),
+            ["method `score` called as a statement of an event handler"]
+        );
+    }
+
+    #[test]
+    fn test_fragment_arguments() {
+        let source = r#"
+module test
+
+blueprint Card(title : String, count : i32, note : String = "") {
+    text { "${title} ${note} ${count}" }
+}
+
+blueprint Main {
+    Card("a", 1)
+    Card(title = "a", count = 1, note = "b")
+    Card("a")
+    Card(1, 1)
+    Card(title = "a", 1)
+    Card("a", 1, "b", "c")
+    Card("a", 1, titel = "b")
+    Card("a", title = "b", count = 1)
+}
+"#;
+        let mut found = messages(source, "E0702");
+        found.extend(messages(source, "E0401"));
+        found.extend(messages(source, "E0301"));
+        assert_eq!(
+            found,
+            [
+                "missing arguments of `Card`: `count`",
+                "positional argument of `Card` after named arguments",
+                "missing arguments of `Card`: `count`",
+                "`Card` takes at most 3 arguments, found 4",
+                "parameter `title` of `Card` is given twice",
+                "`title` of `Card` expects `String`, found `i32`",
+                "no parameter `titel` on blueprint `Card`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_handlers() {
+        let source = r#"
+module test
+
+backend Editor {
+    label : String = ""
+    width : f64 = 0.0
+}
+
+blueprint Page {
+    with Editor
+    text { label } .. on_click { e: PointerEvent -> label = "clicked" }
+    text { label } .. on_resize { rect: Rect? -> width = 1.0 }
+    text { label } .. on_key { e: PointerEvent -> label = "" }
+    text { label } .. on_focus { e -> label = "" }
+    text { label } .. on_clik { label = "" }
+    text { label } .. on_blur { width = "wide" }
+    on_enter { label = "done" }
+}
+"#;
+        let result = typecheck_source(source);
+        let found: Vec<(&str, &str)> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap_or_default(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("E0717", "`on_key` passes `KeyEvent`, but `e` is declared as `PointerEvent`"),
+                ("E0717", "`on_focus` passes no value, but the handler takes `e`"),
+                ("E0716", "unknown event `on_clik`"),
+                ("E0401", "`width` has type `f64`, found `String`"),
+            ]
+        );
+        let unknown = &result.diagnostics.iter().nth(2).unwrap();
+        assert_eq!(&source[unknown.span.start as usize..unknown.span.end as usize], "on_clik");
+        assert_eq!(unknown.suggestions[0].replacement, "on_click");
+    }
+
+    #[test]
+    fn test_invalid_constants() {
+        let source = r#"
+module test
+
+theme Spacing {
+    base : i32 = 4 / 0
+    large : i32 = base * 4
+    huge : i32 = 9223372036854775807 + 1
+    gap : i32 = 2
+    variant Dense {
+        gap = 1 % 0
+    }
+}
+
+blueprint Main(divisor : i32) {
+    day : i32 = 60 * 60 * 24
+    count : i32 = 60 / (3 - 3)
+    ratio : i32 = 60 / divisor
+    text { "${-(-9223372036854775807 - 1)}" }
+}
+"#;
+        assert_eq!(
+            messages(source, "E0413"),
+            [
+                "division by zero in the value of theme field `base`",
+                "arithmetic overflow in the value of theme field `huge`",
+                "division by zero in the override of theme field `gap` in variant `Dense`",
+                "division by zero in a constant expression",
+                "arithmetic overflow in a constant expression",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integer_out_of_range() {
+        let source = r#"
+module test
+
+backend Store {
+    small : i8 = -128
+    large : u32 = 4294967295
+    id : u64 = -1
+    level : u8? = 256
+    command resize(width : i16)
+}
+
+blueprint Main {
+    with Store
+    count : i32 = 3000000000
+    total : i64 = 3000000000
+    text { "${count}" } .. on_click {
+        small = -129
+        resize(40000)
+    }
+}
+"#;
+        assert_eq!(
+            messages(source, "E0414"),
+            [
+                "integer literal `-1` is out of range for `u64`",
+                "integer literal `256` is out of range for `u8`",
+                "integer literal `3000000000` is out of range for `i32`",
+                "integer literal `-129` is out of range for `i8`",
+                "integer literal `40000` is out of range for `i16`",
+            ]
         );
     }
 }
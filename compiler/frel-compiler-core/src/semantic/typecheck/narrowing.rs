@@ -0,0 +1,55 @@
+// Null narrowing for Frel type checking
+//
+// A condition comparing a path (`user`, `user.profile`) with `null` tells
+// where the path is known not to be null: in the `then` branch of
+// `when user != null`, in the `else` branch of `when user == null`, on the
+// right of `user != null && ...` and of `user == null || ...`. The type
+// checker types such a path as its inner type there, so its fields can be
+// read without `?.`.
+//
+// Narrowing holds for the whole branch: blueprint branches are only rendered
+// while their condition holds. In an event handler it ends at an assignment
+// to the path, or to a path it is part of, and at any command call.
+
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+
+use super::super::const_eval::qualified_path;
+
+/// Paths known not to be null when `condition` evaluates to `outcome`
+pub fn non_null_when(condition: &Expr, outcome: bool) -> Vec<String> {
+    match condition {
+        Expr::Binary { op, left, right } => match (op, outcome) {
+            (BinaryOp::Ne, true) | (BinaryOp::Eq, false) => compared_with_null(left, right)
+                .and_then(qualified_path)
+                .into_iter()
+                .collect(),
+            (BinaryOp::And, true) | (BinaryOp::Or, false) => {
+                let mut paths = non_null_when(left, outcome);
+                paths.extend(non_null_when(right, outcome));
+                paths
+            }
+            _ => Vec::new(),
+        },
+        Expr::Unary {
+            op: UnaryOp::Not,
+            expr,
+        } => non_null_when(expr, !outcome),
+        _ => Vec::new(),
+    }
+}
+
+/// The side of a comparison the other side of which is `null`
+fn compared_with_null<'a>(left: &'a Expr, right: &'a Expr) -> Option<&'a Expr> {
+    match (left, right) {
+        (expr, Expr::Null) | (Expr::Null, expr) => Some(expr),
+        _ => None,
+    }
+}
+
+/// Whether an assignment to `target` can change the value of `path`
+pub fn invalidates(target: &str, path: &str) -> bool {
+    path == target
+        || path
+            .strip_prefix(target)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
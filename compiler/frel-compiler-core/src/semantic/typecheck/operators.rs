@@ -55,10 +55,17 @@ pub fn infer_binary_op_type(
         }
         // Null coalescing
         Elvis => {
-            // T? ?: T -> T
-            if let Type::Nullable(inner) = left {
-                if types_compatible(inner, right) {
-                    return (**inner).clone();
+            // T? ?: T -> T, T? ?: T? -> T?; a left side narrowed to T is never null
+            let value = match left {
+                Type::Nullable(inner) => inner.as_ref(),
+                value => value,
+            };
+            if types_compatible(value, right) {
+                return value.clone();
+            }
+            if let Type::Nullable(right_inner) = right {
+                if types_compatible(value, right_inner) {
+                    return value.clone().make_nullable();
                 }
             }
             report_binary_type_error(op, left, right, span, diagnostics);
@@ -140,12 +147,31 @@ pub fn types_compatible(expected: &Type, actual: &Type) -> bool {
     if let Type::Nullable(inner) = expected {
         return types_compatible(inner, actual);
     }
-    // Numeric widening
-    if expected.is_numeric() && actual.is_numeric() {
-        // Allow implicit widening (smaller -> larger)
-        return true; // Simplified for now
+    // A value makes a reference to itself, and a value or a reference makes
+    // a draft copy for editing
+    match (expected, actual) {
+        (Type::Ref(inner), _) | (Type::Draft(inner), _) if types_compatible(inner, actual) => {
+            return true;
+        }
+        (Type::Draft(inner), Type::Ref(actual)) => return types_compatible(inner, actual),
+        _ => {}
+    }
+    // Functions match parameter by parameter; the result of a callback
+    // expected to return `unit` is dropped, so it may return anything
+    if let (
+        Type::Function { params: expected_params, ret: expected_ret },
+        Type::Function { params: actual_params, ret: actual_ret },
+    ) = (expected, actual)
+    {
+        return expected_params.len() == actual_params.len()
+            && expected_params
+                .iter()
+                .zip(actual_params)
+                .all(|(expected, actual)| types_compatible(actual, expected))
+            && (**expected_ret == Type::Unit || types_compatible(expected_ret, actual_ret));
     }
-    false
+    // Numbers widen implicitly, but narrowing needs an explicit conversion
+    actual.widens_to(expected)
 }
 
 /// Expect a boolean type, reporting an error if not
@@ -159,9 +185,42 @@ pub fn expect_bool(ty: &Type, span: Span, diagnostics: &mut Diagnostics) {
     }
 }
 
+/// Expect a numeric type, reporting an error if not
+pub fn expect_numeric(ty: &Type, span: Span, diagnostics: &mut Diagnostics) {
+    if !ty.is_numeric() && *ty != Type::Unknown && !ty.is_error() {
+        diagnostics.add(Diagnostic::from_code(
+            &codes::E0401,
+            span,
+            format!("expected a number, found `{}`", ty),
+        ));
+    }
+}
+
+/// Expect an integer type, reporting an error if not
+pub fn expect_integer(ty: &Type, span: Span, diagnostics: &mut Diagnostics) {
+    if !ty.is_integer() && *ty != Type::Unknown && !ty.is_error() {
+        diagnostics.add(Diagnostic::from_code(
+            &codes::E0401,
+            span,
+            format!("expected an integer, found `{}`", ty),
+        ));
+    }
+}
+
+/// Expect a string type, reporting an error if not
+pub fn expect_string(ty: &Type, span: Span, diagnostics: &mut Diagnostics) {
+    if *ty != Type::String && *ty != Type::Unknown && !ty.is_error() {
+        diagnostics.add(Diagnostic::from_code(
+            &codes::E0401,
+            span,
+            format!("expected `String`, found `{}`", ty),
+        ));
+    }
+}
+
 /// Expect an iterable type, reporting an error if not
 pub fn expect_iterable(ty: &Type, span: Span, diagnostics: &mut Diagnostics) {
-    let is_iterable = ty.is_collection() || *ty == Type::Unknown || ty.is_error();
+    let is_iterable = ty.is_iterable() || *ty == Type::Unknown || ty.is_error();
     if !is_iterable {
         diagnostics.add(Diagnostic::from_code(
             &codes::E0401,
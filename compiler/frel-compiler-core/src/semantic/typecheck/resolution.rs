@@ -90,6 +90,13 @@ impl<'a> TypeResolver<'a> {
                 let inner_ty = self.resolve_type_expr(inner, span);
                 Type::Accessor(Box::new(inner_ty))
             }
+            TypeExpr::Function { params, ret } => {
+                let params: Vec<_> = params
+                    .iter()
+                    .map(|p| self.resolve_type_expr(p, span))
+                    .collect();
+                Type::function(params, self.resolve_type_expr(ret, span))
+            }
         };
 
         self.type_resolutions.insert(span, ty.clone());
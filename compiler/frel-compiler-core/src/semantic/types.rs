@@ -144,6 +144,8 @@ pub enum Type {
     Map(Box<Type>, Box<Type>),
     /// Tree type: tree<T>
     Tree(Box<Type>),
+    /// Integer range: `0..count`, iterated like a list of its integers
+    Range(Box<Type>),
 
     // ========================================================================
     // Function types
@@ -274,6 +276,38 @@ impl Type {
         )
     }
 
+    /// Smallest and largest value of an integer type
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let range = match self {
+            Type::I8 => (i8::MIN.into(), i8::MAX.into()),
+            Type::I16 => (i16::MIN.into(), i16::MAX.into()),
+            Type::I32 => (i32::MIN.into(), i32::MAX.into()),
+            Type::I64 => (i64::MIN.into(), i64::MAX.into()),
+            Type::U8 => (0, u8::MAX.into()),
+            Type::U16 => (0, u16::MAX.into()),
+            Type::U32 => (0, u32::MAX.into()),
+            Type::U64 => (0, u64::MAX.into()),
+            _ => return None,
+        };
+        Some(range)
+    }
+
+    /// Whether a value of this numeric type converts to `target` without
+    /// loss: to a wider integer, from an integer to a float or decimal, or
+    /// from `f32` to `f64`
+    pub fn widens_to(&self, target: &Type) -> bool {
+        if let (Some((min, max)), Some((target_min, target_max))) =
+            (self.integer_range(), target.integer_range())
+        {
+            return target_min <= min && max <= target_max;
+        }
+        match self {
+            _ if self.is_integer() => target.is_float() || *target == Type::Decimal,
+            Type::F32 => *target == Type::F64,
+            _ => false,
+        }
+    }
+
     /// Check if this is a signed integer type
     pub fn is_signed_integer(&self) -> bool {
         matches!(self, Type::I8 | Type::I16 | Type::I32 | Type::I64)
@@ -353,6 +387,30 @@ impl Type {
         !matches!(self, Type::Unknown | Type::Error)
     }
 
+    /// Check if values of this type are plain data that can be copied and
+    /// restored later (undo history)
+    ///
+    /// References are copied as identities, so any `ref T` is snapshot-able.
+    pub fn is_snapshotable(&self) -> bool {
+        match self {
+            Type::Backend(_)
+            | Type::Blueprint(_)
+            | Type::Contract(_)
+            | Type::Theme(_)
+            | Type::Asset(_)
+            | Type::Function { .. }
+            | Type::BlueprintInstance { .. }
+            | Type::Accessor(_) => false,
+            Type::Nullable(inner)
+            | Type::Draft(inner)
+            | Type::List(inner)
+            | Type::Set(inner)
+            | Type::Tree(inner) => inner.is_snapshotable(),
+            Type::Map(key, value) => key.is_snapshotable() && value.is_snapshotable(),
+            _ => true,
+        }
+    }
+
     /// Get the inner type of a nullable
     pub fn nullable_inner(&self) -> Option<&Type> {
         match self {
@@ -377,10 +435,15 @@ impl Type {
         }
     }
 
-    /// Get the element type of a collection
+    /// Check if `repeat` can iterate over values of this type
+    pub fn is_iterable(&self) -> bool {
+        self.is_collection() || matches!(self, Type::Range(_))
+    }
+
+    /// Get the element type of a collection or range
     pub fn element_type(&self) -> Option<&Type> {
         match self {
-            Type::List(elem) | Type::Set(elem) | Type::Tree(elem) => Some(elem),
+            Type::List(elem) | Type::Set(elem) | Type::Tree(elem) | Type::Range(elem) => Some(elem),
             _ => None,
         }
     }
@@ -547,6 +610,7 @@ impl std::fmt::Display for Type {
             Type::Set(elem) => write!(f, "set<{}>", elem),
             Type::Map(k, v) => write!(f, "map<{}, {}>", k, v),
             Type::Tree(elem) => write!(f, "tree<{}>", elem),
+            Type::Range(elem) => write!(f, "range<{}>", elem),
             Type::Function { params, ret } => {
                 write!(f, "fn(")?;
                 for (i, p) in params.iter().enumerate() {
@@ -645,6 +709,8 @@ mod tests {
         assert!(Type::List(Box::new(Type::I32)).is_collection());
         assert!(Type::Set(Box::new(Type::String)).is_collection());
         assert!(Type::Map(Box::new(Type::String), Box::new(Type::I32)).is_collection());
+        assert!(Type::Range(Box::new(Type::I32)).is_iterable());
+        assert_eq!(Type::Range(Box::new(Type::U8)).element_type(), Some(&Type::U8));
 
         // Composites
         assert!(Type::Scheme(SymbolId(0)).is_composite());
@@ -655,6 +721,12 @@ mod tests {
         assert!(Type::Graphics.is_intrinsic());
         assert!(Type::Blob.is_intrinsic());
         assert!(!Type::Scheme(SymbolId(0)).is_intrinsic());
+
+        // Snapshot-able (undo history)
+        assert!(Type::List(Box::new(Type::Scheme(SymbolId(0)))).is_snapshotable());
+        assert!(Type::Ref(Box::new(Type::Backend(SymbolId(0)))).is_snapshotable());
+        assert!(!Type::Backend(SymbolId(0)).is_snapshotable());
+        assert!(!Type::Nullable(Box::new(Type::Asset(Box::new(Type::Blob)))).is_snapshotable());
     }
 
     #[test]
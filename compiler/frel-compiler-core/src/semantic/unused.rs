@@ -0,0 +1,368 @@
+// Unused symbol checks for Frel
+//
+// After name resolution, every reference the resolver recorded is a use of
+// the symbol it resolved to. The checks report, as warnings tagged
+// `Unnecessary` so editors can gray the code out:
+//
+// - local declarations and `consume` statements that are never read (E0307)
+// - blueprint parameters the blueprint never reads (E0308); a parameter
+//   named like a member of a backend the blueprint uses with `with` sets that
+//   member, and is used
+// - backends and schemes nothing in the module reaches, and no other module
+//   imports (E0309)
+// - imports of which nothing is used (E0310)
+//
+// Backends and schemes are reached from references outside of any backend or
+// scheme (blueprints, arenas, contracts, themes), then from the references
+// inside the declarations reached, so two backends referring only to each
+// other are both unused. Imports by other modules are read from their
+// signatures in the registry. Names starting with `_` are never reported.
+
+use std::collections::{HashMap, HashSet};
+
+use super::resolve::ResolveResult;
+use super::scope::ScopeId;
+use super::signature::SignatureRegistry;
+use super::symbol::{SymbolId, SymbolKind};
+use crate::ast;
+use crate::diagnostic::{codes, Diagnostic, Diagnostics, Suggestion};
+use crate::source::Span;
+
+/// Check a resolved file of `module` for unused symbols
+pub fn check_unused(
+    file: &ast::File,
+    result: &ResolveResult,
+    module: &str,
+    registry: &SignatureRegistry,
+) -> Diagnostics {
+    let mut diagnostics = Diagnostics::new();
+    let used: HashSet<SymbolId> = result.references.iter().map(|(id, _)| *id).collect();
+    let reported = |id: SymbolId, name: &str| !used.contains(&id) && !name.starts_with('_');
+
+    for symbol in result.symbols.iter() {
+        if symbol.kind == SymbolKind::LocalVar
+            && symbol.def_span != Span::default()
+            && reported(symbol.id, &symbol.name)
+        {
+            diagnostics.add(
+                Diagnostic::from_code(
+                    &codes::E0307,
+                    symbol.def_span,
+                    format!("unused local '{}'", symbol.name),
+                )
+                .unnecessary(),
+            );
+        }
+    }
+
+    for decl in &file.declarations {
+        if let ast::TopLevelDecl::Blueprint(blueprint) = decl {
+            check_parameters(blueprint, result, &reported, &mut diagnostics);
+        }
+    }
+
+    for decl in unreached_declarations(result, module, registry) {
+        let symbol = result.symbols.get(decl).expect("declaration of the file");
+        if symbol.name.starts_with('_') {
+            continue;
+        }
+        diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0309,
+                symbol.def_span,
+                format!("{} '{}' is never used", symbol.kind.as_str(), symbol.name),
+            )
+            .unnecessary(),
+        );
+    }
+
+    // An import is used when any of the names it brings into scope is
+    let mut imports: HashMap<Span, bool> = HashMap::new();
+    for symbol in result.symbols.symbols_in_scope(ScopeId::ROOT) {
+        if symbol.source_module.is_some() {
+            *imports.entry(symbol.def_span).or_default() |= used.contains(&symbol.id);
+        }
+    }
+    for import in &file.imports {
+        if imports.get(&import.span) == Some(&false) {
+            let path = if import.import_all {
+                format!("{}.*", import.path)
+            } else {
+                import.path.clone()
+            };
+            diagnostics.add(
+                Diagnostic::from_code(&codes::E0310, import.span, format!("unused import '{}'", path))
+                    .with_suggestion(Suggestion::delete(import.span, "remove the import"))
+                    .unnecessary(),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+/// Report the parameters of `blueprint` its body never reads
+fn check_parameters(
+    blueprint: &ast::Blueprint,
+    result: &ResolveResult,
+    reported: &dyn Fn(SymbolId, &str) -> bool,
+    diagnostics: &mut Diagnostics,
+) {
+    let symbols = &result.symbols;
+    let Some(body_scope) = symbols
+        .lookup_local(ScopeId::ROOT, &blueprint.name)
+        .and_then(|id| symbols.get(id))
+        .and_then(|symbol| symbol.body_scope)
+    else {
+        return;
+    };
+
+    // Members of the backends used with `with`, which parameters of the same name set
+    let backend_members: Vec<ScopeId> = blueprint
+        .body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ast::BlueprintStmt::With(with) => symbols
+                .lookup_in_scope_chain(body_scope, &with.backend, &result.scopes)
+                .and_then(|id| symbols.get(id))
+                .filter(|symbol| symbol.kind == SymbolKind::Backend)
+                .and_then(|symbol| symbol.body_scope),
+            _ => None,
+        })
+        .collect();
+
+    for param in &blueprint.params {
+        let Some(id) = symbols.lookup_local(body_scope, &param.name) else {
+            continue;
+        };
+        let sets_member = backend_members
+            .iter()
+            .any(|&scope| symbols.lookup_local(scope, &param.name).is_some());
+        if sets_member || !reported(id, &param.name) {
+            continue;
+        }
+        diagnostics.add(
+            Diagnostic::from_code(
+                &codes::E0308,
+                param.span,
+                format!("unused parameter '{}' of blueprint '{}'", param.name, blueprint.name),
+            )
+            .unnecessary(),
+        );
+    }
+}
+
+/// Backends and schemes of the file that nothing reaches, in declaration order
+fn unreached_declarations(
+    result: &ResolveResult,
+    module: &str,
+    registry: &SignatureRegistry,
+) -> Vec<SymbolId> {
+    let symbols = &result.symbols;
+    let scopes = &result.scopes;
+
+    // Body scope -> declaration, for the backends and schemes of the file
+    let declarations: HashMap<ScopeId, SymbolId> = symbols
+        .symbols_in_scope(ScopeId::ROOT)
+        .filter(|s| matches!(s.kind, SymbolKind::Backend | SymbolKind::Scheme))
+        .filter(|s| !s.is_external())
+        .filter_map(|s| s.body_scope.map(|scope| (scope, s.id)))
+        .collect();
+
+    // The backend or scheme whose body a scope is in, if any
+    let owner = |scope: ScopeId| {
+        std::iter::once(scope)
+            .chain(scopes.ancestors(scope))
+            .find(|&s| scopes.parent(s) == Some(ScopeId::ROOT))
+            .and_then(|s| declarations.get(&s).copied())
+    };
+
+    let imported = imported_elsewhere(module, registry);
+    let mut reached: HashSet<SymbolId> = declarations
+        .values()
+        .copied()
+        .filter(|&id| {
+            symbols
+                .get(id)
+                .is_some_and(|s| imported.contains("*") || imported.contains(&s.name))
+        })
+        .collect();
+    loop {
+        let before = reached.len();
+        for &(id, scope) in &result.references {
+            if owner(scope).is_none_or(|owner| reached.contains(&owner)) {
+                reached.insert(id);
+            }
+        }
+        if reached.len() == before {
+            break;
+        }
+    }
+
+    let mut unreached: Vec<SymbolId> = declarations
+        .into_values()
+        .filter(|id| !reached.contains(id))
+        .collect();
+    unreached.sort_by_key(|&id| symbols.get(id).map(|s| s.def_span.start));
+    unreached
+}
+
+/// Names of `module` imported by the other modules of the registry; `*` for
+/// a glob import
+fn imported_elsewhere(module: &str, registry: &SignatureRegistry) -> HashSet<String> {
+    let prefix = format!("{}.", module);
+    let mut imported = HashSet::new();
+    for path in registry.module_paths() {
+        if path == module {
+            continue;
+        }
+        let Some(signature) = registry.get(&path) else {
+            continue;
+        };
+        for import in &signature.imports {
+            if let Some(name) = import.strip_prefix(&prefix).filter(|name| !name.contains('.')) {
+                imported.insert(name.to_string());
+            }
+        }
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{analyze_module, build_signature, Module};
+
+    fn parse(source: &str) -> ast::File {
+        let result = crate::parse_file(source);
+        assert!(!result.diagnostics.has_errors(), "{:?}", result.diagnostics);
+        result.file.unwrap()
+    }
+
+    /// Unused-symbol diagnostics of `source`, after registering `others`
+    fn unused(source: &str, others: &[&str]) -> Vec<(String, String)> {
+        let registry = SignatureRegistry::new();
+        for other in others {
+            registry.register(build_signature(&Module::from_file(parse(other))).signature);
+        }
+        let result = analyze_module(&Module::from_file(parse(source)), &registry);
+        result
+            .diagnostics
+            .iter()
+            .filter(|d| d.tags.contains(&crate::diagnostic::DiagnosticTag::Unnecessary))
+            .map(|d| (d.code.clone().unwrap_or_default(), d.message.clone()))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(code, message)| (code.to_string(), message.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_locals_and_parameters() {
+        let source = r#"module app
+
+backend Counter {
+    count : i32 = 0
+}
+
+blueprint Card(title : String, count : i32, subtitle : String, _spare : i32) {
+    with Counter
+    width : i32 = 100
+    height : i32 = width * 2
+    _scratch : i32 = 0
+    text { title }
+}
+
+blueprint Main {
+    Card("a", 1, "b", 2)
+}
+"#;
+        assert_eq!(
+            unused(source, &[]),
+            pairs(&[
+                ("E0307", "unused local 'height'"),
+                ("E0308", "unused parameter 'subtitle' of blueprint 'Card'"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unreached_backends_and_schemes() {
+        let data = r#"module app.data
+
+scheme Order {
+    lines : List<OrderLine>
+}
+
+scheme OrderLine {
+    quantity : i32
+}
+
+scheme Draft {
+    order : Order
+}
+
+backend Ping {
+    include Pong
+}
+
+backend Pong {
+    include Ping
+}
+
+backend Exported {
+    count : i32 = 0
+}
+"#;
+        let client = "module app.client\n\nimport app.data.Exported\n\nblueprint Main {\n    with Exported\n    text { \"${count}\" }\n}\n";
+        let page = "module app.page\n\nimport app.data.Order\n\nblueprint Page(order : Order) {\n    text { \"${order}\" }\n}\n";
+        assert_eq!(
+            unused(data, &[client]),
+            pairs(&[
+                ("E0309", "scheme 'Order' is never used"),
+                ("E0309", "scheme 'OrderLine' is never used"),
+                ("E0309", "scheme 'Draft' is never used"),
+                ("E0309", "backend 'Ping' is never used"),
+                ("E0309", "backend 'Pong' is never used"),
+            ])
+        );
+        assert_eq!(
+            unused(data, &[client, page]),
+            pairs(&[
+                ("E0309", "scheme 'Draft' is never used"),
+                ("E0309", "backend 'Ping' is never used"),
+                ("E0309", "backend 'Pong' is never used"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unused_imports() {
+        let data = "module app.data\n\nscheme User {\n    name : String\n}\n\nenum Status { Active Inactive }\n";
+        let source = r#"module app
+
+import app.data.User
+import app.data.Status
+import app.data.*
+
+blueprint Main(user : User) {
+    text { user.name }
+}
+"#;
+        let registry = SignatureRegistry::new();
+        registry.register(build_signature(&Module::from_file(parse(data))).signature);
+        let result = analyze_module(&Module::from_file(parse(source)), &registry);
+        let warnings: Vec<_> = result
+            .diagnostics
+            .iter()
+            .filter(|d| d.code.as_deref() == Some("E0310"))
+            .collect();
+        assert_eq!(warnings.len(), 1, "{:?}", result.diagnostics);
+        assert_eq!(warnings[0].message, "unused import 'app.data.Status'");
+        assert_eq!(warnings[0].suggestions[0].replacement, "");
+    }
+}
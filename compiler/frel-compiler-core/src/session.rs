@@ -0,0 +1,257 @@
+// Recorded compiler sessions
+//
+// A session bundle captures what a failing build saw, so a bug report can be
+// replayed deterministically:
+//
+//   bundle/
+//     session.json   configuration, the steps of the session and the
+//                    diagnostics it ended with
+//     files/...      the input files when the session started, by path
+//                    relative to the project root
+//
+// `frelc compile --record` and `frel-server --record` write bundles and
+// `frelc replay` reproduces them. Paths in a bundle are always relative, so
+// it can be replayed on another machine.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::edition::{Edition, Experimental, Features};
+use crate::output::FileDiagnostic;
+
+/// Version of the bundle format
+pub const SESSION_VERSION: u32 = 1;
+
+/// Name of the session description in a bundle
+pub const SESSION_FILE: &str = "session.json";
+
+/// Directory of the input files in a bundle
+pub const FILES_DIR: &str = "files";
+
+/// Configuration and steps of a recorded session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    /// Tool that recorded the session: `frelc` or `frel-server`
+    pub tool: String,
+    /// Version of the compiler that recorded the session
+    pub compiler_version: String,
+    pub edition: Edition,
+    /// Experimental features enabled for the session
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Code generation target
+    pub target: String,
+    pub steps: Vec<Step>,
+    /// Diagnostics the session ended with, sorted by `sort_diagnostics`
+    pub outcome: Vec<FileDiagnostic>,
+}
+
+/// Something that happened during a session, in order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Step {
+    /// `frelc` compiled one file
+    Compile { file: String },
+    /// The server built the whole project
+    Build,
+    /// A file's contents changed on disk; `None` if it was removed
+    Write {
+        file: String,
+        contents: Option<String>,
+    },
+    /// The server rebuilt the modules affected by a changed file
+    FileChanged { file: String },
+}
+
+/// A session and the input files it started with
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub session: Session,
+    /// Contents of the input files, by relative path
+    pub files: BTreeMap<String, String>,
+}
+
+impl Session {
+    pub fn new(tool: &str, edition: Edition, features: &Features, target: &str) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            tool: tool.to_string(),
+            compiler_version: crate::VERSION.to_string(),
+            edition,
+            features: features.iter().map(|f| f.as_str().to_string()).collect(),
+            target: target.to_string(),
+            steps: Vec::new(),
+            outcome: Vec::new(),
+        }
+    }
+
+    /// The experimental features enabled for the session
+    pub fn features(&self) -> Result<Features, String> {
+        self.features
+            .iter()
+            .map(|name| name.parse::<Experimental>())
+            .collect()
+    }
+
+    /// Record the diagnostics the session ended with
+    pub fn set_outcome(&mut self, mut diagnostics: Vec<FileDiagnostic>) {
+        sort_diagnostics(&mut diagnostics);
+        self.outcome = diagnostics;
+    }
+}
+
+impl Bundle {
+    /// Write the bundle into `dir`, replacing an earlier bundle there
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let files_dir = dir.join(FILES_DIR);
+        if files_dir.exists() {
+            fs::remove_dir_all(&files_dir)?;
+        }
+        for (path, contents) in &self.files {
+            let file = files_dir.join(relative_path(path)?);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file, contents)?;
+        }
+        let json = serde_json::to_string_pretty(&self.session).map_err(io::Error::other)?;
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(SESSION_FILE), json)
+    }
+
+    /// Read a bundle written by `save`
+    pub fn load(dir: &Path) -> io::Result<Bundle> {
+        let json = fs::read_to_string(dir.join(SESSION_FILE))?;
+        let session: Session = serde_json::from_str(&json).map_err(io::Error::other)?;
+        if session.version != SESSION_VERSION {
+            return Err(io::Error::other(format!(
+                "unsupported session version {} (expected {})",
+                session.version, SESSION_VERSION
+            )));
+        }
+
+        let mut files = BTreeMap::new();
+        let files_dir = dir.join(FILES_DIR);
+        let mut pending = vec![files_dir.clone()];
+        while let Some(current) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&current) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let Ok(name) = path.strip_prefix(&files_dir) {
+                    let name = name.to_string_lossy().replace('\\', "/");
+                    files.insert(name, fs::read_to_string(&path)?);
+                }
+            }
+        }
+
+        Ok(Bundle { session, files })
+    }
+}
+
+/// Order diagnostics so recordings and replays compare equal
+pub fn sort_diagnostics(diagnostics: &mut [FileDiagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        let key = |d: &FileDiagnostic| {
+            (
+                d.file.clone(),
+                d.diagnostic.span.start,
+                d.diagnostic.span.end,
+            )
+        };
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| a.diagnostic.message.cmp(&b.diagnostic.message))
+    });
+}
+
+/// Whether two sorted diagnostic lists report the same problems
+///
+/// Severity, code, message, file and span are compared; help texts and
+/// suggestions may change between compiler versions.
+pub fn same_diagnostics(a: &[FileDiagnostic], b: &[FileDiagnostic]) -> bool {
+    let key = |d: &FileDiagnostic| {
+        let diagnostic = &d.diagnostic;
+        (
+            d.file.clone(),
+            diagnostic.severity,
+            diagnostic.code.clone(),
+            diagnostic.message.clone(),
+            diagnostic.span,
+        )
+    };
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| key(a) == key(b))
+}
+
+/// A path recorded in a bundle, refusing ones that would escape the
+/// directory it is replayed in
+pub fn relative_path(path: &str) -> io::Result<&Path> {
+    let relative = Path::new(path);
+    let escapes = relative
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)));
+    if escapes {
+        return Err(io::Error::other(format!("not a relative path: {}", path)));
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Diagnostics;
+    use crate::output::CompilerOutput;
+    use crate::source::Span;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("frel-session-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut session = Session::new("frelc", Edition::E2025, &Features::default(), "javascript");
+        session.steps.push(Step::Compile {
+            file: "app/main.frel".to_string(),
+        });
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.error("second", Span::new(9, 12));
+        diagnostics.error("first", Span::new(2, 4));
+        let mut output = CompilerOutput::new();
+        output.add_diagnostics("app/main.frel", None, &diagnostics);
+        session.set_outcome(output.diagnostics.clone());
+
+        let mut files = BTreeMap::new();
+        files.insert("app/main.frel".to_string(), "module app\n".to_string());
+        Bundle { session, files }.save(&dir).unwrap();
+
+        let loaded = Bundle::load(&dir).unwrap();
+        assert_eq!(loaded.session.edition, Edition::E2025);
+        assert_eq!(loaded.session.steps.len(), 1);
+        assert_eq!(loaded.files["app/main.frel"], "module app\n");
+        assert_eq!(loaded.session.outcome[0].diagnostic.message, "first");
+
+        let mut replayed = output.diagnostics;
+        sort_diagnostics(&mut replayed);
+        assert!(same_diagnostics(&loaded.session.outcome, &replayed));
+        assert!(!same_diagnostics(&loaded.session.outcome, &replayed[1..]));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bundle_rejects_escaping_paths() {
+        let session = Session::new("frelc", Edition::LATEST, &Features::default(), "javascript");
+        let mut files = BTreeMap::new();
+        files.insert("../outside.frel".to_string(), String::new());
+        let dir = std::env::temp_dir().join(format!("frel-session-escape-{}", std::process::id()));
+        assert!(Bundle { session, files }.save(&dir).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -5,6 +5,24 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Identifies one source file of a multi-file compilation
+///
+/// Offsets in a [`Span`] are relative to its file, so spans of two files in
+/// the same module only compare equal when their files do too. `FileId(0)` is
+/// the default: a single-file compilation, or a span whose file is unknown.
+///
+/// IDs are only meaningful within one compilation and are not serialized;
+/// output names files by path instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    /// Check if this is the default file (0)
+    pub fn is_default(&self) -> bool {
+        self.0 == 0
+    }
+}
+
 /// A span representing a range of bytes in source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
@@ -12,24 +30,39 @@ pub struct Span {
     pub start: u32,
     /// End byte offset (exclusive)
     pub end: u32,
+    /// File the offsets are relative to
+    #[serde(skip)]
+    pub file: FileId,
 }
 
 impl Span {
-    /// Create a new span
+    /// Create a new span in the default file
     pub fn new(start: u32, end: u32) -> Self {
-        Self { start, end }
+        Self {
+            start,
+            end,
+            file: FileId::default(),
+        }
     }
 
     /// Create a span of length zero at a position (for insertion points)
     pub fn point(pos: u32) -> Self {
-        Self { start: pos, end: pos }
+        Self::new(pos, pos)
+    }
+
+    /// The same range in `file`
+    pub fn in_file(self, file: FileId) -> Span {
+        Span { file, ..self }
     }
 
     /// Create a span covering two spans (from start of first to end of second)
+    ///
+    /// The result keeps the file of `self`.
     pub fn merge(self, other: Span) -> Span {
         Span {
             start: self.start.min(other.start),
             end: self.end.max(other.end),
+            file: self.file,
         }
     }
 
@@ -57,7 +90,7 @@ impl Span {
 
 impl Default for Span {
     fn default() -> Self {
-        Self { start: 0, end: 0 }
+        Self::new(0, 0)
     }
 }
 
@@ -121,6 +154,15 @@ impl LineIndex {
         }
     }
 
+    /// Convert a line/column back to a byte offset, clamping to the line end
+    pub fn offset(&self, position: LineCol, source: &str) -> u32 {
+        let line = (position.line as usize).saturating_sub(1);
+        let (Some(start), Some(end)) = (self.line_start(line), self.line_end(line, source)) else {
+            return source.len() as u32;
+        };
+        (start + position.col.saturating_sub(1)).min(end)
+    }
+
     /// Get the byte offset of a line start (0-indexed line number)
     pub fn line_start(&self, line: usize) -> Option<u32> {
         self.line_starts.get(line).copied()
@@ -0,0 +1,355 @@
+// Input source maps
+//
+// Teams that generate `.frel` files from templates can put a source map next
+// to a generated file (`app.frel.map` for `app.frel`) in the Source Map v3
+// format emitted by template engines. With `--remap-diagnostics` the CLI, the
+// compiler server and the language server report the diagnostics of the
+// generated file at the template positions the map points to.
+//
+// Only the primary span of a diagnostic is required to map. Labels and
+// related information are kept when they map into the same template;
+// suggestions are dropped, as their edits apply to the generated file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::diagnostic::{Diagnostic, Diagnostics, Label, RelatedInfo};
+use crate::source::{LineCol, LineIndex, Span};
+use crate::vfs::SourceProvider;
+
+/// Extension appended to a generated file's name to find its source map
+pub const SIDECAR_EXTENSION: &str = "map";
+
+/// A parsed source map of a generated file
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    /// Template files, as resolved when the map was loaded
+    sources: Vec<String>,
+    /// Mapped segments of each generated line, by generated column
+    lines: Vec<Vec<Segment>>,
+}
+
+/// Start of a mapped range on a generated line; all fields 0-based
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    column: u32,
+    source: usize,
+    line: u32,
+    col: u32,
+}
+
+/// A position in a template file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalPosition {
+    pub file: String,
+    pub position: LineCol,
+}
+
+/// Diagnostics moved from a generated file into one of its templates
+#[derive(Debug)]
+pub struct Remapped {
+    pub file: String,
+    /// Contents of the template, which the diagnostics' spans point into
+    pub source: String,
+    pub diagnostics: Diagnostics,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSourceMap {
+    version: u32,
+    #[serde(default)]
+    source_root: Option<String>,
+    sources: Vec<String>,
+    mappings: String,
+}
+
+impl SourceMap {
+    /// Parse a Source Map v3 document
+    pub fn parse(json: &str) -> Result<SourceMap, String> {
+        let raw: RawSourceMap = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if raw.version != 3 {
+            return Err(format!("unsupported source map version {}", raw.version));
+        }
+        let root = raw.source_root.unwrap_or_default();
+        let sources = raw
+            .sources
+            .iter()
+            .map(|source| match root.as_str() {
+                "" => source.clone(),
+                root => format!("{}/{}", root.trim_end_matches('/'), source),
+            })
+            .collect::<Vec<_>>();
+
+        // Source index, original line and column are relative to the previous
+        // segment of the whole map, the generated column to the previous
+        // segment of the line
+        let (mut source, mut line, mut col) = (0i64, 0i64, 0i64);
+        let mut lines = Vec::new();
+        for text in raw.mappings.split(';') {
+            let mut segments = Vec::new();
+            let mut column = 0i64;
+            for segment in text.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment)?;
+                column += fields[0];
+                if fields.len() < 4 {
+                    continue;
+                }
+                source += fields[1];
+                line += fields[2];
+                col += fields[3];
+                if source < 0 || source as usize >= sources.len() || line < 0 || col < 0 {
+                    return Err(format!("invalid mapping segment `{}`", segment));
+                }
+                segments.push(Segment {
+                    column: column.max(0) as u32,
+                    source: source as usize,
+                    line: line as u32,
+                    col: col as u32,
+                });
+            }
+            segments.sort_by_key(|segment| segment.column);
+            lines.push(segments);
+        }
+
+        Ok(SourceMap { sources, lines })
+    }
+
+    /// Path of the source map of a generated file
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".");
+        name.push(SIDECAR_EXTENSION);
+        PathBuf::from(name)
+    }
+
+    /// Load the source map next to a generated file; `None` if there is none
+    ///
+    /// Relative template paths are resolved against the map's directory.
+    pub fn load_sidecar(
+        files: &dyn SourceProvider,
+        path: &Path,
+    ) -> Option<Result<SourceMap, String>> {
+        let map_path = Self::sidecar_path(path);
+        let json = files.read(&map_path).ok()?;
+        let dir = map_path.parent().unwrap_or(Path::new(""));
+        let result = Self::parse(&json)
+            .map(|mut map| {
+                for source in &mut map.sources {
+                    *source = dir.join(&*source).display().to_string();
+                }
+                map
+            })
+            .map_err(|e| format!("invalid source map {}: {}", map_path.display(), e));
+        Some(result)
+    }
+
+    /// Template position of a position in the generated file
+    pub fn original(&self, position: LineCol) -> Option<OriginalPosition> {
+        let segments = self.lines.get(position.line.checked_sub(1)? as usize)?;
+        let column = position.col.saturating_sub(1);
+        let index = segments.partition_point(|segment| segment.column <= column);
+        let segment = segments.get(index.checked_sub(1)?)?;
+        Some(OriginalPosition {
+            file: self.sources[segment.source].clone(),
+            position: LineCol {
+                line: segment.line + 1,
+                col: segment.col + (column - segment.column) + 1,
+            },
+        })
+    }
+
+    /// Move the diagnostics of a generated file into the templates they map to
+    ///
+    /// Templates are read with `read`. Returns the diagnostics that couldn't
+    /// be mapped, then the mapped ones by template.
+    pub fn remap_diagnostics(
+        &self,
+        diagnostics: &Diagnostics,
+        generated: &str,
+        read: impl Fn(&str) -> Option<String>,
+    ) -> (Diagnostics, Vec<Remapped>) {
+        let index = LineIndex::new(generated);
+        let mut templates: BTreeMap<String, Option<(String, LineIndex)>> = BTreeMap::new();
+        let mut remapped: BTreeMap<String, Diagnostics> = BTreeMap::new();
+        let mut unmapped = Diagnostics::new();
+
+        for diagnostic in diagnostics.iter() {
+            let mut span_in = |span: Span| {
+                let start = self.original(index.line_col(span.start))?;
+                let template = templates
+                    .entry(start.file.clone())
+                    .or_insert_with(|| {
+                        let source = read(&start.file)?;
+                        let index = LineIndex::new(&source);
+                        Some((source, index))
+                    })
+                    .as_ref()?;
+                let (source, template_index) = (&template.0, &template.1);
+                let start_offset = template_index.offset(start.position, source);
+
+                // The end maps through the span's last byte, which may be
+                // on a line that maps elsewhere than the end offset
+                let last = index.line_col(span.end.max(span.start + 1) - 1);
+                let end_offset = match self.original(last) {
+                    Some(end) if end.file == start.file => {
+                        template_index.offset(end.position, source) + 1
+                    }
+                    _ => start_offset,
+                };
+                let end_offset = end_offset.clamp(start_offset, source.len() as u32);
+                let span = match span.is_empty() {
+                    true => Span::new(start_offset, start_offset),
+                    false => Span::new(start_offset, end_offset),
+                };
+                Some((start.file, span))
+            };
+
+            let Some((file, span)) = span_in(diagnostic.span) else {
+                unmapped.add(diagnostic.clone());
+                continue;
+            };
+            let mut moved = Diagnostic {
+                span,
+                labels: Vec::new(),
+                suggestions: Vec::new(),
+                related: Vec::new(),
+                ..diagnostic.clone()
+            };
+            for label in &diagnostic.labels {
+                if let Some((label_file, span)) = span_in(label.span) {
+                    if label_file == file {
+                        moved.labels.push(Label {
+                            span,
+                            ..label.clone()
+                        });
+                    }
+                }
+            }
+            for info in &diagnostic.related {
+                match &info.file {
+                    Some(_) => moved.related.push(info.clone()),
+                    None => {
+                        if let Some((info_file, span)) = span_in(info.span) {
+                            let file = (info_file != file).then_some(info_file);
+                            moved.related.push(RelatedInfo {
+                                span,
+                                file,
+                                message: info.message.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+            remapped.entry(file).or_default().add(moved);
+        }
+
+        let remapped = remapped
+            .into_iter()
+            .filter_map(|(file, diagnostics)| {
+                let (source, _) = templates.remove(&file)??;
+                Some(Remapped {
+                    file,
+                    source,
+                    diagnostics,
+                })
+            })
+            .collect();
+        (unmapped, remapped)
+    }
+}
+
+/// Decode a segment of base64 VLQ numbers
+fn decode_vlq(segment: &str) -> Result<Vec<i64>, String> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0u32);
+    for c in segment.chars() {
+        let digit = match c {
+            'A'..='Z' => c as i64 - 'A' as i64,
+            'a'..='z' => c as i64 - 'a' as i64 + 26,
+            '0'..='9' => c as i64 - '0' as i64 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(format!("invalid mapping character `{}`", c)),
+        };
+        if shift > 60 {
+            return Err(format!("mapping value too large in `{}`", segment));
+        }
+        value += (digit & 31) << shift;
+        if digit & 32 != 0 {
+            shift += 5;
+            continue;
+        }
+        values.push(if value & 1 == 1 {
+            -(value >> 1)
+        } else {
+            value >> 1
+        });
+        (value, shift) = (0, 0);
+    }
+    if shift != 0 {
+        return Err(format!("truncated mapping segment `{}`", segment));
+    }
+    if values.is_empty() {
+        return Err("empty mapping segment".to_string());
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Template whose lines 5 to 9 generate `GENERATED`, with the blueprint
+    /// body indented by 4 more columns
+    const TEMPLATE: &str =
+        "{{! header }}\n{{#each}}\n\n\n\nmodule app\n\nblueprint Main {\n        missing { }\n}\n";
+    const GENERATED: &str = "module app\n\nblueprint Main {\n    missing { }\n}\n";
+    const MAP: &str =
+        r#"{"version":3,"sources":["app.frel.hbs"],"mappings":"AAKA;AACA;AACA;AACI;AACJ"}"#;
+
+    #[test]
+    fn test_original_position() {
+        let map = SourceMap::parse(MAP).unwrap();
+        let position = |line, col| LineCol { line, col };
+        assert_eq!(
+            map.original(position(4, 5)),
+            Some(OriginalPosition {
+                file: "app.frel.hbs".to_string(),
+                position: position(9, 9),
+            })
+        );
+        assert_eq!(
+            map.original(position(1, 1)).unwrap().position,
+            position(6, 1)
+        );
+        assert_eq!(map.original(position(7, 1)), None);
+        assert!(SourceMap::parse(r#"{"version":3,"sources":[],"mappings":"AAAA"}"#).is_err());
+    }
+
+    #[test]
+    fn test_remap_diagnostics() {
+        let map = SourceMap::parse(MAP).unwrap();
+        let mut diagnostics = Diagnostics::new();
+        let start = GENERATED.find("missing").unwrap() as u32;
+        diagnostics.error(
+            "cannot find `missing` in this scope",
+            Span::new(start, start + 7),
+        );
+
+        let read = |file: &str| (file == "app.frel.hbs").then(|| TEMPLATE.to_string());
+        let (unmapped, remapped) = map.remap_diagnostics(&diagnostics, GENERATED, read);
+        assert!(unmapped.is_empty());
+        assert_eq!(remapped.len(), 1);
+        assert_eq!(remapped[0].file, "app.frel.hbs");
+        let span = remapped[0].diagnostics.iter().next().unwrap().span;
+        assert_eq!(&TEMPLATE[span.start as usize..span.end as usize], "missing");
+
+        // Without the template the diagnostic stays in the generated file
+        let (unmapped, remapped) = map.remap_diagnostics(&diagnostics, GENERATED, |_| None);
+        assert_eq!(unmapped.len(), 1);
+        assert!(remapped.is_empty());
+    }
+}
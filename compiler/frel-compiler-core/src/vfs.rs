@@ -0,0 +1,237 @@
+// File access for the Frel compiler
+//
+// The compiler reads sources, `frel.toml`, source maps and saved signatures
+// through a `SourceProvider`, and writes generated files and signatures
+// through an `OutputSink`, instead of calling `std::fs` itself:
+//
+// - `DiskFs` is the real filesystem, used by the CLI and the server
+// - `MemoryFs` holds files in memory, for tests without temp directories,
+//   virtual projects (e.g. editor buffers) and builds where there is no
+//   filesystem, such as WASM
+//
+// Both traits work on full paths; `MemoryFs` has no directories of its own,
+// a directory exists as long as a file is under it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Read access to source files
+pub trait SourceProvider: Send + Sync {
+    /// Read a file as text
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Size of a file in bytes
+    fn size(&self, path: &Path) -> io::Result<u64>;
+
+    /// Check if `path` is a file
+    fn is_file(&self, path: &Path) -> bool {
+        self.size(path).is_ok()
+    }
+
+    /// Files under `dir` at any depth, sorted; a missing directory is
+    /// `io::ErrorKind::NotFound`
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// Write access to generated files
+pub trait OutputSink: Send + Sync {
+    /// Write a file, creating its directories
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// Remove a file; removing a missing file is not an error
+    fn remove(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The real filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFs;
+
+impl SourceProvider for DiskFs {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.len())
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![fs::read_dir(dir)?];
+        while let Some(entries) = pending.pop() {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if let Ok(entries) = fs::read_dir(&path) {
+                        pending.push(entries);
+                    }
+                } else if path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl OutputSink for DiskFs {
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Files held in memory
+///
+/// Implements both traits, so what the compiler writes can be read back,
+/// as on the disk.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: RwLock<BTreeMap<PathBuf, String>>,
+}
+
+impl MemoryFs {
+    /// An empty filesystem
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file
+    pub fn with_file(self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.insert(path, contents);
+        self
+    }
+
+    /// Add or replace a file
+    pub fn insert(&self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.files.write().unwrap().insert(path.into(), contents.into());
+    }
+
+    /// Contents of a file
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.files.read().unwrap().get(path.as_ref()).cloned()
+    }
+
+    /// Remove a file, returning its contents
+    pub fn take(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.files.write().unwrap().remove(path.as_ref())
+    }
+
+    /// Paths of all files, sorted
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.read().unwrap().keys().cloned().collect()
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{}: no such file", path.display()))
+}
+
+impl SourceProvider for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.get(path).ok_or_else(|| not_found(path))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        let files = self.files.read().unwrap();
+        files.get(path).map(|contents| contents.len() as u64).ok_or_else(|| not_found(path))
+    }
+
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let files: Vec<PathBuf> = self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|path| path.starts_with(dir) && *path != dir)
+            .cloned()
+            .collect();
+        if files.is_empty() {
+            return Err(not_found(dir));
+        }
+        Ok(files)
+    }
+}
+
+impl OutputSink for MemoryFs {
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.take(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_fs() {
+        let files = MemoryFs::new()
+            .with_file("/app/main.frel", "module app.main\n")
+            .with_file("/app/ui/card.frel", "module app.ui\n")
+            .with_file("/application.frel", "module other\n");
+
+        assert_eq!(files.read(Path::new("/app/main.frel")).unwrap(), "module app.main\n");
+        assert_eq!(files.size(Path::new("/app/ui/card.frel")).unwrap(), 14);
+        assert!(files.is_file(Path::new("/app/main.frel")));
+        assert!(!files.is_file(Path::new("/app")));
+        assert_eq!(
+            files.files(Path::new("/app")).unwrap(),
+            vec![PathBuf::from("/app/main.frel"), PathBuf::from("/app/ui/card.frel")]
+        );
+        assert_eq!(
+            files.files(Path::new("/missing")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        files.write(Path::new("/build/app/main.js"), "main()").unwrap();
+        assert_eq!(files.read(Path::new("/build/app/main.js")).unwrap(), "main()");
+        files.remove(Path::new("/build/app/main.js")).unwrap();
+        files.remove(Path::new("/build/app/main.js")).unwrap();
+        assert_eq!(
+            files.read(Path::new("/build/app/main.js")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_disk_fs() {
+        let dir = std::env::temp_dir().join(format!("frel-vfs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        DiskFs.write(&dir.join("app/main.frel"), "module app.main\n").unwrap();
+        DiskFs.write(&dir.join("app/ui/card.frel"), "module app.ui\n").unwrap();
+        assert_eq!(DiskFs.read(&dir.join("app/main.frel")).unwrap(), "module app.main\n");
+        assert_eq!(
+            DiskFs.files(&dir).unwrap(),
+            vec![dir.join("app/main.frel"), dir.join("app/ui/card.frel")]
+        );
+
+        DiskFs.remove(&dir.join("app/main.frel")).unwrap();
+        DiskFs.remove(&dir.join("app/main.frel")).unwrap();
+        assert!(!DiskFs.is_file(&dir.join("app/main.frel")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+// Content fingerprints of generated files
+//
+// For long-term caching, generated files can be named by a hash of their
+// contents: `app/todo_list.js` is written as `app/todo_list.3f9a0c1e.js`, so a
+// changed file gets a new URL and an unchanged one may be cached forever. An
+// `AssetManifest` maps the logical names to the hashed ones; it is written as
+// `manifest.json` next to the files, for import maps and deploy scripts.
+//
+// The hash is FNV-1a over the contents, so names are the same on every
+// platform and compiler build. The manifest spans modules, so fingerprints are
+// applied by whoever writes the files (`frelc compile`, the compiler server)
+// when `GenerateOptions::fingerprint` is set, not by each code generator.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the manifest in the output directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Hex digits of the content hash in a file name
+const HASH_LEN: usize = 8;
+
+/// Hash of a file's contents as it appears in the file name
+pub fn content_hash(contents: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in contents.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)[..HASH_LEN].to_string()
+}
+
+/// `path` with the hash of `contents` before its extension, e.g.
+/// `app/editor.js` -> `app/editor.3f9a0c1e.js`
+pub fn fingerprinted_path(path: &Path, contents: &str) -> PathBuf {
+    let hash = content_hash(contents);
+    let name = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => {
+            format!("{}.{}.{}", stem.to_string_lossy(), hash, ext.to_string_lossy())
+        }
+        _ => format!("{}.{}", path.to_string_lossy(), hash),
+    };
+    path.with_file_name(name)
+}
+
+/// Logical file names mapped to their fingerprinted names, as paths relative
+/// to the output directory with `/` separators
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetManifest {
+    files: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a manifest written by `to_json`
+    pub fn parse(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Record the fingerprinted name of a file, returning the one it replaces
+    pub fn insert(&mut self, logical: &Path, hashed: &Path) -> Option<PathBuf> {
+        self.files
+            .insert(manifest_key(logical), manifest_key(hashed))
+            .map(PathBuf::from)
+    }
+
+    /// Forget a file, returning its fingerprinted name
+    pub fn remove(&mut self, logical: &Path) -> Option<PathBuf> {
+        self.files.remove(&manifest_key(logical)).map(PathBuf::from)
+    }
+
+    /// Fingerprinted name of a file
+    pub fn get(&self, logical: &Path) -> Option<PathBuf> {
+        self.files.get(&manifest_key(logical)).map(PathBuf::from)
+    }
+
+    /// Whether `path` is the fingerprinted name of a file
+    pub fn is_fingerprinted(&self, path: &Path) -> bool {
+        let key = manifest_key(path);
+        self.files.values().any(|hashed| *hashed == key)
+    }
+
+    /// Entries by logical name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().map(|(logical, hashed)| (logical.as_str(), hashed.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Pretty-printed JSON, one entry per line
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// A relative path with `/` separators on every platform
+fn manifest_key(path: &Path) -> String {
+    let parts: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprinted_path() {
+        let path = fingerprinted_path(Path::new("app/todo_list.js"), "export {};\n");
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(path.parent(), Some(Path::new("app")));
+        assert!(name.starts_with("todo_list.") && name.ends_with(".js"), "{}", name);
+        assert_eq!(name.len(), "todo_list..js".len() + HASH_LEN);
+
+        // Same contents, same name; other contents, another name
+        assert_eq!(fingerprinted_path(Path::new("app/todo_list.js"), "export {};\n"), path);
+        assert_ne!(fingerprinted_path(Path::new("app/todo_list.js"), "export {}\n"), path);
+        assert_eq!(content_hash(""), "cbf29ce4");
+    }
+
+    #[test]
+    fn test_asset_manifest() {
+        let mut manifest = AssetManifest::new();
+        let hashed = fingerprinted_path(Path::new("app/editor.js"), "a");
+        assert_eq!(manifest.insert(Path::new("app/editor.js"), &hashed), None);
+        assert!(manifest.is_fingerprinted(&hashed));
+        assert!(!manifest.is_fingerprinted(Path::new("app/editor.js")));
+
+        let json = manifest.to_json();
+        assert!(json.contains("\"app/editor.js\""), "{}", json);
+        let parsed = AssetManifest::parse(&json).unwrap();
+        assert_eq!(parsed.get(Path::new("app/editor.js")), Some(hashed.clone()));
+
+        let newer = fingerprinted_path(Path::new("app/editor.js"), "b");
+        assert_eq!(manifest.insert(Path::new("app/editor.js"), &newer), Some(hashed));
+        assert_eq!(manifest.remove(Path::new("app/editor.js")), Some(newer));
+        assert!(manifest.is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+// Frel Code Generation Plugin API
+//
+// A code generator implements `CodegenPlugin` for one target host language.
+// It receives a module lowered to the typed IR (`frel_compiler_core::ir`), so
+// it never resolves names or types itself, and returns the files to write.
+//
+// Plugins are looked up by target name in a `PluginRegistry`: the CLI's
+// `--target` flag and the compiler server dispatch through it, so a new
+// backend only has to be registered.
+//
+// The registry also holds external semantic passes (`SemanticPass`): policy
+// checks that run after type checking and may report diagnostics.
+//
+// Plugins may come from third parties, so the API gives them no ambient
+// access to files or the environment: both kinds of plugins get a
+// `PluginContext` granting the project root, the build directory and the
+// `FREL_` environment variables, and nothing else.
+//
+// Generated files can be named by a hash of their contents for long-term
+// caching, with a manifest of the names (see fingerprint.rs).
+
+pub mod fingerprint;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use frel_compiler_core::ir;
+
+pub use fingerprint::{content_hash, fingerprinted_path, AssetManifest, MANIFEST_FILE};
+pub use frel_compiler_core::sandbox::PluginContext;
+pub use frel_compiler_core::semantic::{run_passes, SemanticPass};
+
+/// Options shared by all code generators
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// Emit the stable, human-readable ID of every call site (`CallSite::id`)
+    pub fragment_ids: bool,
+    /// Generate compact code for production: dead branches stripped,
+    /// whitespace removed and local names shortened
+    pub minify: bool,
+    /// Name the files by a hash of their contents and list them in an
+    /// `AssetManifest`; applied by the caller writing the files
+    pub fingerprint: bool,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            fragment_ids: true,
+            minify: false,
+            fingerprint: false,
+        }
+    }
+}
+
+/// A file produced by a code generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    /// Path relative to the build directory, e.g. `app/editor.js`
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl OutputFile {
+    /// File of a module in the build directory: one directory per segment of
+    /// the module path, e.g. `app.editor` -> `app/editor.<extension>`
+    pub fn for_module(module_path: &str, extension: &str, contents: String) -> Self {
+        let mut path: PathBuf = module_path.split('.').collect();
+        path.set_extension(extension);
+        Self { path, contents }
+    }
+}
+
+/// Error reported by a code generator
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{target} code generation failed: {message}")]
+pub struct CodegenError {
+    /// Name of the plugin's target
+    pub target: String,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, CodegenError>;
+
+/// A code generator for one target host language
+pub trait CodegenPlugin: Send + Sync {
+    /// Target name, as passed to `--target`
+    fn name(&self) -> &str;
+
+    /// Other names accepted for the target, e.g. `js`
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Extension of the module's main output file, without the dot
+    fn file_extension(&self) -> &str;
+
+    /// Generate the files of a module; the module's main file comes first.
+    /// Files other than the module's are read through `context`.
+    fn generate(
+        &self,
+        module: &ir::Module,
+        options: &GenerateOptions,
+        context: &PluginContext,
+    ) -> Result<Vec<OutputFile>>;
+}
+
+/// Code generators by target name, and semantic passes
+#[derive(Default, Clone)]
+pub struct PluginRegistry {
+    plugins: Vec<Arc<dyn CodegenPlugin>>,
+    passes: Vec<Arc<dyn SemanticPass>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a plugin, replacing any plugin with the same name
+    pub fn register(&mut self, plugin: impl CodegenPlugin + 'static) {
+        self.plugins.retain(|p| p.name() != plugin.name());
+        self.plugins.push(Arc::new(plugin));
+    }
+
+    /// Plugin for `target`, by name or alias
+    pub fn get(&self, target: &str) -> Option<Arc<dyn CodegenPlugin>> {
+        self.plugins
+            .iter()
+            .find(|p| p.name() == target || p.aliases().contains(&target))
+            .cloned()
+    }
+
+    /// Names of the registered targets, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.plugins.iter().map(|p| p.name()).collect()
+    }
+
+    /// Register a semantic pass, run after the passes registered before it
+    pub fn register_pass(&mut self, pass: impl SemanticPass + 'static) {
+        self.passes.push(Arc::new(pass));
+    }
+
+    /// Registered semantic passes, in run order
+    pub fn passes(&self) -> &[Arc<dyn SemanticPass>] {
+        &self.passes
+    }
+}
+
+impl std::fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginRegistry")
+            .field("targets", &self.names())
+            .field(
+                "passes",
+                &self.passes.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Listing;
+
+    impl CodegenPlugin for Listing {
+        fn name(&self) -> &str {
+            "listing"
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["ls"]
+        }
+
+        fn file_extension(&self) -> &str {
+            "txt"
+        }
+
+        fn generate(
+            &self,
+            module: &ir::Module,
+            _: &GenerateOptions,
+            _: &PluginContext,
+        ) -> Result<Vec<OutputFile>> {
+            if module.declarations.is_empty() {
+                return Err(CodegenError {
+                    target: self.name().to_string(),
+                    message: "nothing to list".to_string(),
+                });
+            }
+            let names: Vec<&str> = module.blueprints().map(|bp| bp.name.as_str()).collect();
+            let contents = names.join("\n");
+            Ok(vec![OutputFile::for_module(&module.path, "txt", contents)])
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatch() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Listing);
+        assert_eq!(registry.names(), ["listing"]);
+        assert!(registry.get("cobol").is_none());
+
+        let plugin = registry.get("ls").unwrap();
+        let file = frel_compiler_core::parse_file("module app.editor\n\nblueprint Main { }\n");
+        let module = ir::lower(&file.file.unwrap());
+        let files = plugin
+            .generate(&module, &GenerateOptions::default(), &PluginContext::new())
+            .unwrap();
+        assert_eq!(files[0].path, PathBuf::from("app/editor.txt"));
+        assert_eq!(files[0].contents, "Main");
+
+        let empty = ir::lower(&frel_compiler_core::parse_file("module app\n").file.unwrap());
+        let error = plugin
+            .generate(&empty, &GenerateOptions::default(), &PluginContext::new())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "listing code generation failed: nothing to list"
+        );
+    }
+
+    /// Security review rule: no blueprint may be named `Admin`
+    struct NoAdmin;
+
+    impl SemanticPass for NoAdmin {
+        fn name(&self) -> &str {
+            "no-admin"
+        }
+
+        fn run(
+            &self,
+            module: &frel_compiler_core::Module,
+            analysis: &frel_compiler_core::ModuleAnalysisResult,
+            _: &PluginContext,
+            diagnostics: &mut frel_compiler_core::Diagnostics,
+        ) {
+            let admin = analysis
+                .symbols
+                .iter()
+                .find(|symbol| symbol.name == "Admin");
+            if let Some(symbol) = admin {
+                let message = format!("`Admin` is not allowed in {}", module.path);
+                diagnostics.error(message, symbol.def_span);
+            }
+        }
+    }
+
+    #[test]
+    fn test_registered_pass_runs_on_compile() {
+        let mut registry = PluginRegistry::new();
+        registry.register_pass(NoAdmin);
+        let compile = |source: &str| {
+            frel_compiler_core::compile_with_passes(
+                source,
+                "app.frel",
+                Default::default(),
+                Default::default(),
+                registry.passes(),
+                &PluginContext::new(),
+            )
+        };
+
+        assert!(compile("module app\n\nblueprint Main { }\n").success());
+        let result = compile("module app\n\nblueprint Admin { }\n");
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, ["`Admin` is not allowed in app"]);
+    }
+}
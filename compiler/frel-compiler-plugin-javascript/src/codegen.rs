@@ -1,148 +1,102 @@
 // JavaScript code generation
 //
-// This module generates JavaScript code from Frel AST that works with the
+// This module generates JavaScript code from the Frel IR that works with the
 // @frel/runtime package. The generated code includes:
 //
 // - Subscription callbacks
-// - Internal binding functions
-// - Call site binding functions
+// - Internal binding functions, registering keyboard shortcuts
+// - Error boundaries, creating their body or fallback
+// - Loading placeholders, shown while the commands next to them are in flight
+// - Call site binding functions, mounting portaled fragments in their overlay
 // - Theme initializers
+// - Contract clients, calling services through a runtime transport
+// - Scheme codecs (JSON decoding, encoding, type guards and validation)
+// - Enums with variant lists, parsing, display names and exhaustive matching
 // - Metadata (function tables)
 
-use frel_compiler_core::ast::*;
-use std::collections::HashMap;
-
-/// Context for code generation, including import resolution
-struct CodeGenContext<'a> {
-    module: &'a str,
-    /// Maps simple names to their qualified module paths
-    /// e.g., "text" -> "test.common" (from `import test.common.text`)
-    import_map: HashMap<String, String>,
-    /// Wildcard import modules (from `import foo.bar.*`)
-    /// These are checked when a name isn't in the explicit import_map
-    wildcard_modules: Vec<String>,
-    /// Names defined in the current module (blueprints, schemes, etc.)
-    local_names: Vec<String>,
-}
-
-impl<'a> CodeGenContext<'a> {
-    fn new(module: &'a str, imports: &[Import], local_names: Vec<String>) -> Self {
-        let mut import_map = HashMap::new();
-        let mut wildcard_modules = Vec::new();
-
-        for import in imports {
-            if import.import_all {
-                // Wildcard import: `import foo.bar.*`
-                // Store the module path for later resolution
-                wildcard_modules.push(import.path.clone());
-            } else {
-                // Single import: `import foo.bar.Baz`
-                if let Some((module_path, name)) = import.path.rsplit_once('.') {
-                    import_map.insert(name.to_string(), module_path.to_string());
-                }
-            }
-        }
-
-        Self {
-            module,
-            import_map,
-            wildcard_modules,
-            local_names,
-        }
-    }
+use frel_compiler_core::ast::{BinaryOp, UnaryOp};
+use frel_compiler_core::ir::*;
+use frel_compiler_core::semantic::members;
+use frel_compiler_core::semantic::{SymbolId, Type};
+use frel_compiler_plugin_api::GenerateOptions;
 
-    /// Resolve a simple name to its fully qualified name
-    fn resolve_name(&self, name: &str) -> String {
-        // 1. Check explicit imports first
-        if let Some(module_path) = self.import_map.get(name) {
-            return format!("{}.{}", module_path, name);
-        }
-
-        // 2. Check if it's a local name (defined in current module)
-        if self.local_names.contains(&name.to_string()) {
-            return format!("{}.{}", self.module, name);
-        }
-
-        // 3. Check wildcard imports - if there's exactly one, use it
-        //    If there are multiple, we can't resolve without semantic analysis
-        if self.wildcard_modules.len() == 1 {
-            return format!("{}.{}", self.wildcard_modules[0], name);
-        }
-
-        // 4. Fall back to current module (may be incorrect if name comes from wildcard)
-        format!("{}.{}", self.module, name)
-    }
+/// Generate JavaScript code for a Frel module
+pub fn generate_module(module: &Module) -> String {
+    generate_module_with_options(module, &GenerateOptions::default())
 }
 
-/// Generate JavaScript code for a Frel file
-pub fn generate_file(file: &File) -> String {
+/// Generate JavaScript code for a Frel module with explicit options
+pub fn generate_module_with_options(module: &Module, options: &GenerateOptions) -> String {
+    let _profile = frel_compiler_core::profile::enter("codegen");
     let mut output = String::new();
 
-    // Collect local names first (names defined in this module)
-    let local_names: Vec<String> = file
-        .declarations
-        .iter()
-        .filter_map(|decl| match decl {
-            TopLevelDecl::Blueprint(bp) => Some(bp.name.clone()),
-            TopLevelDecl::Backend(b) => Some(b.name.clone()),
-            TopLevelDecl::Contract(c) => Some(c.name.clone()),
-            TopLevelDecl::Scheme(s) => Some(s.name.clone()),
-            TopLevelDecl::Enum(e) => Some(e.name.clone()),
-            TopLevelDecl::Theme(t) => Some(t.name.clone()),
-            TopLevelDecl::Arena(a) => Some(a.name.clone()),
-        })
-        .collect();
-
-    let ctx = CodeGenContext::new(&file.module, &file.imports, local_names);
-
     // File header
     output.push_str(&format!(
         "// Generated by Frel compiler\n\
          // Module: {}\n\
          // DO NOT EDIT - This file is auto-generated\n\n",
-        file.module
+        module.path
     ));
 
     // Runtime imports
-    output.push_str("import { Runtime, Key, OneOf, Everything } from '@frel/runtime';\n\n");
+    let mut runtime_imports = vec!["Runtime", "Key", "OneOf", "Everything", "Builtins"];
+    if module
+        .declarations
+        .iter()
+        .any(|decl| matches!(decl, Decl::Scheme(_)))
+    {
+        runtime_imports.extend(["defineScheme", "SchemeTypes"]);
+    }
+    if module
+        .declarations
+        .iter()
+        .any(|decl| matches!(decl, Decl::Enum(_)))
+    {
+        runtime_imports.push("defineEnum");
+    }
+    output.push_str(&format!(
+        "import {{ {} }} from '@frel/runtime';\n",
+        runtime_imports.join(", ")
+    ));
+    output.push('\n');
 
     // Generate imports
-    for import in &file.imports {
+    for import in &module.imports {
         output.push_str(&generate_import(import));
     }
 
-    if !file.imports.is_empty() {
+    if !module.imports.is_empty() {
         output.push('\n');
     }
 
     // Collect all declarations for metadata generation
-    let mut blueprint_names = Vec::new();
+    let mut blueprints = Vec::new();
     let mut theme_names = Vec::new();
 
     // Generate declarations
-    for decl in &file.declarations {
+    for decl in &module.declarations {
         match decl {
-            TopLevelDecl::Blueprint(bp) => {
-                blueprint_names.push(bp.name.clone());
-                output.push_str(&generate_blueprint(bp, &ctx));
+            Decl::Blueprint(bp) => {
+                blueprints.push(bp.as_ref());
+                output.push_str(&generate_blueprint(bp, options));
             }
-            TopLevelDecl::Backend(backend) => {
+            Decl::Backend(backend) => {
                 output.push_str(&generate_backend(backend));
             }
-            TopLevelDecl::Contract(contract) => {
+            Decl::Contract(contract) => {
                 output.push_str(&generate_contract(contract));
             }
-            TopLevelDecl::Scheme(scheme) => {
-                output.push_str(&generate_scheme(scheme));
+            Decl::Scheme(scheme) => {
+                output.push_str(&generate_scheme(scheme, module));
             }
-            TopLevelDecl::Enum(enum_decl) => {
+            Decl::Enum(enum_decl) => {
                 output.push_str(&generate_enum(enum_decl));
             }
-            TopLevelDecl::Theme(theme) => {
+            Decl::Theme(theme) => {
                 theme_names.push(theme.name.clone());
                 output.push_str(&generate_theme(theme));
             }
-            TopLevelDecl::Arena(arena) => {
+            Decl::Arena(arena) => {
                 output.push_str(&generate_arena(arena));
             }
         }
@@ -150,15 +104,64 @@ pub fn generate_file(file: &File) -> String {
     }
 
     // Generate metadata registration
-    output.push_str(&generate_metadata_registration(
-        &file.module,
-        &blueprint_names,
-        &theme_names,
-    ));
+    output.push_str(&generate_metadata_registration(&blueprints, &theme_names));
 
     output
 }
 
+/// Selectors of a blueprint, for memoizing derived expressions
+///
+/// An expression is memoizable when it computes something from fields of
+/// the blueprint's unnamed `with` backends only. It is then generated as a
+/// selector (`runtime.select`) cached on the backend instance and recomputed
+/// only when one of its inputs changes, so the fragments sharing the
+/// instance evaluate it once.
+struct SelectorScope<'a> {
+    /// Qualified blueprint name, prefix of the selector names
+    prefix: &'a str,
+}
+
+impl<'a> SelectorScope<'a> {
+    fn new(blueprint: &'a Blueprint) -> Self {
+        Self {
+            prefix: &blueprint.qualified_name,
+        }
+    }
+
+    /// Inputs of `expr` if it is memoizable
+    ///
+    /// Plain field reads are not: there is nothing to compute.
+    fn memoizable(&self, expr: &Expr) -> Option<Vec<String>> {
+        if matches!(expr.kind, ExprKind::Read(_)) {
+            return None;
+        }
+        let mut reads = expr.reads().peekable();
+        let memoizable = reads.peek().is_some()
+            && reads.all(|r| matches!(r.kind, RefKind::BackendField { slot: None, .. }));
+        memoizable.then(|| expr.dependencies())
+    }
+
+    /// JavaScript for `expr`, read through the selector `name` when memoizable
+    fn generate(&self, expr: &Expr, datum_var: &str, name: &str) -> String {
+        let expr_js = generate_expr(expr, datum_var);
+        let Some(inputs) = self.memoizable(expr) else {
+            return expr_js;
+        };
+        format!(
+            "runtime.select({}, '{}${}', [{}], () => {})",
+            datum_var,
+            self.prefix,
+            name,
+            inputs
+                .iter()
+                .map(|input| format!("'{}'", input))
+                .collect::<Vec<_>>()
+                .join(", "),
+            expr_js
+        )
+    }
+}
+
 fn generate_import(import: &Import) -> String {
     if let Some((module, name)) = import.path.rsplit_once('.') {
         format!(
@@ -175,146 +178,225 @@ fn generate_import(import: &Import) -> String {
     }
 }
 
-fn generate_blueprint(blueprint: &Blueprint, ctx: &CodeGenContext) -> String {
+fn generate_blueprint(blueprint: &Blueprint, options: &GenerateOptions) -> String {
     let mut output = String::new();
     let name = &blueprint.name;
 
-    // Collect fields from local declarations
-    let fields: Vec<_> = blueprint
-        .body
-        .iter()
-        .filter_map(|stmt| match stmt {
-            BlueprintStmt::LocalDecl(decl) => Some(decl),
-            _ => None,
-        })
-        .collect();
-
-    // Collect all call sites (fragment creations, including nested ones)
-    let call_sites: Vec<_> = collect_fragment_creations(&blueprint.body);
-
-    // Collect top-level children (direct fragment creations, not inside when/repeat/select)
-    let top_children: Vec<usize> = collect_top_children(&blueprint.body, &call_sites);
+    // Expressions over backend fields only are read through cached selectors
+    let selectors = SelectorScope::new(blueprint);
 
     // Generate subscription callbacks for derived fields
-    for field in &fields {
-        if let Some(callback) = generate_field_callback(name, field) {
+    for field in &blueprint.fields {
+        if let Some(callback) = generate_field_callback(name, field, &selectors) {
+            output.push_str(&callback);
+            output.push('\n');
+        }
+    }
+
+    // Generate subscription callbacks for provided context values
+    for provide in &blueprint.provides {
+        if let Some(callback) = generate_provide_callback(name, provide, &selectors) {
             output.push_str(&callback);
             output.push('\n');
         }
     }
 
     // Generate call site callbacks and bindings
-    for (idx, call_site) in call_sites.iter().enumerate() {
-        output.push_str(&generate_call_site_callbacks(name, idx, call_site));
+    for (idx, call_site) in blueprint.call_sites.iter().enumerate() {
+        output.push_str(&generate_call_site_callbacks(name, idx, call_site, &selectors));
+    }
+
+    // Generate window options of virtualized repeat containers
+    for (idx, call_site) in blueprint.call_sites.iter().enumerate() {
+        output.push_str(&generate_virtualize(name, idx, call_site));
+    }
+
+    // Generate focus options of call sites with focus instructions
+    for (idx, call_site) in blueprint.call_sites.iter().enumerate() {
+        output.push_str(&generate_focus(name, idx, call_site));
     }
 
     // Generate internal binding function (if non-empty)
-    let has_internal_binding = has_internal_binding_content(&blueprint.params, &fields);
-    output.push_str(&generate_internal_binding(name, &blueprint.params, &fields));
+    let has_internal_binding = has_internal_binding_content(blueprint);
+    output.push_str(&generate_internal_binding(blueprint, &selectors));
 
     // Generate call site binding functions
-    for (idx, call_site) in call_sites.iter().enumerate() {
-        output.push_str(&generate_call_site_binding(name, idx, call_site));
+    for (idx, call_site) in blueprint.call_sites.iter().enumerate() {
+        output.push_str(&generate_call_site_binding(name, idx, call_site, &selectors));
+    }
+
+    // Generate error boundaries
+    for (idx, boundary) in blueprint.boundaries.iter().enumerate() {
+        output.push_str(&generate_boundary(name, idx, boundary));
+    }
+
+    // Generate loading placeholders
+    for (idx, loading) in blueprint.loading.iter().enumerate() {
+        output.push_str(&generate_loading(name, idx, loading));
     }
 
     // Generate metadata object
     output.push_str(&generate_blueprint_metadata(
-        name,
-        &call_sites,
-        &top_children,
+        blueprint,
         has_internal_binding,
-        ctx,
+        options,
     ));
 
     output
 }
 
-/// Extract a ContentExpr from a fragment body if present
-fn extract_content_expr(body: &Option<FragmentBody>) -> Option<&Expr> {
-    if let Some(FragmentBody::Default(stmts)) = body {
-        for stmt in stmts {
-            if let BlueprintStmt::ContentExpr(expr) = stmt {
-                return Some(expr);
-            }
-        }
+/// Function creating an error boundary's body in a closure of its own
+///
+/// A failure while the body is created renders the fallback instead; later
+/// failures in the body reach the boundary through `runtime.fail`.
+fn generate_boundary(blueprint_name: &str, idx: usize, boundary: &ErrorBoundary) -> String {
+    let create = |indices: &[usize], indent: &str| {
+        indices
+            .iter()
+            .map(|i| {
+                format!(
+                    "{}runtime.instantiate_call_site(closure_id, boundary_id, call_sites['{}']);\n",
+                    indent, i
+                )
+            })
+            .collect::<String>()
+    };
+
+    let mut output = format!(
+        "function {name}$boundary${idx}(runtime, closure_id) {{\n\
+         \x20\x20const call_sites = {name}$metadata.call_sites;\n\
+         \x20\x20const boundary_id = runtime.open_boundary(closure_id, (error) => {{\n",
+        name = blueprint_name,
+        idx = idx
+    );
+    if let Some(error) = &boundary.error {
+        output.push_str(&format!(
+            "\x20\x20\x20\x20runtime.set(closure_id, '{}', error);\n",
+            error
+        ));
     }
-    None
+    output.push_str(&create(&boundary.fallback, "\x20\x20\x20\x20"));
+    output.push_str("\x20\x20});\n\x20\x20try {\n");
+    output.push_str(&create(&boundary.body, "\x20\x20\x20\x20"));
+    output.push_str(
+        "\x20\x20} catch (error) {\n\
+         \x20\x20\x20\x20runtime.fail(boundary_id, error);\n\
+         \x20\x20}\n}\n\n",
+    );
+    output
 }
 
-fn collect_fragment_creations(stmts: &[BlueprintStmt]) -> Vec<&FragmentCreation> {
-    let mut result = Vec::new();
-    for stmt in stmts {
-        match stmt {
-            BlueprintStmt::FragmentCreation(fc) => {
-                result.push(fc);
-            }
-            BlueprintStmt::Control(ctrl) => match ctrl {
-                ControlStmt::When {
-                    then_stmt,
-                    else_stmt,
-                    ..
-                } => {
-                    if let BlueprintStmt::FragmentCreation(fc) = then_stmt.as_ref() {
-                        result.push(fc);
-                    }
-                    if let Some(else_s) = else_stmt {
-                        if let BlueprintStmt::FragmentCreation(fc) = else_s.as_ref() {
-                            result.push(fc);
-                        }
-                    }
-                }
-                ControlStmt::Repeat { body, .. } => {
-                    result.extend(collect_fragment_creations(body));
-                }
-                ControlStmt::Select {
-                    branches,
-                    else_branch,
-                    ..
-                } => {
-                    for branch in branches {
-                        if let BlueprintStmt::FragmentCreation(fc) = branch.body.as_ref() {
-                            result.push(fc);
-                        }
-                    }
-                    if let Some(else_b) = else_branch {
-                        if let BlueprintStmt::FragmentCreation(fc) = else_b.as_ref() {
-                            result.push(fc);
-                        }
-                    }
-                }
-            },
-            _ => {}
-        }
+/// Function opening a loading placeholder in a closure of its own
+///
+/// The runtime creates the body while any of the `.loading` flags of the
+/// commands is true, and destroys it once they are all false again.
+fn generate_loading(blueprint_name: &str, idx: usize, loading: &LoadingSlot) -> String {
+    let flags = loading
+        .commands
+        .iter()
+        .map(|command| format!("'{}.loading'", command))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut output = format!(
+        "function {name}$loading${idx}(runtime, closure_id) {{\n\
+         \x20\x20const call_sites = {name}$metadata.call_sites;\n\
+         \x20\x20runtime.open_loading(closure_id, [{flags}], (loading_id) => {{\n",
+        name = blueprint_name,
+        idx = idx,
+        flags = flags
+    );
+    for i in &loading.body {
+        output.push_str(&format!(
+            "\x20\x20\x20\x20runtime.instantiate_call_site(closure_id, loading_id, call_sites['{}']);\n",
+            i
+        ));
     }
-    result
+    output.push_str("\x20\x20});\n}\n\n");
+    output
 }
 
-/// Collect indices of top-level children (direct fragment creations at the body level).
-/// These are the children that should be instantiated immediately by the runtime.
-/// Children inside control statements (when/repeat/select) are not top-level.
-fn collect_top_children(stmts: &[BlueprintStmt], all_call_sites: &[&FragmentCreation]) -> Vec<usize> {
-    let mut result = Vec::new();
-    for stmt in stmts {
-        if let BlueprintStmt::FragmentCreation(fc) = stmt {
-            // Find this fragment creation's index in all_call_sites
-            if let Some(idx) = all_call_sites.iter().position(|cs| std::ptr::eq(*cs, fc)) {
-                result.push(idx);
-            }
-        }
-        // Control statements are not traversed - their children are not top-level
+/// Function returning the window options of a virtualized repeat container
+///
+/// The adapter calls it when laying out the container, so the options may
+/// depend on fields of the parent closure.
+fn generate_virtualize(blueprint_name: &str, idx: usize, call_site: &CallSite) -> String {
+    let Some(virtualize) = &call_site.virtualize else {
+        return String::new();
+    };
+    let options = virtualize
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, generate_expr(value, "parent_id")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "function {}${}$virtualize(runtime, parent_id) {{\n\
+         \x20\x20return {{ {} }};\n\
+         }}\n\n",
+        blueprint_name, idx, options
+    )
+}
+
+/// Function returning the focus options of a call site
+///
+/// The adapter turns them into the `tabindex` attribute, focuses the element
+/// when created and installs the keyboard handling of the focus group.
+fn generate_focus(blueprint_name: &str, idx: usize, call_site: &CallSite) -> String {
+    let focus = &call_site.focus;
+    if focus.is_empty() {
+        return String::new();
+    }
+    let mut options = Vec::new();
+    if let Some(tab_index) = &focus.tab_index {
+        options.push(format!("tab_index: {}", generate_expr(tab_index, "parent_id")));
+    }
+    if focus.autofocus {
+        options.push("autofocus: true".to_string());
+    }
+    if let Some(group) = focus.group {
+        options.push(format!("group: '{}'", group.as_str()));
+    }
+    format!(
+        "function {}${}$focus(runtime, parent_id) {{\n\
+         \x20\x20return {{ {} }};\n\
+         }}\n\n",
+        blueprint_name,
+        idx,
+        options.join(", ")
+    )
+}
+
+/// Parameter name an argument is bound to; `_` for positional arguments
+fn arg_name(arg: &Arg) -> &str {
+    arg.name.as_deref().unwrap_or("_")
+}
+
+/// Subscription selector for the names an expression depends on
+fn dependency_selector(deps: &[String]) -> String {
+    if deps.len() == 1 {
+        format!("Key('{}')", deps[0])
+    } else {
+        format!(
+            "OneOf({})",
+            deps.iter()
+                .map(|d| format!("'{}'", d))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
     }
-    result
 }
 
-fn generate_field_callback(blueprint_name: &str, field: &LocalDecl) -> Option<String> {
+fn generate_field_callback(
+    blueprint_name: &str,
+    field: &Field,
+    selectors: &SelectorScope,
+) -> Option<String> {
     // Generate callback for fields with dependencies
-    let deps = collect_expr_dependencies(&field.init);
-    if deps.is_empty() {
+    if field.init.dependencies().is_empty() {
         return None;
     }
 
     let callback_name = format!("{}${}$callback", blueprint_name, field.name);
-    let expr_js = generate_expr(&field.init, "closure_id");
+    let expr_js = selectors.generate(&field.init, "closure_id", &field.name);
 
     Some(format!(
         "function {callback_name}(runtime, subscription) {{\n\
@@ -327,17 +409,47 @@ fn generate_field_callback(blueprint_name: &str, field: &LocalDecl) -> Option<St
     ))
 }
 
+fn generate_provide_callback(
+    blueprint_name: &str,
+    provide: &Provide,
+    selectors: &SelectorScope,
+) -> Option<String> {
+    // Generate callback for values with dependencies
+    if provide.value.dependencies().is_empty() {
+        return None;
+    }
+
+    let selector = format!("provide${}", provide.name);
+    let expr_js = selectors.generate(&provide.value, "closure_id", &selector);
+
+    Some(format!(
+        "function {blueprint_name}$provide${name}$callback(runtime, subscription) {{\n\
+         \x20\x20const closure_id = subscription.source_id;\n\
+         \x20\x20runtime.provide(closure_id, '{name}', {expr_js});\n\
+         }}\n",
+        blueprint_name = blueprint_name,
+        name = provide.name,
+        expr_js = expr_js
+    ))
+}
+
 fn generate_call_site_callbacks(
     blueprint_name: &str,
     idx: usize,
-    call_site: &FragmentCreation,
+    call_site: &CallSite,
+    selectors: &SelectorScope,
 ) -> String {
     let mut output = String::new();
 
     for arg in &call_site.args {
-        let param_name = arg.name.as_ref().map(|s| s.as_str()).unwrap_or("_");
+        // Injected backends are bound once, see generate_call_site_binding
+        if arg.injected_backend.is_some() {
+            continue;
+        }
+        let param_name = arg_name(arg);
         let callback_name = format!("{}${}${}$callback", blueprint_name, idx, param_name);
-        let expr_js = generate_expr(&arg.value, "closure_id");
+        let selector = format!("{}${}", idx, param_name);
+        let expr_js = selectors.generate(&arg.value, "closure_id", &selector);
 
         output.push_str(&format!(
             "function {callback_name}(runtime, subscription) {{\n\
@@ -350,12 +462,12 @@ fn generate_call_site_callbacks(
         ));
     }
 
-    // Handle ContentExpr in body (e.g., text { "Hello" } or text { item.title })
-    if let Some(content_expr) = extract_content_expr(&call_site.body) {
-        let deps = collect_expr_dependencies(content_expr);
-        if !deps.is_empty() {
+    // Handle content expressions (e.g., text { "Hello" } or text { item.title })
+    if let Some(content_expr) = &call_site.content {
+        if !content_expr.dependencies().is_empty() {
             let callback_name = format!("{}${}$content$callback", blueprint_name, idx);
-            let expr_js = generate_expr(content_expr, "closure_id");
+            let selector = format!("{}$content", idx);
+            let expr_js = selectors.generate(content_expr, "closure_id", &selector);
 
             output.push_str(&format!(
                 "function {callback_name}(runtime, subscription) {{\n\
@@ -372,28 +484,60 @@ fn generate_call_site_callbacks(
 }
 
 /// Returns true if internal_binding would be non-empty
-fn has_internal_binding_content(params: &[Parameter], fields: &[&LocalDecl]) -> bool {
-    // Has content if any parameter has a default or any field exists
-    params.iter().any(|p| p.default.is_some()) || !fields.is_empty()
+fn has_internal_binding_content(blueprint: &Blueprint) -> bool {
+    // Has content if any parameter has a default, any field exists, a backend is owned,
+    // a shortcut is declared or a context value is provided or consumed
+    blueprint.params.iter().any(|p| p.default.is_some())
+        || !blueprint.fields.is_empty()
+        || !blueprint.backends.is_empty()
+        || !blueprint.shortcuts.is_empty()
+        || !blueprint.consumes.is_empty()
+        || !blueprint.provides.is_empty()
 }
 
-fn generate_internal_binding(
-    blueprint_name: &str,
-    params: &[Parameter],
-    fields: &[&LocalDecl],
-) -> String {
+fn generate_internal_binding(blueprint: &Blueprint, selectors: &SelectorScope) -> String {
     // Skip generating empty functions
-    if !has_internal_binding_content(params, fields) {
+    if !has_internal_binding_content(blueprint) {
         return String::new();
     }
 
     let mut output = String::new();
-    let fn_name = format!("{}$internal_binding", blueprint_name);
+    let fn_name = format!("{}$internal_binding", blueprint.name);
 
     output.push_str(&format!("function {}(runtime, closure_id) {{\n", fn_name));
 
+    // Create or reuse backend instances by lifetime; a parent may replace them by injection.
+    // Unnamed instances are kept in a local when a shortcut invokes their commands.
+    for (idx, backend) in blueprint.backends.iter().enumerate() {
+        let slot = backend
+            .slot
+            .as_ref()
+            .map_or("null".to_string(), |slot| format!("'{}'", slot));
+        let invoked = blueprint
+            .shortcuts
+            .iter()
+            .flat_map(|shortcut| &shortcut.actions)
+            .any(|action| {
+                matches!(action, Action::Command { receiver: Receiver::Owned(i), .. } if *i == idx)
+            });
+        let binding = if invoked {
+            format!("const backend${} = ", idx)
+        } else {
+            String::new()
+        };
+        output.push_str(&format!(
+            "\x20\x20{}runtime.use_backend(closure_id, {}, {});\n",
+            binding, slot, backend.backend
+        ));
+    }
+
+    // Read context values from the nearest provider above, following its changes
+    for name in &blueprint.consumes {
+        output.push_str(&format!("\x20\x20runtime.consume(closure_id, '{}');\n", name));
+    }
+
     // Initialize parameters with defaults if provided
-    for param in params {
+    for param in &blueprint.params {
         if let Some(default) = &param.default {
             let default_js = generate_expr(default, "closure_id");
             output.push_str(&format!(
@@ -407,9 +551,9 @@ fn generate_internal_binding(
     }
 
     // Initialize and subscribe derived fields
-    for field in fields {
-        let deps = collect_expr_dependencies(&field.init);
-        let init_js = generate_expr(&field.init, "closure_id");
+    for field in &blueprint.fields {
+        let deps = field.init.dependencies();
+        let init_js = selectors.generate(&field.init, "closure_id", &field.name);
 
         // Initialize field
         output.push_str(&format!(
@@ -419,33 +563,87 @@ fn generate_internal_binding(
 
         // Subscribe if there are dependencies
         if !deps.is_empty() {
-            let callback_name = format!("{}${}$callback", blueprint_name, field.name);
-            let selector = if deps.len() == 1 {
-                format!("Key('{}')", deps[0])
-            } else {
-                format!(
-                    "OneOf({})",
-                    deps.iter()
-                        .map(|d| format!("'{}'", d))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            };
+            let callback_name = format!("{}${}$callback", blueprint.name, field.name);
+            output.push_str(&format!(
+                "\x20\x20runtime.subscribe(closure_id, closure_id, {}, {});\n",
+                dependency_selector(&deps),
+                callback_name
+            ));
+        }
+    }
+
+    // Provide context values to the fragments below; children are created after this
+    for provide in &blueprint.provides {
+        let deps = provide.value.dependencies();
+        let selector = format!("provide${}", provide.name);
+        let value_js = selectors.generate(&provide.value, "closure_id", &selector);
+
+        output.push_str(&format!(
+            "\x20\x20runtime.provide(closure_id, '{}', {});\n",
+            provide.name, value_js
+        ));
+
+        // Subscribe if there are dependencies
+        if !deps.is_empty() {
+            let callback_name = format!("{}$provide${}$callback", blueprint.name, provide.name);
             output.push_str(&format!(
                 "\x20\x20runtime.subscribe(closure_id, closure_id, {}, {});\n",
-                selector, callback_name
+                dependency_selector(&deps),
+                callback_name
             ));
         }
     }
 
+    // Register keyboard shortcuts; the runtime drops them with the closure
+    for shortcut in &blueprint.shortcuts {
+        output.push_str(&format!(
+            "\x20\x20runtime.add_shortcut(closure_id, '{}', () => {{\n",
+            escape_string(&shortcut.chord)
+        ));
+        for action in &shortcut.actions {
+            output.push_str(&format!("\x20\x20\x20\x20{};\n", generate_action(action)));
+        }
+        output.push_str("\x20\x20});\n");
+    }
+
     output.push_str("}\n\n");
     output
 }
 
+/// Statement of a handler body, run with the handler's closure as `closure_id`
+fn generate_action(action: &Action) -> String {
+    match action {
+        Action::Assign { name, value } => format!(
+            "runtime.set(closure_id, '{}', {})",
+            name,
+            generate_expr(value, "closure_id")
+        ),
+        Action::Command {
+            receiver,
+            name,
+            args,
+        } => {
+            let receiver_js = match receiver {
+                Receiver::Field(field) => format!("runtime.get(closure_id, '{}')", field),
+                Receiver::Owned(idx) => format!("backend${}", idx),
+            };
+            let args_js: Vec<_> = args.iter().map(|e| generate_expr(e, "closure_id")).collect();
+            // A failed command is reported to the nearest error boundary
+            format!(
+                "runtime.guard(closure_id, {}.{}({}))",
+                receiver_js,
+                name,
+                args_js.join(", ")
+            )
+        }
+    }
+}
+
 fn generate_call_site_binding(
     blueprint_name: &str,
     idx: usize,
-    call_site: &FragmentCreation,
+    call_site: &CallSite,
+    selectors: &SelectorScope,
 ) -> String {
     let mut output = String::new();
     let fn_name = format!("{}${}$call_site_binding", blueprint_name, idx);
@@ -456,9 +654,21 @@ fn generate_call_site_binding(
     ));
 
     for arg in &call_site.args {
-        let param_name = arg.name.as_ref().map(|s| s.as_str()).unwrap_or("_");
-        let expr_js = generate_expr(&arg.value, "parent_id");
-        let deps = collect_expr_dependencies(&arg.value);
+        let param_name = arg_name(arg);
+
+        // Share the parent's backend instance instead of copying its state
+        if let Some(backend) = &arg.injected_backend {
+            output.push_str(&format!(
+                "\x20\x20runtime.inject_backend(child_id, '{}', {}, {}.fields);\n",
+                param_name,
+                generate_expr(&arg.value, "parent_id"),
+                backend
+            ));
+            continue;
+        }
+        let deps = arg.value.dependencies();
+        let selector = format!("{}${}", idx, param_name);
+        let expr_js = selectors.generate(&arg.value, "parent_id", &selector);
 
         // Initialize child parameter
         output.push_str(&format!(
@@ -469,28 +679,19 @@ fn generate_call_site_binding(
         // Subscribe if there are dependencies
         if !deps.is_empty() {
             let callback_name = format!("{}${}${}$callback", blueprint_name, idx, param_name);
-            let selector = if deps.len() == 1 {
-                format!("Key('{}')", deps[0])
-            } else {
-                format!(
-                    "OneOf({})",
-                    deps.iter()
-                        .map(|d| format!("'{}'", d))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            };
             output.push_str(&format!(
                 "\x20\x20runtime.subscribe(parent_id, child_id, {}, {});\n",
-                selector, callback_name
+                dependency_selector(&deps),
+                callback_name
             ));
         }
     }
 
-    // Handle ContentExpr in body (e.g., text { "Hello" } or text { item.title })
-    if let Some(content_expr) = extract_content_expr(&call_site.body) {
-        let expr_js = generate_expr(content_expr, "parent_id");
-        let deps = collect_expr_dependencies(content_expr);
+    // Handle content expressions (e.g., text { "Hello" } or text { item.title })
+    if let Some(content_expr) = &call_site.content {
+        let selector = format!("{}$content", idx);
+        let expr_js = selectors.generate(content_expr, "parent_id", &selector);
+        let deps = content_expr.dependencies();
 
         // Initialize the content parameter
         output.push_str(&format!(
@@ -501,36 +702,33 @@ fn generate_call_site_binding(
         // Subscribe if there are dependencies
         if !deps.is_empty() {
             let callback_name = format!("{}${}$content$callback", blueprint_name, idx);
-            let selector = if deps.len() == 1 {
-                format!("Key('{}')", deps[0])
-            } else {
-                format!(
-                    "OneOf({})",
-                    deps.iter()
-                        .map(|d| format!("'{}'", d))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            };
             output.push_str(&format!(
                 "\x20\x20runtime.subscribe(parent_id, child_id, {}, {});\n",
-                selector, callback_name
+                dependency_selector(&deps),
+                callback_name
             ));
         }
     }
 
+    // Mounted in an overlay container instead of the parent; unmounted when destroyed
+    if let Some(portal) = &call_site.portal {
+        output.push_str(&format!(
+            "\x20\x20runtime.mount_portal(child_id, {});\n",
+            generate_expr(portal, "parent_id")
+        ));
+    }
+
     output.push_str("}\n\n");
     output
 }
 
 fn generate_blueprint_metadata(
-    blueprint_name: &str,
-    call_sites: &[&FragmentCreation],
-    top_children: &[usize],
+    blueprint: &Blueprint,
     has_internal_binding: bool,
-    ctx: &CodeGenContext,
+    options: &GenerateOptions,
 ) -> String {
     let mut output = String::new();
+    let blueprint_name = &blueprint.name;
 
     output.push_str(&format!("export const {}$metadata = {{\n", blueprint_name));
 
@@ -543,22 +741,55 @@ fn generate_blueprint_metadata(
     }
 
     // Top children array (indices of call_sites to instantiate immediately)
-    let top_children_str = top_children
+    let top_children_str = blueprint
+        .top_children
         .iter()
         .map(|i| i.to_string())
         .collect::<Vec<_>>()
         .join(", ");
     output.push_str(&format!(
-        "\x20\x20top_children: [{}],\n\
-         \x20\x20call_sites: {{\n",
+        "\x20\x20top_children: [{}],\n",
         top_children_str
     ));
 
-    for (idx, call_site) in call_sites.iter().enumerate() {
-        let child_blueprint = ctx.resolve_name(&call_site.name);
+    // Error boundaries, created after the top children
+    if !blueprint.boundaries.is_empty() {
+        let boundaries = (0..blueprint.boundaries.len())
+            .map(|idx| format!("{}$boundary${}", blueprint_name, idx))
+            .collect::<Vec<_>>()
+            .join(", ");
+        output.push_str(&format!("\x20\x20boundaries: [{}],\n", boundaries));
+    }
+
+    // Loading placeholders, opened after the error boundaries
+    if !blueprint.loading.is_empty() {
+        let loading = (0..blueprint.loading.len())
+            .map(|idx| format!("{}$loading${}", blueprint_name, idx))
+            .collect::<Vec<_>>()
+            .join(", ");
+        output.push_str(&format!("\x20\x20loading: [{}],\n", loading));
+    }
+    output.push_str("\x20\x20call_sites: {\n");
+
+    for (idx, call_site) in blueprint.call_sites.iter().enumerate() {
+        let id = if options.fragment_ids {
+            format!(", id: '{}'", escape_string(&call_site.id))
+        } else {
+            String::new()
+        };
+        let virtualize = if call_site.virtualize.is_some() {
+            format!(", virtualize: {}${}$virtualize", blueprint_name, idx)
+        } else {
+            String::new()
+        };
+        let focus = if call_site.focus.is_empty() {
+            String::new()
+        } else {
+            format!(", focus: {}${}$focus", blueprint_name, idx)
+        };
         output.push_str(&format!(
-            "\x20\x20\x20\x20'{}': {{ blueprint: '{}', binding: {}${}$call_site_binding }},\n",
-            idx, child_blueprint, blueprint_name, idx
+            "\x20\x20\x20\x20'{}': {{ blueprint: '{}', binding: {}${}$call_site_binding{}{}{} }},\n",
+            idx, call_site.blueprint, blueprint_name, idx, id, virtualize, focus
         ));
     }
 
@@ -567,11 +798,7 @@ fn generate_blueprint_metadata(
     output
 }
 
-fn generate_metadata_registration(
-    module: &str,
-    blueprints: &[String],
-    themes: &[String],
-) -> String {
+fn generate_metadata_registration(blueprints: &[&Blueprint], themes: &[String]) -> String {
     let mut output = String::new();
 
     output.push_str("// Register metadata with runtime\n");
@@ -579,8 +806,8 @@ fn generate_metadata_registration(
 
     for bp in blueprints {
         output.push_str(&format!(
-            "\x20\x20runtime.register_metadata('{}.{}', {}$metadata);\n",
-            module, bp, bp
+            "\x20\x20runtime.register_metadata('{}', {}$metadata);\n",
+            bp.qualified_name, bp.name
         ));
     }
 
@@ -598,81 +825,294 @@ fn generate_backend(backend: &Backend) -> String {
     output.push_str(&format!("// Backend: {}\n", backend.name));
     output.push_str(&format!("export class {} {{\n", backend.name));
 
+    // Fields shared with blueprints the instance is injected into, with the
+    // in-flight flag of each command
+    let field_names = backend
+        .fields
+        .iter()
+        .map(|field| format!("'{}'", field.name))
+        .chain(backend.commands.iter().map(|cmd| format!("'{}.loading'", cmd.name)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    output.push_str(&format!("  static fields = [{}];\n", field_names));
+    output.push_str(&format!(
+        "  static lifetime = '{}';\n",
+        backend.lifetime.as_str()
+    ));
+    if backend.has_undo() {
+        let undoable = backend
+            .undoable_fields()
+            .map(|field| format!("'{}'", field.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        output.push_str(&format!("  static undoable = [{}];\n", undoable));
+    }
+    output.push('\n');
+
     // Constructor with fields
     output.push_str("  constructor(runtime, closure_id) {\n");
     output.push_str("    this.runtime = runtime;\n");
     output.push_str("    this.closure_id = closure_id;\n");
 
-    for member in &backend.members {
-        if let BackendMember::Field(field) = member {
-            if let Some(init) = &field.init {
-                let init_js = generate_expr(init, "closure_id");
-                output.push_str(&format!(
-                    "    runtime.set(closure_id, '{}', {});\n",
-                    field.name, init_js
-                ));
-            }
+    for field in &backend.fields {
+        if let Some(init) = &field.init {
+            let init_js = generate_expr(init, "closure_id");
+            output.push_str(&format!(
+                "    runtime.set(closure_id, '{}', {});\n",
+                field.name, init_js
+            ));
         }
     }
+    for cmd in &backend.commands {
+        output.push_str(&format!(
+            "    runtime.set(closure_id, '{}.loading', false);\n",
+            cmd.name
+        ));
+    }
+
+    // Initial values are not undoable: record changes from here on
+    if backend.has_undo() {
+        output.push_str(&format!(
+            "    runtime.track_undo(closure_id, {}.undoable);\n",
+            backend.name
+        ));
+    }
 
     output.push_str("  }\n\n");
 
     // Generate getters/setters for fields
-    for member in &backend.members {
-        if let BackendMember::Field(field) = member {
-            output.push_str(&format!(
-                "  get {}() {{ return this.runtime.get(this.closure_id, '{}'); }}\n",
-                field.name, field.name
-            ));
-            output.push_str(&format!(
-                "  set {}(value) {{ this.runtime.set(this.closure_id, '{}', value); }}\n\n",
-                field.name, field.name
-            ));
-        }
+    for field in &backend.fields {
+        output.push_str(&format!(
+            "  get {}() {{ return this.runtime.get(this.closure_id, '{}'); }}\n",
+            field.name, field.name
+        ));
+        output.push_str(&format!(
+            "  set {}(value) {{ this.runtime.set(this.closure_id, '{}', value); }}\n\n",
+            field.name, field.name
+        ));
     }
 
     // Generate command stubs
-    for member in &backend.members {
-        if let BackendMember::Command(cmd) = member {
-            let params = cmd
-                .params
-                .iter()
-                .map(|p| p.name.clone())
-                .collect::<Vec<_>>()
-                .join(", ");
-            output.push_str(&format!(
-                "  async {}({}) {{\n\
+    for cmd in &backend.commands {
+        let params = cmd
+            .params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        // The runtime applies the policy and tracks the `.loading` flag; the
+        // host implements `name$run`
+        let policy = cmd
+            .policy
+            .map_or("null".to_string(), |policy| format!("'{}'", policy.as_str()));
+        let run_args = if params.is_empty() {
+            "signal".to_string()
+        } else {
+            format!("{}, signal", params)
+        };
+        output.push_str(&format!(
+            "  async {name}({params}) {{\n\
+             \x20\x20\x20\x20return this.runtime.run_command(this.closure_id, '{name}', {policy}, (signal) => this.{name}$run({run_args}));\n\
+             \x20\x20}}\n\n\
+             \x20\x20async {name}$run({run_args}) {{\n\
+             \x20\x20\x20\x20// TODO: Implement in host language\n\
+             \x20\x20}}\n\n",
+            name = cmd.name,
+            params = params,
+            policy = policy,
+            run_args = run_args,
+        ));
+    }
+
+    // Methods with a body compute their result from the instance's fields;
+    // the host implements the others
+    for method in &backend.methods {
+        let params = method
+            .params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &method.body {
+            Some(body) => {
+                let body_js = generate_method_body(backend, method, body);
+                output.push_str(&format!(
+                    "  {}({}) {{\n\
+                     \x20\x20\x20\x20const runtime = this.runtime;\n\
+                     \x20\x20\x20\x20return {};\n\
+                     \x20\x20}}\n\n",
+                    method.name, params, body_js
+                ));
+            }
+            None => output.push_str(&format!(
+                "  {}({}) {{\n\
                  \x20\x20\x20\x20// TODO: Implement in host language\n\
                  \x20\x20}}\n\n",
-                cmd.name, params
-            ));
+                method.name, params
+            )),
         }
     }
 
+    // Generated undo history commands
+    if backend.has_undo() {
+        output.push_str("  undo() { this.runtime.undo(this.closure_id); }\n");
+        output.push_str("  redo() { this.runtime.redo(this.closure_id); }\n");
+    }
+
     output.push_str("}\n");
     output
 }
 
-fn generate_contract(_contract: &Contract) -> String {
-    String::from("// Contract: bound at runtime\n")
+/// The expression of a method body, inside the backend's class
+///
+/// The method's parameters are JavaScript parameters and the backend's
+/// methods are called on the instance; other names are fields of its closure.
+fn generate_method_body(backend: &Backend, method: &BackendMethod, body: &Expr) -> String {
+    generate_expr_with(body, &|r| {
+        let is_param = r.kind == RefKind::Param && method.params.iter().any(|p| p.name == r.name);
+        if is_param {
+            r.name.clone()
+        } else if backend.methods.iter().any(|m| m.name == r.name) {
+            format!("this.{}", r.name)
+        } else {
+            format!("runtime.get(this.closure_id, '{}')", r.name)
+        }
+    })
 }
 
-fn generate_scheme(scheme: &Scheme) -> String {
+/// Client class of a contract
+///
+/// Methods pass their arguments to the transport the client is created with
+/// (`FetchTransport`, `WebSocketTransport` or a host implementation), along
+/// with the method's remote name and HTTP route from `static methods`.
+/// Parameter and result types are emitted as JSDoc for TypeScript checking.
+fn generate_contract(contract: &Contract) -> String {
     let mut output = String::new();
 
-    output.push_str(&format!("// Scheme: {}\n", scheme.name));
-    output.push_str(&format!("export const {}$fields = [\n", scheme.name));
+    output.push_str(&format!("// Contract: {}\n", contract.name));
+    output.push_str(&format!("export class {} {{\n", contract.name));
+    output.push_str(&format!(
+        "  static contract = '{}';\n",
+        escape_string(&contract.qualified_name)
+    ));
+    output.push_str("  static methods = {\n");
+    for method in &contract.methods {
+        let params = method
+            .params
+            .iter()
+            .map(|p| format!("'{}'", p.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        output.push_str(&format!(
+            "    {}: {{ name: '{}', params: [{}], http: {{ method: '{}', path: '{}' }} }},\n",
+            method.name,
+            escape_string(&method.remote_name),
+            params,
+            method.route.method,
+            escape_string(&method.route.path)
+        ));
+    }
+    output.push_str("  };\n\n");
 
-    for member in &scheme.members {
-        if let SchemeMember::Field(field) = member {
-            output.push_str(&format!("  '{}',\n", field.name));
+    output.push_str("  /** @param {import('@frel/runtime').ContractTransport} transport */\n");
+    output.push_str("  constructor(transport) {\n");
+    output.push_str("    this.transport = transport;\n");
+    output.push_str("  }\n");
+
+    for method in &contract.methods {
+        output.push_str("\n  /**\n");
+        for param in &method.params {
+            output.push_str(&format!(
+                "   * @param {{{}}} {}\n",
+                js_doc_type(&param.ty),
+                param.name
+            ));
         }
+        output.push_str(&format!(
+            "   * @returns {{Promise<{}>}}\n",
+            js_doc_type(&method.result)
+        ));
+        output.push_str("   */\n");
+
+        let params = method
+            .params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let args = if params.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", params)
+        };
+        output.push_str(&format!(
+            "  {name}({params}) {{\n\
+             \x20\x20\x20\x20return this.transport.call({class}.contract, {class}.methods.{name}, {args});\n\
+             \x20\x20}}\n",
+            name = method.name,
+            params = params,
+            class = contract.name,
+            args = args,
+        ));
     }
 
-    output.push_str("];\n\n");
+    output.push_str("}\n");
+    output
+}
 
-    // Factory function
-    output.push_str(&format!(
+/// JSDoc type of the JSON value a contract sends or receives for a type
+fn js_doc_type(ty: &Type) -> String {
+    match ty {
+        Type::Unit => "void".to_string(),
+        Type::Bool => "boolean".to_string(),
+        Type::I8
+        | Type::I16
+        | Type::I32
+        | Type::I64
+        | Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::F32
+        | Type::F64
+        | Type::Color
+        | Type::Enum(_) => "number".to_string(),
+        Type::Decimal
+        | Type::String
+        | Type::Secret
+        | Type::Uuid
+        | Type::Url
+        | Type::Graphics
+        | Type::Instant
+        | Type::LocalDate
+        | Type::LocalTime
+        | Type::LocalDateTime
+        | Type::Timezone
+        | Type::Duration => "string".to_string(),
+        Type::Scheme(_) => "Object".to_string(),
+        Type::Nullable(inner) => format!("({} | null)", js_doc_type(inner)),
+        Type::List(inner) | Type::Set(inner) => {
+            format!("Array<{}>", js_doc_type(inner))
+        }
+        Type::Map(_, value) => format!("Object<string, {}>", js_doc_type(value)),
+        _ => "*".to_string(),
+    }
+}
+
+fn generate_scheme(scheme: &Scheme, module: &Module) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("// Scheme: {}\n", scheme.name));
+    output.push_str(&format!("export const {}$fields = [\n", scheme.name));
+
+    for field in &scheme.fields {
+        output.push_str(&format!("  '{}',\n", field.name));
+    }
+
+    output.push_str("];\n\n");
+
+    // Factory function
+    output.push_str(&format!(
         "export function create{}(runtime, owner, data) {{\n\
          \x20\x20const id = runtime.create_datum('{}', data, owner);\n\
          \x20\x20return id;\n\
@@ -680,22 +1120,185 @@ fn generate_scheme(scheme: &Scheme) -> String {
         scheme.name, scheme.name
     ));
 
+    // Type of decoded values, for TypeScript checking
+    output.push_str(&format!(
+        "\n/**\n * @typedef {{Object}} {}$data\n",
+        scheme.name
+    ));
+    for field in &scheme.fields {
+        output.push_str(&format!(
+            " * @property {{{}}} {}\n",
+            host_doc_type(&field.ty, module),
+            field.name
+        ));
+    }
+    output.push_str(" */\n\n");
+
+    // Codec: fromJson, toJson, is and validate
+    output.push_str(&format!(
+        "/** @type {{import('@frel/runtime').SchemeCodec<{}$data>}} */\n",
+        scheme.name
+    ));
+    output.push_str(&format!(
+        "export const {} = defineScheme('{}', [\n",
+        scheme.name,
+        escape_string(&scheme.qualified_name)
+    ));
+    for field in &scheme.fields {
+        let rules = field
+            .rules
+            .iter()
+            .map(|rule| {
+                let arg = match rule {
+                    FieldRule::MinLen(n)
+                    | FieldRule::MaxLen(n)
+                    | FieldRule::Precision(n)
+                    | FieldRule::MinItems(n)
+                    | FieldRule::MaxItems(n) => n.to_string(),
+                    FieldRule::Min(n) | FieldRule::Max(n) => n.to_string(),
+                    FieldRule::NotBlank => "false".to_string(),
+                    FieldRule::Pattern(pattern) => format!("'{}'", escape_string(pattern)),
+                };
+                format!("['{}', {}]", rule.code(), arg)
+            })
+            .collect::<Vec<_>>();
+        let rules = if rules.is_empty() {
+            String::new()
+        } else {
+            format!(", rules: [{}]", rules.join(", "))
+        };
+        output.push_str(&format!(
+            "  {{ name: '{}', type: {}{} }},\n",
+            field.name,
+            scheme_field_type(&field.ty, module),
+            rules
+        ));
+    }
+    output.push_str("]);\n");
+
     output
 }
 
+/// Name a declaration is in scope by in the generated module
+fn local_type_name(module: &Module, id: SymbolId) -> Option<&str> {
+    let name = module.type_name(id)?;
+    Some(name.rsplit_once('.').map_or(name, |(_, name)| name))
+}
+
+/// Field type of a scheme codec, from `SchemeTypes` of the runtime
+///
+/// Enums and nested schemes are looked up lazily, so a scheme can refer to
+/// declarations later in the module.
+fn scheme_field_type(ty: &Type, module: &Module) -> String {
+    let int = |min: &str, max: &str| format!("SchemeTypes.int({}, {})", min, max);
+    match ty {
+        Type::Bool => "SchemeTypes.bool".to_string(),
+        Type::I8 => int("-128", "127"),
+        Type::I16 => int("-32768", "32767"),
+        Type::I32 => int("-2147483648", "2147483647"),
+        Type::I64 => int("Number.MIN_SAFE_INTEGER", "Number.MAX_SAFE_INTEGER"),
+        Type::U8 => int("0", "255"),
+        Type::U16 => int("0", "65535"),
+        Type::U32 | Type::Color => int("0", "4294967295"),
+        Type::U64 => int("0", "Number.MAX_SAFE_INTEGER"),
+        Type::F32 | Type::F64 | Type::Duration => "SchemeTypes.float".to_string(),
+        Type::Decimal => "SchemeTypes.decimal".to_string(),
+        Type::String | Type::Secret | Type::Graphics | Type::Blob | Type::Timezone => {
+            "SchemeTypes.string".to_string()
+        }
+        Type::Uuid => "SchemeTypes.uuid".to_string(),
+        Type::Url => "SchemeTypes.url".to_string(),
+        Type::Instant => "SchemeTypes.instant".to_string(),
+        Type::LocalDate => "SchemeTypes.local_date".to_string(),
+        Type::LocalTime => "SchemeTypes.local_time".to_string(),
+        Type::LocalDateTime => "SchemeTypes.local_date_time".to_string(),
+        Type::Enum(id) => match local_type_name(module, *id) {
+            Some(name) => format!("SchemeTypes.enumeration('{}', () => {})", name, name),
+            None => "SchemeTypes.any".to_string(),
+        },
+        Type::Scheme(id) => match local_type_name(module, *id) {
+            Some(name) => format!("SchemeTypes.scheme(() => {})", name),
+            None => "SchemeTypes.any".to_string(),
+        },
+        Type::Nullable(inner) => {
+            format!("SchemeTypes.nullable({})", scheme_field_type(inner, module))
+        }
+        Type::List(inner) => format!("SchemeTypes.list({})", scheme_field_type(inner, module)),
+        Type::Set(inner) => format!("SchemeTypes.set({})", scheme_field_type(inner, module)),
+        Type::Map(_, value) => format!("SchemeTypes.map({})", scheme_field_type(value, module)),
+        _ => "SchemeTypes.any".to_string(),
+    }
+}
+
+/// JSDoc type of a decoded scheme field value
+fn host_doc_type(ty: &Type, module: &Module) -> String {
+    match ty {
+        Type::Instant => "Date".to_string(),
+        Type::Blob | Type::Graphics | Type::Timezone => "string".to_string(),
+        Type::Scheme(id) => match module.type_name(*id) {
+            Some(name) => match name.rsplit_once('.') {
+                Some((path, name)) if path == module.path => format!("{}$data", name),
+                Some((path, name)) => {
+                    format!("import('@frel/{}').{}$data", path.replace('.', "/"), name)
+                }
+                None => format!("{}$data", name),
+            },
+            None => "Object".to_string(),
+        },
+        Type::Nullable(inner) => format!("({} | null)", host_doc_type(inner, module)),
+        Type::List(inner) => format!("Array<{}>", host_doc_type(inner, module)),
+        Type::Set(inner) => format!("Set<{}>", host_doc_type(inner, module)),
+        Type::Map(_, value) => format!("Object<string, {}>", host_doc_type(value, module)),
+        _ => js_doc_type(ty),
+    }
+}
+
 fn generate_enum(enum_decl: &Enum) -> String {
-    let mut output = String::new();
+    let quoted = |name: &str| format!("'{}'", escape_string(name));
+    let names: Vec<_> = enum_decl.variants.iter().map(|v| quoted(&v.name)).collect();
+    let labels: Vec<_> = enum_decl
+        .variants
+        .iter()
+        .filter_map(|v| Some(format!("{}: {}", v.name, quoted(v.label.as_deref()?))))
+        .collect();
+    // Variants carrying data are constructors of tagged values
+    let data_variants: Vec<_> = enum_decl.variants.iter().filter(|v| v.arity > 0).collect();
 
+    let variant_type = if names.is_empty() {
+        "never".to_string()
+    } else {
+        names.join(" | ")
+    };
+    let enum_type = if data_variants.is_empty() {
+        variant_type
+    } else {
+        let data_names: Vec<_> = data_variants.iter().map(|v| quoted(&v.name)).collect();
+        format!("{}, {}", variant_type, data_names.join(" | "))
+    };
+
+    let mut output = format!(
+        "/** @type {{import('@frel/runtime').EnumType<{}>}} */\n",
+        enum_type
+    );
     output.push_str(&format!(
-        "export const {} = Object.freeze({{\n",
-        enum_decl.name
+        "export const {} = defineEnum('{}', [{}]",
+        enum_decl.name,
+        enum_decl.qualified_name,
+        names.join(", ")
     ));
-
-    for (i, variant) in enum_decl.variants.iter().enumerate() {
-        output.push_str(&format!("  {}: {},\n", variant, i));
+    if !labels.is_empty() {
+        output.push_str(&format!(", {{\n  {},\n}}", labels.join(",\n  ")));
+    } else if !data_variants.is_empty() {
+        output.push_str(", {}");
     }
-
-    output.push_str("});\n");
+    if !data_variants.is_empty() {
+        let arities: Vec<_> = data_variants
+            .iter()
+            .map(|v| format!("{}: {}", v.name, v.arity))
+            .collect();
+        output.push_str(&format!(", {{ {} }}", arities.join(", ")));
+    }
+    output.push_str(");\n");
     output
 }
 
@@ -704,25 +1307,13 @@ fn generate_theme(theme: &Theme) -> String {
 
     output.push_str(&format!("// Theme: {}\n", theme.name));
 
-    // Collect fields and variants
-    let mut fields = Vec::new();
-    let mut variants = Vec::new();
-
-    for member in &theme.members {
-        match member {
-            ThemeMember::Field(field) => fields.push(field),
-            ThemeMember::Variant(variant) => variants.push(variant),
-            _ => {}
-        }
-    }
-
     // Theme initializer
     output.push_str(&format!("function {}$init(runtime) {{\n", theme.name));
 
     // Base theme
     output.push_str("  // Base theme\n");
     output.push_str(&format!("  runtime.create_datum('{}', {{\n", theme.name));
-    for field in &fields {
+    for field in &theme.fields {
         if !field.is_asset {
             if let Some(init) = &field.init {
                 // Theme values are typically literals, datum_var unused
@@ -734,7 +1325,7 @@ fn generate_theme(theme: &Theme) -> String {
     output.push_str("  });\n\n");
 
     // Variants
-    for variant in &variants {
+    for variant in &theme.variants {
         output.push_str(&format!("  // Variant: {}\n", variant.name));
         output.push_str(&format!(
             "  runtime.create_datum('{}${}', {{\n",
@@ -742,7 +1333,7 @@ fn generate_theme(theme: &Theme) -> String {
         ));
 
         // Start with base values
-        for field in &fields {
+        for field in &theme.fields {
             if !field.is_asset {
                 if let Some(init) = &field.init {
                     // Theme values are typically literals, datum_var unused
@@ -773,9 +1364,9 @@ fn generate_arena(arena: &Arena) -> String {
          \x20\x20scheme: '{}',\n\
          \x20\x20contract: {},\n\
          }};\n",
-        arena.scheme_name,
+        arena.scheme,
         arena.name,
-        arena.scheme_name,
+        arena.scheme,
         arena
             .contract
             .as_ref()
@@ -789,32 +1380,43 @@ fn generate_arena(arena: &Arena) -> String {
 // ============================================================================
 
 fn generate_expr(expr: &Expr, datum_var: &str) -> String {
-    match expr {
-        Expr::Null => "null".to_string(),
-        Expr::Bool(b) => b.to_string(),
-        Expr::Int(i) => i.to_string(),
-        Expr::Float(f) => f.to_string(),
-        Expr::Color(c) => format!("0x{:08X}", c),
-        Expr::String(s) => format!("'{}'", escape_string(s)),
-        Expr::StringTemplate(elements) => generate_template(elements, datum_var),
-        Expr::List(items) => {
-            let items_js: Vec<_> = items.iter().map(|e| generate_expr(e, datum_var)).collect();
+    generate_expr_with(expr, &|r| {
+        format!("runtime.get({}, '{}')", datum_var, r.name)
+    })
+}
+
+/// JavaScript for an expression, with `read` giving the code reading a name
+fn generate_expr_with(expr: &Expr, read: &dyn Fn(&Ref) -> String) -> String {
+    match &expr.kind {
+        ExprKind::Null => "null".to_string(),
+        ExprKind::Bool(b) => b.to_string(),
+        // Folded negative numbers are parenthesized like negations: `(-1) ** n`
+        ExprKind::Int(i) if *i < 0 => format!("({})", i),
+        ExprKind::Int(i) => i.to_string(),
+        ExprKind::Float(f) if *f < 0.0 => format!("({})", f),
+        ExprKind::Float(f) => f.to_string(),
+        ExprKind::Color(c) => format!("0x{:08X}", c),
+        ExprKind::String(s) => format!("'{}'", escape_string(s)),
+        ExprKind::Template(parts) => generate_template(parts, read),
+        ExprKind::List(items) => {
+            let items_js: Vec<_> = items.iter().map(|e| generate_expr_with(e, read)).collect();
             format!("[{}]", items_js.join(", "))
         }
-        Expr::Object(fields) => {
+        ExprKind::Object(fields) => {
             let fields_js: Vec<_> = fields
                 .iter()
-                .map(|(k, v)| format!("{}: {}", k, generate_expr(v, datum_var)))
+                .map(|(k, v)| format!("{}: {}", k, generate_expr_with(v, read)))
                 .collect();
             format!("{{ {} }}", fields_js.join(", "))
         }
-        Expr::Identifier(name) => {
-            format!("runtime.get({}, '{}')", datum_var, name)
-        }
-        Expr::QualifiedName(parts) => parts.join("."),
-        Expr::Binary { op, left, right } => {
-            let left_js = generate_expr(left, datum_var);
-            let right_js = generate_expr(right, datum_var);
+        // Lambda parameters are parameters of the arrow function
+        ExprKind::Read(r) if r.kind == RefKind::LambdaParam => r.name.clone(),
+        // Parameters, fields, loop items and `with` backend fields all live in the closure
+        ExprKind::Read(r) => read(r),
+        ExprKind::Path(parts) => parts.join("."),
+        ExprKind::Binary { op, left, right } => {
+            let left_js = generate_expr_with(left, read);
+            let right_js = generate_expr_with(right, read);
             let op_js = match op {
                 BinaryOp::Add => "+",
                 BinaryOp::Sub => "-",
@@ -834,8 +1436,8 @@ fn generate_expr(expr: &Expr, datum_var: &str) -> String {
             };
             format!("({} {} {})", left_js, op_js, right_js)
         }
-        Expr::Unary { op, expr } => {
-            let expr_js = generate_expr(expr, datum_var);
+        ExprKind::Unary { op, expr } => {
+            let expr_js = generate_expr_with(expr, read);
             let op_js = match op {
                 UnaryOp::Not => "!",
                 UnaryOp::Neg => "-",
@@ -843,44 +1445,137 @@ fn generate_expr(expr: &Expr, datum_var: &str) -> String {
             };
             format!("({}{})", op_js, expr_js)
         }
-        Expr::Ternary {
+        ExprKind::Ternary {
             condition,
             then_expr,
             else_expr,
         } => {
-            let cond_js = generate_expr(condition, datum_var);
-            let then_js = generate_expr(then_expr, datum_var);
-            let else_js = generate_expr(else_expr, datum_var);
+            let cond_js = generate_expr_with(condition, read);
+            let then_js = generate_expr_with(then_expr, read);
+            let else_js = generate_expr_with(else_expr, read);
             format!("({} ? {} : {})", cond_js, then_js, else_js)
         }
-        Expr::FieldAccess { base, field } => {
-            let base_js = generate_expr(base, datum_var);
-            // If base is an identifier, we need to get the datum first
-            if matches!(base.as_ref(), Expr::Identifier(_)) {
+        ExprKind::Field {
+            base,
+            field,
+            optional: true,
+        } => {
+            let base_js = generate_expr_with(base, read);
+            format!("{}?.{}", base_js, field)
+        }
+        ExprKind::Field { base, field, .. } => {
+            let base_js = generate_expr_with(base, read);
+            // If base is a name, we need to get the datum first
+            if matches!(base.kind, ExprKind::Read(_)) {
                 format!("runtime.get({}, '{}')", base_js, field)
             } else {
                 format!("{}.{}", base_js, field)
             }
         }
-        Expr::OptionalChain { base, field } => {
-            let base_js = generate_expr(base, datum_var);
-            format!("{}?.{}", base_js, field)
-        }
-        Expr::Call { callee, args } => {
-            let callee_js = generate_expr(callee, datum_var);
-            let args_js: Vec<_> = args.iter().map(|e| generate_expr(e, datum_var)).collect();
+        ExprKind::Call { callee, args } => {
+            let callee_js = generate_expr_with(callee, read);
+            let args_js: Vec<_> = args.iter().map(|e| generate_expr_with(e, read)).collect();
             format!("{}({})", callee_js, args_js.join(", "))
         }
+        ExprKind::BuiltinCall { name, args } => {
+            let args_js: Vec<_> = args.iter().map(|e| generate_expr_with(e, read)).collect();
+            format!("Builtins.{}({})", name, args_js.join(", "))
+        }
+        ExprKind::Member {
+            base,
+            name,
+            args,
+            optional,
+        } => {
+            let base_js = generate_expr_with(base, read);
+            let args_js: Vec<_> = args.iter().flatten().map(|e| generate_expr_with(e, read)).collect();
+            let receiver = match &base.ty {
+                Type::Nullable(inner) if *optional => inner,
+                ty => ty,
+            };
+            match members::Receiver::of(receiver) {
+                // `?.` skips the member of null, and the arguments with it
+                Some(receiver) if *optional => format!(
+                    "(($v) => $v == null ? null : {})({})",
+                    generate_member(receiver, name, "$v", &args_js),
+                    base_js
+                ),
+                Some(receiver) => generate_member(receiver, name, &base_js, &args_js),
+                None => format!("{}{}.{}", base_js, if *optional { "?" } else { "" }, name),
+            }
+        }
+        // A range is iterated as the array of its integers
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => format!(
+            "(($s, $e) => Array.from({{ length: Math.max(0, $e - $s{}) }}, (_, $i) => $s + $i))({}, {})",
+            if *inclusive { " + 1" } else { "" },
+            generate_expr_with(start, read),
+            generate_expr_with(end, read)
+        ),
+        ExprKind::Lambda { params, body } => {
+            let body_js = generate_expr_with(body, read);
+            // An object body would be read as a block
+            let body_js = match body.kind {
+                ExprKind::Object(_) => format!("({})", body_js),
+                _ => body_js,
+            };
+            format!("(({}) => {})", params.join(", "), body_js)
+        }
     }
 }
 
-fn generate_template(elements: &[TemplateElement], datum_var: &str) -> String {
-    let parts: Vec<String> = elements
+/// JavaScript for the member `name` of `this`, a value of a built-in type
+///
+/// Strings and lists are JavaScript strings and arrays, a Set is a `Set`, a
+/// Map an object, an Instant a `Date` and a Duration a number of milliseconds.
+fn generate_member(receiver: members::Receiver, name: &str, this: &str, args: &[String]) -> String {
+    use members::Receiver;
+
+    let arg = args.first().map(String::as_str).unwrap_or("undefined");
+    match (receiver, name) {
+        (Receiver::Set, "length") => format!("{}.size", this),
+        (Receiver::Map, "length") => format!("Object.keys({}).length", this),
+        (_, "length") => format!("{}.length", this),
+        (Receiver::Set, "isEmpty") => format!("({}.size === 0)", this),
+        (Receiver::Map, "isEmpty") => format!("(Object.keys({}).length === 0)", this),
+        (_, "isEmpty") => format!("({}.length === 0)", this),
+        (_, "toUpper") => format!("{}.toUpperCase()", this),
+        (_, "toLower") => format!("{}.toLowerCase()", this),
+        (Receiver::Set, "contains") => format!("{}.has({})", this, arg),
+        (_, "contains") => format!("{}.includes({})", this, arg),
+        (_, "trim" | "startsWith" | "endsWith" | "indexOf") => {
+            format!("{}.{}({})", this, name, args.join(", "))
+        }
+        (_, "first") => format!("({}[0] ?? null)", this),
+        (_, "last") => format!("({}.at(-1) ?? null)", this),
+        (_, "containsKey") => format!("Object.hasOwn({}, {})", this, arg),
+        (_, "get") => format!("(Object.hasOwn({0}, {1}) ? {0}[{1}] : null)", this, arg),
+        (_, "keys") => format!("Object.keys({})", this),
+        (_, "values") => format!("Object.values({})", this),
+        (_, "abs" | "round" | "floor" | "ceil") => format!("Math.{}({})", name, this),
+        (_, "toString") => format!("String({})", this),
+        (_, "epochMillis") => format!("{}.getTime()", this),
+        (_, "plus") => format!("new Date({}.getTime() + {})", this, arg),
+        (_, "minus") => format!("new Date({}.getTime() - {})", this, arg),
+        (_, "until") => format!("({}.getTime() - {}.getTime())", arg, this),
+        (_, "millis") => this.to_string(),
+        (_, "seconds") => format!("({} / 1000)", this),
+        (_, "minutes") => format!("({} / 60000)", this),
+        (_, "hours") => format!("({} / 3600000)", this),
+        _ => format!("{}.{}", this, name),
+    }
+}
+
+fn generate_template(parts: &[TemplatePart], read: &dyn Fn(&Ref) -> String) -> String {
+    let parts: Vec<String> = parts
         .iter()
-        .map(|el| match el {
-            TemplateElement::Text(s) => format!("'{}'", escape_string(s)),
-            TemplateElement::Interpolation(expr) => {
-                format!("String({})", generate_expr(expr, datum_var))
+        .map(|part| match part {
+            TemplatePart::Text(s) => format!("'{}'", escape_string(s)),
+            TemplatePart::Interpolation(expr) => {
+                format!("String({})", generate_expr_with(expr, read))
             }
         })
         .collect();
@@ -900,75 +1595,6 @@ fn escape_string(s: &str) -> String {
         .replace('\t', "\\t")
 }
 
-fn collect_expr_dependencies(expr: &Expr) -> Vec<String> {
-    let mut deps = Vec::new();
-    collect_deps_recursive(expr, &mut deps);
-    deps.sort();
-    deps.dedup();
-    deps
-}
-
-fn collect_deps_recursive(expr: &Expr, deps: &mut Vec<String>) {
-    match expr {
-        Expr::Identifier(name) => {
-            deps.push(name.clone());
-        }
-        Expr::Binary { left, right, .. } => {
-            collect_deps_recursive(left, deps);
-            collect_deps_recursive(right, deps);
-        }
-        Expr::Unary { expr, .. } => {
-            collect_deps_recursive(expr, deps);
-        }
-        Expr::Ternary {
-            condition,
-            then_expr,
-            else_expr,
-        } => {
-            collect_deps_recursive(condition, deps);
-            collect_deps_recursive(then_expr, deps);
-            collect_deps_recursive(else_expr, deps);
-        }
-        Expr::FieldAccess { base, .. } => {
-            collect_deps_recursive(base, deps);
-        }
-        Expr::OptionalChain { base, .. } => {
-            collect_deps_recursive(base, deps);
-        }
-        Expr::Call { callee, args } => {
-            collect_deps_recursive(callee, deps);
-            for arg in args {
-                collect_deps_recursive(arg, deps);
-            }
-        }
-        Expr::List(items) => {
-            for item in items {
-                collect_deps_recursive(item, deps);
-            }
-        }
-        Expr::Object(fields) => {
-            for (_, v) in fields {
-                collect_deps_recursive(v, deps);
-            }
-        }
-        Expr::StringTemplate(elements) => {
-            for el in elements {
-                if let TemplateElement::Interpolation(e) = el {
-                    collect_deps_recursive(e, deps);
-                }
-            }
-        }
-        // Literals have no dependencies
-        Expr::Null
-        | Expr::Bool(_)
-        | Expr::Int(_)
-        | Expr::Float(_)
-        | Expr::Color(_)
-        | Expr::String(_)
-        | Expr::QualifiedName(_) => {}
-    }
-}
-
 // ============================================================================
 // Tests
 // ============================================================================
@@ -976,48 +1602,31 @@ fn collect_deps_recursive(expr: &Expr, deps: &mut Vec<String>) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use frel_compiler_core::source::Span;
 
-    fn empty_span() -> Span {
-        Span { start: 0, end: 0 }
+    fn lower_source(source: &str) -> Module {
+        frel_compiler_core::ir::lower(&frel_compiler_core::parse_file(source).file.unwrap())
     }
 
-    fn test_ctx(module: &str) -> CodeGenContext<'_> {
-        CodeGenContext::new(module, &[], vec![])
+    fn generate_source(source: &str) -> String {
+        generate_module(&lower_source(source))
     }
 
-    fn test_ctx_with_imports<'a>(module: &'a str, imports: &[Import]) -> CodeGenContext<'a> {
-        CodeGenContext::new(module, imports, vec![])
-    }
-
-    fn test_ctx_with_locals<'a>(
-        module: &'a str,
-        imports: &[Import],
-        local_names: Vec<String>,
-    ) -> CodeGenContext<'a> {
-        CodeGenContext::new(module, imports, local_names)
+    /// Initializer of the field `value` of a blueprint with the given parameters
+    fn lower_expr(params: &str, expr: &str) -> Expr {
+        let source = format!(
+            "module test\n\nblueprint Main({}) {{\n    value : String = {}\n}}\n",
+            params, expr
+        );
+        let module = lower_source(&source);
+        let main = module.blueprints().next().unwrap();
+        main.fields[0].init.clone()
     }
 
     #[test]
     fn test_generate_simple_blueprint() {
-        let blueprint = Blueprint {
-            name: "Counter".to_string(),
-            params: vec![Parameter {
-                name: "initial".to_string(),
-                type_expr: TypeExpr::Named("u32".to_string()),
-                default: Some(Expr::Int(0)),
-            }],
-            body: vec![BlueprintStmt::LocalDecl(LocalDecl {
-                name: "count".to_string(),
-                type_expr: TypeExpr::Named("u32".to_string()),
-                init: Expr::Identifier("initial".to_string()),
-                span: empty_span(),
-            })],
-            span: empty_span(),
-        };
-
-        let ctx = test_ctx("myapp");
-        let output = generate_blueprint(&blueprint, &ctx);
+        let output = generate_source(
+            "module myapp\n\nblueprint Counter(initial : u32 = 0) {\n    count : u32 = initial\n}\n",
+        );
 
         // Should generate callback for count (depends on initial)
         assert!(output.contains("Counter$count$callback"));
@@ -1031,32 +1640,9 @@ mod tests {
 
     #[test]
     fn test_generate_derived_field() {
-        let blueprint = Blueprint {
-            name: "Doubler".to_string(),
-            params: vec![],
-            body: vec![
-                BlueprintStmt::LocalDecl(LocalDecl {
-                    name: "value".to_string(),
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Expr::Int(10),
-                    span: empty_span(),
-                }),
-                BlueprintStmt::LocalDecl(LocalDecl {
-                    name: "doubled".to_string(),
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Expr::Binary {
-                        op: BinaryOp::Mul,
-                        left: Box::new(Expr::Identifier("value".to_string())),
-                        right: Box::new(Expr::Int(2)),
-                    },
-                    span: empty_span(),
-                }),
-            ],
-            span: empty_span(),
-        };
-
-        let ctx = test_ctx("myapp");
-        let output = generate_blueprint(&blueprint, &ctx);
+        let output = generate_source(
+            "module myapp\n\nblueprint Doubler {\n    value : u32 = 10\n    doubled : u32 = value * 2\n}\n",
+        );
 
         // Should generate callback for doubled (depends on value)
         assert!(output.contains("Doubler$doubled$callback"));
@@ -1068,31 +1654,17 @@ mod tests {
 
     #[test]
     fn test_generate_call_site() {
-        let blueprint = Blueprint {
-            name: "Parent".to_string(),
-            params: vec![],
-            body: vec![
-                BlueprintStmt::LocalDecl(LocalDecl {
-                    name: "message".to_string(),
-                    type_expr: TypeExpr::Named("String".to_string()),
-                    init: Expr::String("Hello".to_string()),
-                    span: empty_span(),
-                }),
-                BlueprintStmt::FragmentCreation(FragmentCreation {
-                    name: "Child".to_string(),
-                    args: vec![Arg {
-                        name: Some("text".to_string()),
-                        value: Expr::Identifier("message".to_string()),
-                    }],
-                    body: None,
-                    postfix: vec![],
-                }),
-            ],
-            span: empty_span(),
-        };
+        let output = generate_source(
+            r#"module myapp
+
+blueprint Child(text : String) { }
 
-        let ctx = test_ctx("myapp");
-        let output = generate_blueprint(&blueprint, &ctx);
+blueprint Parent {
+    message : String = "Hello"
+    Child(text = message)
+}
+"#,
+        );
 
         // Should generate call site binding
         assert!(output.contains("Parent$0$call_site_binding"));
@@ -1106,27 +1678,10 @@ mod tests {
 
     #[test]
     fn test_generate_call_site_with_import() {
-        // Test that imported blueprints get correct qualified names
-        let imports = vec![Import {
-            path: "test.common.text".to_string(),
-            import_all: false,
-            span: empty_span(),
-        }];
-
-        let blueprint = Blueprint {
-            name: "simple_text".to_string(),
-            params: vec![],
-            body: vec![BlueprintStmt::FragmentCreation(FragmentCreation {
-                name: "text".to_string(),
-                args: vec![],
-                body: None,
-                postfix: vec![],
-            })],
-            span: empty_span(),
-        };
-
-        let ctx = test_ctx_with_imports("blueprint.simple_text", &imports);
-        let output = generate_blueprint(&blueprint, &ctx);
+        // Imported blueprints get their module's qualified name
+        let output = generate_source(
+            "module blueprint.simple_text\n\nimport test.common.text\n\nblueprint simple_text {\n    text { }\n}\n",
+        );
 
         // Metadata should reference imported blueprint with correct qualified name
         assert!(output.contains("'0': { blueprint: 'test.common.text'"));
@@ -1136,58 +1691,24 @@ mod tests {
 
     #[test]
     fn test_generate_call_site_with_wildcard_import() {
-        // Test that wildcard imports resolve correctly
-        let imports = vec![Import {
-            path: "test.common".to_string(),
-            import_all: true,
-            span: empty_span(),
-        }];
-
-        let blueprint = Blueprint {
-            name: "Hello".to_string(),
-            params: vec![],
-            body: vec![BlueprintStmt::FragmentCreation(FragmentCreation {
-                name: "text".to_string(),
-                args: vec![],
-                body: None,
-                postfix: vec![],
-            })],
-            span: empty_span(),
-        };
-
-        // "Hello" is a local name, "text" comes from wildcard import
-        let ctx = test_ctx_with_locals(
-            "blueprint.simple_text",
-            &imports,
-            vec!["Hello".to_string()],
+        // "Hello" is a local name, "text" comes from the wildcard import
+        let output = generate_source(
+            "module blueprint.simple_text\n\nimport test.common.*\n\nblueprint Hello {\n    text { }\n}\n",
         );
-        let output = generate_blueprint(&blueprint, &ctx);
 
         // Metadata should reference blueprint from wildcard import module
         assert!(output.contains("'0': { blueprint: 'test.common.text'"));
+        assert!(output.contains("runtime.register_metadata('blueprint.simple_text.Hello'"));
         // Should NOT contain the current module prefix for the imported blueprint
         assert!(!output.contains("blueprint.simple_text.text"));
     }
 
     #[test]
     fn test_generate_content_expr_static() {
-        // Test that static ContentExpr (e.g., text { "Hello" }) generates correct code
-        let blueprint = Blueprint {
-            name: "Hello".to_string(),
-            params: vec![],
-            body: vec![BlueprintStmt::FragmentCreation(FragmentCreation {
-                name: "text".to_string(),
-                args: vec![],
-                body: Some(FragmentBody::Default(vec![BlueprintStmt::ContentExpr(
-                    Expr::String("Hello, World!".to_string()),
-                )])),
-                postfix: vec![],
-            })],
-            span: empty_span(),
-        };
-
-        let ctx = test_ctx("myapp");
-        let output = generate_blueprint(&blueprint, &ctx);
+        // Static content (e.g., text { "Hello" }) is set once
+        let output = generate_source(
+            "module myapp\n\nblueprint Hello {\n    text { \"Hello, World!\" }\n}\n",
+        );
 
         // Should generate call site binding that sets content
         assert!(output.contains("Hello$0$call_site_binding"));
@@ -1198,31 +1719,10 @@ mod tests {
 
     #[test]
     fn test_generate_content_expr_reactive() {
-        // Test that reactive ContentExpr (e.g., text { count }) generates callback
-        let blueprint = Blueprint {
-            name: "Counter".to_string(),
-            params: vec![],
-            body: vec![
-                BlueprintStmt::LocalDecl(LocalDecl {
-                    name: "count".to_string(),
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Expr::Int(0),
-                    span: empty_span(),
-                }),
-                BlueprintStmt::FragmentCreation(FragmentCreation {
-                    name: "text".to_string(),
-                    args: vec![],
-                    body: Some(FragmentBody::Default(vec![BlueprintStmt::ContentExpr(
-                        Expr::Identifier("count".to_string()),
-                    )])),
-                    postfix: vec![],
-                }),
-            ],
-            span: empty_span(),
-        };
-
-        let ctx = test_ctx("myapp");
-        let output = generate_blueprint(&blueprint, &ctx);
+        // Reactive content (e.g., text { count }) gets a callback
+        let output = generate_source(
+            "module myapp\n\nblueprint Counter {\n    count : u32 = 0\n    text { count }\n}\n",
+        );
 
         // Should generate callback for reactive content
         assert!(output.contains("Counter$0$content$callback"));
@@ -1236,46 +1736,25 @@ mod tests {
 
     #[test]
     fn test_generate_enum() {
-        let enum_decl = Enum {
-            name: "Status".to_string(),
-            variants: vec![
-                "pending".to_string(),
-                "active".to_string(),
-                "completed".to_string(),
-            ],
-            span: empty_span(),
-        };
-
-        let output = generate_enum(&enum_decl);
+        let output = generate_source(
+            "module myapp\n\nenum Status { pending .. label { \"Waiting\" } active completed }\n",
+        );
 
-        assert!(output.contains("export const Status = Object.freeze({"));
-        assert!(output.contains("pending: 0,"));
-        assert!(output.contains("active: 1,"));
-        assert!(output.contains("completed: 2,"));
+        assert!(output.contains(
+            "import { Runtime, Key, OneOf, Everything, Builtins, defineEnum } from '@frel/runtime';"
+        ));
+        assert!(output.contains(
+            "/** @type {import('@frel/runtime').EnumType<'pending' | 'active' | 'completed'>} */"
+        ));
+        assert!(output.contains(
+            "export const Status = defineEnum('myapp.Status', ['pending', 'active', 'completed'], {\n  pending: 'Waiting',\n});"
+        ));
     }
 
     #[test]
     fn test_generate_scheme() {
-        let scheme = Scheme {
-            name: "User".to_string(),
-            members: vec![
-                SchemeMember::Field(SchemeField {
-                    name: "id".to_string(),
-                    type_expr: TypeExpr::Named("UUID".to_string()),
-                    instructions: vec![],
-                    span: empty_span(),
-                }),
-                SchemeMember::Field(SchemeField {
-                    name: "name".to_string(),
-                    type_expr: TypeExpr::Named("String".to_string()),
-                    instructions: vec![],
-                    span: empty_span(),
-                }),
-            ],
-            span: empty_span(),
-        };
-
-        let output = generate_scheme(&scheme);
+        let output =
+            generate_source("module myapp\n\nscheme User {\n    id : UUID\n    name : String\n}\n");
 
         assert!(output.contains("User$fields"));
         assert!(output.contains("'id',"));
@@ -1285,27 +1764,36 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_backend() {
-        let backend = Backend {
-            name: "CounterBackend".to_string(),
-            params: vec![],
-            members: vec![
-                BackendMember::Field(Field {
-                    name: "count".to_string(),
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Some(Expr::Int(0)),
-                    span: empty_span(),
-                }),
-                BackendMember::Command(Command {
-                    name: "increment".to_string(),
-                    params: vec![],
-                    span: empty_span(),
-                }),
-            ],
-            span: empty_span(),
-        };
+    fn test_generate_contract_client() {
+        let output = generate_source(
+            "module myapp\n\ncontract Users {\n    get_user(id : u32) : String? .. get { \"/users/{id}\" }\n    save(names : List<String>) .. name { \"saveAll\" }\n}\n",
+        );
 
-        let output = generate_backend(&backend);
+        assert!(output.contains("export class Users {"));
+        assert!(output.contains("static contract = 'myapp.Users';"));
+        assert!(output.contains(
+            "get_user: { name: 'get_user', params: ['id'], http: { method: 'GET', path: '/users/{id}' } },"
+        ));
+        // Unbound methods are posted to their remote name
+        assert!(output.contains(
+            "save: { name: 'saveAll', params: ['names'], http: { method: 'POST', path: '/saveAll' } },"
+        ));
+        assert!(output.contains("constructor(transport) {"));
+        assert!(output.contains(
+            "return this.transport.call(Users.contract, Users.methods.get_user, { id });"
+        ));
+        // Types for TypeScript checking
+        assert!(
+            output.contains("   * @param {number} id\n   * @returns {Promise<(string | null)>}")
+        );
+        assert!(output.contains("   * @param {Array<string>} names\n   * @returns {Promise<void>}"));
+    }
+
+    #[test]
+    fn test_generate_backend() {
+        let output = generate_source(
+            "module myapp\n\nbackend CounterBackend {\n    count : u32 = 0\n    command increment()\n}\n",
+        );
 
         assert!(output.contains("export class CounterBackend"));
         // Constructor should initialize field
@@ -1318,26 +1806,46 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_theme_with_variant() {
-        let theme = Theme {
-            name: "AppTheme".to_string(),
-            members: vec![
-                ThemeMember::Field(ThemeField {
-                    name: "padding".to_string(),
-                    is_asset: false,
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Some(Expr::Int(16)),
-                    span: empty_span(),
-                }),
-                ThemeMember::Variant(ThemeVariant {
-                    name: "Compact".to_string(),
-                    overrides: vec![("padding".to_string(), Expr::Int(8))],
-                }),
-            ],
-            span: empty_span(),
-        };
+    fn test_generate_command_policy() {
+        let source = r#"module app
+
+backend SearchBackend {
+    results : List<String> = []
+    latest command search(query: String)
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains(
+            "return this.runtime.run_command(this.closure_id, 'search', 'latest', (signal) => this.search$run(query, signal));"
+        ));
+        assert!(output.contains("async search$run(query, signal) {"));
+    }
+
+    #[test]
+    fn test_generate_undoable_fields() {
+        let source = r#"module app
 
-        let output = generate_theme(&theme);
+backend Form {
+    undoable name : String = ""
+    note : String = ""
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains("static undoable = ['name'];"));
+        assert!(output.contains(
+            "    runtime.set(closure_id, 'note', '');\n    runtime.track_undo(closure_id, Form.undoable);\n  }"
+        ));
+        assert!(output.contains("undo() { this.runtime.undo(this.closure_id); }"));
+        assert!(output.contains("redo() { this.runtime.redo(this.closure_id); }"));
+    }
+
+    #[test]
+    fn test_generate_theme_with_variant() {
+        let output = generate_source(
+            "module myapp\n\ntheme AppTheme {\n    padding : u32 = 16\n    variant Compact {\n        padding = 8\n    }\n}\n",
+        );
 
         // Should generate init function
         assert!(output.contains("AppTheme$init(runtime)"));
@@ -1351,11 +1859,7 @@ mod tests {
 
     #[test]
     fn test_generate_expr_binary() {
-        let expr = Expr::Binary {
-            op: BinaryOp::Add,
-            left: Box::new(Expr::Identifier("a".to_string())),
-            right: Box::new(Expr::Identifier("b".to_string())),
-        };
+        let expr = lower_expr("a : String, b : String", "a + b");
 
         let output = generate_expr(&expr, "closure_id");
 
@@ -1367,24 +1871,16 @@ mod tests {
 
     #[test]
     fn test_generate_expr_ternary() {
-        let expr = Expr::Ternary {
-            condition: Box::new(Expr::Identifier("flag".to_string())),
-            then_expr: Box::new(Expr::Int(1)),
-            else_expr: Box::new(Expr::Int(0)),
-        };
+        let expr = lower_expr("flag : bool", "flag ? \"1\" : \"0\"");
 
         let output = generate_expr(&expr, "closure_id");
 
-        assert_eq!(output, "(runtime.get(closure_id, 'flag') ? 1 : 0)");
+        assert_eq!(output, "(runtime.get(closure_id, 'flag') ? '1' : '0')");
     }
 
     #[test]
     fn test_generate_expr_string_template() {
-        let expr = Expr::StringTemplate(vec![
-            TemplateElement::Text("Hello, ".to_string()),
-            TemplateElement::Interpolation(Box::new(Expr::Identifier("name".to_string()))),
-            TemplateElement::Text("!".to_string()),
-        ]);
+        let expr = lower_expr("name : String", "\"Hello, ${name}!\"");
 
         let output = generate_expr(&expr, "closure_id");
 
@@ -1394,33 +1890,64 @@ mod tests {
     }
 
     #[test]
-    fn test_collect_dependencies() {
-        let expr = Expr::Binary {
-            op: BinaryOp::Add,
-            left: Box::new(Expr::Binary {
-                op: BinaryOp::Mul,
-                left: Box::new(Expr::Identifier("a".to_string())),
-                right: Box::new(Expr::Identifier("b".to_string())),
-            }),
-            right: Box::new(Expr::Identifier("c".to_string())),
-        };
+    fn test_generate_expr_builtin_call() {
+        let expr = lower_expr(
+            "start : Instant, zone : String",
+            "format(start, \"HH:mm\", zone)",
+        );
 
-        let deps = collect_expr_dependencies(&expr);
+        let output = generate_expr(&expr, "closure_id");
 
-        assert_eq!(deps, vec!["a", "b", "c"]);
+        assert_eq!(
+            output,
+            "Builtins.format(runtime.get(closure_id, 'start'), 'HH:mm', runtime.get(closure_id, 'zone'))"
+        );
+        assert_eq!(expr.dependencies(), vec!["start", "zone"]);
     }
 
     #[test]
-    fn test_collect_dependencies_deduplicates() {
-        let expr = Expr::Binary {
-            op: BinaryOp::Add,
-            left: Box::new(Expr::Identifier("x".to_string())),
-            right: Box::new(Expr::Identifier("x".to_string())),
-        };
+    fn test_generate_expr_member() {
+        let member = |params: &str, expr: &str| generate_expr(&lower_expr(params, expr), "c");
 
-        let deps = collect_expr_dependencies(&expr);
+        assert_eq!(
+            member("name : String", "name.length.toString()"),
+            "String(runtime.get(c, 'name').length)"
+        );
+        assert_eq!(
+            member("name : String", "name.trim().toUpper()"),
+            "runtime.get(c, 'name').trim().toUpperCase()"
+        );
+        assert_eq!(
+            member("tags : Set<String>, tag : String", "tags.contains(tag) ? \"y\" : \"n\""),
+            "(runtime.get(c, 'tags').has(runtime.get(c, 'tag')) ? 'y' : 'n')"
+        );
+        assert_eq!(
+            member("name : String?", "name?.toLower() ?: \"\""),
+            "((($v) => $v == null ? null : $v.toLowerCase())(runtime.get(c, 'name')) ?? '')"
+        );
+        assert_eq!(
+            member("d : Duration", "d.hours.toString()"),
+            "String((runtime.get(c, 'd') / 3600000))"
+        );
+    }
 
-        assert_eq!(deps, vec!["x"]);
+    #[test]
+    fn test_generate_expr_range() {
+        assert_eq!(
+            generate_expr(&lower_expr("count : i32", "0..count"), "c"),
+            "(($s, $e) => Array.from({ length: Math.max(0, $e - $s) }, (_, $i) => $s + $i))(0, runtime.get(c, 'count'))"
+        );
+        assert_eq!(
+            generate_expr(&lower_expr("n : i32", "1..=n"), "c"),
+            "(($s, $e) => Array.from({ length: Math.max(0, $e - $s + 1) }, (_, $i) => $s + $i))(1, runtime.get(c, 'n'))"
+        );
+    }
+
+    #[test]
+    fn test_expr_dependencies() {
+        let expr = lower_expr("a : i32, b : i32, c : i32", "a * b + c + a");
+
+        assert_eq!(expr.dependencies(), vec!["a", "b", "c"]);
     }
 
     #[test]
@@ -1432,30 +1959,15 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_file_complete() {
-        let file = File {
-            module: "myapp.counter".to_string(),
-            source_path: None,
-            imports: vec![],
-            declarations: vec![TopLevelDecl::Blueprint(Blueprint {
-                name: "Counter".to_string(),
-                params: vec![],
-                body: vec![BlueprintStmt::LocalDecl(LocalDecl {
-                    name: "count".to_string(),
-                    type_expr: TypeExpr::Named("u32".to_string()),
-                    init: Expr::Int(0),
-                    span: empty_span(),
-                })],
-                span: empty_span(),
-            })],
-        };
-
-        let output = generate_file(&file);
+    fn test_generate_module_complete() {
+        let output =
+            generate_source("module myapp.counter\n\nblueprint Counter {\n    count : u32 = 0\n}\n");
 
         // Header
         assert!(output.contains("// Module: myapp.counter"));
         // Runtime import
-        assert!(output.contains("import { Runtime, Key, OneOf, Everything } from '@frel/runtime'"));
+        assert!(output
+            .contains("import { Runtime, Key, OneOf, Everything, Builtins } from '@frel/runtime'"));
         // Blueprint generated
         assert!(output.contains("Counter$internal_binding"));
         assert!(output.contains("Counter$metadata"));
@@ -1463,4 +1975,477 @@ mod tests {
         assert!(output.contains("registerMetadata(runtime)"));
         assert!(output.contains("runtime.register_metadata('myapp.counter.Counter', Counter$metadata)"));
     }
+
+    #[test]
+    fn test_fragment_ids() {
+        let source = r#"module app
+
+blueprint Main {
+    flag : bool = true
+    items : List<String> = []
+    text { "title" }
+    text { "subtitle" }
+    when flag text { "on" } else text { "off" }
+    repeat on items { item ->
+        Row()
+    }
+}
+"#;
+        let module = lower_source(source);
+        let main = module.blueprints().next().unwrap();
+
+        let ids: Vec<&str> = main.call_sites.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "app.Main/text",
+                "app.Main/text#2",
+                "app.Main/when/text",
+                "app.Main/else/text",
+                "app.Main/repeat/Row",
+            ]
+        );
+
+        let output = generate_module(&module);
+        assert!(output.contains("Main$1$call_site_binding, id: 'app.Main/text#2' }"));
+
+        let options = GenerateOptions {
+            fragment_ids: false,
+            ..GenerateOptions::default()
+        };
+        let output = generate_module_with_options(&module, &options);
+        assert!(!output.contains("id: '"));
+    }
+
+    #[test]
+    fn test_backend_injection() {
+        let source = r#"module app
+
+backend CounterBackend {
+    count : u32 = 0
+}
+
+blueprint CounterLabel {
+    with counter : CounterBackend
+    text { "count" }
+}
+
+blueprint Main {
+    with shared : CounterBackend
+    CounterLabel(counter = shared)
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains("static fields = ['count'];"));
+        assert!(output.contains("runtime.use_backend(closure_id, 'counter', CounterBackend);"));
+        assert!(output.contains(
+            "runtime.inject_backend(child_id, 'counter', runtime.get(parent_id, 'shared'), CounterBackend.fields);"
+        ));
+        // Injected instances are not re-bound when the parent field changes
+        assert!(!output.contains("Main$0$counter$callback"));
+    }
+
+    #[test]
+    fn test_backend_lifetime() {
+        let source = r#"module app
+
+singleton backend Session {
+    user : String = ""
+}
+
+blueprint Header(session : Session) {
+    with session
+    text { user }
+}
+
+blueprint Main {
+    with Session
+    text { user }
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains("static lifetime = 'singleton';"));
+        assert!(output.contains("runtime.use_backend(closure_id, null, Session);"));
+        // Backend-typed parameters are passed by the parent
+        assert!(!output.contains("function Header$internal_binding"));
+    }
+
+    #[test]
+    fn test_generate_shortcuts() {
+        let source = r#"module app
+
+backend Editor {
+    text : String = ""
+    command save()
+}
+
+blueprint Main {
+    with Editor
+    shortcut "shift+ctrl+s" { save() }
+    shortcut "Escape" { text = "" }
+    shortcut "Ctrl+Banana" { save() }
+}
+
+blueprint Panel {
+    with document : Editor
+    shortcut "Ctrl+S" { save() }
+}
+"#;
+        let module = lower_source(source);
+        let main = module.blueprints().next().unwrap();
+        assert_eq!(
+            main.shortcuts
+                .iter()
+                .map(|shortcut| shortcut.chord.as_str())
+                .collect::<Vec<_>>(),
+            ["Ctrl+Shift+S", "Escape"]
+        );
+
+        let output = generate_module(&module);
+        assert!(output.contains("const backend$0 = runtime.use_backend(closure_id, null, Editor);"));
+        assert!(output.contains(
+            "  runtime.add_shortcut(closure_id, 'Ctrl+Shift+S', () => {\n    runtime.guard(closure_id, backend$0.save());\n  });\n"
+        ));
+        assert!(output.contains(
+            "  runtime.add_shortcut(closure_id, 'Escape', () => {\n    runtime.set(closure_id, 'text', '');\n  });\n"
+        ));
+        // Named instances are read from their slot when the shortcut runs
+        assert!(output.contains("  runtime.use_backend(closure_id, 'document', Editor);\n"));
+        assert!(output.contains("    runtime.guard(closure_id, runtime.get(closure_id, 'document').save());\n"));
+    }
+
+    #[test]
+    fn test_generate_virtualize() {
+        let source = r#"module app
+
+backend Catalog {
+    items : List<String> = []
+    rows : u32 = 40
+}
+
+blueprint Main {
+    with Catalog
+    column {
+        repeat on items { item ->
+            text { item }
+        }
+    } .. virtualize { item_height: rows overscan: 5 }
+    column {
+        .. virtualize { estimate: 32 }
+        repeat on items { item ->
+            text { item }
+        }
+    }
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains(
+            "function Main$0$virtualize(runtime, parent_id) {\n  return { item_height: runtime.get(parent_id, 'rows'), overscan: 5 };\n}"
+        ));
+        // Written inside the container's body
+        assert!(output.contains(
+            "function Main$1$virtualize(runtime, parent_id) {\n  return { estimate: 32 };\n}"
+        ));
+        assert!(output.contains(
+            "binding: Main$0$call_site_binding, id: 'app.Main/column', virtualize: Main$0$virtualize }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_focus() {
+        let source = r#"module app
+
+backend Form {
+    order : i32 = 2
+}
+
+blueprint Dialog {
+    with Form
+    column {
+        text { "name" }
+    } .. focus_trap
+    text { "name" } .. autofocus .. tab_index { order }
+    text { "cancel" } .. tab_index { -1 }
+    row {
+        .. focus_group { arrows }
+        text { "bold" }
+    }
+    text { "help" }
+    column { text { "left" } } .. focus_group { roving }
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains(
+            "function Dialog$0$focus(runtime, parent_id) {\n  return { group: 'trap' };\n}"
+        ));
+        assert!(output.contains(
+            "function Dialog$1$focus(runtime, parent_id) {\n  return { tab_index: runtime.get(parent_id, 'order'), autofocus: true };\n}"
+        ));
+        assert!(output.contains("return { tab_index: (-1) };"));
+        // Written inside the fragment's body, and as the `roving` alias
+        assert_eq!(output.matches("return { group: 'arrows' };").count(), 2);
+        assert!(output.contains(
+            "binding: Dialog$1$call_site_binding, id: 'app.Dialog/text', focus: Dialog$1$focus }"
+        ));
+        assert!(!output.contains("Dialog$4$focus"));
+    }
+
+    #[test]
+    fn test_generate_portal() {
+        let source = r#"module app
+
+blueprint Page(layer : String) {
+    column { text { "modal" } } .. portal
+    text { "tip" } .. portal { layer }
+    text { "inline" }
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains(
+            "function Page$0$call_site_binding(runtime, parent_id, child_id) {\n\
+             \x20\x20runtime.mount_portal(child_id, 'overlay');\n}"
+        ));
+        assert!(output.contains("  runtime.mount_portal(child_id, runtime.get(parent_id, 'layer'));\n}"));
+        assert_eq!(output.matches("runtime.mount_portal").count(), 2);
+    }
+
+    #[test]
+    fn test_generate_context() {
+        let source = r#"module app
+
+blueprint App {
+    provide accent = "teal"
+    Section()
+}
+
+blueprint Section {
+    consume depth: i32
+    provide depth = depth + 1
+    Badge()
+}
+
+blueprint Badge {
+    consume accent: String
+    consume depth: i32
+    text { "${accent} ${depth}" }
+}
+"#;
+        let output = generate_source(source);
+
+        assert!(output.contains(
+            "function App$internal_binding(runtime, closure_id) {\n  runtime.provide(closure_id, 'accent', 'teal');\n}"
+        ));
+        assert!(output.contains(
+            "function Section$provide$depth$callback(runtime, subscription) {\n  const closure_id = subscription.source_id;\n  runtime.provide(closure_id, 'depth', (runtime.get(closure_id, 'depth') + 1));\n}"
+        ));
+        // The consumed value is read before providing the next one
+        assert!(output.contains(
+            "  runtime.consume(closure_id, 'depth');\n  runtime.provide(closure_id, 'depth', (runtime.get(closure_id, 'depth') + 1));\n  runtime.subscribe(closure_id, closure_id, Key('depth'), Section$provide$depth$callback);\n"
+        ));
+        assert!(output.contains(
+            "function Badge$internal_binding(runtime, closure_id) {\n  runtime.consume(closure_id, 'accent');\n  runtime.consume(closure_id, 'depth');\n}"
+        ));
+    }
+
+    #[test]
+    fn test_generate_error_boundary() {
+        let source = r#"module app
+
+blueprint Profile(name : String) {
+    text { name }
+}
+
+blueprint Page {
+    Profile("header")
+    on_error {
+        Profile("Ada")
+    } fallback { error ->
+        Profile(name = error)
+    }
+}
+"#;
+        let module = lower_source(source);
+        let page = module.blueprints().find(|bp| bp.name == "Page").unwrap();
+        assert_eq!(page.top_children, vec![0]);
+        assert_eq!(
+            page.boundaries,
+            vec![ErrorBoundary {
+                body: vec![1],
+                fallback: vec![2],
+                error: Some("error".to_string()),
+            }]
+        );
+
+        let output = generate_module(&module);
+        assert!(output.contains(
+            "function Page$boundary$0(runtime, closure_id) {\n\
+             \x20\x20const call_sites = Page$metadata.call_sites;\n\
+             \x20\x20const boundary_id = runtime.open_boundary(closure_id, (error) => {\n\
+             \x20\x20\x20\x20runtime.set(closure_id, 'error', error);\n\
+             \x20\x20\x20\x20runtime.instantiate_call_site(closure_id, boundary_id, call_sites['2']);\n\
+             \x20\x20});\n\
+             \x20\x20try {\n\
+             \x20\x20\x20\x20runtime.instantiate_call_site(closure_id, boundary_id, call_sites['1']);\n\
+             \x20\x20} catch (error) {\n\
+             \x20\x20\x20\x20runtime.fail(boundary_id, error);\n\
+             \x20\x20}\n}\n"
+        ));
+        assert!(output.contains("  top_children: [0],\n  boundaries: [Page$boundary$0],\n"));
+        // The fallback reads the message from the blueprint's closure
+        assert!(output.contains(
+            "function Page$2$call_site_binding(runtime, parent_id, child_id) {\n  runtime.set(child_id, 'name', runtime.get(parent_id, 'error'));\n"
+        ));
+    }
+
+    #[test]
+    fn test_generate_loading_slot() {
+        let source = r#"module app
+
+backend Search {
+    query : String = ""
+    latest command search()
+    command clear()
+}
+
+blueprint Page {
+    with Search
+    loading {
+        text { "Searching..." }
+    }
+    button { "Search" } .. on_click { search() }
+    button { "Clear" } .. on_click { query = "" clear() search() }
+}
+"#;
+        let module = lower_source(source);
+        let page = module.blueprints().find(|bp| bp.name == "Page").unwrap();
+        assert_eq!(page.top_children, vec![1, 2]);
+        assert_eq!(
+            page.loading,
+            vec![LoadingSlot {
+                body: vec![0],
+                commands: vec!["search".to_string(), "clear".to_string()],
+            }]
+        );
+
+        let output = generate_module(&module);
+        assert!(output.contains(
+            "function Page$loading$0(runtime, closure_id) {\n\
+             \x20\x20const call_sites = Page$metadata.call_sites;\n\
+             \x20\x20runtime.open_loading(closure_id, ['search.loading', 'clear.loading'], (loading_id) => {\n\
+             \x20\x20\x20\x20runtime.instantiate_call_site(closure_id, loading_id, call_sites['0']);\n\
+             \x20\x20});\n}\n"
+        ));
+        assert!(output.contains("  top_children: [1, 2],\n  loading: [Page$loading$0],\n"));
+        // Every command tracks its in-flight flag, with or without a policy
+        assert!(output.contains("static fields = ['query', 'search.loading', 'clear.loading'];"));
+        assert!(output.contains("runtime.set(closure_id, 'clear.loading', false);"));
+        assert!(output.contains(
+            "return this.runtime.run_command(this.closure_id, 'clear', null, (signal) => this.clear$run(signal));"
+        ));
+    }
+
+    #[test]
+    fn test_memoized_selectors() {
+        let source = r#"module app
+
+backend Catalog {
+    items : List<String> = []
+    filter : String = ""
+}
+
+blueprint Row(label : String, hint : String) {
+    text { label }
+}
+
+blueprint Main {
+    with Catalog
+    prefix : String = ">"
+    count : u32 = items.length
+    repeat on items { item ->
+        Row(label = filter + item, hint = "Filter: " + filter)
+    }
+    text { prefix + filter }
+}
+"#;
+        let output = generate_source(source);
+
+        // Only backend fields: cached on the backend instance
+        assert!(output.contains(
+            "runtime.select(closure_id, 'app.Main$count', ['items'], () => runtime.get(closure_id, 'items').length)"
+        ));
+        assert!(output.contains("runtime.select(parent_id, 'app.Main$0$hint', ['filter'], "));
+        // Loop variables and local fields are not backend inputs
+        assert!(!output.contains("app.Main$0$label"));
+        assert!(!output.contains("app.Main$1$content"));
+        // Plain field reads are not memoized
+        assert!(!output.contains("app.Row$"));
+    }
+
+    #[test]
+    fn test_generate_scheme_codec() {
+        let output = generate_source(
+            r#"module app
+
+scheme User {
+    id : Uuid .. identity
+    name : String .. min_len { 2 } .. blank { false }
+    age : u8? .. range { min: 18 max: 120 }
+    role : Role
+}
+
+enum Role { Admin Member }
+"#,
+        );
+
+        assert!(output.contains(
+            "import { Runtime, Key, OneOf, Everything, Builtins, defineScheme, SchemeTypes, defineEnum } from '@frel/runtime';"
+        ));
+        assert!(output.contains(" * @property {string} id"));
+        assert!(output.contains("/** @type {import('@frel/runtime').SchemeCodec<User$data>} */"));
+        assert!(output.contains("export const User = defineScheme('app.User', ["));
+        assert!(output.contains("{ name: 'id', type: SchemeTypes.uuid },"));
+        assert!(output.contains(
+            "{ name: 'name', type: SchemeTypes.string, rules: [['min_len', 2], ['blank', false]] },"
+        ));
+        assert!(output.contains(
+            "{ name: 'age', type: SchemeTypes.nullable(SchemeTypes.int(0, 255)), rules: [['min', 18], ['max', 120]] },"
+        ));
+        assert!(output.contains("type: SchemeTypes.enumeration('Role', () => Role)"));
+    }
+
+    /// Compare the output of every `test-data/codegen/*.frel` with its sibling
+    /// `.js`; set `FREL_UPDATE_GOLDEN=1` to rewrite the expected files.
+    #[test]
+    fn test_codegen_golden() {
+        let dir =
+            std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../test-data/codegen"));
+        let update = std::env::var_os("FREL_UPDATE_GOLDEN").is_some();
+        let mut sources: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "frel"))
+            .collect();
+        sources.sort();
+        assert!(!sources.is_empty());
+
+        for source in sources {
+            let output = generate_source(&std::fs::read_to_string(&source).unwrap());
+            let expected_path = source.with_extension("js");
+            if update {
+                std::fs::write(&expected_path, &output).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&expected_path).unwrap_or_default();
+            assert!(
+                output == expected,
+                "{} differs from the generated code; rerun with FREL_UPDATE_GOLDEN=1 if the change is intended",
+                expected_path.display()
+            );
+        }
+    }
 }
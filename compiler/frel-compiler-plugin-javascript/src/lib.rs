@@ -1,15 +1,70 @@
 // Frel JavaScript Code Generation Plugin
 //
-// This crate implements JavaScript code generation from Frel AST.
-// It produces ES6 modules that can run in modern JavaScript environments.
+// This crate implements JavaScript code generation from the Frel IR.
+// It produces ES6 modules that can run in modern JavaScript environments,
+// one per Frel module, and is registered as the `javascript` target.
+// Minified modules come with a name map of their shortened identifiers.
 
-use frel_compiler_core::ast;
+use frel_compiler_core::ir;
+use frel_compiler_plugin_api::{CodegenPlugin, OutputFile, PluginContext, Result};
 
 pub mod codegen;
+pub mod minify;
 
-/// Generate JavaScript code from a Frel AST
-pub fn generate(file: &ast::File) -> String {
-    codegen::generate_file(file)
+pub use frel_compiler_plugin_api::GenerateOptions;
+
+/// The `javascript` (or `js`) code generation target
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JavaScriptPlugin;
+
+impl CodegenPlugin for JavaScriptPlugin {
+    fn name(&self) -> &str {
+        "javascript"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["js"]
+    }
+
+    fn file_extension(&self) -> &str {
+        "js"
+    }
+
+    fn generate(
+        &self,
+        module: &ir::Module,
+        options: &GenerateOptions,
+        _: &PluginContext,
+    ) -> Result<Vec<OutputFile>> {
+        if !options.minify {
+            let code = codegen::generate_module_with_options(module, options);
+            return Ok(vec![OutputFile::for_module(&module.path, self.file_extension(), code)]);
+        }
+        let minified = generate_minified(module, options);
+        Ok(vec![
+            OutputFile::for_module(&module.path, self.file_extension(), minified.code),
+            OutputFile::for_module(&module.path, "names.json", minified.names.to_json()),
+        ])
+    }
+}
+
+/// Generate JavaScript code from a lowered Frel module
+pub fn generate(module: &ir::Module) -> String {
+    codegen::generate_module(module)
+}
+
+/// Generate JavaScript code from a lowered Frel module with explicit options
+pub fn generate_with_options(module: &ir::Module, options: &GenerateOptions) -> String {
+    codegen::generate_module_with_options(module, options)
+}
+
+/// Generate minified JavaScript code from a lowered Frel module, with the map
+/// of the names it shortened
+pub fn generate_minified(module: &ir::Module, options: &GenerateOptions) -> minify::Minified {
+    let mut module = module.clone();
+    minify::strip_dead_branches(&mut module);
+    let code = codegen::generate_module_with_options(&module, options);
+    minify::minify(&code, &module.path)
 }
 
 #[cfg(test)]
@@ -18,14 +73,54 @@ mod tests {
 
     #[test]
     fn test_generate_empty_module() {
-        let file = ast::File {
-            module: "test".to_string(),
-            source_path: None,
+        let module = ir::Module {
+            path: "test".to_string(),
             imports: vec![],
             declarations: vec![],
+            type_names: Default::default(),
         };
 
-        let output = generate(&file);
+        let output = generate(&module);
         assert!(output.contains("// Module: test"));
     }
+
+    #[test]
+    fn test_plugin_output_file() {
+        let module = ir::Module {
+            path: "app.editor".to_string(),
+            imports: vec![],
+            declarations: vec![],
+            type_names: Default::default(),
+        };
+
+        let files = JavaScriptPlugin
+            .generate(&module, &GenerateOptions::default(), &PluginContext::new())
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, std::path::PathBuf::from("app/editor.js"));
+        assert_eq!(files[0].contents, generate(&module));
+    }
+
+    #[test]
+    fn test_plugin_minified_output() {
+        let source = "module app.editor\n\nblueprint Main {\n    text { \"Hi\" }\n}\n";
+        let file = frel_compiler_core::parse_file(source).file.unwrap();
+        let module = frel_compiler_core::ir::lower(&file);
+        let options = GenerateOptions {
+            minify: true,
+            ..GenerateOptions::default()
+        };
+
+        let files = JavaScriptPlugin.generate(&module, &options, &PluginContext::new()).unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                std::path::PathBuf::from("app/editor.js"),
+                std::path::PathBuf::from("app/editor.names.json")
+            ]
+        );
+        assert!(!files[0].contents.contains('\n'));
+        assert!(files[1].contents.contains("\"Main$0$call_site_binding\""));
+    }
 }
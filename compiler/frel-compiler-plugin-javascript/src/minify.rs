@@ -0,0 +1,834 @@
+// Minified output of the JavaScript target
+//
+// With `GenerateOptions::minify`, a module is generated as usual and then:
+//
+// - Dead branches are stripped from the IR first: conditionals, `&&`, `||`
+//   and `??` whose deciding operand is a constant (`Expr::const_value`) are
+//   replaced by the branch that is taken.
+// - Comments and whitespace are removed from the generated code. A line break
+//   is kept only where automatic semicolon insertion could depend on it.
+// - Identifiers the module declares for itself are shortened: declarations
+//   that are not exported, function parameters and local variables. Exported
+//   names, properties, imports and globals are kept.
+//
+// Short names are assigned in declaration order, so the same module always
+// minifies to the same code. They are listed in a name map written next to
+// the module (`<module>.names.json`) to read stack traces of production builds.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use frel_compiler_core::ast::BinaryOp;
+use frel_compiler_core::ir::{Expr, ExprKind, Module, TemplatePart};
+use frel_compiler_core::ConstValue;
+use serde::Serialize;
+
+/// Minified code and the names it shortened
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minified {
+    pub code: String,
+    pub names: NameMap,
+}
+
+/// Original names of the shortened identifiers of a module
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NameMap {
+    pub module: String,
+    /// Declarations of the module, by short name
+    pub names: BTreeMap<String, String>,
+    /// Parameters and local variables of each function, in source order
+    pub scopes: Vec<ScopeNames>,
+}
+
+/// Shortened parameters and local variables of a function
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScopeNames {
+    /// Path of the function through the declarations and methods enclosing
+    /// it, e.g. `Counter.inc/=>` for an arrow function in a method
+    pub scope: String,
+    /// Original names by short name
+    pub names: BTreeMap<String, String>,
+}
+
+impl NameMap {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("name map serializes")
+    }
+}
+
+/// Replace the conditionals of `module` that have a constant deciding operand
+/// by the branch that is taken
+pub fn strip_dead_branches(module: &mut Module) {
+    module.for_each_expr_mut(&mut strip_expr);
+}
+
+fn strip_expr(expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Template(parts) => {
+            for part in parts {
+                if let TemplatePart::Interpolation(inner) = part {
+                    strip_expr(inner);
+                }
+            }
+        }
+        ExprKind::List(items) => items.iter_mut().for_each(strip_expr),
+        ExprKind::Object(fields) => fields.iter_mut().for_each(|(_, value)| strip_expr(value)),
+        ExprKind::Binary { left, right, .. } => {
+            strip_expr(left);
+            strip_expr(right);
+        }
+        ExprKind::Unary { expr, .. } => strip_expr(expr),
+        ExprKind::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            strip_expr(condition);
+            strip_expr(then_expr);
+            strip_expr(else_expr);
+        }
+        ExprKind::Field { base, .. } => strip_expr(base),
+        ExprKind::Call { callee, args } => {
+            strip_expr(callee);
+            args.iter_mut().for_each(strip_expr);
+        }
+        ExprKind::BuiltinCall { args, .. } => args.iter_mut().for_each(strip_expr),
+        ExprKind::Member { base, args, .. } => {
+            strip_expr(base);
+            args.iter_mut().flatten().for_each(strip_expr);
+        }
+        ExprKind::Range { start, end, .. } => {
+            strip_expr(start);
+            strip_expr(end);
+        }
+        ExprKind::Lambda { body, .. } => strip_expr(body),
+        _ => {}
+    }
+
+    let taken = match &expr.kind {
+        ExprKind::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => match condition.const_value() {
+            Some(ConstValue::Bool(true)) => Some(then_expr.as_ref().clone()),
+            Some(ConstValue::Bool(false)) => Some(else_expr.as_ref().clone()),
+            _ => None,
+        },
+        ExprKind::Binary { op, left, right } => match (op, left.const_value()) {
+            (BinaryOp::And, Some(ConstValue::Bool(false)))
+            | (BinaryOp::Or, Some(ConstValue::Bool(true))) => Some(left.as_ref().clone()),
+            (BinaryOp::And, Some(ConstValue::Bool(true)))
+            | (BinaryOp::Or, Some(ConstValue::Bool(false)))
+            | (BinaryOp::Elvis, Some(ConstValue::Null)) => Some(right.as_ref().clone()),
+            (BinaryOp::Elvis, Some(_)) => Some(left.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(taken) = taken {
+        *expr = taken;
+    }
+}
+
+// ============================================================================
+// Tokens
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Word,
+    Number,
+    /// String or template literal, kept as written
+    Literal,
+    Punct,
+}
+
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// A line break comes before the token
+    newline: bool,
+}
+
+const PUNCTUATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==",
+    "!=", "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=",
+    "|=", "^=", "**", "<<", ">>",
+];
+
+fn tokenize(code: &str) -> Vec<Token<'_>> {
+    let bytes = code.as_bytes();
+    let mut tokens = Vec::new();
+    let mut newline = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let kind = match c {
+            b'\n' => {
+                newline = true;
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = code[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+                newline |= code[i..end].contains('\n');
+                i = end;
+                continue;
+            }
+            b'\'' | b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+                Kind::Literal
+            }
+            b'`' => {
+                i = template_end(bytes, i);
+                Kind::Literal
+            }
+            c if c.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                Kind::Number
+            }
+            c if is_word_byte(c) || !c.is_ascii() => {
+                while i < bytes.len() && (is_word_byte(bytes[i]) || !bytes[i].is_ascii()) {
+                    i += 1;
+                }
+                Kind::Word
+            }
+            _ => {
+                let rest = &code[i..];
+                let len = PUNCTUATORS.iter().find(|p| rest.starts_with(*p)).map_or(1, |p| p.len());
+                i += len;
+                Kind::Punct
+            }
+        };
+        let end = i.min(bytes.len());
+        tokens.push(Token {
+            kind,
+            text: &code[start..end],
+            newline,
+        });
+        newline = false;
+    }
+    tokens
+}
+
+fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
+
+/// End of the template literal starting at `start`, after its closing backtick
+fn template_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    let mut depth = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'`' if depth == 0 => return i + 1,
+            b'`' => i = template_end(bytes, i) - 1,
+            b'$' if depth == 0 && bytes.get(i + 1) == Some(&b'{') => {
+                depth = 1;
+                i += 1;
+            }
+            b'{' if depth > 0 => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+const KEYWORDS: &[&str] = &[
+    "arguments", "as", "async", "await", "break", "case", "catch", "class", "const", "continue",
+    "debugger", "default", "delete", "do", "else", "enum", "export", "extends", "false",
+    "finally", "for", "from", "function", "get", "if", "implements", "import", "in",
+    "Infinity", "instanceof", "interface", "let", "NaN", "new", "null", "of", "package",
+    "private", "protected", "public", "return", "set", "static", "super", "switch", "this",
+    "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "with", "yield",
+];
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(&word)
+}
+
+// ============================================================================
+// Minification
+// ============================================================================
+
+/// Minify the generated code of the module `module_path`
+pub fn minify(code: &str, module_path: &str) -> Minified {
+    let tokens = tokenize(code);
+    let mut names = NameMap {
+        module: module_path.to_string(),
+        ..NameMap::default()
+    };
+    let matching = matching_brackets(&tokens);
+    // Identifiers inside template literals are not analyzed, so a module with
+    // one keeps its names
+    let templates = tokens.iter().any(|t| t.text.starts_with('`'));
+    let (replacements, expression_ends) = match &matching {
+        Some(matching) if !templates => {
+            let analysis = Analysis::new(&tokens, matching);
+            analysis.record_names(&mut names);
+            (analysis.replacements(), Some(analysis.expression_ends))
+        }
+        _ => (HashMap::new(), None),
+    };
+
+    let mut code = String::with_capacity(code.len() / 2);
+    let mut previous: Option<(&Token, &str)> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let text = replacements.get(&i).map_or(token.text, String::as_str);
+        if let Some((before, before_text)) = previous {
+            let ends = matches!(before.kind, Kind::Word | Kind::Number | Kind::Literal)
+                || matches!(before.text, ")" | "]" | "++" | "--")
+                || (before.text == "}"
+                    && expression_ends.as_ref().is_none_or(|ends| ends.contains(&(i - 1))));
+            let starts = matches!(token.kind, Kind::Word | Kind::Number | Kind::Literal)
+                || matches!(text, "(" | "[" | "{" | "+" | "-" | "/" | "!" | "~" | "++" | "--");
+            if token.newline && ends && starts {
+                code.push('\n');
+            } else if needs_space(before, before_text, token, text) {
+                code.push(' ');
+            }
+        }
+        code.push_str(text);
+        previous = Some((token, text));
+    }
+
+    Minified { code, names }
+}
+
+/// Whether two tokens would read as one token, or as a comment, if joined
+fn needs_space(before: &Token, before_text: &str, token: &Token, text: &str) -> bool {
+    let word = |t: &Token| matches!(t.kind, Kind::Word | Kind::Number);
+    let last = before_text.chars().last();
+    let first = text.chars().next();
+    (word(before) && word(token))
+        || (before.kind == Kind::Number && first == Some('.'))
+        || matches!(
+            (last, first),
+            (Some('+'), Some('+')) | (Some('-'), Some('-')) | (Some('/'), Some('/' | '*'))
+        )
+}
+
+/// Index of the matching bracket of every bracket token; `None` if the
+/// brackets are unbalanced
+fn matching_brackets(tokens: &[Token]) -> Option<Vec<usize>> {
+    let mut matching = vec![usize::MAX; tokens.len()];
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.text {
+            "(" | "[" | "{" => open.push(i),
+            ")" | "]" | "}" => {
+                let start = open.pop()?;
+                matching[start] = i;
+                matching[i] = start;
+            }
+            _ => {}
+        }
+    }
+    open.is_empty().then_some(matching)
+}
+
+/// What an opening bracket starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bracket {
+    Block,
+    Object,
+    /// Body of a class, named by the class
+    Class,
+    /// `(` or `[`
+    Group,
+    /// Names of `import { ... }` and `export { ... }`
+    Names,
+}
+
+/// A function or catch clause: a scope for its parameters and locals
+struct Site {
+    /// First token: the `(` of the parameters, or the single parameter of an
+    /// arrow function
+    start: usize,
+    /// Last token of the body
+    end: usize,
+    params: Vec<usize>,
+    /// Parameters that can be renamed: plain names, and not of a catch clause
+    renamable: bool,
+    /// The name before the parameters is a property
+    method: bool,
+    name: String,
+}
+
+struct Scope {
+    parent: Option<usize>,
+    label: String,
+    /// Bindings in declaration order; `false` for names that must be kept
+    bindings: Vec<(String, bool)>,
+    short: HashMap<String, String>,
+}
+
+impl Scope {
+    fn declare(&mut self, name: &str, renamable: bool) {
+        if self.bindings.iter().all(|(bound, _)| bound != name) {
+            self.bindings.push((name.to_string(), renamable && !is_keyword(name)));
+        }
+    }
+
+    /// Renamed bindings as (original, short), in declaration order
+    fn renamed(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.bindings
+            .iter()
+            .filter_map(|(name, _)| self.short.get(name).map(|short| (name, short)))
+    }
+}
+
+/// Scopes of a token stream and the role of each token
+struct Analysis<'a> {
+    tokens: &'a [Token<'a>],
+    scopes: Vec<Scope>,
+    /// Innermost scope of each token
+    scope_of: Vec<usize>,
+    /// Bracket enclosing each token
+    bracket_of: Vec<Bracket>,
+    /// Names of methods, never renamed
+    methods: HashSet<usize>,
+    /// Closing braces of function bodies and object literals, which can be
+    /// followed by an operator
+    expression_ends: HashSet<usize>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(tokens: &'a [Token<'a>], matching: &[usize]) -> Self {
+        let text = |i: usize| tokens.get(i).map_or("", |t| t.text);
+        let word = |i: usize| tokens.get(i).is_some_and(|t| t.kind == Kind::Word);
+
+        // Brackets enclosing each token
+        let mut bracket_of = Vec::with_capacity(tokens.len());
+        let mut kinds = vec![Bracket::Block; tokens.len()];
+        let mut open: Vec<Bracket> = Vec::new();
+        let mut class = false;
+        for (i, token) in tokens.iter().enumerate() {
+            bracket_of.push(open.last().copied().unwrap_or(Bracket::Block));
+            match token.text {
+                "class" => class = true,
+                "{" => {
+                    let kind = if std::mem::take(&mut class) {
+                        Bracket::Class
+                    } else if matches!(text(i.wrapping_sub(1)), "import" | "export") {
+                        Bracket::Names
+                    } else if object_context(tokens, i) {
+                        Bracket::Object
+                    } else {
+                        Bracket::Block
+                    };
+                    kinds[i] = kind;
+                    open.push(kind);
+                }
+                "(" | "[" => open.push(Bracket::Group),
+                "}" | ")" | "]" => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+
+        // Functions and catch clauses
+        let mut sites = Vec::new();
+        let mut expression_ends: HashSet<usize> = (0..tokens.len())
+            .filter(|&i| text(i) == "}" && kinds[matching[i]] == Bracket::Object)
+            .collect();
+        for i in 0..tokens.len() {
+            let before = text(i.wrapping_sub(1));
+            let (close, params) = if text(i) == "(" {
+                (matching[i], (i + 1..matching[i]).collect())
+            } else if word(i) && text(i + 1) == "=>" && before != "." {
+                (i, vec![i])
+            } else {
+                continue;
+            };
+            let arrow = text(close + 1) == "=>";
+            let catch = before == "catch";
+            let function = !arrow
+                && text(close + 1) == "{"
+                && (word(i.wrapping_sub(1)) || before == "function")
+                && !matches!(before, "if" | "for" | "while" | "switch" | "with");
+            if !arrow && !function {
+                continue;
+            }
+            let body = close + if arrow { 2 } else { 1 };
+            let end = if text(body) == "{" {
+                matching[body]
+            } else {
+                expression_end(tokens, matching, body)
+            };
+
+            // `name(...) {` in a class or object literal
+            let method = function && before != "function" && text(i.wrapping_sub(2)) != "function";
+            let name = if catch {
+                "catch".to_string()
+            } else if !function || before == "function" {
+                "=>".to_string()
+            } else if method && bracket_of[i - 1] == Bracket::Class {
+                // Methods are labeled with their class
+                let class = (0..i)
+                    .rev()
+                    .find(|&c| text(c) == "{" && matching[c] > i && kinds[c] == Bracket::Class)
+                    .and_then(|c| (0..c).rev().find(|&k| text(k) == "class"))
+                    .map_or("", |k| text(k + 1));
+                format!("{}.{}", class, before)
+            } else {
+                before.to_string()
+            };
+            // Function declarations end a statement; other functions are
+            // expressions an operator can follow
+            let declaration = function
+                && text(i.wrapping_sub(2)) == "function"
+                && (i == 2
+                    || matches!(text(i - 3), ";" | "{" | "}" | "export" | "async" | "default"));
+            if text(end) == "}" && !method && !declaration {
+                expression_ends.insert(end);
+            }
+            let renamable = !catch
+                && params.iter().enumerate().all(|(n, &p)| {
+                    if n % 2 == 0 {
+                        word(p)
+                    } else {
+                        text(p) == ","
+                    }
+                });
+            sites.push(Site {
+                start: i,
+                end,
+                params,
+                renamable,
+                method: method && !catch,
+                name,
+            });
+        }
+
+        // Scopes, innermost first for each token
+        let mut scopes = vec![Scope {
+            parent: None,
+            label: String::new(),
+            bindings: Vec::new(),
+            short: HashMap::new(),
+        }];
+        let mut scope_of = Vec::with_capacity(tokens.len());
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        let mut site_scopes = Vec::with_capacity(sites.len());
+        let mut next = sites.iter().peekable();
+        for i in 0..tokens.len() {
+            while open.last().is_some_and(|&(_, end)| end < i) {
+                open.pop();
+            }
+            while let Some(site) = next.next_if(|site| site.start == i) {
+                let parent = open.last().map_or(0, |&(scope, _)| scope);
+                let label = match scopes[parent].label.as_str() {
+                    "" => site.name.clone(),
+                    outer => format!("{}/{}", outer, site.name),
+                };
+                scopes.push(Scope {
+                    parent: Some(parent),
+                    label,
+                    bindings: Vec::new(),
+                    short: HashMap::new(),
+                });
+                open.push((scopes.len() - 1, site.end));
+                site_scopes.push(scopes.len() - 1);
+            }
+            scope_of.push(open.last().map_or(0, |&(scope, _)| scope));
+        }
+
+        let mut analysis = Self {
+            tokens,
+            scopes,
+            scope_of,
+            bracket_of,
+            methods: HashSet::new(),
+            expression_ends,
+        };
+
+        // Declarations: parameters, then variables, classes and functions
+        let exported = exported_names(tokens);
+        for (site, &scope) in sites.iter().zip(&site_scopes) {
+            for &p in &site.params {
+                if word(p) {
+                    analysis.scopes[scope].declare(text(p), site.renamable);
+                }
+            }
+            if site.method {
+                analysis.methods.insert(site.start - 1);
+            }
+        }
+        for i in 0..tokens.len() {
+            let declared = match text(i) {
+                "const" | "let" | "var" | "class" | "function" => i + 1,
+                _ => continue,
+            };
+            let scope = analysis.scope_of[i];
+            if word(declared) {
+                let name = text(declared);
+                let export = scope == 0 && is_exported(tokens, i) || exported.contains(name);
+                analysis.scopes[scope].declare(name, !export);
+            } else if matches!(text(declared), "{" | "[") {
+                // Destructured names are kept
+                for p in declared + 1..matching[declared] {
+                    if word(p) {
+                        analysis.scopes[scope].declare(text(p), false);
+                    }
+                }
+            }
+        }
+
+        analysis.assign_short_names();
+        analysis
+    }
+
+    /// Give the renamable bindings of each scope the first short names not
+    /// used by the scopes enclosing it nor by any identifier of the code
+    fn assign_short_names(&mut self) {
+        let reserved: HashSet<&str> = self
+            .tokens
+            .iter()
+            .filter(|t| t.kind == Kind::Word)
+            .map(|t| t.text)
+            .chain(KEYWORDS.iter().copied())
+            .collect();
+        let mut candidates = (0..).map(short_name).filter(|name| !reserved.contains(name.as_str()));
+        let mut short_names: Vec<String> = Vec::new();
+        let mut first = vec![0; self.scopes.len()];
+        for scope in 0..self.scopes.len() {
+            let start = match self.scopes[scope].parent {
+                Some(parent) => first[parent] + self.scopes[parent].short.len(),
+                None => 0,
+            };
+            first[scope] = start;
+            let renamable: Vec<String> = self.scopes[scope]
+                .bindings
+                .iter()
+                .filter(|(_, renamable)| *renamable)
+                .map(|(name, _)| name.clone())
+                .collect();
+            for (n, name) in renamable.into_iter().enumerate() {
+                while short_names.len() <= start + n {
+                    short_names.extend(candidates.next());
+                }
+                self.scopes[scope].short.insert(name, short_names[start + n].clone());
+            }
+        }
+    }
+
+    /// Text replacing each renamed identifier, by token index
+    fn replacements(&self) -> HashMap<usize, String> {
+        let text = |i: usize| self.tokens.get(i).map_or("", |t| t.text);
+        let mut replacements = HashMap::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            if token.kind != Kind::Word || is_keyword(token.text) || self.methods.contains(&i) {
+                continue;
+            }
+            let before = text(i.wrapping_sub(1));
+            let after = text(i + 1);
+            let in_object = self.bracket_of[i] == Bracket::Object && matches!(before, "{" | ",");
+            if matches!(before, "." | "?.")
+                || matches!(self.bracket_of[i], Bracket::Class | Bracket::Names)
+                || (in_object && after == ":")
+            {
+                continue;
+            }
+            let Some(short) = self.resolve(self.scope_of[i], token.text) else {
+                continue;
+            };
+            let shorthand = in_object && matches!(after, "," | "}");
+            let replacement = if shorthand {
+                format!("{}:{}", token.text, short)
+            } else {
+                short.to_string()
+            };
+            replacements.insert(i, replacement);
+        }
+        replacements
+    }
+
+    /// Short name `name` refers to from `scope`, if the binding is renamed
+    fn resolve(&self, mut scope: usize, name: &str) -> Option<&str> {
+        loop {
+            let current = &self.scopes[scope];
+            if current.bindings.iter().any(|(bound, _)| bound == name) {
+                return current.short.get(name).map(String::as_str);
+            }
+            scope = current.parent?;
+        }
+    }
+
+    fn record_names(&self, names: &mut NameMap) {
+        for (i, scope) in self.scopes.iter().enumerate() {
+            let renamed: BTreeMap<String, String> = scope
+                .renamed()
+                .map(|(name, short)| (short.clone(), name.clone()))
+                .collect();
+            if i == 0 {
+                names.names = renamed;
+            } else if !renamed.is_empty() {
+                names.scopes.push(ScopeNames {
+                    scope: scope.label.clone(),
+                    names: renamed,
+                });
+            }
+        }
+    }
+}
+
+/// Whether the `{` at `open` starts an object literal rather than a block
+fn object_context(tokens: &[Token], open: usize) -> bool {
+    let Some(before) = open.checked_sub(1).map(|i| &tokens[i]) else {
+        return false;
+    };
+    match before.kind {
+        Kind::Punct => !matches!(before.text, ")" | "]" | "}" | ";" | "=>" | "{"),
+        Kind::Word => matches!(before.text, "return" | "typeof" | "in" | "of" | "case"),
+        Kind::Number | Kind::Literal => false,
+    }
+}
+
+/// Last token of the expression body of an arrow function starting at `start`
+fn expression_end(tokens: &[Token], matching: &[usize], start: usize) -> usize {
+    let mut i = start;
+    while i < tokens.len() {
+        match tokens[i].text {
+            "(" | "[" | "{" => i = matching[i],
+            "," | ")" | "]" | "}" | ";" => return i.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+    tokens.len() - 1
+}
+
+/// Whether the declaration keyword at `at` is exported
+fn is_exported(tokens: &[Token], at: usize) -> bool {
+    let before = |n: usize| at.checked_sub(n).map_or("", |i| tokens[i].text);
+    before(1) == "export" || (before(1) == "async" && before(2) == "export")
+}
+
+/// Names listed by `export { ... }`
+fn exported_names<'a>(tokens: &[Token<'a>]) -> HashSet<&'a str> {
+    let mut names = HashSet::new();
+    let mut listing = false;
+    for pair in tokens.windows(2) {
+        match (pair[0].text, pair[1].text) {
+            ("export", "{") => listing = true,
+            (_, "}") => listing = false,
+            (_, name) if listing && pair[1].kind == Kind::Word => {
+                names.insert(name);
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// The `n`th short name: `a` to `z`, `A` to `Z`, `$`, `_`, then two characters
+fn short_name(n: usize) -> String {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_0123456789";
+    let mut name = vec![FIRST[n % FIRST.len()]];
+    let mut n = n / FIRST.len();
+    while n > 0 {
+        n -= 1;
+        name.push(REST[n % REST.len()]);
+        n /= REST.len();
+    }
+    String::from_utf8(name).expect("short names are ASCII")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_renames_locals() {
+        let code = r#"// Generated
+import { Runtime } from '@frel/runtime';
+
+function Main$count(runtime, closure_id) {
+  const id = runtime.get(closure_id, 'id');
+  return runtime.select(closure_id, 'count', ['items'], (items) => runtime.count(items, id));
+}
+
+export const Main$metadata = {
+  count: Main$count,
+  /* shorthand */
+  Runtime,
+};
+
+export class Store {
+  constructor(runtime) {
+    this.runtime = runtime;
+  }
+
+  load(runtime) { return runtime - -1; }
+}
+"#;
+        let minified = minify(code, "app");
+        assert_eq!(
+            minified.code,
+            "import{Runtime}from'@frel/runtime';\
+             function a(b,c){const d=b.get(c,'id');\
+             return b.select(c,'count',['items'],(e)=>b.count(e,d));}\
+             export const Main$metadata={count:a,Runtime,};\
+             export class Store{constructor(b){this.runtime=b;}load(b){return b- -1;}}"
+        );
+        assert_eq!(minified.names.names["a"], "Main$count");
+        let scopes: Vec<_> = minified.names.scopes.iter().map(|s| s.scope.as_str()).collect();
+        assert_eq!(
+            scopes,
+            ["Main$count", "Main$count/=>", "Store.constructor", "Store.load"]
+        );
+        assert_eq!(minified.names.scopes[1].names["e"], "items");
+    }
+
+    #[test]
+    fn test_minify_keeps_line_breaks_for_asi() {
+        let minified = minify("let a = x\n(b)\nreturn\ny\nfoo()\n", "app");
+        assert_eq!(minified.code, "let c=x\n(b)\nreturn\ny\nfoo()");
+    }
+
+    #[test]
+    fn test_strip_dead_branches() {
+        let source = r#"module app
+
+blueprint Main(name : String) {
+    debug : bool = false
+    label : String = true ? name : "none"
+    hint : String = false || 1 > 2 ? "big" : name
+    title : String = null ?? name
+}
+"#;
+        let file = frel_compiler_core::parse_file(source).file.unwrap();
+        let mut module = frel_compiler_core::ir::lower(&file);
+        strip_dead_branches(&mut module);
+        let main = module.blueprints().next().unwrap();
+        let kinds: Vec<_> = main.fields.iter().map(|field| &field.init.kind).collect();
+        assert!(matches!(kinds[0], ExprKind::Bool(false)));
+        for kind in &kinds[1..] {
+            assert!(matches!(kind, ExprKind::Read(r) if r.name == "name"), "{:?}", kind);
+        }
+    }
+}
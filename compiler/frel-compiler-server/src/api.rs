@@ -1,13 +1,27 @@
 // HTTP API endpoint handlers
 
-use std::path::PathBuf;
-
-use actix_web::{web, HttpResponse, Responder};
-use frel_compiler_core::source::{LineIndex, Span};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use actix_web::web::Bytes;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use frel_compiler_core::ast::{DumpOptions, DumpVisitor};
+use frel_compiler_core::semantic::dump::{name_annotations, node_types};
+use frel_compiler_core::source::{LineCol, LineIndex, Span};
+use frel_compiler_core::{
+    BlueprintComplexity, ComplexityReport, FileDiagnostic, ScopeGraphExport, ScopeId, Severity,
+    SymbolKind,
+};
+use futures_util::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::compiler;
-use crate::state::SharedState;
+use crate::events::{EventFeed, EventFilter};
+use crate::state::{ProjectState, SharedState};
 
 /// Helper to compute line/column from a span using source content
 fn span_to_line_col(span: &Span, source: &str) -> (Option<usize>, Option<usize>) {
@@ -19,6 +33,52 @@ fn span_to_line_col(span: &Span, source: &str) -> (Option<usize>, Option<usize>)
     (Some(loc.line as usize), Some(loc.col as usize))
 }
 
+/// Move a diagnostic's location into its template when the source map of
+/// `path` covers it (`--remap-diagnostics`)
+fn remap_location(state: &ProjectState, path: Option<&Path>, info: &mut DiagnosticInfo) {
+    let (Some(path), Some(line), Some(column)) = (path, info.line, info.column) else {
+        return;
+    };
+    let position = LineCol {
+        line: line as u32,
+        col: column as u32,
+    };
+    if let Some(original) = state.original_position(path, position) {
+        info.file = Some(original.file);
+        info.line = Some(original.position.line as usize);
+        info.column = Some(original.position.col as usize);
+    }
+}
+
+/// Whether the request's `If-None-Match` header names `etag`, or is `*`
+fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// ETag of responses that only change with a build: the build ID, after the
+/// time the server started so IDs of an earlier run don't match
+fn build_etag(state: &ProjectState) -> String {
+    static STARTED: OnceLock<u128> = OnceLock::new();
+    let started = STARTED.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis())
+    });
+    format!("\"{:x}-{}\"", started, state.build_id)
+}
+
+/// `304 Not Modified` for a request whose `If-None-Match` holds `etag`
+fn not_modified(etag: String) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header((header::ETAG, etag))
+        .finish()
+}
+
 // === Response types ===
 
 #[derive(Serialize)]
@@ -26,6 +86,8 @@ pub struct StatusResponse {
     pub initialized: bool,
     pub error_count: usize,
     pub module_count: usize,
+    /// Number of the last build; see `ProjectState::build_id`
+    pub build_id: u64,
 }
 
 #[derive(Serialize)]
@@ -60,6 +122,86 @@ pub struct DiagnosticsResponse {
     pub warning_count: usize,
 }
 
+/// Filters and pagination of `GET /diagnostics`
+#[derive(Deserialize, Default)]
+pub struct DiagnosticsQuery {
+    /// Only diagnostics of this file: its full path or a trailing part of it,
+    /// e.g. `app/main.frel`
+    pub file: Option<String>,
+    /// Comma-separated severities, e.g. `error,warning`
+    pub severity: Option<String>,
+    /// Comma-separated diagnostic codes, e.g. `E0301,E0401`
+    pub code: Option<String>,
+    /// Number of matching diagnostics to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// Largest number of diagnostics returned; all by default
+    pub limit: Option<usize>,
+    /// `sarif` for a SARIF 2.1.0 log of all matching diagnostics, ignoring
+    /// pagination
+    pub format: Option<String>,
+}
+
+impl DiagnosticsQuery {
+    /// Whether a diagnostic passes the query's filters
+    pub fn matches(&self, diagnostic: &FileDiagnostic) -> bool {
+        let listed = |list: &Option<String>, value: Option<&str>| match list {
+            Some(list) => {
+                value.is_some_and(|value| list.split(',').any(|item| item.trim() == value))
+            }
+            None => true,
+        };
+        let file = self
+            .file
+            .as_deref()
+            .is_none_or(|file| Path::new(&diagnostic.file).ends_with(file));
+        let severity = diagnostic.diagnostic.severity.as_str();
+
+        file && listed(&self.severity, Some(severity))
+            && listed(&self.code, diagnostic.diagnostic.code.as_deref())
+    }
+}
+
+/// A diagnostic with its file and the position of its span
+#[derive(Serialize)]
+pub struct QueriedDiagnostic {
+    /// Start line, as in `DiagnosticInfo`
+    pub line: Option<usize>,
+    /// Start column, as in `DiagnosticInfo`
+    pub column: Option<usize>,
+    #[serde(flatten)]
+    pub diagnostic: FileDiagnostic,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticsPage {
+    pub module: Option<String>,
+    pub diagnostics: Vec<QueriedDiagnostic>,
+    /// Number of matching diagnostics, before pagination
+    pub total: usize,
+    pub offset: usize,
+    /// Errors among the matching diagnostics
+    pub error_count: usize,
+    /// Warnings among the matching diagnostics
+    pub warning_count: usize,
+}
+
+#[derive(Serialize, Default)]
+pub struct DiagnosticsSummary {
+    pub total: usize,
+    pub by_severity: BTreeMap<&'static str, usize>,
+    pub by_code: BTreeMap<String, usize>,
+    /// Files with diagnostics, in path order
+    pub files: Vec<FileSummary>,
+}
+
+#[derive(Serialize)]
+pub struct FileSummary {
+    pub file: String,
+    pub total: usize,
+    pub by_severity: BTreeMap<&'static str, usize>,
+}
+
 #[derive(Serialize)]
 pub struct AstResponse {
     pub module: String,
@@ -73,6 +215,29 @@ pub struct GeneratedResponse {
     pub javascript: String,
 }
 
+#[derive(Serialize)]
+pub struct HotReloadFile {
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Serialize)]
+pub struct HotReloadResponse {
+    pub module: String,
+    pub hash: String,
+    pub generation: u64,
+    /// The module's latest build failed; the files are from an earlier one
+    pub stale: bool,
+    pub files: Vec<HotReloadFile>,
+}
+
+#[derive(Serialize)]
+pub struct ReportResponse {
+    pub module: String,
+    pub blueprints: Vec<BlueprintComplexity>,
+    pub slow_count: usize,
+}
+
 // === Scope dump types ===
 
 #[derive(Serialize)]
@@ -100,6 +265,68 @@ pub struct ScopeResponse {
     pub scopes: Vec<ScopeInfo>,
 }
 
+/// Query of `GET /ast`, shaping the dump
+#[derive(Deserialize, Default)]
+pub struct AstQuery {
+    /// Fold the nodes deeper than this, the file being level 0
+    pub depth: Option<usize>,
+    /// Shorten long expressions
+    #[serde(default)]
+    pub collapse: bool,
+    /// Append the span of each node
+    #[serde(default)]
+    pub spans: bool,
+    /// Append the types found by the analysis, once the module is compiled
+    #[serde(default)]
+    pub types: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ScopeGraphQuery {
+    /// `dot` (default) or `json`
+    pub format: Option<String>,
+}
+
+/// Query of `GET /symbols`
+#[derive(Deserialize, Default)]
+pub struct SymbolsQuery {
+    /// Characters of the name, in order; matched case-insensitively.
+    /// Empty matches every symbol.
+    #[serde(default)]
+    pub query: String,
+    /// Comma-separated symbol kinds, e.g. `blueprint,backend`; kinds of more
+    /// than one word are written with underscores, e.g. `enum_variant`
+    pub kind: Option<String>,
+    /// Largest number of symbols returned; all by default
+    pub limit: Option<usize>,
+}
+
+/// A symbol declared in the project
+#[derive(Serialize)]
+pub struct SymbolMatch {
+    pub name: String,
+    pub kind: &'static str,
+    pub module: String,
+    /// Declaration the symbol is a member of, e.g. the backend of a field
+    pub container: Option<String>,
+    /// Source file declaring the symbol
+    pub file: Option<String>,
+    pub span: Span,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<LineCol>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<LineCol>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolsResponse {
+    pub query: String,
+    /// Best matches first
+    pub symbols: Vec<SymbolMatch>,
+    /// Number of matching symbols, before the limit
+    pub total: usize,
+}
+
 #[derive(Serialize)]
 pub struct SourceResponse {
     pub path: String,
@@ -134,6 +361,41 @@ pub struct WriteResponse {
     pub error_count: usize,
 }
 
+#[derive(Deserialize)]
+pub struct OverlayRequest {
+    pub path: String,
+    pub content: String,
+    /// Time-to-live in milliseconds (omit to keep until cleared)
+    pub ttl_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ClearOverlayRequest {
+    /// File to clear (omit to clear all overlays)
+    pub path: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct OverlayResponse {
+    pub success: bool,
+    pub modules_rebuilt: Vec<String>,
+    pub duration_ms: u64,
+    pub error_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct OverlayInfo {
+    pub path: String,
+    pub age_ms: u64,
+    pub ttl_ms: Option<u64>,
+    pub expired: bool,
+}
+
+#[derive(Serialize)]
+pub struct OverlaysResponse {
+    pub overlays: Vec<OverlayInfo>,
+}
+
 // === Expectations types (for compiler development mode) ===
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -171,13 +433,23 @@ pub struct CompareResponse {
 // === Handlers ===
 
 /// GET /status - Get server status
-pub async fn get_status(state: web::Data<SharedState>) -> impl Responder {
+///
+/// The response carries an `ETag` of the build ID; a request whose
+/// `If-None-Match` holds it gets `304 Not Modified` until the next build.
+pub async fn get_status(state: web::Data<SharedState>, req: HttpRequest) -> impl Responder {
     let state = state.read().await;
-    HttpResponse::Ok().json(StatusResponse {
-        initialized: state.initialized,
-        error_count: state.error_count(),
-        module_count: state.modules().len(),
-    })
+    let etag = build_etag(&state);
+    if etag_matches(&req, &etag) {
+        return not_modified(etag);
+    }
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(StatusResponse {
+            initialized: state.initialized,
+            error_count: state.error_count(),
+            module_count: state.modules().len(),
+            build_id: state.build_id,
+        })
 }
 
 /// GET /modules - List all modules
@@ -219,80 +491,114 @@ pub async fn get_modules(state: web::Data<SharedState>) -> impl Responder {
     HttpResponse::Ok().json(ModulesResponse { modules })
 }
 
-/// GET /diagnostics - Get all diagnostics
-pub async fn get_all_diagnostics(state: web::Data<SharedState>) -> impl Responder {
+/// GET /diagnostics - Query diagnostics of the whole project
+///
+/// Filtered by `file`, `severity` and `code`, and paginated with `offset`
+/// and `limit`; see `DiagnosticsQuery`. With `format=sarif` all matching
+/// diagnostics are returned as a SARIF log.
+///
+/// Like `GET /status`, the response carries an `ETag` of the build ID, so
+/// polling with `If-None-Match` gets `304 Not Modified` without the
+/// diagnostics being collected again.
+pub async fn get_all_diagnostics(
+    state: web::Data<SharedState>,
+    query: web::Query<DiagnosticsQuery>,
+    req: HttpRequest,
+) -> impl Responder {
     let state = state.read().await;
-    let mut all_diagnostics = Vec::new();
-    let mut total_errors = 0;
-    let mut total_warnings = 0;
-
-    // Collect from parse cache
-    for (path, entry) in &state.parse_cache {
-        // Get source for line/column computation
-        let source = state.sources.get(path).map(|s| s.content.as_str());
-
-        for diag in entry.diagnostics.iter() {
-            let severity = format!("{:?}", diag.severity).to_lowercase();
-            if diag.severity == frel_compiler_core::Severity::Error {
-                total_errors += 1;
-            } else if diag.severity == frel_compiler_core::Severity::Warning {
-                total_warnings += 1;
-            }
-
-            let (line, column) = source
-                .map(|s| span_to_line_col(&diag.span, s))
-                .unwrap_or((None, None));
-
-            all_diagnostics.push(DiagnosticInfo {
-                severity,
-                code: diag.code.clone(),
-                message: diag.message.clone(),
-                file: Some(path.display().to_string()),
-                line,
-                column,
-            });
+    let etag = build_etag(&state);
+    match query.format.as_deref() {
+        None | Some("json" | "sarif") if etag_matches(&req, &etag) => not_modified(etag),
+        None | Some("json") => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(query_diagnostics(&state, &query)),
+        Some("sarif") => {
+            let diagnostics = matching_diagnostics(&state, &query);
+            HttpResponse::Ok()
+                .content_type("application/sarif+json")
+                .insert_header((header::ETAG, etag))
+                .json(compiler::project_sarif(&state, &diagnostics))
         }
+        Some(other) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown format '{}', expected 'json' or 'sarif'", other),
+        })),
     }
+}
 
-    // Collect from analysis cache
-    for (module, entry) in &state.analysis_cache {
-        // Get source from first file in module for line/column computation
-        let source = state
-            .module_index
-            .files_for_module(module)
-            .first()
-            .and_then(|p| state.sources.get(p))
-            .map(|s| s.content.as_str());
-
-        for diag in entry.result.diagnostics.iter() {
-            let severity = format!("{:?}", diag.severity).to_lowercase();
-            if diag.severity == frel_compiler_core::Severity::Error {
-                total_errors += 1;
-            } else if diag.severity == frel_compiler_core::Severity::Warning {
-                total_warnings += 1;
-            }
+/// GET /diagnostics-summary - Count the project's diagnostics per severity,
+/// code and file
+///
+/// Takes the filters of `GET /diagnostics`; pagination is ignored.
+pub async fn get_diagnostics_summary(
+    state: web::Data<SharedState>,
+    query: web::Query<DiagnosticsQuery>,
+) -> impl Responder {
+    let state = state.read().await;
+    HttpResponse::Ok().json(summarize_diagnostics(&state, &query))
+}
 
-            let (line, column) = source
-                .map(|s| span_to_line_col(&diag.span, s))
-                .unwrap_or((None, None));
-
-            all_diagnostics.push(DiagnosticInfo {
-                severity,
-                code: diag.code.clone(),
-                message: diag.message.clone(),
-                file: Some(module.clone()),
-                line,
-                column,
-            });
+/// The project's diagnostics matching a query, in file order
+fn matching_diagnostics(state: &ProjectState, query: &DiagnosticsQuery) -> Vec<FileDiagnostic> {
+    let mut diagnostics = compiler::project_output(state).diagnostics;
+    diagnostics.retain(|diagnostic| query.matches(diagnostic));
+    diagnostics
+}
+
+/// One page of the project's diagnostics matching a query
+pub fn query_diagnostics(state: &ProjectState, query: &DiagnosticsQuery) -> DiagnosticsPage {
+    let matching = matching_diagnostics(state, query);
+    let count = |severity| {
+        matching
+            .iter()
+            .filter(|d| d.diagnostic.severity == severity)
+            .count()
+    };
+
+    DiagnosticsPage {
+        module: None,
+        total: matching.len(),
+        offset: query.offset,
+        error_count: count(Severity::Error),
+        warning_count: count(Severity::Warning),
+        diagnostics: matching
+            .iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|diagnostic| QueriedDiagnostic {
+                line: diagnostic.start.map(|start| start.line as usize),
+                column: diagnostic.start.map(|start| start.col as usize),
+                diagnostic: diagnostic.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Counts of the project's diagnostics matching a query
+pub fn summarize_diagnostics(state: &ProjectState, query: &DiagnosticsQuery) -> DiagnosticsSummary {
+    let mut summary = DiagnosticsSummary::default();
+    let mut files: BTreeMap<String, FileSummary> = BTreeMap::new();
+
+    for diagnostic in matching_diagnostics(state, query) {
+        let severity = diagnostic.diagnostic.severity.as_str();
+        summary.total += 1;
+        *summary.by_severity.entry(severity).or_default() += 1;
+        if let Some(code) = &diagnostic.diagnostic.code {
+            *summary.by_code.entry(code.clone()).or_default() += 1;
         }
+
+        let file = files
+            .entry(diagnostic.file.clone())
+            .or_insert_with(|| FileSummary {
+                file: diagnostic.file.clone(),
+                total: 0,
+                by_severity: BTreeMap::new(),
+            });
+        file.total += 1;
+        *file.by_severity.entry(severity).or_default() += 1;
     }
 
-    HttpResponse::Ok().json(DiagnosticsResponse {
-        module: None,
-        diagnostics: all_diagnostics,
-        error_count: total_errors,
-        warning_count: total_warnings,
-    })
+    summary.files = files.into_values().collect();
+    summary
 }
 
 /// GET /diagnostics/{module} - Get diagnostics for a specific module
@@ -307,40 +613,34 @@ pub async fn get_module_diagnostics(
     let mut error_count = 0;
     let mut warning_count = 0;
 
-    // Get first file in module for file path and line/column computation
-    let first_file = state
-        .module_index
-        .files_for_module(&module_path)
-        .first()
-        .cloned();
-    let module_source = first_file
-        .as_ref()
-        .and_then(|p| state.sources.get(p))
-        .map(|s| s.content.as_str());
-    let file_display = first_file.as_ref().map(|p| p.display().to_string());
-
-    // Get from analysis cache
+    // Get from analysis cache, each diagnostic in the file it points into
     if let Some(entry) = state.analysis_cache.get(&module_path) {
-        for diag in entry.result.diagnostics.iter() {
-            let severity = format!("{:?}", diag.severity).to_lowercase();
-            if diag.severity == frel_compiler_core::Severity::Error {
-                error_count += 1;
-            } else if diag.severity == frel_compiler_core::Severity::Warning {
-                warning_count += 1;
-            }
+        let by_file = compiler::module_diagnostics(&state, &module_path, &entry.result.diagnostics);
+        for (file_path, found) in by_file {
+            let source = state.sources.get(file_path).map(|s| s.content.as_str());
+            for diag in found.iter() {
+                let severity = format!("{:?}", diag.severity).to_lowercase();
+                if diag.severity == frel_compiler_core::Severity::Error {
+                    error_count += 1;
+                } else if diag.severity == frel_compiler_core::Severity::Warning {
+                    warning_count += 1;
+                }
 
-            let (line, column) = module_source
-                .map(|s| span_to_line_col(&diag.span, s))
-                .unwrap_or((None, None));
-
-            diagnostics.push(DiagnosticInfo {
-                severity,
-                code: diag.code.clone(),
-                message: diag.message.clone(),
-                file: file_display.clone(),
-                line,
-                column,
-            });
+                let (line, column) = source
+                    .map(|s| span_to_line_col(&diag.span, s))
+                    .unwrap_or((None, None));
+
+                let mut info = DiagnosticInfo {
+                    severity,
+                    code: diag.code.clone(),
+                    message: diag.message.clone(),
+                    file: Some(file_path.display().to_string()),
+                    line,
+                    column,
+                };
+                remap_location(&state, Some(file_path), &mut info);
+                diagnostics.push(info);
+            }
         }
     }
 
@@ -361,14 +661,16 @@ pub async fn get_module_diagnostics(
                     .map(|s| span_to_line_col(&diag.span, s))
                     .unwrap_or((None, None));
 
-                diagnostics.push(DiagnosticInfo {
+                let mut info = DiagnosticInfo {
                     severity,
                     code: diag.code.clone(),
                     message: diag.message.clone(),
                     file: Some(file_path.display().to_string()),
                     line,
                     column,
-                });
+                };
+                remap_location(&state, Some(file_path), &mut info);
+                diagnostics.push(info);
             }
         }
     }
@@ -382,9 +684,12 @@ pub async fn get_module_diagnostics(
 }
 
 /// GET /ast/{module} - Get AST for a module
+///
+/// Query parameters shape the dump: `depth`, `collapse`, `spans` and `types`.
 pub async fn get_module_ast(
     state: web::Data<SharedState>,
     path: web::Path<String>,
+    query: web::Query<AstQuery>,
 ) -> impl Responder {
     let module_path = path.into_inner();
     let state = state.read().await;
@@ -395,7 +700,18 @@ pub async fn get_module_ast(
             // Serialize AST to JSON
             let ast_json = serde_json::to_value(&entry.file).unwrap_or(serde_json::Value::Null);
             // Generate human-readable dump format
-            let dump = frel_compiler_core::ast::DumpVisitor::dump(&entry.file);
+            let mut options = DumpOptions {
+                max_depth: query.depth,
+                collapse_exprs: query.collapse,
+                spans: query.spans,
+                ..DumpOptions::default()
+            };
+            if let Some(analysis) = state.analysis_cache.get(&module_path).filter(|_| query.types) {
+                let result = &analysis.result;
+                options.types = node_types(&result.expr_types, &result.symbols);
+                options.names = name_annotations(&result.names, &result.symbols);
+            }
+            let dump = DumpVisitor::dump_with(&entry.file, options);
             return HttpResponse::Ok().json(AstResponse {
                 module: module_path,
                 ast: ast_json,
@@ -421,7 +737,7 @@ pub async fn get_module_generated(
     if let Some(entry) = state.analysis_cache.get(&module_path) {
         return HttpResponse::Ok().json(GeneratedResponse {
             module: module_path,
-            javascript: entry.generated_js.clone(),
+            javascript: entry.code(),
         });
     }
 
@@ -431,6 +747,85 @@ pub async fn get_module_generated(
     }))
 }
 
+/// GET /hot-reload/{module} - Get the files last generated for a module
+///
+/// The response carries an `ETag` of the files' hash; a request whose
+/// `If-None-Match` holds the current hash gets `304 Not Modified`.
+pub async fn get_module_hot_reload(
+    state: web::Data<SharedState>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> impl Responder {
+    let module_path = path.into_inner();
+    let state = state.read().await;
+
+    let Some(artifacts) = state.artifacts.get(&module_path) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Module not found or never generated",
+            "module": module_path
+        }));
+    };
+
+    let hash = format!("{:016x}", artifacts.hash);
+    let etag = format!("\"{}\"", hash);
+    if etag_matches(&req, &etag) {
+        return not_modified(etag);
+    }
+
+    let stale = state
+        .analysis_cache
+        .get(&module_path)
+        .is_none_or(|entry| entry.outputs.is_empty());
+    let files = artifacts
+        .outputs
+        .iter()
+        .map(|file| HotReloadFile {
+            path: file.path.display().to_string(),
+            contents: file.contents.clone(),
+        })
+        .collect();
+
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(HotReloadResponse {
+            module: module_path,
+            hash,
+            generation: artifacts.generation,
+            stale,
+            files,
+        })
+}
+
+/// GET /report/{module} - Get the blueprint complexity report for a module
+pub async fn get_module_report(
+    state: web::Data<SharedState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let module_path = path.into_inner();
+    let state = state.read().await;
+
+    let files = state.module_index.files_for_module(&module_path);
+    if files.is_empty() {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Module not found",
+            "module": module_path
+        }));
+    }
+
+    let mut report = ComplexityReport::default();
+    for file_path in files {
+        if let Some(entry) = state.parse_cache.get(file_path) {
+            report.merge(frel_compiler_core::analyze_complexity(&entry.file));
+        }
+    }
+
+    HttpResponse::Ok().json(ReportResponse {
+        module: module_path,
+        slow_count: report.slow().count(),
+        blueprints: report.blueprints,
+    })
+}
+
 /// GET /scope/{module} - Get scope dump for a module
 pub async fn get_module_scope(
     state: web::Data<SharedState>,
@@ -480,6 +875,166 @@ pub async fn get_module_scope(
     }))
 }
 
+/// GET /symbols - Search the symbols declared in all modules
+///
+/// Symbols whose name contains the characters of `query` in order, best
+/// matches first: exact names, then prefixes, substrings and the rest.
+/// Filtered by `kind` and capped by `limit`; see `SymbolsQuery`.
+pub async fn get_symbols(
+    state: web::Data<SharedState>,
+    query: web::Query<SymbolsQuery>,
+) -> impl Responder {
+    let state = state.read().await;
+    HttpResponse::Ok().json(search_symbols(&state, &query))
+}
+
+/// The project's symbols matching a query
+///
+/// Imports and symbols of other modules are left out, so every symbol is
+/// listed once, in the module declaring it.
+pub fn search_symbols(state: &ProjectState, query: &SymbolsQuery) -> SymbolsResponse {
+    let pattern = query.query.to_lowercase();
+    let kinds: Option<Vec<String>> = query.kind.as_ref().map(|kinds| {
+        kinds
+            .split(',')
+            .map(|kind| kind.trim().replace('_', " "))
+            .collect()
+    });
+
+    let mut modules: Vec<&String> = state.analysis_cache.keys().collect();
+    modules.sort();
+
+    let mut matches = Vec::new();
+    for module in modules {
+        let result = &state.analysis_cache[module].result;
+        let files = state.module_index.files_for_module(module);
+        for symbol in result.symbols.iter() {
+            if symbol.kind == SymbolKind::Import || symbol.source_module.is_some() {
+                continue;
+            }
+            if kinds
+                .as_ref()
+                .is_some_and(|kinds| !kinds.iter().any(|kind| kind == symbol.kind.as_str()))
+            {
+                continue;
+            }
+            let Some(score) = fuzzy_score(&pattern, &symbol.name.to_lowercase()) else {
+                continue;
+            };
+
+            // Scopes between the symbol and the module scope, innermost first
+            let enclosing: Vec<ScopeId> = std::iter::once(symbol.scope)
+                .chain(result.scopes.ancestors(symbol.scope))
+                .take_while(|scope| *scope != ScopeId::ROOT)
+                .collect();
+            let name_of = |scope: &ScopeId| result.scopes.get(*scope).and_then(|s| s.name.clone());
+            let container = enclosing.iter().find_map(name_of);
+
+            // The file holding the module-level declaration of the symbol
+            let top_level = enclosing.last().and_then(name_of);
+            let declaration = top_level.as_deref().unwrap_or(&symbol.name);
+            let file = files.iter().find(|path| {
+                state.parse_cache.get(*path).is_some_and(|entry| {
+                    entry.file.declarations.iter().any(|decl| decl.name() == declaration)
+                })
+            });
+            let line_index = file
+                .and_then(|path| state.sources.get(path))
+                .map(|source| LineIndex::new(&source.content));
+
+            matches.push((
+                score,
+                SymbolMatch {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind.as_str(),
+                    module: module.clone(),
+                    container,
+                    file: file.map(|path| path.display().to_string()),
+                    span: symbol.def_span,
+                    start: line_index.as_ref().map(|index| index.line_col(symbol.def_span.start)),
+                    end: line_index.as_ref().map(|index| index.line_col(symbol.def_span.end)),
+                },
+            ));
+        }
+    }
+
+    matches.sort_by(|(a, a_symbol), (b, b_symbol)| {
+        a.cmp(b)
+            .then_with(|| a_symbol.name.cmp(&b_symbol.name))
+            .then_with(|| a_symbol.module.cmp(&b_symbol.module))
+    });
+    let total = matches.len();
+    SymbolsResponse {
+        query: query.query.clone(),
+        symbols: matches
+            .into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(_, symbol)| symbol)
+            .collect(),
+        total,
+    }
+}
+
+/// How well a lowercase name matches a lowercase pattern, lower is better;
+/// `None` when the characters of the pattern are not in the name in order
+///
+/// Exact names score 0, prefixes 1 and substrings 2. Other matches score
+/// 3 plus the number of characters skipped between the matched ones, so
+/// `sc` is closer to `save_changes` than to `session_count`.
+fn fuzzy_score(pattern: &str, name: &str) -> Option<usize> {
+    if name == pattern {
+        return Some(0);
+    }
+    if name.starts_with(pattern) {
+        return Some(1);
+    }
+    if name.contains(pattern) {
+        return Some(2);
+    }
+
+    let mut gaps = 0;
+    let mut position = None;
+    let mut chars = name.chars().enumerate();
+    for wanted in pattern.chars() {
+        let (index, _) = chars.find(|(_, c)| *c == wanted)?;
+        if let Some(previous) = position {
+            gaps += index - previous - 1;
+        }
+        position = Some(index);
+    }
+    Some(3 + gaps)
+}
+
+/// GET /scope-graph/{module} - Export the scope graph of a module as DOT or JSON
+pub async fn get_module_scope_graph(
+    state: web::Data<SharedState>,
+    path: web::Path<String>,
+    query: web::Query<ScopeGraphQuery>,
+) -> impl Responder {
+    let module_path = path.into_inner();
+    let state = state.read().await;
+
+    let Some(entry) = state.analysis_cache.get(&module_path) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Module not found or not compiled",
+            "module": module_path
+        }));
+    };
+
+    let result = &entry.result;
+    let export = ScopeGraphExport::new(&result.scopes, &result.symbols, &result.resolutions);
+    match query.format.as_deref() {
+        None | Some("dot") => HttpResponse::Ok()
+            .content_type("text/vnd.graphviz")
+            .body(export.to_dot()),
+        Some("json") => HttpResponse::Ok().json(export),
+        Some(other) => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Unknown format '{}', expected 'dot' or 'json'", other),
+            "module": module_path
+        })),
+    }
+}
+
 /// POST /notify - Notify server of a file change
 pub async fn post_notify(
     state: web::Data<SharedState>,
@@ -518,8 +1073,9 @@ pub async fn post_write(
 ) -> impl Responder {
     let path = PathBuf::from(&body.path);
 
-    // Write file to disk
-    if let Err(e) = std::fs::write(&path, &body.content) {
+    // Write the file through the project's sink, the disk unless it runs in memory
+    let sink = state.read().await.sink.clone();
+    if let Err(e) = sink.write(&path, &body.content) {
         return HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Failed to write file: {}", e),
             "path": path.display().to_string()
@@ -569,8 +1125,8 @@ pub async fn get_source(
         });
     }
 
-    // Try to read from disk if not in cache
-    match std::fs::read_to_string(&file_path) {
+    // Try the overlay or disk if not in cache
+    match state.read_source(&file_path) {
         Ok(content) => {
             let module = state.module_index.module_for_file(&file_path).map(|s| s.to_string());
             HttpResponse::Ok().json(SourceResponse {
@@ -586,13 +1142,264 @@ pub async fn get_source(
     }
 }
 
-/// GET /events - SSE endpoint for compilation events
-pub async fn get_events() -> impl Responder {
-    // TODO: Implement SSE stream
-    // For now, return a placeholder response
+/// A file of the build directory served by `GET /build/{path}`
+#[derive(Debug, PartialEq, Eq)]
+struct BuildFile {
+    path: PathBuf,
+    content_type: &'static str,
+    cache_control: &'static str,
+}
+
+/// Cache header of a fingerprinted file: its name changes with its contents
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The file of the build directory at `path`, relative to it
+///
+/// Fingerprinted files may be cached forever; anything else, the manifest
+/// included, is revalidated on every request.
+fn build_file(state: &ProjectState, path: &str) -> Option<BuildFile> {
+    let relative = Path::new(path);
+    if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+        return None;
+    }
+    let cache_control = if state.manifest.is_fingerprinted(relative) {
+        IMMUTABLE
+    } else {
+        "no-cache"
+    };
+    let content_type = match relative.extension().and_then(|ext| ext.to_str()) {
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("css") => "text/css; charset=utf-8",
+        _ => "application/octet-stream",
+    };
+    Some(BuildFile {
+        path: state.build_dir.join(relative),
+        content_type,
+        cache_control,
+    })
+}
+
+/// GET /build/{path} - Serve a file of the build directory
+pub async fn get_build_file(
+    state: web::Data<SharedState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let path = path.into_inner();
+    let state = state.read().await;
+    let file = build_file(&state, &path);
+    match file.map(|file| (state.files.read(&file.path), file)) {
+        Some((Ok(contents), file)) => HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, file.content_type))
+            .insert_header((header::CACHE_CONTROL, file.cache_control))
+            .body(contents),
+        _ => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "File not found",
+            "path": path
+        })),
+    }
+}
+
+/// POST /overlay - Override a file's contents in memory and trigger recompilation
+///
+/// The overlay takes precedence over the disk until it is cleared or its TTL
+/// elapses, so diagnostics reflect unsaved editor buffers.
+pub async fn post_overlay(
+    state: web::Data<SharedState>,
+    body: web::Json<OverlayRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let ttl = body.ttl_ms.map(Duration::from_millis);
+
+    let result = {
+        let mut state = state.write().await;
+        let path = match overlay_path(&state.root, &body.path) {
+            Ok(path) => path,
+            Err(error) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({ "error": error }))
+            }
+        };
+        state.set_overlay(path.clone(), body.content, ttl);
+        compiler::handle_file_change(&mut state, &path)
+    };
+
+    HttpResponse::Ok().json(OverlayResponse {
+        success: true,
+        modules_rebuilt: result.modules_rebuilt,
+        duration_ms: result.duration.as_millis() as u64,
+        error_count: result.error_count,
+    })
+}
+
+/// Path of an overlay as the watcher and the builds name the file: relative
+/// paths are resolved against the project root, and `.` and `..` removed
+///
+/// A path outside the root is an error, as no build would read it.
+fn overlay_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+    let root = normalize(root);
+    let resolved = normalize(&root.join(path));
+    if !resolved.starts_with(&root) {
+        return Err(format!("'{}' is outside the project root", path));
+    }
+    Ok(resolved)
+}
+
+/// POST /overlay/clear - Drop one or all overlays and recompile from disk
+pub async fn post_clear_overlay(
+    state: web::Data<SharedState>,
+    body: web::Json<ClearOverlayRequest>,
+) -> impl Responder {
+    let start = Instant::now();
+    let mut state = state.write().await;
+
+    let cleared = match &body.path {
+        Some(path) => {
+            let path = match overlay_path(&state.root, path) {
+                Ok(path) => path,
+                Err(error) => {
+                    return HttpResponse::BadRequest().json(serde_json::json!({ "error": error }))
+                }
+            };
+            if state.clear_overlay(&path) {
+                vec![path]
+            } else {
+                vec![]
+            }
+        }
+        None => state.clear_overlays(),
+    };
+
+    let mut modules_rebuilt = Vec::new();
+    for path in &cleared {
+        let result = compiler::handle_file_change(&mut state, path);
+        for module in result.modules_rebuilt {
+            if !modules_rebuilt.contains(&module) {
+                modules_rebuilt.push(module);
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(OverlayResponse {
+        success: true,
+        modules_rebuilt,
+        duration_ms: start.elapsed().as_millis() as u64,
+        error_count: state.error_count(),
+    })
+}
+
+/// GET /overlays - List active in-memory overlays
+pub async fn get_overlays(state: web::Data<SharedState>) -> impl Responder {
+    let state = state.read().await;
+    let mut overlays: Vec<OverlayInfo> = state
+        .overlays
+        .iter()
+        .map(|(path, overlay)| OverlayInfo {
+            path: path.display().to_string(),
+            age_ms: overlay.created.elapsed().as_millis() as u64,
+            ttl_ms: overlay.ttl.map(|ttl| ttl.as_millis() as u64),
+            expired: overlay.is_expired(),
+        })
+        .collect();
+    overlays.sort_by(|a, b| a.path.cmp(&b.path));
+
+    HttpResponse::Ok().json(OverlaysResponse { overlays })
+}
+
+/// Filters of `GET /events`
+#[derive(Deserialize, Default)]
+pub struct EventsQuery {
+    /// Comma-separated module globs, e.g. `app.*,lib.ui`
+    pub module: Option<String>,
+    /// Comma-separated event types, e.g. `build_completed,diagnostics_updated`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// `error` or `warning`: only module events of modules with problems of
+    /// that severity
+    pub severity: Option<String>,
+    /// Coalesce per-module events into a `progress` message sent at most
+    /// this many milliseconds after the first of them
+    pub summary_ms: Option<u64>,
+}
+
+/// GET /events - SSE stream of compilation events
+///
+/// Opens with a `connected` message, then sends every `CompilationEvent` as
+/// it is published, filtered by `EventsQuery`. A client too slow to keep up
+/// gets a `lagged` message with the number of events it missed and should
+/// refetch the state it shows.
+pub async fn get_events(
+    state: web::Data<SharedState>,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let filter = match EventFilter::parse(
+        query.module.as_deref(),
+        query.kind.as_deref(),
+        query.severity.as_deref(),
+    ) {
+        Ok(filter) => filter,
+        Err(error) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": error }))
+        }
+    };
+    let interval = query.summary_ms.map(Duration::from_millis);
+    let receiver = state.read().await.subscribe();
+
+    let connected = serde_json::json!({ "type": "connected" });
+    let connected = stream::once(async move { Ok(sse_message(&connected)) });
+    let feed = EventFeed::new(filter, interval);
+    let events = stream::unfold(
+        (receiver, feed, VecDeque::new()),
+        |(mut receiver, mut feed, mut pending)| async move {
+            loop {
+                if let Some(message) = pending.pop_front() {
+                    return Some((Ok::<_, actix_web::Error>(message), (receiver, feed, pending)));
+                }
+                let received = match feed.deadline() {
+                    Some(deadline) => tokio::select! {
+                        received = receiver.recv() => Some(received),
+                        _ = tokio::time::sleep_until(deadline.into()) => None,
+                    },
+                    None => Some(receiver.recv().await),
+                };
+                match received {
+                    Some(Ok(event)) => {
+                        pending.extend(feed.push(event).iter().map(sse_message));
+                    }
+                    Some(Err(RecvError::Lagged(skipped))) => {
+                        pending.push_back(sse_message(
+                            &serde_json::json!({ "type": "lagged", "skipped": skipped }),
+                        ));
+                    }
+                    Some(Err(RecvError::Closed)) => return None,
+                    None => pending.extend(feed.flush().as_ref().map(sse_message)),
+                }
+            }
+        },
+    );
+
     HttpResponse::Ok()
         .content_type("text/event-stream")
-        .body("data: {\"type\": \"connected\"}\n\n")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(connected.chain(events))
+}
+
+/// Encode a value as one SSE `data` message
+fn sse_message(value: &impl Serialize) -> Bytes {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    Bytes::from(format!("data: {}\n\n", json))
 }
 
 // === Expectations handlers (for compiler development mode) ===
@@ -618,34 +1425,29 @@ fn get_current_module_state(
         .and_then(|file_path| state.parse_cache.get(file_path))
         .map(|entry| serde_json::to_value(&entry.file).unwrap_or(serde_json::Value::Null));
 
-    // Get first file for file path and line/column computation
-    let first_file = state
-        .module_index
-        .files_for_module(module_path)
-        .first()
-        .cloned();
-    let module_source = first_file
-        .as_ref()
-        .and_then(|p| state.sources.get(p))
-        .map(|s| s.content.as_str());
-    let file_display = first_file.as_ref().map(|p| p.display().to_string());
-
-    // Get diagnostics
+    // Get diagnostics, each in the file it points into
     let mut diagnostics = Vec::new();
     if let Some(entry) = state.analysis_cache.get(module_path) {
-        for diag in entry.result.diagnostics.iter() {
-            let (line, column) = module_source
-                .map(|s| span_to_line_col(&diag.span, s))
-                .unwrap_or((None, None));
-
-            diagnostics.push(DiagnosticInfo {
-                severity: format!("{:?}", diag.severity).to_lowercase(),
-                code: diag.code.clone(),
-                message: diag.message.clone(),
-                file: file_display.clone(),
-                line,
-                column,
-            });
+        for (file_path, found) in
+            compiler::module_diagnostics(state, module_path, &entry.result.diagnostics)
+        {
+            let source = state.sources.get(file_path).map(|s| s.content.as_str());
+            for diag in found.iter() {
+                let (line, column) = source
+                    .map(|s| span_to_line_col(&diag.span, s))
+                    .unwrap_or((None, None));
+
+                let mut info = DiagnosticInfo {
+                    severity: format!("{:?}", diag.severity).to_lowercase(),
+                    code: diag.code.clone(),
+                    message: diag.message.clone(),
+                    file: Some(file_path.display().to_string()),
+                    line,
+                    column,
+                };
+                remap_location(state, Some(file_path), &mut info);
+                diagnostics.push(info);
+            }
         }
     }
     for file_path in state.module_index.files_for_module(module_path) {
@@ -657,14 +1459,16 @@ fn get_current_module_state(
                     .map(|s| span_to_line_col(&diag.span, s))
                     .unwrap_or((None, None));
 
-                diagnostics.push(DiagnosticInfo {
+                let mut info = DiagnosticInfo {
                     severity: format!("{:?}", diag.severity).to_lowercase(),
                     code: diag.code.clone(),
                     message: diag.message.clone(),
                     file: Some(file_path.display().to_string()),
                     line,
                     column,
-                });
+                };
+                remap_location(state, Some(file_path), &mut info);
+                diagnostics.push(info);
             }
         }
     }
@@ -673,7 +1477,7 @@ fn get_current_module_state(
     let generated_js = state
         .analysis_cache
         .get(module_path)
-        .map(|entry| entry.generated_js.clone());
+        .map(|entry| entry.code());
 
     ModuleExpectations {
         module: module_path.to_string(),
@@ -693,19 +1497,13 @@ pub async fn get_expectations(
 
     let exp_file = expectations_file(&state, &module_path);
 
-    if exp_file.exists() {
-        match std::fs::read_to_string(&exp_file) {
-            Ok(content) => match serde_json::from_str::<ModuleExpectations>(&content) {
-                Ok(expectations) => {
-                    return HttpResponse::Ok().json(ExpectationsResponse {
-                        module: module_path,
-                        exists: true,
-                        expectations: Some(expectations),
-                    });
-                }
-                Err(_) => {}
-            },
-            Err(_) => {}
+    if let Ok(content) = state.files.read(&exp_file) {
+        if let Ok(expectations) = serde_json::from_str::<ModuleExpectations>(&content) {
+            return HttpResponse::Ok().json(ExpectationsResponse {
+                module: module_path,
+                exists: true,
+                expectations: Some(expectations),
+            });
         }
     }
 
@@ -725,19 +1523,11 @@ pub async fn save_expectations(
     let state = state.read().await;
 
     let current = get_current_module_state(&state, &module_path);
-    let exp_dir = expectations_dir(&state);
     let exp_file = expectations_file(&state, &module_path);
 
-    // Create expectations directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all(&exp_dir) {
-        return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": format!("Failed to create expectations directory: {}", e)
-        }));
-    }
-
-    // Write expectations file
+    // Write expectations file, creating its directory if it doesn't exist
     match serde_json::to_string_pretty(&current) {
-        Ok(json) => match std::fs::write(&exp_file, json) {
+        Ok(json) => match state.sink.write(&exp_file, &json) {
             Ok(_) => HttpResponse::Ok().json(SaveExpectationsResponse {
                 success: true,
                 module: module_path,
@@ -763,13 +1553,11 @@ pub async fn compare_expectations(
     let current = get_current_module_state(&state, &module_path);
     let exp_file = expectations_file(&state, &module_path);
 
-    let expected = if exp_file.exists() {
-        std::fs::read_to_string(&exp_file)
-            .ok()
-            .and_then(|content| serde_json::from_str::<ModuleExpectations>(&content).ok())
-    } else {
-        None
-    };
+    let expected = state
+        .files
+        .read(&exp_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ModuleExpectations>(&content).ok());
 
     let (ast_matches, diagnostics_match, generated_js_matches) = if let Some(ref exp) = expected {
         let ast_matches = current.ast == exp.ast;
@@ -792,3 +1580,219 @@ pub async fn compare_expectations(
         expected,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const BROKEN: &str = "module test.app\n\nimport test.data.Store\n\nblueprint Main {\n    with Store\n    a : i32 = unknown\n    b : i32 = missing\n    text { \"${a} ${b} ${count}\" }\n}\n";
+    const GOOD: &str = "module test.data\n\nbackend Store {\n    count: i32 = 0\n}\n";
+
+    fn built_project(name: &str) -> (PathBuf, ProjectState) {
+        let root = std::env::temp_dir().join(format!("frel-api-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("app.frel"), BROKEN).unwrap();
+        fs::write(root.join("data.frel"), GOOD).unwrap();
+        let mut state = ProjectState::new(root.clone(), root.join("build"));
+        compiler::full_build(&mut state);
+        (root, state)
+    }
+
+    #[test]
+    fn test_query_diagnostics() {
+        let (root, state) = built_project("query");
+
+        let all = query_diagnostics(&state, &DiagnosticsQuery::default());
+        assert_eq!(all.total, 2);
+        assert_eq!(all.error_count, 2);
+        assert!(all.diagnostics.iter().all(|d| d.diagnostic.file.ends_with("app.frel")));
+
+        let query = DiagnosticsQuery {
+            file: Some("data.frel".to_string()),
+            ..DiagnosticsQuery::default()
+        };
+        assert_eq!(query_diagnostics(&state, &query).total, 0);
+
+        let query = DiagnosticsQuery {
+            file: Some("app.frel".to_string()),
+            severity: Some("warning, error".to_string()),
+            code: Some("E0402,E0301".to_string()),
+            ..DiagnosticsQuery::default()
+        };
+        assert_eq!(query_diagnostics(&state, &query).total, 2);
+        let query = DiagnosticsQuery {
+            code: Some("E0402".to_string()),
+            ..DiagnosticsQuery::default()
+        };
+        assert_eq!(query_diagnostics(&state, &query).total, 0);
+
+        let query = DiagnosticsQuery {
+            offset: 1,
+            limit: Some(5),
+            ..DiagnosticsQuery::default()
+        };
+        let page = query_diagnostics(&state, &query);
+        assert_eq!(page.total, 2);
+        assert_eq!(page.diagnostics.len(), 1);
+        let message = &page.diagnostics[0].diagnostic.diagnostic.message;
+        assert_eq!(message, &all.diagnostics[1].diagnostic.diagnostic.message);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_overlay_path() {
+        let root = Path::new("/work/app");
+        assert_eq!(
+            overlay_path(root, "src/main.frel").unwrap(),
+            Path::new("/work/app/src/main.frel")
+        );
+        assert_eq!(
+            overlay_path(root, "/work/app/./src/../main.frel").unwrap(),
+            Path::new("/work/app/main.frel")
+        );
+        assert!(overlay_path(root, "../other/main.frel").is_err());
+        assert!(overlay_path(root, "/etc/passwd").is_err());
+        assert!(overlay_path(root, "/work/application/main.frel").is_err());
+    }
+
+    #[test]
+    fn test_build_file() {
+        let root = std::env::temp_dir().join(format!("frel-api-build-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("data.frel"), GOOD).unwrap();
+        let mut state = ProjectState::new(root.clone(), root.join("build"));
+        state.fingerprint = true;
+        compiler::full_build(&mut state);
+
+        let hashed = state.manifest.get(Path::new("test/data.js")).unwrap();
+        let file = build_file(&state, &hashed.display().to_string()).unwrap();
+        assert!(file.path.exists());
+        assert_eq!(file.cache_control, IMMUTABLE);
+        assert_eq!(file.content_type, "text/javascript; charset=utf-8");
+
+        let manifest = build_file(&state, "manifest.json").unwrap();
+        assert!(manifest.path.exists());
+        assert_eq!(manifest.cache_control, "no-cache");
+
+        assert_eq!(build_file(&state, "../app.frel"), None);
+        assert_eq!(build_file(&state, "/etc/passwd"), None);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_project_sarif() {
+        let (root, state) = built_project("sarif");
+
+        let diagnostics = matching_diagnostics(&state, &DiagnosticsQuery::default());
+        let log = compiler::project_sarif(&state, &diagnostics);
+        let run = &log.runs[0];
+        assert_eq!(run.tool.driver.name, "frel-server");
+        assert_eq!(run.results.len(), 2);
+        let location = &run.results[0].locations[0].physical_location;
+        assert_eq!(location.artifact_location.uri, "app.frel");
+        assert_eq!(location.artifact_location.uri_base_id.as_deref(), Some("SRCROOT"));
+        let start = location.region.as_ref().map(|r| r.start_line);
+        assert_eq!(start, diagnostics[0].start.map(|start| start.line));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_search_symbols() {
+        let (root, state) = built_project("symbols");
+        let search = |query: &str, kind: Option<&str>| {
+            let query = SymbolsQuery {
+                query: query.to_string(),
+                kind: kind.map(str::to_string),
+                limit: None,
+            };
+            search_symbols(&state, &query)
+        };
+
+        let found = search("store", None);
+        assert_eq!(found.total, 1);
+        let store = &found.symbols[0];
+        assert_eq!((store.name.as_str(), store.kind), ("Store", "backend"));
+        assert_eq!(store.module, "test.data");
+        assert_eq!(store.container, None);
+        assert!(store.file.as_ref().unwrap().ends_with("data.frel"));
+        assert_eq!(store.start.map(|start| start.line), Some(3));
+
+        // Characters in order, not necessarily adjacent
+        let found = search("cnt", Some("field"));
+        let names: Vec<_> = found.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["count"]);
+        assert_eq!(found.symbols[0].container.as_deref(), Some("Store"));
+        assert!(found.symbols[0].file.as_ref().unwrap().ends_with("data.frel"));
+
+        let found = search("", Some("blueprint,backend"));
+        let names: Vec<_> = found.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Main", "Store"]);
+
+        // Exact names before substrings; kinds of more than one word
+        let found = search("a", None);
+        let names: Vec<_> = found.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["a", "Main"]);
+        assert_eq!(search("a", Some("local_variable")).total, 1);
+
+        let query = SymbolsQuery {
+            limit: Some(1),
+            ..SymbolsQuery::default()
+        };
+        let found = search_symbols(&state, &query);
+        assert_eq!(found.symbols.len(), 1);
+        assert!(found.total > 1);
+
+        assert_eq!(search("xyz", None).total, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_summarize_diagnostics() {
+        let (root, state) = built_project("summary");
+
+        let summary = summarize_diagnostics(&state, &DiagnosticsQuery::default());
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.by_severity.get("error"), Some(&2));
+        assert_eq!(summary.by_code.get("E0301"), Some(&2));
+        assert_eq!(summary.files.len(), 1);
+        assert_eq!(summary.files[0].total, 2);
+
+        let query = DiagnosticsQuery {
+            severity: Some("warning".to_string()),
+            ..DiagnosticsQuery::default()
+        };
+        assert_eq!(summarize_diagnostics(&state, &query).total, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_build_etag() {
+        let (root, mut state) = built_project("etag");
+        let request = |value: &str| {
+            actix_web::test::TestRequest::default()
+                .insert_header((header::IF_NONE_MATCH, value))
+                .to_http_request()
+        };
+
+        let etag = build_etag(&state);
+        assert!(etag_matches(&request(&etag), &etag));
+        assert!(etag_matches(&request(&format!("\"other\", W/{}", etag)), &etag));
+        assert!(etag_matches(&request("*"), &etag));
+        assert!(!etag_matches(&request("\"other\""), &etag));
+        assert!(!etag_matches(&actix_web::test::TestRequest::default().to_http_request(), &etag));
+
+        // A rebuild changes it, even if nothing else did
+        compiler::handle_file_change(&mut state, &root.join("data.frel"));
+        assert_ne!(build_etag(&state), etag);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -3,15 +3,30 @@
 // Handles full builds and incremental rebuilds.
 
 use std::collections::HashSet;
-use std::fs;
-use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use frel_compiler_core::{analyze_module, build_signature, Module};
+use frel_compiler_core::edition::{ProjectConfig, CONFIG_FILE};
+use frel_compiler_core::ir::lower_with_registry;
+use frel_compiler_core::diagnostic::codes;
+use frel_compiler_core::{
+    analyze_module, ast, build_signature, save_signature, signature_file_path,
+    unused_theme_members, ArtifactKind, CompilerOutput, Diagnostic, Diagnostics, FileDiagnostic,
+    FileId, Module, ModuleAnalysisResult, ModuleSignature, RelatedInfo, SarifBuilder, SarifLog,
+    SignatureRegistry, Span, SourceProvider, SymbolKind, ThemeUsage, UnusedMember,
+};
+use frel_compiler_plugin_api::{
+    fingerprinted_path, run_passes, GenerateOptions, OutputFile, MANIFEST_FILE,
+};
 
+use crate::events::CompilationEvent;
+use crate::record;
 use crate::state::{
-    hash_content, hash_exports, AnalysisCacheEntry, FileState, ParseCacheEntry, ProjectState,
-    SignatureCacheEntry,
+    hash_ast, hash_content, hash_exports, hash_outputs, hash_signature, AnalysisCacheEntry,
+    FileState, ModuleArtifacts, ParseCacheEntry, ProjectState, RemovedMember,
+    SignatureCacheEntry, WatchSettings,
 };
 
 /// Result of a full build
@@ -19,6 +34,39 @@ pub struct BuildResult {
     pub duration: Duration,
     pub modules_built: usize,
     pub error_count: usize,
+    /// Cache hit/miss statistics for this build
+    pub cache: CacheStats,
+    /// Why the build was aborted, if it exceeded a resource limit
+    pub aborted: Option<String>,
+}
+
+/// Cache hit/miss counters collected during a build
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Files whose parse result was reused
+    pub parse_hits: usize,
+    /// Files that had to be (re)parsed
+    pub parse_misses: usize,
+    /// Modules whose signature was reused
+    pub signature_hits: usize,
+    /// Modules whose signature had to be (re)built
+    pub signature_misses: usize,
+    /// Modules whose analysis and generated code were reused
+    pub analysis_hits: usize,
+    /// Modules that had to be (re)analyzed
+    pub analysis_misses: usize,
+}
+
+impl CacheStats {
+    /// Total number of cache hits across all phases
+    pub fn hits(&self) -> usize {
+        self.parse_hits + self.signature_hits + self.analysis_hits
+    }
+
+    /// Total number of cache misses across all phases
+    pub fn misses(&self) -> usize {
+        self.parse_misses + self.signature_misses + self.analysis_misses
+    }
 }
 
 /// Result of an incremental rebuild
@@ -26,25 +74,87 @@ pub struct IncrementalResult {
     pub duration: Duration,
     pub modules_rebuilt: Vec<String>,
     pub error_count: usize,
+    /// Events describing what changed, in order
+    pub events: Vec<CompilationEvent>,
 }
 
 /// Perform a full build of the project
+///
+/// Results already in the caches are reused when their inputs are unchanged:
+/// files are only re-parsed when their content hash changes, signatures are
+/// only rebuilt when the module's ASTs change, and modules are only
+/// re-analyzed when their ASTs or their dependencies' signatures change.
 pub fn full_build(state: &mut ProjectState) -> BuildResult {
     let start = Instant::now();
+    state.build_id += 1;
+    let mut cache = CacheStats::default();
+    state.limit_diagnostics.remove(&state.root);
+
+    // 1. Discover all .frel files, including unsaved buffers not yet on disk
+    let mut files = discover_frel_files(state.files.as_ref(), &state.root);
+    files.retain(|path| !state.is_build_output(path));
+    for path in state.overlay_paths() {
+        if path.extension().is_some_and(|e| e == "frel") && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    // Files over the size limit are treated as missing; forget the size
+    // errors of files that are gone
+    let gone: Vec<PathBuf> = state
+        .limit_diagnostics
+        .keys()
+        .filter(|path| **path != state.root && state.source_size(path).is_err())
+        .cloned()
+        .collect();
+    for path in &gone {
+        state.limit_diagnostics.remove(path);
+    }
+    files.retain(|path| check_file_size(state, path));
 
-    // 1. Discover all .frel files
-    let files = discover_frel_files(&state.root);
+    // Drop files that no longer exist so stale ASTs don't leak into modules
+    let discovered: HashSet<&PathBuf> = files.iter().collect();
+    let removed: Vec<PathBuf> = state
+        .sources
+        .keys()
+        .filter(|path| !discovered.contains(path))
+        .cloned()
+        .collect();
+    for path in &removed {
+        state.sources.remove(path);
+        state.source_maps.remove(path);
+        state.parse_cache.remove(path);
+        state.module_index.remove_file(path);
+    }
 
     // 2. Read and parse all files
     for path in &files {
-        if let Ok(content) = fs::read_to_string(path) {
+        if let Ok(content) = state.read_source(path) {
             let hash = hash_content(&content);
+            state.load_source_map(path);
+
+            let cached = state
+                .parse_cache
+                .get(path)
+                .is_some_and(|entry| entry.content_hash == hash);
+            if cached {
+                cache.parse_hits += 1;
+                continue;
+            }
+            cache.parse_misses += 1;
+
             state.sources.insert(path.clone(), FileState::new(content.clone()));
 
-            let parse_result = frel_compiler_core::parse_file_with_path(
+            let file_id = state.file_id(path);
+            let mut parse_result = frel_compiler_core::parse_file_in_module(
                 &content,
                 &path.display().to_string(),
+                state.edition,
+                state.features.clone(),
+                file_id,
             );
+            parse_result.diagnostics.apply_lints(&state.lints);
 
             if let Some(ref file) = parse_result.file {
                 state.module_index.update_file(path, &file.module);
@@ -54,45 +164,72 @@ pub fn full_build(state: &mut ProjectState) -> BuildResult {
                         file: file.clone(),
                         diagnostics: parse_result.diagnostics.clone(),
                         content_hash: hash,
+                        ast_hash: hash_ast(file),
                     },
                 );
 
-                // Extract imports for dependency graph
-                // For each import, get the module path (either the full path for whole-module
-                // imports, or the prefix for single-declaration imports)
-                let imports: Vec<String> = file
-                    .imports
-                    .iter()
-                    .filter_map(|imp| {
-                        // If it could be a module path, use as-is
-                        // Otherwise split to get the module part
-                        if let Some((module, _)) = imp.path.rsplit_once('.') {
-                            Some(module.to_string())
-                        } else {
-                            // Single component - could be a whole-module import
-                            Some(imp.path.clone())
-                        }
-                    })
-                    .collect();
-                state.dependencies.update_module_deps(&file.module, &imports);
+                state
+                    .dependencies
+                    .update_module_deps(&file.module, &import_modules(file));
             }
         }
     }
 
-    // 3. Build signatures for all modules (Phase 1)
+    update_theme_usage(state);
+
+    // Forget modules that lost all of their files
     let modules: Vec<String> = state.module_index.all_modules().iter().map(|s| s.to_string()).collect();
+    let stale: Vec<String> = state
+        .signature_cache
+        .keys()
+        .chain(state.analysis_cache.keys())
+        .filter(|module| !modules.contains(module))
+        .cloned()
+        .collect();
+    for module in &stale {
+        drop_module(state, module);
+    }
+    state.publish(CompilationEvent::BuildStarted {
+        modules: modules.clone(),
+    });
 
+    if let Some(reason) = memory_overrun(state) {
+        return abort_full_build(state, start, modules.len(), cache, reason);
+    }
+
+    // 3. Build signatures for all modules (Phase 1)
     for module_path in &modules {
+        if let Some(reason) = build_overrun(state, start) {
+            return abort_full_build(state, start, modules.len(), cache, reason);
+        }
+        let source_hash = module_source_hash(state, module_path);
+
+        if let Some(cached) = state.signature_cache.get(module_path) {
+            if cached.source_hash == source_hash {
+                cache.signature_hits += 1;
+                if !state.registry.contains(module_path) {
+                    state.registry.register(cached.result.signature.clone());
+                }
+                continue;
+            }
+        }
+
         if let Some(module_obj) = build_module_object(state, module_path) {
+            cache.signature_misses += 1;
             let result = build_signature(&module_obj);
+            track_removed_members(state, module_path, &result.signature);
             let exports_hash = hash_exports(&result.signature);
+            let signature_hash = hash_signature(&result.signature);
 
+            persist_signature(state, &result.signature);
             state.registry.register(result.signature.clone());
             state.signature_cache.insert(
                 module_path.clone(),
                 SignatureCacheEntry {
                     result,
                     exports_hash,
+                    signature_hash,
+                    source_hash,
                     generation: state.generation,
                 },
             );
@@ -101,39 +238,46 @@ pub fn full_build(state: &mut ProjectState) -> BuildResult {
 
     // 4. Analyze all modules (Phase 2)
     for module_path in &modules {
-        if let Some(module_obj) = build_module_object(state, module_path) {
-            let result = analyze_module(&module_obj, &state.registry);
-
-            // Generate JavaScript if no errors
-            let generated_js = if !result.diagnostics.has_errors() {
-                // Get the first file's AST for codegen
-                if let Some(file_path) = state.module_index.files_for_module(module_path).first() {
-                    if let Some(cache_entry) = state.parse_cache.get(file_path) {
-                        frel_compiler_plugin_javascript::generate(&cache_entry.file)
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
+        if let Some(reason) = build_overrun(state, start) {
+            return abort_full_build(state, start, modules.len(), cache, reason);
+        }
+        let input_hash = module_input_hash(state, module_path);
 
-            // Write output if we have generated code
-            if !generated_js.is_empty() {
-                let output_path = module_output_path(&state.build_dir, module_path);
-                if let Some(parent) = output_path.parent() {
-                    let _ = fs::create_dir_all(parent);
+        if let Some(cached) = state.analysis_cache.get(module_path) {
+            if cached.input_hash == input_hash {
+                cache.analysis_hits += 1;
+                // Restore the artifacts if one was removed from the build directory
+                if cached.outputs.iter().any(|f| !state.files.is_file(&state.output_path(f))) {
+                    let outputs = cached.outputs.clone();
+                    emit_outputs(state, &outputs);
                 }
-                let _ = fs::write(&output_path, &generated_js);
+                continue;
+            }
+        }
+
+        if let Some(module_obj) = build_module_object(state, module_path) {
+            cache.analysis_misses += 1;
+            let mut result = analyze_module(&module_obj, &state.registry);
+            let findings = run_passes(&state.passes, &module_obj, &result, &state.plugin_context());
+            result.diagnostics.merge(findings);
+            explain_removed_members(state, &module_obj, &mut result.diagnostics);
+            result.diagnostics.apply_lints(&state.lints);
+
+            // Generate code if no errors
+            let (outputs, stripped) = generate_outputs(state, module_path, &mut result);
+            emit_outputs(state, &outputs);
+            store_artifacts(state, module_path, &outputs);
+            state.stripped_styles.insert(module_path.clone(), stripped);
+            for event in module_events(module_path, &result) {
+                state.publish(event);
             }
 
             state.analysis_cache.insert(
                 module_path.clone(),
                 AnalysisCacheEntry {
                     result,
-                    generated_js,
+                    outputs,
+                    input_hash,
                     generation: state.generation,
                 },
             );
@@ -141,133 +285,376 @@ pub fn full_build(state: &mut ProjectState) -> BuildResult {
     }
 
     state.initialized = true;
+    record::record_build(state);
     let error_count = state.error_count();
+    let duration = start.elapsed();
+    state.publish(CompilationEvent::BuildCompleted {
+        duration_ms: duration.as_millis() as u64,
+        modules_built: modules.len(),
+        error_count,
+    });
 
     BuildResult {
-        duration: start.elapsed(),
+        duration,
         modules_built: modules.len(),
         error_count,
+        cache,
+        aborted: None,
     }
 }
 
-/// Handle a file change with incremental rebuild
-pub fn handle_file_change(state: &mut ProjectState, path: &Path) -> IncrementalResult {
-    let start = Instant::now();
-    state.generation += 1;
+/// End a full build that exceeded a resource limit
+fn abort_full_build(
+    state: &mut ProjectState,
+    start: Instant,
+    modules_built: usize,
+    cache: CacheStats,
+    reason: String,
+) -> BuildResult {
+    abort_build(state, &reason);
+    state.initialized = true;
+    record::record_build(state);
+    state.publish(CompilationEvent::BuildAborted {
+        reason: reason.clone(),
+    });
+    BuildResult {
+        duration: start.elapsed(),
+        modules_built,
+        error_count: state.error_count(),
+        cache,
+        aborted: Some(reason),
+    }
+}
 
-    let mut modules_to_rebuild: HashSet<String> = HashSet::new();
+/// Record why a build was aborted, as an error on the project root
+///
+/// Modules the build didn't reach keep their previous results until the
+/// next build.
+fn abort_build(state: &mut ProjectState, reason: &str) {
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.error(format!("build aborted: {}", reason), Span::default());
+    state.limit_diagnostics.insert(state.root.clone(), diagnostics);
+}
 
-    // 1. Read new content
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => {
-            // File deleted - remove from state
-            let path_buf = path.to_path_buf();
-            if let Some(module) = state.module_index.module_for_file(&path_buf).map(String::from) {
-                modules_to_rebuild.insert(module.clone());
-                state.dependencies.remove_module(&module);
-            }
-            state.sources.remove(&path_buf);
-            state.parse_cache.remove(&path_buf);
-            state.module_index.remove_file(&path_buf);
+/// Why a build started at `start` must stop, if it ran over the time limit
+fn build_overrun(state: &ProjectState, start: Instant) -> Option<String> {
+    let limit = state.limits.max_build_duration;
+    (start.elapsed() > limit).then(|| format!("build ran over the time limit of {:?}", limit))
+}
 
-            return IncrementalResult {
-                duration: start.elapsed(),
-                modules_rebuilt: modules_to_rebuild.into_iter().collect(),
-                error_count: state.error_count(),
-            };
+/// Why a build must not analyze the sources, if they would take too much memory
+fn memory_overrun(state: &ProjectState) -> Option<String> {
+    let estimate = state.memory_estimate();
+    let limit = state.limits.max_memory;
+    (estimate > limit).then(|| {
+        format!(
+            "estimated memory use of {} bytes is over the limit of {} bytes",
+            estimate, limit
+        )
+    })
+}
+
+/// Whether a source file is within the size limit
+///
+/// An oversized file is reported in `state.limit_diagnostics` and treated as
+/// missing, so its module is built without it.
+fn check_file_size(state: &mut ProjectState, path: &Path) -> bool {
+    let limit = state.limits.max_file_size;
+    match state.source_size(path) {
+        Ok(size) if size > limit => {
+            let mut diagnostics = Diagnostics::new();
+            let message = format!(
+                "file is {} bytes, over the limit of {} bytes; it is not compiled",
+                size, limit
+            );
+            diagnostics.error(message, Span::default());
+            state.limit_diagnostics.insert(path.to_path_buf(), diagnostics);
+            false
         }
-    };
+        _ => {
+            state.limit_diagnostics.remove(path);
+            true
+        }
+    }
+}
 
-    let path_buf = path.to_path_buf();
-    let new_hash = hash_content(&content);
-
-    // 2. Quick exit if content unchanged
-    if let Some(file_state) = state.sources.get(&path_buf) {
-        if file_state.content_hash == new_hash {
-            return IncrementalResult {
-                duration: start.elapsed(),
-                modules_rebuilt: vec![],
-                error_count: state.error_count(),
-            };
+/// Machine-readable output of the project's current state: the files
+/// generated for every module and all parse, analysis and limit diagnostics
+///
+/// `timings` is left empty; callers fill it in for the build they report.
+pub fn project_output(state: &ProjectState) -> CompilerOutput {
+    let mut output = CompilerOutput::new();
+
+    let mut paths: Vec<&PathBuf> = state.parse_cache.keys().collect();
+    paths.sort();
+    for path in paths {
+        add_file_diagnostics(&mut output, state, path, &state.parse_cache[path].diagnostics);
+    }
+
+    let mut modules: Vec<&String> = state.analysis_cache.keys().collect();
+    modules.sort();
+    for module in modules {
+        let entry = &state.analysis_cache[module];
+        for (file, diagnostics) in module_diagnostics(state, module, &entry.result.diagnostics) {
+            add_file_diagnostics(&mut output, state, file, &diagnostics);
+        }
+        for file in &entry.outputs {
+            let path = state.output_path(file).display().to_string();
+            output.add_artifact(path, ArtifactKind::Code, Some(module));
         }
     }
+    if state.fingerprint && !state.manifest.is_empty() {
+        let path = state.build_dir.join(MANIFEST_FILE).display().to_string();
+        output.add_artifact(path, ArtifactKind::Manifest, None);
+    }
 
-    // 3. Update source state
-    state.sources.insert(path_buf.clone(), FileState::new(content.clone()));
+    let mut paths: Vec<&PathBuf> = state.limit_diagnostics.keys().collect();
+    paths.sort();
+    for path in paths {
+        let file = path.display().to_string();
+        output.add_diagnostics(&file, None, &state.limit_diagnostics[path]);
+    }
 
-    // 4. Parse the changed file
-    let parse_result = frel_compiler_core::parse_file_with_path(
-        &content,
-        &path.display().to_string(),
-    );
+    if !state.config_diagnostics.is_empty() {
+        let path = state.root.join(CONFIG_FILE);
+        let source = state.files.read(&path).ok();
+        let file = path.display().to_string();
+        output.add_diagnostics(&file, source.as_deref(), &state.config_diagnostics);
+    }
 
-    let old_module = state.module_index.module_for_file(&path_buf).map(String::from);
+    output
+}
 
-    if let Some(ref file) = parse_result.file {
-        let new_module = file.module.clone();
+/// Diagnostics of a module's analysis by the file their spans point into,
+/// with the spans relative to that file alone
+pub fn module_diagnostics<'a>(
+    state: &'a ProjectState,
+    module: &str,
+    diagnostics: &Diagnostics,
+) -> Vec<(&'a PathBuf, Diagnostics)> {
+    let path = |file: FileId| state.module_file(module, file).map(|path| path.display().to_string());
+    let mut files: Vec<(&PathBuf, Diagnostics)> = Vec::new();
+    for (file, diagnostics) in diagnostics.clone().split_by_file(path) {
+        let Some(path) = state.module_file(module, file) else {
+            continue;
+        };
+        match files.iter_mut().find(|(known, _)| *known == path) {
+            Some((_, known)) => known.merge(diagnostics),
+            None => files.push((path, diagnostics)),
+        }
+    }
+    files
+}
 
-        // Update module index
-        state.module_index.update_file(&path_buf, &new_module);
+/// Add the diagnostics of a source file to `output`, moving those its source
+/// map covers into their templates
+fn add_file_diagnostics(
+    output: &mut CompilerOutput,
+    state: &ProjectState,
+    path: &Path,
+    diagnostics: &Diagnostics,
+) {
+    let file = path.display().to_string();
+    let Some(source) = state.sources.get(path).map(|s| s.content.as_str()) else {
+        output.add_diagnostics(&file, None, diagnostics);
+        return;
+    };
+    let Some(map) = state.source_maps.get(path) else {
+        output.add_diagnostics(&file, Some(source), diagnostics);
+        return;
+    };
+    let read = |template: &str| state.files.read(Path::new(template)).ok();
+    let (unmapped, remapped) = map.remap_diagnostics(diagnostics, source, read);
+    output.add_diagnostics(&file, Some(source), &unmapped);
+    for template in remapped {
+        output.add_diagnostics(&template.file, Some(&template.source), &template.diagnostics);
+    }
+}
 
-        // Update parse cache
-        state.parse_cache.insert(
-            path_buf.clone(),
-            ParseCacheEntry {
-                file: file.clone(),
-                diagnostics: parse_result.diagnostics.clone(),
-                content_hash: new_hash,
-            },
-        );
+/// The given project diagnostics as a SARIF log, with paths relative to the
+/// project root
+///
+/// Sources are taken from the project's files, overlays included, so
+/// regions match what was compiled; remapped templates are read as they are.
+pub fn project_sarif(state: &ProjectState, diagnostics: &[FileDiagnostic]) -> SarifLog {
+    let load = |file: &str| state.read_source(Path::new(file)).ok();
+    let root = state.root.display().to_string();
+    let mut sarif =
+        SarifBuilder::new(record::TOOL, frel_compiler_core::VERSION, &load).with_root(root);
+    for diagnostic in diagnostics {
+        sarif.add(&diagnostic.file, &diagnostic.diagnostic);
+    }
+    sarif.finish()
+}
 
-        // Update dependency graph
-        let imports: Vec<String> = file
-            .imports
-            .iter()
-            .filter_map(|imp| {
-                if let Some((module, _)) = imp.path.rsplit_once('.') {
-                    Some(module.to_string())
-                } else {
-                    Some(imp.path.clone())
-                }
-            })
-            .collect();
-        state.dependencies.update_module_deps(&new_module, &imports);
+/// Fill the registry with the signatures saved by an earlier run
+///
+/// Signatures of modules with sources are replaced as the modules are
+/// built; the others (e.g. copied from a library build) stay importable.
+/// Returns the diagnostics of the files that could not be loaded.
+pub fn load_signatures(state: &mut ProjectState) -> Vec<(PathBuf, Diagnostics)> {
+    let result = SignatureRegistry::load_dir(state.files.as_ref(), &state.signature_dir());
+    state.registry = Arc::new(result.registry);
+    result.errors
+}
 
-        // Mark this module for rebuild
-        modules_to_rebuild.insert(new_module.clone());
+/// Outcome of `reload_config`
+pub enum ConfigReload {
+    /// The configuration was applied and the project rebuilt
+    Reloaded {
+        /// Whether the edition changed, so every file was parsed again
+        edition_changed: bool,
+        build: BuildResult,
+    },
+    /// `frel.toml` is invalid; nothing was rebuilt and its errors are in
+    /// `state.config_diagnostics`
+    Rejected { error_count: usize },
+}
 
-        // If module changed, also rebuild old module
-        if let Some(old) = old_module {
-            if old != new_module {
-                modules_to_rebuild.insert(old);
-            }
+/// Re-read `frel.toml` and rebuild the project without restarting the server
+///
+/// The rebuild is a full build, so the module set is scanned again: files
+/// that appeared or went away are picked up and stale modules dropped.
+/// When the edition changes, the parse cache is cleared as the edition
+/// decides which syntax each file may use; so it is when the lint levels
+/// change, as parse diagnostics are cached with their levels applied. The
+/// watch settings are combined again with `.gitignore` and the command line,
+/// and so are the lint levels with the command line. An invalid configuration
+/// is reported as errors on `frel.toml` and the previous one stays in effect.
+pub fn reload_config(state: &mut ProjectState) -> ConfigReload {
+    let files = state.files.as_ref();
+    let loaded = ProjectConfig::load(files, &state.root).and_then(|config| {
+        let watch = WatchSettings::load(files, &state.root, &config.watch, &state.watch_flags)?;
+        Ok((config, watch))
+    });
+    let (config, watch) = match loaded {
+        Ok(loaded) => loaded,
+        Err(diagnostics) => {
+            let error_count = diagnostics.error_count();
+            state.config_diagnostics = diagnostics;
+            state.build_id += 1;
+            state.publish(CompilationEvent::ConfigRejected { error_count });
+            return ConfigReload::Rejected { error_count };
         }
+    };
+    state.config_diagnostics = Diagnostics::new();
+    state.watch = watch;
+    state.generation += 1;
+
+    let mut lints = config.lints.clone();
+    lints.extend(&state.lint_flags);
+    let lints_changed = lints != state.lints;
+    state.lints = lints;
+
+    let edition_changed = config.edition() != state.edition;
+    if edition_changed {
+        state.edition = config.edition();
     }
+    if edition_changed || lints_changed {
+        state.parse_cache.clear();
+    }
+    state.publish(CompilationEvent::ConfigReloaded {
+        edition: state.edition.to_string(),
+    });
+
+    ConfigReload::Reloaded {
+        edition_changed,
+        build: full_build(state),
+    }
+}
+
+/// Handle a file change with incremental rebuild
+pub fn handle_file_change(state: &mut ProjectState, path: &Path) -> IncrementalResult {
+    handle_file_changes(state, &[path.to_path_buf()])
+}
+
+/// Handle changes of several files with a single incremental rebuild
+///
+/// The watcher passes a burst of saves at once, so a module depending on
+/// more than one of the files is rebuilt once.
+pub fn handle_file_changes(state: &mut ProjectState, paths: &[PathBuf]) -> IncrementalResult {
+    let start = Instant::now();
+    state.generation += 1;
+    state.build_id += 1;
+
+    let mut modules_to_rebuild: HashSet<String> = HashSet::new();
+    let mut events = Vec::new();
+    state.limit_diagnostics.remove(&state.root);
+
+    let changed: Vec<&PathBuf> = paths
+        .iter()
+        .filter(|path| apply_file_change(state, path, &mut modules_to_rebuild, &mut events))
+        .collect();
+    if changed.is_empty() {
+        return IncrementalResult {
+            duration: start.elapsed(),
+            modules_rebuilt: vec![],
+            error_count: state.error_count(),
+            events,
+        };
+    }
+
+    // Which theme members are stripped depends on every module
+    if update_theme_usage(state) && state.strip_unused_styles {
+        modules_to_rebuild.extend(theme_modules(state));
+    }
+
+    // Drop modules left without files; their importers now report an
+    // unresolved import at the import site
+    let orphaned: Vec<String> = modules_to_rebuild
+        .iter()
+        .filter(|module| state.module_index.files_for_module(module).is_empty())
+        .cloned()
+        .collect();
+    for module in orphaned {
+        modules_to_rebuild.remove(&module);
+        modules_to_rebuild.extend(state.dependencies.get_transitive_importers(&module));
+        drop_module(state, &module);
+        emit(state, &mut events, CompilationEvent::ModuleRemoved { module });
+    }
+
+    let mut started: Vec<String> = modules_to_rebuild.iter().cloned().collect();
+    started.sort();
+    emit(state, &mut events, CompilationEvent::BuildStarted { modules: started });
 
     // 5. Rebuild signatures for affected modules
+    let members_hash = project_members_hash(state);
     let mut exports_changed: HashSet<String> = HashSet::new();
+    let mut aborted = memory_overrun(state);
 
     for module_path in &modules_to_rebuild {
+        aborted = aborted.or_else(|| build_overrun(state, start));
+        if aborted.is_some() {
+            break;
+        }
         if let Some(module_obj) = build_module_object(state, module_path) {
             let result = build_signature(&module_obj);
+            track_removed_members(state, module_path, &result.signature);
             let new_exports_hash = hash_exports(&result.signature);
+            let signature_hash = hash_signature(&result.signature);
+            let source_hash = module_source_hash(state, module_path);
 
-            // Check if exports changed
+            // Check if exports or their members changed; a blueprint `with` an
+            // imported backend sees its members
             if let Some(cached) = state.signature_cache.get(module_path) {
-                if cached.exports_hash != new_exports_hash {
+                if cached.exports_hash != new_exports_hash
+                    || cached.signature_hash != signature_hash
+                {
                     exports_changed.insert(module_path.clone());
                 }
             } else {
                 exports_changed.insert(module_path.clone());
             }
 
+            persist_signature(state, &result.signature);
             state.registry.register(result.signature.clone());
             state.signature_cache.insert(
                 module_path.clone(),
                 SignatureCacheEntry {
                     result,
                     exports_hash: new_exports_hash,
+                    signature_hash,
+                    source_hash,
                     generation: state.generation,
                 },
             );
@@ -280,63 +667,321 @@ pub fn handle_file_change(state: &mut ProjectState, path: &Path) -> IncrementalR
         modules_to_rebuild.extend(importers);
     }
 
+    // Provided context values and shortcuts reach modules that don't import
+    // the module declaring them
+    if aborted.is_none() && project_members_hash(state) != members_hash {
+        modules_to_rebuild.extend(state.module_index.all_modules().iter().map(|s| s.to_string()));
+    }
+
     // 7. Re-analyze affected modules
     for module_path in &modules_to_rebuild {
+        aborted = aborted.or_else(|| build_overrun(state, start));
+        if aborted.is_some() {
+            break;
+        }
         if let Some(module_obj) = build_module_object(state, module_path) {
-            let result = analyze_module(&module_obj, &state.registry);
-
-            // Generate JavaScript if no errors
-            let generated_js = if !result.diagnostics.has_errors() {
-                if let Some(file_path) = state.module_index.files_for_module(module_path).first() {
-                    if let Some(cache_entry) = state.parse_cache.get(file_path) {
-                        frel_compiler_plugin_javascript::generate(&cache_entry.file)
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
+            let input_hash = module_input_hash(state, module_path);
+            let mut result = analyze_module(&module_obj, &state.registry);
+            let findings = run_passes(&state.passes, &module_obj, &result, &state.plugin_context());
+            result.diagnostics.merge(findings);
+            explain_removed_members(state, &module_obj, &mut result.diagnostics);
+            result.diagnostics.apply_lints(&state.lints);
 
-            // Write output
-            if !generated_js.is_empty() {
-                let output_path = module_output_path(&state.build_dir, module_path);
-                if let Some(parent) = output_path.parent() {
-                    let _ = fs::create_dir_all(parent);
-                }
-                let _ = fs::write(&output_path, &generated_js);
+            // Generate code if no errors
+            let (outputs, stripped) = generate_outputs(state, module_path, &mut result);
+            emit_outputs(state, &outputs);
+            store_artifacts(state, module_path, &outputs);
+            state.stripped_styles.insert(module_path.clone(), stripped);
+            for event in module_events(module_path, &result) {
+                emit(state, &mut events, event);
             }
 
             state.analysis_cache.insert(
                 module_path.clone(),
                 AnalysisCacheEntry {
                     result,
-                    generated_js,
+                    outputs,
+                    input_hash,
                     generation: state.generation,
                 },
             );
         }
     }
 
+    if let Some(reason) = aborted.clone() {
+        abort_build(state, &reason);
+    }
+    for path in changed {
+        record::record_file_change(state, path);
+    }
+
     let error_count = state.error_count();
+    let duration = start.elapsed();
+    let finished = match aborted {
+        Some(reason) => CompilationEvent::BuildAborted { reason },
+        None => CompilationEvent::BuildCompleted {
+            duration_ms: duration.as_millis() as u64,
+            modules_built: modules_to_rebuild.len(),
+            error_count,
+        },
+    };
+    emit(state, &mut events, finished);
 
     IncrementalResult {
-        duration: start.elapsed(),
+        duration,
         modules_rebuilt: modules_to_rebuild.into_iter().collect(),
         error_count,
+        events,
     }
 }
 
-/// Discover all .frel files in a directory
-pub fn discover_frel_files(root: &Path) -> Vec<std::path::PathBuf> {
-    let pattern = root.join("**/*.frel");
-    let pattern_str = pattern.display().to_string();
+/// Update the sources, parse cache and module index for a changed file,
+/// adding the modules to rebuild
+///
+/// Returns false when there is nothing to rebuild: the content is unchanged,
+/// or a file that belonged to no module went away.
+fn apply_file_change(
+    state: &mut ProjectState,
+    path: &Path,
+    modules_to_rebuild: &mut HashSet<String>,
+    events: &mut Vec<CompilationEvent>,
+) -> bool {
+    let path_buf = path.to_path_buf();
+
+    // 1. Read new content (an overlay takes precedence over the disk); a
+    // file over the size limit is handled like a removed one
+    let content = if check_file_size(state, path) {
+        state.read_source(path)
+    } else {
+        Err(std::io::Error::other("over the file size limit"))
+    };
+    match content {
+        Ok(content) => {
+            let new_hash = hash_content(&content);
+            state.load_source_map(path);
+
+            // 2. Quick exit if content unchanged
+            if let Some(file_state) = state.sources.get(&path_buf) {
+                if file_state.content_hash == new_hash {
+                    return false;
+                }
+            }
+
+            let event = CompilationEvent::FileChanged {
+                path: path.display().to_string(),
+            };
+            emit(state, events, event);
+
+            // 3. Update source state
+            state.sources.insert(path_buf.clone(), FileState::new(content.clone()));
+
+            // 4. Parse the changed file
+            let file_id = state.file_id(&path_buf);
+            let mut parse_result = frel_compiler_core::parse_file_in_module(
+                &content,
+                &path.display().to_string(),
+                state.edition,
+                state.features.clone(),
+                file_id,
+            );
+            parse_result.diagnostics.apply_lints(&state.lints);
+
+            let old_module = state.module_index.module_for_file(&path_buf).map(String::from);
+
+            if let Some(ref file) = parse_result.file {
+                let new_module = file.module.clone();
+
+                // Update module index
+                state.module_index.update_file(&path_buf, &new_module);
+
+                // Update parse cache
+                state.parse_cache.insert(
+                    path_buf.clone(),
+                    ParseCacheEntry {
+                        file: file.clone(),
+                        diagnostics: parse_result.diagnostics.clone(),
+                        content_hash: new_hash,
+                        ast_hash: hash_ast(file),
+                    },
+                );
+
+                // Update dependency graph
+                state
+                    .dependencies
+                    .update_module_deps(&new_module, &import_modules(file));
+
+                // Mark this module for rebuild
+                modules_to_rebuild.insert(new_module.clone());
 
-    glob::glob(&pattern_str)
-        .map(|paths| paths.filter_map(Result::ok).collect())
+                // If module changed, also rebuild old module
+                if let Some(old) = old_module {
+                    if old != new_module {
+                        modules_to_rebuild.insert(old);
+                    }
+                }
+            }
+        }
+        Err(_) => {
+            // File deleted or renamed away - remove it from state
+            let Some(module) = state.module_index.module_for_file(&path_buf).map(String::from)
+            else {
+                state.sources.remove(&path_buf);
+                state.source_maps.remove(&path_buf);
+                state.parse_cache.remove(&path_buf);
+                return false;
+            };
+
+            state.sources.remove(&path_buf);
+            state.source_maps.remove(&path_buf);
+            state.parse_cache.remove(&path_buf);
+            state.module_index.remove_file(&path_buf);
+            let event = CompilationEvent::FileRemoved {
+                path: path.display().to_string(),
+            };
+            emit(state, events, event);
+            modules_to_rebuild.insert(module);
+        }
+    }
+    true
+}
+
+/// Publish an event of an incremental rebuild and keep it for its result
+fn emit(state: &ProjectState, events: &mut Vec<CompilationEvent>, event: CompilationEvent) {
+    state.publish(event.clone());
+    events.push(event);
+}
+
+/// Events announcing a module that was just analyzed
+fn module_events(module: &str, result: &ModuleAnalysisResult) -> [CompilationEvent; 2] {
+    [
+        CompilationEvent::ModuleUpdated {
+            module: module.to_string(),
+            has_errors: result.diagnostics.has_errors(),
+        },
+        CompilationEvent::DiagnosticsUpdated {
+            module: module.to_string(),
+            error_count: result.diagnostics.error_count(),
+            warning_count: result.diagnostics.warning_count(),
+        },
+    ]
+}
+
+/// Discover all .frel files in a directory, sorted
+pub fn discover_frel_files(files: &dyn SourceProvider, root: &Path) -> Vec<PathBuf> {
+    files
+        .files(root)
         .unwrap_or_default()
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|e| e == "frel"))
+        .collect()
+}
+
+/// Get the module paths a file imports from
+///
+/// For single-declaration imports the last path segment is the declaration
+/// name; whole-module imports already hold the module path.
+fn import_modules(file: &ast::File) -> Vec<String> {
+    file.imports
+        .iter()
+        .map(|imp| {
+            if imp.import_all {
+                imp.path.clone()
+            } else if let Some((module, _)) = imp.path.rsplit_once('.') {
+                module.to_string()
+            } else {
+                imp.path.clone()
+            }
+        })
+        .collect()
+}
+
+/// Hash the ASTs of all files contributing to a module
+fn module_source_hash(state: &ProjectState, module_path: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module_path.hash(&mut hasher);
+    for path in state.module_index.files_for_module(module_path) {
+        path.hash(&mut hasher);
+        state.parse_cache.get(path).map(|e| e.ast_hash).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Hash everything a module's analysis depends on: its own ASTs, the
+/// signatures of the modules it imports from, and the context values and
+/// shortcuts of the project
+fn module_input_hash(state: &ProjectState, module_path: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module_source_hash(state, module_path).hash(&mut hasher);
+
+    let mut imports: Vec<String> = state.dependencies.get_imports(module_path).into_iter().collect();
+    imports.sort();
+    for import in &imports {
+        import.hash(&mut hasher);
+        state
+            .signature_cache
+            .get(import)
+            .map(|e| e.signature_hash)
+            .hash(&mut hasher);
+    }
+
+    // Consumed context values may be provided by any module, and shortcuts
+    // conflict with those of any module
+    project_members_hash(state).hash(&mut hasher);
+
+    // Lint levels decide which diagnostics are kept and at what severity
+    state.lints.hash(&mut hasher);
+
+    // The generated code of a theme module depends on what the project reads
+    if state.strip_unused_styles && theme_modules(state).contains(module_path) {
+        state.theme_usage.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Hash the context values provided and the shortcuts declared by the
+/// modules of the project, which the analysis of every module checks its
+/// `consume` statements and shortcuts against
+fn project_members_hash(state: &ProjectState) -> u64 {
+    let mut modules: Vec<&String> = state.signature_cache.keys().collect();
+    modules.sort();
+    let mut hasher = DefaultHasher::new();
+    for module in modules {
+        module.hash(&mut hasher);
+        let signature = &state.signature_cache[module].result.signature;
+        signature.contexts.hash(&mut hasher);
+        signature.shortcuts.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Recompute the theme member names read in the project's sources,
+/// returning whether they changed
+fn update_theme_usage(state: &mut ProjectState) -> bool {
+    let mut usage = ThemeUsage::new();
+    for (path, entry) in &state.parse_cache {
+        if let Some(source) = state.sources.get(path) {
+            usage.add_source(&source.content, &entry.file);
+        }
+    }
+    let changed = usage != state.theme_usage;
+    state.theme_usage = usage;
+    changed
+}
+
+/// Modules declaring a theme
+fn theme_modules(state: &ProjectState) -> HashSet<String> {
+    state
+        .parse_cache
+        .values()
+        .filter(|entry| {
+            entry
+                .file
+                .declarations
+                .iter()
+                .any(|decl| matches!(decl, ast::TopLevelDecl::Theme(_)))
+        })
+        .map(|entry| entry.file.module.clone())
+        .collect()
 }
 
 /// Build a Module object from cached ASTs
@@ -355,13 +1000,1071 @@ fn build_module_object(state: &ProjectState, module_path: &str) -> Option<Module
     Some(Module::from_files(module_path.to_string(), files))
 }
 
-/// Compute output path for a module
-fn module_output_path(build_dir: &Path, module_path: &str) -> std::path::PathBuf {
-    let parts: Vec<&str> = module_path.split('.').collect();
-    let mut path = build_dir.to_path_buf();
-    for part in parts {
-        path.push(part);
+/// Remove everything derived from a module that no longer has source files
+///
+/// The module's diagnostics, signature and generated artifact go away, but
+/// its importers keep their dependency edges so they are rebuilt if the
+/// module reappears (e.g. the second half of a rename).
+fn drop_module(state: &mut ProjectState, module_path: &str) {
+    state.signature_cache.remove(module_path);
+    state.removed_members.remove(module_path);
+    let analysis = state.analysis_cache.remove(module_path);
+    state.artifacts.remove(module_path);
+    state.stripped_styles.remove(module_path);
+    state.registry.unregister(module_path);
+    state.dependencies.remove_imports(module_path);
+    let _ = state.sink.remove(&signature_file_path(&state.signature_dir(), module_path));
+
+    for file in analysis.iter().flat_map(|entry| &entry.outputs) {
+        let output_path = state.output_path(file);
+        let _ = state.sink.remove(&output_path);
+        state.emitted.remove(&output_path);
+        if state.manifest.remove(&file.path).is_some() {
+            write_manifest(state);
+        }
+    }
+}
+
+/// Remember the backend members a module's new signature lost against the
+/// cached one, and forget those it has again
+///
+/// A backend that is gone entirely is forgotten too: importers report it at
+/// their `with`.
+fn track_removed_members(state: &mut ProjectState, module_path: &str, signature: &ModuleSignature) {
+    let mut removed = state.removed_members.remove(module_path).unwrap_or_default();
+    if let Some(cached) = state.signature_cache.get(module_path) {
+        for member in backend_members(&cached.result.signature) {
+            if !removed.contains(&member) {
+                removed.push(member);
+            }
+        }
+    }
+
+    let current = backend_members(signature);
+    removed.retain(|member| {
+        !current.contains(member)
+            && signature
+                .get_export(&member.backend)
+                .is_some_and(|export| export.kind == SymbolKind::Backend)
+    });
+    if !removed.is_empty() {
+        state.removed_members.insert(module_path.to_string(), removed);
+    }
+}
+
+/// The members of the backends a signature exports
+fn backend_members(signature: &ModuleSignature) -> Vec<RemovedMember> {
+    let mut members = Vec::new();
+    for export in signature.all_exports() {
+        let (SymbolKind::Backend, Some(body)) = (export.kind, export.body_scope) else {
+            continue;
+        };
+        for symbol in signature.symbols.symbols_in_scope(body) {
+            members.push(RemovedMember {
+                backend: export.name.clone(),
+                member: symbol.name.clone(),
+            });
+        }
+    }
+    members
+}
+
+/// Explain the unresolved names of blueprints that used a member an edit
+/// removed from their `with` backend, pointing at the `with`
+fn explain_removed_members(state: &ProjectState, module: &Module, diagnostics: &mut Diagnostics) {
+    if state.removed_members.is_empty() {
+        return;
+    }
+    for file in &module.files {
+        for decl in &file.declarations {
+            let ast::TopLevelDecl::Blueprint(blueprint) = decl else {
+                continue;
+            };
+            for stmt in &blueprint.body {
+                let ast::BlueprintStmt::With(with) = stmt else {
+                    continue;
+                };
+                for source in backend_modules(file, &with.backend) {
+                    let Some(removed) = state.removed_members.get(source) else {
+                        continue;
+                    };
+                    for member in removed.iter().filter(|m| m.backend == with.backend) {
+                        explain_removed_member(
+                            state,
+                            source,
+                            member,
+                            blueprint.span,
+                            with.span,
+                            diagnostics,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The modules a backend named in a file's `with` may come from: the file's
+/// own, and those it imports the name from
+fn backend_modules<'a>(file: &'a ast::File, backend: &str) -> Vec<&'a str> {
+    let mut modules = vec![file.module.as_str()];
+    for import in &file.imports {
+        if import.import_all {
+            modules.push(&import.path);
+        } else if let Some((module, name)) = import.path.rsplit_once('.') {
+            if name == backend {
+                modules.push(module);
+            }
+        }
+    }
+    modules
+}
+
+fn explain_removed_member(
+    state: &ProjectState,
+    source: &str,
+    member: &RemovedMember,
+    blueprint: Span,
+    with: Span,
+    diagnostics: &mut Diagnostics,
+) {
+    let unresolved = format!("cannot find `{}` in this scope", member.member);
+    let declared = state.registry.get(source).and_then(|signature| {
+        let export = signature.get_export(&member.backend)?;
+        let symbol = signature.get_symbol(export.symbol_id)?;
+        let path = signature.source_path.clone()?;
+        Some(RelatedInfo::in_file(
+            symbol.def_span,
+            path,
+            format!("backend `{}` is declared here", member.backend),
+        ))
+    });
+
+    diagnostics.retain_mut(|diagnostic| {
+        let stale = diagnostic.code.as_deref() == Some(codes::E0301.code)
+            && diagnostic.message == unresolved
+            && blueprint.start <= diagnostic.span.start
+            && diagnostic.span.end <= blueprint.end;
+        if stale {
+            diagnostic.message = format!(
+                "`{}` was removed from backend `{}`",
+                member.member, member.backend
+            );
+            diagnostic.related.push(RelatedInfo::new(
+                with,
+                format!("`{}` is used here, from module `{}`", member.backend, source),
+            ));
+            diagnostic.related.extend(declared.clone());
+        }
+        true
+    });
+}
+
+/// Save a module's signature for the next cold start
+///
+/// Failing to save only costs a rebuild later, so errors are ignored like
+/// those of `write_output`.
+fn persist_signature(state: &ProjectState, signature: &ModuleSignature) {
+    let _ = save_signature(state.sink.as_ref(), &state.signature_dir(), signature);
+}
+
+/// Generate a module's files with the project's code generator, returning
+/// them with the theme members left out as unused
+///
+/// Modules with errors are not generated. A failing generator is reported
+/// as an error of the module.
+fn generate_outputs(
+    state: &ProjectState,
+    module_path: &str,
+    result: &mut ModuleAnalysisResult,
+) -> (Vec<OutputFile>, Vec<UnusedMember>) {
+    if result.diagnostics.has_errors() {
+        return (Vec::new(), Vec::new());
+    }
+    // Lower the first file's AST for codegen
+    let files = state.module_index.files_for_module(module_path);
+    let Some(cache_entry) = files.first().and_then(|path| state.parse_cache.get(path)) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut ir = lower_with_registry(&cache_entry.file, &state.registry);
+    let stripped = if state.strip_unused_styles {
+        unused_theme_members(&cache_entry.file, &state.theme_usage)
+    } else {
+        Vec::new()
+    };
+    ir.strip_theme_members(&stripped);
+    let options = GenerateOptions {
+        fingerprint: state.fingerprint,
+        ..GenerateOptions::default()
+    };
+    match state.codegen.generate(&ir, &options, &state.plugin_context()) {
+        Ok(outputs) => (outputs, stripped),
+        Err(e) => {
+            result
+                .diagnostics
+                .add(Diagnostic::error(e.to_string(), Span::default()));
+            (Vec::new(), Vec::new())
+        }
+    }
+}
+
+/// Write a module's generated files and record them as build artifacts
+///
+/// Recording the paths lets the watcher ignore the writes instead of treating
+/// them as source changes and scheduling another build.
+///
+/// With fingerprinting, each file is written under the hash of its contents
+/// and the manifest updated; the file of the previous contents is removed.
+fn emit_outputs(state: &mut ProjectState, outputs: &[OutputFile]) {
+    let mut manifest_changed = false;
+    for file in outputs {
+        if state.fingerprint {
+            let hashed = fingerprinted_path(&file.path, &file.contents);
+            match state.manifest.insert(&file.path, &hashed) {
+                Some(previous) if previous == hashed => {}
+                Some(previous) => {
+                    let previous = state.build_dir.join(previous);
+                    let _ = state.sink.remove(&previous);
+                    state.emitted.remove(&previous);
+                    manifest_changed = true;
+                }
+                None => manifest_changed = true,
+            }
+        }
+        let output_path = state.output_path(file);
+        write_output(state, &output_path, &file.contents);
+        state.emitted.insert(output_path);
+    }
+    if manifest_changed {
+        write_manifest(state);
+    }
+}
+
+/// Write the manifest of fingerprinted files to the build directory
+fn write_manifest(state: &mut ProjectState) {
+    let path = state.build_dir.join(MANIFEST_FILE);
+    write_output(state, &path, &state.manifest.to_json());
+    state.emitted.insert(path);
+}
+
+/// Keep a module's generated files for hot reload
+///
+/// A build that generated nothing (the module has errors) keeps the files
+/// of the last successful one.
+fn store_artifacts(state: &mut ProjectState, module_path: &str, outputs: &[OutputFile]) {
+    if outputs.is_empty() {
+        return;
+    }
+    let artifacts = ModuleArtifacts {
+        outputs: outputs.to_vec(),
+        hash: hash_outputs(outputs),
+        generation: state.generation,
+    };
+    state.artifacts.insert(module_path.to_string(), artifacts);
+}
+
+/// Write generated code, creating parent directories as needed
+fn write_output(state: &ProjectState, output_path: &Path, code: &str) {
+    let _ = state.sink.write(output_path, code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::state::Limits;
+    use frel_compiler_core::source::LineCol;
+    use frel_compiler_core::{Edition, Experimental, MemoryFs};
+
+    /// Create an empty project directory unique to this test
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("frel-server-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn new_state(root: &Path) -> ProjectState {
+        ProjectState::new(root.to_path_buf(), root.join("build"))
+    }
+
+    const DATA: &str = "module test.data\n\nbackend Store {\n    count: i32 = 0\n}\n";
+    const APP: &str = "module test.app\n\nimport test.data.Store\n\nblueprint Main {\n    with Store\n}\n";
+
+    #[test]
+    fn test_oversized_file_is_skipped() {
+        let root = temp_project("file-limit");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        let mut state = new_state(&root);
+        state.limits.max_file_size = 16;
+
+        let result = full_build(&mut state);
+        assert_eq!(result.error_count, 1);
+        assert!(state.modules().is_empty());
+        let errors = &state.limit_diagnostics[&root.join("data.frel")];
+        assert!(errors.iter().next().unwrap().message.contains("over the limit of 16 bytes"));
+
+        state.limits.max_file_size = 1024;
+        let result = handle_file_change(&mut state, &root.join("data.frel"));
+        assert_eq!(result.error_count, 0);
+        assert_eq!(state.modules(), ["test.data"]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_build_aborted_by_limits() {
+        let root = temp_project("build-limit");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        let mut state = new_state(&root);
+        state.limits.max_memory = 64;
+
+        let result = full_build(&mut state);
+        let reason = result.aborted.unwrap();
+        assert!(reason.starts_with("estimated memory use"), "{}", reason);
+        assert!(state.analysis_cache.is_empty());
+        assert_eq!(state.error_count(), 1);
+
+        state.limits = Limits {
+            max_build_duration: Duration::ZERO,
+            ..Limits::default()
+        };
+        fs::write(root.join("data.frel"), DATA.replace("count", "total")).unwrap();
+        let result = handle_file_change(&mut state, &root.join("data.frel"));
+        assert!(matches!(
+            result.events.last(),
+            Some(CompilationEvent::BuildAborted { reason }) if reason.contains("time limit")
+        ));
+
+        state.limits = Limits::default();
+        let result = full_build(&mut state);
+        assert!(result.aborted.is_none());
+        assert_eq!(result.error_count, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_project_output() {
+        let root = temp_project("output");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        fs::write(root.join("app.frel"), APP.replace("with Store", "with Missing")).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        let output = project_output(&state);
+        assert!(!output.success());
+        let artifacts: Vec<&str> = output.artifacts.iter().map(|a| a.path.as_str()).collect();
+        let data_js = state.build_dir.join("test/data.js").display().to_string();
+        assert_eq!(artifacts, [data_js.as_str()]);
+        assert_eq!(output.artifacts[0].module.as_deref(), Some("test.data"));
+
+        let error = &output.diagnostics[0];
+        assert_eq!(error.file, root.join("app.frel").display().to_string());
+        assert_eq!(error.start.map(|start| start.line), Some(6));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_project_output_multi_file_module() {
+        let root = temp_project("multi-file");
+        fs::write(root.join("a.frel"), "module test.split\n\nbackend A { count : i32 = 0 }\n").unwrap();
+        let second = "module test.split\n\n\nbackend B {\n    total : i32 = missing\n}\n";
+        fs::write(root.join("b.frel"), second).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        // The unresolved name is reported in the second file, not the module's first
+        let output = project_output(&state);
+        let error = output
+            .diagnostics
+            .iter()
+            .find(|d| d.diagnostic.message.contains("missing"))
+            .unwrap();
+        assert_eq!(error.file, root.join("b.frel").display().to_string());
+        assert_eq!(error.start.map(|start| start.line), Some(5));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_project_output_remaps_to_templates() {
+        let root = temp_project("remap");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        let app = APP.replace("with Store", "with Missing");
+        // app.frel is app.frel.tpl without its two header lines
+        fs::write(root.join("app.frel.tpl"), format!("{{{{! header }}}}\n{{{{#app}}}}\n{}", app))
+            .unwrap();
+        fs::write(root.join("app.frel"), &app).unwrap();
+        let map = r#"{"version":3,"sources":["app.frel.tpl"],"mappings":"AAEA;AACA;AACA;AACA;AACA;AACA;AACA"}"#;
+        fs::write(root.join("app.frel.map"), map).unwrap();
+
+        let mut state = new_state(&root);
+        state.remap_diagnostics = true;
+        full_build(&mut state);
+
+        let output = project_output(&state);
+        let error = &output.diagnostics[0];
+        assert_eq!(error.file, root.join("app.frel.tpl").display().to_string());
+        assert_eq!(error.start.map(|start| start.line), Some(8));
+        let position = LineCol { line: 6, col: 10 };
+        let original = state.original_position(&root.join("app.frel"), position).unwrap();
+        assert_eq!(original.position, LineCol { line: 8, col: 10 });
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_full_build_reuses_unchanged_modules() {
+        let root = temp_project("cache-noop");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+
+        let first = full_build(&mut state);
+        assert_eq!(first.error_count, 0);
+        assert_eq!(first.cache.hits(), 0);
+        assert_eq!(first.cache.parse_misses, 2);
+        assert_eq!(first.cache.analysis_misses, 2);
+
+        let second = full_build(&mut state);
+        assert_eq!(second.error_count, 0);
+        assert_eq!(second.cache.misses(), 0);
+        assert_eq!(second.cache.parse_hits, 2);
+        assert_eq!(second.cache.signature_hits, 2);
+        assert_eq!(second.cache.analysis_hits, 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_full_build_comment_only_change() {
+        let root = temp_project("cache-comment");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        // A trailing comment changes the content but not the AST
+        fs::write(root.join("data.frel"), format!("{}// note\n", DATA)).unwrap();
+        let result = full_build(&mut state);
+
+        assert_eq!(result.cache.parse_misses, 1);
+        assert_eq!(result.cache.signature_hits, 1);
+        assert_eq!(result.cache.analysis_hits, 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_full_build_dependency_signature_change() {
+        let root = temp_project("cache-deps");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        // Adding a member to an imported backend invalidates the importer
+        let changed = DATA.replace("count: i32 = 0", "count: i32 = 0\n    label: String = \"\"");
+        fs::write(root.join("data.frel"), changed).unwrap();
+        let result = full_build(&mut state);
+
+        assert_eq!(result.cache.parse_hits, 1);
+        assert_eq!(result.cache.parse_misses, 1);
+        assert_eq!(result.cache.signature_misses, 1);
+        assert_eq!(result.cache.analysis_misses, 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_dependency_member_type_change() {
+        let root = temp_project("member-type");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        // Only the type of an imported member changes
+        let changed = DATA.replace("count: i32", "count: i64");
+        fs::write(&data, &changed).unwrap();
+        let result = handle_file_change(&mut state, &data);
+        assert!(result.modules_rebuilt.contains(&"test.app".to_string()));
+
+        fs::write(&data, DATA).unwrap();
+        let result = full_build(&mut state);
+        assert_eq!(result.cache.signature_misses, 1);
+        assert_eq!(result.cache.analysis_misses, 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_full_build_drops_deleted_files() {
+        let root = temp_project("cache-deleted");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        fs::remove_file(root.join("data.frel")).unwrap();
+        let result = full_build(&mut state);
+
+        assert!(!state.registry.contains("test.data"));
+        assert!(!state.analysis_cache.contains_key("test.data"));
+        assert!(result.error_count > 0, "importer should report the missing module");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_signatures_persist_between_runs() {
+        let root = temp_project("signatures");
+        fs::write(root.join("data.frel"), DATA).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        assert!(signature_file_path(&state.signature_dir(), "test.data").exists());
+
+        // A new run starts with the saved signatures
+        let mut next = new_state(&root);
+        assert!(load_signatures(&mut next).is_empty());
+        assert!(next.registry.resolve_import("test.data", "Store").is_some());
+
+        // Dropping a module removes its saved signature
+        fs::remove_file(root.join("data.frel")).unwrap();
+        full_build(&mut state);
+        assert!(!signature_file_path(&state.signature_dir(), "test.data").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_project_edition() {
+        let root = temp_project("edition");
+        let source = "module test.session\n\nsingleton backend Session {\n    user : String = \"\"\n}\n";
+        fs::write(root.join("session.frel"), source).unwrap();
+
+        let mut state = new_state(&root);
+        state.edition = Edition::E2025;
+        let result = full_build(&mut state);
+        assert_eq!(result.error_count, 1);
+        let entry = state.parse_cache.get(&root.join("session.frel")).unwrap();
+        let error = entry.diagnostics.iter().next().unwrap();
+        assert_eq!(error.code.as_deref(), Some("E0208"));
+
+        let mut state = new_state(&root);
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let root = temp_project("reload");
+        let source = "module test.session\n\nsingleton backend Session {\n    user : String = \"\"\n}\n";
+        fs::write(root.join("session.frel"), source).unwrap();
+
+        let mut state = new_state(&root);
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        // The edition changes: files are parsed again with the new one
+        fs::write(root.join(CONFIG_FILE), "edition = \"2025\"\n").unwrap();
+        fs::write(root.join("data.frel"), "module test.data\n").unwrap();
+        match reload_config(&mut state) {
+            ConfigReload::Reloaded { edition_changed, build } => {
+                assert!(edition_changed);
+                assert_eq!(build.error_count, 1);
+            }
+            ConfigReload::Rejected { .. } => panic!("valid configuration rejected"),
+        }
+        assert_eq!(state.edition, Edition::E2025);
+        // The module set is scanned again
+        assert!(state.modules().contains(&"test.data"));
+
+        // An invalid configuration keeps the previous one
+        fs::write(root.join(CONFIG_FILE), "edition = \"2030\"\n").unwrap();
+        assert!(matches!(
+            reload_config(&mut state),
+            ConfigReload::Rejected { error_count: 1 }
+        ));
+        assert_eq!(state.edition, Edition::E2025);
+        assert_eq!(state.error_count(), 2);
+        let output = project_output(&state);
+        assert!(output.diagnostics.iter().any(|d| d.file.ends_with(CONFIG_FILE)));
+
+        fs::remove_file(root.join(CONFIG_FILE)).unwrap();
+        match reload_config(&mut state) {
+            ConfigReload::Reloaded { edition_changed, build } => {
+                assert!(edition_changed);
+                assert_eq!(build.error_count, 0);
+            }
+            ConfigReload::Rejected { .. } => panic!("default configuration rejected"),
+        }
+        assert!(state.config_diagnostics.is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_reload_lints() {
+        let root = temp_project("lints");
+        let source = "module test\n\nsingleton backend Selection {\n    selected : String = \"\"\n}\n\n\
+            blueprint List {\n    with Selection\n    items : List<String> = []\n    \
+            repeat on items { item ->\n        text { item } .. on_click { selected = item }\n    }\n}\n";
+        fs::write(root.join("list.frel"), source).unwrap();
+
+        let mut state = new_state(&root);
+        state.lint_flags.deny_warnings();
+        state.lints = state.lint_flags.clone();
+        assert_eq!(full_build(&mut state).error_count, 1);
+
+        // frel.toml allows the lint; `warnings` denied on the command line
+        // only applies to what is still a warning
+        fs::write(root.join(CONFIG_FILE), "[lints]\nshared_item_state = \"allow\"\n").unwrap();
+        match reload_config(&mut state) {
+            ConfigReload::Reloaded { build, .. } => assert_eq!(build.error_count, 0),
+            ConfigReload::Rejected { .. } => panic!("valid configuration rejected"),
+        }
+        assert!(project_output(&state).diagnostics.is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_file_changes_coalesced() {
+        let root = temp_project("burst");
+        let data = root.join("data.frel");
+        let view = root.join("view.frel");
+        fs::write(&data, "module test.data\n\nbackend Store {\n    count : i32 = 0\n}\n").unwrap();
+        fs::write(&view, "module test.view\n").unwrap();
+
+        let mut state = new_state(&root);
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        // Saving both files is a single build
+        fs::write(&data, "module test.data\n\nbackend Store {\n    count : i32 = 1\n}\n").unwrap();
+        fs::write(&view, "module test.view\n\n").unwrap();
+        let result = handle_file_changes(&mut state, &[data.clone(), view.clone()]);
+        assert_eq!(result.modules_rebuilt.len(), 2);
+        let started = result
+            .events
+            .iter()
+            .filter(|event| matches!(event, CompilationEvent::BuildStarted { .. }))
+            .count();
+        assert_eq!(started, 1);
+
+        // Nothing changed since: no build at all
+        let result = handle_file_changes(&mut state, &[data, view]);
+        assert!(result.modules_rebuilt.is_empty());
+        assert!(result.events.is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_enabled_features() {
+        let root = temp_project("features");
+        let source = "module test.users\n\nbackend Users {\n    command load(id : i32) : String\n}\n";
+        fs::write(root.join("users.frel"), source).unwrap();
+
+        let mut state = new_state(&root);
+        assert_eq!(full_build(&mut state).error_count, 1);
+
+        let mut state = new_state(&root);
+        state.features.enable(Experimental::CommandResults);
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rebuild_publishes_events() {
+        let root = temp_project("publish");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        let mut receiver = state.subscribe();
+        fs::write(&data, DATA.replace("count", "total")).unwrap();
+        let result = handle_file_change(&mut state, &data);
+
+        let mut published = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            published.push(event);
+        }
+        assert_eq!(published.len(), result.events.len());
+        assert!(matches!(published[0], CompilationEvent::FileChanged { .. }));
+        assert!(matches!(
+            &published[1],
+            CompilationEvent::BuildStarted { modules } if modules == &["test.data"]
+        ));
+        assert!(published.iter().any(|e| matches!(
+            e,
+            CompilationEvent::DiagnosticsUpdated { module, error_count: 0, .. } if module == "test.data"
+        )));
+        assert!(matches!(
+            published.last(),
+            Some(CompilationEvent::BuildCompleted { modules_built: 1, error_count: 0, .. })
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_unused_theme_members_stripped() {
+        let root = temp_project("strip-styles");
+        let theme = "module test.theme\n\ntheme Look {\n    padding : u32 = 16\n    shadow : u32 = 4\n}\n";
+        let app = "module test.app\n\nimport test.theme.Look\n\nbackend Store {\n    look : ref Look\n    size : u32 = look.padding\n}\n";
+        fs::write(root.join("theme.frel"), theme).unwrap();
+        let app_path = root.join("app.frel");
+        fs::write(&app_path, app).unwrap();
+        let mut state = new_state(&root);
+        let generated = |state: &ProjectState| {
+            fs::read_to_string(state.build_dir.join("test/theme.js")).unwrap()
+        };
+
+        full_build(&mut state);
+        assert!(generated(&state).contains("padding"));
+        assert!(!generated(&state).contains("shadow"));
+        let stripped = &state.stripped_styles["test.theme"];
+        assert_eq!(stripped.len(), 1);
+        assert_eq!(stripped[0].name, "shadow");
+
+        // Reading the member elsewhere brings it back
+        fs::write(&app_path, app.replace("look.padding", "look.padding + look.shadow")).unwrap();
+        let result = handle_file_change(&mut state, &app_path);
+        assert!(result.modules_rebuilt.contains(&"test.theme".to_string()));
+        assert!(generated(&state).contains("shadow"));
+        assert!(state.stripped_styles["test.theme"].is_empty());
+
+        // Opting out keeps every member
+        fs::write(&app_path, app).unwrap();
+        let mut state = new_state(&root);
+        state.strip_unused_styles = false;
+        full_build(&mut state);
+        assert!(generated(&state).contains("shadow"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_artifacts_kept_for_hot_reload() {
+        let root = temp_project("artifacts");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        let first = state.artifacts["test.data"].hash;
+        assert!(state.artifacts["test.data"].outputs[0].contents.contains("count"));
+
+        fs::write(&data, DATA.replace("count", "total")).unwrap();
+        handle_file_change(&mut state, &data);
+        let second = state.artifacts["test.data"].hash;
+        assert_ne!(first, second);
+
+        // A failing build keeps the last working files
+        fs::write(&data, DATA.replace("i32", "Missing")).unwrap();
+        let result = handle_file_change(&mut state, &data);
+        assert!(result.error_count > 0);
+        assert_eq!(state.artifacts["test.data"].hash, second);
+
+        fs::remove_file(&data).unwrap();
+        handle_file_change(&mut state, &data);
+        assert!(!state.artifacts.contains_key("test.data"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_context_provided_by_other_module() {
+        let root = temp_project("contexts");
+        let theme = root.join("theme.frel");
+        fs::write(&theme, "module test.theme\n\nblueprint Themed {\n    text { \"hi\" }\n}\n").unwrap();
+        fs::write(
+            root.join("badge.frel"),
+            "module test.badge\n\nblueprint Badge {\n    consume accent: String\n    text { accent }\n}\n",
+        )
+        .unwrap();
+        let mut state = new_state(&root);
+        assert_eq!(full_build(&mut state).error_count, 1);
+
+        // Providing the value in a module the consumer doesn't import
+        let provider = "module test.theme\n\nblueprint Themed {\n    provide accent = \"red\"\n    text { \"hi\" }\n}\n";
+        fs::write(&theme, provider).unwrap();
+        let result = handle_file_change(&mut state, &theme);
+        assert!(result.modules_rebuilt.contains(&"test.badge".to_string()));
+        assert_eq!(result.error_count, 0);
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        fs::write(&theme, "module test.theme\n\nblueprint Themed {\n    text { \"hi\" }\n}\n").unwrap();
+        assert_eq!(handle_file_change(&mut state, &theme).error_count, 1);
+
+        // A full build sees the change as well
+        fs::write(&theme, provider).unwrap();
+        assert_eq!(full_build(&mut state).error_count, 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_shortcut_conflict_with_other_module() {
+        let root = temp_project("shortcuts");
+        let settings = root.join("settings.frel");
+        let shortcut = |chord: &str| {
+            format!("module test.settings\n\nblueprint Settings {{\n    shortcut \"{}\" {{ }}\n}}\n", chord)
+        };
+        // Conflicts are warnings, reported at each declaration
+        let conflicts = |state: &ProjectState| {
+            ["test.editor", "test.settings"]
+                .iter()
+                .filter(|module| {
+                    let diagnostics = &state.analysis_cache[**module].result.diagnostics;
+                    diagnostics.iter().any(|d| d.code.as_deref() == Some("E0708"))
+                })
+                .count()
+        };
+        fs::write(&settings, shortcut("Escape")).unwrap();
+        fs::write(
+            root.join("editor.frel"),
+            "module test.editor\n\nblueprint Editor {\n    shortcut \"Ctrl+S\" { }\n}\n",
+        )
+        .unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        assert_eq!(conflicts(&state), 0);
+
+        // Neither module imports the other
+        fs::write(&settings, shortcut("Ctrl+S")).unwrap();
+        let result = handle_file_change(&mut state, &settings);
+        assert!(result.modules_rebuilt.contains(&"test.editor".to_string()));
+        assert_eq!(conflicts(&state), 2);
+        full_build(&mut state);
+        assert_eq!(conflicts(&state), 2);
+
+        fs::write(&settings, shortcut("Escape")).unwrap();
+        handle_file_change(&mut state, &settings);
+        assert_eq!(conflicts(&state), 0);
+
+        // A full build sees the change as well
+        fs::write(&settings, shortcut("Ctrl+S")).unwrap();
+        full_build(&mut state);
+        assert_eq!(conflicts(&state), 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_deleted_file_drops_module() {
+        let root = temp_project("deleted");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        let artifact = state.build_dir.join("test/data.js");
+        assert!(artifact.exists());
+
+        fs::remove_file(&data).unwrap();
+        let result = handle_file_change(&mut state, &data);
+
+        assert!(!state.registry.contains("test.data"));
+        assert!(!state.signature_cache.contains_key("test.data"));
+        assert!(!state.analysis_cache.contains_key("test.data"));
+        assert!(!state.modules().contains(&"test.data"));
+        assert!(!artifact.exists());
+        assert!(result.modules_rebuilt.contains(&"test.app".to_string()));
+        assert!(matches!(result.events[0], CompilationEvent::FileRemoved { .. }));
+        assert!(result.events.iter().any(|e| matches!(
+            e,
+            CompilationEvent::ModuleRemoved { module } if module == "test.data"
+        )));
+
+        // The importer reports the missing module at its import
+        let app = &state.analysis_cache["test.app"].result.diagnostics;
+        let error = app.iter().find(|d| d.message.contains("test.data")).unwrap();
+        let import_start = APP.find("import").unwrap() as u32;
+        assert_eq!(error.span.start, import_start);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_removed_backend_member() {
+        let root = temp_project("removed-member");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        let app = APP.replace("with Store\n", "with Store\n    text { \"${count}\" }\n");
+        fs::write(root.join("app.frel"), &app).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        assert_eq!(state.error_count(), 0);
+
+        fs::write(&data, DATA.replace("count: i32 = 0", "total: i32 = 0")).unwrap();
+        handle_file_change(&mut state, &data);
+
+        // The importer is told where the name went, at its `with`
+        let diagnostics = &state.analysis_cache["test.app"].result.diagnostics;
+        let error = diagnostics
+            .iter()
+            .find(|d| d.message == "`count` was removed from backend `Store`")
+            .unwrap_or_else(|| panic!("{:?}", diagnostics));
+        assert_eq!(error.related[0].span.start, app.find("with").unwrap() as u32);
+        assert_eq!(error.related[1].message, "backend `Store` is declared here");
+
+        // Another edit of the importer still explains it
+        let app_path = root.join("app.frel");
+        fs::write(&app_path, app.replace("blueprint Main", "blueprint Home")).unwrap();
+        handle_file_change(&mut state, &app_path);
+        let diagnostics = &state.analysis_cache["test.app"].result.diagnostics;
+        assert!(diagnostics.iter().any(|d| d.message.contains("removed from backend")));
+
+        // Restoring the member forgets it, and remembers the one replacing it
+        fs::write(&data, DATA).unwrap();
+        handle_file_change(&mut state, &data);
+        assert_eq!(
+            state.removed_members["test.data"],
+            [RemovedMember {
+                backend: "Store".to_string(),
+                member: "total".to_string(),
+            }]
+        );
+        assert_eq!(state.error_count(), 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_fingerprinted_outputs() {
+        let root = temp_project("fingerprint");
+        let data = root.join("data.frel");
+        fs::write(&data, DATA).unwrap();
+        let mut state = new_state(&root);
+        state.fingerprint = true;
+        full_build(&mut state);
+
+        let logical = Path::new("test/data.js");
+        let first = state.manifest.get(logical).unwrap();
+        assert_ne!(first, logical);
+        assert!(state.build_dir.join(&first).exists());
+        assert!(!state.build_dir.join(logical).exists());
+        let manifest = fs::read_to_string(state.build_dir.join(MANIFEST_FILE)).unwrap();
+        assert!(manifest.contains(&first.display().to_string()), "{}", manifest);
+
+        // New contents get a new name; the file of the old ones goes away
+        fs::write(&data, DATA.replace("= 0", "= 1")).unwrap();
+        handle_file_change(&mut state, &data);
+        let second = state.manifest.get(logical).unwrap();
+        assert_ne!(second, first);
+        assert!(state.build_dir.join(&second).exists());
+        assert!(!state.build_dir.join(&first).exists());
+
+        fs::remove_file(&data).unwrap();
+        handle_file_change(&mut state, &data);
+        assert!(state.manifest.is_empty());
+        assert!(!state.build_dir.join(&second).exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_renamed_file_keeps_module() {
+        let root = temp_project("renamed");
+        let old_path = root.join("data.frel");
+        let new_path = root.join("store.frel");
+        fs::write(&old_path, DATA).unwrap();
+        fs::write(root.join("app.frel"), APP).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+
+        // A rename arrives as a removal of the old path and a new file
+        fs::rename(&old_path, &new_path).unwrap();
+        handle_file_change(&mut state, &old_path);
+        let result = handle_file_change(&mut state, &new_path);
+
+        assert_eq!(result.error_count, 0);
+        assert!(result.modules_rebuilt.contains(&"test.app".to_string()));
+        assert_eq!(state.module_index.files_for_module("test.data"), &[new_path]);
+        assert!(!state.sources.contains_key(&old_path));
+        assert!(state.build_dir.join("test/data.js").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_overlay_takes_precedence_over_disk() {
+        let root = temp_project("overlay");
+        let path = root.join("data.frel");
+        fs::write(&path, DATA).unwrap();
+        let mut state = new_state(&root);
+        full_build(&mut state);
+        assert_eq!(state.error_count(), 0);
+
+        // An unsaved buffer with an error is compiled instead of the disk file
+        state.set_overlay(path.clone(), "module test.data\nbackend {".to_string(), None);
+        handle_file_change(&mut state, &path);
+        assert!(state.error_count() > 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), DATA);
+
+        // Clearing the overlay recompiles from disk
+        assert!(state.clear_overlay(&path));
+        handle_file_change(&mut state, &path);
+        assert_eq!(state.error_count(), 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_overlay_for_unsaved_new_file() {
+        let root = temp_project("overlay-new");
+        let mut state = new_state(&root);
+        state.set_overlay(root.join("draft.frel"), DATA.to_string(), None);
+
+        let result = full_build(&mut state);
+        assert_eq!(result.modules_built, 1);
+        assert!(state.registry.contains("test.data"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_expired_overlay_is_ignored() {
+        let root = temp_project("overlay-ttl");
+        let path = root.join("data.frel");
+        fs::write(&path, DATA).unwrap();
+        let mut state = new_state(&root);
+        state.set_overlay(path.clone(), "module other".to_string(), Some(Duration::ZERO));
+
+        assert!(state.has_expired_overlays());
+        assert_eq!(state.read_source(&path).unwrap(), DATA);
+        assert_eq!(state.take_expired_overlays(), vec![path]);
+        assert!(state.overlays.is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_build_in_memory() {
+        let files = Arc::new(
+            MemoryFs::new()
+                .with_file("/project/data.frel", DATA)
+                .with_file("/project/app.frel", APP)
+                .with_file("/project/frel.toml", "edition = \"2025\"\n"),
+        );
+        let mut state = ProjectState::new("/project".into(), "/project/build".into())
+            .with_files(files.clone(), files.clone());
+
+        let result = full_build(&mut state);
+        assert_eq!(result.error_count, 0);
+        assert!(files.get("/project/build/test/data.js").is_some());
+        assert!(files.get(signature_file_path(&state.signature_dir(), "test.data")).is_some());
+        assert!(matches!(reload_config(&mut state), ConfigReload::Reloaded { .. }));
+
+        files.take("/project/data.frel");
+        let result = handle_file_change(&mut state, Path::new("/project/data.frel"));
+        assert!(result.modules_rebuilt.contains(&"test.app".to_string()));
+        assert!(files.get("/project/build/test/data.js").is_none());
+        assert!(!Path::new("/project").exists());
     }
-    path.set_extension("js");
-    path
 }
@@ -1,9 +1,11 @@
 // Compilation events for SSE/WebSocket notifications
 
+use std::time::{Duration, Instant};
+
 use serde::Serialize;
 
 /// Events broadcast to connected clients
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum CompilationEvent {
     /// Build started
@@ -16,10 +18,30 @@ pub enum CompilationEvent {
         modules_built: usize,
         error_count: usize,
     },
+    /// Build aborted because it exceeded a resource limit
+    BuildAborted {
+        reason: String,
+    },
+    /// `frel.toml` was reloaded and the project rebuilt with it
+    ConfigReloaded {
+        edition: String,
+    },
+    /// `frel.toml` is invalid; the previous configuration stays in effect
+    ConfigRejected {
+        error_count: usize,
+    },
     /// File changed detected
     FileChanged {
         path: String,
     },
+    /// Source file deleted or renamed away
+    FileRemoved {
+        path: String,
+    },
+    /// Module dropped because none of its files remain
+    ModuleRemoved {
+        module: String,
+    },
     /// Module updated (recompiled)
     ModuleUpdated {
         module: String,
@@ -32,3 +54,366 @@ pub enum CompilationEvent {
         warning_count: usize,
     },
 }
+
+impl CompilationEvent {
+    /// The event's `type`, e.g. `module_updated`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CompilationEvent::BuildStarted { .. } => "build_started",
+            CompilationEvent::BuildCompleted { .. } => "build_completed",
+            CompilationEvent::BuildAborted { .. } => "build_aborted",
+            CompilationEvent::ConfigReloaded { .. } => "config_reloaded",
+            CompilationEvent::ConfigRejected { .. } => "config_rejected",
+            CompilationEvent::FileChanged { .. } => "file_changed",
+            CompilationEvent::FileRemoved { .. } => "file_removed",
+            CompilationEvent::ModuleRemoved { .. } => "module_removed",
+            CompilationEvent::ModuleUpdated { .. } => "module_updated",
+            CompilationEvent::DiagnosticsUpdated { .. } => "diagnostics_updated",
+        }
+    }
+
+    /// The module the event is about, if it is about one
+    pub fn module(&self) -> Option<&str> {
+        match self {
+            CompilationEvent::ModuleRemoved { module }
+            | CompilationEvent::ModuleUpdated { module, .. }
+            | CompilationEvent::DiagnosticsUpdated { module, .. } => Some(module),
+            _ => None,
+        }
+    }
+}
+
+/// Event types a client can ask for
+pub const EVENT_TYPES: &[&str] = &[
+    "build_started",
+    "build_completed",
+    "build_aborted",
+    "config_reloaded",
+    "config_rejected",
+    "file_changed",
+    "file_removed",
+    "module_removed",
+    "module_updated",
+    "diagnostics_updated",
+];
+
+/// Lowest severity of the module events a client receives
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventSeverity {
+    /// Only modules with errors
+    Error,
+    /// Modules with errors or warnings
+    Warning,
+}
+
+/// The events one client receives
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    /// Module globs; events about other modules are dropped
+    pub modules: Vec<glob::Pattern>,
+    /// Event types received; all when empty
+    pub kinds: Vec<String>,
+    /// Drop `module_updated` and `diagnostics_updated` of modules without
+    /// problems of this severity
+    pub severity: Option<EventSeverity>,
+}
+
+impl EventFilter {
+    /// Parse the comma-separated lists of `GET /events`
+    pub fn parse(
+        module: Option<&str>,
+        kind: Option<&str>,
+        severity: Option<&str>,
+    ) -> Result<Self, String> {
+        let items = |list: Option<&str>| -> Vec<String> {
+            list.into_iter()
+                .flat_map(|list| list.split(','))
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        let modules = items(module)
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| format!("Invalid module glob '{}': {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        let kinds = items(kind);
+        if let Some(unknown) = kinds
+            .iter()
+            .find(|kind| !EVENT_TYPES.contains(&kind.as_str()))
+        {
+            return Err(format!(
+                "Unknown event type '{}', expected one of: {}",
+                unknown,
+                EVENT_TYPES.join(", ")
+            ));
+        }
+        let severity = match severity {
+            None => None,
+            Some("error") => Some(EventSeverity::Error),
+            Some("warning") => Some(EventSeverity::Warning),
+            Some(other) => {
+                return Err(format!(
+                    "Unknown severity '{}', expected 'error' or 'warning'",
+                    other
+                ))
+            }
+        };
+        Ok(Self {
+            modules,
+            kinds,
+            severity,
+        })
+    }
+
+    /// Whether a client with this filter receives an event
+    pub fn matches(&self, event: &CompilationEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|kind| kind == event.kind()) {
+            return false;
+        }
+        if let (Some(module), false) = (event.module(), self.modules.is_empty()) {
+            if !self.modules.iter().any(|pattern| pattern.matches(module)) {
+                return false;
+            }
+        }
+        match (self.severity, event) {
+            (None, _) => true,
+            // `module_updated` only tells whether the module has errors
+            (Some(_), CompilationEvent::ModuleUpdated { has_errors, .. }) => *has_errors,
+            (
+                Some(severity),
+                CompilationEvent::DiagnosticsUpdated {
+                    error_count,
+                    warning_count,
+                    ..
+                },
+            ) => *error_count > 0 || (severity == EventSeverity::Warning && *warning_count > 0),
+            (Some(_), _) => true,
+        }
+    }
+}
+
+/// The `module_updated` and `diagnostics_updated` events of a period,
+/// sent as one message instead of them
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(tag = "type", rename = "progress")]
+pub struct ProgressSummary {
+    /// Modules updated, in the order of their first event
+    pub modules: Vec<String>,
+    /// Modules updated with errors
+    pub modules_with_errors: usize,
+    /// Errors reported by the `diagnostics_updated` events
+    pub error_count: usize,
+    /// Warnings reported by the `diagnostics_updated` events
+    pub warning_count: usize,
+}
+
+impl ProgressSummary {
+    /// Add a per-module event; false if it is not one
+    pub fn add(&mut self, event: &CompilationEvent) -> bool {
+        match event {
+            CompilationEvent::ModuleUpdated { module, has_errors } => {
+                self.note(module);
+                self.modules_with_errors += *has_errors as usize;
+            }
+            CompilationEvent::DiagnosticsUpdated {
+                module,
+                error_count,
+                warning_count,
+            } => {
+                self.note(module);
+                self.error_count += error_count;
+                self.warning_count += warning_count;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn note(&mut self, module: &str) {
+        if !self.modules.iter().any(|m| m == module) {
+            self.modules.push(module.to_string());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+/// A message sent to an event stream client
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum EventMessage {
+    Event(CompilationEvent),
+    Progress(ProgressSummary),
+}
+
+/// The events of one client: filtered and, when asked for, with the
+/// per-module events coalesced into periodic `progress` summaries
+#[derive(Debug)]
+pub struct EventFeed {
+    filter: EventFilter,
+    /// Longest time a per-module event waits in the summary
+    interval: Option<Duration>,
+    summary: ProgressSummary,
+    /// When the first event of the pending summary arrived
+    since: Option<Instant>,
+}
+
+impl EventFeed {
+    pub fn new(filter: EventFilter, interval: Option<Duration>) -> Self {
+        Self {
+            filter,
+            interval,
+            summary: ProgressSummary::default(),
+            since: None,
+        }
+    }
+
+    /// The messages to send for an event
+    ///
+    /// A pending summary goes out before any other event, so a client sees
+    /// the progress of a build before its `build_completed`.
+    pub fn push(&mut self, event: CompilationEvent) -> Vec<EventMessage> {
+        if !self.filter.matches(&event) {
+            return Vec::new();
+        }
+        if self.interval.is_some() && self.summary.add(&event) {
+            self.since.get_or_insert_with(Instant::now);
+            return Vec::new();
+        }
+        let mut messages: Vec<EventMessage> = self.flush().into_iter().collect();
+        messages.push(EventMessage::Event(event));
+        messages
+    }
+
+    /// Take the pending summary, if any
+    pub fn flush(&mut self) -> Option<EventMessage> {
+        self.since = None;
+        (!self.summary.is_empty())
+            .then(|| EventMessage::Progress(std::mem::take(&mut self.summary)))
+    }
+
+    /// When the pending summary is due, if there is one
+    pub fn deadline(&self) -> Option<Instant> {
+        Some(self.since? + self.interval?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updated(module: &str, errors: usize, warnings: usize) -> [CompilationEvent; 2] {
+        [
+            CompilationEvent::ModuleUpdated {
+                module: module.to_string(),
+                has_errors: errors > 0,
+            },
+            CompilationEvent::DiagnosticsUpdated {
+                module: module.to_string(),
+                error_count: errors,
+                warning_count: warnings,
+            },
+        ]
+    }
+
+    fn received(filter: &EventFilter, events: &[CompilationEvent]) -> Vec<String> {
+        events
+            .iter()
+            .filter(|event| filter.matches(event))
+            .map(|event| format!("{} {}", event.kind(), event.module().unwrap_or("-")))
+            .collect()
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let mut events = vec![CompilationEvent::BuildStarted {
+            modules: vec!["app.main".to_string(), "lib.ui".to_string()],
+        }];
+        events.extend(updated("app.main", 2, 0));
+        events.extend(updated("app.settings", 0, 1));
+        events.extend(updated("lib.ui", 0, 0));
+
+        let filter = EventFilter::parse(Some("app.*"), None, Some("warning")).unwrap();
+        assert_eq!(
+            received(&filter, &events),
+            [
+                "build_started -",
+                "module_updated app.main",
+                "diagnostics_updated app.main",
+                "diagnostics_updated app.settings",
+            ]
+        );
+
+        let filter = EventFilter::parse(
+            None,
+            Some("build_started, diagnostics_updated"),
+            Some("error"),
+        )
+        .unwrap();
+        assert_eq!(
+            received(&filter, &events),
+            ["build_started -", "diagnostics_updated app.main"]
+        );
+
+        assert_eq!(
+            EventFilter::parse(None, Some("module_changed"), None).unwrap_err(),
+            format!(
+                "Unknown event type 'module_changed', expected one of: {}",
+                EVENT_TYPES.join(", ")
+            )
+        );
+        assert!(EventFilter::parse(Some("app.[ui"), None, None).is_err());
+        assert!(EventFilter::parse(None, None, Some("info")).is_err());
+    }
+
+    #[test]
+    fn test_progress_summary() {
+        let mut feed = EventFeed::new(EventFilter::default(), Some(Duration::from_millis(500)));
+        assert_eq!(feed.deadline(), None);
+
+        let started = CompilationEvent::BuildStarted {
+            modules: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(feed.push(started.clone()), [EventMessage::Event(started)]);
+        for event in updated("a", 1, 2).into_iter().chain(updated("b", 0, 3)) {
+            assert!(feed.push(event).is_empty());
+        }
+        assert!(feed.deadline().is_some());
+
+        let completed = CompilationEvent::BuildCompleted {
+            duration_ms: 10,
+            modules_built: 2,
+            error_count: 1,
+        };
+        let messages = feed.push(completed.clone());
+        assert_eq!(
+            messages,
+            [
+                EventMessage::Progress(ProgressSummary {
+                    modules: vec!["a".to_string(), "b".to_string()],
+                    modules_with_errors: 1,
+                    error_count: 1,
+                    warning_count: 5,
+                }),
+                EventMessage::Event(completed),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&messages[0]).unwrap(),
+            serde_json::json!({
+                "type": "progress",
+                "modules": ["a", "b"],
+                "modules_with_errors": 1,
+                "error_count": 1,
+                "warning_count": 5,
+            })
+        );
+        assert_eq!(feed.flush(), None);
+        assert_eq!(feed.deadline(), None);
+    }
+}
@@ -7,6 +7,7 @@
 pub mod api;
 pub mod compiler;
 pub mod events;
+pub mod record;
 pub mod server;
 pub mod state;
 pub mod watcher;
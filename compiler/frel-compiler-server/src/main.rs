@@ -4,14 +4,34 @@
 
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use tokio::sync::{watch, RwLock};
 
-use frel_compiler_server::state::ProjectState;
+use frel_compiler_core::edition::{
+    Experimental, Features, ProjectConfig, WatchConfig, CONFIG_FILE,
+};
+use frel_compiler_core::session::Session;
+use frel_compiler_core::{DiskFs, LintLevels};
+use frel_compiler_plugin_api::PluginRegistry;
+use frel_compiler_plugin_javascript::JavaScriptPlugin;
+use frel_compiler_server::record::{self, Recorder};
+use frel_compiler_server::state::{Limits, ProjectState, WatchSettings};
 use frel_compiler_server::{compiler, server, watcher};
 
+/// How the initial build is reported
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Rendered progress and a summary line
+    Human,
+    /// One JSON envelope with artifacts, diagnostics and timings on stdout
+    Json,
+    /// A SARIF 2.1.0 log of the diagnostics on stdout, for code scanning
+    Sarif,
+}
+
 #[derive(Parser)]
 #[command(name = "frel-server")]
 #[command(about = "Frel compiler server - always-compiled daemon", long_about = None)]
@@ -32,6 +52,76 @@ struct Cli {
     /// Exit after first compilation (for CI/scripts)
     #[arg(long)]
     once: bool,
+
+    /// Target language; one of the registered code generators (javascript)
+    #[arg(short, long, default_value = "javascript")]
+    target: String,
+
+    /// Abort builds running longer than this many seconds
+    #[arg(long, value_name = "SECONDS", default_value = "60")]
+    max_build_secs: u64,
+
+    /// Abort builds whose estimated memory use exceeds this many MiB
+    #[arg(long, value_name = "MIB", default_value = "2048")]
+    max_memory_mb: u64,
+
+    /// Skip source files larger than this many KiB
+    #[arg(long, value_name = "KIB", default_value = "4096")]
+    max_file_size_kb: u64,
+
+    /// Wait this many milliseconds without file events before rebuilding a
+    /// burst of saves; overrides `debounce_ms` of frel.toml
+    #[arg(long, value_name = "MS")]
+    debounce_ms: Option<u64>,
+
+    /// Do not watch paths matching this glob, relative to the project
+    /// (e.g. "dist/**"); added to the `ignore` patterns of frel.toml
+    #[arg(long = "watch-ignore", value_name = "PATTERN")]
+    watch_ignore: Vec<String>,
+
+    /// Report the initial build for humans, as a JSON envelope for build tools,
+    /// or as SARIF for code scanning
+    #[arg(long, value_enum, default_value = "human")]
+    message_format: MessageFormat,
+
+    /// Accept the experimental syntax of a feature (e.g. command-results)
+    #[arg(long = "enable-feature", value_name = "NAME")]
+    enable_features: Vec<Experimental>,
+
+    /// Report diagnostics of generated files at their template positions, read
+    /// from a source map next to each file (app.frel.map)
+    #[arg(long)]
+    remap_diagnostics: bool,
+
+    /// Keep theme fields, instruction sets and variants that no module uses
+    /// in the generated code
+    #[arg(long)]
+    keep_unused_styles: bool,
+
+    /// Name generated files by a hash of their contents, listed in
+    /// manifest.json of the build directory
+    #[arg(long)]
+    fingerprint: bool,
+
+    /// Allow a lint, given by code or name: its diagnostics are not reported
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report a lint as a warning; `-W warnings` keeps all warnings
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Report a lint as an error; `-D warnings` denies all warnings
+    #[arg(short = 'D', long = "deny", value_name = "LINT")]
+    deny: Vec<String>,
+
+    /// Report every warning as an error, as `-D warnings`
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Write a session bundle into this directory whenever a build fails
+    #[arg(long, value_name = "DIR")]
+    record: Option<PathBuf>,
 }
 
 #[actix_web::main]
@@ -46,28 +136,128 @@ async fn main() -> Result<()> {
         project_root.join(&cli.output)
     };
 
-    println!("Frel Compiler Server");
-    println!("  Project: {}", project_root.display());
-    println!("  Output:  {}", build_dir.display());
-    println!();
+    let registry = plugins();
+    let codegen = registry.get(&cli.target).ok_or_else(|| {
+        anyhow::anyhow!(
+            "unsupported target: {} (available: {})",
+            cli.target,
+            registry.names().join(", ")
+        )
+    })?;
+
+    // The watcher reloads frel.toml when it changes, as does SIGHUP
+    let config = ProjectConfig::load(&DiskFs, &project_root).map_err(|diagnostics| {
+        for diagnostic in diagnostics.iter() {
+            eprintln!("  {}", diagnostic.message);
+        }
+        anyhow::anyhow!("invalid {} in {}", CONFIG_FILE, project_root.display())
+    })?;
+    let watch_flags = WatchConfig {
+        debounce_ms: cli.debounce_ms,
+        ignore: cli.watch_ignore,
+    };
+    let watch = WatchSettings::load(&DiskFs, &project_root, &config.watch, &watch_flags).map_err(
+        |diagnostics| {
+            for diagnostic in diagnostics.iter() {
+                eprintln!("  {}", diagnostic.message);
+            }
+            anyhow::anyhow!("invalid watch settings for {}", project_root.display())
+        },
+    )?;
+    let features = Features::from_iter(cli.enable_features);
+    let lint_flags = LintLevels::from_flags(&cli.allow, &cli.warn, &cli.deny, cli.deny_warnings)
+        .map_err(anyhow::Error::msg)?;
+
+    // In JSON and SARIF mode stdout only carries the build's report
+    let human = cli.message_format == MessageFormat::Human;
+    if human {
+        println!("Frel Compiler Server");
+        println!("  Project: {}", project_root.display());
+        println!("  Output:  {}", build_dir.display());
+        println!("  Target:  {}", codegen.name());
+        println!("  Edition: {}", config.edition());
+        if features != Features::default() {
+            let names: Vec<&str> = features.iter().map(Experimental::as_str).collect();
+            println!("  Features: {}", names.join(", "));
+        }
+        if let Some(dir) = &cli.record {
+            println!("  Recording failing builds to {}", dir.display());
+        }
+        println!();
+    }
 
     // Create shared state
-    let state = Arc::new(RwLock::new(ProjectState::new(
-        project_root.clone(),
-        build_dir,
-    )));
+    let mut project = ProjectState::new(project_root.clone(), build_dir);
+    if let Some(dir) = cli.record {
+        let session = Session::new(record::TOOL, config.edition(), &features, codegen.name());
+        project.recorder = Some(Recorder::new(dir, session));
+    }
+    project.edition = config.edition();
+    project.features = features;
+    project.codegen = codegen;
+    project.passes = registry.passes().to_vec();
+    project.remap_diagnostics = cli.remap_diagnostics;
+    project.strip_unused_styles = !cli.keep_unused_styles;
+    project.fingerprint = cli.fingerprint;
+    project.limits = Limits {
+        max_build_duration: Duration::from_secs(cli.max_build_secs),
+        max_memory: cli.max_memory_mb * 1024 * 1024,
+        max_file_size: cli.max_file_size_kb * 1024,
+    };
+    project.watch = watch;
+    project.watch_flags = watch_flags;
+    project.lints = config.lints.clone();
+    project.lints.extend(&lint_flags);
+    project.lint_flags = lint_flags;
+    let state = Arc::new(RwLock::new(project));
 
-    // Initial compilation
-    println!("Building project...");
+    // Initial compilation, starting from the signatures of the last run
+    if human {
+        println!("Building project...");
+    }
     let build_result = {
         let mut state = state.write().await;
+        for (path, diagnostics) in compiler::load_signatures(&mut state) {
+            for diagnostic in diagnostics.iter() {
+                eprintln!("  Skipped {}: {}", path.display(), diagnostic.message);
+            }
+        }
         compiler::full_build(&mut state)
     };
 
-    println!(
-        "Build completed in {:?}: {} module(s), {} error(s)",
-        build_result.duration, build_result.modules_built, build_result.error_count
-    );
+    if human {
+        println!(
+            "Build completed in {:?}: {} module(s), {} error(s)",
+            build_result.duration, build_result.modules_built, build_result.error_count
+        );
+        println!(
+            "  Cache: {} hit(s), {} miss(es)",
+            build_result.cache.hits(),
+            build_result.cache.misses()
+        );
+        if let Some(reason) = &build_result.aborted {
+            eprintln!("  Build aborted: {}", reason);
+        }
+        let state = state.read().await;
+        let mut stripped: Vec<_> = state.stripped_styles.values().flatten().collect();
+        if !stripped.is_empty() {
+            stripped.sort_by(|a, b| a.theme.cmp(&b.theme));
+            println!("  Stripped {} unused theme member(s):", stripped.len());
+            for member in stripped {
+                println!("    {}.{} ({})", member.theme, member.name, member.kind.as_str());
+            }
+        }
+    } else {
+        let state = state.read().await;
+        let mut output = compiler::project_output(&state);
+        output.finish(build_result.duration);
+        match cli.message_format {
+            MessageFormat::Sarif => {
+                println!("{}", compiler::project_sarif(&state, &output.diagnostics).to_json())
+            }
+            _ => println!("{}", output.to_json()),
+        }
+    }
 
     if cli.once {
         // Exit after first compilation
@@ -80,22 +270,23 @@ async fn main() -> Result<()> {
     // Start file watcher
     let watcher_state = state.clone();
     let watcher_root = project_root.clone();
-    let watcher_handle = actix_rt::spawn(async move {
-        if let Err(e) = watcher::run_watcher(watcher_state, watcher_root, shutdown_rx).await {
-            eprintln!("File watcher error: {}", e);
-        }
-    });
+    let watcher_handle =
+        actix_rt::spawn(watcher::supervise(watcher_state, watcher_root, shutdown_rx));
 
     // Start HTTP server
-    println!();
-    println!("Server listening on http://localhost:{}", cli.port);
-    println!("Press Ctrl-C to stop");
+    if human {
+        println!();
+        println!("Server listening on http://localhost:{}", cli.port);
+        println!("Press Ctrl-C to stop");
+    }
 
     // Create the server but don't await it yet
+    let signal_state = state.clone();
     let server = server::run_server(state, cli.port)?;
     let server_handle = server.handle();
 
-    // Spawn task to handle shutdown signals (Ctrl-C and SIGTERM)
+    // Spawn task to handle shutdown signals (Ctrl-C and SIGTERM) and
+    // configuration reloads (SIGHUP)
     // Use tokio::spawn (not actix_rt::spawn) for better signal handling
     let signal_handle = server_handle.clone();
     tokio::spawn(async move {
@@ -105,10 +296,17 @@ async fn main() -> Result<()> {
             use tokio::signal::unix::{signal, SignalKind};
             let mut sigint = signal(SignalKind::interrupt()).expect("Failed to register SIGINT handler");
             let mut sigterm = signal(SignalKind::terminate()).expect("Failed to register SIGTERM handler");
+            let mut sighup = signal(SignalKind::hangup()).expect("Failed to register SIGHUP handler");
 
-            let shutdown_reason = tokio::select! {
-                _ = sigint.recv() => "Ctrl-C",
-                _ = sigterm.recv() => "SIGTERM",
+            let shutdown_reason = loop {
+                tokio::select! {
+                    _ = sigint.recv() => break "Ctrl-C",
+                    _ = sigterm.recv() => break "SIGTERM",
+                    _ = sighup.recv() => {
+                        println!("Received SIGHUP, reloading {}", CONFIG_FILE);
+                        watcher::reload_config(&signal_state).await;
+                    }
+                }
             };
 
             println!();
@@ -117,7 +315,8 @@ async fn main() -> Result<()> {
 
         #[cfg(not(unix))]
         {
-            // On non-Unix systems, use ctrl_c
+            // On non-Unix systems, use ctrl_c; there is no SIGHUP to reload on
+            drop(signal_state);
             let _ = tokio::signal::ctrl_c().await;
             println!();
             println!("Received Ctrl-C, shutting down...");
@@ -140,3 +339,11 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Code generators selectable with `--target`, and the semantic passes run on
+/// every module
+fn plugins() -> PluginRegistry {
+    let mut registry = PluginRegistry::new();
+    registry.register(JavaScriptPlugin);
+    registry
+}
@@ -0,0 +1,246 @@
+// Session recording for bug reports
+//
+// With `--record <dir>` the server keeps a session bundle of the files it
+// read and the builds it ran, and writes it into the directory whenever a
+// build ends with errors. `replay` runs a recorded session again from its
+// bundle; `frelc replay` uses it to reproduce server bug reports.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use frel_compiler_core::session::{relative_path, sort_diagnostics, Bundle, Session, Step};
+use frel_compiler_core::FileDiagnostic;
+
+use crate::compiler;
+use crate::state::ProjectState;
+
+/// Name of the recording tool in server session bundles
+pub const TOOL: &str = "frel-server";
+
+/// A session being recorded
+pub struct Recorder {
+    /// Directory the bundle is written into
+    dir: PathBuf,
+    bundle: Bundle,
+    /// Contents of the files as last recorded, by relative path
+    current: BTreeMap<String, String>,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf, session: Session) -> Self {
+        Self {
+            dir,
+            bundle: Bundle {
+                session,
+                files: BTreeMap::new(),
+            },
+            current: BTreeMap::new(),
+        }
+    }
+
+    /// Record a full build over the sources it read
+    ///
+    /// The first build's sources become the bundle's files; later builds
+    /// record the files that changed since as writes.
+    fn build(&mut self, sources: BTreeMap<String, String>) {
+        if self.bundle.session.steps.is_empty() {
+            self.bundle.files = sources.clone();
+        } else {
+            for (file, contents) in &sources {
+                if self.current.get(file) != Some(contents) {
+                    self.write(file, Some(contents.clone()));
+                }
+            }
+            let removed: Vec<String> = self
+                .current
+                .keys()
+                .filter(|file| !sources.contains_key(*file))
+                .cloned()
+                .collect();
+            for file in removed {
+                self.write(&file, None);
+            }
+        }
+        self.current = sources;
+        self.bundle.session.steps.push(Step::Build);
+    }
+
+    /// Record an incremental rebuild of `file`, now holding `contents`
+    fn file_changed(&mut self, file: String, contents: Option<String>) {
+        if self.current.get(&file) != contents.as_ref() {
+            self.write(&file, contents.clone());
+            match contents {
+                Some(contents) => self.current.insert(file.clone(), contents),
+                None => self.current.remove(&file),
+            };
+        }
+        self.bundle.session.steps.push(Step::FileChanged { file });
+    }
+
+    fn write(&mut self, file: &str, contents: Option<String>) {
+        self.bundle.session.steps.push(Step::Write {
+            file: file.to_string(),
+            contents,
+        });
+    }
+}
+
+/// Record the full build that just ran, saving the bundle if it failed
+pub fn record_build(state: &mut ProjectState) {
+    let Some(mut recorder) = state.recorder.take() else {
+        return;
+    };
+    let sources = state
+        .sources
+        .iter()
+        .filter_map(|(path, file)| Some((relative(state, path)?, file.content.clone())))
+        .collect();
+    recorder.build(sources);
+    save_if_failed(state, &mut recorder);
+    state.recorder = Some(recorder);
+}
+
+/// Record the incremental rebuild of `path` that just ran, saving the bundle
+/// if it failed
+pub fn record_file_change(state: &mut ProjectState, path: &Path) {
+    let Some(mut recorder) = state.recorder.take() else {
+        return;
+    };
+    if let Some(file) = relative(state, path) {
+        let contents = state.sources.get(path).map(|file| file.content.clone());
+        recorder.file_changed(file, contents);
+        save_if_failed(state, &mut recorder);
+    }
+    state.recorder = Some(recorder);
+}
+
+fn save_if_failed(state: &ProjectState, recorder: &mut Recorder) {
+    if state.error_count() == 0 {
+        return;
+    }
+    recorder.bundle.session.set_outcome(outcome(state));
+    match recorder.bundle.save(&recorder.dir) {
+        Ok(()) => eprintln!("  Recorded failing build to {}", recorder.dir.display()),
+        Err(e) => eprintln!("  Failed to record session: {}", e),
+    }
+}
+
+/// Replay a recorded server session in `state`, whose root must be an
+/// empty directory, and return the diagnostics it ends with
+pub fn replay(bundle: &Bundle, state: &mut ProjectState) -> io::Result<Vec<FileDiagnostic>> {
+    for (file, contents) in &bundle.files {
+        write_file(&state.root, file, Some(contents))?;
+    }
+    for step in &bundle.session.steps {
+        match step {
+            Step::Build => {
+                compiler::full_build(state);
+            }
+            Step::Write { file, contents } => write_file(&state.root, file, contents.as_deref())?,
+            Step::FileChanged { file } => {
+                let path = state.root.join(relative_path(file)?);
+                compiler::handle_file_change(state, &path);
+            }
+            Step::Compile { .. } => {
+                return Err(io::Error::other(
+                    "not a server session: it compiles single files",
+                ));
+            }
+        }
+    }
+    Ok(outcome(state))
+}
+
+fn write_file(root: &Path, file: &str, contents: Option<&str>) -> io::Result<()> {
+    let path = root.join(relative_path(file)?);
+    match contents {
+        Some(contents) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)
+        }
+        None => fs::remove_file(path).or_else(|e| match e.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        }),
+    }
+}
+
+/// The project's diagnostics, with paths relative to the project root
+fn outcome(state: &ProjectState) -> Vec<FileDiagnostic> {
+    let mut diagnostics = compiler::project_output(state).diagnostics;
+    for diagnostic in &mut diagnostics {
+        if let Some(file) = relative(state, Path::new(&diagnostic.file)) {
+            diagnostic.file = file;
+        }
+    }
+    sort_diagnostics(&mut diagnostics);
+    diagnostics
+}
+
+/// Path of a project file relative to the root, with `/` separators
+fn relative(state: &ProjectState, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(&state.root).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frel_compiler_core::{Edition, Features};
+
+    #[test]
+    fn test_record_and_replay() {
+        let root = std::env::temp_dir().join(format!("frel-record-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (project, bundle_dir, replay_dir) = (
+            root.join("project"),
+            root.join("bundle"),
+            root.join("replay"),
+        );
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&replay_dir).unwrap();
+
+        // A session that breaks an import by renaming the backend
+        let data = project.join("data.frel");
+        fs::write(
+            &data,
+            "module test.data\n\nbackend Store {\n    count: i32 = 0\n}\n",
+        )
+        .unwrap();
+        let app =
+            "module test.app\n\nimport test.data.Store\n\nblueprint Main {\n    with Store\n}\n";
+        fs::write(project.join("app.frel"), app).unwrap();
+
+        let mut state = ProjectState::new(project.clone(), project.join("build"));
+        let session = Session::new(TOOL, Edition::LATEST, &Features::default(), "javascript");
+        state.recorder = Some(Recorder::new(bundle_dir.clone(), session));
+        compiler::full_build(&mut state);
+        assert!(!bundle_dir.exists(), "successful builds are not recorded");
+
+        fs::write(
+            &data,
+            "module test.data\n\nbackend Storage {\n    count: i32 = 0\n}\n",
+        )
+        .unwrap();
+        compiler::handle_file_change(&mut state, &data);
+        assert!(state.error_count() > 0);
+
+        let bundle = Bundle::load(&bundle_dir).unwrap();
+        assert_eq!(bundle.files.len(), 2);
+        assert_eq!(bundle.session.steps.len(), 3);
+        assert_eq!(bundle.session.outcome[0].file, "app.frel");
+
+        let mut replay_state = ProjectState::new(replay_dir.clone(), replay_dir.join("build"));
+        let replayed = replay(&bundle, &mut replay_state).unwrap();
+        assert!(frel_compiler_core::session::same_diagnostics(
+            &bundle.session.outcome,
+            &replayed
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -14,13 +14,22 @@ pub fn run_server(state: SharedState, port: u16) -> std::io::Result<Server> {
             .route("/status", web::get().to(api::get_status))
             .route("/modules", web::get().to(api::get_modules))
             .route("/diagnostics", web::get().to(api::get_all_diagnostics))
+            .route("/diagnostics-summary", web::get().to(api::get_diagnostics_summary))
             .route("/diagnostics/{module:.*}", web::get().to(api::get_module_diagnostics))
             .route("/ast/{module:.*}", web::get().to(api::get_module_ast))
             .route("/generated/{module:.*}", web::get().to(api::get_module_generated))
+            .route("/hot-reload/{module:.*}", web::get().to(api::get_module_hot_reload))
+            .route("/build/{path:.*}", web::get().to(api::get_build_file))
             .route("/scope/{module:.*}", web::get().to(api::get_module_scope))
+            .route("/scope-graph/{module:.*}", web::get().to(api::get_module_scope_graph))
+            .route("/symbols", web::get().to(api::get_symbols))
+            .route("/report/{module:.*}", web::get().to(api::get_module_report))
             .route("/source/{path:.*}", web::get().to(api::get_source))
             .route("/notify", web::post().to(api::post_notify))
             .route("/write", web::post().to(api::post_write))
+            .route("/overlays", web::get().to(api::get_overlays))
+            .route("/overlay", web::post().to(api::post_overlay))
+            .route("/overlay/clear", web::post().to(api::post_clear_overlay))
             .route("/events", web::get().to(api::get_events))
             // Expectations endpoints (compiler dev mode)
             .route("/expectations/{module:.*}/save", web::post().to(api::save_expectations))
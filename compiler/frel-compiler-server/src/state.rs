@@ -4,39 +4,250 @@
 
 use std::collections::{HashMap, HashSet};
 use std::hash::{DefaultHasher, Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use frel_compiler_core::edition::WatchConfig;
+use frel_compiler_core::source::{FileId, LineCol, Span};
+use frel_compiler_core::source_map::{OriginalPosition, SourceMap};
 use frel_compiler_core::{
-    ast, Diagnostics, ModuleAnalysisResult, ModuleSignature, SignatureRegistry, SignatureResult,
+    ast, Diagnostic, Diagnostics, DiskFs, Edition, Features, LintLevels, ModuleAnalysisResult, ModuleSignature, OutputSink, SignatureRegistry,
+    SignatureResult, SourceProvider, ThemeUsage, UnusedMember,
 };
-use tokio::sync::RwLock;
+use frel_compiler_plugin_api::{AssetManifest, CodegenPlugin, OutputFile, PluginContext, SemanticPass};
+use frel_compiler_plugin_javascript::JavaScriptPlugin;
+use tokio::sync::{broadcast, RwLock};
+
+use crate::events::CompilationEvent;
+use crate::record::Recorder;
 
 /// Shared state wrapper for async access
 pub type SharedState = Arc<RwLock<ProjectState>>;
 
+/// Memory held per byte of source by its AST and analysis, for
+/// `ProjectState::memory_estimate`
+pub const AST_BYTES_PER_SOURCE_BYTE: u64 = 32;
+
+/// Events buffered per subscriber before a slow one starts missing events
+pub const EVENT_CAPACITY: usize = 256;
+
+/// Resource limits of the compiler server
+///
+/// A build that exceeds one is aborted with an error diagnostic and a
+/// `BuildAborted` event instead of hanging the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest a build may run; checked before each module
+    pub max_build_duration: Duration,
+    /// Largest `ProjectState::memory_estimate` a build may start analysis with
+    pub max_memory: u64,
+    /// Largest source file compiled; bigger files are skipped with an error
+    pub max_file_size: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_build_duration: Duration::from_secs(60),
+            max_memory: 2 * 1024 * 1024 * 1024,
+            max_file_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Quiet time after a file event before the watcher rebuilds
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Paths never watched, in addition to the configured ones
+pub const DEFAULT_IGNORE: &[&str] = &["**/.git", "**/node_modules"];
+
+/// File watching settings of the compiler server
+///
+/// Combined from the defaults, the project's `.gitignore`, the `[watch]`
+/// table of `frel.toml` and the command line, which wins over `frel.toml`.
+#[derive(Debug, Clone)]
+pub struct WatchSettings {
+    /// Quiet time after the last event of a burst before it is rebuilt
+    pub debounce: Duration,
+    /// Patterns of the paths, relative to the project root, not watched
+    pub ignore: Vec<glob::Pattern>,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            debounce: DEFAULT_DEBOUNCE,
+            ignore: DEFAULT_IGNORE
+                .iter()
+                .map(|pattern| glob::Pattern::new(pattern).unwrap())
+                .collect(),
+        }
+    }
+}
+
+impl WatchSettings {
+    /// Settings of the project at `root` from its `frel.toml` (`config`) and
+    /// the command line (`flags`); an invalid pattern is an error
+    pub fn load(
+        files: &dyn SourceProvider,
+        root: &Path,
+        config: &WatchConfig,
+        flags: &WatchConfig,
+    ) -> Result<WatchSettings, Diagnostics> {
+        let gitignore = files.read(&root.join(".gitignore")).unwrap_or_default();
+        let patterns = gitignore_patterns(&gitignore)
+            .into_iter()
+            .chain(config.ignore.iter().cloned())
+            .chain(flags.ignore.iter().cloned());
+
+        let mut settings = WatchSettings::default();
+        let mut diagnostics = Diagnostics::new();
+        for pattern in patterns {
+            match glob::Pattern::new(&pattern) {
+                Ok(compiled) => settings.ignore.push(compiled),
+                Err(e) => diagnostics.add(Diagnostic::error(
+                    format!("invalid ignore pattern \"{}\": {}", pattern, e.msg),
+                    Span::default(),
+                )),
+            }
+        }
+        if diagnostics.has_errors() {
+            return Err(diagnostics);
+        }
+        if let Some(ms) = flags.debounce_ms.or(config.debounce_ms) {
+            settings.debounce = Duration::from_millis(ms);
+        }
+        Ok(settings)
+    }
+
+    /// Check if `path` or one of its directories under `root` is ignored
+    pub fn is_ignored(&self, root: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        relative
+            .ancestors()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| {
+                self.ignore
+                    .iter()
+                    .any(|pattern| pattern.matches_path_with(dir, options))
+            })
+    }
+}
+
+/// Translate the lines of a `.gitignore` into glob patterns
+///
+/// Comments and negations are skipped. A pattern with a slash is anchored
+/// at the root, one without matches at any depth.
+pub fn gitignore_patterns(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .map(|line| {
+            let line = line.trim_end_matches('/');
+            match line.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if line.contains('/') => line.to_string(),
+                None => format!("**/{}", line),
+            }
+        })
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
 /// Main project compilation state
 pub struct ProjectState {
     /// Project root directory
     pub root: PathBuf,
     /// Build output directory
     pub build_dir: PathBuf,
+    /// Where sources, `frel.toml`, source maps and saved signatures are read
+    pub files: Arc<dyn SourceProvider>,
+    /// Where generated files and signatures are written
+    pub sink: Arc<dyn OutputSink>,
+    /// Edition from the project's frel.toml
+    pub edition: Edition,
+    /// Experimental features enabled on the command line
+    pub features: Features,
+    /// Code generator of the build target
+    pub codegen: Arc<dyn CodegenPlugin>,
+    /// External semantic passes, run on every analyzed module
+    pub passes: Vec<Arc<dyn SemanticPass>>,
+    /// Resource limits after which a build is aborted
+    pub limits: Limits,
+    /// Debounce and ignore patterns of the file watcher
+    pub watch: WatchSettings,
+    /// Watch settings given on the command line, applied again over
+    /// `frel.toml` when it is reloaded
+    pub watch_flags: WatchConfig,
+    /// Lint levels of `frel.toml` with the command line's applied over them
+    pub lints: LintLevels,
+    /// Lint levels given on the command line, applied again over
+    /// `frel.toml` when it is reloaded
+    pub lint_flags: LintLevels,
+    /// Errors of the resource limits: per skipped file, and for an aborted
+    /// build under the project root
+    pub limit_diagnostics: HashMap<PathBuf, Diagnostics>,
+    /// Errors of `frel.toml` while it is invalid; the configuration loaded
+    /// before stays in effect until it is fixed
+    pub config_diagnostics: Diagnostics,
+    /// Compilation events, streamed to clients of `GET /events`
+    pub events: broadcast::Sender<CompilationEvent>,
+    /// Session recorder, when started with `--record`
+    pub recorder: Option<Recorder>,
+    /// Report diagnostics of generated files at their template positions
+    pub remap_diagnostics: bool,
+    /// Omit theme members no module uses from the generated code
+    pub strip_unused_styles: bool,
+    /// Name generated files by a hash of their contents, listed in the
+    /// build directory's manifest.json
+    pub fingerprint: bool,
+    /// Fingerprinted names of the generated files, by logical name
+    pub manifest: AssetManifest,
+    /// Member names read in the project's sources, deciding what is stripped
+    pub theme_usage: ThemeUsage,
+    /// Theme members stripped from the last generated code of each module
+    pub stripped_styles: HashMap<String, Vec<UnusedMember>>,
+    /// Source maps of generated files, loaded when remapping diagnostics
+    pub source_maps: HashMap<PathBuf, SourceMap>,
     /// Source files: path -> content + hash
     pub sources: HashMap<PathBuf, FileState>,
+    /// In-memory overrides of file contents (unsaved editor buffers)
+    pub overlays: HashMap<PathBuf, Overlay>,
+    /// Artifacts written by the compiler (never treated as source changes)
+    pub emitted: HashSet<PathBuf>,
     /// Module index: file <-> module mapping
     pub module_index: ModuleIndex,
     /// Dependency graph for invalidation
     pub dependencies: DependencyGraph,
     /// Parse cache: path -> AST + diagnostics
     pub parse_cache: HashMap<PathBuf, ParseCacheEntry>,
+    /// File IDs the sources are parsed with, kept for the session so spans
+    /// of a module's files stay apart in its analysis
+    pub file_ids: HashMap<PathBuf, FileId>,
     /// Signature cache: module -> SignatureResult
     pub signature_cache: HashMap<String, SignatureCacheEntry>,
     /// Analysis cache: module -> AnalysisResult + generated JS
     pub analysis_cache: HashMap<String, AnalysisCacheEntry>,
-    /// Current signature registry
-    pub registry: SignatureRegistry,
+    /// Backend members removed by an edit, per module, while they stay removed
+    pub removed_members: HashMap<String, Vec<RemovedMember>>,
+    /// Last files generated per module, kept while the module has errors so
+    /// hot reload can serve the last working version
+    pub artifacts: HashMap<String, ModuleArtifacts>,
+    /// Current signature registry, shared with readers outside the state lock
+    pub registry: Arc<SignatureRegistry>,
     /// Generation counter for cache invalidation
     pub generation: u64,
+    /// Number of builds and configuration reloads started; it changes
+    /// whenever the diagnostics or status may have, so polled endpoints
+    /// use it as their `ETag`
+    pub build_id: u64,
     /// Whether initial compilation is complete
     pub initialized: bool,
 }
@@ -46,18 +257,64 @@ impl ProjectState {
         Self {
             root,
             build_dir,
+            files: Arc::new(DiskFs),
+            sink: Arc::new(DiskFs),
+            edition: Edition::LATEST,
+            features: Features::default(),
+            codegen: Arc::new(JavaScriptPlugin),
+            passes: Vec::new(),
+            limits: Limits::default(),
+            watch: WatchSettings::default(),
+            watch_flags: WatchConfig::default(),
+            lints: LintLevels::new(),
+            lint_flags: LintLevels::new(),
+            limit_diagnostics: HashMap::new(),
+            config_diagnostics: Diagnostics::new(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            recorder: None,
+            remap_diagnostics: false,
+            strip_unused_styles: true,
+            fingerprint: false,
+            manifest: AssetManifest::new(),
+            theme_usage: ThemeUsage::new(),
+            stripped_styles: HashMap::new(),
+            source_maps: HashMap::new(),
             sources: HashMap::new(),
+            overlays: HashMap::new(),
+            emitted: HashSet::new(),
             module_index: ModuleIndex::new(),
             dependencies: DependencyGraph::new(),
             parse_cache: HashMap::new(),
+            file_ids: HashMap::new(),
             signature_cache: HashMap::new(),
             analysis_cache: HashMap::new(),
-            registry: SignatureRegistry::new(),
+            removed_members: HashMap::new(),
+            artifacts: HashMap::new(),
+            registry: Arc::new(SignatureRegistry::new()),
             generation: 0,
+            build_id: 0,
             initialized: false,
         }
     }
 
+    /// Read and write the project's files through `files` and `sink`
+    /// instead of the disk, e.g. both a `MemoryFs`
+    pub fn with_files(mut self, files: Arc<dyn SourceProvider>, sink: Arc<dyn OutputSink>) -> Self {
+        self.files = files;
+        self.sink = sink;
+        self
+    }
+
+    /// Send an event to every subscriber; it is dropped when there are none
+    pub fn publish(&self, event: CompilationEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Receive the events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<CompilationEvent> {
+        self.events.subscribe()
+    }
+
     /// Get total error count across all modules
     /// Only counts parse_cache + analysis_cache to avoid duplicate counting
     /// (signature_cache resolve errors are re-reported in analysis_cache)
@@ -69,13 +326,190 @@ impl ProjectState {
         for entry in self.analysis_cache.values() {
             count += entry.result.diagnostics.error_count();
         }
+        for diagnostics in self.limit_diagnostics.values() {
+            count += diagnostics.error_count();
+        }
+        count += self.config_diagnostics.error_count();
         count
     }
 
+    /// Size of a source file in bytes, from its overlay or the project's files
+    pub fn source_size(&self, path: &Path) -> std::io::Result<u64> {
+        match self.overlays.get(path) {
+            Some(overlay) if !overlay.is_expired() => Ok(overlay.content.len() as u64),
+            _ => self.files.size(path),
+        }
+    }
+
+    /// Rough estimate of the memory held for the project's sources, in bytes
+    ///
+    /// Each source byte is counted once for the text and `AST_BYTES_PER_SOURCE_BYTE`
+    /// times for its AST, scopes and types.
+    pub fn memory_estimate(&self) -> u64 {
+        let source_bytes: u64 = self.sources.values().map(|s| s.content.len() as u64).sum();
+        source_bytes * (1 + AST_BYTES_PER_SOURCE_BYTE)
+    }
+
     /// Get all modules
     pub fn modules(&self) -> Vec<&str> {
         self.module_index.all_modules()
     }
+
+    /// The file ID of a source, assigned the first time it is parsed
+    pub fn file_id(&mut self, path: &Path) -> FileId {
+        let next = FileId(self.file_ids.len() as u32 + 1);
+        *self.file_ids.entry(path.to_path_buf()).or_insert(next)
+    }
+
+    /// The file of `module` that spans in `file` point into
+    ///
+    /// Spans in no file of the module, like those of external passes, belong
+    /// to its first file.
+    pub fn module_file(&self, module: &str, file: FileId) -> Option<&PathBuf> {
+        let files = self.module_index.files_for_module(module);
+        files
+            .iter()
+            .find(|path| self.file_ids.get(*path) == Some(&file))
+            .or_else(|| files.first())
+    }
+
+    /// Read a source file, preferring a live overlay over the project's files
+    pub fn read_source(&self, path: &Path) -> std::io::Result<String> {
+        match self.overlays.get(path) {
+            Some(overlay) if !overlay.is_expired() => Ok(overlay.content.clone()),
+            _ => self.files.read(path),
+        }
+    }
+
+    /// Reload the source map next to a source file, if diagnostics are remapped
+    pub fn load_source_map(&mut self, path: &Path) {
+        if !self.remap_diagnostics {
+            return;
+        }
+        match SourceMap::load_sidecar(self.files.as_ref(), path) {
+            Some(Ok(map)) => {
+                self.source_maps.insert(path.to_path_buf(), map);
+            }
+            Some(Err(message)) => {
+                eprintln!("  Ignoring {}", message);
+                self.source_maps.remove(path);
+            }
+            None => {
+                self.source_maps.remove(path);
+            }
+        }
+    }
+
+    /// Template position of a position in the source file at `path`, if its
+    /// source map covers it
+    pub fn original_position(&self, path: &Path, position: LineCol) -> Option<OriginalPosition> {
+        self.source_maps.get(path)?.original(position)
+    }
+
+    /// Override a file's contents in memory until cleared or the TTL elapses
+    pub fn set_overlay(&mut self, path: PathBuf, content: String, ttl: Option<Duration>) {
+        self.overlays.insert(path, Overlay::new(content, ttl));
+    }
+
+    /// Remove the overlay for a file, returning whether one was present
+    pub fn clear_overlay(&mut self, path: &Path) -> bool {
+        self.overlays.remove(path).is_some()
+    }
+
+    /// Remove all overlays, returning the affected paths
+    pub fn clear_overlays(&mut self) -> Vec<PathBuf> {
+        self.overlays.drain().map(|(path, _)| path).collect()
+    }
+
+    /// Check if any overlay has outlived its TTL
+    pub fn has_expired_overlays(&self) -> bool {
+        self.overlays.values().any(Overlay::is_expired)
+    }
+
+    /// Remove overlays that have outlived their TTL, returning the affected paths
+    pub fn take_expired_overlays(&mut self) -> Vec<PathBuf> {
+        let expired: Vec<PathBuf> = self
+            .overlays
+            .iter()
+            .filter(|(_, overlay)| overlay.is_expired())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &expired {
+            self.overlays.remove(path);
+        }
+        expired
+    }
+
+    /// Check if a path is compiler output rather than project source
+    ///
+    /// Anything under the build directory counts, as does any artifact the
+    /// compiler has written elsewhere in the tree.
+    pub fn is_build_output(&self, path: &Path) -> bool {
+        path.starts_with(&self.build_dir) || self.emitted.contains(path)
+    }
+
+    /// Check if the file watcher ignores `path`
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.watch.is_ignored(&self.root, path)
+    }
+
+    /// Where a generated file is written: its fingerprinted name once it has
+    /// one, or its logical path under the build directory
+    pub fn output_path(&self, file: &OutputFile) -> PathBuf {
+        let path = self.manifest.get(&file.path).unwrap_or_else(|| file.path.clone());
+        self.build_dir.join(path)
+    }
+
+    /// What the plugins may access: the project, the build directory and
+    /// the `FREL_` environment variables
+    pub fn plugin_context(&self) -> PluginContext {
+        PluginContext::new()
+            .with_project_root(&self.root)
+            .with_build_dir(&self.build_dir)
+            .with_process_env()
+    }
+
+    /// Directory the module signatures are saved to between runs
+    pub fn signature_dir(&self) -> PathBuf {
+        self.build_dir.join("signatures")
+    }
+
+    /// Paths of live overlays (these may not exist on disk)
+    pub fn overlay_paths(&self) -> Vec<PathBuf> {
+        self.overlays
+            .iter()
+            .filter(|(_, overlay)| !overlay.is_expired())
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// In-memory override of a file's contents
+///
+/// Overlays take precedence over the disk during builds so diagnostics
+/// reflect what the user sees in the editor, even before saving.
+pub struct Overlay {
+    /// Buffer content
+    pub content: String,
+    /// When the overlay was set
+    pub created: Instant,
+    /// How long the overlay stays live (None = until cleared)
+    pub ttl: Option<Duration>,
+}
+
+impl Overlay {
+    pub fn new(content: String, ttl: Option<Duration>) -> Self {
+        Self {
+            content,
+            created: Instant::now(),
+            ttl,
+        }
+    }
+
+    /// Check if the overlay has outlived its TTL
+    pub fn is_expired(&self) -> bool {
+        self.ttl.is_some_and(|ttl| self.created.elapsed() >= ttl)
+    }
 }
 
 /// State for a single source file
@@ -104,6 +538,8 @@ pub struct ParseCacheEntry {
     pub diagnostics: Diagnostics,
     /// Content hash when parsed
     pub content_hash: u64,
+    /// Hash of the parsed AST (unchanged by edits that don't affect the tree)
+    pub ast_hash: u64,
 }
 
 /// Cached signature result for a module
@@ -112,20 +548,53 @@ pub struct SignatureCacheEntry {
     pub result: SignatureResult,
     /// Hash of exports for change detection
     pub exports_hash: u64,
+    /// Hash of the full signature (exports and members) for dependent invalidation
+    pub signature_hash: u64,
+    /// Hash of the module's ASTs the signature was built from
+    pub source_hash: u64,
     /// Generation when cached
     pub generation: u64,
 }
 
+/// A member an edit removed from a backend, which importers may still use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedMember {
+    pub backend: String,
+    pub member: String,
+}
+
 /// Cached analysis result for a module
 pub struct AnalysisCacheEntry {
     /// Full analysis result
     pub result: ModuleAnalysisResult,
-    /// Generated JavaScript code
-    pub generated_js: String,
+    /// Files generated for the module, the main file first
+    pub outputs: Vec<OutputFile>,
+    /// Hash of the module's ASTs and its dependencies' signatures
+    pub input_hash: u64,
     /// Generation when cached
     pub generation: u64,
 }
 
+impl AnalysisCacheEntry {
+    /// Contents of the module's main generated file; empty if none
+    pub fn code(&self) -> String {
+        self.outputs
+            .first()
+            .map(|file| file.contents.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Files generated for a module by its last successful build
+pub struct ModuleArtifacts {
+    /// Generated files, the main file first
+    pub outputs: Vec<OutputFile>,
+    /// Hash of the files' paths and contents
+    pub hash: u64,
+    /// Generation of the build that generated them
+    pub generation: u64,
+}
+
 /// Maps files to modules and vice versa
 pub struct ModuleIndex {
     /// File path -> module path
@@ -149,6 +618,9 @@ impl ModuleIndex {
             if old_module != module_path {
                 if let Some(files) = self.module_to_files.get_mut(old_module) {
                     files.retain(|p| p != path);
+                    if files.is_empty() {
+                        self.module_to_files.remove(old_module);
+                    }
                 }
             }
         }
@@ -156,12 +628,13 @@ impl ModuleIndex {
         self.file_to_module
             .insert(path.clone(), module_path.to_owned());
 
-        // Only add to module's file list if not already present
+        // Only add to module's file list if not already present, keeping it
+        // sorted so modules are built from their files in a stable order
         let files = self.module_to_files
             .entry(module_path.to_owned())
             .or_default();
-        if !files.contains(path) {
-            files.push(path.clone());
+        if let Err(index) = files.binary_search(path) {
+            files.insert(index, path.clone());
         }
     }
 
@@ -190,9 +663,11 @@ impl ModuleIndex {
         self.file_to_module.get(path).map(|s| s.as_str())
     }
 
-    /// Get all module paths
+    /// Get all module paths, sorted
     pub fn all_modules(&self) -> Vec<&str> {
-        self.module_to_files.keys().map(|s| s.as_str()).collect()
+        let mut modules: Vec<&str> = self.module_to_files.keys().map(|s| s.as_str()).collect();
+        modules.sort_unstable();
+        modules
     }
 }
 
@@ -267,6 +742,20 @@ impl DependencyGraph {
             .unwrap_or_else(HashSet::new)
     }
 
+    /// Forget a module's own imports, keeping the edges of its importers
+    ///
+    /// Used when a module disappears: its importers still import it, so they
+    /// must be rebuilt if it comes back.
+    pub fn remove_imports(&mut self, module: &str) {
+        if let Some(imports) = self.imports.remove(module) {
+            for imported in imports {
+                if let Some(set) = self.importers.get_mut(&imported) {
+                    set.remove(module);
+                }
+            }
+        }
+    }
+
     /// Remove a module from the graph
     pub fn remove_module(&mut self, module: &str) {
         // Remove from importers of modules this one imports
@@ -295,6 +784,31 @@ pub fn hash_content(content: &str) -> u64 {
     hasher.finish()
 }
 
+/// Compute a hash of generated files, to tell clients whether they changed
+pub fn hash_outputs(outputs: &[OutputFile]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for file in outputs {
+        file.path.hash(&mut hasher);
+        file.contents.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Compute a hash of a parsed AST for change detection
+///
+/// Unlike the content hash, this ignores comments whose text changes but not
+/// their length, and whitespace after the last token; the tree is hashed with
+/// its spans, so any other edit shifts the spans after it.
+pub fn hash_ast(file: &ast::File) -> u64 {
+    // Comments are kept for the formatter but don't affect analysis
+    let file = ast::File {
+        comments: Vec::new(),
+        ..file.clone()
+    };
+    let json = serde_json::to_string(&file).unwrap_or_default();
+    hash_content(&json)
+}
+
 /// Compute a hash of a module's exports for invalidation detection
 pub fn hash_exports(signature: &ModuleSignature) -> u64 {
     let mut hasher = DefaultHasher::new();
@@ -305,3 +819,34 @@ pub fn hash_exports(signature: &ModuleSignature) -> u64 {
     }
     hasher.finish()
 }
+
+/// Compute a hash of a module's full signature for invalidation detection
+///
+/// Covers exported declarations, every member symbol and the declared types
+/// of members, but not spans: importers only see names, kinds, types and
+/// scope structure.
+pub fn hash_signature(signature: &ModuleSignature) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    signature.path.hash(&mut hasher);
+    for export in &signature.exports {
+        export.name.hash(&mut hasher);
+        std::mem::discriminant(&export.kind).hash(&mut hasher);
+        export.symbol_id.0.hash(&mut hasher);
+        export.body_scope.map(|s| s.0).hash(&mut hasher);
+    }
+    for scope in signature.scopes.iter() {
+        scope.id.0.hash(&mut hasher);
+        std::mem::discriminant(&scope.kind).hash(&mut hasher);
+        scope.parent.map(|s| s.0).hash(&mut hasher);
+        scope.name.hash(&mut hasher);
+    }
+    for symbol in signature.symbols.iter() {
+        symbol.name.hash(&mut hasher);
+        std::mem::discriminant(&symbol.kind).hash(&mut hasher);
+        symbol.scope.0.hash(&mut hasher);
+        symbol.body_scope.map(|s| s.0).hash(&mut hasher);
+        symbol.source_module.hash(&mut hasher);
+    }
+    signature.member_types.hash(&mut hasher);
+    hasher.finish()
+}
@@ -1,15 +1,48 @@
 // File watching using notify crate
+//
+// `supervise` keeps the watcher running for the life of the server: if it
+// fails or panics it is restarted after a short delay.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::sync::watch;
 
-use crate::compiler;
-use crate::state::SharedState;
+use frel_compiler_core::edition::CONFIG_FILE;
+
+use crate::compiler::{self, BuildResult, ConfigReload};
+use crate::events::CompilationEvent;
+use crate::state::{ProjectState, SharedState};
+
+/// Delay before restarting a watcher that died
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Debounce windows after which a burst of events is rebuilt even if it
+/// has not gone quiet
+const MAX_BURST_WINDOWS: u32 = 20;
+
+/// Run the file watcher until shutdown, restarting it whenever it dies
+pub async fn supervise(state: SharedState, root: PathBuf, shutdown: watch::Receiver<bool>) {
+    let mut restarts = 0;
+    loop {
+        let task = actix_rt::spawn(run_watcher(state.clone(), root.clone(), shutdown.clone()));
+        match task.await {
+            Ok(Ok(())) if *shutdown.borrow() => break,
+            Ok(Ok(())) => eprintln!("File watcher stopped unexpectedly"),
+            Ok(Err(e)) => eprintln!("File watcher error: {}", e),
+            Err(e) => eprintln!("File watcher panicked: {}", e),
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+        restarts += 1;
+        eprintln!("Restarting file watcher (restart {})", restarts);
+        tokio::time::sleep(RESTART_DELAY).await;
+    }
+}
 
 /// Run the file watcher with shutdown support
 pub async fn run_watcher(
@@ -48,63 +81,304 @@ pub async fn run_watcher(
         // Use recv_timeout to allow periodic shutdown checks
         match rx.recv_timeout(Duration::from_millis(200)) {
             Ok(event) => {
-                // Filter for .frel files and clone them
-                let frel_paths: Vec<_> = event
-                    .paths
-                    .iter()
-                    .filter(|p| p.extension().map(|e| e == "frel").unwrap_or(false))
-                    .cloned()
-                    .collect();
-
-                if frel_paths.is_empty() {
+                // Debounce: coalesce a burst of events until it goes quiet
+                let debounce = state.read().await.watch.debounce;
+                let all_paths = collect_burst(&rx, event.paths, debounce);
+
+                // A new configuration or a moved directory changes the module
+                // set: rebuild the whole project instead of single files
+                let (config, rescan) = {
+                    let state = state.read().await;
+                    (config_changed(&state, &all_paths), needs_rescan(&state, &all_paths))
+                };
+                if config {
+                    println!("{} changed, reloading", CONFIG_FILE);
+                    reload_config(&state).await;
                     continue;
                 }
-
-                // Debounce: collect all events for a short period
-                let mut all_paths = frel_paths;
-                while let Ok(more_event) = rx.recv_timeout(Duration::from_millis(50)) {
-                    let more_frel: Vec<_> = more_event
-                        .paths
-                        .iter()
-                        .filter(|p| p.extension().map(|e| e == "frel").unwrap_or(false))
-                        .cloned()
-                        .collect();
-                    all_paths.extend(more_frel);
+                if rescan {
+                    println!("Project directories changed, rescanning modules");
+                    let result = compiler::full_build(&mut *state.write().await);
+                    report_rebuild(&result);
+                    continue;
                 }
 
-                // Deduplicate paths
-                let mut unique_paths: Vec<_> = all_paths;
-                unique_paths.sort();
-                unique_paths.dedup();
+                // Keep only source files, ignoring our own build output
+                let unique_paths = source_changes(&*state.read().await, &all_paths);
+                if unique_paths.is_empty() {
+                    continue;
+                }
 
-                // Process each changed file
-                for path in unique_paths {
+                // Rebuild all files of the burst at once
+                for path in &unique_paths {
                     println!("File changed: {}", path.display());
+                }
+                let result = {
+                    let mut state = state.write().await;
+                    compiler::handle_file_changes(&mut state, &unique_paths)
+                };
 
-                    let result = {
-                        let mut state = state.write().await;
-                        compiler::handle_file_change(&mut state, &path)
-                    };
-
-                    if !result.modules_rebuilt.is_empty() {
-                        println!(
-                            "  Rebuilt {} module(s) in {:?}, {} error(s)",
-                            result.modules_rebuilt.len(),
-                            result.duration,
-                            result.error_count
-                        );
+                for event in &result.events {
+                    match event {
+                        CompilationEvent::ModuleRemoved { module } => {
+                            println!("  Removed module {}", module);
+                        }
+                        CompilationEvent::BuildAborted { reason } => {
+                            eprintln!("  Build aborted: {}", reason);
+                        }
+                        _ => {}
                     }
                 }
+
+                if !result.modules_rebuilt.is_empty() {
+                    println!(
+                        "  Rebuilt {} module(s) in {:?}, {} error(s)",
+                        result.modules_rebuilt.len(),
+                        result.duration,
+                        result.error_count
+                    );
+                }
             }
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                // No events, continue
+                // No events - use the idle time to drop overlays whose TTL elapsed
+                let has_expired = state.read().await.has_expired_overlays();
+                if has_expired {
+                    let mut state = state.write().await;
+                    let expired = state.take_expired_overlays();
+                    for path in &expired {
+                        println!("Overlay expired: {}", path.display());
+                    }
+                    compiler::handle_file_changes(&mut state, &expired);
+                }
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
-                // Channel closed, exit
-                break;
+                // The notify backend died; the supervisor restarts the watcher
+                anyhow::bail!("file system event channel closed");
             }
         }
     }
 
     Ok(())
 }
+
+/// Collect the paths of a burst of events, starting with `paths`
+///
+/// The burst ends once no event arrived for `debounce`, so a save that
+/// writes several files, or an editor saving repeatedly, is rebuilt once.
+/// A burst that never goes quiet is cut after `MAX_BURST_WINDOWS` windows.
+pub fn collect_burst(
+    rx: &mpsc::Receiver<Event>,
+    mut paths: Vec<PathBuf>,
+    debounce: Duration,
+) -> Vec<PathBuf> {
+    let deadline = Instant::now() + debounce * MAX_BURST_WINDOWS;
+    while Instant::now() < deadline {
+        match rx.recv_timeout(debounce) {
+            Ok(event) => paths.extend(event.paths),
+            // A closed channel is reported by the next receive of the loop
+            Err(_) => break,
+        }
+    }
+    paths
+}
+
+/// Reload `frel.toml` and rebuild the project, reporting the outcome
+///
+/// Called by the watcher when the file changes and on SIGHUP.
+pub async fn reload_config(state: &SharedState) {
+    let reload = compiler::reload_config(&mut *state.write().await);
+    match reload {
+        ConfigReload::Reloaded {
+            edition_changed,
+            build,
+        } => {
+            if edition_changed {
+                println!("  Edition changed, all files parsed again");
+            }
+            report_rebuild(&build);
+        }
+        ConfigReload::Rejected { error_count } => eprintln!(
+            "  Invalid {}, {} error(s); keeping the previous configuration",
+            CONFIG_FILE, error_count
+        ),
+    }
+}
+
+/// Print the outcome of a full rebuild started by the watcher
+fn report_rebuild(result: &BuildResult) {
+    match &result.aborted {
+        Some(reason) => eprintln!("  Build aborted: {}", reason),
+        None => println!(
+            "  Rebuilt {} module(s) in {:?}, {} error(s)",
+            result.modules_built, result.duration, result.error_count
+        ),
+    }
+}
+
+/// Check if the project's `frel.toml` is among the watch event paths
+pub fn config_changed(state: &ProjectState, paths: &[PathBuf]) -> bool {
+    let config = state.root.join(CONFIG_FILE);
+    paths.contains(&config)
+}
+
+/// Check if watch events change the project's source files by whole
+/// directories: one with sources was created or moved in, or removed or
+/// moved away
+///
+/// Watchers report a moved directory once, not each file in it, so the
+/// module set has to be scanned again.
+pub fn needs_rescan(state: &ProjectState, paths: &[PathBuf]) -> bool {
+    let has_sources = |dir: &Path| {
+        state
+            .sources
+            .keys()
+            .any(|source| source.starts_with(dir) && source != dir)
+    };
+    paths
+        .iter()
+        .filter(|path| **path != state.root && !state.is_build_output(path))
+        .filter(|path| !state.is_ignored(path))
+        .any(|path| {
+            if path.is_dir() {
+                // Known directories show up when a file in them changes
+                !has_sources(path) && !compiler::discover_frel_files(state.files.as_ref(), path).is_empty()
+            } else {
+                !path.exists() && has_sources(path)
+            }
+        })
+}
+
+/// Reduce watch event paths to the source files that need recompiling
+///
+/// Non-`.frel` paths and anything the compiler itself wrote are dropped, so
+/// writing build artifacts inside the project tree never retriggers a build.
+/// So are the paths matching the ignore patterns of the watch settings.
+pub fn source_changes(state: &ProjectState, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = paths
+        .iter()
+        .filter(|p| p.extension().map(|e| e == "frel").unwrap_or(false))
+        .filter(|p| !state.is_build_output(p) && !state.is_ignored(p))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::gitignore_patterns;
+    use frel_compiler_core::edition::WatchConfig;
+    use std::fs;
+
+    #[test]
+    fn test_build_output_does_not_schedule_build() {
+        let root = std::env::temp_dir().join(format!("frel-watcher-loop-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let source = root.join("data.frel");
+        fs::write(&source, "module test.data\n\nscheme User {\n    name .. String\n}\n").unwrap();
+
+        let mut state = ProjectState::new(root.clone(), root.join("build"));
+        compiler::full_build(&mut state);
+        assert!(!state.emitted.is_empty());
+
+        // Events for everything the build wrote must not count as changes
+        let mut events: Vec<PathBuf> = state.emitted.iter().cloned().collect();
+        events.push(state.build_dir.clone());
+        events.push(state.build_dir.join("test").join("stale.frel"));
+        assert!(source_changes(&state, &events).is_empty());
+
+        // Real source edits still do
+        events.push(source.clone());
+        events.push(source.clone());
+        assert_eq!(source_changes(&state, &events), vec![source]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_directory_changes_rescan() {
+        let root = std::env::temp_dir().join(format!("frel-watcher-rescan-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let app = root.join("app");
+        fs::create_dir_all(&app).unwrap();
+        fs::write(app.join("data.frel"), "module app.data\n\nbackend Store {\n    count : i32 = 0\n}\n")
+            .unwrap();
+
+        let mut state = ProjectState::new(root.clone(), root.join("build"));
+        compiler::full_build(&mut state);
+        assert!(config_changed(&state, &[root.join(CONFIG_FILE)]));
+        assert!(!config_changed(&state, &[app.join(CONFIG_FILE)]));
+
+        // Editing a file inside a known directory is a single file change
+        assert!(!needs_rescan(&state, &[app.clone(), app.join("data.frel")]));
+        assert!(!needs_rescan(&state, &[root.clone(), state.build_dir.clone()]));
+
+        // A directory with sources moved in
+        let lib = root.join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        assert!(!needs_rescan(&state, std::slice::from_ref(&lib)));
+        fs::write(lib.join("util.frel"), "module lib.util\n").unwrap();
+        assert!(needs_rescan(&state, std::slice::from_ref(&lib)));
+
+        // A directory with sources moved away
+        fs::rename(&app, root.join("moved")).unwrap();
+        assert!(needs_rescan(&state, std::slice::from_ref(&app)));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let patterns = gitignore_patterns("# output\n/dist/\n*.log\n!keep.log\n\ngen/tmp\n");
+        assert_eq!(patterns, vec!["dist", "**/*.log", "gen/tmp"]);
+
+        let root = std::env::temp_dir().join(format!("frel-watcher-ignore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".gitignore"), "/dist/\n").unwrap();
+        fs::write(root.join(CONFIG_FILE), "[watch]\ndebounce_ms = 300\nignore = [\"scratch/*.frel\"]\n")
+            .unwrap();
+
+        let mut state = ProjectState::new(root.clone(), root.join("build"));
+        state.watch_flags = WatchConfig {
+            debounce_ms: Some(20),
+            ignore: vec!["vendor".to_string()],
+        };
+        assert!(matches!(compiler::reload_config(&mut state), ConfigReload::Reloaded { .. }));
+        assert_eq!(state.watch.debounce, Duration::from_millis(20));
+
+        let events = vec![
+            root.join("dist").join("app.frel"),
+            root.join("node_modules").join("pkg").join("lib.frel"),
+            root.join("scratch").join("draft.frel"),
+            root.join("scratch").join("nested").join("draft.frel"),
+            root.join("vendor").join("ui.frel"),
+            root.join("app.frel"),
+        ];
+        assert_eq!(
+            source_changes(&state, &events),
+            vec![root.join("app.frel"), root.join("scratch").join("nested").join("draft.frel")]
+        );
+
+        // An invalid pattern is rejected like any invalid configuration
+        fs::write(root.join(CONFIG_FILE), "[watch]\nignore = [\"[\"]\n").unwrap();
+        assert!(matches!(compiler::reload_config(&mut state), ConfigReload::Rejected { .. }));
+        assert_eq!(state.watch.debounce, Duration::from_millis(20));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_collect_burst() {
+        let (tx, rx) = mpsc::channel();
+        let event = |name: &str| Event::default().add_path(PathBuf::from(name));
+        tx.send(event("b.frel")).unwrap();
+        tx.send(event("c.frel")).unwrap();
+        let paths = collect_burst(&rx, vec![PathBuf::from("a.frel")], Duration::from_millis(10));
+        let expected: Vec<PathBuf> = ["a.frel", "b.frel", "c.frel"].iter().map(PathBuf::from).collect();
+        assert_eq!(paths, expected);
+    }
+}
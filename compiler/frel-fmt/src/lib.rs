@@ -0,0 +1,133 @@
+// Canonical code formatter for Frel
+//
+// Reprints a parsed file in the canonical Frel style:
+// - one declaration, statement or member per line; a block stays on one line
+//   only when it holds a single statement without a block of its own
+// - four-space indentation following block nesting, with each `..` postfix
+//   item on a line of its own, indented one extra level
+// - single spaces around binary operators, after commas and inside one-line
+//   `{ }` blocks; none inside parentheses, brackets and generic arguments,
+//   nor around the `..` of a range written tight (`0..count`)
+// - ` : ` after declared names (`count : i32 = 0`), `: ` for parameters,
+//   named arguments and instruction parameters (`font { size: 24 }`)
+// - at most one blank line in a row, none at the start or end of a block
+// - comments kept where they were, trailing comments one space after code
+//
+// The formatter works from the AST and its token stream: the AST's outline
+// places the line breaks and the AST carries the comments, the tokens keep
+// the spelling of literals (`0x2A`, `#FFF`) that the AST normalizes. Of the
+// author's line breaks, only blank lines and those around comments are kept;
+// layout grids, strings and block comments are copied verbatim.
+//
+// Files with parse errors are not formatted.
+
+mod printer;
+
+use frel_compiler_core::{ast, Diagnostics};
+
+/// Format Frel source, or return the parse errors that prevent it
+pub fn format_source(source: &str) -> Result<String, Diagnostics> {
+    let result = frel_compiler_core::parse_file(source);
+    match result.file {
+        Some(file) if !result.diagnostics.has_errors() => Ok(format_file(source, &file)),
+        _ => Err(result.diagnostics),
+    }
+}
+
+/// Reprint a file parsed from `source` in canonical form
+pub fn format_file(source: &str, file: &ast::File) -> String {
+    printer::Printer::new(source, file).print()
+}
+
+/// Check if `source` is already in canonical form
+pub fn is_formatted(source: &str) -> Result<bool, Diagnostics> {
+    Ok(format_source(source)? == source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        let formatted = format_source(source).expect("source should parse");
+        assert_eq!(
+            format_source(&formatted).unwrap(),
+            formatted,
+            "formatting should be idempotent"
+        );
+        formatted
+    }
+
+    #[test]
+    fn test_canonical_source_unchanged() {
+        let source = r#"// Simple counter example
+module examples.counter
+
+backend CounterBackend {
+    count : i32 = 0
+    items : List<String>? = null
+
+    command increment()
+    method label(prefix: String) : String
+}
+
+blueprint Counter(title: String) {
+    with CounterBackend
+
+    column {
+        gap { 16 } // between rows
+
+        text { "Count: ${count + 1}" }
+            .. font { size: 24 weight: 700 }
+
+        when count > 0 {
+            text { count > 10 ? "many" : "few" }
+        }
+
+        box {
+            padding { 0x10 }
+            on_click { count = -count * 2 }
+        }
+    }
+}
+"#;
+        assert_eq!(format(source), source);
+        assert!(is_formatted(source).unwrap());
+    }
+
+    #[test]
+    fn test_normalizes_layout() {
+        let source = "\n\nmodule test\nbackend B{\n  count:i32=0\n\n\n\n      command reset( )\n\n}\n\n\nblueprint Main( title : String ){\nwith B\ntext{\"x\"}   ..   font{size:24}\n\n}";
+        let expected = "module test\nbackend B {\n    count : i32 = 0\n\n    command reset()\n}\n\nblueprint Main(title: String) {\n    with B\n    text { \"x\" }\n        .. font { size: 24 }\n}\n";
+        assert_eq!(format(source), expected);
+        assert!(!is_formatted(source).unwrap());
+    }
+
+    #[test]
+    fn test_ranges_tight() {
+        let source = "module test\n\nblueprint Main(count: i32) {\n    repeat on 0..count { i ->\n        text{\"x\"}..bold\n    }\n    repeat on 1 ..= count { i -> text { \"${i}\" } }\n}\n";
+        let expected = "module test\n\nblueprint Main(count: i32) {\n    repeat on 0..count { i ->\n        text { \"x\" }\n            .. bold\n    }\n    repeat on 1..=count { i ->\n        text { \"${i}\" }\n    }\n}\n";
+        assert_eq!(format(source), expected);
+    }
+
+    #[test]
+    fn test_lines_follow_tree() {
+        let compact = "module test\nblueprint Main { column { text { \"a\" } .. width { 10 } } box { on_click { save() } } }\nenum Size { Small Large }\n";
+        let spread = "module test\nblueprint Main {\n    column {\n        text {\n            \"a\"\n        }\n        .. width {\n            10\n        }\n    }\n    box {\n        on_click {\n            save()\n        }\n    }\n}\nenum Size {\n    Small\n    Large\n}\n";
+        let expected = "module test\nblueprint Main {\n    column {\n        text { \"a\" }\n            .. width { 10 }\n    }\n    box {\n        on_click { save() }\n    }\n}\nenum Size { Small Large }\n";
+        assert_eq!(format(compact), expected);
+        assert_eq!(format(spread), expected);
+    }
+
+    #[test]
+    fn test_comments_preserved() {
+        let source = "module test\n\n/* header\n   comment */\nblueprint Main {\n// leading\n    text {   \"x\" }    // trailing   \n  /* inline */ text { \"y\" }\n}\n";
+        let expected = "module test\n\n/* header\n   comment */\nblueprint Main {\n    // leading\n    text { \"x\" } // trailing\n    /* inline */ text { \"y\" }\n}\n";
+        assert_eq!(format(source), expected);
+    }
+
+    #[test]
+    fn test_parse_errors_not_formatted() {
+        assert!(format_source("module test\n\nblueprint Main {\n").is_err());
+    }
+}
@@ -0,0 +1,586 @@
+// Printer for the Frel formatter
+//
+// Lines are laid out from the block structure the parser records: each
+// declaration, statement and member starts a line, each postfix item of a
+// fragment a continuation line, and blocks holding more than a single leaf
+// statement are split. Of the author's line breaks only comments and blank
+// lines survive. The spacing within a line is decided from the token kinds.
+
+use std::collections::HashSet;
+
+use frel_compiler_core::ast::{self, Comment, CommentKind};
+use frel_compiler_core::lexer::Lexer;
+use frel_compiler_core::{Span, Token, TokenKind};
+
+const INDENT: &str = "    ";
+
+/// A token or a comment
+enum Item {
+    Token(Token),
+    Comment(Comment),
+}
+
+impl Item {
+    fn span(&self) -> Span {
+        match self {
+            Item::Token(token) => token.span,
+            Item::Comment(comment) => comment.span,
+        }
+    }
+
+    fn kind(&self) -> Option<TokenKind> {
+        match self {
+            Item::Token(token) => Some(token.kind),
+            Item::Comment(_) => None,
+        }
+    }
+
+    fn is_line_comment(&self) -> bool {
+        matches!(self, Item::Comment(comment) if comment.kind == CommentKind::Line)
+    }
+}
+
+/// How a token is used, where its kind alone is ambiguous
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Plain,
+    /// Unary `-` or `!`
+    Prefix,
+    /// `<` and `>` around generic arguments
+    GenericOpen,
+    GenericClose,
+    /// `?` of a nullable type
+    Nullable,
+    /// `:` after a declared name, a return type or in a ternary
+    SpacedColon,
+    /// `:` after a parameter, argument or instruction parameter name
+    TightColon,
+    /// `..` or `..=` of a range, `0..count`
+    Range,
+}
+
+/// A printed line, as a range of items
+struct Line {
+    start: usize,
+    end: usize,
+    /// Whether a blank line goes before it
+    blank: bool,
+}
+
+pub struct Printer<'a> {
+    source: &'a str,
+    items: Vec<Item>,
+    /// Blocks printed over several lines, from `{` to `}`
+    split: Vec<Span>,
+    /// Start offsets of the statements starting a line
+    line_starts: HashSet<u32>,
+    /// Start offsets of the `..` starting a continuation line
+    postfix: HashSet<u32>,
+    /// Start offsets of the `:` following declared names
+    declaration_colons: HashSet<u32>,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(source: &'a str, file: &ast::File) -> Self {
+        let (tokens, _, _) = Lexer::new(source).tokenize_with_comments();
+
+        let mut items: Vec<Item> = tokens
+            .iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Newline | TokenKind::Eof))
+            .map(|token| Item::Token(token.clone()))
+            .chain(file.comments.iter().map(|comment| Item::Comment(*comment)))
+            .collect();
+        items.sort_by_key(|item| item.span().start);
+
+        let outline = &file.outline;
+        let split = split_blocks(file);
+        // Statements of a block left on one line stay where they are
+        let line_starts = outline
+            .items
+            .iter()
+            .copied()
+            .filter(|&start| {
+                innermost_block(&outline.blocks, start).map_or(true, |block| split.contains(&block))
+            })
+            .collect();
+
+        Self {
+            source,
+            items,
+            split,
+            line_starts,
+            postfix: outline.postfix.iter().copied().collect(),
+            declaration_colons: declaration_colons(&tokens, &declaration_spans(file)),
+        }
+    }
+
+    pub fn print(self) -> String {
+        let mut out = String::new();
+        for line in self.lines() {
+            if line.blank {
+                out.push('\n');
+            }
+            let mut text = INDENT.repeat(self.indent(&line));
+            self.write_items(&self.items[line.start..line.end], &mut text);
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Break the items into lines
+    ///
+    /// A line starts at each statement, postfix item and closing brace of a
+    /// split block, after a line comment and at a comment the author put on
+    /// its own line. A blank line before a statement or comment is kept,
+    /// except at the start of a block.
+    fn lines(&self) -> Vec<Line> {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut opened_block = true;
+
+        for (i, item) in self.items.iter().enumerate() {
+            let gap = match i.checked_sub(1) {
+                Some(previous) => Span::new(self.items[previous].span().end, item.span().start),
+                None => Span::default(),
+            };
+            let gap = gap.text(self.source);
+            let own_line = gap.contains('\n');
+
+            let breaks = match lines.last() {
+                None => true,
+                Some(_) if self.items[i - 1].is_line_comment() => true,
+                Some(_) if item.kind().is_none() => own_line,
+                // `/* note */ text { }` stays together
+                Some(line) => {
+                    let after_comments = self.items[line.start..i]
+                        .iter()
+                        .all(|item| item.kind().is_none());
+                    self.breaks_before(item) && (own_line || !after_comments)
+                }
+            };
+
+            if breaks {
+                let continues = matches!(item, Item::Token(token)
+                    if self.is_split_close(token) || self.postfix.contains(&token.span.start));
+                lines.push(Line {
+                    start: i,
+                    end: i + 1,
+                    blank: !opened_block && !continues && gap.matches('\n').count() > 1,
+                });
+                opened_block = false;
+            } else if let Some(line) = lines.last_mut() {
+                line.end = i + 1;
+            }
+
+            if item.kind() == Some(TokenKind::LBrace)
+                && self
+                    .split
+                    .iter()
+                    .any(|block| block.start == item.span().start)
+            {
+                opened_block = true;
+            }
+        }
+        lines
+    }
+
+    /// Whether a token starts a line of its own; comments follow the author
+    fn breaks_before(&self, item: &Item) -> bool {
+        let Item::Token(token) = item else {
+            return false;
+        };
+        self.line_starts.contains(&token.span.start)
+            || self.postfix.contains(&token.span.start)
+            || self.is_split_close(token)
+    }
+
+    fn is_split_close(&self, token: &Token) -> bool {
+        token.kind == TokenKind::RBrace
+            && self.split.iter().any(|block| block.end == token.span.end)
+    }
+
+    /// Indentation of a line: one level per split block around it, and one
+    /// more for lines continuing a statement
+    fn indent(&self, line: &Line) -> usize {
+        let start = self.items[line.start].span().start;
+        let depth = self
+            .split
+            .iter()
+            .filter(|block| block.start < start && start + 1 < block.end)
+            .count();
+        // A comment line is indented like the code after it
+        let lead = self.items[line.start..].iter().find_map(|item| match item {
+            Item::Token(token) => Some(token),
+            Item::Comment(_) => None,
+        });
+        let continuation = lead.is_some_and(|token| {
+            !self.line_starts.contains(&token.span.start) && !self.is_split_close(token)
+        });
+        depth + usize::from(continuation)
+    }
+
+    /// Write the items of a line
+    fn write_items(&self, line: &[Item], text: &mut String) {
+        let mut roles = Vec::with_capacity(line.len());
+        let mut generics = 0usize;
+        // Pending ternary `?` per bracket depth
+        let mut questions = vec![0usize];
+        let mut previous: Option<(&Token, Role)> = None;
+
+        for (i, item) in line.iter().enumerate() {
+            let Item::Token(token) = item else {
+                roles.push(Role::Plain);
+                continue;
+            };
+            let role = match token.kind {
+                TokenKind::Minus | TokenKind::Bang
+                    if !previous.is_some_and(|(p, role)| is_operand(p.kind, role)) =>
+                {
+                    Role::Prefix
+                }
+                TokenKind::Lt
+                    if previous.is_some_and(|(p, _)| {
+                        p.kind == TokenKind::Identifier && p.span.end == token.span.start
+                    }) =>
+                {
+                    generics += 1;
+                    Role::GenericOpen
+                }
+                // A spaced `..` starts an instruction; one written between
+                // two operands without spaces is a range
+                TokenKind::DotDotEq => Role::Range,
+                TokenKind::DotDot
+                    if previous.is_some_and(|(p, role)| {
+                        p.kind != TokenKind::RBrace
+                            && is_operand(p.kind, role)
+                            && p.span.end == token.span.start
+                    }) && line[i + 1..].first().is_some_and(|next| {
+                        next.kind().is_some_and(is_operand_start)
+                            && next.span().start == token.span.end
+                    }) =>
+                {
+                    Role::Range
+                }
+                TokenKind::Gt if generics > 0 => {
+                    generics -= 1;
+                    Role::GenericClose
+                }
+                TokenKind::Question => {
+                    let next = line[i + 1..].iter().find_map(|item| item.kind());
+                    if next.is_some_and(is_operand_start) {
+                        if let Some(pending) = questions.last_mut() {
+                            *pending += 1;
+                        }
+                        Role::Plain
+                    } else {
+                        Role::Nullable
+                    }
+                }
+                TokenKind::Colon => {
+                    let pending = questions.last_mut().filter(|pending| **pending > 0);
+                    if self.declaration_colons.contains(&token.span.start) {
+                        Role::SpacedColon
+                    } else if let Some(pending) = pending {
+                        *pending -= 1;
+                        Role::SpacedColon
+                    } else if previous.is_some_and(|(p, _)| p.kind == TokenKind::RParen) {
+                        Role::SpacedColon
+                    } else {
+                        Role::TightColon
+                    }
+                }
+                kind if is_opener(kind) => {
+                    questions.push(0);
+                    Role::Plain
+                }
+                kind if is_closer(kind) => {
+                    if questions.len() > 1 {
+                        questions.pop();
+                    }
+                    Role::Plain
+                }
+                _ => Role::Plain,
+            };
+            roles.push(role);
+            previous = Some((token, role));
+        }
+
+        let mut previous: Option<(&Item, Role)> = None;
+        for (item, role) in line.iter().zip(&roles) {
+            if let Some((prev, prev_role)) = previous {
+                if space_between(prev, prev_role, item, *role) {
+                    text.push(' ');
+                }
+            }
+            text.push_str(self.text(item));
+            previous = Some((item, *role));
+        }
+    }
+
+    fn text(&self, item: &Item) -> &'a str {
+        let text = item.span().text(self.source);
+        match item {
+            Item::Comment(comment) if comment.kind == CommentKind::Line => text.trim_end(),
+            _ => text,
+        }
+    }
+}
+
+/// Blocks printed over several lines
+///
+/// Declaration bodies are split unless empty, except that of an enum whose
+/// variants carry no data or labels. A block inside a blueprint stays on one
+/// line while it holds at most one statement and no block, postfix item or
+/// line comment.
+fn split_blocks(file: &ast::File) -> Vec<Span> {
+    let outline = &file.outline;
+    outline
+        .blocks
+        .iter()
+        .copied()
+        .filter(|block| {
+            let inside = |offset: u32| block.start < offset && offset < block.end;
+            let items = outline.items.iter().filter(|start| inside(**start)).count();
+            let line_comment = file
+                .comments
+                .iter()
+                .any(|comment| comment.kind == CommentKind::Line && inside(comment.span.start));
+            let declaration = file.declarations.iter().find(|decl| {
+                let span = decl.span();
+                span.start <= block.start && block.end <= span.end
+            });
+            let outermost = !outline
+                .blocks
+                .iter()
+                .any(|other| other != block && other.start < block.start && block.end < other.end);
+            match declaration {
+                Some(ast::TopLevelDecl::Enum(decl))
+                    if decl.instructions.is_empty() && decl.payloads.is_empty() =>
+                {
+                    line_comment
+                }
+                Some(ast::TopLevelDecl::Blueprint(_)) if !outermost => {
+                    items > 1
+                        || line_comment
+                        || outline
+                            .blocks
+                            .iter()
+                            .any(|other| other != block && inside(other.start))
+                        || outline.postfix.iter().any(|start| inside(*start))
+                }
+                _ => items > 0 || line_comment,
+            }
+        })
+        .collect()
+}
+
+/// The innermost block around `offset`
+fn innermost_block(blocks: &[Span], offset: u32) -> Option<Span> {
+    blocks
+        .iter()
+        .copied()
+        .filter(|block| block.start < offset && offset < block.end)
+        .min_by_key(|block| block.end - block.start)
+}
+
+fn space_between(prev: &Item, prev_role: Role, next: &Item, role: Role) -> bool {
+    let (Item::Token(prev), Item::Token(next)) = (prev, next) else {
+        return true;
+    };
+    use TokenKind::*;
+
+    if matches!(next.kind, Comma | RParen | RBracket | Dot | QuestionDot)
+        || matches!(
+            role,
+            Role::GenericOpen
+                | Role::GenericClose
+                | Role::Nullable
+                | Role::TightColon
+                | Role::Range
+        )
+        || matches!(prev.kind, LParen | LBracket | Dot | QuestionDot)
+        || matches!(prev_role, Role::Prefix | Role::GenericOpen | Role::Range)
+        || matches!(prev.kind, StringTemplateStart | StringTemplateMiddle)
+        || matches!(next.kind, StringTemplateMiddle | StringTemplateEnd)
+    {
+        return false;
+    }
+    match (prev.kind, next.kind) {
+        // Calls, parameter lists and indexing
+        (Identifier | RParen | RBracket, LParen | LBracket) => false,
+        (Gt, LParen) => prev_role != Role::GenericClose,
+        _ => true,
+    }
+}
+
+fn is_opener(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket
+    )
+}
+
+fn is_closer(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket
+    )
+}
+
+/// Tokens ending an operand, after which `-` is binary
+fn is_operand(kind: TokenKind, role: Role) -> bool {
+    use TokenKind::*;
+    matches!(
+        kind,
+        Identifier
+            | IntLiteral
+            | FloatLiteral
+            | ColorLiteral
+            | StringLiteral
+            | StringTemplateEnd
+            | True
+            | False
+            | Null
+            | RParen
+            | RBracket
+            | RBrace
+    ) || role == Role::GenericClose
+}
+
+/// Tokens starting an operand, after which `?` is a ternary
+fn is_operand_start(kind: TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        kind,
+        Identifier
+            | IntLiteral
+            | FloatLiteral
+            | ColorLiteral
+            | StringLiteral
+            | StringTemplateStart
+            | True
+            | False
+            | Null
+            | LParen
+            | LBracket
+            | Minus
+            | Bang
+    )
+}
+
+/// The `:` following each declared name
+fn declaration_colons(tokens: &[Token], spans: &[Span]) -> HashSet<u32> {
+    let mut colons = HashSet::new();
+    for span in spans {
+        let colon = tokens
+            .iter()
+            .skip_while(|token| token.span.start < span.start)
+            .take_while(|token| token.span.end <= span.end && token.kind != TokenKind::Eq)
+            .find(|token| token.kind == TokenKind::Colon);
+        if let Some(colon) = colon {
+            colons.insert(colon.span.start);
+        }
+    }
+    colons
+}
+
+/// Spans of the declarations written `name : Type`
+fn declaration_spans(file: &ast::File) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for decl in &file.declarations {
+        match decl {
+            ast::TopLevelDecl::Blueprint(blueprint) => {
+                local_decl_spans(&blueprint.body, &mut spans)
+            }
+            ast::TopLevelDecl::Backend(backend) => {
+                for member in &backend.members {
+                    if let ast::BackendMember::Field(field) = member {
+                        spans.push(field.span);
+                    }
+                }
+            }
+            ast::TopLevelDecl::Scheme(scheme) => {
+                for member in &scheme.members {
+                    spans.push(match member {
+                        ast::SchemeMember::Field(field) => field.span,
+                        ast::SchemeMember::Virtual(field) => field.span,
+                    });
+                }
+            }
+            ast::TopLevelDecl::Theme(theme) => {
+                for member in &theme.members {
+                    if let ast::ThemeMember::Field(field) = member {
+                        spans.push(field.span);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+fn local_decl_spans(stmts: &[ast::BlueprintStmt], spans: &mut Vec<Span>) {
+    for stmt in stmts {
+        local_decl_stmt_spans(stmt, spans);
+    }
+}
+
+fn local_decl_stmt_spans(stmt: &ast::BlueprintStmt, spans: &mut Vec<Span>) {
+    match stmt {
+        ast::BlueprintStmt::LocalDecl(decl) => spans.push(decl.span),
+        ast::BlueprintStmt::Consume(consume) => spans.push(consume.span),
+        ast::BlueprintStmt::FragmentCreation(fragment) => match &fragment.body {
+            Some(ast::FragmentBody::Default(body))
+            | Some(ast::FragmentBody::InlineBlueprint { body, .. }) => {
+                local_decl_spans(body, spans)
+            }
+            Some(ast::FragmentBody::Slots(slots)) => {
+                for slot in slots {
+                    slot_decl_spans(slot, spans);
+                }
+            }
+            None => {}
+        },
+        ast::BlueprintStmt::SlotBinding(slot) => slot_decl_spans(slot, spans),
+        ast::BlueprintStmt::Control(control) => match control {
+            ast::ControlStmt::When {
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                local_decl_stmt_spans(then_stmt, spans);
+                if let Some(else_stmt) = else_stmt {
+                    local_decl_stmt_spans(else_stmt, spans);
+                }
+            }
+            ast::ControlStmt::Repeat { body, .. } => local_decl_spans(body, spans),
+            ast::ControlStmt::Select {
+                branches,
+                else_branch,
+                ..
+            } => {
+                for branch in branches {
+                    local_decl_stmt_spans(&branch.body, spans);
+                }
+                if let Some(else_branch) = else_branch {
+                    local_decl_stmt_spans(else_branch, spans);
+                }
+            }
+            ast::ControlStmt::OnError { body, fallback, .. } => {
+                local_decl_spans(body, spans);
+                if let Some(fallback) = fallback {
+                    local_decl_spans(fallback, spans);
+                }
+            }
+            ast::ControlStmt::Loading { body, .. } => local_decl_spans(body, spans),
+        },
+        _ => {}
+    }
+}
+
+fn slot_decl_spans(slot: &ast::SlotBinding, spans: &mut Vec<Span>) {
+    if let ast::BlueprintValue::Inline { body, .. } = &slot.blueprint {
+        local_decl_spans(body, spans);
+    }
+}
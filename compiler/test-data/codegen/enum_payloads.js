@@ -0,0 +1,74 @@
+// Generated by Frel compiler
+// Module: codegen.enum_payloads
+// DO NOT EDIT - This file is auto-generated
+
+import { Runtime, Key, OneOf, Everything, Builtins, defineEnum } from '@frel/runtime';
+
+/** @type {import('@frel/runtime').EnumType<'Idle' | 'Loaded' | 'Failed', 'Loaded' | 'Failed'>} */
+export const Fetch = defineEnum('codegen.enum_payloads.Fetch', ['Idle', 'Loaded', 'Failed'], {
+  Loaded: 'Loaded',
+}, { Loaded: 2, Failed: 1 });
+
+// Backend: Page
+export class Page {
+  static fields = ['fetch', 'retry'];
+  static lifetime = 'fragment';
+
+  constructor(runtime, closure_id) {
+    this.runtime = runtime;
+    this.closure_id = closure_id;
+    runtime.set(closure_id, 'fetch', runtime.get(runtime.get(closure_id, 'Fetch'), 'Idle'));
+    runtime.set(closure_id, 'retry', runtime.get(runtime.get(closure_id, 'Fetch'), 'Failed')(503));
+  }
+
+  get fetch() { return this.runtime.get(this.closure_id, 'fetch'); }
+  set fetch(value) { this.runtime.set(this.closure_id, 'fetch', value); }
+
+  get retry() { return this.runtime.get(this.closure_id, 'retry'); }
+  set retry(value) { this.runtime.set(this.closure_id, 'retry', value); }
+
+}
+
+function Status$1$content$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'content', runtime.get(closure_id, 'body'));
+}
+
+function Status$2$content$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'content', ('Failed with ' + String(runtime.get(closure_id, 'code'))));
+}
+
+function Status$internal_binding(runtime, closure_id) {
+  runtime.use_backend(closure_id, null, Page);
+}
+
+function Status$0$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'content', 'Idle');
+}
+
+function Status$1$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'content', runtime.get(parent_id, 'body'));
+  runtime.subscribe(parent_id, child_id, Key('body'), Status$1$content$callback);
+}
+
+function Status$2$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'content', ('Failed with ' + String(runtime.get(parent_id, 'code'))));
+  runtime.subscribe(parent_id, child_id, Key('code'), Status$2$content$callback);
+}
+
+export const Status$metadata = {
+  internal_binding: Status$internal_binding,
+  top_children: [],
+  call_sites: {
+    '0': { blueprint: 'codegen.enum_payloads.text', binding: Status$0$call_site_binding, id: 'codegen.enum_payloads.Status/select/text' },
+    '1': { blueprint: 'codegen.enum_payloads.text', binding: Status$1$call_site_binding, id: 'codegen.enum_payloads.Status/select/text#2' },
+    '2': { blueprint: 'codegen.enum_payloads.text', binding: Status$2$call_site_binding, id: 'codegen.enum_payloads.Status/select/text#3' },
+  }
+};
+
+
+// Register metadata with runtime
+export function registerMetadata(runtime) {
+  runtime.register_metadata('codegen.enum_payloads.Status', Status$metadata);
+}
@@ -0,0 +1,45 @@
+// Generated by Frel compiler
+// Module: codegen.enum_utils
+// DO NOT EDIT - This file is auto-generated
+
+import { Runtime, Key, OneOf, Everything, Builtins, defineScheme, SchemeTypes, defineEnum } from '@frel/runtime';
+
+/** @type {import('@frel/runtime').EnumType<'Low' | 'Medium' | 'High'>} */
+export const Priority = defineEnum('codegen.enum_utils.Priority', ['Low', 'Medium', 'High']);
+
+/** @type {import('@frel/runtime').EnumType<'Open' | 'InProgress' | 'Blocked' | 'Done' | 'Dropped'>} */
+export const TaskState = defineEnum('codegen.enum_utils.TaskState', ['Open', 'InProgress', 'Blocked', 'Done', 'Dropped'], {
+  Open: 'Not started',
+  InProgress: 'In progress',
+  Dropped: 'Won\'t do',
+});
+
+// Scheme: Task
+export const Task$fields = [
+  'title',
+  'priority',
+  'state',
+];
+
+export function createTask(runtime, owner, data) {
+  const id = runtime.create_datum('Task', data, owner);
+  return id;
+}
+
+/**
+ * @typedef {Object} Task$data
+ * @property {string} title
+ * @property {number} priority
+ * @property {number} state
+ */
+
+/** @type {import('@frel/runtime').SchemeCodec<Task$data>} */
+export const Task = defineScheme('codegen.enum_utils.Task', [
+  { name: 'title', type: SchemeTypes.string },
+  { name: 'priority', type: SchemeTypes.enumeration('Priority', () => Priority) },
+  { name: 'state', type: SchemeTypes.enumeration('TaskState', () => TaskState) },
+]);
+
+// Register metadata with runtime
+export function registerMetadata(runtime) {
+}
@@ -0,0 +1,136 @@
+// Generated by Frel compiler
+// Module: codegen.lambdas
+// DO NOT EDIT - This file is auto-generated
+
+import { Runtime, Key, OneOf, Everything, Builtins } from '@frel/runtime';
+
+// Backend: Picker
+export class Picker {
+  static fields = ['picked', 'count', 'pick.loading'];
+  static lifetime = 'fragment';
+
+  constructor(runtime, closure_id) {
+    this.runtime = runtime;
+    this.closure_id = closure_id;
+    runtime.set(closure_id, 'picked', '');
+    runtime.set(closure_id, 'count', 0);
+    runtime.set(closure_id, 'pick.loading', false);
+  }
+
+  get picked() { return this.runtime.get(this.closure_id, 'picked'); }
+  set picked(value) { this.runtime.set(this.closure_id, 'picked', value); }
+
+  get count() { return this.runtime.get(this.closure_id, 'count'); }
+  set count(value) { this.runtime.set(this.closure_id, 'count', value); }
+
+  async pick(id) {
+    return this.runtime.run_command(this.closure_id, 'pick', null, (signal) => this.pick$run(id, signal));
+  }
+
+  async pick$run(id, signal) {
+    // TODO: Implement in host language
+  }
+
+}
+
+function Row$0$call_site_binding(runtime, parent_id, child_id) {
+}
+
+export const Row$metadata = {
+  top_children: [0],
+  call_sites: {
+    '0': { blueprint: 'codegen.lambdas.box', binding: Row$0$call_site_binding, id: 'codegen.lambdas.Row/box' },
+  }
+};
+
+
+function Picks$scaled$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(closure_id, 'scaled', ((x) => (x * runtime.get(closure_id, 'scale'))));
+}
+
+function Picks$total$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(closure_id, 'total', runtime.get(closure_id, 'scaled')(runtime.get(closure_id, 'count')));
+}
+
+function Picks$0$content$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'content', String(runtime.get(closure_id, 'total')));
+}
+
+function Picks$1$label$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'label', 'a');
+}
+
+function Picks$1$on_pick$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'on_pick', ((id) => runtime.get(closure_id, 'pick')(id)));
+}
+
+function Picks$1$format$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'format', ((n) => (String(n) + ' items')));
+}
+
+function Picks$2$label$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'label', 'b');
+}
+
+function Picks$2$on_pick$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'on_pick', ((id) => runtime.get(closure_id, 'pick')((String(id) + '!'))));
+}
+
+function Picks$2$format$callback(runtime, subscription) {
+  const closure_id = subscription.source_id;
+  runtime.set(subscription.target_id, 'format', ((n) => String(runtime.get(closure_id, 'scaled')(n))));
+}
+
+function Picks$internal_binding(runtime, closure_id) {
+  runtime.use_backend(closure_id, null, Picker);
+  runtime.set(closure_id, 'scale', 2);
+  runtime.set(closure_id, 'scaled', ((x) => (x * runtime.get(closure_id, 'scale'))));
+  runtime.subscribe(closure_id, closure_id, Key('scale'), Picks$scaled$callback);
+  runtime.set(closure_id, 'total', runtime.get(closure_id, 'scaled')(runtime.get(closure_id, 'count')));
+  runtime.subscribe(closure_id, closure_id, OneOf('count', 'scaled'), Picks$total$callback);
+}
+
+function Picks$0$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'content', String(runtime.get(parent_id, 'total')));
+  runtime.subscribe(parent_id, child_id, Key('total'), Picks$0$content$callback);
+}
+
+function Picks$1$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'label', 'a');
+  runtime.set(child_id, 'on_pick', ((id) => runtime.get(parent_id, 'pick')(id)));
+  runtime.subscribe(parent_id, child_id, Key('pick'), Picks$1$on_pick$callback);
+  runtime.set(child_id, 'format', ((n) => (String(n) + ' items')));
+}
+
+function Picks$2$call_site_binding(runtime, parent_id, child_id) {
+  runtime.set(child_id, 'label', 'b');
+  runtime.set(child_id, 'on_pick', ((id) => runtime.get(parent_id, 'pick')((String(id) + '!'))));
+  runtime.subscribe(parent_id, child_id, Key('pick'), Picks$2$on_pick$callback);
+  runtime.set(child_id, 'format', ((n) => String(runtime.get(parent_id, 'scaled')(n))));
+  runtime.subscribe(parent_id, child_id, Key('scaled'), Picks$2$format$callback);
+}
+
+export const Picks$metadata = {
+  internal_binding: Picks$internal_binding,
+  top_children: [0, 1, 2],
+  call_sites: {
+    '0': { blueprint: 'codegen.lambdas.text', binding: Picks$0$call_site_binding, id: 'codegen.lambdas.Picks/text' },
+    '1': { blueprint: 'codegen.lambdas.Row', binding: Picks$1$call_site_binding, id: 'codegen.lambdas.Picks/Row' },
+    '2': { blueprint: 'codegen.lambdas.Row', binding: Picks$2$call_site_binding, id: 'codegen.lambdas.Picks/Row#2' },
+  }
+};
+
+
+// Register metadata with runtime
+export function registerMetadata(runtime) {
+  runtime.register_metadata('codegen.lambdas.Row', Row$metadata);
+  runtime.register_metadata('codegen.lambdas.Picks', Picks$metadata);
+}
@@ -0,0 +1,61 @@
+// Generated by Frel compiler
+// Module: codegen.method_bodies
+// DO NOT EDIT - This file is auto-generated
+
+import { Runtime, Key, OneOf, Everything, Builtins } from '@frel/runtime';
+
+// Backend: Cart
+export class Cart {
+  static fields = ['price', 'quantity', 'discount', 'checkout.loading'];
+  static lifetime = 'fragment';
+
+  constructor(runtime, closure_id) {
+    this.runtime = runtime;
+    this.closure_id = closure_id;
+    runtime.set(closure_id, 'price', 12.5);
+    runtime.set(closure_id, 'quantity', 1);
+    runtime.set(closure_id, 'discount', null);
+    runtime.set(closure_id, 'checkout.loading', false);
+  }
+
+  get price() { return this.runtime.get(this.closure_id, 'price'); }
+  set price(value) { this.runtime.set(this.closure_id, 'price', value); }
+
+  get quantity() { return this.runtime.get(this.closure_id, 'quantity'); }
+  set quantity(value) { this.runtime.set(this.closure_id, 'quantity', value); }
+
+  get discount() { return this.runtime.get(this.closure_id, 'discount'); }
+  set discount(value) { this.runtime.set(this.closure_id, 'discount', value); }
+
+  async checkout() {
+    return this.runtime.run_command(this.closure_id, 'checkout', null, (signal) => this.checkout$run(signal));
+  }
+
+  async checkout$run(signal) {
+    // TODO: Implement in host language
+  }
+
+  subtotal() {
+    const runtime = this.runtime;
+    return (runtime.get(this.closure_id, 'price') * runtime.get(this.closure_id, 'quantity'));
+  }
+
+  total(shipping) {
+    const runtime = this.runtime;
+    return ((this.subtotal() - (runtime.get(this.closure_id, 'discount') ?? 0)) + shipping);
+  }
+
+  label() {
+    const runtime = this.runtime;
+    return (String(runtime.get(this.closure_id, 'quantity')) + ' × ' + String(runtime.get(this.closure_id, 'price')));
+  }
+
+  exchange_rate(currency) {
+    // TODO: Implement in host language
+  }
+
+}
+
+// Register metadata with runtime
+export function registerMetadata(runtime) {
+}
@@ -0,0 +1,105 @@
+// Generated by Frel compiler
+// Module: codegen.scheme_codec
+// DO NOT EDIT - This file is auto-generated
+
+import { Runtime, Key, OneOf, Everything, Builtins, defineScheme, SchemeTypes, defineEnum } from '@frel/runtime';
+
+// Scheme: Order
+export const Order$fields = [
+  'id',
+  'placed_at',
+  'total',
+  'status',
+  'note',
+  'customer',
+  'lines',
+  'tags',
+];
+
+export function createOrder(runtime, owner, data) {
+  const id = runtime.create_datum('Order', data, owner);
+  return id;
+}
+
+/**
+ * @typedef {Object} Order$data
+ * @property {string} id
+ * @property {Date} placed_at
+ * @property {string} total
+ * @property {number} status
+ * @property {(string | null)} note
+ * @property {Customer$data} customer
+ * @property {Array<OrderLine$data>} lines
+ * @property {Set<string>} tags
+ */
+
+/** @type {import('@frel/runtime').SchemeCodec<Order$data>} */
+export const Order = defineScheme('codegen.scheme_codec.Order', [
+  { name: 'id', type: SchemeTypes.uuid },
+  { name: 'placed_at', type: SchemeTypes.instant },
+  { name: 'total', type: SchemeTypes.decimal, rules: [['min', 0], ['max', 99999.99], ['precision', 2]] },
+  { name: 'status', type: SchemeTypes.enumeration('Status', () => Status) },
+  { name: 'note', type: SchemeTypes.nullable(SchemeTypes.string), rules: [['max_len', 500]] },
+  { name: 'customer', type: SchemeTypes.scheme(() => Customer) },
+  { name: 'lines', type: SchemeTypes.list(SchemeTypes.scheme(() => OrderLine)), rules: [['min_items', 1], ['max_items', 100]] },
+  { name: 'tags', type: SchemeTypes.set(SchemeTypes.string), rules: [['max_items', 10]] },
+]);
+
+// Scheme: OrderLine
+export const OrderLine$fields = [
+  'sku',
+  'quantity',
+];
+
+export function createOrderLine(runtime, owner, data) {
+  const id = runtime.create_datum('OrderLine', data, owner);
+  return id;
+}
+
+/**
+ * @typedef {Object} OrderLine$data
+ * @property {string} sku
+ * @property {number} quantity
+ */
+
+/** @type {import('@frel/runtime').SchemeCodec<OrderLine$data>} */
+export const OrderLine = defineScheme('codegen.scheme_codec.OrderLine', [
+  { name: 'sku', type: SchemeTypes.string, rules: [['pattern', '^[A-Z]{3}-\\d{4}$']] },
+  { name: 'quantity', type: SchemeTypes.int(0, 65535), rules: [['min', 1]] },
+]);
+
+// Scheme: Customer
+export const Customer$fields = [
+  'name',
+  'email',
+  'website',
+  'born',
+];
+
+export function createCustomer(runtime, owner, data) {
+  const id = runtime.create_datum('Customer', data, owner);
+  return id;
+}
+
+/**
+ * @typedef {Object} Customer$data
+ * @property {string} name
+ * @property {string} email
+ * @property {(string | null)} website
+ * @property {(string | null)} born
+ */
+
+/** @type {import('@frel/runtime').SchemeCodec<Customer$data>} */
+export const Customer = defineScheme('codegen.scheme_codec.Customer', [
+  { name: 'name', type: SchemeTypes.string, rules: [['min_len', 2], ['blank', false]] },
+  { name: 'email', type: SchemeTypes.string, rules: [['pattern', '^[^@]+@[^@]+$']] },
+  { name: 'website', type: SchemeTypes.nullable(SchemeTypes.url) },
+  { name: 'born', type: SchemeTypes.nullable(SchemeTypes.local_date) },
+]);
+
+/** @type {import('@frel/runtime').EnumType<'Draft' | 'Placed' | 'Shipped' | 'Cancelled'>} */
+export const Status = defineEnum('codegen.scheme_codec.Status', ['Draft', 'Placed', 'Shipped', 'Cancelled']);
+
+// Register metadata with runtime
+export function registerMetadata(runtime) {
+}
@@ -0,0 +1,173 @@
+// Code actions
+//
+// Turns the suggestions attached to compiler diagnostics into edits:
+// - Each suggestion of a diagnostic touching the requested range is offered
+//   as a quick fix, the diagnostic's first suggestion being preferred
+// - A "fix all" source action applies the first suggestion of every
+//   diagnostic, like `frelc check --fix`
+
+use std::collections::HashMap;
+
+use frel_compiler_core::{Diagnostics, Span, Suggestion};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::diagnostics::{self, Document};
+
+/// Code actions for `range` of a document, limited to the kinds in `only` if given
+pub fn code_actions(
+    found: &Diagnostics,
+    source: &str,
+    uri: &Url,
+    range: Range,
+    only: Option<&[CodeActionKind]>,
+) -> Vec<CodeActionOrCommand> {
+    let document = Document::new(source);
+    let wanted = |kind: &CodeActionKind| {
+        only.is_none_or(|only| {
+            only.iter()
+                .any(|requested| kind.as_str().starts_with(requested.as_str()))
+        })
+    };
+    let requested = Span::new(document.offset(range.start), document.offset(range.end));
+    let touches = |span: Span| span.start <= requested.end && requested.start <= span.end;
+
+    let mut actions = Vec::new();
+    if wanted(&CodeActionKind::QUICKFIX) {
+        for diag in found.iter().filter(|diag| touches(diag.span)) {
+            let lsp = diagnostics::convert(diag, &document, uri);
+            for (i, suggestion) in diag.suggestions.iter().enumerate() {
+                let edit = TextEdit::new(
+                    document.range(suggestion.span),
+                    suggestion.replacement.clone(),
+                );
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: title(suggestion),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![lsp.clone()]),
+                    edit: Some(workspace_edit(uri, edit)),
+                    is_preferred: Some(i == 0),
+                    ..CodeAction::default()
+                }));
+            }
+        }
+    }
+
+    if wanted(&CodeActionKind::SOURCE_FIX_ALL) {
+        let (fixed, count) = found.apply_fixes(source);
+        if count > 0 {
+            let whole = Range::new(document.position(0), document.position(source.len() as u32));
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Apply all suggested fixes ({})", count),
+                kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+                edit: Some(workspace_edit(uri, TextEdit::new(whole, fixed))),
+                ..CodeAction::default()
+            }));
+        }
+    }
+
+    actions
+}
+
+/// Title of the quick fix applying a suggestion
+fn title(suggestion: &Suggestion) -> String {
+    if !suggestion.message.is_empty() {
+        return capitalize(&suggestion.message);
+    }
+    if suggestion.replacement.is_empty() {
+        "Remove".to_string()
+    } else {
+        format!("Replace with '{}'", suggestion.replacement)
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn workspace_edit(uri: &Url, edit: TextEdit) -> WorkspaceEdit {
+    WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+        ..WorkspaceEdit::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::analyze;
+    use frel_compiler_core::Features;
+    use tower_lsp::lsp_types::Position;
+
+    const SOURCE: &str =
+        "module app\n\nbluprint Main {\n    with Store\n}\n\nbackend Store {\n    tags: List<String> = [\"a\" \"b\"]\n}\n";
+
+    fn uri() -> Url {
+        Url::parse("file:///project/app.frel").unwrap()
+    }
+
+    fn actions(range: Range, only: Option<&[CodeActionKind]>) -> Vec<CodeAction> {
+        let found = analyze(SOURCE, "/project/app.frel", &Features::default());
+        code_actions(&found, SOURCE, &uri(), range, only)
+            .into_iter()
+            .filter_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => Some(action),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect()
+    }
+
+    fn edits(action: &CodeAction) -> &[TextEdit] {
+        &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri()]
+    }
+
+    #[test]
+    fn test_quick_fixes() {
+        // Cursor on the misspelled keyword
+        let cursor = Position::new(2, 3);
+        let found = actions(
+            Range::new(cursor, cursor),
+            Some(&[CodeActionKind::QUICKFIX]),
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "Replace with 'blueprint'");
+        assert_eq!(found[0].is_preferred, Some(true));
+        let edit = &edits(&found[0])[0];
+        assert_eq!(edit.new_text, "blueprint");
+        assert_eq!(
+            edit.range,
+            Range::new(Position::new(2, 0), Position::new(2, 8))
+        );
+
+        // Cursor on the list missing a comma
+        let cursor = Position::new(7, 32);
+        let found = actions(
+            Range::new(cursor, cursor),
+            Some(&[CodeActionKind::QUICKFIX]),
+        );
+        assert_eq!(found[0].title, "Insert ','");
+        let edit = &edits(&found[0])[0];
+        assert_eq!(
+            edit.range,
+            Range::new(Position::new(7, 29), Position::new(7, 29))
+        );
+    }
+
+    #[test]
+    fn test_fix_all() {
+        let cursor = Position::new(0, 0);
+        let found = actions(Range::new(cursor, cursor), Some(&[CodeActionKind::SOURCE]));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, Some(CodeActionKind::SOURCE_FIX_ALL));
+
+        let fixed = &edits(&found[0])[0].new_text;
+        assert!(fixed.contains("blueprint Main"));
+        assert!(fixed.contains("[\"a\", \"b\"]"));
+        let remaining = analyze(fixed, "/project/app.frel", &Features::default());
+        assert!(!remaining.has_errors(), "{:?}", remaining);
+    }
+}
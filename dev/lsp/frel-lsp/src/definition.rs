@@ -0,0 +1,249 @@
+// Go-to-definition
+//
+// Finds the symbol referenced at a position from the resolutions recorded by
+// name resolution and returns where it is defined:
+// - Local symbols jump to their definition span in the same document
+// - Imported symbols are followed into the module declaring them, using
+//   signatures built from the other Frel files of the workspace
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use frel_compiler_core::{
+    ast, build_signature, resolve_with_registry, Module, ResolveResult, ScopeId, SignatureRegistry,
+    Symbol,
+};
+use tower_lsp::lsp_types::{Location, Url};
+
+use crate::diagnostics::{other_file_location, Document};
+
+/// Directories never searched for Frel sources
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+/// Find the definition of the name at `offset` in a document
+pub fn definition(
+    source: &str,
+    path: &str,
+    uri: &Url,
+    offset: u32,
+    registry: &SignatureRegistry,
+) -> Option<Location> {
+    let file = frel_compiler_core::parse_file_with_path(source, path).file?;
+    let result = resolve_with_registry(&file, registry);
+    let word = word_at(source, offset)?;
+    let symbol = symbol_at(&result, offset, word)?;
+
+    if let (Some(module), Some(origin)) = (&symbol.source_module, symbol.resolved_import) {
+        let signature = registry.get(module)?;
+        let origin = signature.get_symbol(origin)?;
+        return other_file_location(signature.source_path.as_ref()?, origin.def_span, uri);
+    }
+
+    // Loop variables and inline parameters have no recorded definition span
+    if symbol.def_span.is_empty() {
+        return None;
+    }
+    Some(Location::new(
+        uri.clone(),
+        Document::new(source).range(symbol.def_span),
+    ))
+}
+
+/// The symbol named `word` referenced at `offset`
+fn symbol_at<'a>(result: &'a ResolveResult, offset: u32, word: &str) -> Option<&'a Symbol> {
+    let contains = |start: u32, end: u32| start <= offset && offset <= end;
+
+    // Narrowest resolution recorded around the offset
+    let resolved = result
+        .resolutions
+        .iter()
+        .filter(|(span, _)| !span.is_empty() && contains(span.start, span.end))
+        .filter_map(|(span, id)| Some((span, result.symbols.get(*id)?)))
+        .filter(|(_, symbol)| symbol.name == word)
+        .min_by_key(|(span, _)| span.len())
+        .map(|(_, symbol)| symbol);
+    if resolved.is_some() {
+        return resolved;
+    }
+
+    // Names without a span of their own (fragment names, handler targets) or
+    // sharing one with other names: look them up from the innermost scope
+    let scope = result
+        .scopes
+        .iter()
+        .filter(|scope| !scope.span.is_empty() && contains(scope.span.start, scope.span.end))
+        .min_by_key(|scope| scope.span.len())
+        .map_or(ScopeId::ROOT, |scope| scope.id);
+    let id = result
+        .symbols
+        .lookup_in_scope_chain(scope, word, &result.scopes)
+        .or_else(|| {
+            result
+                .symbols
+                .lookup_in_children(scope, word, &result.scopes)
+                .map(|(id, _)| id)
+        })?;
+    result.symbols.get(id)
+}
+
+/// The identifier at or just before `offset`
+fn word_at(source: &str, offset: u32) -> Option<&str> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let offset = (offset as usize).min(source.len());
+    if !source.is_char_boundary(offset) {
+        return None;
+    }
+
+    let start = source[..offset].rfind(|c| !is_ident(c)).map_or(0, |i| {
+        i + source[i..].chars().next().map_or(1, char::len_utf8)
+    });
+    let end = source[offset..]
+        .find(|c| !is_ident(c))
+        .map_or(source.len(), |i| offset + i);
+
+    let word = &source[start..end];
+    (!word.is_empty()).then_some(word)
+}
+
+/// Build signatures for the modules of the given sources, as (path, text) pairs
+///
+/// The file at `current` is left out: its own declarations are resolved directly.
+pub fn build_registry(sources: &[(String, String)], current: &str) -> SignatureRegistry {
+    let mut modules: HashMap<String, Vec<ast::File>> = HashMap::new();
+    for (path, text) in sources {
+        if path == current {
+            continue;
+        }
+        if let Some(file) = frel_compiler_core::parse_file_with_path(text, path).file {
+            modules.entry(file.module.clone()).or_default().push(file);
+        }
+    }
+
+    let registry = SignatureRegistry::new();
+    for (path, files) in modules {
+        registry.register(build_signature(&Module { path, files }).signature);
+    }
+    registry
+}
+
+/// All `.frel` files under the given directories, sorted by path
+pub fn frel_files(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending: Vec<PathBuf> = roots.to_vec();
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    pending.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "frel") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Path of a document as passed to the compiler
+pub fn document_path(uri: &Url) -> String {
+    uri.to_file_path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| uri.to_string())
+}
+
+/// Directory to search when the client sent no workspace folders
+pub fn fallback_root(uri: &Url) -> Option<PathBuf> {
+    uri.to_file_path().ok()?.parent().map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    const APP: &str = r#"module app
+
+backend Counter {
+    count : i32 = 0
+}
+
+blueprint Row {
+    text { "row" }
+}
+
+blueprint Main {
+    with Counter
+    Row()
+    text { "x" } .. on_click { count = count + 1 }
+}
+"#;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/app.frel").unwrap()
+    }
+
+    /// Definition of the `nth` occurrence of `name` in `source`
+    fn goto(source: &str, name: &str, nth: usize, registry: &SignatureRegistry) -> Option<Range> {
+        let offset = source.match_indices(name).nth(nth).unwrap().0 as u32 + 1;
+        definition(source, "/project/app.frel", &uri(), offset, registry).map(|l| l.range)
+    }
+
+    #[test]
+    fn test_local_definitions() {
+        let registry = SignatureRegistry::new();
+
+        // `Row()` jumps to the blueprint, `with Counter` to the backend
+        let row = goto(APP, "Row", 1, &registry).unwrap();
+        assert_eq!(row.start, Position::new(6, 0));
+        let counter = goto(APP, "Counter", 1, &registry).unwrap();
+        assert_eq!(counter.start, Position::new(2, 0));
+
+        // Backend fields used in a handler jump to the field
+        let count = goto(APP, "count", 2, &registry).unwrap();
+        assert_eq!(count.start, Position::new(3, 4));
+    }
+
+    #[test]
+    fn test_imported_definition() {
+        let dir = std::env::temp_dir().join(format!("frel-lsp-definition-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_path = dir.join("data.frel");
+        let data = "module data\n\nbackend Store {\n    total : i32 = 0\n}\n";
+        std::fs::write(&data_path, data).unwrap();
+
+        let source = "module app\n\nimport data.Store\n\nblueprint Main {\n    with Store\n    text { \"x\" } .. on_click { total = 1 }\n}\n";
+        let sources = vec![
+            (data_path.display().to_string(), data.to_string()),
+            ("/project/app.frel".to_string(), source.to_string()),
+        ];
+        let registry = build_registry(&sources, "/project/app.frel");
+
+        let store = source.find("Store\n    text").unwrap() as u32;
+        let location = definition(source, "/project/app.frel", &uri(), store, &registry).unwrap();
+        assert_eq!(location.uri, Url::from_file_path(&data_path).unwrap());
+        assert_eq!(location.range.start, Position::new(2, 0));
+
+        let total = source.find("total").unwrap() as u32;
+        let location = definition(source, "/project/app.frel", &uri(), total, &registry).unwrap();
+        assert_eq!(location.range.start, Position::new(3, 4));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_word_at() {
+        assert_eq!(word_at("with Counter\n", 7), Some("Counter"));
+        assert_eq!(word_at("with Counter\n", 12), Some("Counter"));
+        assert_eq!(word_at("a + b", 2), None);
+    }
+}
@@ -0,0 +1,310 @@
+// Diagnostics for open documents
+//
+// Runs the parser and semantic analysis on a document and converts the
+// resulting compiler diagnostics into LSP diagnostics:
+// - Spans (byte offsets) become ranges in UTF-16 code units
+// - Secondary labels and related info become related information
+// - Help text is appended to the message, as editors have no field for it
+// - With `--remap-diagnostics`, diagnostics of a generated document are
+//   published to the templates its source map points to
+
+use std::path::Path;
+
+use frel_compiler_core::diagnostic::{self, Severity};
+use frel_compiler_core::source_map::SourceMap;
+use frel_compiler_core::{semantic, Diagnostics, DiskFs, Edition, Features, LineIndex, Span};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    NumberOrString, Position, Range, Url,
+};
+
+/// Parse and analyze a document, returning all diagnostics
+pub fn analyze(source: &str, path: &str, features: &Features) -> Diagnostics {
+    let result = frel_compiler_core::parse_file_with_features(
+        source,
+        path,
+        Edition::LATEST,
+        features.clone(),
+    );
+    let mut diagnostics = result.diagnostics;
+
+    if let Some(file) = result.file {
+        diagnostics.merge(semantic::analyze(&file).diagnostics);
+    }
+
+    diagnostics
+}
+
+/// A document's source with its line index, for converting spans to ranges
+pub struct Document<'a> {
+    source: &'a str,
+    index: LineIndex,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            index: LineIndex::new(source),
+        }
+    }
+
+    /// Convert a byte offset to a position, with the column in UTF-16 code units
+    pub fn position(&self, offset: u32) -> Position {
+        let mut offset = (offset as usize).min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = self.index.line_col(offset as u32).line - 1;
+        let line_start = self.index.line_start(line as usize).unwrap_or(0) as usize;
+        let character = self.source[line_start..offset].encode_utf16().count();
+
+        Position::new(line, character as u32)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// Convert a position back to a byte offset, clamping to the line end
+    pub fn offset(&self, position: Position) -> u32 {
+        let Some(line_start) = self.index.line_start(position.line as usize) else {
+            return self.source.len() as u32;
+        };
+        let line = &self.source[line_start as usize..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+
+        let mut units = 0;
+        for (offset, ch) in line.char_indices() {
+            if units >= position.character as usize {
+                return line_start + offset as u32;
+            }
+            units += ch.len_utf16();
+        }
+        line_start + line.len() as u32
+    }
+}
+
+/// Convert compiler diagnostics of the document at `uri` into LSP diagnostics
+pub fn to_lsp(diagnostics: &Diagnostics, source: &str, uri: &Url) -> Vec<Diagnostic> {
+    let document = Document::new(source);
+    diagnostics
+        .iter()
+        .map(|diag| convert(diag, &document, uri))
+        .collect()
+}
+
+/// Convert compiler diagnostics of the document at `uri` into LSP diagnostics
+/// by the URI they are published to, the document's own first
+///
+/// Diagnostics covered by the source map next to the document are moved into
+/// the templates it points to, which are read with `read`.
+pub fn to_lsp_remapped(
+    diagnostics: &Diagnostics,
+    source: &str,
+    uri: &Url,
+    read: impl Fn(&str) -> Option<String>,
+) -> Vec<(Url, Vec<Diagnostic>)> {
+    let map = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| SourceMap::load_sidecar(&DiskFs, &path))
+        .and_then(Result::ok);
+    let Some(map) = map else {
+        return vec![(uri.clone(), to_lsp(diagnostics, source, uri))];
+    };
+
+    let (unmapped, remapped) = map.remap_diagnostics(diagnostics, source, read);
+    let mut published = vec![(uri.clone(), to_lsp(&unmapped, source, uri))];
+    for template in remapped {
+        if let Ok(template_uri) = Url::from_file_path(&template.file) {
+            let lsp = to_lsp(&template.diagnostics, &template.source, &template_uri);
+            published.push((template_uri, lsp));
+        }
+    }
+    published
+}
+
+/// Convert a single compiler diagnostic of the document at `uri`
+pub fn convert(diag: &diagnostic::Diagnostic, document: &Document, uri: &Url) -> Diagnostic {
+    let mut message = diag.message.clone();
+    if let Some(help) = &diag.help {
+        message.push_str("\n\nhelp: ");
+        message.push_str(help);
+    }
+
+    // Labels other than the primary one point elsewhere in the same document
+    let labels = diag
+        .labels
+        .iter()
+        .filter(|label| !label.message.is_empty() && label.span != diag.span)
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), document.range(label.span)),
+            message: label.message.clone(),
+        });
+
+    let related = diag.related.iter().map(|info| {
+        let location = match &info.file {
+            Some(file) => other_file_location(file, info.span, uri),
+            None => Some(Location::new(uri.clone(), document.range(info.span))),
+        };
+        DiagnosticRelatedInformation {
+            // Fall back to the diagnostic itself if the other file is gone
+            location: location
+                .unwrap_or_else(|| Location::new(uri.clone(), document.range(diag.span))),
+            message: info.message.clone(),
+        }
+    });
+
+    let related_information: Vec<_> = labels.chain(related).collect();
+
+    let tags: Vec<_> = diag
+        .tags
+        .iter()
+        .map(|tag| match tag {
+            diagnostic::DiagnosticTag::Unnecessary => DiagnosticTag::UNNECESSARY,
+            diagnostic::DiagnosticTag::Deprecated => DiagnosticTag::DEPRECATED,
+        })
+        .collect();
+
+    Diagnostic {
+        range: document.range(diag.span),
+        severity: Some(severity(diag.severity)),
+        code: diag.code.clone().map(NumberOrString::String),
+        code_description: None,
+        source: Some("frel".to_string()),
+        message,
+        related_information: (!related_information.is_empty()).then_some(related_information),
+        tags: (!tags.is_empty()).then_some(tags),
+        data: diag.data.clone(),
+    }
+}
+
+fn severity(severity: Severity) -> DiagnosticSeverity {
+    match severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Info => DiagnosticSeverity::INFORMATION,
+        Severity::Hint => DiagnosticSeverity::HINT,
+    }
+}
+
+/// Location of a span in another file, read from disk to compute the range
+///
+/// Relative paths are resolved against the directory of the current document.
+pub fn other_file_location(file: &str, span: Span, uri: &Url) -> Option<Location> {
+    let path = match uri.to_file_path() {
+        Ok(current) if Path::new(file).is_relative() => current.parent()?.join(file),
+        _ => Path::new(file).to_path_buf(),
+    };
+    let source = std::fs::read_to_string(&path).ok()?;
+    let other = Url::from_file_path(&path).ok()?;
+    Some(Location::new(other, Document::new(&source).range(span)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frel_compiler_core::diagnostic::{Label, RelatedInfo};
+    use frel_compiler_core::Experimental;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/app.frel").unwrap()
+    }
+
+    #[test]
+    fn test_position_utf16() {
+        let document = Document::new("a\n\"héllo😀\" x");
+        assert_eq!(document.position(0), Position::new(0, 0));
+        assert_eq!(document.position(2), Position::new(1, 0));
+        // é is 2 bytes and 1 code unit, 😀 is 4 bytes and 2 code units
+        assert_eq!(document.position(14), Position::new(1, 9));
+        // Offsets inside a character and past the end are clamped
+        assert_eq!(document.position(5), Position::new(1, 2));
+        assert_eq!(document.position(100), Position::new(1, 11));
+
+        assert_eq!(document.offset(Position::new(1, 9)), 14);
+        assert_eq!(document.offset(Position::new(1, 2)), 4);
+        assert_eq!(document.offset(Position::new(0, 50)), 1);
+    }
+
+    #[test]
+    fn test_convert_diagnostic() {
+        let source = "module app\n\nblueprint Main {\n    count : u32 = 0\n}\n";
+        let diag = diagnostic::Diagnostic::error("duplicate", Span::new(33, 38))
+            .with_code("E0302")
+            .with_help("rename one of them")
+            .with_label(Label::primary(Span::new(33, 38)))
+            .with_label(Label::new(Span::new(12, 21), "in this blueprint"))
+            .with_related(RelatedInfo::new(Span::new(0, 6), "module declared here"))
+            .unnecessary();
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.add(diag);
+
+        let converted = to_lsp(&diagnostics, source, &uri());
+        assert_eq!(converted.len(), 1);
+        let lsp = &converted[0];
+        assert_eq!(
+            lsp.range,
+            Range::new(Position::new(3, 4), Position::new(3, 9))
+        );
+        assert_eq!(lsp.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(lsp.code, Some(NumberOrString::String("E0302".to_string())));
+        assert_eq!(lsp.message, "duplicate\n\nhelp: rename one of them");
+        assert_eq!(lsp.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        let related = lsp.related_information.as_ref().unwrap();
+        let messages: Vec<_> = related.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["in this blueprint", "module declared here"]);
+        assert_eq!(related[0].location.range.start, Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_analyze_reports_semantic_errors() {
+        let source = "module app\n\nblueprint Main {\n    with Missing\n}\n";
+        let diagnostics = analyze(source, "app.frel", &Features::default());
+        assert!(diagnostics.has_errors());
+        let lsp = to_lsp(&diagnostics, source, &uri());
+        assert!(lsp.iter().all(|d| d.code.is_some()));
+        assert_eq!(lsp[0].range.start.line, 3);
+    }
+
+    #[test]
+    fn test_remap_to_template() {
+        let dir = std::env::temp_dir().join(format!("frel-lsp-remap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = "module app\n\nblueprint Main {\n    with Missing\n}\n";
+        let template = format!("{{{{! header }}}}\n{}", source);
+        std::fs::write(dir.join("app.frel.tpl"), &template).unwrap();
+        let map =
+            r#"{"version":3,"sources":["app.frel.tpl"],"mappings":"AACA;AACA;AACA;AACA;AACA"}"#;
+        std::fs::write(dir.join("app.frel.map"), map).unwrap();
+
+        let uri = Url::from_file_path(dir.join("app.frel")).unwrap();
+        let diagnostics = analyze(source, "app.frel", &Features::default());
+        let read = |file: &str| std::fs::read_to_string(file).ok();
+        let published = to_lsp_remapped(&diagnostics, source, &uri, read);
+        assert_eq!(published.len(), 2);
+        assert!(published[0].1.is_empty());
+        let template_uri = Url::from_file_path(dir.join("app.frel.tpl")).unwrap();
+        assert_eq!(published[1].0, template_uri);
+        assert_eq!(published[1].1[0].range.start, Position::new(4, 4));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_analyze_with_features() {
+        let source = "module app\n\nbackend Users {\n    command load(id : i32) : String\n}\n";
+        let diagnostics = analyze(source, "app.frel", &Features::default());
+        let lsp = to_lsp(&diagnostics, source, &uri());
+        assert_eq!(
+            lsp[0].code,
+            Some(NumberOrString::String("E0210".to_string()))
+        );
+
+        let features = [Experimental::CommandResults].into_iter().collect();
+        assert!(!analyze(source, "app.frel", &features).has_errors());
+    }
+}
@@ -0,0 +1,43 @@
+// Formatting
+//
+// Formats a whole document with frel-fmt, like `frelc fmt`. Documents with
+// parse errors are left untouched.
+
+use frel_compiler_core::Span;
+use tower_lsp::lsp_types::TextEdit;
+
+use crate::diagnostics::Document;
+
+/// Edits formatting the document, `None` if it does not parse
+pub fn format_document(source: &str) -> Option<Vec<TextEdit>> {
+    let formatted = frel_fmt::format_source(source).ok()?;
+    if formatted == source {
+        return Some(Vec::new());
+    }
+    let range = Document::new(source).range(Span::new(0, source.len() as u32));
+    Some(vec![TextEdit::new(range, formatted)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    #[test]
+    fn test_format_document() {
+        let source = "module app\n\nbackend Counter {\ncount:i32=0\n}";
+        let edits = format_document(source).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(0, 0), Position::new(4, 1))
+        );
+        assert_eq!(
+            edits[0].new_text,
+            "module app\n\nbackend Counter {\n    count : i32 = 0\n}\n"
+        );
+
+        assert!(format_document(&edits[0].new_text).unwrap().is_empty());
+        assert!(format_document("module app\n\nbackend Counter {\n").is_none());
+    }
+}
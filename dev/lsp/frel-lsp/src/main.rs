@@ -1,22 +1,85 @@
 // Frel Language Server Protocol Implementation
 //
-// This will provide IDE features like:
-// - Diagnostics (errors/warnings)
+// Provides IDE features over stdio, accepting the experimental syntax of
+// features given as `--enable-feature <name>` and publishing diagnostics of
+// generated files to their templates with `--remap-diagnostics`:
+// - Diagnostics (parse, name resolution and type errors)
+// - Go-to-definition, including definitions in other modules
+// - Quick fixes from the suggestions of diagnostics
+// - Renaming a symbol and its references in a document
+// - Formatting documents in the canonical style
+//
+// Planned:
 // - Autocomplete
-// - Go-to-definition
 // - Hover information
-// - Code formatting
-//
-// Status: Placeholder - to be implemented
 
-fn main() {
-    println!("Frel LSP Server - Not yet implemented");
-    println!("This will be implemented using tower-lsp once the compiler is stable.");
+mod actions;
+mod definition;
+mod diagnostics;
+mod formatting;
+mod rename;
+mod server;
+
+use frel_compiler_core::{Experimental, Features};
+use tower_lsp::{LspService, Server};
+
+use server::FrelLanguageServer;
+
+#[tokio::main]
+async fn main() {
+    let features = match enabled_features(std::env::args().skip(1)) {
+        Ok(features) => features,
+        Err(message) => {
+            eprintln!("frel-lsp: {}", message);
+            std::process::exit(2);
+        }
+    };
+
+    let remap_diagnostics = std::env::args().any(|arg| arg == "--remap-diagnostics");
+
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+
+    let (service, socket) =
+        LspService::new(|client| FrelLanguageServer::new(client, features, remap_diagnostics));
+    Server::new(stdin, stdout, socket).serve(service).await;
+}
+
+/// Features enabled by `--enable-feature <name>` arguments
+fn enabled_features(mut args: impl Iterator<Item = String>) -> Result<Features, String> {
+    let mut features = Features::default();
+    while let Some(arg) = args.next() {
+        let name = match arg.strip_prefix("--enable-feature") {
+            Some("") => args
+                .next()
+                .ok_or("--enable-feature requires a feature name")?,
+            Some(value) if value.starts_with('=') => value[1..].to_string(),
+            _ => continue,
+        };
+        features.enable(name.parse::<Experimental>()?);
+    }
+    Ok(features)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
 
-    // Future implementation will use:
-    // - tower_lsp for LSP protocol handling
-    // - frel_core for parsing and semantic analysis
-    // - tokio for async runtime
+    #[test]
+    fn test_enabled_features() {
+        let features = enabled_features(args(&["--stdio", "--enable-feature", "command-results"]));
+        assert!(features.unwrap().is_enabled(Experimental::CommandResults));
+        let features = enabled_features(args(&["--enable-feature=command-results"]));
+        assert!(features.unwrap().is_enabled(Experimental::CommandResults));
 
-    std::process::exit(1);
+        assert!(enabled_features(args(&["--enable-feature"])).is_err());
+        assert!(enabled_features(args(&["--enable-feature", "generics"])).is_err());
+    }
 }
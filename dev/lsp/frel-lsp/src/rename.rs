@@ -0,0 +1,133 @@
+// Rename
+//
+// Renames the symbol at a position and all of its references in the document,
+// including backend members used through `with`. Symbols imported from other
+// modules are refused: they have to be renamed where they are declared.
+
+use std::collections::HashMap;
+
+use frel_compiler_core::semantic::rename;
+use frel_compiler_core::{resolve_with_registry, ResolveResult, SignatureRegistry, Span};
+use tower_lsp::lsp_types::{PrepareRenameResponse, TextEdit, Url, WorkspaceEdit};
+
+use crate::diagnostics::Document;
+
+/// Range and current name of the symbol at `offset`, if it can be renamed
+pub fn prepare_rename(
+    source: &str,
+    path: &str,
+    offset: u32,
+    registry: &SignatureRegistry,
+) -> Option<PrepareRenameResponse> {
+    let result = resolve(source, path, registry)?;
+    let symbol = rename::symbol_at(source, &result, offset)?;
+    let span = rename::references(source, &result, symbol)
+        .into_iter()
+        .find(|span| span.start <= offset && offset <= span.end)?;
+
+    Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: Document::new(source).range(span),
+        placeholder: source[span.start as usize..span.end as usize].to_string(),
+    })
+}
+
+/// Edits renaming the symbol at `offset` to `new_name`
+///
+/// Returns `Ok(None)` when there is no symbol at the offset.
+pub fn rename_at(
+    source: &str,
+    path: &str,
+    uri: &Url,
+    offset: u32,
+    new_name: &str,
+    registry: &SignatureRegistry,
+) -> Result<Option<WorkspaceEdit>, String> {
+    let Some(result) = resolve(source, path, registry) else {
+        return Ok(None);
+    };
+    let Some(symbol) = rename::symbol_at(source, &result, offset) else {
+        return Ok(None);
+    };
+    let spans: Vec<Span> = rename::rename(source, &result, symbol, new_name)?;
+
+    let document = Document::new(source);
+    let edits = spans
+        .into_iter()
+        .map(|span| TextEdit::new(document.range(span), new_name.to_string()))
+        .collect();
+    Ok(Some(WorkspaceEdit::new(HashMap::from([(
+        uri.clone(),
+        edits,
+    )]))))
+}
+
+fn resolve(source: &str, path: &str, registry: &SignatureRegistry) -> Option<ResolveResult> {
+    let file = frel_compiler_core::parse_file_with_path(source, path).file?;
+    Some(resolve_with_registry(&file, registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    const APP: &str = r#"module app
+
+backend Counter {
+    count : i32 = 0
+}
+
+blueprint Main {
+    with Counter
+    doubled : i32 = count * 2
+}
+"#;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/app.frel").unwrap()
+    }
+
+    #[test]
+    fn test_rename() {
+        let registry = SignatureRegistry::new();
+        let offset = APP.find("count *").unwrap() as u32 + 2;
+
+        let prepared = prepare_rename(APP, "/project/app.frel", offset, &registry).unwrap();
+        let PrepareRenameResponse::RangeWithPlaceholder { range, placeholder } = prepared else {
+            panic!("expected a range with placeholder");
+        };
+        assert_eq!(placeholder, "count");
+        assert_eq!(
+            range,
+            Range::new(Position::new(8, 20), Position::new(8, 25))
+        );
+
+        let edit = rename_at(
+            APP,
+            "/project/app.frel",
+            &uri(),
+            offset,
+            "clicks",
+            &registry,
+        )
+        .unwrap()
+        .unwrap();
+        let edits = &edit.changes.unwrap()[&uri()];
+        let lines: Vec<u32> = edits.iter().map(|e| e.range.start.line).collect();
+        assert_eq!(lines, [3, 8]);
+        assert!(edits.iter().all(|e| e.new_text == "clicks"));
+
+        let taken = rename_at(
+            APP,
+            "/project/app.frel",
+            &uri(),
+            offset,
+            "doubled",
+            &registry,
+        );
+        assert_eq!(
+            taken.unwrap_err(),
+            "`doubled` is already defined in this scope"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// Language server implementation
+//
+// Keeps the text of open documents and re-analyzes a document whenever it is
+// opened or changed, publishing the resulting diagnostics to the client.
+// Definitions are looked up across the `.frel` files of the workspace folders.
+// Code actions apply the fixes suggested by diagnostics.
+// Renames edit the references within the document, formatting replaces it.
+// With `--remap-diagnostics`, diagnostics of generated documents are published
+// to the templates their source maps point to.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use dashmap::DashMap;
+use frel_compiler_core::Features;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer};
+
+use crate::actions;
+use crate::definition::{self, document_path};
+use crate::diagnostics::{self, Document};
+use crate::formatting;
+use crate::rename;
+
+pub struct FrelLanguageServer {
+    client: Client,
+    /// Text of open documents
+    documents: DashMap<Url, String>,
+    /// Workspace folders searched for other modules
+    roots: RwLock<Vec<PathBuf>>,
+    /// Experimental features enabled on the command line
+    features: Features,
+    /// Publish diagnostics at template positions, by source maps
+    remap_diagnostics: bool,
+    /// Templates each document last published diagnostics to
+    templates: DashMap<Url, Vec<Url>>,
+}
+
+impl FrelLanguageServer {
+    pub fn new(client: Client, features: Features, remap_diagnostics: bool) -> Self {
+        Self {
+            client,
+            documents: DashMap::new(),
+            roots: RwLock::new(Vec::new()),
+            features,
+            remap_diagnostics,
+            templates: DashMap::new(),
+        }
+    }
+
+    /// Analyze a document and publish its diagnostics
+    async fn check(&self, uri: Url, source: String, version: Option<i32>) {
+        let path = document_path(&uri);
+        let found = diagnostics::analyze(&source, &path, &self.features);
+        if !self.remap_diagnostics {
+            let lsp = diagnostics::to_lsp(&found, &source, &uri);
+            self.documents.insert(uri.clone(), source);
+            self.client.publish_diagnostics(uri, lsp, version).await;
+            return;
+        }
+
+        // Open templates take precedence over the files on disk
+        let read = |file: &str| {
+            let open = Url::from_file_path(file)
+                .ok()
+                .and_then(|template| self.documents.get(&template).map(|text| text.clone()));
+            open.or_else(|| std::fs::read_to_string(file).ok())
+        };
+        let mut published = diagnostics::to_lsp_remapped(&found, &source, &uri, read);
+        self.documents.insert(uri.clone(), source);
+
+        let (_, lsp) = published.remove(0);
+        self.client
+            .publish_diagnostics(uri.clone(), lsp, version)
+            .await;
+        let templates: Vec<Url> = published
+            .iter()
+            .map(|(template, _)| template.clone())
+            .collect();
+        for (template, lsp) in published {
+            self.client.publish_diagnostics(template, lsp, None).await;
+        }
+        self.clear_templates(&uri, templates).await;
+    }
+
+    /// Record the templates a document published to, clearing the ones it no
+    /// longer has diagnostics for
+    async fn clear_templates(&self, uri: &Url, templates: Vec<Url>) {
+        let previous = self.templates.insert(uri.clone(), templates.clone());
+        for template in previous.unwrap_or_default() {
+            if !templates.contains(&template) {
+                self.client
+                    .publish_diagnostics(template, vec![], None)
+                    .await;
+            }
+        }
+    }
+
+    /// Frel sources of the workspace as (path, text), open documents taking
+    /// precedence over the files on disk
+    fn workspace_sources(&self, uri: &Url) -> Vec<(String, String)> {
+        let mut roots = self.roots.read().unwrap().clone();
+        if roots.is_empty() {
+            roots.extend(definition::fallback_root(uri));
+        }
+
+        let mut sources = BTreeMap::new();
+        for path in definition::frel_files(&roots) {
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                sources.insert(path.display().to_string(), text);
+            }
+        }
+        for document in self.documents.iter() {
+            sources.insert(document_path(document.key()), document.value().clone());
+        }
+        sources.into_iter().collect()
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for FrelLanguageServer {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let folders = params.workspace_folders.unwrap_or_default();
+        *self.roots.write().unwrap() = folders
+            .iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect();
+
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                definition_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::SOURCE_FIX_ALL,
+                        ]),
+                        ..CodeActionOptions::default()
+                    },
+                )),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                ..ServerCapabilities::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "frel-lsp".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, "Frel language server initialized")
+            .await;
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.check(document.uri, document.text, Some(document.version))
+            .await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // Full sync: the last change holds the whole document
+        let Some(change) = params.content_changes.into_iter().last() else {
+            return;
+        };
+        let document = params.text_document;
+        self.check(document.uri, change.text, Some(document.version))
+            .await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.remove(&uri);
+        // Clear diagnostics of closed documents
+        self.clear_templates(&uri, Vec::new()).await;
+        self.templates.remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let Some(source) = self.documents.get(&uri).map(|text| text.clone()) else {
+            return Ok(None);
+        };
+
+        let path = document_path(&uri);
+        let offset = Document::new(&source).offset(position.position);
+        let registry = definition::build_registry(&self.workspace_sources(&uri), &path);
+
+        Ok(
+            definition::definition(&source, &path, &uri, offset, &registry)
+                .map(GotoDefinitionResponse::Scalar),
+        )
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Some(source) = self.documents.get(&uri).map(|text| text.clone()) else {
+            return Ok(None);
+        };
+
+        let found = diagnostics::analyze(&source, &document_path(&uri), &self.features);
+        let only = params.context.only.as_deref();
+        let response = actions::code_actions(&found, &source, &uri, params.range, only);
+        Ok((!response.is_empty()).then_some(response))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri;
+        let Some(source) = self.documents.get(&uri).map(|text| text.clone()) else {
+            return Ok(None);
+        };
+
+        let path = document_path(&uri);
+        let offset = Document::new(&source).offset(params.position);
+        let registry = definition::build_registry(&self.workspace_sources(&uri), &path);
+        Ok(rename::prepare_rename(&source, &path, offset, &registry))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let Some(source) = self.documents.get(&uri).map(|text| text.clone()) else {
+            return Ok(None);
+        };
+
+        let path = document_path(&uri);
+        let offset = Document::new(&source).offset(position.position);
+        let registry = definition::build_registry(&self.workspace_sources(&uri), &path);
+        rename::rename_at(&source, &path, &uri, offset, &params.new_name, &registry)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let Some(source) = self.documents.get(&uri).map(|text| text.clone()) else {
+            return Ok(None);
+        };
+
+        Ok(formatting::format_document(&source))
+    }
+}
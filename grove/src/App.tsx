@@ -1,6 +1,6 @@
 import { useState } from 'react';
 import { useCompiler } from './hooks/useCompiler';
-import type { OutputTab, DiagnosticInfo, ModuleInfo, DevMode, CompareResponse, ScopeInfo } from './types';
+import type { OutputTab, DiagnosticInfo, ModuleInfo, DevMode, CompareResponse, ScopeInfo, AstDumpOptions } from './types';
 
 function App() {
   const compiler = useCompiler();
@@ -27,6 +27,8 @@ function App() {
         diagnostics={compiler.diagnostics}
         ast={compiler.ast}
         astDump={compiler.astDump}
+        astOptions={compiler.astOptions}
+        onAstOptionsChange={compiler.setAstOptions}
         scopes={compiler.scopes}
         generatedJs={compiler.generatedJs}
       />
@@ -177,11 +179,23 @@ interface OutputAreaProps {
   diagnostics: DiagnosticInfo[];
   ast: unknown | null;
   astDump: string;
+  astOptions: AstDumpOptions;
+  onAstOptionsChange: (options: AstDumpOptions) => void;
   scopes: ScopeInfo[];
   generatedJs: string;
 }
 
-function OutputArea({ activeTab, onTabChange, diagnostics, ast, astDump, scopes, generatedJs }: OutputAreaProps) {
+function OutputArea({
+  activeTab,
+  onTabChange,
+  diagnostics,
+  ast,
+  astDump,
+  astOptions,
+  onAstOptionsChange,
+  scopes,
+  generatedJs,
+}: OutputAreaProps) {
   return (
     <div className="output-area">
       <div className="output-tabs">
@@ -215,7 +229,12 @@ function OutputArea({ activeTab, onTabChange, diagnostics, ast, astDump, scopes,
           <DiagnosticsPanel diagnostics={diagnostics} />
         )}
         {activeTab === 'ast' && (
-          <AstPanel ast={ast} astDump={astDump} />
+          <AstPanel
+            ast={ast}
+            astDump={astDump}
+            options={astOptions}
+            onOptionsChange={onAstOptionsChange}
+          />
         )}
         {activeTab === 'scope' && (
           <ScopePanel scopes={scopes} />
@@ -264,7 +283,14 @@ function DiagnosticsPanel({ diagnostics }: { diagnostics: DiagnosticInfo[] }) {
 }
 
 // AST panel with syntax highlighting
-function AstPanel({ ast, astDump }: { ast: unknown | null; astDump: string }) {
+interface AstPanelProps {
+  ast: unknown | null;
+  astDump: string;
+  options: AstDumpOptions;
+  onOptionsChange: (options: AstDumpOptions) => void;
+}
+
+function AstPanel({ ast, astDump, options, onOptionsChange }: AstPanelProps) {
   if (!ast) {
     return (
       <div className="empty-state">
@@ -281,6 +307,17 @@ function AstPanel({ ast, astDump }: { ast: unknown | null; astDump: string }) {
       let remaining = line;
       let key = 0;
 
+      // Nodes folded below the depth shown
+      if (line.trimStart().startsWith('…')) {
+        return <div key={i} className="ast-line ast-folded">{line}</div>;
+      }
+
+      // Type and span appended by the dump options
+      const suffixMatch = remaining.match(/^(.*?)( -> .*?)?( @\d+\.\.\d+)?$/);
+      const typeSuffix = suffixMatch?.[2] ?? '';
+      const spanSuffix = suffixMatch?.[3] ?? '';
+      remaining = remaining.slice(0, remaining.length - typeSuffix.length - spanSuffix.length);
+
       // Keywords that start a line
       const keywordMatch = remaining.match(/^(\s*)(FILE|IMPORT|BLUEPRINT|BACKEND|CONTRACT|SCHEME|ENUM|THEME|ARENA|WITH|LOCAL|FRAGMENT|BLOCK|WHEN|ELSE|REPEAT|SELECT|CASE|ON|INSTR|LAYOUT|COLUMN_SIZES|ROW|SLOT|REF|INLINE|FIELD|METHOD|COMMAND|INCLUDE|VIRTUAL|SET|VARIANT|CONTENT|TERNARY|THEN|INSTR_REF|ASSET)\b(.*)/);
 
@@ -339,14 +376,53 @@ function AstPanel({ ast, astDump }: { ast: unknown | null; astDump: string }) {
           parts.push(<span key={key++}>{highlighted.slice(lastIndex)}</span>);
         }
       }
+      if (typeSuffix) {
+        parts.push(<span key={key++} className="ast-inferred">{typeSuffix}</span>);
+      }
+      if (spanSuffix) {
+        parts.push(<span key={key++} className="ast-span">{spanSuffix}</span>);
+      }
 
       return <div key={i} className="ast-line">{parts.length > 0 ? parts : line}</div>;
     });
   };
 
+  const toggle = (option: 'collapse' | 'spans' | 'types') => (
+    <label className="mode-toggle">
+      <input
+        type="checkbox"
+        checked={options[option]}
+        onChange={(e) => onOptionsChange({ ...options, [option]: e.target.checked })}
+      />
+      <span>{option[0].toUpperCase() + option.slice(1)}</span>
+    </label>
+  );
+
   return (
-    <div className="ast-tree">
-      {highlightDump(astDump)}
+    <div className="ast-panel">
+      <div className="ast-toolbar">
+        <label className="mode-toggle">
+          <span>Depth</span>
+          <select
+            value={options.depth ?? ''}
+            onChange={(e) => onOptionsChange({
+              ...options,
+              depth: e.target.value === '' ? null : Number(e.target.value),
+            })}
+          >
+            <option value="">All</option>
+            {[1, 2, 3, 4, 5, 6].map((depth) => (
+              <option key={depth} value={depth}>{depth}</option>
+            ))}
+          </select>
+        </label>
+        {toggle('collapse')}
+        {toggle('spans')}
+        {toggle('types')}
+      </div>
+      <div className="ast-tree">
+        {highlightDump(astDump)}
+      </div>
     </div>
   );
 }
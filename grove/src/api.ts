@@ -5,6 +5,7 @@ import type {
   ModulesResponse,
   DiagnosticsResponse,
   AstResponse,
+  AstDumpOptions,
   GeneratedResponse,
   ScopeResponse,
   SourceResponse,
@@ -41,8 +42,14 @@ export const api = {
     return fetchJson(path);
   },
 
-  async getAst(module: string): Promise<AstResponse> {
-    return fetchJson(`/ast/${encodeURIComponent(module)}`);
+  async getAst(module: string, options?: AstDumpOptions): Promise<AstResponse> {
+    const params = new URLSearchParams();
+    if (options?.depth != null) params.set('depth', String(options.depth));
+    if (options?.collapse) params.set('collapse', 'true');
+    if (options?.spans) params.set('spans', 'true');
+    if (options?.types) params.set('types', 'true');
+    const query = params.toString();
+    return fetchJson(`/ast/${encodeURIComponent(module)}${query ? `?${query}` : ''}`);
   },
 
   async getGenerated(module: string): Promise<GeneratedResponse> {
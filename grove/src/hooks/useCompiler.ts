@@ -10,6 +10,7 @@ import type {
   DevMode,
   CompareResponse,
   ScopeInfo,
+  AstDumpOptions,
 } from '../types';
 
 export interface CompilerState {
@@ -38,6 +39,7 @@ export interface CompilerState {
   diagnostics: DiagnosticInfo[];
   ast: unknown | null;
   astDump: string;
+  astOptions: AstDumpOptions;
   scopes: ScopeInfo[];
   generatedJs: string;
 
@@ -51,6 +53,7 @@ export interface CompilerState {
   selectModule: (modulePath: string) => void;
   selectFile: (filePath: string) => void;
   setActiveTab: (tab: OutputTab) => void;
+  setAstOptions: (options: AstDumpOptions) => void;
   updateSource: (content: string) => void;
   setDevMode: (mode: DevMode) => void;
   saveExpectations: () => Promise<void>;
@@ -97,6 +100,13 @@ export function useCompiler(): CompilerState {
   const [diagnostics, setDiagnostics] = useState<DiagnosticInfo[]>([]);
   const [ast, setAst] = useState<unknown | null>(null);
   const [astDump, setAstDump] = useState('');
+  const [astOptions, setAstOptions] = useState<AstDumpOptions>({
+    depth: null,
+    collapse: false,
+    spans: false,
+    types: false,
+  });
+  const astOptionsRef = useRef(astOptions);
   const [scopes, setScopes] = useState<ScopeInfo[]>([]);
   const [generatedJs, setGeneratedJs] = useState('');
 
@@ -109,6 +119,10 @@ export function useCompiler(): CompilerState {
     devModeRef.current = devMode;
   }, [devMode]);
 
+  useEffect(() => {
+    astOptionsRef.current = astOptions;
+  }, [astOptions]);
+
   // Fetch server status and modules
   const refresh = useCallback(async () => {
     setStatus('connecting');
@@ -138,7 +152,7 @@ export function useCompiler(): CompilerState {
     try {
       const [diagRes, astRes, scopeRes, genRes] = await Promise.all([
         api.getDiagnostics(modulePath),
-        api.getAst(modulePath).catch(() => ({ module: modulePath, ast: null, dump: '' })),
+        api.getAst(modulePath, astOptionsRef.current).catch(() => ({ module: modulePath, ast: null, dump: '' })),
         api.getScope(modulePath).catch(() => ({ module: modulePath, scopes: [] })),
         api.getGenerated(modulePath).catch(() => ({ module: modulePath, javascript: '' })),
       ]);
@@ -218,7 +232,7 @@ export function useCompiler(): CompilerState {
         if (currentModule) {
           const fetchPromises: Promise<unknown>[] = [
             api.getDiagnostics(currentModule),
-            api.getAst(currentModule).catch(() => ({ module: currentModule, ast: null, dump: '' })),
+            api.getAst(currentModule, astOptionsRef.current).catch(() => ({ module: currentModule, ast: null, dump: '' })),
             api.getScope(currentModule).catch(() => ({ module: currentModule, scopes: [] })),
             api.getGenerated(currentModule).catch(() => ({ module: currentModule, javascript: '' })),
             api.getStatus(),
@@ -261,6 +275,19 @@ export function useCompiler(): CompilerState {
     }, 500); // 500ms debounce
   }, []);
 
+  // Reload the dump when its options change
+  useEffect(() => {
+    const currentModule = selectedModuleRef.current;
+    if (!currentModule) return;
+
+    api.getAst(currentModule, astOptions)
+      .then((astRes) => {
+        setAst(astRes.ast);
+        setAstDump(astRes.dump);
+      })
+      .catch((err) => console.error('Failed to load AST:', err));
+  }, [astOptions]);
+
   // Initial fetch
   useEffect(() => {
     refresh();
@@ -339,6 +366,7 @@ export function useCompiler(): CompilerState {
     diagnostics,
     ast,
     astDump,
+    astOptions,
     scopes,
     generatedJs,
     devMode,
@@ -348,6 +376,7 @@ export function useCompiler(): CompilerState {
     selectModule,
     selectFile,
     setActiveTab,
+    setAstOptions,
     updateSource,
     setDevMode,
     saveExpectations,
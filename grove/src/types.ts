@@ -40,6 +40,14 @@ export interface AstResponse {
   dump: string;
 }
 
+// Query parameters of GET /ast shaping the dump
+export interface AstDumpOptions {
+  depth: number | null;
+  collapse: boolean;
+  spans: boolean;
+  types: boolean;
+}
+
 export interface GeneratedResponse {
   module: string;
   javascript: string;
@@ -9,7 +9,12 @@ export class BrowserAdapter {
   private rootElement: HTMLElement;
 
   constructor(rootElement: HTMLElement) {
-    this.runtime = new Runtime();
+    this.runtime = new Runtime({
+      portals: {
+        mount: (id, container) => this.renderer.mountPortal(id, container),
+        unmount: (id, container) => this.renderer.unmountPortal(id, container),
+      },
+    });
     this.rootElement = rootElement;
     this.renderer = new DOMRenderer(this.runtime, rootElement);
 
@@ -1,6 +1,8 @@
 // DOM Renderer - Manages the actual DOM tree
 
-import { Runtime, Fragment } from '@frel/runtime';
+import { Runtime, Fragment, FocusOptions } from '@frel/runtime';
+
+const FOCUSABLE = 'a[href], button, input, select, textarea, [tabindex]';
 
 export class DOMRenderer {
   private runtime: Runtime;
@@ -41,8 +43,12 @@ export class DOMRenderer {
   }
 
   // Node management
-  createNode(fragmentId: number, tagName: string): HTMLElement {
+  createNode(fragmentId: number, tagName: string, stableId?: string | null): HTMLElement {
     const element = document.createElement(tagName);
+    if (stableId) {
+      // Stable selector for E2E tests and analytics: [data-frel-id="app.Main/text"]
+      element.dataset.frelId = stableId;
+    }
     this.nodeMap.set(fragmentId, element);
     return element;
   }
@@ -59,6 +65,118 @@ export class DOMRenderer {
     }
   }
 
+  // Portals
+
+  /**
+   * Move the element of a fragment rendered with `portal` into an overlay
+   * container: the element marked `data-frel-overlay="<container>"`, created
+   * at the end of the document body when there is none.
+   */
+  mountPortal(fragmentId: number, container: string): void {
+    // The element is created after the call site binding runs
+    queueMicrotask(() => {
+      const node = this.nodeMap.get(fragmentId);
+      if (node) this.overlay(container).appendChild(node);
+    });
+  }
+
+  /** Remove the element of a fragment rendered with `portal` from its overlay */
+  unmountPortal(fragmentId: number, _container: string): void {
+    this.removeNode(fragmentId);
+  }
+
+  private overlay(container: string): HTMLElement {
+    const existing = document.querySelector<HTMLElement>(
+      `[data-frel-overlay="${CSS.escape(container)}"]`
+    );
+    if (existing) return existing;
+    const overlay = document.createElement('div');
+    overlay.dataset.frelOverlay = container;
+    document.body.appendChild(overlay);
+    return overlay;
+  }
+
+  // Focus
+
+  /**
+   * Apply the focus instructions of a fragment to its element.
+   *
+   * `tab_index` becomes the `tabindex` attribute, `autofocus` focuses the
+   * element once it is in the document and `group` installs the keyboard
+   * handling of the focus group.
+   */
+  applyFocus(element: HTMLElement, options: FocusOptions): void {
+    if (options.tab_index !== undefined) {
+      element.tabIndex = options.tab_index;
+    }
+    if (options.group) {
+      element.dataset.frelFocusGroup = options.group;
+      element.addEventListener('keydown', (event) =>
+        options.group === 'trap' ? this.trapTab(element, event) : this.moveByArrows(element, event)
+      );
+    }
+    if (options.autofocus) {
+      // The element is attached after the fragment is built
+      queueMicrotask(() => element.focus());
+    }
+  }
+
+  /** Focusable elements of a subtree, in tab order */
+  private tabStops(element: HTMLElement): HTMLElement[] {
+    const stops = Array.from(element.querySelectorAll<HTMLElement>(FOCUSABLE)).filter(
+      (node) => node.tabIndex >= 0
+    );
+    // Positive tabindex values come first, document order breaks ties
+    const rank = (node: HTMLElement) => (node.tabIndex === 0 ? Infinity : node.tabIndex);
+    return stops
+      .map((node, index) => ({ node, index }))
+      .sort((a, b) => rank(a.node) - rank(b.node) || a.index - b.index)
+      .map(({ node }) => node);
+  }
+
+  /** Tab and Shift+Tab cycle within the trap; an inner trap handles the key first */
+  private trapTab(element: HTMLElement, event: KeyboardEvent): void {
+    if (event.key !== 'Tab' || event.defaultPrevented) return;
+    const stops = this.tabStops(element);
+    if (stops.length === 0) return;
+    const current = stops.indexOf(document.activeElement as HTMLElement);
+    const step = event.shiftKey ? -1 : 1;
+    const next = current === -1 ? 0 : (current + step + stops.length) % stops.length;
+    event.preventDefault();
+    stops[next].focus();
+  }
+
+  /** Arrow keys move focus between the elements of the group, Home and End to its ends */
+  private moveByArrows(element: HTMLElement, event: KeyboardEvent): void {
+    if (event.defaultPrevented) return;
+    const items = Array.from(element.querySelectorAll<HTMLElement>(FOCUSABLE));
+    const current = items.indexOf(document.activeElement as HTMLElement);
+    if (items.length === 0 || current === -1) return;
+    let next: number;
+    switch (event.key) {
+      case 'ArrowDown':
+      case 'ArrowRight':
+        next = Math.min(current + 1, items.length - 1);
+        break;
+      case 'ArrowUp':
+      case 'ArrowLeft':
+        next = Math.max(current - 1, 0);
+        break;
+      case 'Home':
+        next = 0;
+        break;
+      case 'End':
+        next = items.length - 1;
+        break;
+      default:
+        return;
+    }
+    event.preventDefault();
+    // Roving tab stop: only the focused item is reached by Tab
+    items.forEach((item, index) => (item.tabIndex = index === next ? 0 : -1));
+    items[next].focus();
+  }
+
   // Style application
   applyStyle(element: HTMLElement, property: string, value: string): void {
     (element.style as any)[property] = value;
@@ -0,0 +1,318 @@
+// Frel Built-in Functions
+//
+// Generated code calls the functions every module can use without an import
+// as `Builtins.name(...)`. The compiler evaluates calls with constant
+// arguments where the result does not depend on the host (ISO parsing, UTC
+// formatting with numeric fields); everything else runs here.
+//
+// Host values: an Instant is a Date, a LocalDate/LocalTime/LocalDateTime an
+// ISO 8601 string, a Timezone an IANA zone name, a Duration a number of
+// milliseconds and a Decimal its decimal text.
+
+/** Date and time fields a pattern is formatted from */
+interface Fields {
+    year?: number;
+    month?: number;
+    day?: number;
+    weekday?: number;
+    hour?: number;
+    minute?: number;
+    second?: number;
+    millisecond?: number;
+}
+
+type PatternPart = { letter: string; width: number } | string;
+
+const PATTERN_LETTERS = 'yMdEHhmsSa';
+
+/** Schemes `url()` keeps; `data:` is kept for images only */
+const SAFE_URL_SCHEMES = ['http', 'https', 'mailto', 'tel'];
+
+const LOCAL_DATE = /^(\d{4})-(\d{2})-(\d{2})$/;
+const LOCAL_TIME = /^(\d{2}):(\d{2})(?::(\d{2})(?:\.(\d{1,9}))?)?$/;
+const INSTANT =
+    /^(\d{4})-(\d{2})-(\d{2})(?:[Tt](\d{2}):(\d{2})(?::(\d{2}))?(?:\.(\d{1,9}))?([Zz]|[+-]\d{2}:\d{2}))?$/;
+
+/** Split a pattern into fields and literal text; `'text'` is literal, `''` a quote */
+function parsePattern(pattern: string): PatternPart[] {
+    const parts: PatternPart[] = [];
+    let literal = '';
+    for (let i = 0; i < pattern.length; i++) {
+        const c = pattern[i];
+        if (c === "'") {
+            if (pattern[i + 1] === "'") {
+                literal += "'";
+                i++;
+                continue;
+            }
+            for (i++; i < pattern.length; i++) {
+                if (pattern[i] !== "'") {
+                    literal += pattern[i];
+                } else if (pattern[i + 1] === "'") {
+                    literal += "'";
+                    i++;
+                } else {
+                    break;
+                }
+            }
+        } else if (PATTERN_LETTERS.includes(c)) {
+            let width = 1;
+            while (pattern[i + 1] === c) {
+                width++;
+                i++;
+            }
+            if (literal) parts.push(literal);
+            literal = '';
+            parts.push({ letter: c, width });
+        } else {
+            literal += c;
+        }
+    }
+    if (literal) parts.push(literal);
+    return parts;
+}
+
+function millis(fraction: string | undefined): number {
+    return fraction ? Number(fraction.slice(0, 3).padEnd(3, '0')) : 0;
+}
+
+function weekdayOf(year: number, month: number, day: number): number {
+    const date = new Date(0);
+    date.setUTCFullYear(year, month - 1, day);
+    return date.getUTCDay();
+}
+
+function dateFields(year: number, month: number, day: number): Fields {
+    return { year, month, day, weekday: weekdayOf(year, month, day) };
+}
+
+function instantFields(value: Date, zone: string | undefined): Fields {
+    if (zone === undefined) {
+        return {
+            ...dateFields(value.getFullYear(), value.getMonth() + 1, value.getDate()),
+            hour: value.getHours(),
+            minute: value.getMinutes(),
+            second: value.getSeconds(),
+            millisecond: value.getMilliseconds(),
+        };
+    }
+    const parts = new Intl.DateTimeFormat('en-US', {
+        timeZone: zone,
+        hourCycle: 'h23',
+        year: 'numeric',
+        month: 'numeric',
+        day: 'numeric',
+        hour: 'numeric',
+        minute: 'numeric',
+        second: 'numeric',
+    }).formatToParts(value);
+    const part = (type: Intl.DateTimeFormatPartTypes) => Number(parts.find((p) => p.type === type)?.value);
+    return {
+        ...dateFields(part('year'), part('month'), part('day')),
+        hour: part('hour'),
+        minute: part('minute'),
+        second: part('second'),
+        millisecond: value.getUTCMilliseconds(),
+    };
+}
+
+function localFields(value: string): Fields {
+    const [date, time] = value.includes('T') ? value.split('T') : LOCAL_DATE.test(value) ? [value] : [undefined, value];
+    const fields: Fields = {};
+    if (date !== undefined) {
+        const match = LOCAL_DATE.exec(date);
+        if (!match) throw new RangeError(`invalid local date: ${JSON.stringify(value)}`);
+        Object.assign(fields, dateFields(Number(match[1]), Number(match[2]), Number(match[3])));
+    }
+    if (time !== undefined) {
+        const match = LOCAL_TIME.exec(time);
+        if (!match) throw new RangeError(`invalid local time: ${JSON.stringify(value)}`);
+        fields.hour = Number(match[1]);
+        fields.minute = Number(match[2]);
+        fields.second = Number(match[3] ?? 0);
+        fields.millisecond = millis(match[4]);
+    }
+    return fields;
+}
+
+function field(fields: Fields, name: keyof Fields, token: string): number {
+    const value = fields[name];
+    if (value === undefined) throw new RangeError(`\`${token}\` needs a value with a ${name}`);
+    return value;
+}
+
+function formatField(fields: Fields, letter: string, width: number, locale: string | undefined): string {
+    const token = letter.repeat(width);
+    const pad = (value: number, digits = width) => String(value).padStart(digits, '0');
+    switch (letter) {
+        case 'y': {
+            const year = field(fields, 'year', token);
+            return width === 2 ? pad(((year % 100) + 100) % 100) : pad(year);
+        }
+        case 'M': {
+            const month = field(fields, 'month', token);
+            if (width <= 2) return pad(month);
+            return new Intl.DateTimeFormat(locale, { month: width === 3 ? 'short' : 'long', timeZone: 'UTC' }).format(
+                Date.UTC(2000, month - 1, 1),
+            );
+        }
+        case 'd':
+            return pad(field(fields, 'day', token));
+        case 'E': {
+            // 2000-01-02 was a Sunday, weekday 0
+            const weekday = field(fields, 'weekday', token);
+            return new Intl.DateTimeFormat(locale, { weekday: width >= 4 ? 'long' : 'short', timeZone: 'UTC' }).format(
+                Date.UTC(2000, 0, 2 + weekday),
+            );
+        }
+        case 'H':
+            return pad(field(fields, 'hour', token));
+        case 'h':
+            return pad(((field(fields, 'hour', token) + 11) % 12) + 1);
+        case 'm':
+            return pad(field(fields, 'minute', token));
+        case 's':
+            return pad(field(fields, 'second', token));
+        case 'S':
+            return pad(field(fields, 'millisecond', token), 3);
+        default: {
+            const hour = field(fields, 'hour', token);
+            const parts = new Intl.DateTimeFormat(locale, { hour: 'numeric', hour12: true, timeZone: 'UTC' }).formatToParts(
+                Date.UTC(2000, 0, 1, hour),
+            );
+            return parts.find((p) => p.type === 'dayPeriod')?.value ?? (hour < 12 ? 'AM' : 'PM');
+        }
+    }
+}
+
+const DURATION_UNITS: [string, number][] = [
+    ['day', 86_400_000],
+    ['hour', 3_600_000],
+    ['minute', 60_000],
+    ['second', 1000],
+    ['millisecond', 1],
+];
+
+/** Number of fraction digits of a Decimal's text */
+function fractionDigits(value: string): number {
+    const point = value.indexOf('.');
+    return point < 0 ? 0 : value.length - point - 1;
+}
+
+/** Fixed number of fraction digits, or none to keep the defaults */
+function digits(count: number | undefined): Intl.NumberFormatOptions {
+    return count === undefined ? {} : { minimumFractionDigits: count, maximumFractionDigits: count };
+}
+
+/**
+ * Format a number or Decimal.
+ *
+ * Intl formats the text of a Decimal exactly (ES2023), so an amount never
+ * goes through a float.
+ */
+function formatNumeric(value: number | string, options: Intl.NumberFormatOptions, locale: string | undefined): string {
+    return new Intl.NumberFormat(locale ?? Builtins.locale, options).format(value as number);
+}
+
+/**
+ * Built-in functions called by generated code.
+ */
+export const Builtins = {
+    /** Locale of names, `humanize` and numbers without a locale argument; the host's default when undefined */
+    locale: undefined as string | undefined,
+
+    /**
+     * Format a date or time with a pattern like `yyyy-MM-dd HH:mm`.
+     *
+     * @param value An Instant (Date) or an ISO LocalDate, LocalTime or LocalDateTime
+     * @param pattern Field letters `y M d E H h m s S a`; `MMM` and longer are month names
+     * @param zone Time zone an Instant is shown in; the host's when left out
+     */
+    format(value: Date | string, pattern: string, zone?: string): string {
+        const fields = value instanceof Date ? instantFields(value, zone) : localFields(value);
+        return parsePattern(pattern)
+            .map((part) => (typeof part === 'string' ? part : formatField(fields, part.letter, part.width, Builtins.locale)))
+            .join('');
+    },
+
+    /**
+     * Parse an ISO 8601 date-time with an offset (`2024-03-15T10:30:00Z`) or a
+     * date (`2024-03-15`, midnight UTC); null if the text is not one.
+     */
+    parseInstant(text: string): Date | null {
+        const match = INSTANT.exec(text);
+        if (!match) return null;
+        const [, y, mo, d, h = '00', mi = '00', s = '00', fraction, offset = 'Z'] = match;
+        const [year, month, day, hour, minute, second] = [y, mo, d, h, mi, s].map(Number);
+        const date = new Date(0);
+        date.setUTCFullYear(year, month - 1, day);
+        if (date.getUTCMonth() !== month - 1 || date.getUTCDate() !== day) return null;
+        if (hour > 23 || minute > 59 || second > 59) return null;
+        const offsetMinutes =
+            offset.toUpperCase() === 'Z'
+                ? 0
+                : (offset[0] === '-' ? -1 : 1) * (Number(offset.slice(1, 3)) * 60 + Number(offset.slice(4, 6)));
+        date.setUTCHours(hour, minute - offsetMinutes, second, millis(fraction));
+        return date;
+    },
+
+    /** Describe a duration in milliseconds in its largest whole unit, like `3 hours` */
+    humanize(duration: number): string {
+        const [unit, size] = DURATION_UNITS.find(([, size]) => Math.abs(duration) >= size) ?? ['second', 1000];
+        return new Intl.NumberFormat(Builtins.locale, { style: 'unit', unit, unitDisplay: 'long' }).format(
+            Math.trunc(duration / size),
+        );
+    },
+
+    /**
+     * Format a number with the grouping and decimal separator of a locale.
+     *
+     * @param value A number or a Decimal's text
+     * @param decimals Fraction digits; a Decimal keeps its own by default, a number up to three
+     * @param locale BCP 47 language tag; `Builtins.locale` when left out
+     */
+    formatNumber(value: number | string, decimals?: number, locale?: string): string {
+        const count = decimals ?? (typeof value === 'string' ? fractionDigits(value) : undefined);
+        return formatNumeric(value, digits(count), locale);
+    },
+
+    /**
+     * Format an amount of money with the currency's symbol and fraction digits.
+     *
+     * @param amount A number or a Decimal's text
+     * @param currency ISO 4217 code, e.g. `EUR`
+     * @param locale BCP 47 language tag; `Builtins.locale` when left out
+     */
+    formatCurrency(amount: number | string, currency: string, locale?: string): string {
+        return formatNumeric(amount, { style: 'currency', currency }, locale);
+    },
+
+    /**
+     * Format a ratio as a percentage, e.g. 0.25 as `25%`.
+     *
+     * @param ratio A number or a Decimal's text
+     * @param decimals Fraction digits of the percentage; none by default
+     * @param locale BCP 47 language tag; `Builtins.locale` when left out
+     */
+    formatPercent(ratio: number | string, decimals?: number, locale?: string): string {
+        return formatNumeric(ratio, { style: 'percent', ...digits(decimals ?? 0) }, locale);
+    },
+
+    /**
+     * Keep a URL that is relative or has a safe scheme (`http`, `https`,
+     * `mailto`, `tel`, a `data:` image); `about:blank` otherwise. Tabs and
+     * line breaks are removed first, as browsers do.
+     */
+    url(text: string): string {
+        const url = text.replace(/^[\u0000-\u0020]+|[\u0000-\u0020]+$/g, '').replace(/[\t\n\r]/g, '');
+        const scheme = /^([a-zA-Z0-9+.-]+):/.exec(url)?.[1].toLowerCase();
+        if (scheme === undefined) return url;
+        const dataImage = scheme === 'data' && url.slice(5).toLowerCase().startsWith('image/');
+        return SAFE_URL_SCHEMES.includes(scheme) || dataImage ? url : 'about:blank';
+    },
+
+    /** Pass a string to a URL parameter as is, marking its interpolations as trusted */
+    raw(text: string): string {
+        return text;
+    },
+};
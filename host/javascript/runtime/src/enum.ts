@@ -0,0 +1,138 @@
+// Frel Enum Utilities
+//
+// Generated enums map each variant name to its ordinal, so `Status.Draft` is
+// the value stored in fields and passed to the runtime. A variant carrying
+// data maps to its constructor instead: `Fetch.Failed(404)` is the tagged
+// value `{ tag, values: [404] }`, `tag` being the variant's ordinal.
+// defineEnum adds the helpers UI code needs to list, render and parse the
+// values as non-enumerable properties, leaving Object.keys(Status) the
+// variant names.
+
+/**
+ * Thrown by `parse` for text that is not a variant name.
+ */
+export class EnumParseError extends Error {
+    constructor(
+        /** Qualified enum name, e.g. `app.Status` */
+        public readonly enumName: string,
+        /** The text that failed to parse */
+        public readonly input: string,
+        /** Variant names the text could have been */
+        public readonly variants: readonly string[],
+    ) {
+        super(`invalid ${enumName}: ${JSON.stringify(input)}, expected one of ${variants.join(', ')}`);
+        this.name = 'EnumParseError';
+    }
+}
+
+/**
+ * Value of a variant carrying data.
+ */
+export interface TaggedValue {
+    /** Ordinal of the variant */
+    readonly tag: number;
+    /** The data, in the order of the variant's payload types */
+    readonly values: readonly unknown[];
+}
+
+/** Value of an enum: a variant's ordinal, or a tagged value */
+export type EnumValue = number | TaggedValue;
+
+/** Ordinal of the variant of an enum value */
+export function tagOf(value: EnumValue): number {
+    return typeof value === 'number' ? value : value.tag;
+}
+
+/**
+ * Helpers of a generated enum whose variants are `V`.
+ */
+export interface EnumUtils<V extends string> {
+    /** Qualified enum name, e.g. `app.Status` */
+    readonly enumName: string;
+    /** Variant names in declaration (ordinal) order */
+    readonly variants: readonly V[];
+    /** Ordinals of the variants, `[0, 1, ...]` */
+    readonly values: readonly number[];
+    /** Name of the variant of the value; throws for an unknown ordinal */
+    nameOf(value: EnumValue): V;
+    /** Display name of the variant: its `.. label { }` or else its name */
+    label(value: EnumValue): string;
+    /** Ordinal of the variant with the name; throws EnumParseError otherwise */
+    parse(text: string): number;
+    /** Ordinal of the variant with the name, or null */
+    tryParse(text: string): number | null;
+    /**
+     * Exhaustive switch: call the case of the value's variant. Every variant
+     * needs a case, so adding one to the enum fails type checking where
+     * cases are missing.
+     */
+    match<R>(value: EnumValue, cases: { readonly [K in V]: (value: EnumValue) => R }): R;
+}
+
+/**
+ * A generated enum: variant ordinals by name, the constructors of the
+ * variants `D` carrying data, plus EnumUtils
+ */
+export type EnumType<V extends string, D extends V = never> = Readonly<Record<Exclude<V, D>, number>> &
+    Readonly<Record<D, (...values: unknown[]) => TaggedValue>> &
+    EnumUtils<V>;
+
+/**
+ * Build a generated enum.
+ *
+ * @param enumName Qualified enum name
+ * @param variants Variant names in declaration order
+ * @param labels Display names of the variants that override their names
+ * @param arities Number of values of the variants carrying data
+ */
+export function defineEnum<V extends string, D extends V = never>(
+    enumName: string,
+    variants: readonly V[],
+    labels: Readonly<Partial<Record<V, string>>> = {},
+    arities: Readonly<Partial<Record<D, number>>> = {},
+): EnumType<V, D> {
+    const ordinals = new Map<string, number>(variants.map((variant, i) => [variant, i]));
+    const nameOf = (value: EnumValue): V => {
+        const variant = variants[tagOf(value)];
+        if (variant === undefined) throw new RangeError(`${enumName} has no variant with ordinal ${tagOf(value)}`);
+        return variant;
+    };
+    const tryParse = (text: string): number | null => ordinals.get(text) ?? null;
+
+    const utils: EnumUtils<V> = {
+        enumName,
+        variants: Object.freeze([...variants]),
+        values: Object.freeze(variants.map((_, i) => i)),
+        nameOf,
+        label: (value) => {
+            const variant = nameOf(value);
+            return labels[variant] ?? variant;
+        },
+        parse: (text) => {
+            const value = tryParse(text);
+            if (value === null) throw new EnumParseError(enumName, text, variants);
+            return value;
+        },
+        tryParse,
+        match: (value, cases) => cases[nameOf(value)](value),
+    };
+
+    const result: Record<string, unknown> = {};
+    variants.forEach((variant, i) => {
+        const arity: number | undefined = (arities as Partial<Record<string, number>>)[variant];
+        result[variant] =
+            arity === undefined
+                ? i
+                : (...values: unknown[]): TaggedValue => {
+                      if (values.length !== arity) {
+                          throw new RangeError(`${enumName}.${variant} takes ${arity} values, found ${values.length}`);
+                      }
+                      return Object.freeze({ tag: i, values: Object.freeze(values) });
+                  };
+    });
+    for (const [key, value] of Object.entries(utils)) {
+        // a variant named like a helper keeps its ordinal or constructor
+        if (!Object.hasOwn(result, key)) Object.defineProperty(result, key, { value, enumerable: false });
+    }
+    return Object.freeze(result) as EnumType<V, D>;
+}
@@ -16,9 +16,19 @@ export type {
     Callback,
     DatumData,
     ClosureData,
+    BackendInstance,
+    BackendClass,
+    BackendLifetime,
+    CommandPolicy,
     SubscriptionData,
     BlueprintMetadata,
     CallSiteMetadata,
+    VirtualizeOptions,
+    ItemWindow,
+    FocusGroup,
+    FocusOptions,
+    KeyInput,
+    PortalHost,
     RuntimeSnapshot,
     DatumSnapshotData,
     ClosureSnapshotData,
@@ -64,3 +74,30 @@ export type {
     TraceDiff,
     RegisterFunction,
 } from './testing.js';
+
+// Contract transports
+export { FetchTransport, WebSocketTransport, ContractError } from './transport.js';
+export type {
+    HttpMethod,
+    ContractMethod,
+    ContractTransport,
+    FetchTransportOptions,
+    WebSocketTransportOptions,
+} from './transport.js';
+
+// Scheme codecs
+export { defineScheme, SchemeTypes, SchemeError } from './scheme.js';
+export type {
+    FieldError,
+    FieldType,
+    Rule,
+    SchemeFieldDescriptor,
+    SchemeCodec,
+} from './scheme.js';
+
+// Enum utilities
+export { defineEnum, tagOf, EnumParseError } from './enum.js';
+export type { EnumType, EnumUtils, EnumValue, TaggedValue } from './enum.js';
+
+// Built-in functions
+export { Builtins } from './builtins.js';
@@ -9,12 +9,20 @@ import type {
     SubscriptionIdentity,
     FunctionIdentity,
     DatumData,
+    BackendClass,
+    BackendInstance,
     ClosureData,
+    CommandPolicy,
+    ItemWindow,
+    KeyInput,
+    PortalHost,
     SubscriptionData,
+    VirtualizeOptions,
     Selector,
     Callback,
     Availability,
     BlueprintMetadata,
+    CallSiteMetadata,
     RuntimeSnapshot,
     DatumSnapshotData,
     ClosureSnapshotData,
@@ -22,11 +30,48 @@ import type {
 import { Tracer, selectorToString } from './tracer.js';
 
 const GEN_LIMIT = 1000;
+const DEFAULT_OVERSCAN = 3;
+
+/** A command invocation in flight */
+interface InFlightCommand {
+    controller: AbortController;
+    done: Promise<unknown>;
+}
+
+/** An error boundary, keyed by the closure holding its body */
+interface ErrorBoundary {
+    /** Renders the fallback in place of the body, with the error message */
+    fallback: (message: string) => void;
+    /** Set once the body failed; later failures go to the next boundary up */
+    failed: boolean;
+}
+
+/** Values of fields before a group of changes */
+type UndoStep = Map<string, unknown>;
+
+/** Undo history of a backend instance */
+interface UndoHistory {
+    fields: Set<string>;
+    undo: UndoStep[];
+    redo: UndoStep[];
+    /** Step collecting the changes made until the next drain */
+    open: UndoStep | null;
+    /** Set while undo or redo restores values, which are not recorded */
+    replaying: boolean;
+}
+
+/** Last result of a derived selector and the input values it was computed from */
+interface SelectorCache {
+    inputs: unknown[];
+    value: unknown;
+}
 
 /** Options for Runtime constructor */
 export interface RuntimeOptions {
     /** Optional tracer for debugging and testing */
     tracer?: Tracer;
+    /** Platform hooks mounting fragments rendered with `portal` */
+    portals?: PortalHost;
 }
 
 export class Runtime {
@@ -39,6 +84,33 @@ export class Runtime {
     // Static lookup (keyed by qualified name)
     private metadata: Map<string, BlueprintMetadata> = new Map();
 
+    // Latest command invocation in flight, by closure and command name
+    private commands: Map<ClosureIdentity, Map<string, InFlightCommand>> = new Map();
+
+    // Number of command invocations started and not settled, by closure and command name
+    private pending: Map<ClosureIdentity, Map<string, number>> = new Map();
+
+    // Undo histories of backend instances with undoable fields
+    private undo_histories: Map<ClosureIdentity, UndoHistory> = new Map();
+
+    // Cached selector results by the closure owning their inputs and selector name
+    private selectors: Map<ClosureIdentity, Map<string, SelectorCache>> = new Map();
+
+    // Keyboard shortcut handlers by closure and canonical chord
+    private shortcuts: Map<ClosureIdentity, Map<string, () => void>> = new Map();
+
+    // Names of the context values each closure provides
+    private provided: Map<ClosureIdentity, Set<string>> = new Map();
+
+    // Error boundaries by the closure holding their body
+    private boundaries: Map<ClosureIdentity, ErrorBoundary> = new Map();
+
+    // Overlay containers of the closures rendered with `portal`
+    private portals: Map<ClosureIdentity, string> = new Map();
+
+    // Screen backend instances keyed by root closure, singletons under null
+    private shared_backends: Map<ClosureIdentity | null, Map<BackendClass, BackendInstance>> = new Map();
+
     // Identity counters
     // datum: even (low bit = 0), closures: odd (low bit = 1)
     private next_datum_id: DatumIdentity = 0;
@@ -54,8 +126,12 @@ export class Runtime {
     // Tracing
     private tracer?: Tracer;
 
+    // Platform hooks
+    private portal_host?: PortalHost;
+
     constructor(options?: RuntimeOptions) {
         this.tracer = options?.tracer;
+        this.portal_host = options?.portals;
     }
 
     // ========================================================================
@@ -169,11 +245,14 @@ export class Runtime {
         const closure: ClosureData = {
             closure_id: id,
             blueprint: blueprint_name,
+            fragment_id: null,
             parent_closure_id,
             child_closure_ids: [],
             subscriptions_to_this: new Set(),
             subscriptions_by_this: new Set(),
             owned_datum: new Set(),
+            shared_fields: {},
+            sharers: new Set(),
             fields: {},
             set_generation: this.current_generation,
         };
@@ -221,17 +300,51 @@ export class Runtime {
             this.subscriptions.delete(sub_id);
         }
 
-        // 3. Destroy all owned_datum
+        // 3. Stop sharing backend fields in both directions
+        for (const owner_id of new Set(Object.values(closure.shared_fields))) {
+            this.closures.get(owner_id)?.sharers.delete(id);
+        }
+        for (const sharer_id of closure.sharers) {
+            const sharer = this.closures.get(sharer_id);
+            if (!sharer) continue;
+            for (const [field, owner_id] of Object.entries(sharer.shared_fields)) {
+                if (owner_id === id) delete sharer.shared_fields[field];
+            }
+        }
+
+        // 4. Destroy all owned_datum
         for (const datum_id of closure.owned_datum) {
             this.destroy_datum(datum_id);
         }
 
-        // 4. Recursively destroy all children
+        // 5. Recursively destroy all children
         for (const child_id of [...closure.child_closure_ids]) {
             this.destroy_closure(child_id);
         }
 
-        // 5. Remove from parent's child list
+        // 6. Abort commands in flight and destroy screen backends of a root closure
+        for (const command of this.commands.get(id)?.values() ?? []) {
+            command.controller.abort();
+        }
+        this.commands.delete(id);
+        this.pending.delete(id);
+        this.undo_histories.delete(id);
+        this.selectors.delete(id);
+        this.shortcuts.delete(id);
+        this.provided.delete(id);
+        this.boundaries.delete(id);
+        const container = this.portals.get(id);
+        if (container !== undefined) {
+            this.portals.delete(id);
+            this.portal_host?.unmount(id, container);
+            this.tracer?.trace('portal', 'unmount', { id, container });
+        }
+        for (const instance of this.shared_backends.get(id)?.values() ?? []) {
+            this.destroy_closure(instance.closure_id);
+        }
+        this.shared_backends.delete(id);
+
+        // 7. Remove from parent's child list
         if (closure.parent_closure_id !== null) {
             const parent = this.closures.get(closure.parent_closure_id);
             if (parent) {
@@ -242,7 +355,7 @@ export class Runtime {
             }
         }
 
-        // 6. Remove the closure
+        // 8. Remove the closure
         this.closures.delete(id);
 
         this.tracer?.trace('closure', 'destroy', { id });
@@ -258,6 +371,10 @@ export class Runtime {
             return datum?.fields?.[field];
         } else {
             const closure = this.closures.get(id);
+            const owner_id = closure?.shared_fields[field];
+            if (owner_id !== undefined) {
+                return this.get(owner_id, field);
+            }
             return closure?.fields[field];
         }
     }
@@ -266,7 +383,8 @@ export class Runtime {
         if (this.is_datum(id)) {
             this.set_datum_field(id, field, value);
         } else {
-            this.set_closure_field(id, field, value);
+            const owner_id = this.closures.get(id)?.shared_fields[field];
+            this.set_closure_field(owner_id ?? id, field, value);
         }
     }
 
@@ -306,6 +424,7 @@ export class Runtime {
         const old_value = closure.fields[field];
         if (old_value === value) return; // No change
 
+        this.record_undo(id, field, old_value);
         closure.fields[field] = value;
         closure.set_generation = this.current_generation;
 
@@ -319,6 +438,279 @@ export class Runtime {
 
         // Notify subscribers
         this.notify_subscribers(id, closure.subscriptions_to_this, 'structural', field);
+
+        // Notify subscribers of closures sharing this field through a backend
+        for (const sharer_id of closure.sharers) {
+            const sharer = this.closures.get(sharer_id);
+            if (sharer && sharer.shared_fields[field] === id) {
+                this.notify_subscribers(sharer_id, sharer.subscriptions_to_this, 'structural', field);
+            }
+        }
+    }
+
+    /**
+     * Inject a backend instance into a closure's backend slot.
+     *
+     * The closure's fields named by the backend are redirected to the closure
+     * owning the instance, so every blueprint given the same instance reads
+     * and writes the same state.
+     */
+    inject_backend(id: ClosureIdentity, slot: string, instance: BackendInstance, fields: string[]): void {
+        const closure = this.closures.get(id);
+        if (!closure) return;
+
+        this.set_closure_field(id, slot, instance);
+        if (instance.closure_id === id) return;
+
+        this.tracer?.trace('closure', 'inject_backend', { id, slot, owner: instance.closure_id });
+        this.share_backend(closure, instance, fields);
+    }
+
+    /**
+     * Give a closure an instance of a backend, according to the backend's lifetime.
+     *
+     * Fragment backends get a new instance on the closure itself. Screen and
+     * singleton backends are created once per root closure or once per runtime,
+     * on a closure of their own, and their fields are shared with the closure.
+     * With a null slot the fields are shared without storing the instance.
+     */
+    use_backend(id: ClosureIdentity, slot: string | null, backend: BackendClass): BackendInstance | undefined {
+        const closure = this.closures.get(id);
+        if (!closure) return undefined;
+
+        if (backend.lifetime === 'fragment') {
+            const instance = new backend(this, id);
+            if (slot !== null) this.set_closure_field(id, slot, instance);
+            return instance;
+        }
+
+        const scope = backend.lifetime === 'screen' ? this.root_closure(id) : null;
+        let instances = this.shared_backends.get(scope);
+        if (!instances) {
+            instances = new Map();
+            this.shared_backends.set(scope, instances);
+        }
+
+        let instance = instances.get(backend);
+        if (!instance) {
+            // Not a child of the root: backend closures are not fragments
+            const owner_id = this.create_closure(`backend:${backend.name}`, null);
+            instance = new backend(this, owner_id);
+            instances.set(backend, instance);
+        }
+
+        if (slot !== null) this.set_closure_field(id, slot, instance);
+        this.share_backend(closure, instance, backend.fields);
+        return instance;
+    }
+
+    // ========================================================================
+    // Derived Selectors
+    // ========================================================================
+
+    /**
+     * Value of an expression depending only on backend fields.
+     *
+     * The result is cached on the closure owning the inputs and recomputed
+     * only when an input value changed (compared with Object.is), so every
+     * fragment sharing the backend instance, such as the items of a large
+     * list, reuses a single computation.
+     */
+    select(id: ClosureIdentity, selector: string, inputs: string[], compute: () => unknown): unknown {
+        const values = inputs.map(field => this.get(id, field));
+        const owner_id = this.closures.get(id)?.shared_fields[inputs[0]] ?? id;
+
+        let cache = this.selectors.get(owner_id);
+        if (!cache) {
+            cache = new Map();
+            this.selectors.set(owner_id, cache);
+        }
+
+        const cached = cache.get(selector);
+        if (cached && cached.inputs.every((value, i) => Object.is(value, values[i]))) {
+            return cached.value;
+        }
+
+        const value = compute();
+        cache.set(selector, { inputs: values, value });
+        this.tracer?.trace('selector', 'compute', { id: owner_id, selector });
+        return value;
+    }
+
+    // ========================================================================
+    // Undo History
+    // ========================================================================
+
+    /**
+     * Record changes to the given fields of a backend instance.
+     *
+     * Changes made between two drains form one step; values are copied with
+     * structuredClone, so in-place mutations do not alter the history.
+     */
+    track_undo(id: ClosureIdentity, fields: string[]): void {
+        this.undo_histories.set(id, {
+            fields: new Set(fields),
+            undo: [],
+            redo: [],
+            open: null,
+            replaying: false,
+        });
+    }
+
+    /** Revert the last step of a backend instance's undo history */
+    undo(id: ClosureIdentity): void {
+        this.replay(id, 'undo', 'redo');
+    }
+
+    /** Reapply the last undone step of a backend instance's undo history */
+    redo(id: ClosureIdentity): void {
+        this.replay(id, 'redo', 'undo');
+    }
+
+    private record_undo(id: ClosureIdentity, field: string, old_value: unknown): void {
+        const history = this.undo_histories.get(id);
+        if (!history || history.replaying || !history.fields.has(field)) return;
+
+        if (!history.open) {
+            history.open = new Map();
+            history.undo.push(history.open);
+            history.redo.length = 0;
+        }
+        if (!history.open.has(field)) {
+            history.open.set(field, structuredClone(old_value));
+        }
+    }
+
+    private replay(id: ClosureIdentity, from: 'undo' | 'redo', to: 'undo' | 'redo'): void {
+        const history = this.undo_histories.get(id);
+        const closure = this.closures.get(id);
+        if (!history || !closure) return;
+
+        history.open = null;
+        const step = history[from].pop();
+        if (!step) return;
+
+        // The current values become the step that reverts this one
+        const inverse: UndoStep = new Map();
+        history.replaying = true;
+        try {
+            for (const [field, value] of step) {
+                inverse.set(field, structuredClone(closure.fields[field]));
+                this.set_closure_field(id, field, structuredClone(value));
+            }
+        } finally {
+            history.replaying = false;
+        }
+        history[to].push(inverse);
+
+        this.tracer?.trace('field', from, { id, fields: [...step.keys()] });
+    }
+
+    /**
+     * Run a command invocation under a concurrency policy.
+     *
+     * - latest: abort the signal of the invocation in flight and start the new
+     *   one; the aborted invocation resolves to undefined
+     * - queue: start the new invocation when the ones in flight have finished
+     * - drop: ignore the new invocation while another one is in flight
+     * - null: run invocations independently of each other
+     *
+     * The field `<name>.loading` of the closure is true while an invocation
+     * has not settled, for the `loading` placeholders waiting for the command.
+     */
+    run_command(
+        closure_id: ClosureIdentity,
+        name: string,
+        policy: CommandPolicy | null,
+        run: (signal: AbortSignal) => Promise<unknown>
+    ): Promise<unknown> {
+        let commands = this.commands.get(closure_id);
+        if (!commands) {
+            commands = new Map();
+            this.commands.set(closure_id, commands);
+        }
+        const in_flight = commands.get(name);
+
+        if (in_flight && policy === 'drop') {
+            this.tracer?.trace('command', 'drop', { id: closure_id, command: name });
+            return Promise.resolve(undefined);
+        }
+        if (in_flight && policy === 'latest') {
+            in_flight.controller.abort();
+            this.tracer?.trace('command', 'supersede', { id: closure_id, command: name });
+        }
+
+        this.set_pending(closure_id, name, 1);
+
+        const controller = new AbortController();
+        const previous = policy === 'queue' && in_flight ? in_flight.done : Promise.resolve();
+        const done = previous
+            .catch(() => undefined)
+            .then(() => {
+                this.tracer?.trace('command', 'start', { id: closure_id, command: name, policy });
+                return run(controller.signal);
+            });
+
+        const command: InFlightCommand = { controller, done };
+        commands.set(name, command);
+
+        const settle = () => {
+            if (commands.get(name) === command) commands.delete(name);
+            this.tracer?.trace('command', 'end', { id: closure_id, command: name, aborted: controller.signal.aborted });
+            this.set_pending(closure_id, name, -1);
+            this.drain_notifications();
+        };
+        return done.then(
+            (result) => {
+                settle();
+                return controller.signal.aborted ? undefined : result;
+            },
+            (error) => {
+                settle();
+                if (!controller.signal.aborted) throw error;
+                return undefined;
+            }
+        );
+    }
+
+    /** Count an invocation of a command starting (1) or settling (-1), updating its `.loading` field */
+    private set_pending(closure_id: ClosureIdentity, name: string, delta: number): void {
+        if (!this.closures.has(closure_id)) return;
+        let pending = this.pending.get(closure_id);
+        if (!pending) {
+            pending = new Map();
+            this.pending.set(closure_id, pending);
+        }
+        const count = (pending.get(name) ?? 0) + delta;
+        if (count > 0) {
+            pending.set(name, count);
+        } else {
+            pending.delete(name);
+        }
+        this.set(closure_id, `${name}.loading`, count > 0);
+    }
+
+    /** Redirect a closure's backend fields to the closure owning the instance */
+    private share_backend(closure: ClosureData, instance: BackendInstance, fields: string[]): void {
+        for (const field of fields) {
+            closure.shared_fields[field] = instance.closure_id;
+        }
+        this.closures.get(instance.closure_id)?.sharers.add(closure.closure_id);
+
+        // Derived fields were computed from the closure's own instance
+        for (const field of fields) {
+            this.notify_subscribers(closure.closure_id, closure.subscriptions_to_this, 'structural', field);
+        }
+    }
+
+    /** The root closure (screen) a closure belongs to */
+    private root_closure(id: ClosureIdentity): ClosureIdentity {
+        let closure = this.closures.get(id);
+        while (closure && closure.parent_closure_id !== null) {
+            id = closure.parent_closure_id;
+            closure = this.closures.get(id);
+        }
+        return id;
     }
 
     private propagate_carried(owner_id: ClosureIdentity): void {
@@ -532,10 +924,12 @@ export class Runtime {
     instantiate(
         blueprint_name: string,
         parent_closure_id: ClosureIdentity | null,
-        params: Record<string, unknown>
+        params: Record<string, unknown>,
+        fragment_id: string | null = null
     ): ClosureIdentity {
         const closure_id = this.create_closure(blueprint_name, parent_closure_id);
         const closure = this.closures.get(closure_id)!;
+        closure.fragment_id = fragment_id;
 
         // Set parameters
         for (const [key, value] of Object.entries(params)) {
@@ -555,15 +949,290 @@ export class Runtime {
             for (const idx of meta.top_children) {
                 const call_site = meta.call_sites[idx];
                 if (call_site) {
-                    const child_id = this.instantiate(call_site.blueprint, closure_id, {});
-                    call_site.binding(this, closure_id, child_id);
+                    this.instantiate_call_site(closure_id, closure_id, call_site);
                 }
             }
+
+            // Error boundaries create their children themselves
+            for (const boundary of meta.boundaries ?? []) {
+                boundary(this, closure_id);
+            }
+
+            // Loading placeholders create theirs while a command is in flight
+            for (const loading of meta.loading ?? []) {
+                loading(this, closure_id);
+            }
         }
 
         return closure_id;
     }
 
+    /**
+     * Create the fragment of a call site of `parent_id` as a child of `owner_id`.
+     *
+     * The owner is the parent itself, or a closure between them grouping
+     * children, such as the body of an error boundary.
+     */
+    instantiate_call_site(
+        parent_id: ClosureIdentity,
+        owner_id: ClosureIdentity,
+        call_site: CallSiteMetadata
+    ): ClosureIdentity {
+        const child_id = this.instantiate(call_site.blueprint, owner_id, {}, call_site.id ?? null);
+        call_site.binding(this, parent_id, child_id);
+        return child_id;
+    }
+
+    /**
+     * Items of a virtualized repeat to materialize for a scroll position.
+     *
+     * Only the items intersecting the viewport, plus `overscan` items on each
+     * side, are instantiated by the adapter; the space of the others is kept
+     * as padding so the scroll extent stays the same. `measured` holds the
+     * heights of items rendered so far when sizing by estimate.
+     */
+    item_window(
+        options: VirtualizeOptions,
+        count: number,
+        scroll_top: number,
+        viewport_height: number,
+        measured?: Map<number, number>
+    ): ItemWindow {
+        const overscan = options.overscan ?? DEFAULT_OVERSCAN;
+        const fallback = options.item_height ?? options.estimate ?? 0;
+        const height = (index: number) =>
+            options.item_height ?? measured?.get(index) ?? fallback;
+
+        // Find the visible items, walking the offsets unless all heights are the same
+        const uniform = options.item_height !== undefined || !measured?.size;
+        let first: number;
+        let last: number;
+        if (uniform) {
+            const size = Math.max(fallback, 1);
+            first = Math.floor(scroll_top / size);
+            last = Math.ceil((scroll_top + viewport_height) / size);
+        } else {
+            let offset = 0;
+            first = count;
+            last = count;
+            for (let i = 0; i < count; i++) {
+                const next = offset + height(i);
+                if (first === count && next > scroll_top) first = i;
+                if (offset >= scroll_top + viewport_height) {
+                    last = i;
+                    break;
+                }
+                offset = next;
+            }
+        }
+
+        const start = Math.max(0, Math.min(count, first - overscan));
+        const end = Math.max(start, Math.min(count, last + overscan));
+
+        if (uniform) {
+            return { start, end, before: start * fallback, after: (count - end) * fallback };
+        }
+        let before = 0;
+        let after = 0;
+        for (let i = 0; i < start; i++) before += height(i);
+        for (let i = end; i < count; i++) after += height(i);
+        return { start, end, before, after };
+    }
+
+    // ========================================================================
+    // Context Values
+    // ========================================================================
+
+    /**
+     * Provide a context value to the closures below `id`.
+     *
+     * The value is kept in a field of the closure that no Frel name can
+     * refer to; consumers subscribe to it. Called again when the value changes.
+     */
+    provide(id: ClosureIdentity, name: string, value: unknown): void {
+        let names = this.provided.get(id);
+        if (!names) {
+            names = new Set();
+            this.provided.set(id, names);
+        }
+        names.add(name);
+        this.set(id, context_field(name), value);
+    }
+
+    /**
+     * Copy the context value `name` of the nearest closure above `id` providing
+     * it into the field `name` of `id`, and keep the field up to date.
+     *
+     * A closure providing the name it consumes reads the value from above.
+     * Without a provider the field stays undefined.
+     */
+    consume(id: ClosureIdentity, name: string): void {
+        const field = context_field(name);
+        let provider = this.closures.get(id)?.parent_closure_id ?? null;
+        while (provider !== null && !this.provided.get(provider)?.has(name)) {
+            provider = this.closures.get(provider)?.parent_closure_id ?? null;
+        }
+        if (provider === null) {
+            this.tracer?.trace('context', 'missing', { id, name });
+            return;
+        }
+
+        this.set(id, name, this.get(provider, field));
+        this.subscribe(provider, id, { type: 'Key', key: field }, (runtime, subscription) => {
+            runtime.set(subscription.target_id, name, runtime.get(subscription.source_id, field));
+        });
+    }
+
+    // ========================================================================
+    // Error Boundaries
+    // ========================================================================
+
+    /**
+     * Open an error boundary below `parent_id` and return the closure its body
+     * is created in.
+     *
+     * `fallback` creates the fallback's children in the same closure once the
+     * body failed.
+     */
+    open_boundary(parent_id: ClosureIdentity, fallback: (message: string) => void): ClosureIdentity {
+        const id = this.create_closure('on_error', parent_id);
+        this.boundaries.set(id, { fallback, failed: false });
+        return id;
+    }
+
+    /**
+     * Report a failure in the subtree of `id` to the nearest error boundary
+     * above it that has not failed yet.
+     *
+     * The boundary's body is destroyed and its fallback created instead. A
+     * failure of the fallback goes to the next boundary up; without one the
+     * error is thrown again.
+     */
+    fail(id: ClosureIdentity, error: unknown): void {
+        let boundary_id: ClosureIdentity | null = id;
+        while (boundary_id !== null && (this.boundaries.get(boundary_id)?.failed ?? true)) {
+            boundary_id = this.closures.get(boundary_id)?.parent_closure_id ?? null;
+        }
+        if (boundary_id === null) throw error;
+
+        const boundary = this.boundaries.get(boundary_id)!;
+        boundary.failed = true;
+        const message = error instanceof Error ? error.message : String(error);
+        this.tracer?.trace('boundary', 'fail', { id: boundary_id, error: message });
+
+        for (const child_id of [...(this.closures.get(boundary_id)?.child_closure_ids ?? [])]) {
+            this.destroy_closure(child_id);
+        }
+        boundary.fallback(message);
+    }
+
+    /**
+     * Report a rejection of `result`, e.g. a failed command, to the nearest
+     * error boundary above `id`; the returned promise resolves to undefined then.
+     */
+    guard(id: ClosureIdentity, result: Promise<unknown>): Promise<unknown> {
+        return result.catch((error) => {
+            this.fail(id, error);
+            return undefined;
+        });
+    }
+
+    // ========================================================================
+    // Loading Placeholders
+    // ========================================================================
+
+    /**
+     * Open a loading placeholder below `parent_id` and return the closure its
+     * body is created in.
+     *
+     * `render` creates the body in that closure while any of the fields
+     * `flags` of `parent_id` is true, e.g. `search.loading`; the body is
+     * destroyed once they are all false again.
+     */
+    open_loading(
+        parent_id: ClosureIdentity,
+        flags: string[],
+        render: (loading_id: ClosureIdentity) => void
+    ): ClosureIdentity {
+        const id = this.create_closure('loading', parent_id);
+        let shown = false;
+        const update = () => {
+            const loading = flags.some((flag) => this.get(parent_id, flag) === true);
+            if (loading === shown) return;
+            shown = loading;
+            this.tracer?.trace('loading', loading ? 'show' : 'hide', { id });
+            if (loading) {
+                render(id);
+            } else {
+                for (const child_id of [...(this.closures.get(id)?.child_closure_ids ?? [])]) {
+                    this.destroy_closure(child_id);
+                }
+            }
+        };
+        this.subscribe(parent_id, id, { type: 'OneOf', keys: flags }, update);
+        update();
+        return id;
+    }
+
+    // ========================================================================
+    // Portals
+    // ========================================================================
+
+    /**
+     * Render the closure `id` in the overlay container `container` instead of
+     * its parent's layout.
+     *
+     * The platform's `PortalHost` moves the node; it is unmounted when the
+     * closure is destroyed.
+     */
+    mount_portal(id: ClosureIdentity, container: string): void {
+        this.portals.set(id, container);
+        this.portal_host?.mount(id, container);
+        this.tracer?.trace('portal', 'mount', { id, container });
+    }
+
+    // ========================================================================
+    // Keyboard Shortcuts
+    // ========================================================================
+
+    /**
+     * Register a keyboard shortcut of a closure.
+     *
+     * `chord` is in the compiler's canonical form: modifiers in the order
+     * `Ctrl+Alt+Shift+Meta`, then the key. The shortcut is dropped with the closure.
+     */
+    add_shortcut(id: ClosureIdentity, chord: string, handler: () => void): void {
+        let shortcuts = this.shortcuts.get(id);
+        if (!shortcuts) {
+            shortcuts = new Map();
+            this.shortcuts.set(id, shortcuts);
+        }
+        shortcuts.set(chord, handler);
+    }
+
+    /**
+     * Run the shortcut matching a key press, if any.
+     *
+     * When several closures declare the chord, the most recently created one
+     * wins. Changes made by the shortcut are notified before returning and form
+     * one undo step. Returns whether a shortcut ran, so adapters can prevent
+     * the platform's default action.
+     */
+    handle_key(input: KeyInput): boolean {
+        const chord = chord_of(input);
+        let match: [ClosureIdentity, () => void] | undefined;
+        for (const [id, shortcuts] of this.shortcuts) {
+            const handler = shortcuts.get(chord);
+            if (handler && (match === undefined || id > match[0])) match = [id, handler];
+        }
+        if (match === undefined) return false;
+
+        this.tracer?.trace('shortcut', 'run', { id: match[0], chord });
+        match[1]();
+        this.drain_events();
+        return true;
+    }
+
     // ========================================================================
     // Events
     // ========================================================================
@@ -578,6 +1247,11 @@ export class Runtime {
             this.process_event(event);
         }
         this.drain_notifications();
+
+        // Changes made while handling these events form one undo step
+        for (const history of this.undo_histories.values()) {
+            history.open = null;
+        }
     }
 
     private process_event(_event: unknown): void {
@@ -620,7 +1294,13 @@ export class Runtime {
                 const callback = this.functions.get(sub.callback_id);
                 if (callback) {
                     this.tracer?.trace('notification', 'callback', { sub_id, callback_id: sub.callback_id });
-                    callback(this, sub);
+                    // A failing callback is a failure of the closure it updates
+                    try {
+                        callback(this, sub);
+                    } catch (error) {
+                        if (!this.is_closure(sub.target_id)) throw error;
+                        this.fail(sub.target_id, error);
+                    }
                 }
             }
         }
@@ -712,5 +1392,26 @@ export class Runtime {
 export const Everything: Selector = { type: 'Everything' };
 export const Structural: Selector = { type: 'Structural' };
 export const Carried: Selector = { type: 'Carried' };
+/** Closure field holding a provided context value, out of reach of Frel names */
+function context_field(name: string): string {
+    return `context:${name}`;
+}
+
+/** Key names the compiler writes differently from `KeyboardEvent.key` */
+const KEY_NAMES: Record<string, string> = { ' ': 'Space', Esc: 'Escape', Del: 'Delete' };
+
+/** Canonical chord of a key press, as the compiler writes it */
+function chord_of(input: KeyInput): string {
+    let key = KEY_NAMES[input.key] ?? input.key;
+    if (key.length === 1) key = key.toUpperCase();
+    const modifiers = [
+        input.ctrlKey ? 'Ctrl+' : '',
+        input.altKey ? 'Alt+' : '',
+        input.shiftKey ? 'Shift+' : '',
+        input.metaKey ? 'Meta+' : '',
+    ];
+    return modifiers.join('') + key;
+}
+
 export const Key = (key: string): Selector => ({ type: 'Key', key });
 export const OneOf = (...keys: string[]): Selector => ({ type: 'OneOf', keys });
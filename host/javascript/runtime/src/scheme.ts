@@ -0,0 +1,414 @@
+// Frel Scheme Codecs
+//
+// Generated code describes the stored fields of each scheme with SchemeTypes
+// and validation rules, and defineScheme turns the description into a codec:
+// `fromJson` decodes and validates JSON, `toJson` encodes, `is` is a type
+// guard and `validate` checks the rules of a decoded value.
+//
+// JSON encodings: Instant is an ISO 8601 string decoded to a Date, Decimal a
+// string (numbers are accepted), Uuid a lower-cased string, an enum value the
+// name of its variant, a Set an array and a Map an object.
+
+/** A failed check of a field, as reported by `validate` and `fromJson` */
+export interface FieldError {
+    /** Path of the field, e.g. `address.city` or `tags[2]` */
+    field: string;
+    message: string;
+    /** `type`, `required`, or the instruction of the failed rule, e.g. `min_len` */
+    code: string;
+}
+
+/** JSON that doesn't decode to a valid scheme value */
+export class SchemeError extends Error {
+    constructor(
+        public readonly scheme: string,
+        public readonly errors: FieldError[],
+    ) {
+        super(`invalid ${scheme}: ${errors.map((e) => `${e.field}: ${e.message}`).join('; ')}`);
+        this.name = 'SchemeError';
+    }
+}
+
+/**
+ * Encoding of a value of a field type.
+ */
+export interface FieldType<T = unknown> {
+    /** Type name for error messages */
+    name: string;
+    /** Decode a JSON value, reporting problems to `errors` */
+    decode(json: unknown, path: string, errors: FieldError[]): T | undefined;
+    encode(value: T): unknown;
+    is(value: unknown): value is T;
+    /** Check nested rules of a decoded value (scheme and collection elements) */
+    validate?(value: T, path: string, errors: FieldError[]): void;
+}
+
+/** A validation rule: instruction name and argument, e.g. `['min_len', 3]` */
+export type Rule =
+    | ['min_len' | 'max_len' | 'min' | 'max' | 'precision' | 'min_items' | 'max_items', number]
+    | ['pattern', string]
+    | ['blank', false];
+
+/** A stored field of a scheme */
+export interface SchemeFieldDescriptor {
+    name: string;
+    type: FieldType<any>;
+    rules?: Rule[];
+}
+
+/**
+ * Codec of a scheme, generated as the scheme's export.
+ */
+export interface SchemeCodec<T> extends FieldType<T> {
+    /** Qualified scheme name */
+    scheme: string;
+    fields: SchemeFieldDescriptor[];
+    /** Decode and validate JSON; throws a SchemeError listing every problem */
+    fromJson(json: unknown): T;
+    toJson(value: T): Record<string, unknown>;
+    /** Whether a value has the fields of the scheme with the right types */
+    is(value: unknown): value is T;
+    /** Failed rules of a value, appended to `errors` with paths under `path` */
+    validate(value: T, path?: string, errors?: FieldError[]): FieldError[];
+}
+
+// ========================================================================
+// Field types
+// ========================================================================
+
+function typeError(name: string, json: unknown, path: string, errors: FieldError[]): undefined {
+    const found = json === null ? 'null' : Array.isArray(json) ? 'array' : typeof json;
+    errors.push({ field: path, message: `expected ${name}, found ${found}`, code: 'type' });
+    return undefined;
+}
+
+/** A type whose JSON and host values are the same primitive */
+function primitive<T>(name: string, is: (value: unknown) => value is T): FieldType<T> {
+    return {
+        name,
+        decode: (json, path, errors) => (is(json) ? json : typeError(name, json, path, errors)),
+        encode: (value) => value,
+        is,
+    };
+}
+
+/** A string type whose values match a pattern */
+function text(name: string, pattern: RegExp, normalize: (value: string) => string = (v) => v): FieldType<string> {
+    const is = (value: unknown): value is string => typeof value === 'string' && pattern.test(value);
+    return {
+        name,
+        decode: (json, path, errors) => (is(json) ? normalize(json) : typeError(name, json, path, errors)),
+        encode: (value) => value,
+        is,
+    };
+}
+
+const isString = (value: unknown): value is string => typeof value === 'string';
+const isNumber = (value: unknown): value is number => typeof value === 'number' && Number.isFinite(value);
+const isDate = (value: unknown): value is Date => value instanceof Date && !Number.isNaN(value.getTime());
+
+const DATE = '\\d{4}-\\d{2}-\\d{2}';
+const TIME = '\\d{2}:\\d{2}(:\\d{2}(\\.\\d+)?)?';
+const DECIMAL = /^-?\d+(\.\d+)?$/;
+
+function listType<T>(inner: FieldType<T>): FieldType<T[]> {
+    return {
+        get name() {
+            return `List<${inner.name}>`;
+        },
+        decode: (json, path, errors) => {
+            if (!Array.isArray(json)) return typeError(`List<${inner.name}>`, json, path, errors);
+            return json.map((item, i) => inner.decode(item, `${path}[${i}]`, errors) as T);
+        },
+        encode: (value) => value.map((item) => inner.encode(item)),
+        is: (value): value is T[] => Array.isArray(value) && value.every((item) => inner.is(item)),
+        validate: (value, path, errors) => {
+            value.forEach((item, i) => inner.validate?.(item, `${path}[${i}]`, errors));
+        },
+    };
+}
+
+const url: FieldType<string> = {
+    name: 'Url',
+    decode: (json, path, errors) => (url.is(json) ? json : typeError('Url', json, path, errors)),
+    encode: (value) => value,
+    is: (value): value is string => {
+        if (typeof value !== 'string') return false;
+        try {
+            new URL(value);
+            return true;
+        } catch {
+            return false;
+        }
+    },
+};
+
+const instant: FieldType<Date> = {
+    name: 'Instant',
+    decode: (json, path, errors) => {
+        const date = typeof json === 'string' || isNumber(json) ? new Date(json) : null;
+        return date && isDate(date) ? date : typeError('Instant', json, path, errors);
+    },
+    encode: (value) => value.toISOString(),
+    is: isDate,
+};
+
+const decimal: FieldType<string> = {
+    name: 'Decimal',
+    decode: (json, path, errors) => {
+        const value = isNumber(json) ? String(json) : json;
+        return decimal.is(value) ? value : typeError('Decimal', json, path, errors);
+    },
+    encode: (value) => value,
+    is: (value): value is string => typeof value === 'string' && DECIMAL.test(value),
+};
+
+/**
+ * Field types referred to by generated scheme descriptions.
+ */
+export const SchemeTypes = {
+    any: primitive('any', (_value: unknown): _value is unknown => true),
+    bool: primitive('bool', (value: unknown): value is boolean => typeof value === 'boolean'),
+    float: primitive('number', isNumber),
+    string: primitive('String', isString),
+    uuid: text('Uuid', /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/i, (v) => v.toLowerCase()),
+    local_date: text('LocalDate', new RegExp(`^${DATE}$`)),
+    local_time: text('LocalTime', new RegExp(`^${TIME}$`)),
+    local_date_time: text('LocalDateTime', new RegExp(`^${DATE}T${TIME}$`)),
+    url,
+    instant,
+    decimal,
+
+    /** An integer between `min` and `max` inclusive */
+    int(min: number, max: number): FieldType<number> {
+        const name = 'integer';
+        const is = (value: unknown): value is number =>
+            Number.isInteger(value) && (value as number) >= min && (value as number) <= max;
+        return {
+            name,
+            decode: (json, path, errors) => (is(json) ? json : typeError(name, json, path, errors)),
+            encode: (value) => value,
+            is,
+        };
+    },
+
+    nullable<T>(inner: FieldType<T>): FieldType<T | null> {
+        return {
+            get name() {
+                return `${inner.name}?`;
+            },
+            decode: (json, path, errors) => (json === null || json === undefined ? null : inner.decode(json, path, errors)),
+            encode: (value) => (value === null ? null : inner.encode(value)),
+            is: (value): value is T | null => value === null || inner.is(value),
+            validate: (value, path, errors) => {
+                if (value !== null) inner.validate?.(value, path, errors);
+            },
+        };
+    },
+
+    list: listType,
+
+    set<T>(inner: FieldType<T>): FieldType<Set<T>> {
+        const list = listType(inner);
+        return {
+            get name() {
+                return `Set<${inner.name}>`;
+            },
+            decode: (json, path, errors) => {
+                const items = list.decode(json, path, errors);
+                return items && new Set(items);
+            },
+            encode: (value) => list.encode([...value]),
+            is: (value): value is Set<T> => value instanceof Set && list.is([...value]),
+            validate: (value, path, errors) => list.validate?.([...value], path, errors),
+        };
+    },
+
+    /** A map with string keys, encoded as a JSON object */
+    map<T>(inner: FieldType<T>): FieldType<Record<string, T>> {
+        const isObject = (value: unknown): value is Record<string, unknown> =>
+            typeof value === 'object' && value !== null && !Array.isArray(value);
+        return {
+            get name() {
+                return `Map<String, ${inner.name}>`;
+            },
+            decode: (json, path, errors) => {
+                if (!isObject(json)) return typeError(`Map<String, ${inner.name}>`, json, path, errors);
+                const map: Record<string, T> = {};
+                for (const [key, item] of Object.entries(json)) {
+                    map[key] = inner.decode(item, `${path}.${key}`, errors) as T;
+                }
+                return map;
+            },
+            encode: (value) => Object.fromEntries(Object.entries(value).map(([k, v]) => [k, inner.encode(v)])),
+            is: (value): value is Record<string, T> => isObject(value) && Object.values(value).every((v) => inner.is(v)),
+            validate: (value, path, errors) => {
+                for (const [key, item] of Object.entries(value)) inner.validate?.(item, `${path}.${key}`, errors);
+            },
+        };
+    },
+
+    /** An enum; `get` returns the generated object of variant indexes */
+    enumeration(name: string, get: () => Readonly<Record<string, number>>): FieldType<number> {
+        let names: Map<number, string> | null = null;
+        const nameOf = (index: number) => {
+            names ??= new Map(Object.entries(get()).map(([variant, i]) => [i, variant]));
+            return names.get(index);
+        };
+        return {
+            name,
+            decode: (json, path, errors) => {
+                const variants = get();
+                if (typeof json === 'string' && Object.hasOwn(variants, json)) return variants[json];
+                const expected = `one of ${Object.keys(variants).join(', ')}`;
+                errors.push({ field: path, message: `expected ${expected}, found ${JSON.stringify(json)}`, code: 'type' });
+                return undefined;
+            },
+            encode: (value) => nameOf(value),
+            is: (value): value is number => typeof value === 'number' && nameOf(value) !== undefined,
+        };
+    },
+
+    /** A nested scheme; `get` defers the lookup so fields can refer to later declarations */
+    scheme<T>(get: () => SchemeCodec<T>): FieldType<T> {
+        return {
+            get name() {
+                return get().scheme;
+            },
+            decode: (json, path, errors) => get().decode(json, path, errors),
+            encode: (value) => get().encode(value),
+            is: (value): value is T => get().is(value),
+            validate: (value, path, errors) => get().validate?.(value, path, errors),
+        } as FieldType<T>;
+    },
+};
+
+// ========================================================================
+// Rules
+// ========================================================================
+
+const items = (count: number) => (count === 1 ? '1 item' : `${count} items`);
+
+function checkRule(rule: Rule, value: unknown, path: string, errors: FieldError[]): void {
+    const fail = (message: string) => errors.push({ field: path, message, code: rule[0] });
+    const size = typeof value === 'string'
+        ? [...value].length
+        : Array.isArray(value)
+            ? value.length
+            : value instanceof Set
+                ? value.size
+                : typeof value === 'object' && value !== null
+                    ? Object.keys(value).length
+                    : null;
+    const number = typeof value === 'number' ? value : typeof value === 'string' && DECIMAL.test(value) ? Number(value) : null;
+
+    switch (rule[0]) {
+        case 'min_len':
+            if (typeof value === 'string' && size! < rule[1]) fail(`must be at least ${rule[1]} characters`);
+            break;
+        case 'max_len':
+            if (typeof value === 'string' && size! > rule[1]) fail(`must be at most ${rule[1]} characters`);
+            break;
+        case 'blank':
+            if (typeof value === 'string' && value.trim() === '') fail('must not be blank');
+            break;
+        case 'pattern':
+            if (typeof value === 'string' && !new RegExp(rule[1]).test(value)) fail(`must match ${rule[1]}`);
+            break;
+        case 'min':
+            if (number !== null && number < rule[1]) fail(`must be at least ${rule[1]}`);
+            break;
+        case 'max':
+            if (number !== null && number > rule[1]) fail(`must be at most ${rule[1]}`);
+            break;
+        case 'precision': {
+            const digits = typeof value === 'string' ? (value.split('.')[1] ?? '').length : 0;
+            if (digits > rule[1]) fail(`must have at most ${rule[1]} decimal places`);
+            break;
+        }
+        case 'min_items':
+            if (typeof value !== 'string' && size !== null && size < rule[1]) fail(`must have at least ${items(rule[1])}`);
+            break;
+        case 'max_items':
+            if (typeof value !== 'string' && size !== null && size > rule[1]) fail(`must have at most ${items(rule[1])}`);
+            break;
+    }
+}
+
+// ========================================================================
+// Schemes
+// ========================================================================
+
+/**
+ * Build the codec of a scheme from the description of its stored fields.
+ *
+ * A missing field decodes to null when it is nullable and is an error
+ * otherwise. Fields not in the scheme are ignored.
+ */
+export function defineScheme<T>(scheme: string, fields: SchemeFieldDescriptor[]): SchemeCodec<T> {
+    const isObject = (value: unknown): value is Record<string, unknown> =>
+        typeof value === 'object' && value !== null && !Array.isArray(value);
+    const join = (path: string, field: string) => (path ? `${path}.${field}` : field);
+
+    function validateInto(value: T, path: string, errors: FieldError[]): void {
+        const record = value as Record<string, unknown>;
+        for (const field of fields) {
+            const fieldValue = record[field.name];
+            const fieldPath = join(path, field.name);
+            if (fieldValue === null || fieldValue === undefined) continue;
+            for (const rule of field.rules ?? []) checkRule(rule, fieldValue, fieldPath, errors);
+            field.type.validate?.(fieldValue, fieldPath, errors);
+        }
+    }
+
+    function decode(json: unknown, path: string, errors: FieldError[]): T | undefined {
+        if (!isObject(json)) return typeError(scheme, json, path || scheme, errors);
+        const before = errors.length;
+        const value: Record<string, unknown> = {};
+        for (const field of fields) {
+            const fieldPath = join(path, field.name);
+            const fieldJson = json[field.name];
+            if (fieldJson === undefined && !field.type.is(null)) {
+                errors.push({ field: fieldPath, message: 'is required', code: 'required' });
+                continue;
+            }
+            const fieldBefore = errors.length;
+            const fieldValue = field.type.decode(fieldJson, fieldPath, errors);
+            value[field.name] = fieldValue;
+            // nested schemes validate themselves while decoding, so only the
+            // field's own rules are left to check
+            if (errors.length > fieldBefore || fieldValue === null) continue;
+            for (const rule of field.rules ?? []) checkRule(rule, fieldValue, fieldPath, errors);
+        }
+        return errors.length > before ? undefined : (value as T);
+    }
+
+    const codec: SchemeCodec<T> = {
+        scheme,
+        name: scheme,
+        fields,
+        decode,
+        encode: (value) => codec.toJson(value),
+        is: (value): value is T => isObject(value) && fields.every((field) => field.type.is(value[field.name] ?? null)),
+        validate: (value: T, path = '', errors: FieldError[] = []) => {
+            validateInto(value, path, errors);
+            return errors;
+        },
+        fromJson(json: unknown): T {
+            const errors: FieldError[] = [];
+            const value = decode(json, '', errors);
+            if (errors.length > 0 || value === undefined) throw new SchemeError(scheme, errors);
+            return value;
+        },
+        toJson(value: T): Record<string, unknown> {
+            const record = value as Record<string, unknown>;
+            const json: Record<string, unknown> = {};
+            for (const field of fields) {
+                const fieldValue = record[field.name] ?? null;
+                json[field.name] = fieldValue === null ? null : field.type.encode(fieldValue);
+            }
+            return json;
+        },
+    };
+    return codec;
+}
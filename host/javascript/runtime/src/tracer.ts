@@ -6,9 +6,16 @@
 export type TraceCategory =
     | 'datum'        // Datum lifecycle: create, destroy
     | 'closure'      // Closure/fragment lifecycle: create, destroy, instantiate
-    | 'field'        // Field changes: set (no get - too high volume)
+    | 'field'        // Field changes: set, undo, redo (no get - too high volume)
     | 'subscription' // Reactive flow: subscribe, unsubscribe, notify
     | 'notification' // Drain loop: drain_start, drain_end, callback, generation
+    | 'command'      // Command policies: start, end, drop, supersede
+    | 'selector'     // Derived selectors: compute (cache misses only)
+    | 'shortcut'     // Keyboard shortcuts: run
+    | 'context'      // Context values: missing (consumed without a provider)
+    | 'boundary'     // Error boundaries: fail (body replaced by the fallback)
+    | 'loading'      // Loading placeholders: show, hide
+    | 'portal'       // Overlay rendering: mount, unmount
     | 'rendering';   // Future: render operations
 
 /**
@@ -20,6 +27,13 @@ export const ALL_CATEGORIES: Set<TraceCategory> = new Set([
     'field',
     'subscription',
     'notification',
+    'command',
+    'selector',
+    'shortcut',
+    'context',
+    'boundary',
+    'loading',
+    'portal',
     'rendering',
 ]);
 
@@ -79,6 +93,8 @@ export class ConsoleSink implements TraceSink {
         field: '\x1b[33m',        // Yellow
         subscription: '\x1b[32m', // Green
         notification: '\x1b[34m', // Blue
+        command: '\x1b[90m',      // Gray
+        selector: '\x1b[94m',     // Bright blue
         rendering: '\x1b[31m',    // Red
     };
     private readonly reset = '\x1b[0m';
@@ -0,0 +1,221 @@
+// Frel Contract Transports
+//
+// Generated contract clients hand every call to a transport together with the
+// method's metadata. FetchTransport sends calls as REST requests, and
+// WebSocketTransport as request/response messages over a single socket. Hosts
+// can implement ContractTransport for anything else.
+
+/** HTTP method of a contract method's route */
+export type HttpMethod = 'GET' | 'POST' | 'PUT' | 'PATCH' | 'DELETE';
+
+/**
+ * Metadata of a contract method, as generated in the client's `static methods`.
+ */
+export interface ContractMethod {
+    /** Name the service knows the method by (`.. name { "getUser" }`) */
+    name: string;
+    /** Parameter names, in declaration order */
+    params: string[];
+    /** Route of request/response transports; `{param}` placeholders are filled from the arguments */
+    http: { method: HttpMethod; path: string };
+}
+
+/**
+ * Sends contract calls to a service.
+ */
+export interface ContractTransport {
+    /**
+     * Call a method of a contract and resolve with its decoded result.
+     *
+     * @param contract Qualified contract name, e.g. `app.UserAPI`
+     * @param method Metadata of the method
+     * @param args Arguments by parameter name
+     */
+    call(contract: string, method: ContractMethod, args: Record<string, unknown>): Promise<unknown>;
+}
+
+/**
+ * A failed contract call.
+ */
+export class ContractError extends Error {
+    constructor(
+        message: string,
+        /** HTTP status, for calls sent by FetchTransport */
+        public readonly status: number | null = null,
+        /** Error payload sent by the service, if any */
+        public readonly body: unknown = null,
+    ) {
+        super(message);
+        this.name = 'ContractError';
+    }
+}
+
+/** Options of FetchTransport */
+export interface FetchTransportOptions {
+    /** URL the method paths are relative to, e.g. `https://api.example.com/v1` */
+    baseUrl: string;
+    /** Headers added to every request, or a function computing them per request */
+    headers?: Record<string, string> | (() => Record<string, string> | Promise<Record<string, string>>);
+    /** fetch implementation (default: the global fetch) */
+    fetch?: typeof fetch;
+}
+
+/**
+ * Sends each call as an HTTP request to the method's route.
+ *
+ * Arguments named in the path are substituted into it. The remaining
+ * arguments go in the query string of GET and DELETE requests and in a JSON
+ * body otherwise. Responses are decoded as JSON; empty responses resolve with
+ * `undefined`.
+ */
+export class FetchTransport implements ContractTransport {
+    private readonly baseUrl: string;
+    private readonly fetchFn: typeof fetch;
+
+    constructor(private readonly options: FetchTransportOptions) {
+        this.baseUrl = options.baseUrl.replace(/\/+$/, '');
+        this.fetchFn = options.fetch ?? globalThis.fetch.bind(globalThis);
+    }
+
+    async call(contract: string, method: ContractMethod, args: Record<string, unknown>): Promise<unknown> {
+        const rest: Record<string, unknown> = { ...args };
+        const path = method.http.path.replace(/\{(\w+)\}/g, (_, name: string) => {
+            const value = rest[name];
+            delete rest[name];
+            return encodeURIComponent(String(value));
+        });
+
+        const verb = method.http.method;
+        const inQuery = verb === 'GET' || verb === 'DELETE';
+        let url = this.baseUrl + path;
+        if (inQuery) {
+            const query = new URLSearchParams();
+            for (const [name, value] of Object.entries(rest)) {
+                if (value === undefined || value === null) continue;
+                query.set(name, typeof value === 'object' ? JSON.stringify(value) : String(value));
+            }
+            const text = query.toString();
+            if (text) url += (url.includes('?') ? '&' : '?') + text;
+        }
+
+        const headers: Record<string, string> = { Accept: 'application/json', ...(await this.headers()) };
+        const init: RequestInit = { method: verb, headers };
+        if (!inQuery) {
+            headers['Content-Type'] = 'application/json';
+            init.body = JSON.stringify(rest);
+        }
+
+        const response = await this.fetchFn(url, init);
+        const text = await response.text();
+        const body = text ? JSON.parse(text) : undefined;
+        if (!response.ok) {
+            throw new ContractError(
+                `${contract}.${method.name} failed: ${response.status} ${response.statusText}`,
+                response.status,
+                body ?? null,
+            );
+        }
+        return body;
+    }
+
+    private async headers(): Promise<Record<string, string>> {
+        const headers = this.options.headers;
+        return typeof headers === 'function' ? await headers() : headers ?? {};
+    }
+}
+
+/** Options of WebSocketTransport */
+export interface WebSocketTransportOptions {
+    /** Socket URL, or an already created socket */
+    socket: string | WebSocket;
+    /** Milliseconds to wait for a response before failing the call (default: no timeout) */
+    timeout?: number;
+}
+
+interface PendingCall {
+    resolve: (value: unknown) => void;
+    reject: (error: Error) => void;
+    timer: ReturnType<typeof setTimeout> | null;
+}
+
+/**
+ * Sends calls as JSON messages over one WebSocket.
+ *
+ * A call is sent as `{ id, contract, method, params }` and resolves with the
+ * response of the same `id`: `{ id, result }` or `{ id, error }`. Calls made
+ * before the socket opens are sent when it does; calls still waiting when it
+ * closes fail.
+ */
+export class WebSocketTransport implements ContractTransport {
+    private readonly socket: WebSocket;
+    private readonly pending = new Map<number, PendingCall>();
+    private readonly queue: string[] = [];
+    private nextId = 1;
+
+    constructor(private readonly options: WebSocketTransportOptions) {
+        this.socket = typeof options.socket === 'string' ? new WebSocket(options.socket) : options.socket;
+        this.socket.addEventListener('open', () => this.flush());
+        this.socket.addEventListener('message', (event) => this.receive(event.data));
+        this.socket.addEventListener('close', () => this.failPending('socket closed'));
+    }
+
+    call(contract: string, method: ContractMethod, args: Record<string, unknown>): Promise<unknown> {
+        const id = this.nextId++;
+        const message = JSON.stringify({ id, contract, method: method.name, params: args });
+
+        return new Promise((resolve, reject) => {
+            const timer = this.options.timeout === undefined
+                ? null
+                : setTimeout(() => {
+                    this.pending.delete(id);
+                    reject(new ContractError(`${contract}.${method.name} timed out`));
+                }, this.options.timeout);
+            this.pending.set(id, { resolve, reject, timer });
+            this.queue.push(message);
+            this.flush();
+        });
+    }
+
+    /** Close the socket; calls still waiting fail */
+    close(): void {
+        this.socket.close();
+        this.failPending('transport closed');
+    }
+
+    private flush(): void {
+        if (this.socket.readyState !== WebSocket.OPEN) return;
+        for (const message of this.queue.splice(0)) {
+            this.socket.send(message);
+        }
+    }
+
+    private receive(data: unknown): void {
+        if (typeof data !== 'string') return;
+        let message: { id?: number; result?: unknown; error?: unknown };
+        try {
+            message = JSON.parse(data);
+        } catch {
+            return;
+        }
+        const call = message.id === undefined ? undefined : this.pending.get(message.id);
+        if (!call) return;
+        this.pending.delete(message.id!);
+        if (call.timer !== null) clearTimeout(call.timer);
+
+        if (message.error !== undefined && message.error !== null) {
+            const text = typeof message.error === 'string' ? message.error : JSON.stringify(message.error);
+            call.reject(new ContractError(text, null, message.error));
+        } else {
+            call.resolve(message.result);
+        }
+    }
+
+    private failPending(reason: string): void {
+        for (const call of this.pending.values()) {
+            if (call.timer !== null) clearTimeout(call.timer);
+            call.reject(new ContractError(reason));
+        }
+        this.pending.clear();
+        this.queue.length = 0;
+    }
+}
@@ -1,5 +1,7 @@
 // Frel Runtime Type Definitions
 
+import type { Runtime } from './runtime.js';
+
 /** Identity for datums (even numbers, low bit = 0) */
 export type DatumIdentity = number;
 
@@ -48,6 +50,8 @@ export interface DatumData {
 export interface ClosureData {
     closure_id: ClosureIdentity;
     blueprint: string;
+    /** Stable ID of the call site that created this closure, if emitted */
+    fragment_id: string | null;
 
     // Structural (unsubscribable, holds closure identities)
     parent_closure_id: ClosureIdentity | null;
@@ -58,6 +62,12 @@ export interface ClosureData {
     subscriptions_by_this: Set<SubscriptionIdentity>;
     owned_datum: Set<DatumIdentity>;
 
+    // Shared backends (unsubscribable)
+    /** Fields of injected backend instances, mapped to the closure that owns them */
+    shared_fields: Record<string, ClosureIdentity>;
+    /** Closures that share fields of a backend instance owned by this closure */
+    sharers: Set<ClosureIdentity>;
+
     // Fields (subscribable)
     fields: Record<string, unknown>;
     set_generation: number;
@@ -79,12 +89,105 @@ export interface BlueprintMetadata {
     /** Indices into call_sites for children to instantiate immediately */
     top_children: number[];
     call_sites: Record<string, CallSiteMetadata>;
+    /** Error boundaries at the top level, created after the top children */
+    boundaries?: ((runtime: Runtime, closure_id: ClosureIdentity) => void)[];
+    /** Loading placeholders at the top level, opened after the error boundaries */
+    loading?: ((runtime: Runtime, closure_id: ClosureIdentity) => void)[];
+}
+
+/** A backend instance, as generated for `backend` declarations */
+export interface BackendInstance {
+    /** Closure that holds the backend's fields */
+    closure_id: ClosureIdentity;
+}
+
+/** What happens when a command is invoked while a previous invocation is in flight */
+export type CommandPolicy = 'latest' | 'queue' | 'drop';
+
+/** How long a backend instance lives: per fragment, per screen (root fragment) or app-wide */
+export type BackendLifetime = 'fragment' | 'screen' | 'singleton';
+
+/** A generated backend class */
+export interface BackendClass {
+    new (runtime: Runtime, closure_id: ClosureIdentity): BackendInstance;
+    readonly name: string;
+    /** Field names, shared with the closures using the instance */
+    readonly fields: string[];
+    readonly lifetime: BackendLifetime;
 }
 
 /** Call site metadata */
 export interface CallSiteMetadata {
+    /** Stable, human-readable ID (e.g. `app.Main/when/text`); omitted when disabled */
+    id?: string;
     blueprint: string;
     binding: (runtime: Runtime, parent_id: ClosureIdentity, child_id: ClosureIdentity) => void;
+    /** Window options when the call site is a virtualized repeat container */
+    virtualize?: (runtime: Runtime, parent_id: ClosureIdentity) => VirtualizeOptions;
+    /** Focus options when the call site has `tab_index`, `autofocus` or `focus_group` */
+    focus?: (runtime: Runtime, parent_id: ClosureIdentity) => FocusOptions;
+}
+
+/** How the keyboard moves focus within a fragment's subtree */
+export type FocusGroup = 'trap' | 'arrows';
+
+/** Options of the focus instructions of a fragment */
+export interface FocusOptions {
+    /** Position in the tab order; negative values skip the fragment in tab navigation */
+    tab_index?: number;
+    /** Focus the fragment when it is created */
+    autofocus?: boolean;
+    /** `trap`: Tab cycles within the subtree; `arrows`: one tab stop, arrow keys move focus */
+    group?: FocusGroup;
+}
+
+/** Options of the `virtualize` instruction; exactly one of the heights is set */
+export interface VirtualizeOptions {
+    /** Fixed height of every item in DIP */
+    item_height?: number;
+    /** Height assumed for items that have not been measured yet */
+    estimate?: number;
+    /** Items materialized beyond each edge of the viewport (default 3) */
+    overscan?: number;
+}
+
+/**
+ * A key press, as passed to `Runtime.handle_key`.
+ *
+ * The fields are those of a DOM `KeyboardEvent`, so browser adapters can pass
+ * the event itself.
+ */
+export interface KeyInput {
+    /** Key value, e.g. `s`, `S`, `Enter`, `ArrowUp`, `F5` */
+    key: string;
+    ctrlKey: boolean;
+    altKey: boolean;
+    shiftKey: boolean;
+    metaKey: boolean;
+}
+
+/**
+ * Platform hooks of the `portal` instruction.
+ *
+ * The runtime calls `mount` when a fragment rendered in an overlay is created
+ * and `unmount` when it is destroyed; the adapter moves the fragment's node
+ * into the named overlay container and back.
+ */
+export interface PortalHost {
+    mount(closure_id: ClosureIdentity, container: string): void;
+    unmount(closure_id: ClosureIdentity, container: string): void;
+}
+
+/** Repeat items to materialize for a scroll position */
+export interface ItemWindow {
+    /** Index of the first item to materialize */
+    start: number;
+    /** Index after the last item to materialize */
+    end: number;
+    /** Space taken by the items before the window, in DIP */
+    before: number;
+    /** Space taken by the items after the window, in DIP */
+    after: number;
 }
 
 // ========================================================================